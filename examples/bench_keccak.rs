@@ -0,0 +1,42 @@
+use std::time::Instant;
+
+use op_reth::cli::keccak;
+use reth_primitives::{Address, Header, H256};
+
+/// Times the keccak backend selected at build time on the inputs the state import and the block
+/// import hash most. Compare the backends with
+///
+/// cargo run --release --example bench_keccak
+/// cargo run --release --example bench_keccak --features asm-keccak
+fn main() {
+    const ROUNDS: u64 = 1_000_000;
+    println!("Backend: {}", keccak::BACKEND);
+
+    let time = Instant::now();
+    let mut hash = H256::zero();
+    for round in 0..ROUNDS {
+        hash = keccak::keccak256(Address::from_low_u64_be(round ^ hash.to_low_u64_be()));
+    }
+    report("addresses", ROUNDS, time);
+
+    let time = Instant::now();
+    for _ in 0..ROUNDS {
+        hash = keccak::keccak256(hash);
+    }
+    report("storage keys", ROUNDS, time);
+
+    let time = Instant::now();
+    for number in 0..ROUNDS / 10 {
+        hash = keccak::seal(Header { number, parent_hash: hash, ..Default::default() }).hash();
+    }
+    report("headers", ROUNDS / 10, time);
+    println!("Last hash: {hash:?}");
+}
+
+fn report(input: &str, rounds: u64, time: Instant) {
+    let elapsed = time.elapsed();
+    println!(
+        "{input:>12}: {rounds} hashes in {elapsed:?}, {:.0} ns per hash",
+        elapsed.as_nanos() as f64 / rounds as f64
+    );
+}
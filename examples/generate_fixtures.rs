@@ -0,0 +1,112 @@
+//! Generates the fixtures of the integration tests from `tests/fixtures/genesis.json`: the genesis
+//! alloc as a state export, and block and receipt exports of a short chain on top of the genesis
+//! block.
+//!
+//! Run `cargo run --example generate_fixtures` after changing the genesis fixture. The `fixtures`
+//! test fails while the checked-in fixtures differ from the output of this generator.
+
+use std::{fs, path::Path};
+
+use eyre::Result;
+use op_reth::cli::{
+    blocks::{ErigonBlock, ErigonHeader, ErigonTx, LegacyTx},
+    export::RECEIPTS_PREFIX,
+    genesis::Genesis,
+    receipts::{Receipt, ReceiptLog},
+};
+use reth_primitives::{
+    proofs,
+    rpc::{H160, H256},
+    Header, Receipt as RethReceipt, TransactionSigned, TxType, U256,
+};
+
+/// The directory holding the fixtures, relative to the manifest
+const FIXTURES_DIR: &str = "tests/fixtures";
+
+/// The seconds between two blocks
+const BLOCK_TIME: u64 = 2;
+
+/// The gas used by each of the plain transfers
+const TRANSFER_GAS: u64 = 21_000;
+
+/// OP Goerli transfers signed by the funded account of the genesis, one per block
+const TXS: [&str; 2] = [
+    "f86502843b9aca00825208943535353535353535353535353535353535353535018082036ca0d06fd7fc55668a302e23b878edd5ac7684a70fd62cd470c58a02977428d34e8ea00826e2e88874e39fbfc047aa68e548249c0abc7bb976c7ff459f47f4ee437c0f",
+    "f86505843b9aca00825208943535353535353535353535353535353535353535018082036ba0d7d054b02615f2b9d130da607fa1f18d43eb7a24008764527b9d3c0cb66f0f0aa07bd93e7f180229b8579e5e873c56aed9fddf7cba07b96e56c2b84c05f7926e6b",
+];
+
+fn main() -> Result<()> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURES_DIR);
+    generate(&dir.join("genesis.json"), &dir)?;
+    println!("Fixtures written to {}", dir.display());
+    Ok(())
+}
+
+/// Writes the state, block and receipt exports generated from the genesis at `genesis_path` into
+/// the directory `dir`
+pub fn generate(genesis_path: &Path, dir: &Path) -> Result<()> {
+    let genesis = Genesis::from_file(genesis_path)?;
+
+    fs::write(dir.join("state.json"), serde_json::to_vec_pretty(&genesis.exported_state())?)?;
+
+    let mut parent = genesis.to_header()?.seal_slow();
+    let mut blocks =
+        vec![ErigonBlock { header: ErigonHeader::from(&*parent), txs: vec![], uncles: vec![] }];
+    let mut receipts = vec![vec![]];
+    for raw in TXS {
+        let legacy: LegacyTx = rlp::decode(&hex::decode(raw)?)?;
+        let tx = TransactionSigned::from(legacy);
+        let receipt = RethReceipt {
+            tx_type: TxType::Legacy,
+            success: true,
+            cumulative_gas_used: TRANSFER_GAS,
+            logs: vec![],
+        };
+        let header = Header {
+            parent_hash: parent.hash(),
+            state_root: parent.state_root,
+            transactions_root: proofs::calculate_transaction_root([&tx]),
+            receipts_root: proofs::calculate_receipt_root([&receipt]),
+            difficulty: parent.difficulty,
+            number: parent.number + 1,
+            gas_limit: parent.gas_limit,
+            gas_used: TRANSFER_GAS,
+            timestamp: parent.timestamp + BLOCK_TIME,
+            ..Default::default()
+        }
+        .seal_slow();
+
+        receipts.push(vec![Receipt {
+            ty: TxType::Legacy as u8,
+            post_state: vec![],
+            status: 1,
+            cumulative_gas_used: TRANSFER_GAS,
+            bloom: vec![0; 256],
+            logs: rlp::encode_list::<ReceiptLog, ReceiptLog>(&[]).to_vec(),
+            tx_hash: H256::from_slice(&tx.hash().0),
+            contract_address: format!("{:?}", H160::zero()),
+            gas_used: TRANSFER_GAS,
+            block_hash: H256::from_slice(&header.hash().0),
+            block_number: U256::from(header.number),
+            transaction_index: 0,
+            l1_gas_price: U256::from(1),
+            l1_gas_used: U256::from(0x1b62),
+            l1_fee: U256::from(0x2913),
+            l1_fee_scalar: "1.5".to_string(),
+        }]);
+        blocks.push(ErigonBlock {
+            header: ErigonHeader::from(&*header),
+            txs: vec![ErigonTx::from(&tx)],
+            uncles: vec![],
+        });
+        parent = header;
+    }
+    fs::write(dir.join("blocks.rlp"), rlp::encode_list::<ErigonBlock, _>(&blocks))?;
+
+    let mut export = rlp::RlpStream::new_list(receipts.len());
+    for block_receipts in &receipts {
+        export.append_list::<Receipt, _>(block_receipts);
+    }
+    fs::write(dir.join("receipts.rlp"), [&[RECEIPTS_PREFIX][..], &export.out()].concat())?;
+    Ok(())
+}
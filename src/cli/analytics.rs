@@ -0,0 +1,241 @@
+use std::str::FromStr;
+
+use clap::{Parser, Subcommand};
+use eyre::Result;
+use reth::runner::CliContext;
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTx,
+};
+use reth_primitives::{keccak256, rpc::H160, U256};
+
+use crate::cli::{args::DatabaseArgs, genesis, preflight::ImportStage, receipts::Receipt, replay};
+
+/// The legacy OVM_ETH predeploy which mints and burns ETH on deposits and withdrawals
+pub const OVM_ETH_ADDRESS: &str = "0xDeadDeAddeAddEAddeadDEaDDEAdDeaDDeAD0000";
+
+/// Analytics command
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(subcommand)]
+    command: Subcommands,
+}
+
+/// Analytics subcommands
+#[derive(Debug, Subcommand)]
+pub enum Subcommands {
+    /// Compute the total ETH supply and reconcile it against deposits and withdrawals
+    #[command(name = "supply")]
+    Supply(SupplyCommand),
+}
+
+/// Supply command
+#[derive(Debug, Parser)]
+pub struct SupplyCommand {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The path to the receipts export used to reconcile deposits. Defaults to the receipts
+    /// export in the chain directory.
+    #[arg(long, value_name = "RECEIPTS", verbatim_doc_comment)]
+    receipts: Option<String>,
+
+    /// The path to the genesis file. When omitted, the genesis supply is assumed to be zero.
+    #[arg(long, value_name = "GENESIS", verbatim_doc_comment)]
+    genesis: Option<String>,
+
+    /// The block to reconcile up to. Defaults to the highest canonical block in the database.
+    /// Blocks before the state of the database need the history written by `replay`.
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment)]
+    block: Option<u64>,
+}
+
+/// Deposit and withdrawal totals extracted from the OVM_ETH logs of a receipt export
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DepositTotals {
+    /// The total amount of ETH minted by deposits
+    pub minted: U256,
+    /// The total amount of ETH burned by withdrawals
+    pub burned: U256,
+    /// The number of deposits
+    pub deposits: usize,
+    /// The number of withdrawals
+    pub withdrawals: usize,
+}
+
+impl DepositTotals {
+    /// The net amount of ETH bridged into L2, or `None` if more was burned than minted
+    pub fn net(&self) -> Option<U256> {
+        self.minted.checked_sub(self.burned)
+    }
+}
+
+/// The result of reconciling the state supply against the bridged supply
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupplyReport {
+    /// The block the deposits were reconciled up to
+    pub block: u64,
+    /// The sum of all account balances in the plain state
+    pub state_supply: U256,
+    /// The sum of all account balances in the genesis allocation
+    pub genesis_supply: U256,
+    /// The deposit and withdrawal totals
+    pub totals: DepositTotals,
+}
+
+impl SupplyReport {
+    /// The supply implied by the genesis allocation and the bridged amounts
+    pub fn expected_supply(&self) -> Option<U256> {
+        self.totals.net().and_then(|net| self.genesis_supply.checked_add(net))
+    }
+
+    /// Returns true if the state supply matches the expected supply
+    pub fn is_consistent(&self) -> bool {
+        self.expected_supply() == Some(self.state_supply)
+    }
+}
+
+/// Sums the OVM_ETH `Mint` and `Burn` events of all successful receipts up to and including the
+/// given block
+pub fn deposit_totals(receipts: &[Receipt], block: u64) -> Result<DepositTotals> {
+    let ovm_eth = H160::from_str(OVM_ETH_ADDRESS)?;
+    let mint_topic = keccak256("Mint(address,uint256)");
+    let burn_topic = keccak256("Burn(address,uint256)");
+    let block = U256::from(block);
+
+    let mut totals = DepositTotals::default();
+    for receipt in receipts.iter().filter(|r| r.status == 1 && r.block_number <= block) {
+        for log in receipt.decode_logs().map_err(|e| eyre::eyre!(e))? {
+            if log.address != ovm_eth {
+                continue
+            }
+            let Some(topic) = log.topics.first() else { continue };
+            let amount = U256::try_from_be_slice(&log.data).ok_or_else(|| {
+                eyre::eyre!("Invalid OVM_ETH amount in transaction {:?}", receipt.tx_hash)
+            })?;
+            if topic.0 == mint_topic.0 {
+                totals.minted += amount;
+                totals.deposits += 1;
+            } else if topic.0 == burn_topic.0 {
+                totals.burned += amount;
+                totals.withdrawals += 1;
+            }
+        }
+    }
+
+    Ok(totals)
+}
+
+/// Sums the balances of all accounts in the plain state of the given database, or of the accounts
+/// after the given block, reconstructed from the history written by `replay` for blocks before
+/// the state of the database
+pub fn state_supply(db: &Env<WriteMap>, block: Option<u64>) -> Result<U256> {
+    let supply = db.view(|tx| -> Result<U256> {
+        let mut supply = U256::ZERO;
+        let historical = match block {
+            Some(block) => replay::historical_transition(tx, block)?.map(|_| block),
+            None => None,
+        };
+        if let Some(block) = historical {
+            for address in replay::historical_accounts(tx)? {
+                if let Some(account) = replay::account_at(tx, address, block)? {
+                    supply += account.balance;
+                }
+            }
+            return Ok(supply)
+        }
+        let mut cursor = tx.cursor_read::<tables::PlainAccountState>()?;
+        let mut entry = cursor.first()?;
+        while let Some((_, account)) = entry {
+            supply += account.balance;
+            entry = cursor.next()?;
+        }
+        Ok(supply)
+    })??;
+    Ok(supply)
+}
+
+/// Returns the highest canonical block number in the given database
+pub fn canonical_tip(db: &Env<WriteMap>) -> Result<Option<u64>> {
+    let tip = db.view(|tx| -> Result<Option<u64>> {
+        Ok(tx.cursor_read::<tables::CanonicalHeaders>()?.last()?.map(|(number, _)| number))
+    })??;
+    Ok(tip)
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, ctx: CliContext) -> Result<()> {
+        match self.command {
+            Subcommands::Supply(command) => command.execute(ctx).await,
+        }
+    }
+}
+
+impl SupplyCommand {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db = self.db.open_ro()?;
+
+        let tip = canonical_tip(&db)?
+            .ok_or_else(|| eyre::eyre!("No canonical blocks found in the database"))?;
+        let block = self.block.unwrap_or(tip);
+        if block > tip {
+            eyre::bail!("Block {block} is above the database tip {tip}");
+        }
+
+        let genesis_supply = match &self.genesis {
+            Some(path) => genesis::Genesis::from_file(path)?
+                .alloc
+                .values()
+                .fold(U256::ZERO, |supply, account| supply + account.balance),
+            None => U256::ZERO,
+        };
+
+        let receipts_path = self.db.input_path(self.receipts.as_deref(), ImportStage::Receipts)?;
+        tracing::info!(target: "reth::cli", path = %receipts_path, "Reading receipts");
+        let receipts = Receipt::from_file(&receipts_path)?;
+        let totals = deposit_totals(&receipts, block)?;
+
+        tracing::info!(target: "reth::cli", "Summing account balances");
+        let report = SupplyReport {
+            block,
+            state_supply: state_supply(&db, self.block)?,
+            genesis_supply,
+            totals,
+        };
+
+        println!("Block:          {}", report.block);
+        println!("State supply:   {}", report.state_supply);
+        println!("Genesis supply: {}", report.genesis_supply);
+        println!("Minted:         {} ({} deposits)", report.totals.minted, report.totals.deposits);
+        println!(
+            "Burned:         {} ({} withdrawals)",
+            report.totals.burned, report.totals.withdrawals
+        );
+
+        match report.expected_supply() {
+            Some(expected) if report.is_consistent() => {
+                println!("Expected:       {expected}");
+                tracing::info!(target: "reth::cli", "Supply is consistent with deposits! 🎉");
+            }
+            Some(expected) => {
+                println!("Expected:       {expected}");
+                let difference = if expected > report.state_supply {
+                    expected - report.state_supply
+                } else {
+                    report.state_supply - expected
+                };
+                tracing::warn!(target: "reth::cli", %expected, actual = %report.state_supply, %difference, "Supply discrepancy detected");
+            }
+            None => {
+                tracing::warn!(target: "reth::cli", "More ETH was burned by withdrawals than was minted by deposits");
+            }
+        }
+
+        Ok(())
+    }
+}
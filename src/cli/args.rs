@@ -0,0 +1,609 @@
+use std::{
+    net::SocketAddr,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use clap::Args;
+use eyre::Result;
+use reth_db::mdbx::{Env, WriteMap};
+use reth_primitives::SealedBlock;
+
+use crate::cli::{
+    chain::{ChainPreset, UnsignedTxPolicy},
+    checksum, db,
+    dead_letter::{DeadLetterFile, ERROR_REPORT_FILE},
+    deposit::DepositStore,
+    dirs,
+    encryption::{self, SnapshotKey},
+    metrics::{self, MetricsReporter},
+    preflight::{self, ImportStage},
+    progress::ImportProgress,
+    retry::RetryPolicy,
+    shutdown::{self, ShutdownGuard, ShutdownPhase},
+    source::rpc::RpcSource,
+    throttle::{self, IoLimiter},
+    validate::{self, SystemTxValidator, DEFAULT_MAX_BLOCK_GAP},
+    watchdog::Watchdog,
+};
+
+/// Arguments selecting the database a command operates on
+#[derive(Debug, Clone, Args)]
+pub struct DatabaseArgs {
+    /// The directory holding the databases and inputs of all chains.
+    ///
+    /// Every chain has its own directory `<DATA_DIR>/<CHAIN>` below it, named after `--chain` or
+    /// `default` without one. It holds the database in `db` and the inputs read by default.
+    /// Defaults to `reth/op-reth` below the platform data directory, e.g.
+    /// `~/.local/share/reth/op-reth` on Linux.
+    #[arg(long, value_name = "DATA_DIR", verbatim_doc_comment)]
+    pub datadir: Option<PathBuf>,
+
+    /// The path to the database. Defaults to `<DATA_DIR>/<CHAIN>/db`.
+    #[arg(long, alias = "db-path", value_name = "DATABASE_PATH", verbatim_doc_comment)]
+    pub database: Option<String>,
+
+    /// The chain the command operates on.
+    ///
+    /// Selects the chain directory below the data directory. Imports check the chain ids of
+    /// signed transactions, the genesis and the OP system transactions against the preset and
+    /// refuse inputs taken from another network. Also selects the default input paths and the
+    /// expected genesis hash of the chain.
+    #[arg(long, value_enum, value_name = "CHAIN", verbatim_doc_comment)]
+    pub chain: Option<ChainPreset>,
+
+    /// Select the chain namespace within the database.
+    ///
+    /// Allows keeping several chains, e.g. OP Goerli and a devnet, under one database path. Each
+    /// chain id is stored in its own environment at `<DATABASE_PATH>.chains/<CHAIN_ID>`, next to
+    /// the database, since reth's tables can't be namespaced within one environment.
+    #[arg(long, value_name = "CHAIN_ID", verbatim_doc_comment)]
+    pub namespace: Option<u64>,
+
+    /// Keep the static data of the database, such as the L1 fee fields of receipts, below this
+    /// path instead of the database path.
+    ///
+    /// Allows placing the hot MDBX environment on fast storage and the immutable history on
+    /// cheaper volumes. The path is recorded in the database, so later commands find the static
+    /// data without the flag.
+    #[arg(long, value_name = "STATIC_PATH", verbatim_doc_comment)]
+    pub static_path: Option<PathBuf>,
+
+    /// Refuse to write the database.
+    ///
+    /// Commands that only read the database open it read-only regardless. With this flag,
+    /// commands that would write it fail instead of opening it for writing, which guards
+    /// inspection sessions against mistyped commands.
+    #[arg(long, verbatim_doc_comment)]
+    pub read_only: bool,
+}
+
+impl DatabaseArgs {
+    /// The data directory given with `--datadir`, or the default one
+    pub fn data_dir(&self) -> PathBuf {
+        self.datadir.clone().unwrap_or_else(dirs::default_data_dir)
+    }
+
+    /// The directory of the chain selected with `--chain` below the data directory
+    pub fn chain_dir(&self) -> PathBuf {
+        dirs::chain_dir(&self.data_dir(), self.chain)
+    }
+
+    /// The path of the database given with `--database`, or the one in the chain directory
+    fn root(&self) -> PathBuf {
+        match &self.database {
+            Some(path) => PathBuf::from(path),
+            None => self.chain_dir().join(dirs::DB_DIR),
+        }
+    }
+
+    /// The path of the selected database environment
+    pub fn path(&self) -> PathBuf {
+        db::namespaced_path(&self.root(), self.namespace)
+    }
+
+    /// The input of the given stage: `path` if given, otherwise the default input in the chain
+    /// directory
+    pub fn input_path(&self, path: Option<&str>, stage: ImportStage) -> Result<String> {
+        if let Some(path) = path {
+            return Ok(path.to_string())
+        }
+        Ok(dirs::default_input(&self.chain_dir(), self.chain, stage)?.display().to_string())
+    }
+
+    /// The path of the static data of the selected database environment
+    pub fn static_path(&self) -> Result<PathBuf> {
+        let root = db::resolve_static_root(&self.path(), self.static_path.as_deref())?;
+        Ok(db::namespaced_path(&root, self.namespace))
+    }
+
+    /// Opens the selected database environment for reading and writing. Fails with
+    /// `--read-only`.
+    pub fn open_rw(&self) -> Result<Env<WriteMap>> {
+        self.ensure_writable()?;
+        db::open_rw_namespaced_env(&self.root(), self.namespace)
+    }
+
+    /// Opens the selected database environment read-only
+    pub fn open_ro(&self) -> Result<Env<WriteMap>> {
+        db::open_ro_env(&self.path())
+    }
+
+    /// Fails if the database was selected with `--read-only`
+    pub fn ensure_writable(&self) -> Result<()> {
+        if self.read_only {
+            eyre::bail!("The database at {} was opened with --read-only", self.path().display());
+        }
+        Ok(())
+    }
+}
+
+/// The default number of records written per database transaction
+pub const DEFAULT_BATCH_SIZE: usize = 10_000;
+
+/// Arguments shared by the import commands
+#[derive(Debug, Clone, Args)]
+pub struct ImportArgs {
+    /// Write records that fail to convert to this file and continue instead of aborting the
+    /// import. Implies `--lenient`.
+    ///
+    /// The file contains one JSON object per line with the record kind, its index and offset in
+    /// the export, the error and the hex-encoded raw bytes, so it can be re-processed later.
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    pub dead_letter: Option<PathBuf>,
+
+    /// Import the records of a dead-letter file written by an earlier lenient import instead of
+    /// the export, e.g. after fixing the cause of their failure.
+    ///
+    /// Every importer reads the records of its own kind. Records failing again are handled like
+    /// the records of an export, so `--dead-letter` collects them into a new file.
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    pub from_dead_letter: Option<PathBuf>,
+
+    /// Abort the import on the first record that fails to decode, even with `--dead-letter`.
+    /// This is the default without `--dead-letter`.
+    #[arg(long, conflicts_with = "lenient", verbatim_doc_comment)]
+    pub strict: bool,
+
+    /// Skip records that fail to decode and continue the import, writing them to an error
+    /// report: the `--dead-letter` file if given, `import-errors.jsonl` in the chain directory
+    /// otherwise.
+    #[arg(long, verbatim_doc_comment)]
+    pub lenient: bool,
+
+    /// Report a stalled import after this many seconds without progress.
+    ///
+    /// The report contains the current stage, the number of processed records, the offset into
+    /// the export, the age of the open database transaction and the state of every thread.
+    #[arg(long, value_name = "SECONDS", verbatim_doc_comment)]
+    pub stall_timeout: Option<u64>,
+
+    /// Abort a stalled import instead of only reporting it.
+    ///
+    /// The import fails with a retryable error. If it does not unwind within another
+    /// `--stall-timeout`, the process exits with code 75.
+    #[arg(long, requires = "stall_timeout", verbatim_doc_comment)]
+    pub abort_on_stall: bool,
+
+    /// Limit disk I/O to this many megabytes per second.
+    ///
+    /// Applies to reading the export and to writing the database, so imports can run on hosts
+    /// that serve other traffic without saturating the disk.
+    #[arg(long, value_name = "MB/s", verbatim_doc_comment)]
+    pub io_limit: Option<u64>,
+
+    /// Map uncompressed exports into memory instead of reading them.
+    ///
+    /// The operating system loads the pages of the export as they are decoded instead of the
+    /// import reading the whole file up front. This is not zero-copy: the decoders copy every
+    /// record out of the mapping, and the effect on peak memory has not been measured. The export
+    /// must not be truncated or rewritten during the import: reading a page that is gone kills the
+    /// process with SIGBUS instead of failing.
+    #[arg(long, conflicts_with = "io_limit", verbatim_doc_comment)]
+    pub mmap: bool,
+
+    /// Do not render progress bars
+    #[arg(long, short, verbatim_doc_comment)]
+    pub quiet: bool,
+
+    /// The expected sha256 digest of the input, verified before decoding starts
+    #[arg(long, value_name = "SHA256", conflicts_with = "checksum_manifest", verbatim_doc_comment)]
+    pub checksum: Option<String>,
+
+    /// A `sha256sum` manifest listing the expected digests of the inputs.
+    ///
+    /// Inputs are looked up by their path as given or by their file name. Imports of inputs
+    /// missing from the manifest are refused.
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    pub checksum_manifest: Option<PathBuf>,
+
+    /// The number of records written per database transaction.
+    ///
+    /// Every batch is committed and synced to disk before the next one starts, which bounds the
+    /// size of the transactions and keeps the progress of an interrupted import.
+    #[arg(long, value_name = "RECORDS", default_value_t = DEFAULT_BATCH_SIZE, verbatim_doc_comment)]
+    pub batch_size: usize,
+
+    /// Only warn instead of refusing the import when the database volume lacks the space the
+    /// import is estimated to need
+    #[arg(long, verbatim_doc_comment)]
+    pub allow_low_space: bool,
+
+    /// The chain the inputs are expected to belong to, taken from the [DatabaseArgs]
+    #[arg(skip)]
+    pub chain: Option<ChainPreset>,
+
+    /// The chain directory holding the default inputs, taken from the [DatabaseArgs]. Defaults
+    /// to the directory of the chain in the default data directory.
+    #[arg(skip)]
+    pub chain_dir: Option<PathBuf>,
+
+    /// The number of times reading an input or writing a batch is retried after a transient
+    /// failure, like interrupted I/O or a database map that could not grow in time
+    #[arg(long, value_name = "COUNT", default_value_t = 3, verbatim_doc_comment)]
+    pub retries: u32,
+
+    /// The pause before the first retry in milliseconds, doubled for every further one
+    #[arg(long, value_name = "MILLIS", default_value_t = 500, verbatim_doc_comment)]
+    pub retry_backoff: u64,
+
+    /// The largest plausible gap in seconds between the timestamps of consecutive blocks.
+    ///
+    /// Blocks whose timestamp decreases, jumps further ahead or lies in the future are reported,
+    /// since such clock anomalies break the activation of timestamp-based hardforks.
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = DEFAULT_MAX_BLOCK_GAP,
+        verbatim_doc_comment
+    )]
+    pub max_block_gap: u64,
+
+    /// Refuse the import when block timestamps are anomalous instead of only reporting them
+    #[arg(long, verbatim_doc_comment)]
+    pub strict_timestamps: bool,
+
+    /// Refuse the import when receipts contradict the imported blocks instead of skipping the
+    /// contradicting receipts.
+    ///
+    /// Receipts are checked against the block hash at their height, the transaction at their
+    /// index and the receipts root of their block.
+    #[arg(long, verbatim_doc_comment)]
+    pub strict_receipts: bool,
+
+    /// Insert blocks that do not directly follow the block before them.
+    ///
+    /// Without the flag a batch of blocks is refused if a block number is skipped, like a block
+    /// that failed to decode in a `--lenient` import, or a parent hash doesn't match the block
+    /// before it.
+    #[arg(long, verbatim_doc_comment)]
+    pub allow_gaps: bool,
+
+    /// How to import legacy transactions without a signature, like the L1 to L2 messages
+    /// enqueued on the legacy OP chains: `skip` drops them from their blocks, `system` inserts
+    /// them with a system sender and `fail` refuses the import.
+    ///
+    /// Defaults to the policy of the chain selected with `--chain`, `fail` without one.
+    #[arg(long, value_name = "POLICY", verbatim_doc_comment)]
+    pub unsigned_txs: Option<UnsignedTxPolicy>,
+
+    /// Decode the blocks of an export on this many threads while inserting them.
+    ///
+    /// The export is split into chunks of `--batch-size` blocks which the decoder threads decode
+    /// ahead of the database writer, so decoding and inserting overlap. Only a bounded number of
+    /// decoded chunks is buffered. Without the flag all blocks are decoded before inserting them.
+    #[arg(long, value_name = "THREADS", verbatim_doc_comment)]
+    pub decode_threads: Option<usize>,
+
+    /// Serve Prometheus metrics of the import on this address, like `127.0.0.1:9001`.
+    ///
+    /// Publishes the items processed per stage, the imported blocks and receipts, the written
+    /// accounts and the records written per table as counters, and the throughput of the current
+    /// stage, the current block and the size of the database as gauges.
+    #[arg(long, value_name = "SOCKET", verbatim_doc_comment)]
+    pub metrics: Option<SocketAddr>,
+
+    /// The path of the database the import writes to, taken from the [DatabaseArgs]. Its size is
+    /// published with `--metrics`.
+    #[arg(skip)]
+    pub db_path: Option<PathBuf>,
+
+    /// The static data path of the database the import writes to, taken from the
+    /// [DatabaseArgs]. The deposits of imported blocks are kept below it.
+    #[arg(skip)]
+    pub static_path: Option<PathBuf>,
+
+    #[clap(flatten)]
+    pub encryption: EncryptionArgs,
+}
+
+/// The subsystems watching a running import, stopped when dropped
+#[derive(Debug)]
+pub struct ImportMonitors {
+    _watchdog: Option<Watchdog>,
+    _metrics: Option<MetricsReporter>,
+    _shutdown: ShutdownGuard<'static>,
+}
+
+impl Default for ImportArgs {
+    fn default() -> Self {
+        Self {
+            dead_letter: None,
+            from_dead_letter: None,
+            strict: false,
+            lenient: false,
+            stall_timeout: None,
+            abort_on_stall: false,
+            io_limit: None,
+            mmap: false,
+            quiet: false,
+            checksum: None,
+            checksum_manifest: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            allow_low_space: false,
+            chain: None,
+            chain_dir: None,
+            retries: 3,
+            retry_backoff: 500,
+            max_block_gap: DEFAULT_MAX_BLOCK_GAP,
+            strict_timestamps: false,
+            strict_receipts: false,
+            allow_gaps: false,
+            unsigned_txs: None,
+            decode_threads: None,
+            metrics: None,
+            db_path: None,
+            static_path: None,
+            encryption: EncryptionArgs::default(),
+        }
+    }
+}
+
+impl ImportArgs {
+    /// Takes the chain, the chain directory, the database path and the static data path selected
+    /// by the database arguments
+    pub fn with_database(mut self, db: &DatabaseArgs) -> Result<Self> {
+        self.chain = db.chain;
+        self.chain_dir = Some(db.chain_dir());
+        self.db_path = Some(db.path());
+        self.static_path = Some(db.static_path()?);
+        Ok(self)
+    }
+
+    /// Opens the [DepositStore] below the static data path of the database, if it is known
+    pub fn deposit_store(&self) -> Result<Option<DepositStore>> {
+        self.static_path.as_deref().map(DepositStore::open).transpose()
+    }
+
+    /// Creates the progress tracker of an import, rendered as progress bars unless `--quiet` is
+    /// set and watched by a [Watchdog] if `--stall-timeout` is set. With `--metrics` the metrics
+    /// exporter is started and a [MetricsReporter] publishes the progress. The progress is
+    /// finished by a shutdown hook if the process is interrupted. The watchdog, the reporter and
+    /// the hook stop when the returned [ImportMonitors] are dropped.
+    pub fn watch(&self) -> Result<(Arc<ImportProgress>, ImportMonitors)> {
+        let progress = Arc::new(if self.quiet {
+            ImportProgress::default()
+        } else {
+            ImportProgress::with_bar()
+        });
+        let watchdog = self.stall_timeout.map(|timeout| {
+            Watchdog::spawn(progress.clone(), Duration::from_secs(timeout), self.abort_on_stall)
+        });
+        let reporter = match self.metrics {
+            Some(addr) => {
+                metrics::install(addr)?;
+                Some(MetricsReporter::spawn(progress.clone(), self.db_path.clone()))
+            }
+            None => None,
+        };
+        let shutdown = shutdown::registry().register("import progress", ShutdownPhase::Import, {
+            let progress = progress.clone();
+            move || async move {
+                tracing::info!(target: "reth::cli", stage = progress.stage(), items = progress.items(), "Import interrupted");
+                progress.finish();
+                Ok(())
+            }
+        });
+        Ok((
+            progress,
+            ImportMonitors { _watchdog: watchdog, _metrics: reporter, _shutdown: shutdown },
+        ))
+    }
+
+    /// Creates the I/O limiter configured with `--io-limit`, if any
+    pub fn io_limiter(&self) -> Option<IoLimiter> {
+        self.io_limit.map(IoLimiter::from_mb_per_sec)
+    }
+
+    /// Reads the input at `path`, limited by the given limiter if any. Reads stdin if the path is
+    /// `-`, otherwise transient failures are retried according to [ImportArgs::retry_policy].
+    pub fn read_input(&self, path: &str, limiter: Option<&IoLimiter>) -> Result<Vec<u8>> {
+        if throttle::is_stdin(path) {
+            return Ok(throttle::read_stdin(limiter)?)
+        }
+        self.retry_policy().run("read input", || Ok(throttle::read_file(path, limiter)?))
+    }
+
+    /// The retry policy configured with `--retries` and `--retry-backoff`
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy { retries: self.retries, backoff: Duration::from_millis(self.retry_backoff) }
+    }
+
+    /// The input of the given stage: the `--from-dead-letter` file or `path` if given, otherwise
+    /// the default input in the chain directory
+    pub fn input_path(&self, path: Option<&str>, stage: ImportStage) -> Result<String> {
+        if let Some(dead_letter) = &self.from_dead_letter {
+            return Ok(dead_letter.display().to_string())
+        }
+        if let Some(path) = path {
+            return Ok(path.to_string())
+        }
+        Ok(dirs::default_input(&self.chain_dir(), self.chain, stage)?.display().to_string())
+    }
+
+    /// The chain directory taken from the database arguments, or the directory of the chain in
+    /// the default data directory
+    fn chain_dir(&self) -> PathBuf {
+        match &self.chain_dir {
+            Some(chain_dir) => chain_dir.clone(),
+            None => dirs::chain_dir(&dirs::default_data_dir(), self.chain),
+        }
+    }
+
+    /// Opens the file collecting the records that fail to decode if the import is lenient.
+    /// Strict imports get none and fail on the first such record.
+    pub fn dead_letter_file(&self) -> Result<Option<DeadLetterFile>> {
+        if self.strict {
+            return Ok(None)
+        }
+        match &self.dead_letter {
+            Some(path) => Ok(Some(DeadLetterFile::create(path)?)),
+            None if self.lenient => {
+                let chain_dir = self.chain_dir();
+                std::fs::create_dir_all(&chain_dir)?;
+                Ok(Some(DeadLetterFile::create(chain_dir.join(ERROR_REPORT_FILE))?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// The policy for transactions without a signature: `--unsigned-txs` if given, the policy of
+    /// the selected chain otherwise
+    pub fn unsigned_tx_policy(&self) -> UnsignedTxPolicy {
+        self.unsigned_txs
+            .or_else(|| self.chain.map(|chain| chain.unsigned_tx_policy()))
+            .unwrap_or(UnsignedTxPolicy::Fail)
+    }
+
+    /// Creates the validator checking the inputs against the chain selected with `--chain`, if any
+    pub fn validator(&self) -> Option<SystemTxValidator> {
+        self.chain.map(SystemTxValidator::new)
+    }
+
+    /// Checks the timestamps of the blocks to import against `--max-block-gap` and the local
+    /// clock, failing on anomalies if `--strict-timestamps` is set
+    pub fn check_timestamps(&self, blocks: &[SealedBlock]) -> Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let issues = validate::check_timestamps(blocks, self.max_block_gap, now);
+        validate::report_timestamp_issues(&issues, self.strict_timestamps)
+    }
+
+    /// Checks that the volume holding the database at `db_path` has room for importing `input`
+    /// in the given stage
+    pub fn preflight(&self, db_path: &Path, stage: ImportStage, input: &Path) -> Result<()> {
+        preflight::check_disk_space(db_path, stage, input, self.allow_low_space)
+    }
+
+    /// Verifies the contents of the input at `path` against the checksum given with `--checksum`
+    /// or listed in `--checksum-manifest`, if any
+    pub fn verify_checksum(&self, path: &Path, data: &[u8]) -> Result<()> {
+        if let Some(expected) = &self.checksum {
+            return checksum::verify(path, data, expected)
+        }
+        if let Some(manifest_path) = &self.checksum_manifest {
+            let manifest = checksum::read_manifest(manifest_path)?;
+            let Some(expected) = checksum::manifest_entry(&manifest, path) else {
+                eyre::bail!(
+                    "{} is not listed in checksum manifest {}",
+                    path.display(),
+                    manifest_path.display()
+                );
+            };
+            return checksum::verify(path, data, expected)
+        }
+        Ok(())
+    }
+
+    /// Decrypts the contents of the input at `path` if they are encrypted, failing if no key was
+    /// given. Checksums cover the encrypted contents, so verify them first.
+    pub fn decrypt(&self, path: &Path, data: Vec<u8>) -> Result<Vec<u8>> {
+        if !encryption::is_encrypted(&data) {
+            return Ok(data)
+        }
+        let Some(key) = self.encryption.key()? else {
+            eyre::bail!("{} is encrypted, pass --key-file or --passphrase-file", path.display())
+        };
+        encryption::decrypt(&data, &key)
+            .map_err(|err| eyre::eyre!("Unable to decrypt {}: {err}", path.display()))
+    }
+}
+
+/// Arguments selecting the secret that snapshots are encrypted and decrypted with
+#[derive(Debug, Clone, Default, Args)]
+pub struct EncryptionArgs {
+    /// A file holding the 256 bit AES key encrypting the chain data, as 32 raw bytes or 64 hex
+    /// digits, e.g. written by `openssl rand -hex 32`.
+    ///
+    /// Exports are encrypted with AES-256-GCM, imports of encrypted inputs are decrypted.
+    #[arg(long, value_name = "FILE", conflicts_with = "passphrase_file", verbatim_doc_comment)]
+    pub key_file: Option<PathBuf>,
+
+    /// A file holding the passphrase the key encrypting the chain data is derived from, on its
+    /// first line.
+    ///
+    /// The key is derived with PBKDF2-HMAC-SHA256 and a random salt stored with the data.
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    pub passphrase_file: Option<PathBuf>,
+}
+
+impl EncryptionArgs {
+    /// Reads the key or passphrase file, if any
+    pub fn key(&self) -> Result<Option<SnapshotKey>> {
+        if let Some(path) = &self.key_file {
+            return Ok(Some(SnapshotKey::from_key_file(path)?))
+        }
+        if let Some(path) = &self.passphrase_file {
+            return Ok(Some(SnapshotKey::from_passphrase_file(path)?))
+        }
+        Ok(None)
+    }
+}
+
+/// The default number of blocks whose data is requested from a legacy node at a time
+pub const DEFAULT_RPC_CONCURRENCY: usize = 8;
+
+/// Arguments selecting the legacy node and the blocks a sync command fetches over JSON-RPC
+#[derive(Debug, Clone, Args)]
+pub struct RpcSyncArgs {
+    /// The JSON-RPC endpoint of the legacy node, e.g. an l2geth or Erigon archive node
+    #[arg(long = "rpc", value_name = "URL", verbatim_doc_comment)]
+    pub url: String,
+
+    /// The blocks to fetch, as `<FIRST>..<LAST>` (inclusive)
+    #[arg(long, value_name = "FIRST..LAST", value_parser = parse_block_range, verbatim_doc_comment)]
+    pub range: RangeInclusive<u64>,
+
+    /// The number of blocks whose data is requested at a time. Failed requests are retried
+    /// according to `--retries` and `--retry-backoff`.
+    #[arg(
+        long,
+        value_name = "BLOCKS",
+        default_value_t = DEFAULT_RPC_CONCURRENCY,
+        verbatim_doc_comment
+    )]
+    pub concurrency: usize,
+}
+
+impl RpcSyncArgs {
+    /// The source fetching the selected blocks from the node
+    pub fn source(&self) -> RpcSource {
+        RpcSource::new(self.url.as_str(), self.range.clone()).with_concurrency(self.concurrency)
+    }
+}
+
+/// Parses an inclusive block range written as `<FIRST>..<LAST>`
+pub fn parse_block_range(range: &str) -> Result<RangeInclusive<u64>, String> {
+    let Some((first, last)) = range.split_once("..") else {
+        return Err(format!("Invalid block range {range}, expected <FIRST>..<LAST>"))
+    };
+    let parse = |number: &str| {
+        number.trim().parse::<u64>().map_err(|err| format!("Invalid block number {number}: {err}"))
+    };
+    let (first, last) = (parse(first)?, parse(last.trim_start_matches('='))?);
+    if first > last {
+        return Err(format!("Invalid block range {range}, the first block follows the last"))
+    }
+    Ok(first..=last)
+}
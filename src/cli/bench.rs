@@ -0,0 +1,280 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use eyre::Result;
+use indicatif::HumanBytes;
+use reth::runner::CliContext;
+use reth_db::mdbx::{Env, WriteMap};
+use reth_primitives::SealedBlock;
+
+use crate::cli::{
+    args::ImportArgs,
+    blocks, db,
+    genesis::{self, Genesis},
+    l1_fee::L1FeeStore,
+    receipts::{self, Receipt},
+    source::{
+        file::FileSource,
+        shards::{self, ShardedSource},
+        BlockSource, ReceiptSource, SourceContext, StateSource,
+    },
+    state::{self, State},
+};
+
+/// Time slices of the block, receipt and state imports against a temporary database.
+///
+/// The imports run in the order genesis, blocks, receipts and state, each on the leading slice of
+/// its input. For every import the throughput and the growth of the database are reported, along
+/// with the peak memory use of the process.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The genesis the block import builds on
+    #[arg(long, value_name = "GENESIS", verbatim_doc_comment)]
+    genesis: Option<String>,
+
+    /// The block export to import, a file or a directory or glob pattern of shards
+    #[arg(long, value_name = "BLOCKS", verbatim_doc_comment)]
+    blocks: Option<String>,
+
+    /// The receipt export to import, a file or a directory or glob pattern of shards
+    #[arg(long, value_name = "RECEIPTS", verbatim_doc_comment)]
+    receipts: Option<String>,
+
+    /// The state dump to import, a file or a directory or glob pattern of dumps
+    #[arg(long, value_name = "STATE", verbatim_doc_comment)]
+    state: Option<String>,
+
+    /// Import the blocks and receipts up to this block only
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment)]
+    to_block: Option<u64>,
+
+    /// Import this many accounts of the state dump only
+    #[arg(long, value_name = "ACCOUNTS", verbatim_doc_comment)]
+    max_accounts: Option<u64>,
+
+    /// The directory to create the temporary database in, to benchmark the disk the import will
+    /// run on. Defaults to the system temporary directory.
+    #[arg(long, value_name = "DIR", verbatim_doc_comment)]
+    work_dir: Option<PathBuf>,
+
+    #[clap(flatten)]
+    import: ImportArgs,
+}
+
+/// The measurements of one import
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageTiming {
+    /// The name of the import
+    pub stage: &'static str,
+    /// The unit of the imported items
+    pub unit: &'static str,
+    /// The number of imported items
+    pub items: u64,
+    /// The growth of the database in bytes
+    pub bytes: u64,
+    /// The time the import took
+    pub elapsed: Duration,
+}
+
+impl StageTiming {
+    /// The imported items per second
+    pub fn items_per_second(&self) -> f64 {
+        self.items as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// The megabytes written to the database per second
+    pub fn megabytes_per_second(&self) -> f64 {
+        self.bytes as f64 / 1e6 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// The outcome of a benchmark
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BenchReport {
+    /// The measurements of every import, in the order they ran
+    pub stages: Vec<StageTiming>,
+    /// The peak resident memory of the process in bytes, where the platform reports it
+    pub peak_memory: Option<u64>,
+}
+
+/// Returns the peak resident memory of the process in bytes. Only reported on Linux.
+pub fn peak_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Reads the leading slice of a file or a set of shards
+struct Slice {
+    path: String,
+    limit: Option<u64>,
+    /// The number of records read, without the genesis block of a block export
+    items: u64,
+}
+
+impl Slice {
+    fn new(path: &str, limit: Option<u64>) -> Self {
+        Self { path: path.to_string(), limit, items: 0 }
+    }
+}
+
+impl BlockSource for Slice {
+    fn describe(&self) -> String {
+        format!("{} up to block {:?}", self.path, self.limit)
+    }
+
+    fn read_blocks(&mut self, ctx: &mut SourceContext<'_>) -> Result<Vec<SealedBlock>> {
+        let mut blocks = if shards::is_sharded(&self.path) {
+            ShardedSource::new(self.path.as_str()).read_blocks(ctx)?
+        } else {
+            FileSource::new(self.path.as_str()).read_blocks(ctx)?
+        };
+        if let Some(limit) = self.limit {
+            blocks.retain(|block| block.number <= limit);
+        }
+        self.items = blocks.iter().filter(|block| block.number > 0).count() as u64;
+        Ok(blocks)
+    }
+}
+
+impl ReceiptSource for Slice {
+    fn describe(&self) -> String {
+        format!("{} up to block {:?}", self.path, self.limit)
+    }
+
+    fn read_receipts(&mut self, ctx: &mut SourceContext<'_>) -> Result<Vec<Receipt>> {
+        let mut receipts = if shards::is_sharded(&self.path) {
+            ShardedSource::new(self.path.as_str()).read_receipts(ctx)?
+        } else {
+            FileSource::new(self.path.as_str()).read_receipts(ctx)?
+        };
+        if let Some(limit) = self.limit {
+            receipts.retain(|receipt| receipt.block_number <= limit);
+        }
+        self.items = receipts.len() as u64;
+        Ok(receipts)
+    }
+}
+
+impl StateSource for Slice {
+    fn describe(&self) -> String {
+        format!("{} limited to {:?} accounts", self.path, self.limit)
+    }
+
+    fn read_state(&mut self, ctx: &mut SourceContext<'_>) -> Result<State> {
+        let state = if shards::is_sharded(&self.path) {
+            ShardedSource::new(self.path.as_str()).read_state(ctx)?
+        } else {
+            FileSource::new(self.path.as_str()).read_state(ctx)?
+        };
+        let state: State = match self.limit {
+            Some(limit) => state.into_iter().take(limit as usize).collect(),
+            None => state,
+        };
+        self.items = state.len() as u64;
+        Ok(state)
+    }
+}
+
+/// The size of the database before an import, to measure the import against
+struct Measurement {
+    size: usize,
+    started: Instant,
+}
+
+impl Measurement {
+    /// Starts measuring an import
+    fn start(db: &Env<WriteMap>) -> Result<Self> {
+        Ok(Self { size: db::stats::collect(db)?.total_size(), started: Instant::now() })
+    }
+
+    /// Measures the finished import of `items` items
+    fn finish(
+        self,
+        db: &Env<WriteMap>,
+        stage: &'static str,
+        unit: &'static str,
+        items: u64,
+    ) -> Result<StageTiming> {
+        let elapsed = self.started.elapsed();
+        let size = db::stats::collect(db)?.total_size();
+        let bytes = size.saturating_sub(self.size) as u64;
+        tracing::info!(target: "reth::cli", stage, items, ?elapsed, "Import measured");
+        Ok(StageTiming { stage, unit, items, bytes, elapsed })
+    }
+}
+
+impl Command {
+    /// Runs the selected imports against a new database in `dir`
+    pub async fn run(&self, dir: &Path) -> Result<BenchReport> {
+        if self.blocks.is_some() && self.genesis.is_none() {
+            eyre::bail!("The block import builds on the genesis, pass --genesis");
+        }
+        let mut db = db::open_rw_env(&dir.join("db"))?;
+        db.create_tables()?;
+        let args = &self.import;
+        let mut report = BenchReport::default();
+
+        if let Some(path) = &self.genesis {
+            let accounts = Genesis::from_file(path)?.exported_state().len() as u64;
+            let measurement = Measurement::start(&db)?;
+            genesis::apply(&mut db, Some(path.as_str()), args).await?;
+            report.stages.push(measurement.finish(&db, "genesis", "accounts", accounts)?);
+        }
+        if let Some(path) = &self.blocks {
+            let mut source = Slice::new(path, self.to_block);
+            let measurement = Measurement::start(&db)?;
+            blocks::apply_from(&mut db, &mut source, args).await?;
+            report.stages.push(measurement.finish(&db, "blocks", "blocks", source.items)?);
+        }
+        if let Some(path) = &self.receipts {
+            let fees = L1FeeStore::open(&dir.join("static"))?;
+            let mut source = Slice::new(path, self.to_block);
+            let measurement = Measurement::start(&db)?;
+            receipts::apply_from(&mut db, &fees, &mut source, args).await?;
+            report.stages.push(measurement.finish(&db, "receipts", "receipts", source.items)?);
+        }
+        if let Some(path) = &self.state {
+            let mut source = Slice::new(path, self.max_accounts);
+            let measurement = Measurement::start(&db)?;
+            state::apply_from(&mut db, &mut source, args).await?;
+            report.stages.push(measurement.finish(&db, "state", "accounts", source.items)?);
+        }
+        report.peak_memory = peak_memory();
+        Ok(report)
+    }
+
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let dir = match &self.work_dir {
+            Some(work_dir) => tempfile::tempdir_in(work_dir)?,
+            None => tempfile::tempdir()?,
+        };
+        let report = self.run(dir.path()).await?;
+
+        println!(
+            "{:<10} {:>12} {:>10} {:>14} {:>12} {:>10}",
+            "Import", "Items", "Time", "Items/s", "Written", "MB/s"
+        );
+        for stage in &report.stages {
+            println!(
+                "{:<10} {:>12} {:>10.2?} {:>14} {:>12} {:>10.1}",
+                stage.stage,
+                format!("{} {}", stage.items, stage.unit),
+                stage.elapsed,
+                format!("{:.0}", stage.items_per_second()),
+                HumanBytes(stage.bytes).to_string(),
+                stage.megabytes_per_second()
+            );
+        }
+        match report.peak_memory {
+            Some(bytes) => println!("Peak memory: {}", HumanBytes(bytes)),
+            None => println!("Peak memory: not reported on this platform"),
+        }
+        Ok(())
+    }
+}
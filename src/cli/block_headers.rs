@@ -0,0 +1,201 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use eyre::Result;
+use reth::runner::CliContext;
+use reth_db::{
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::SealedBlock;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{
+    args::{DatabaseArgs, ImportArgs},
+    blocks::{self, BlockFormat},
+    journal,
+    preflight::ImportStage,
+    source::file::FileSource,
+};
+
+/// The file below a database path holding the block ranges imported without their bodies
+pub const HEADERS_ONLY_FILE: &str = "headers-only.json";
+
+/// Load the headers of a block export without their bodies.
+///
+/// Headers take a fraction of the time and space of full blocks, so the chain can be followed by
+/// hash and number first. The bodies and transactions are filled in later with
+/// `blocks backfill-bodies`, and the ranges still lacking them are recorded next to the database.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The path to the block dump file, or `-` to read it from stdin. Defaults to the block export
+    /// of the chain selected with `--chain`.
+    #[arg(long, value_name = "BLOCK_DUMP_PATH", verbatim_doc_comment)]
+    path: Option<String>,
+
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The layout of the block export. Detected from its contents by default.
+    #[arg(long, value_enum, value_name = "FORMAT", verbatim_doc_comment)]
+    format: Option<BlockFormat>,
+
+    #[clap(flatten)]
+    import: ImportArgs,
+}
+
+/// A range of blocks whose headers were imported without their bodies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderRange {
+    /// The first block of the range
+    pub from: u64,
+    /// The last block of the range (inclusive)
+    pub to: u64,
+}
+
+/// The block ranges of a database whose bodies are missing, stored next to it in ascending order
+#[derive(Debug, Clone)]
+pub struct HeadersOnlyRanges {
+    path: PathBuf,
+}
+
+impl HeadersOnlyRanges {
+    /// The ranges of the database at the given path
+    pub fn new(db_path: &Path) -> Self {
+        Self { path: db_path.join(HEADERS_ONLY_FILE) }
+    }
+
+    /// Reads all ranges, empty if every imported header has its body
+    pub fn read(&self) -> Result<Vec<HeaderRange>> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// Records a range of headers without bodies, merging it with adjacent and overlapping ranges
+    pub fn add(&self, range: HeaderRange) -> Result<()> {
+        let mut ranges = self.read()?;
+        ranges.push(range);
+        ranges.sort_by_key(|range| range.from);
+        let mut merged: Vec<HeaderRange> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.from <= last.to + 1 => last.to = last.to.max(range.to),
+                _ => merged.push(range),
+            }
+        }
+        self.write(&merged)
+    }
+
+    /// Removes the blocks from `from` to `to` from the ranges, once their bodies are imported
+    pub fn remove(&self, from: u64, to: u64) -> Result<()> {
+        let mut ranges = Vec::new();
+        for range in self.read()? {
+            if range.from < from {
+                ranges.push(HeaderRange { from: range.from, to: range.to.min(from - 1) });
+            }
+            if range.to > to {
+                ranges.push(HeaderRange { from: range.from.max(to + 1), to: range.to });
+            }
+        }
+        self.write(&ranges)
+    }
+
+    fn write(&self, ranges: &[HeaderRange]) -> Result<()> {
+        if ranges.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+                _ => Ok(()),
+            }
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_vec_pretty(ranges)?)?;
+        Ok(())
+    }
+}
+
+/// Apply the headers of the block export at `path` to the database at `db_path`, without their
+/// bodies, and record them in the [HeadersOnlyRanges] of the database. Returns the range of the
+/// imported headers, `None` if the export only holds the genesis block.
+///
+/// The headers must continue the canonical chain of the database like the blocks of a full import.
+pub async fn apply(
+    db: &mut Env<WriteMap>,
+    db_path: &Path,
+    path: Option<&str>,
+    format: Option<BlockFormat>,
+    args: &ImportArgs,
+) -> Result<Option<HeaderRange>> {
+    let file_path = args.input_path(path, ImportStage::Blocks)?;
+    let (progress, _monitors) = args.watch()?;
+    let limiter = args.io_limiter();
+    let mut source = FileSource::new(file_path).with_format(format);
+    let blocks = blocks::read_from(&mut source, args, &progress, limiter.as_ref())?;
+
+    db.create_tables()?;
+    if db.view(|tx| tx.get::<tables::Headers>(0))??.is_none() {
+        eyre::bail!("Genesis block not found! Please insert it before using this command.");
+    }
+    // The genesis block leading the export is inserted by the genesis import
+    let blocks = match blocks.first() {
+        Some(first) if first.number == 0 => &blocks[1..],
+        _ => &blocks[..],
+    };
+    let (Some(first), Some(last)) = (blocks.first(), blocks.last()) else { return Ok(None) };
+    let range = HeaderRange { from: first.number, to: last.number };
+
+    progress.set_stage("insert headers");
+    progress.set_total(blocks.len() as u64);
+    let retry = args.retry_policy();
+    for batch in blocks.chunks(args.batch_size.max(1)) {
+        blocks::check_continuity(db, batch, None, args.allow_gaps)?;
+        retry.run("insert headers", || {
+            let tx = db.tx_mut()?;
+            for block in batch {
+                insert_header(&tx, block)?;
+                progress.set_block(block.number);
+                progress.advance(1)?;
+            }
+            tx.commit()?;
+            Ok(())
+        })?;
+    }
+    HeadersOnlyRanges::new(db_path).add(range)?;
+    progress.finish();
+    tracing::info!(target: "reth::cli", from = range.from, to = range.to, "Headers inserted! 🎉");
+    Ok(Some(range))
+}
+
+/// Writes the header of the block and its canonical hash lookups
+fn insert_header<'a, TX: DbTxMut<'a> + DbTx<'a>>(tx: &TX, block: &SealedBlock) -> Result<()> {
+    tx.put::<tables::Headers>(block.number, block.header.clone().unseal())?;
+    tx.put::<tables::CanonicalHeaders>(block.number, block.hash())?;
+    tx.put::<tables::HeaderNumbers>(block.hash(), block.number)?;
+    Ok(())
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(mut self, _ctx: CliContext) -> Result<()> {
+        self.import = self.import.with_database(&self.db)?;
+        let db_path = self.db.path();
+        let path = self.import.input_path(self.path.as_deref(), ImportStage::Blocks)?;
+        journal::record(&db_path, "block-headers", &[Path::new(&path)], async {
+            self.import.preflight(&db_path, ImportStage::Blocks, Path::new(&path))?;
+            let mut db = self.db.open_rw()?;
+            apply(&mut db, &db_path, Some(&path), self.format, &self.import).await?;
+            Ok(())
+        })
+        .await
+    }
+}
@@ -1,56 +1,290 @@
-use crate::cli::db;
-use clap::Parser;
+use crate::cli::{
+    analytics,
+    args::{DatabaseArgs, ImportArgs, RpcSyncArgs},
+    block_headers::HeadersOnlyRanges,
+    chain::{self, UnsignedTxPolicy},
+    compression,
+    dead_letter::{self, DeadLetterFile, ImportError},
+    deposit::{self, DepositStore, TxDeposit, DEPOSIT_TX_TYPE},
+    export,
+    import::detect_block_format,
+    journal, keccak, pipeline,
+    preflight::ImportStage,
+    progress::ImportProgress,
+    regenesis::{RegenesisBoundaries, RegenesisBoundary},
+    replay::{self, DEFAULT_REPLAY_BATCH},
+    retry::RetryPolicy,
+    source::{
+        dead_letter::DeadLetterSource,
+        file::FileSource,
+        freezer::{self, FreezerSource},
+        mapped::Contents,
+        shards::{self, ShardedSource},
+        BlockSource, SourceContext,
+    },
+    throttle::IoLimiter,
+    validate::{self, eip155_chain_id, ContinuityIssue, SystemTxValidator, TimestampIssue},
+};
+use clap::{Parser, Subcommand, ValueEnum};
 use eyre::Result;
 use reth::runner::CliContext;
 use reth_db::{
+    cursor::DbCursorRO,
     database::Database,
     mdbx::{Env, WriteMap},
+    models::{StoredBlockBody, StoredBlockOmmers, StoredBlockWithdrawals},
+    table::Table,
     tables,
-    transaction::DbTx,
+    transaction::{DbTx, DbTxMut},
 };
 use reth_primitives::{
     rpc::{Bloom, H160, H256},
     rpc_utils::rlp::{Decodable, Rlp},
-    Bytes, Header, SealedBlock, Signature, Transaction, TransactionKind, TransactionSigned,
+    Block, Bytes, Header, SealedBlock, Signature, Transaction, TransactionKind, TransactionSigned,
     TxLegacy, U256,
 };
+use reth_rlp::Encodable;
 use serde::Serialize;
 use std::{
-    fs,
+    ops::RangeInclusive,
     path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
+pub mod query;
+
 /// A clone of erigon's block type
 #[derive(Debug, Serialize)]
 pub struct ErigonBlock {
     pub header: ErigonHeader,
-    pub txs: Vec<LegacyTx>,
+    pub txs: Vec<ErigonTx>,
     pub uncles: Vec<ErigonHeader>,
 }
 
-/// Read [SealedBlock]s from the specified file path
+/// The layout of a block export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BlockFormat {
+    /// A single rlp list of blocks, as written by Erigon's block export
+    Erigon,
+    /// Concatenated rlp-encoded blocks, as written by `geth export`
+    Geth,
+    /// Hex-encoded blocks in the standard devp2p encoding, as returned by `debug_getBadBlocks` or
+    /// `debug_getBlockRlp`
+    RlpStandard,
+}
+
+/// Read [SealedBlock]s from the specified file path, detecting the layout of the export and its
+/// compression
 pub fn read_blocks(path: impl AsRef<Path>) -> Result<Vec<SealedBlock>> {
-    let contents = fs::read(path)?;
-    let rlp = Rlp::new(&contents);
+    decode_blocks(&compression::read(path)?, None, None, None)
+}
+
+/// Decode [SealedBlock]s from the contents of an export, detecting its layout. See
+/// [decode_blocks_as].
+pub fn decode_blocks(
+    contents: &[u8],
+    dead_letter: Option<&mut DeadLetterFile>,
+    validator: Option<&mut SystemTxValidator>,
+    progress: Option<&ImportProgress>,
+) -> Result<Vec<SealedBlock>> {
+    let format = detect_block_format(contents)
+        .ok_or_else(|| eyre::eyre!("Unable to detect the layout of the block export"))?;
+    tracing::debug!(target: "reth::cli", ?format, "Detected block export layout");
+    decode_blocks_as(format, contents, dead_letter, validator, progress)
+}
+
+/// Decode [SealedBlock]s from the contents of an export in the given layout, writing blocks that
+/// fail to decode to the given dead-letter file, checking the decoded blocks with the given
+/// validator and reporting them to the given progress
+pub fn decode_blocks_as(
+    format: BlockFormat,
+    contents: &[u8],
+    dead_letter: Option<&mut DeadLetterFile>,
+    validator: Option<&mut SystemTxValidator>,
+    progress: Option<&ImportProgress>,
+) -> Result<Vec<SealedBlock>> {
+    match format {
+        BlockFormat::Erigon => decode_erigon_blocks(contents, dead_letter, validator, progress),
+        BlockFormat::Geth => decode_standard_blocks(contents, dead_letter, validator, progress),
+        BlockFormat::RlpStandard => {
+            let text = std::str::from_utf8(contents)?.trim();
+            let contents = hex::decode(text.trim_start_matches("0x"))?;
+            decode_standard_blocks(&contents, dead_letter, validator, progress)
+        }
+    }
+}
+
+/// Decode the blocks of an Erigon block export
+fn decode_erigon_blocks(
+    contents: &[u8],
+    mut dead_letter: Option<&mut DeadLetterFile>,
+    mut validator: Option<&mut SystemTxValidator>,
+    progress: Option<&ImportProgress>,
+) -> Result<Vec<SealedBlock>> {
+    let rlp = Rlp::new(contents);
+    if !rlp.is_list() {
+        eyre::bail!("The block export is not an rlp list")
+    }
 
     let mut blocks: Vec<SealedBlock> = Vec::with_capacity(4_061_227);
-    for block in rlp.iter() {
+    let mut offset = rlp.payload_info()?.header_len;
+    let mut count = 0;
+    for (index, block) in rlp.iter().enumerate() {
+        let start = offset;
+        offset += block.as_raw().len();
+        count += 1;
+        if let Some(progress) = progress {
+            progress.set_offset(offset as u64);
+            progress.advance(1)?;
+        }
         let erigon_block: Result<ErigonBlock, _> = Decodable::decode(&block);
-        if let Ok(erigon_block) = erigon_block {
-            blocks.push(erigon_block.into());
+        match erigon_block {
+            Ok(erigon_block) => {
+                if let Some(progress) = progress {
+                    progress.set_block(erigon_block.header.number);
+                }
+                if let Some(validator) = validator.as_deref_mut() {
+                    validator.validate_block(&erigon_block);
+                }
+                blocks.push(erigon_block.into())
+            }
+            Err(err) => {
+                let error = ImportError::new("block", index, err).at_offset(start);
+                dead_letter::reject(dead_letter.as_deref_mut(), error, block.as_raw())?;
+            }
         }
     }
+    check_list_end(contents, offset, count)?;
 
     Ok(blocks)
 }
 
+/// Fails if the items of an rlp list end before the end of the export, since iterating the list
+/// stops silently at the first item that can not be split off, like a truncated block
+fn check_list_end(contents: &[u8], end: usize, items: usize) -> Result<()> {
+    if end < contents.len() {
+        let error =
+            ImportError::new("block", items, "truncated or malformed rlp item").at_offset(end);
+        return Err(error.into())
+    }
+    Ok(())
+}
+
+/// Decode concatenated blocks in the standard devp2p encoding
+fn decode_standard_blocks(
+    contents: &[u8],
+    mut dead_letter: Option<&mut DeadLetterFile>,
+    mut validator: Option<&mut SystemTxValidator>,
+    progress: Option<&ImportProgress>,
+) -> Result<Vec<SealedBlock>> {
+    let mut blocks = Vec::new();
+    let mut offset = 0;
+    let mut index = 0;
+    while offset < contents.len() {
+        let len = rlp::PayloadInfo::from(&contents[offset..])
+            .map_err(|err| ImportError::new("block", index, err).at_offset(offset))?
+            .total();
+        let Some(raw) = contents.get(offset..offset + len) else {
+            return Err(ImportError::new("block", index, "truncated").at_offset(offset).into())
+        };
+        let start = offset;
+        offset += len;
+        if let Some(progress) = progress {
+            progress.set_offset(offset as u64);
+            progress.advance(1)?;
+        }
+        match <Block as reth_rlp::Decodable>::decode(&mut &raw[..]) {
+            Ok(block) => {
+                let block = seal_block(block);
+                if let Some(progress) = progress {
+                    progress.set_block(block.number);
+                }
+                if let Some(validator) = validator.as_deref_mut() {
+                    validator.validate_sealed_block(&block);
+                }
+                blocks.push(block)
+            }
+            Err(err) => {
+                let error = ImportError::new("block", index, err).at_offset(start);
+                dead_letter::reject(dead_letter.as_deref_mut(), error, raw)?;
+            }
+        }
+        index += 1;
+    }
+    Ok(blocks)
+}
+
+/// Seals a block decoded from the standard encoding
+pub fn seal_block(block: Block) -> SealedBlock {
+    SealedBlock {
+        header: keccak::seal(block.header),
+        body: block.body,
+        ommers: block.ommers.into_iter().map(keccak::seal).collect(),
+        withdrawals: block.withdrawals,
+    }
+}
+
+/// Splits the contents of an export in the given layout into the encodings of its blocks without
+/// decoding them. Hex-encoded exports have to be decoded into the [BlockFormat::Geth] layout
+/// first.
+pub fn split_blocks(format: BlockFormat, contents: &[u8]) -> Result<Vec<&[u8]>> {
+    match format {
+        BlockFormat::Erigon => {
+            let rlp = Rlp::new(contents);
+            if !rlp.is_list() {
+                eyre::bail!("The block export is not an rlp list")
+            }
+            let blocks: Vec<&[u8]> = rlp.iter().map(|block| block.as_raw()).collect();
+            let end =
+                rlp.payload_info()?.header_len + blocks.iter().map(|raw| raw.len()).sum::<usize>();
+            check_list_end(contents, end, blocks.len())?;
+            Ok(blocks)
+        }
+        BlockFormat::Geth => {
+            let mut blocks = Vec::new();
+            let mut offset = 0;
+            while offset < contents.len() {
+                let index = blocks.len();
+                let len = rlp::PayloadInfo::from(&contents[offset..])
+                    .map_err(|err| ImportError::new("block", index, err).at_offset(offset))?
+                    .total();
+                let Some(raw) = contents.get(offset..offset + len) else {
+                    return Err(ImportError::new("block", index, "truncated")
+                        .at_offset(offset)
+                        .into())
+                };
+                blocks.push(raw);
+                offset += len;
+            }
+            Ok(blocks)
+        }
+        BlockFormat::RlpStandard => {
+            eyre::bail!("Hex-encoded exports have to be decoded before splitting them")
+        }
+    }
+}
+
+/// Decodes a single block split from an export in the given layout with [split_blocks]
+pub fn decode_block(format: BlockFormat, raw: &[u8]) -> Result<SealedBlock, String> {
+    match format {
+        BlockFormat::Erigon => <ErigonBlock as Decodable>::decode(&Rlp::new(raw))
+            .map(SealedBlock::from)
+            .map_err(|err| err.to_string()),
+        BlockFormat::Geth | BlockFormat::RlpStandard => {
+            <Block as reth_rlp::Decodable>::decode(&mut &raw[..])
+                .map(seal_block)
+                .map_err(|err| err.to_string())
+        }
+    }
+}
+
 /// Convert an [ErigonBlock] to a [SealedBlock]
 impl From<ErigonBlock> for SealedBlock {
     fn from(block: ErigonBlock) -> Self {
-        let header = Header::from(block.header).seal_slow();
+        let header = keccak::seal(Header::from(block.header));
         let txs = block.txs.into_iter().map(TransactionSigned::from).collect();
         let uncles =
-            block.uncles.into_iter().map(|header| Header::from(header).seal_slow()).collect();
+            block.uncles.into_iter().map(|header| keccak::seal(Header::from(header))).collect();
         Self { header, body: txs, ommers: uncles, withdrawals: None }
     }
 }
@@ -59,13 +293,65 @@ impl From<ErigonBlock> for SealedBlock {
 impl Decodable for ErigonBlock {
     fn decode(rlp: &Rlp) -> Result<Self, reth_primitives::rpc_utils::rlp::DecoderError> {
         let header: ErigonHeader = rlp.val_at(0)?;
-        let txs = rlp.at(1)?.iter().map(|rlp| Decodable::decode(&rlp).unwrap()).collect();
+        let txs = rlp.at(1)?.iter().map(|rlp| Decodable::decode(&rlp)).collect::<Result<_, _>>()?;
         let uncles: Vec<ErigonHeader> = rlp.list_at(2)?;
 
         Ok(Self { header, uncles, txs })
     }
 }
 
+/// RLP encoder for [ErigonBlock], the inverse of its [Decodable] implementation
+impl rlp::Encodable for ErigonBlock {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(3);
+        s.append(&self.header);
+        s.append_list::<ErigonTx, _>(&self.txs);
+        s.append_list::<ErigonHeader, _>(&self.uncles);
+    }
+}
+
+/// Loads the canonical block with the given number from the database in the layout of Erigon's
+/// block export, restoring its deposits from `deposits`
+pub fn load_erigon_block<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    deposits: &DepositStore,
+    number: u64,
+) -> Result<Option<ErigonBlock>> {
+    let Some(header) = tx.get::<tables::Headers>(number)? else { return Ok(None) };
+    let mut txs = Vec::new();
+    if let Some(body) = tx.get::<tables::BlockBodies>(number)? {
+        for tx_id in body.start_tx_id..body.start_tx_id + body.tx_count {
+            let transaction = deposits
+                .read(tx, tx_id)?
+                .ok_or_else(|| eyre::eyre!("Transaction {tx_id} of block {number} not found"))?;
+            txs.push(ErigonTx::from(&transaction));
+        }
+    }
+    let uncles = tx
+        .get::<tables::BlockOmmers>(number)?
+        .map(|ommers| ommers.ommers.iter().map(ErigonHeader::from).collect())
+        .unwrap_or_default();
+    Ok(Some(ErigonBlock { header: ErigonHeader::from(&header), txs, uncles }))
+}
+
+/// Loads the canonical block with the given number from the database in the standard devp2p
+/// encoding, as `geth export` writes it
+pub fn load_standard_block<'a, TX: DbTx<'a>>(tx: &TX, number: u64) -> Result<Option<Block>> {
+    let Some(header) = tx.get::<tables::Headers>(number)? else { return Ok(None) };
+    let mut body = Vec::new();
+    if let Some(indices) = tx.get::<tables::BlockBodies>(number)? {
+        for tx_id in indices.start_tx_id..indices.start_tx_id + indices.tx_count {
+            let transaction = tx
+                .get::<tables::Transactions>(tx_id)?
+                .ok_or_else(|| eyre::eyre!("Transaction {tx_id} of block {number} not found"))?;
+            body.push(transaction);
+        }
+    }
+    let ommers =
+        tx.get::<tables::BlockOmmers>(number)?.map(|ommers| ommers.ommers).unwrap_or_default();
+    Ok(Some(Block { header, body, ommers, withdrawals: None }))
+}
+
 /// A clone of Erigon's block header type
 #[derive(Debug, Serialize)]
 pub struct ErigonHeader {
@@ -111,6 +397,29 @@ impl From<ErigonHeader> for Header {
     }
 }
 
+/// Convert a [Header] back to an [ErigonHeader]
+impl From<&Header> for ErigonHeader {
+    fn from(header: &Header) -> Self {
+        Self {
+            parent_hash: H256::from_slice(&header.parent_hash.0),
+            uncle_hash: H256::from_slice(&header.ommers_hash.0),
+            coinbase: H160::from_slice(&header.beneficiary.0),
+            state_root: H256::from_slice(&header.state_root.0),
+            tx_hash: H256::from_slice(&header.transactions_root.0),
+            receipts_root: H256::from_slice(&header.receipts_root.0),
+            logs_bloom: Bloom::from_slice(&header.logs_bloom.0),
+            difficulty: header.difficulty,
+            number: header.number,
+            gas_limit: header.gas_limit,
+            gas_used: header.gas_used,
+            timestamp: header.timestamp,
+            extra_data: header.extra_data.to_vec(),
+            mix_hash: H256::from_slice(&header.mix_hash.0),
+            block_nonce: header.nonce.to_le_bytes().to_vec(),
+        }
+    }
+}
+
 /// RLP Decoder for [ErigonHeader]
 impl Decodable for ErigonHeader {
     fn decode(rlp: &Rlp) -> Result<Self, reth_primitives::rpc_utils::rlp::DecoderError> {
@@ -150,8 +459,107 @@ impl Decodable for ErigonHeader {
     }
 }
 
+/// RLP encoder for [ErigonHeader], the inverse of its [Decodable] implementation
+impl rlp::Encodable for ErigonHeader {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(15);
+        s.append(&self.parent_hash);
+        s.append(&self.uncle_hash);
+        s.append(&self.coinbase);
+        s.append(&self.state_root);
+        s.append(&self.tx_hash);
+        s.append(&self.receipts_root);
+        s.append(&self.logs_bloom);
+        s.append(&self.difficulty);
+        s.append(&self.number);
+        s.append(&self.gas_limit);
+        s.append(&self.gas_used);
+        s.append(&self.timestamp);
+        s.append(&self.extra_data);
+        s.append(&self.mix_hash);
+        s.append_list::<u8, u8>(&self.block_nonce);
+    }
+}
+
+/// A transaction of an Erigon block export
+///
+/// l2geth was pre-berlin, so its exports only hold legacy transactions, but exports of later forks
+/// and other OP chains contain typed transaction envelopes as well.
+#[derive(Debug, Serialize)]
+pub enum ErigonTx {
+    /// A legacy transaction, encoded as a list
+    Legacy(LegacyTx),
+    /// An EIP-2930 or EIP-1559 transaction, encoded as a string holding its typed envelope
+    #[serde(skip)]
+    Typed(TransactionSigned),
+}
+
+impl From<LegacyTx> for ErigonTx {
+    fn from(tx: LegacyTx) -> Self {
+        ErigonTx::Legacy(tx)
+    }
+}
+
+/// Convert an [ErigonTx] to a [TransactionSigned]
+impl From<ErigonTx> for TransactionSigned {
+    fn from(tx: ErigonTx) -> Self {
+        match tx {
+            ErigonTx::Legacy(tx) => tx.into(),
+            ErigonTx::Typed(tx) => tx,
+        }
+    }
+}
+
+/// Convert a [TransactionSigned] back to an [ErigonTx]
+impl From<&TransactionSigned> for ErigonTx {
+    fn from(tx: &TransactionSigned) -> Self {
+        match LegacyTx::try_from(tx) {
+            Ok(legacy) => ErigonTx::Legacy(legacy),
+            Err(_) => ErigonTx::Typed(tx.clone()),
+        }
+    }
+}
+
+/// RLP Decoder for [ErigonTx], telling legacy transactions and typed envelopes apart by whether
+/// the item is a list
+impl Decodable for ErigonTx {
+    fn decode(rlp: &Rlp) -> Result<Self, reth_primitives::rpc_utils::rlp::DecoderError> {
+        if rlp.is_list() {
+            return Ok(ErigonTx::Legacy(Decodable::decode(rlp)?))
+        }
+        match rlp.data()?.first() {
+            Some(&DEPOSIT_TX_TYPE) => TxDeposit::decode_enveloped(rlp.data()?)
+                .map(|deposit| ErigonTx::Typed(deposit.into_carrier()))
+                .map_err(|_| {
+                    reth_primitives::rpc_utils::rlp::DecoderError::Custom(
+                        "invalid deposit transaction",
+                    )
+                }),
+            Some(_) => <TransactionSigned as reth_rlp::Decodable>::decode(&mut rlp.as_raw())
+                .map(ErigonTx::Typed)
+                .map_err(|_| {
+                    reth_primitives::rpc_utils::rlp::DecoderError::Custom(
+                        "invalid typed transaction envelope",
+                    )
+                }),
+            None => Err(reth_primitives::rpc_utils::rlp::DecoderError::RlpIsTooShort),
+        }
+    }
+}
+
+/// RLP encoder for [ErigonTx], the inverse of its [Decodable] implementation
+impl rlp::Encodable for ErigonTx {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        match self {
+            ErigonTx::Legacy(tx) => tx.rlp_append(s),
+            ErigonTx::Typed(tx) => {
+                s.append(&deposit::encode_tx(tx));
+            }
+        }
+    }
+}
+
 /// A legacy Ethereum transaction
-/// l2geth was pre-berlin, so it only uses the legacy transaction schema
 #[derive(Debug, Serialize)]
 pub struct LegacyTx {
     pub nonce: u64,
@@ -168,8 +576,9 @@ pub struct LegacyTx {
 /// Convert a [LegacyTx] to a [TransactionSigned]
 impl From<LegacyTx> for TransactionSigned {
     fn from(tx: LegacyTx) -> Self {
+        let (chain_id, odd_y_parity) = parse_v(tx.v);
         let unsigned_tx = Transaction::Legacy(TxLegacy {
-            chain_id: None,
+            chain_id,
             nonce: tx.nonce,
             gas_price: tx.gas_price,
             gas_limit: tx.gas,
@@ -182,17 +591,23 @@ impl From<LegacyTx> for TransactionSigned {
             input: Bytes::from(tx.data),
         });
 
-        let signature = Signature {
-            r: tx.r,
-            s: tx.s,
-            // An odd v means that the odd y-parity of the signature is true.
-            odd_y_parity: (tx.v % U256::from(2)) == U256::from(1),
-        };
-
+        let signature = Signature { r: tx.r, s: tx.s, odd_y_parity };
         TransactionSigned::from_transaction_and_signature(unsigned_tx, signature)
     }
 }
 
+/// Splits the `v` value of a legacy signature into the chain id it is replay protected for, if
+/// any, and the y-parity of the signature.
+///
+/// EIP-155 signatures use `v = 35 + 2 * chain_id + parity`, older ones `v = 27 + parity`. The
+/// unsigned L1 to L2 messages of l2geth carry a zero `v`.
+pub fn parse_v(v: U256) -> (Option<u64>, bool) {
+    match eip155_chain_id(v) {
+        Some(chain_id) => (Some(chain_id), v.as_limbs()[0] - 35 - 2 * chain_id == 1),
+        None => (None, v == U256::from(28) || v == U256::from(1)),
+    }
+}
+
 /// RLP Decoder for [LegacyTx]
 impl Decodable for LegacyTx {
     fn decode(rlp: &Rlp) -> Result<Self, reth_primitives::rpc_utils::rlp::DecoderError> {
@@ -211,10 +626,132 @@ impl Decodable for LegacyTx {
     }
 }
 
+/// RLP encoder for [LegacyTx], the inverse of its [Decodable] implementation
+impl rlp::Encodable for LegacyTx {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(9);
+        s.append(&self.nonce);
+        s.append(&self.gas_price);
+        s.append(&self.gas);
+        match &self.to {
+            Some(to) => s.append(to),
+            None => s.append_empty_data(),
+        };
+        s.append(&self.value);
+        s.append(&self.data);
+        s.append(&self.v);
+        s.append(&self.r);
+        s.append(&self.s);
+    }
+}
+
+/// Convert a [TransactionSigned] back to a [LegacyTx]. Fails for typed transactions, which are
+/// exported as [ErigonTx::Typed] instead.
+impl TryFrom<&TransactionSigned> for LegacyTx {
+    type Error = eyre::Report;
+
+    fn try_from(tx: &TransactionSigned) -> Result<Self> {
+        let Transaction::Legacy(legacy) = &tx.transaction else {
+            eyre::bail!("Transaction {:?} is not a legacy transaction", tx.hash())
+        };
+        Ok(Self {
+            nonce: legacy.nonce,
+            gas_price: legacy.gas_price,
+            gas: legacy.gas_limit,
+            to: match legacy.to {
+                TransactionKind::Call(to) => Some(H160::from_slice(&to.0)),
+                TransactionKind::Create => None,
+            },
+            value: legacy.value,
+            data: legacy.input.to_vec(),
+            // The inverse of `parse_v`, keeping unsigned messages at a zero v
+            v: if tx.signature.r == U256::ZERO && tx.signature.s == U256::ZERO {
+                U256::ZERO
+            } else {
+                U256::from(tx.signature.v(legacy.chain_id))
+            },
+            r: tx.signature.r,
+            s: tx.signature.s,
+        })
+    }
+}
+
 /// Block command
+///
+/// Without a subcommand the block export is imported, like `blocks import`, so that the
+/// `blocks --path <BLOCK_DUMP_PATH>` invocation of earlier versions keeps working.
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct Command {
-    /// The path to the block dump file
+    #[clap(subcommand)]
+    command: Option<Subcommands>,
+
+    #[clap(flatten)]
+    import: ImportCommand,
+}
+
+/// Block subcommands
+#[derive(Debug, Subcommand)]
+pub enum Subcommands {
+    /// Load blocks from an export
+    #[command(name = "import")]
+    Import(ImportCommand),
+    /// Unwind a range of blocks and import them again from a corrected export
+    #[command(name = "reimport")]
+    Reimport(ReimportCommand),
+    /// Write the canonical blocks to an export
+    #[command(name = "export")]
+    Export(ExportCommand),
+    /// Fill in the bodies of blocks whose headers were imported with `block-headers`
+    #[command(name = "backfill-bodies")]
+    BackfillBodies(BackfillBodiesCommand),
+    /// Print a single block of an export or of the database, to debug decoding problems
+    #[command(name = "query")]
+    Query(query::QueryCommand),
+    /// Fetch a range of blocks from the JSON-RPC API of a legacy node and insert them
+    #[command(name = "sync")]
+    Sync(SyncCommand),
+}
+
+/// Block export command
+#[derive(Debug, Parser)]
+pub struct ExportCommand {
+    /// The file to write the blocks to. With `--format geth`, a path ending in `.gz` is
+    /// gzip-compressed like `geth export` does.
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    path: PathBuf,
+
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The layout of the export. `geth` writes the framing `geth import` reads, to load the
+    /// migrated history into op-geth.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = BlockFormat::Erigon,
+        verbatim_doc_comment
+    )]
+    format: BlockFormat,
+
+    /// The first block to export
+    #[arg(long, value_name = "BLOCK", default_value_t = 0, verbatim_doc_comment)]
+    from: u64,
+
+    /// The last block to export (inclusive). Defaults to the highest canonical block.
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment)]
+    to: Option<u64>,
+
+    /// Do not render progress bars
+    #[arg(long, short, verbatim_doc_comment)]
+    quiet: bool,
+}
+
+/// Block reimport command
+#[derive(Debug, Parser)]
+pub struct ReimportCommand {
+    /// The path to the corrected block dump file
     #[arg(
         long,
         value_name = "BLOCK_DUMP_PATH",
@@ -223,59 +760,959 @@ pub struct Command {
     )]
     path: String,
 
-    /// The path to the database
-    #[arg(long, value_name = "DATABASE_PATH", verbatim_doc_comment)]
-    database: String,
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The first block of the range to reimport
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment)]
+    from: u64,
+
+    /// The last block of the range to reimport (inclusive)
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment)]
+    to: u64,
 }
 
-/// Apply genesis state to the given database
-pub async fn apply(db: &mut Env<WriteMap>, path: Option<&str>) -> Result<()> {
-    let contents = fs::read(path.unwrap_or("data/export_0_4061224"))?;
-    let rlp = Rlp::new(&contents);
+/// Fetch a range of blocks from a legacy node over JSON-RPC and insert them, for when no export
+/// can be written on the node's machine.
+///
+/// The blocks are read with `debug_getBlockRlp` and inserted like the blocks of an export, so they
+/// have to continue the canonical chain of the database.
+#[derive(Debug, Parser)]
+pub struct SyncCommand {
+    #[clap(flatten)]
+    rpc: RpcSyncArgs,
 
-    let mut blocks: Vec<SealedBlock> = Vec::with_capacity(4_061_227);
-    for block in rlp.iter() {
-        let erigon_block: Result<ErigonBlock, _> = Decodable::decode(&block);
-        if let Ok(erigon_block) = erigon_block {
-            blocks.push(erigon_block.into());
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    #[clap(flatten)]
+    import: ImportArgs,
+}
+
+/// Fill in the bodies, transactions and their lookup tables of blocks whose headers were imported
+/// without them.
+///
+/// Transaction ids follow the bodies before them, so the bodies are backfilled in block order
+/// starting at the first block without a body. The blocks of the export must match the imported
+/// headers.
+#[derive(Debug, Parser)]
+pub struct BackfillBodiesCommand {
+    /// The path to the block dump file, or `-` to read it from stdin. Defaults to the block export
+    /// of the chain selected with `--chain`.
+    #[arg(long, value_name = "BLOCK_DUMP_PATH", verbatim_doc_comment)]
+    path: Option<String>,
+
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The layout of the block export. Detected from its contents by default.
+    #[arg(long, value_enum, value_name = "FORMAT", verbatim_doc_comment)]
+    format: Option<BlockFormat>,
+
+    #[clap(flatten)]
+    import: ImportArgs,
+}
+
+/// Block import command
+#[derive(Debug, Parser)]
+pub struct ImportCommand {
+    /// The path to the block dump file, or `-` to read it from stdin. Defaults to the block export
+    /// of the chain selected with `--chain`.
+    #[arg(long, value_name = "BLOCK_DUMP_PATH", verbatim_doc_comment)]
+    path: Option<String>,
+
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The layout of the block export. Detected from its contents by default.
+    #[arg(long, value_enum, value_name = "FORMAT", verbatim_doc_comment)]
+    format: Option<BlockFormat>,
+
+    /// Import the export as a legacy segment following a regenesis.
+    ///
+    /// Its first block is the genesis anchor of the segment, which continues the block numbering
+    /// of the database without linking to its tip. The boundary is recorded next to the database.
+    #[arg(long, verbatim_doc_comment)]
+    regenesis: bool,
+
+    /// Re-execute the imported blocks after inserting them, writing the account and storage
+    /// changesets and history indices like reth's pipeline does.
+    ///
+    /// The database has to hold the genesis state, or the state after the blocks executed before,
+    /// not an imported state dump. Run `state hash-and-trie` afterwards.
+    #[arg(long, conflicts_with = "regenesis", verbatim_doc_comment)]
+    execute: bool,
+
+    #[clap(flatten)]
+    import: ImportArgs,
+}
+
+/// Apply blocks to the given database, detecting the layout of the export
+pub async fn apply(db: &mut Env<WriteMap>, path: Option<&str>, args: &ImportArgs) -> Result<()> {
+    apply_as(db, path, None, args).await
+}
+
+/// Apply blocks to the given database, decoding the export in the given layout or the detected one.
+/// A directory or glob pattern is read as an export split into block ranges, a geth freezer
+/// directory as its ancient blocks.
+pub async fn apply_as(
+    db: &mut Env<WriteMap>,
+    path: Option<&str>,
+    format: Option<BlockFormat>,
+    args: &ImportArgs,
+) -> Result<()> {
+    if let Some(dead_letter) = &args.from_dead_letter {
+        return apply_from(db, &mut DeadLetterSource::open(dead_letter)?, args).await
+    }
+    let file_path = args.input_path(path, ImportStage::Blocks)?;
+    if freezer::is_freezer(Path::new(&file_path)) {
+        return apply_from(db, &mut FreezerSource::new(file_path), args).await
+    }
+    if shards::is_sharded(&file_path) {
+        return apply_from(db, &mut ShardedSource::new(file_path).with_format(format), args).await
+    }
+    if let Some(threads) = args.decode_threads {
+        return apply_pipelined(db, &file_path, format, threads, args)
+    }
+    apply_from(db, &mut FileSource::new(file_path).with_format(format), args).await
+}
+
+/// Apply the blocks of the export at `path` to the given database, decoding them on `threads`
+/// threads while inserting the blocks decoded before.
+///
+/// Timestamp anomalies are checked chunk by chunk, so `--strict-timestamps` refuses the chunk
+/// holding the first anomaly but keeps the chunks inserted before it.
+pub fn apply_pipelined(
+    db: &mut Env<WriteMap>,
+    path: &str,
+    format: Option<BlockFormat>,
+    threads: usize,
+    args: &ImportArgs,
+) -> Result<()> {
+    let (progress, _monitors) = args.watch()?;
+    let limiter = args.io_limiter();
+    progress.set_stage("read blocks");
+    let contents = FileSource::new(path).read(&SourceContext {
+        args,
+        progress: &progress,
+        limiter: limiter.as_ref(),
+        dead_letter: None,
+        validator: None,
+    })?;
+    let format = match format.or_else(|| detect_block_format(&contents)) {
+        Some(format) => format,
+        None => eyre::bail!("Unable to detect the layout of the block export"),
+    };
+    let (format, contents) = match format {
+        BlockFormat::RlpStandard => {
+            let text = std::str::from_utf8(&contents)?.trim();
+            (BlockFormat::Geth, Contents::Owned(hex::decode(text.trim_start_matches("0x"))?))
         }
+        format => (format, contents),
+    };
+    let raw = split_blocks(format, &contents)?;
+
+    db.create_tables()?;
+    if db.view(|tx| tx.get::<tables::Headers>(0))??.is_none() {
+        eyre::bail!("Genesis block not found! Please insert it before using this command.");
     }
 
+    let mut dead_letter = args.dead_letter_file()?;
+    let mut validator = args.validator();
+    let retry = args.retry_policy();
+    let deposits = args.deposit_store()?;
+    // The blocks are slices of the contents, so their distance to its start is their offset
+    let chunks: Vec<Vec<(usize, usize, &[u8])>> = raw
+        .into_iter()
+        .enumerate()
+        .map(|(index, raw)| (index, raw.as_ptr() as usize - contents.as_ptr() as usize, raw))
+        .collect::<Vec<_>>()
+        .chunks(args.batch_size.max(1))
+        .map(<[_]>::to_vec)
+        .collect();
+    tracing::info!(target: "reth::cli", path, ?format, threads, chunks = chunks.len(), "Decoding and inserting blocks");
+    progress.set_stage("decode and insert blocks");
+    progress.set_total(chunks.iter().map(Vec::len).sum::<usize>() as u64);
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let mut timestamp_issues = Vec::new();
+    let mut previous: Option<SealedBlock> = None;
+    let decode = |chunk: Vec<_>| {
+        chunk
+            .into_iter()
+            .map(|(index, offset, raw)| (index, offset, raw, decode_block(format, raw)))
+            .collect()
+    };
+    pipeline::run(chunks, threads, pipeline::DEFAULT_QUEUE_DEPTH, decode, |decoded: Vec<_>| {
+        let mut blocks = Vec::with_capacity(decoded.len());
+        for (index, offset, raw, block) in decoded {
+            match block {
+                Ok(block) => {
+                    if let Some(validator) = validator.as_mut() {
+                        validator.validate_sealed_block(&block);
+                    }
+                    blocks.push(block);
+                }
+                Err(err) => {
+                    let error = ImportError::new("block", index, err).at_offset(offset);
+                    dead_letter::reject(dead_letter.as_mut(), error, raw)?;
+                    progress.advance(1)?;
+                }
+            }
+        }
+
+        // Check the chunk together with the last block before it, whose own anomalies were
+        // reported with the chunk before
+        let before = previous.take();
+        let skipped = before.as_ref().map(|block| block.number);
+        blocks.splice(0..0, before);
+        let issues = validate::check_timestamps(&blocks, args.max_block_gap, now);
+        let before = skipped.map(|_| blocks.remove(0));
+        let issues: Vec<_> = issues
+            .into_iter()
+            .filter(|issue| match issue {
+                TimestampIssue::Future { block, .. } => Some(*block) != skipped,
+                _ => true,
+            })
+            .collect();
+        if args.strict_timestamps {
+            validate::report_timestamp_issues(&issues, true)?;
+        }
+        timestamp_issues.extend(issues);
+        previous = blocks.last().cloned().or(before);
+
+        apply_unsigned_tx_policy(&mut blocks, args.unsigned_tx_policy())?;
+
+        // The genesis block leading the export is inserted by the genesis import
+        if blocks.first().map_or(false, |block| block.number == 0) {
+            blocks.remove(0);
+            progress.advance(1)?;
+        }
+        insert_batches(
+            db,
+            deposits.as_ref(),
+            &blocks,
+            usize::MAX,
+            &retry,
+            &progress,
+            limiter.as_ref(),
+            None,
+            args.allow_gaps,
+        )
+    })?;
+
+    if let Some(dead_letter) = dead_letter {
+        dead_letter.finish()?;
+    }
+    if let Some(validator) = validator {
+        validator.finish()?;
+    }
+    validate::report_timestamp_issues(&timestamp_issues, false)?;
+    progress.finish();
+    tracing::info!(target: "reth::cli", "Blocks inserted! 🎉");
+    Ok(())
+}
+
+/// Apply the blocks read from the given source to the given database
+pub async fn apply_from(
+    db: &mut Env<WriteMap>,
+    source: &mut dyn BlockSource,
+    args: &ImportArgs,
+) -> Result<()> {
+    let (progress, _monitors) = args.watch()?;
+    let limiter = args.io_limiter();
+    let blocks = read_from(source, args, &progress, limiter.as_ref())?;
+
     db.create_tables()?;
 
     // Insert all block headers into MDBX
-    match db.update(|tx| {
-        // The following operation requires the genesis block to be present in the database
-        if let Ok(None) = tx.get::<tables::Headers>(0) {
-            eyre::bail!("Genesis block not found! Please insert it before using this command.");
+    progress.set_stage("insert blocks");
+    progress.set_total(blocks.iter().filter(|block| block.number != 0).count() as u64);
+    insert_blocks(
+        db,
+        args.deposit_store()?.as_ref(),
+        &blocks,
+        args.batch_size,
+        &args.retry_policy(),
+        &progress,
+        limiter.as_ref(),
+        args.allow_gaps,
+    )?;
+    progress.finish();
+    tracing::info!(target: "reth::cli", "Blocks inserted! 🎉");
+
+    Ok(())
+}
+
+/// Insert the bodies of the blocks recorded in the [HeadersOnlyRanges] of the database at
+/// `db_path` from the block export at `path`. Returns the number of backfilled blocks.
+///
+/// Blocks of the export outside of the ranges are skipped. The first backfilled block must be the
+/// first block without a body, and every block must hash to the header imported at its height.
+pub async fn backfill_bodies(
+    db: &mut Env<WriteMap>,
+    db_path: &Path,
+    path: Option<&str>,
+    format: Option<BlockFormat>,
+    args: &ImportArgs,
+) -> Result<u64> {
+    let ranges = HeadersOnlyRanges::new(db_path);
+    // Bodies committed by an interrupted backfill are not backfilled twice
+    for range in ranges.read()? {
+        let mut filled = range.from;
+        while filled <= range.to && db.view(|tx| tx.get::<tables::BlockBodies>(filled))??.is_some()
+        {
+            filled += 1;
+        }
+        if filled > range.from {
+            ranges.remove(range.from, filled - 1)?;
+        }
+    }
+    let missing = ranges.read()?;
+    let Some(first) = missing.first().copied() else {
+        eyre::bail!("Every imported header has its body, there is nothing to backfill")
+    };
+
+    let file_path = args.input_path(path, ImportStage::Blocks)?;
+    let (progress, _monitors) = args.watch()?;
+    let limiter = args.io_limiter();
+    let mut source = FileSource::new(file_path).with_format(format);
+    let blocks: Vec<SealedBlock> = read_from(&mut source, args, &progress, limiter.as_ref())?
+        .into_iter()
+        .filter(|block| missing.iter().any(|range| (range.from..=range.to).contains(&block.number)))
+        .collect();
+    let Some(start) = blocks.first() else {
+        eyre::bail!("The export holds none of the blocks without a body")
+    };
+    if start.number != first.from {
+        eyre::bail!(
+            "The export starts backfilling at block {}, but the first block without a body is {}",
+            start.number,
+            first.from
+        );
+    }
+    for block in &blocks {
+        let imported = db.view(|tx| tx.get::<tables::CanonicalHeaders>(block.number))??;
+        if imported != Some(block.hash()) {
+            eyre::bail!(
+                "Block {} of the export has hash {:?}, the imported header is {imported:?}",
+                block.number,
+                block.hash()
+            );
         }
+    }
 
-        dbg!(&blocks[0]);
-        // TODO: Why is there no signature attached to the transaction within block #1?
-        for sealed_block in &blocks[1..] {
-            // TODO: Parent tx num transition
-            // I think we just need the genesis block inserted first?
+    progress.set_stage("backfill bodies");
+    progress.set_total(blocks.len() as u64);
+    let retry = args.retry_policy();
+    let deposits = args.deposit_store()?;
+    insert_batches(
+        db,
+        deposits.as_ref(),
+        &blocks,
+        args.batch_size,
+        &retry,
+        &progress,
+        limiter.as_ref(),
+        None,
+        false,
+    )?;
+    let last = blocks.last().map_or(start.number, |block| block.number);
+    ranges.remove(start.number, last)?;
+    progress.finish();
+    tracing::info!(target: "reth::cli", from = start.number, to = last, "Bodies backfilled! 🎉");
+    Ok(blocks.len() as u64)
+}
 
-            // We have no block rewards pre-merge
-            reth_provider::insert_canonical_block(tx, sealed_block, false).unwrap();
+/// Apply a legacy segment following a regenesis to the given database, continuing the block
+/// numbering of the database.
+///
+/// The first block read from the source is the genesis anchor of the segment. It is imported like
+/// any other block although its parent hash doesn't link to the database tip, and the boundary is
+/// recorded in `boundaries`. The following blocks must link to the anchor.
+pub async fn apply_segment(
+    db: &mut Env<WriteMap>,
+    source: &mut dyn BlockSource,
+    boundaries: &RegenesisBoundaries,
+    args: &ImportArgs,
+) -> Result<RegenesisBoundary> {
+    let (progress, _monitors) = args.watch()?;
+    let limiter = args.io_limiter();
+    let blocks = read_from(source, args, &progress, limiter.as_ref())?;
+    let Some(anchor) = blocks.first() else {
+        eyre::bail!("The segment {} holds no blocks", source.describe())
+    };
+
+    progress.set_stage("check segment");
+    let Some(tip) = analytics::canonical_tip(db)? else {
+        eyre::bail!("Genesis block not found! Please insert it before importing a segment.")
+    };
+    if anchor.number != tip + 1 {
+        eyre::bail!(
+            "The segment starts at block {}, but the database ends at block {tip}. Segments must \
+             continue the block numbering of the database.",
+            anchor.number
+        );
+    }
+    let previous_hash = db
+        .view(|tx| tx.get::<tables::CanonicalHeaders>(tip))??
+        .ok_or_else(|| eyre::eyre!("Canonical hash of block {tip} not found"))?;
+    if anchor.parent_hash == previous_hash {
+        eyre::bail!(
+            "The first block of the segment links to block {tip}, import it as blocks instead"
+        );
+    }
+    for pair in blocks.windows(2) {
+        if pair[1].parent_hash != pair[0].hash() {
+            eyre::bail!("Block {} of the segment doesn't link to its parent", pair[1].number);
         }
+    }
 
-        Ok(())
-    })? {
-        Ok(_) => tracing::info!(target: "reth::cli", "Blocks inserted! 🎉"),
-        Err(err) => {
-            tracing::error!(target: "reth::cli", "Error inserting blocks into DB: {}", err)
+    let boundary = RegenesisBoundary {
+        segment: boundaries.next_segment()?,
+        first_block: anchor.number,
+        anchor_hash: anchor.hash(),
+        anchor_parent_hash: anchor.parent_hash,
+        anchor_state_root: anchor.state_root,
+        previous_hash,
+    };
+
+    progress.set_stage("insert blocks");
+    progress.set_total(blocks.len() as u64);
+    // The boundary is recorded first, so the broken link is explained even if the import fails
+    boundaries.append(boundary.clone())?;
+    insert_batches(
+        db,
+        args.deposit_store()?.as_ref(),
+        &blocks,
+        args.batch_size,
+        &args.retry_policy(),
+        &progress,
+        limiter.as_ref(),
+        Some(anchor.number),
+        args.allow_gaps,
+    )?;
+    progress.finish();
+    tracing::info!(
+        target: "reth::cli",
+        segment = boundary.segment,
+        first_block = boundary.first_block,
+        last_block = tip + blocks.len() as u64,
+        anchor = ?boundary.anchor_hash,
+        "Segment inserted! 🎉"
+    );
+    Ok(boundary)
+}
+
+/// Reads the blocks of the given source, writing the records failing to decode to the
+/// dead-letter file and validating them against the selected chain. Fails on timestamp anomalies
+/// if `--strict-timestamps` is set.
+pub(crate) fn read_from(
+    source: &mut dyn BlockSource,
+    args: &ImportArgs,
+    progress: &ImportProgress,
+    limiter: Option<&IoLimiter>,
+) -> Result<Vec<SealedBlock>> {
+    let mut dead_letter = args.dead_letter_file()?;
+    let mut validator = args.validator();
+    tracing::info!(target: "reth::cli", source = %source.describe(), "Reading blocks");
+    let mut blocks = source.read_blocks(&mut SourceContext {
+        args,
+        progress,
+        limiter,
+        dead_letter: dead_letter.as_mut(),
+        validator: validator.as_mut(),
+    })?;
+    if let Some(dead_letter) = dead_letter {
+        dead_letter.finish()?;
+    }
+    if let Some(validator) = validator {
+        validator.finish()?;
+    }
+    progress.set_stage("check signatures");
+    apply_unsigned_tx_policy(&mut blocks, args.unsigned_tx_policy())?;
+    progress.set_stage("check timestamps");
+    args.check_timestamps(&blocks)?;
+    Ok(blocks)
+}
+
+/// Returns true if the transaction carries no signature, like the L1 to L2 messages enqueued on
+/// the legacy OP chains. Deposits carry their sender and don't count as unsigned.
+pub fn is_unsigned(transaction: &TransactionSigned) -> bool {
+    transaction.signature.r == U256::ZERO &&
+        transaction.signature.s == U256::ZERO &&
+        !deposit::is_deposit(transaction)
+}
+
+/// Applies the policy for transactions without a signature to the given blocks. Returns the
+/// number of such transactions.
+pub fn apply_unsigned_tx_policy(
+    blocks: &mut [SealedBlock],
+    policy: UnsignedTxPolicy,
+) -> Result<usize> {
+    let mut count = 0;
+    for block in blocks.iter_mut() {
+        let Some(index) = block.body.iter().position(is_unsigned) else { continue };
+        match policy {
+            UnsignedTxPolicy::Fail => eyre::bail!(
+                "Transaction {index} of block {} has no signature. Pass --unsigned-txs to import \
+                 it as a system transaction or to skip it.",
+                block.number
+            ),
+            UnsignedTxPolicy::Skip => {
+                let before = block.body.len();
+                block.body.retain(|transaction| !is_unsigned(transaction));
+                count += before - block.body.len();
+            }
+            UnsignedTxPolicy::System => {
+                count += block.body.iter().filter(|tx| is_unsigned(tx)).count()
+            }
+        }
+    }
+    if count > 0 {
+        tracing::info!(target: "reth::cli", count, ?policy, "Applied the policy for transactions without a signature");
+    }
+    Ok(count)
+}
+
+/// Inserts the canonical block into the database, continuing the transaction numbers and state
+/// transitions of its parent. Only the genesis block starts them at zero, so it has to be
+/// inserted first.
+///
+/// The senders of signed transactions are recovered from their signature. Transactions carrying
+/// none are written with their sender instead of going through recovery: deposits with their own
+/// sender, inserted as their [TxDeposit::stand_in] and kept in `deposits`, which is required for
+/// blocks holding deposits, and unsigned transactions inserted as system transactions with
+/// [chain::unsigned_tx_sender].
+pub(crate) fn insert_block<'a, TX: DbTxMut<'a> + DbTx<'a>>(
+    tx: &TX,
+    block: &SealedBlock,
+    deposits: Option<&DepositStore>,
+) -> Result<()> {
+    tx.put::<tables::CanonicalHeaders>(block.number, block.hash())?;
+    tx.put::<tables::Headers>(block.number, block.header.clone().unseal())?;
+    tx.put::<tables::HeaderNumbers>(block.hash(), block.number)?;
+
+    let (mut tx_id, mut transition, parent_td) = match block.number.checked_sub(1) {
+        None => (0, 0, U256::ZERO),
+        Some(parent) => {
+            let body = tx
+                .get::<tables::BlockBodies>(parent)?
+                .ok_or_else(|| eyre::eyre!("Body of parent block {parent} not found"))?;
+            let transition = tx
+                .get::<tables::BlockTransitionIndex>(parent)?
+                .ok_or_else(|| eyre::eyre!("Transition of parent block {parent} not found"))?;
+            let td = tx.get::<tables::HeaderTD>(parent)?.map(|td| td.0).unwrap_or_default();
+            (body.start_tx_id + body.tx_count, transition, td)
+        }
+    };
+    tx.put::<tables::HeaderTD>(block.number, (parent_td + block.difficulty).into())?;
+    if !block.ommers.is_empty() {
+        let ommers = block.ommers.iter().map(|ommer| ommer.clone().unseal()).collect();
+        tx.put::<tables::BlockOmmers>(block.number, StoredBlockOmmers { ommers })?;
+    }
+    tx.put::<tables::BlockBodies>(
+        block.number,
+        StoredBlockBody { start_tx_id: tx_id, tx_count: block.body.len() as u64 },
+    )?;
+
+    let mut stored = Vec::new();
+    for transaction in &block.body {
+        let (sender, transaction) = match TxDeposit::from_carrier(transaction) {
+            Some(deposit) => {
+                let (sender, stand_in) = (deposit.from, deposit.stand_in());
+                stored.push((tx_id, deposit));
+                (sender, stand_in)
+            }
+            None if is_unsigned(transaction) => (chain::unsigned_tx_sender(), transaction.clone()),
+            None => {
+                let sender = transaction.recover_signer().ok_or_else(|| {
+                    eyre::eyre!(
+                        "Unable to recover the sender of transaction {:?} of block {}",
+                        transaction.hash(),
+                        block.number
+                    )
+                })?;
+                (sender, transaction.clone())
+            }
+        };
+        // Stand-ins keep the hash of their deposit, so deposits are looked up by their own hash
+        tx.put::<tables::TxHashNumber>(transaction.hash(), tx_id)?;
+        tx.put::<tables::TxSenders>(tx_id, sender)?;
+        tx.put::<tables::Transactions>(tx_id, transaction)?;
+        tx.put::<tables::TxTransitionIndex>(tx_id, transition)?;
+        tx_id += 1;
+        transition += 1;
+    }
+
+    // We have no block rewards pre-merge, only withdrawals take a transition of the block
+    if let Some(withdrawals) =
+        block.withdrawals.clone().filter(|withdrawals| !withdrawals.is_empty())
+    {
+        tx.put::<tables::BlockWithdrawals>(block.number, StoredBlockWithdrawals { withdrawals })?;
+        transition += 1;
+    }
+    tx.put::<tables::BlockTransitionIndex>(block.number, transition)?;
+
+    if stored.is_empty() {
+        return Ok(())
+    }
+    let Some(deposits) = deposits else {
+        eyre::bail!(
+            "Block {} holds deposit transactions, which need the deposit store of the database",
+            block.number
+        )
+    };
+    deposits.insert(stored)?;
+    Ok(())
+}
+
+/// Insert the given blocks except a leading genesis block, committing a transaction and syncing the
+/// database to disk after every `batch_size` blocks so an interrupted import keeps its progress.
+/// Batches failing transiently are retried according to `retry`.
+#[allow(clippy::too_many_arguments)]
+fn insert_blocks(
+    db: &Env<WriteMap>,
+    deposits: Option<&DepositStore>,
+    blocks: &[SealedBlock],
+    batch_size: usize,
+    retry: &RetryPolicy,
+    progress: &ImportProgress,
+    limiter: Option<&IoLimiter>,
+    allow_gaps: bool,
+) -> Result<()> {
+    // The following operation requires the genesis block to be present in the database
+    if db.view(|tx| tx.get::<tables::Headers>(0))??.is_none() {
+        eyre::bail!("Genesis block not found! Please insert it before using this command.");
+    }
+
+    // The genesis block leading the export is inserted by the genesis import
+    let blocks = match blocks.first() {
+        Some(first) if first.number == 0 => &blocks[1..],
+        _ => blocks,
+    };
+    insert_batches(db, deposits, blocks, batch_size, retry, progress, limiter, None, allow_gaps)
+}
+
+/// Insert the given blocks, committing a transaction and syncing the database to disk after every
+/// `batch_size` blocks. Their deposits are kept in `deposits`.
+///
+/// A batch is only committed if its blocks follow the blocks in the database and each other
+/// without gaps, unless `allow_gaps` is set. The parent link of the regenesis `anchor` is not
+/// checked.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn insert_batches(
+    db: &Env<WriteMap>,
+    deposits: Option<&DepositStore>,
+    blocks: &[SealedBlock],
+    batch_size: usize,
+    retry: &RetryPolicy,
+    progress: &ImportProgress,
+    limiter: Option<&IoLimiter>,
+    anchor: Option<u64>,
+    allow_gaps: bool,
+) -> Result<()> {
+    for batch in blocks.chunks(batch_size.max(1)) {
+        check_continuity(db, batch, anchor, allow_gaps)?;
+        retry.run("insert blocks", || {
+            let tx = db.tx_mut()?;
+            progress.tx_opened();
+            for sealed_block in batch {
+                // Every block continues the transaction numbers and transitions of its parent
+                insert_block(&tx, sealed_block, deposits)?;
+                record_block_writes(progress, sealed_block);
+                progress.set_block(sealed_block.number);
+                if let Some(limiter) = limiter {
+                    limiter.consume(block_size(sealed_block));
+                }
+                progress.advance(1)?;
+            }
+            tx.commit()?;
+            progress.tx_closed();
+            db.inner.sync(true)?;
+            Ok(())
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Checks that the batch continues the canonical chain of the database, failing with
+/// [BrokenContinuity] unless `allow_gaps` is set
+pub(crate) fn check_continuity(
+    db: &Env<WriteMap>,
+    batch: &[SealedBlock],
+    anchor: Option<u64>,
+    allow_gaps: bool,
+) -> Result<()> {
+    let Some(first) = batch.first() else { return Ok(()) };
+    let mut issues = Vec::new();
+    let parent = if first.number == 0 || anchor == Some(first.number) {
+        None
+    } else {
+        let number = first.number - 1;
+        let hash = db.view(|tx| tx.get::<tables::CanonicalHeaders>(number))??;
+        if hash.is_none() {
+            issues.push(ContinuityIssue::MissingParent { block: first.number });
+        }
+        hash.map(|hash| (number, hash))
+    };
+    issues.extend(validate::check_continuity(parent, batch));
+    validate::report_continuity_issues(issues, allow_gaps)
+}
+
+/// Records the approximate writes of inserting the given block in the progress
+fn record_block_writes(progress: &ImportProgress, block: &SealedBlock) {
+    let txs: usize = block.body.iter().map(Encodable::length).sum();
+    progress.record_writes(tables::Headers::NAME, 1, Header::length(&block.header) as u64);
+    // A body only holds the range of its transaction ids
+    progress.record_writes(tables::BlockBodies::NAME, 1, 16);
+    progress.record_writes(tables::Transactions::NAME, block.body.len() as u64, txs as u64);
+}
+
+/// The approximate number of bytes written to the database for the given block
+fn block_size(block: &SealedBlock) -> u64 {
+    let body: usize = block.body.iter().map(Encodable::length).sum();
+    let ommers: usize = block.ommers.iter().map(|ommer| Header::length(ommer)).sum();
+    (Header::length(&block.header) + body + ommers) as u64
+}
+
+/// Remove the headers, bodies, transactions, senders, hash lookups and receipts of all blocks in
+/// the given range. Returns the number of removed transactions.
+///
+/// The hash lookup of a deposit is keyed by the hash of the deposit in `deposits`, which is
+/// required for blocks holding deposits.
+pub fn unwind_blocks<'a, TX: DbTxMut<'a> + DbTx<'a>>(
+    tx: &TX,
+    deposits: Option<&DepositStore>,
+    range: RangeInclusive<u64>,
+) -> Result<u64> {
+    let mut removed = 0;
+    for number in range {
+        if let Some(body) = tx.get::<tables::BlockBodies>(number)? {
+            for tx_id in body.start_tx_id..body.start_tx_id + body.tx_count {
+                if let Some(transaction) = tx.get::<tables::Transactions>(tx_id)? {
+                    let hash = match deposits {
+                        Some(deposits) => deposits.restore(tx_id, transaction)?.hash(),
+                        None if deposit::is_stand_in(&transaction) => eyre::bail!(
+                            "Transaction {tx_id} is a deposit, unwinding it needs the deposit \
+                             store of the database"
+                        ),
+                        None => transaction.hash(),
+                    };
+                    tx.delete::<tables::TxHashNumber>(hash, None)?;
+                }
+                tx.delete::<tables::Transactions>(tx_id, None)?;
+                tx.delete::<tables::TxSenders>(tx_id, None)?;
+                tx.delete::<tables::Receipts>(tx_id, None)?;
+                tx.delete::<tables::TxTransitionIndex>(tx_id, None)?;
+                removed += 1;
+            }
+        }
+        tx.delete::<tables::BlockBodies>(number, None)?;
+        tx.delete::<tables::BlockOmmers>(number, None)?;
+        tx.delete::<tables::BlockWithdrawals>(number, None)?;
+        tx.delete::<tables::BlockTransitionIndex>(number, None)?;
+
+        if let Some(hash) = tx.get::<tables::CanonicalHeaders>(number)? {
+            tx.delete::<tables::HeaderNumbers>(hash, None)?;
+        }
+        tx.delete::<tables::CanonicalHeaders>(number, None)?;
+        tx.delete::<tables::HeaderTD>(number, None)?;
+        tx.delete::<tables::Headers>(number, None)?;
+    }
+    Ok(removed)
+}
+
+/// Returns the id of the first transaction following the given block, if the block has a body
+fn next_tx_id<'a, TX: DbTx<'a>>(tx: &TX, number: u64) -> Result<Option<u64>> {
+    Ok(tx.get::<tables::BlockBodies>(number)?.map(|body| body.start_tx_id + body.tx_count))
+}
+
+/// Returns the first block of the given range whose transactions have receipts in the database
+fn first_block_with_receipts<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    range: RangeInclusive<u64>,
+) -> Result<Option<u64>> {
+    let mut receipts = tx.cursor_read::<tables::Receipts>()?;
+    for number in range {
+        let Some(body) = tx.get::<tables::BlockBodies>(number)? else { continue };
+        let end = body.start_tx_id + body.tx_count;
+        if receipts.seek(body.start_tx_id)?.filter(|(tx_id, _)| *tx_id < end).is_some() {
+            return Ok(Some(number))
+        }
+    }
+    Ok(None)
+}
+
+/// Unwind the given range of blocks and insert the matching blocks from the export at `path`.
+///
+/// Blocks above the range are left untouched, so the reimported range must keep the same number
+/// of transactions for the following transaction ids to stay valid. Nothing is committed if the
+/// corrected range is incomplete or would shift the following transactions. The export carries no
+/// receipts, so ranges whose receipts were already imported are refused instead of dropping them.
+/// The deposits of the reimported blocks are kept in `deposits`.
+pub fn reimport(
+    db: &Env<WriteMap>,
+    deposits: &DepositStore,
+    path: &str,
+    range: RangeInclusive<u64>,
+) -> Result<()> {
+    let (from, to) = (*range.start(), *range.end());
+    if from == 0 {
+        eyre::bail!("The genesis block can not be reimported, use the genesis command instead");
+    }
+    if from > to {
+        eyre::bail!("Invalid block range {from}..={to}");
+    }
+
+    let mut blocks: Vec<SealedBlock> =
+        read_blocks(path)?.into_iter().filter(|block| range.contains(&block.number)).collect();
+    blocks.sort_by_key(|block| block.number);
+    blocks.dedup_by_key(|block| block.number);
+    if blocks.len() as u64 != to - from + 1 {
+        eyre::bail!(
+            "The export only contains {} of the {} blocks in {from}..={to}",
+            blocks.len(),
+            to - from + 1
+        );
+    }
+
+    let tx = db.tx_mut()?;
+    if tx.get::<tables::CanonicalHeaders>(from - 1)?.is_none() {
+        eyre::bail!("Parent block {} not found in the database", from - 1);
+    }
+    if let Some(number) = first_block_with_receipts(&tx, range.clone())? {
+        eyre::bail!(
+            "Block {number} has receipts, which the reimport would drop. Reimport the blocks \
+             before importing the receipts, or repair the tables with `db copy-table --range`."
+        );
+    }
+    let next_start_tx_id = tx.get::<tables::BlockBodies>(to + 1)?.map(|body| body.start_tx_id);
+
+    let removed = unwind_blocks(&tx, Some(deposits), range)?;
+    tracing::info!(target: "reth::cli", from, to, transactions = removed, "Unwound blocks");
+
+    for block in &blocks {
+        insert_block(&tx, block, Some(deposits))?;
+    }
+
+    if let Some(expected) = next_start_tx_id {
+        let actual = next_tx_id(&tx, to)?.unwrap_or_default();
+        if actual != expected {
+            eyre::bail!(
+                "Reimported blocks end at transaction {actual} but block {} starts at {expected}. \
+                 Reimport up to the database tip instead.",
+                to + 1
+            );
         }
     }
 
+    tx.commit()?;
+    tracing::info!(target: "reth::cli", from, to, "Blocks reimported! 🎉");
     Ok(())
 }
 
 impl Command {
+    /// Execute the command
+    pub async fn execute(self, ctx: CliContext) -> Result<()> {
+        match self.command {
+            Some(Subcommands::Import(command)) => command.execute(ctx).await,
+            Some(Subcommands::Reimport(command)) => command.execute(ctx).await,
+            Some(Subcommands::Export(command)) => command.execute(ctx).await,
+            Some(Subcommands::BackfillBodies(command)) => command.execute(ctx).await,
+            Some(Subcommands::Query(command)) => command.execute(ctx).await,
+            Some(Subcommands::Sync(command)) => command.execute(ctx).await,
+            None => self.import.execute(ctx).await,
+        }
+    }
+}
+
+impl ExportCommand {
     /// Execute the command
     pub async fn execute(self, _ctx: CliContext) -> Result<()> {
-        let db_path = PathBuf::from(self.database);
-        let mut db = db::open_rw_env(db_path.as_path())?;
-        apply(&mut db, Some(&self.path)).await
+        let db = self.db.open_ro()?;
+        let tip = analytics::canonical_tip(&db)?
+            .ok_or_else(|| eyre::eyre!("No canonical blocks found in the database"))?;
+        let to = self.to.unwrap_or(tip);
+        if self.from > to || to > tip {
+            eyre::bail!("Invalid block range {}..={to}, the database tip is {tip}", self.from);
+        }
+        let progress =
+            if self.quiet { ImportProgress::default() } else { ImportProgress::with_bar() };
+        let deposits = DepositStore::open(&self.db.static_path()?)?;
+        let range = self.from..=to;
+        let count =
+            export::export_blocks_as(&db, &deposits, &self.path, range, self.format, &progress)?;
+        progress.finish();
+        tracing::info!(target: "reth::cli", path = %self.path.display(), format = ?self.format, blocks = count, "Blocks exported");
+        Ok(())
+    }
+}
+
+impl ReimportCommand {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        journal::record(&self.db.path(), "blocks reimport", &[Path::new(&self.path)], async {
+            let db = self.db.open_rw()?;
+            let deposits = DepositStore::open(&self.db.static_path()?)?;
+            reimport(&db, &deposits, &self.path, self.from..=self.to)
+        })
+        .await
+    }
+}
+
+impl SyncCommand {
+    /// Execute the command
+    pub async fn execute(mut self, _ctx: CliContext) -> Result<()> {
+        self.import = self.import.with_database(&self.db)?;
+        journal::record(&self.db.path(), "blocks sync", &[], async {
+            let mut db = self.db.open_rw()?;
+            apply_from(&mut db, &mut self.rpc.source(), &self.import).await
+        })
+        .await
+    }
+}
+
+impl BackfillBodiesCommand {
+    /// Execute the command
+    pub async fn execute(mut self, _ctx: CliContext) -> Result<()> {
+        self.import = self.import.with_database(&self.db)?;
+        let db_path = self.db.path();
+        let path = self.import.input_path(self.path.as_deref(), ImportStage::Blocks)?;
+        journal::record(&db_path, "blocks backfill-bodies", &[Path::new(&path)], async {
+            self.import.preflight(&db_path, ImportStage::Blocks, Path::new(&path))?;
+            let mut db = self.db.open_rw()?;
+            backfill_bodies(&mut db, &db_path, Some(&path), self.format, &self.import).await?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+impl ImportCommand {
+    /// Execute the command
+    pub async fn execute(mut self, _ctx: CliContext) -> Result<()> {
+        self.import = self.import.with_database(&self.db)?;
+        let db_path = self.db.path();
+        let path = self.import.input_path(self.path.as_deref(), ImportStage::Blocks)?;
+        journal::record(&db_path, "blocks import", &[Path::new(&path)], async {
+            self.import.preflight(&db_path, ImportStage::Blocks, Path::new(&path))?;
+            let mut db = self.db.open_rw()?;
+            if self.regenesis {
+                let mut source = FileSource::new(path.clone()).with_format(self.format);
+                let boundaries = RegenesisBoundaries::new(&db_path);
+                apply_segment(&mut db, &mut source, &boundaries, &self.import).await?;
+                return Ok(())
+            }
+            apply_as(&mut db, Some(&path), self.format, &self.import).await?;
+            if self.execute {
+                let summary =
+                    replay::replay_imported(&db, &db_path, None, DEFAULT_REPLAY_BATCH, true)?;
+                tracing::info!(target: "reth::cli", blocks = summary.blocks, account_changes = summary.account_changes, storage_changes = summary.storage_changes, "Executed imported blocks");
+            }
+            Ok(())
+        })
+        .await
     }
 }
@@ -0,0 +1,171 @@
+use clap::Parser;
+use eyre::Result;
+use reth::runner::CliContext;
+use reth_db::{database::Database, transaction::DbTx};
+use reth_primitives::SealedBlock;
+use reth_rlp::Encodable;
+use serde::Serialize;
+
+use crate::cli::{
+    args::DatabaseArgs,
+    blocks::{self, BlockFormat},
+    compression,
+    deposit::TxDeposit,
+    import::detect_block_format,
+    rpc::{self, types::RpcBlock},
+};
+
+/// Print a single block of a block export or of the database.
+///
+/// The block is printed as JSON in the layout of `eth_getBlockByNumber` with the full
+/// transactions, followed by its decoded ommers, or as its raw rlp encoding with `--raw-rlp`.
+/// Blocks of an export that fail to decode are located by counting from the block before them,
+/// so their raw encoding can still be inspected.
+#[derive(Debug, Parser)]
+pub struct QueryCommand {
+    /// The number of the block to print
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment)]
+    block: u64,
+
+    /// Read the block from this block export instead of the database
+    #[arg(long, value_name = "BLOCK_DUMP_PATH", conflicts_with = "database", verbatim_doc_comment)]
+    path: Option<String>,
+
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The layout of the block export. Detected from its contents by default.
+    #[arg(long, value_enum, value_name = "FORMAT", requires = "path", verbatim_doc_comment)]
+    format: Option<BlockFormat>,
+
+    /// Print the hex-encoded rlp of the block instead of decoding it. Blocks of the database are
+    /// encoded in the standard devp2p encoding.
+    #[arg(long, verbatim_doc_comment)]
+    raw_rlp: bool,
+}
+
+/// A block found with [find_block]
+#[derive(Debug)]
+pub struct ExportedBlock {
+    /// The rlp encoding of the block in the layout of the export
+    pub raw: Vec<u8>,
+    /// The decoded block, or why it failed to decode
+    pub block: Result<SealedBlock, String>,
+}
+
+/// A decoded block as printed by [QueryCommand]
+#[derive(Debug, Serialize)]
+pub struct DecodedBlock {
+    /// The header and the transactions of the block
+    #[serde(flatten)]
+    pub block: RpcBlock,
+    /// The headers of the ommers of the block, without transactions
+    pub ommers: Vec<RpcBlock>,
+}
+
+impl From<SealedBlock> for DecodedBlock {
+    fn from(block: SealedBlock) -> Self {
+        let headers = block.ommers.iter().map(|ommer| ommer.clone().unseal()).collect::<Vec<_>>();
+        let ommers = block
+            .ommers
+            .iter()
+            .zip(headers.clone())
+            .map(|(ommer, header)| rpc::rpc_block(header, ommer.hash(), &[], &[], &[], None, false))
+            .collect();
+        // The export holds no senders, so they are recovered, or taken from deposits
+        let senders = block
+            .body
+            .iter()
+            .map(|tx| {
+                TxDeposit::from_carrier(tx)
+                    .map(|deposit| deposit.from)
+                    .or_else(|| tx.recover_signer())
+            })
+            .collect::<Vec<_>>();
+        let hash = block.hash();
+        let block = rpc::rpc_block(
+            block.header.unseal(),
+            hash,
+            &block.body,
+            &senders,
+            &headers,
+            None,
+            true,
+        );
+        Self { block, ommers }
+    }
+}
+
+/// Finds the block with the given number in the contents of a block export in the given layout.
+///
+/// Blocks that fail to decode take the number following the block before them. Returns `None` if
+/// the export has no block with the number.
+pub fn find_block(
+    format: BlockFormat,
+    contents: &[u8],
+    number: u64,
+) -> Result<Option<ExportedBlock>> {
+    if format == BlockFormat::RlpStandard {
+        let text = std::str::from_utf8(contents)?.trim();
+        let contents = hex::decode(text.trim_start_matches("0x"))?;
+        return find_block(BlockFormat::Geth, &contents, number)
+    }
+    let mut previous: Option<u64> = None;
+    for raw in blocks::split_blocks(format, contents)? {
+        let block = blocks::decode_block(format, raw);
+        let current = match &block {
+            Ok(block) => Some(block.number),
+            Err(_) => previous.map(|previous| previous + 1),
+        };
+        if current == Some(number) {
+            return Ok(Some(ExportedBlock { raw: raw.to_vec(), block }))
+        }
+        if current.map_or(false, |current| current > number) {
+            break
+        }
+        previous = current;
+    }
+    Ok(None)
+}
+
+/// Loads the canonical block with the given number from the database, together with its standard
+/// devp2p encoding
+pub fn load_block<'a, TX: DbTx<'a>>(tx: &TX, number: u64) -> Result<Option<ExportedBlock>> {
+    let Some(block) = blocks::load_standard_block(tx, number)? else { return Ok(None) };
+    let mut raw = Vec::new();
+    block.encode(&mut raw);
+    Ok(Some(ExportedBlock { raw, block: Ok(blocks::seal_block(block)) }))
+}
+
+impl QueryCommand {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let found = match &self.path {
+            Some(path) => {
+                let contents = compression::read(path)?;
+                let format = match self.format {
+                    Some(format) => format,
+                    None => detect_block_format(&contents).ok_or_else(|| {
+                        eyre::eyre!("Unable to detect the layout of the block export")
+                    })?,
+                };
+                find_block(format, &contents, self.block)?
+            }
+            None => load_block(&self.db.open_ro()?.tx()?, self.block)?,
+        };
+        let Some(found) = found else { eyre::bail!("Block {} not found", self.block) };
+
+        if self.raw_rlp {
+            println!("0x{}", hex::encode(&found.raw));
+            return Ok(())
+        }
+        let block = found.block.map_err(|err| {
+            eyre::eyre!(
+                "Block {} can not be decoded: {err}, use --raw-rlp to inspect it",
+                self.block
+            )
+        })?;
+        println!("{}", serde_json::to_string_pretty(&DecodedBlock::from(block))?);
+        Ok(())
+    }
+}
@@ -0,0 +1,457 @@
+use std::{fmt, fs, path::Path, str::FromStr, sync::Arc};
+
+use clap::ValueEnum;
+use eyre::Result;
+use reth_primitives::{
+    proofs::EMPTY_ROOT, Address, Chain, ChainSpec, ChainSpecBuilder, ForkCondition, Genesis,
+    Hardfork, Header, SealedBlock, H256,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::cli::preflight::ImportStage;
+
+/// The chain id of OP Mainnet
+pub const OP_MAINNET_CHAIN_ID: u64 = 10;
+
+/// The first block of OP Mainnet produced after the bedrock upgrade
+pub const OP_MAINNET_BEDROCK_BLOCK: u64 = 105_235_063;
+
+/// The timestamp at which regolith activates on OP Mainnet. Regolith went live together with
+/// bedrock, so it is active from the first bedrock block onwards.
+pub const OP_MAINNET_REGOLITH_TIME: u64 = 0;
+
+/// The timestamp at which regolith activates on OP Goerli
+pub const OP_GOERLI_REGOLITH_TIME: u64 = 1_679_079_600;
+
+/// The timestamp at which canyon activates on OP Mainnet and Base Mainnet
+pub const OP_MAINNET_CANYON_TIME: u64 = 1_704_992_401;
+
+/// The timestamp at which canyon activates on OP Goerli
+pub const OP_GOERLI_CANYON_TIME: u64 = 1_699_981_200;
+
+/// The first block of OP Goerli produced after the bedrock upgrade
+pub const OP_GOERLI_BEDROCK_BLOCK: u64 = 4_061_224;
+
+/// The chain id of Base Mainnet
+pub const BASE_MAINNET_CHAIN_ID: u64 = 8453;
+
+/// The EIP-1559 elasticity multiplier of OP Mainnet
+pub const OP_MAINNET_EIP1559_ELASTICITY: u64 = 6;
+
+/// The EIP-1559 base fee max change denominator of OP Mainnet
+pub const OP_MAINNET_EIP1559_DENOMINATOR: u64 = 50;
+
+/// The EIP-1559 base fee max change denominator of OP Mainnet from canyon on
+pub const OP_MAINNET_EIP1559_DENOMINATOR_CANYON: u64 = 250;
+
+/// The file below a database path holding the chain spec written by the genesis import
+const CHAIN_SPEC_FILE: &str = "chainspec.json";
+
+/// An OP Stack chain specification.
+///
+/// Wraps the reth [ChainSpec] with the OP Stack specific activation points, which are not known to
+/// the upstream hardfork list.
+#[derive(Debug, Clone)]
+pub struct OpChainSpec {
+    /// The underlying reth chain spec
+    pub inner: Arc<ChainSpec>,
+    /// The block at which bedrock activates
+    pub bedrock_block: u64,
+    /// The timestamp at which regolith activates
+    pub regolith_time: u64,
+    /// The timestamp at which canyon activates, if it is scheduled. Shanghai activates with it.
+    pub canyon_time: Option<u64>,
+    /// The EIP-1559 elasticity multiplier
+    pub eip1559_elasticity: u64,
+    /// The EIP-1559 base fee max change denominator
+    pub eip1559_denominator: u64,
+    /// The EIP-1559 base fee max change denominator from canyon on
+    pub eip1559_denominator_canyon: u64,
+}
+
+/// The on-disk layout of an [OpChainSpec]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredChainSpec {
+    spec: ChainSpec,
+    bedrock_block: u64,
+    regolith_time: u64,
+    #[serde(default)]
+    canyon_time: Option<u64>,
+    eip1559_elasticity: u64,
+    eip1559_denominator: u64,
+    #[serde(default = "default_denominator_canyon")]
+    eip1559_denominator_canyon: u64,
+}
+
+/// Chain specs written before canyon was known use the denominator of OP Mainnet
+fn default_denominator_canyon() -> u64 {
+    OP_MAINNET_EIP1559_DENOMINATOR_CANYON
+}
+
+impl OpChainSpec {
+    /// Creates the OP Mainnet chain spec with the given genesis
+    pub fn op_mainnet(genesis: Genesis) -> Self {
+        Self::from_preset(ChainPreset::OpMainnet, genesis)
+    }
+
+    /// Creates the chain spec of a known chain with the given genesis
+    pub fn from_preset(preset: ChainPreset, genesis: Genesis) -> Self {
+        let inner = ChainSpecBuilder::default()
+            .chain(Chain::Id(preset.chain_id()))
+            .genesis(genesis)
+            .london_activated()
+            .with_fork(Hardfork::Shanghai, ForkCondition::Timestamp(preset.canyon_time()))
+            .build();
+        Self {
+            inner: Arc::new(inner),
+            bedrock_block: preset.bedrock_block(),
+            regolith_time: preset.regolith_time(),
+            canyon_time: Some(preset.canyon_time()),
+            eip1559_elasticity: OP_MAINNET_EIP1559_ELASTICITY,
+            eip1559_denominator: OP_MAINNET_EIP1559_DENOMINATOR,
+            eip1559_denominator_canyon: OP_MAINNET_EIP1559_DENOMINATOR_CANYON,
+        }
+    }
+
+    /// Writes the chain spec next to the database at `db_path`, so that later commands use the
+    /// fork rules of the imported genesis
+    pub fn write(&self, db_path: &Path) -> Result<()> {
+        let stored = StoredChainSpec {
+            spec: (*self.inner).clone(),
+            bedrock_block: self.bedrock_block,
+            regolith_time: self.regolith_time,
+            canyon_time: self.canyon_time,
+            eip1559_elasticity: self.eip1559_elasticity,
+            eip1559_denominator: self.eip1559_denominator,
+            eip1559_denominator_canyon: self.eip1559_denominator_canyon,
+        };
+        fs::create_dir_all(db_path)?;
+        fs::write(db_path.join(CHAIN_SPEC_FILE), serde_json::to_vec_pretty(&stored)?)?;
+        Ok(())
+    }
+
+    /// Reads the chain spec written next to the database at `db_path`, if any
+    pub fn read(db_path: &Path) -> Result<Option<Self>> {
+        let data = match fs::read(db_path.join(CHAIN_SPEC_FILE)) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let stored: StoredChainSpec = serde_json::from_slice(&data)?;
+        Ok(Some(Self {
+            inner: Arc::new(stored.spec),
+            bedrock_block: stored.bedrock_block,
+            regolith_time: stored.regolith_time,
+            canyon_time: stored.canyon_time,
+            eip1559_elasticity: stored.eip1559_elasticity,
+            eip1559_denominator: stored.eip1559_denominator,
+            eip1559_denominator_canyon: stored.eip1559_denominator_canyon,
+        }))
+    }
+
+    /// Overrides the bedrock activation block
+    pub fn with_bedrock_block(mut self, block: u64) -> Self {
+        self.bedrock_block = block;
+        self
+    }
+
+    /// Overrides the regolith activation timestamp
+    pub fn with_regolith_time(mut self, timestamp: u64) -> Self {
+        self.regolith_time = timestamp;
+        self
+    }
+
+    /// Overrides the canyon activation timestamp, activating Shanghai with it
+    pub fn with_canyon_time(mut self, timestamp: u64) -> Self {
+        let inner = ChainSpecBuilder::from(&*self.inner)
+            .with_fork(Hardfork::Shanghai, ForkCondition::Timestamp(timestamp))
+            .build();
+        self.inner = Arc::new(inner);
+        self.canyon_time = Some(timestamp);
+        self
+    }
+
+    /// The chain id
+    pub fn chain_id(&self) -> u64 {
+        self.inner.chain().id()
+    }
+
+    /// Returns true if bedrock is active at the given block
+    pub fn is_bedrock_active_at_block(&self, number: u64) -> bool {
+        number >= self.bedrock_block
+    }
+
+    /// Returns true if regolith is active at the given block and timestamp
+    pub fn is_regolith_active(&self, number: u64, timestamp: u64) -> bool {
+        self.is_bedrock_active_at_block(number) && timestamp >= self.regolith_time
+    }
+
+    /// Returns true if canyon is active at the given block and timestamp
+    pub fn is_canyon_active(&self, number: u64, timestamp: u64) -> bool {
+        self.is_bedrock_active_at_block(number) &&
+            self.canyon_time.map_or(false, |canyon| timestamp >= canyon)
+    }
+
+    /// The EIP-1559 base fee max change denominator of the block with the given number and
+    /// timestamp
+    pub fn eip1559_denominator_at(&self, number: u64, timestamp: u64) -> u64 {
+        if self.is_canyon_active(number, timestamp) {
+            self.eip1559_denominator_canyon
+        } else {
+            self.eip1559_denominator
+        }
+    }
+
+    /// Checks the fields of a block that depend on the active OP hardforks: bedrock blocks carry a
+    /// base fee, and blocks from canyon on carry an empty list of withdrawals, which the OP Stack
+    /// has none of. Blocks before canyon carry no withdrawals at all.
+    pub fn check_hardfork_fields(&self, block: &SealedBlock) -> Result<(), String> {
+        if self.is_bedrock_active_at_block(block.number) && block.base_fee_per_gas.is_none() {
+            return Err(format!("Bedrock block {} has no base fee", block.number))
+        }
+        if self.is_canyon_active(block.number, block.timestamp) {
+            if block.withdrawals_root != Some(EMPTY_ROOT) ||
+                block.withdrawals.as_ref().map_or(true, |withdrawals| !withdrawals.is_empty())
+            {
+                return Err(format!(
+                    "Canyon block {} must carry an empty list of withdrawals",
+                    block.number
+                ))
+            }
+        } else if block.withdrawals_root.is_some() || block.withdrawals.is_some() {
+            return Err(format!("Block {} carries withdrawals before canyon", block.number))
+        }
+        Ok(())
+    }
+}
+
+/// Builds the [Genesis] matching the given genesis header. The allocation is left empty because
+/// the state is imported separately.
+pub fn genesis_from_header(header: &Header) -> Genesis {
+    Genesis {
+        nonce: header.nonce,
+        timestamp: header.timestamp,
+        extra_data: header.extra_data.clone(),
+        gas_limit: header.gas_limit,
+        difficulty: header.difficulty,
+        mix_hash: header.mix_hash,
+        coinbase: header.beneficiary,
+        alloc: Default::default(),
+    }
+}
+
+/// The chain id of OP Goerli
+pub const OP_GOERLI_CHAIN_ID: u64 = 420;
+
+/// The fee vault predeploy collecting the sequencer fees
+pub const SEQUENCER_FEE_VAULT: &str = "0x4200000000000000000000000000000000000011";
+
+/// The fee vault predeploy collecting the base fees, which OP Stack chains don't burn
+pub const BASE_FEE_VAULT: &str = "0x4200000000000000000000000000000000000019";
+
+/// The fee vault predeploy collecting the L1 data fees of transactions
+pub const L1_FEE_VAULT: &str = "0x420000000000000000000000000000000000001A";
+
+/// The messenger predeploy relaying L1 to L2 messages
+pub const L2_CROSS_DOMAIN_MESSENGER: &str = "0x4200000000000000000000000000000000000007";
+
+/// The predeploy holding the L1 block attributes since bedrock
+pub const L1_BLOCK: &str = "0x4200000000000000000000000000000000000015";
+
+/// The account sending the L1 attributes deposit at the start of every bedrock block
+pub const L1_ATTRIBUTES_DEPOSITOR: &str = "0xDeaDDEaDDeAdDeAdDEAdDEaddeAddEAdDEAd0001";
+
+/// Returns the sender recorded for transactions inserted as system transactions
+pub fn unsigned_tx_sender() -> Address {
+    Address::from_str(UNSIGNED_TX_SENDER).expect("valid address")
+}
+
+/// Returns the address of the predeploy holding the L1 block attributes
+pub fn l1_block_address() -> Address {
+    Address::from_str(L1_BLOCK).expect("valid address")
+}
+
+/// Returns the address of the fee vault collecting the base fees
+pub fn base_fee_vault_address() -> Address {
+    Address::from_str(BASE_FEE_VAULT).expect("valid address")
+}
+
+/// Returns the address of the fee vault collecting the L1 data fees
+pub fn l1_fee_vault_address() -> Address {
+    Address::from_str(L1_FEE_VAULT).expect("valid address")
+}
+
+/// The OP system addresses of a chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SystemAddresses {
+    /// The fee vault collecting the sequencer fees
+    pub sequencer_fee_vault: Address,
+    /// The messenger relaying L1 to L2 messages
+    pub l2_cross_domain_messenger: Address,
+    /// The predeploy holding the L1 block attributes
+    pub l1_block: Address,
+    /// The sender of the L1 attributes deposits
+    pub l1_attributes_depositor: Address,
+}
+
+/// The sender recorded for legacy transactions inserted as system transactions. l2geth keeps the
+/// L1 sender of enqueued messages outside of the transaction, so the exports don't carry it.
+pub const UNSIGNED_TX_SENDER: &str = "0x0000000000000000000000000000000000000000";
+
+/// How the block import treats legacy transactions without a signature, like the L1 to L2
+/// messages enqueued on the legacy OP chains starting with block 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum UnsignedTxPolicy {
+    /// Drop the transactions from their blocks
+    #[value(name = "skip")]
+    Skip,
+    /// Insert the transactions with [UNSIGNED_TX_SENDER] as their sender, which is written as it
+    /// is instead of being recovered from the missing signature
+    #[value(name = "system")]
+    System,
+    /// Refuse the import
+    #[value(name = "fail")]
+    Fail,
+}
+
+/// A known OP Stack chain, selecting its chain spec, genesis hash and default inputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ChainPreset {
+    /// OP Mainnet
+    #[value(name = "optimism-mainnet", alias = "op-mainnet")]
+    OpMainnet,
+    /// OP Goerli
+    #[value(name = "optimism-goerli", alias = "op-goerli")]
+    OpGoerli,
+    /// Base Mainnet
+    #[value(name = "base-mainnet")]
+    BaseMainnet,
+}
+
+impl ChainPreset {
+    /// The chain id
+    pub fn chain_id(&self) -> u64 {
+        match self {
+            ChainPreset::OpMainnet => OP_MAINNET_CHAIN_ID,
+            ChainPreset::OpGoerli => OP_GOERLI_CHAIN_ID,
+            ChainPreset::BaseMainnet => BASE_MAINNET_CHAIN_ID,
+        }
+    }
+
+    /// The hash of the genesis block. For chains migrated to bedrock this is the legacy genesis.
+    pub fn genesis_hash(&self) -> H256 {
+        let hash = match self {
+            ChainPreset::OpMainnet => {
+                "0x7ca38a1916c42007829c55e69d3e9a73265554b586a499015373241b8a3fa48b"
+            }
+            ChainPreset::OpGoerli => {
+                "0xad7e4e683df9b4b187b52e921d9d88e380c879b3956f6fb4d183baec76012bd0"
+            }
+            ChainPreset::BaseMainnet => {
+                "0xf712aa9241cc24369b143cf6dce85f0902a9731e70d66818a3a5845b296c73dd"
+            }
+        };
+        H256::from_str(hash).expect("valid hash")
+    }
+
+    /// The block at which bedrock activates. Chains launched on bedrock start with it.
+    pub fn bedrock_block(&self) -> u64 {
+        match self {
+            ChainPreset::OpMainnet => OP_MAINNET_BEDROCK_BLOCK,
+            ChainPreset::OpGoerli => OP_GOERLI_BEDROCK_BLOCK,
+            ChainPreset::BaseMainnet => 0,
+        }
+    }
+
+    /// The hash of the bedrock transition block, the first block op-node derives. Chains launched
+    /// on bedrock start with it.
+    pub fn bedrock_block_hash(&self) -> H256 {
+        let hash = match self {
+            ChainPreset::OpMainnet => {
+                "0xdbf6a80fef073de06add9b0d14026d6e5a86c85f6d102c36d3d8e9cf89c2afd3"
+            }
+            ChainPreset::OpGoerli => {
+                "0x0f783549ea4313b784eadd9b8e8a69913b368b7366363ea814d7707ac4a3e8a2"
+            }
+            ChainPreset::BaseMainnet => return self.genesis_hash(),
+        };
+        H256::from_str(hash).expect("valid hash")
+    }
+
+    /// The timestamp at which regolith activates
+    pub fn regolith_time(&self) -> u64 {
+        match self {
+            ChainPreset::OpMainnet | ChainPreset::BaseMainnet => OP_MAINNET_REGOLITH_TIME,
+            ChainPreset::OpGoerli => OP_GOERLI_REGOLITH_TIME,
+        }
+    }
+
+    /// The timestamp at which canyon activates, together with Shanghai
+    pub fn canyon_time(&self) -> u64 {
+        match self {
+            ChainPreset::OpMainnet | ChainPreset::BaseMainnet => OP_MAINNET_CANYON_TIME,
+            ChainPreset::OpGoerli => OP_GOERLI_CANYON_TIME,
+        }
+    }
+
+    /// The preset of the chain with the given chain id, if it is a known chain
+    pub fn from_chain_id(chain_id: u64) -> Option<Self> {
+        Self::value_variants().iter().copied().find(|preset| preset.chain_id() == chain_id)
+    }
+
+    /// How the legacy transactions without a signature of the chain are imported. The legacy OP
+    /// chains enqueued unsigned L1 to L2 messages, chains launched on bedrock have none.
+    pub fn unsigned_tx_policy(&self) -> UnsignedTxPolicy {
+        match self {
+            ChainPreset::OpMainnet | ChainPreset::OpGoerli => UnsignedTxPolicy::System,
+            ChainPreset::BaseMainnet => UnsignedTxPolicy::Fail,
+        }
+    }
+
+    /// The name of the chain on the command line and of its directory below the data directory
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChainPreset::OpMainnet => "optimism-mainnet",
+            ChainPreset::OpGoerli => "optimism-goerli",
+            ChainPreset::BaseMainnet => "base-mainnet",
+        }
+    }
+
+    /// The file name of the input an import stage reads from the chain directory if no path is
+    /// given: the published legacy exports of the chain, if it has any
+    pub fn default_input(&self, stage: ImportStage) -> Option<&'static str> {
+        match (self, stage) {
+            (ChainPreset::OpGoerli, ImportStage::Genesis) => Some("genesis.json"),
+            (ChainPreset::OpGoerli, ImportStage::Blocks) => Some("export_0_4061224"),
+            (ChainPreset::OpGoerli, ImportStage::Receipts) => Some("export_receipt_0_4061223"),
+            (ChainPreset::OpGoerli, ImportStage::State) => {
+                Some("alloc_everything_4061224_final.json")
+            }
+            _ => None,
+        }
+    }
+
+    /// The system addresses of the chain. The presets share the addresses of the OP Stack
+    /// predeploys.
+    pub fn system_addresses(&self) -> SystemAddresses {
+        let address = |address| Address::from_str(address).expect("valid address");
+        SystemAddresses {
+            sequencer_fee_vault: address(SEQUENCER_FEE_VAULT),
+            l2_cross_domain_messenger: address(L2_CROSS_DOMAIN_MESSENGER),
+            l1_block: address(L1_BLOCK),
+            l1_attributes_depositor: address(L1_ATTRIBUTES_DEPOSITOR),
+        }
+    }
+}
+
+impl fmt::Display for ChainPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ChainPreset::OpMainnet => "OP Mainnet",
+            ChainPreset::OpGoerli => "OP Goerli",
+            ChainPreset::BaseMainnet => "Base Mainnet",
+        };
+        f.write_str(name)
+    }
+}
@@ -0,0 +1,103 @@
+use std::{fs, ops::RangeInclusive, path::Path};
+
+use eyre::Result;
+use reth_db::{tables, transaction::DbTx};
+use reth_primitives::H256;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{db::head::BlockPointer, keccak::keccak256, verify::Mismatch};
+
+/// The default distance between the blocks of a checkpoint file
+pub const DEFAULT_CHECKPOINT_INTERVAL: u64 = 10_000;
+
+/// The canonical hashes of every `interval`th block and of the last block of an import, which
+/// other imports of the same chain are checked against without querying a reference node.
+///
+/// The digest commits to the interval and all pointers, so a file altered after export is
+/// refused.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointFile {
+    /// The distance between the checkpoints
+    pub interval: u64,
+    /// The blocks whose number is a multiple of the interval, in ascending order
+    pub checkpoints: Vec<BlockPointer>,
+    /// The last block of the exported range
+    pub tip: BlockPointer,
+    /// The keccak hash of the interval and the pointers
+    pub digest: H256,
+}
+
+impl CheckpointFile {
+    /// Collects the checkpoints of the canonical blocks in the given range
+    pub fn collect<'a, TX: DbTx<'a>>(
+        tx: &TX,
+        range: RangeInclusive<u64>,
+        interval: u64,
+    ) -> Result<Self> {
+        let interval = interval.max(1);
+        let (from, to) = range.into_inner();
+        let pointer = |number: u64| -> Result<BlockPointer> {
+            let hash = tx
+                .get::<tables::CanonicalHeaders>(number)?
+                .ok_or_else(|| eyre::eyre!("Block {number} is not a canonical block"))?;
+            Ok(BlockPointer { number, hash })
+        };
+        let first = (from + interval - 1) / interval * interval;
+        let checkpoints =
+            (first..=to).step_by(interval as usize).map(pointer).collect::<Result<Vec<_>>>()?;
+        let tip = pointer(to)?;
+        let digest = digest(interval, &checkpoints, &tip);
+        Ok(Self { interval, checkpoints, tip, digest })
+    }
+
+    /// Reads the checkpoint file at `path`, failing if its digest doesn't match its contents
+    pub fn read(path: &Path) -> Result<Self> {
+        let file: Self = serde_json::from_slice(&fs::read(path)?)?;
+        let expected = digest(file.interval, &file.checkpoints, &file.tip);
+        if file.digest != expected {
+            eyre::bail!(
+                "The digest of {} is {:?}, but its contents hash to {expected:?}",
+                path.display(),
+                file.digest
+            );
+        }
+        Ok(file)
+    }
+
+    /// Writes the checkpoint file to `path`
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Compares the checkpoints and the tip to the canonical blocks of the database. Returns the
+    /// differing blocks, ordered by block number.
+    pub fn verify<'a, TX: DbTx<'a>>(&self, tx: &TX) -> Result<Vec<(u64, Mismatch)>> {
+        let mut mismatches = Vec::new();
+        let last = self.checkpoints.last().filter(|last| last.number == self.tip.number);
+        let tip = Some(&self.tip).filter(|_| last.is_none());
+        for pointer in self.checkpoints.iter().chain(tip) {
+            let mismatch = match tx.get::<tables::CanonicalHeaders>(pointer.number)? {
+                None => Mismatch::Missing,
+                Some(found) if found != pointer.hash => {
+                    Mismatch::Hash { expected: pointer.hash, found }
+                }
+                Some(_) => continue,
+            };
+            mismatches.push((pointer.number, mismatch));
+        }
+        Ok(mismatches)
+    }
+}
+
+/// Hashes the interval and the numbers and hashes of the checkpoints and the tip
+fn digest(interval: u64, checkpoints: &[BlockPointer], tip: &BlockPointer) -> H256 {
+    let mut data = Vec::with_capacity(8 + (checkpoints.len() + 1) * 40);
+    data.extend_from_slice(&interval.to_be_bytes());
+    for pointer in checkpoints.iter().chain([tip]) {
+        data.extend_from_slice(&pointer.number.to_be_bytes());
+        data.extend_from_slice(pointer.hash.as_bytes());
+    }
+    keccak256(data)
+}
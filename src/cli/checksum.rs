@@ -0,0 +1,55 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use eyre::Result;
+use sha2::{Digest, Sha256};
+
+use crate::cli::dirs;
+
+/// Returns the hex-encoded sha256 digest of the given bytes
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Reads a checksum manifest in the format written by `sha256sum`, mapping file names to their
+/// expected digests
+pub fn read_manifest(path: &Path) -> Result<HashMap<String, String>> {
+    let mut manifest = HashMap::new();
+    for (number, line) in fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+        let Some((digest, name)) = line.split_once(char::is_whitespace) else {
+            eyre::bail!("Invalid line {} in checksum manifest {}", number + 1, path.display());
+        };
+        // `sha256sum --binary` marks file names with a leading `*`
+        let name = name.trim_start().trim_start_matches('*');
+        manifest.insert(name.to_string(), digest.to_lowercase());
+    }
+    Ok(manifest)
+}
+
+/// Looks up the expected digest of the given input in a manifest, either by the path as given, by
+/// the path written with other separators or case, or by its file name
+pub fn manifest_entry<'a>(manifest: &'a HashMap<String, String>, path: &Path) -> Option<&'a str> {
+    let by_path = manifest.get(path.to_string_lossy().as_ref());
+    let by_equivalent_path =
+        || manifest.iter().find(|(name, _)| dirs::same_path(Path::new(name), path)).map(|(_, d)| d);
+    let by_name = || manifest.get(path.file_name()?.to_string_lossy().as_ref());
+    by_path.or_else(by_equivalent_path).or_else(by_name).map(String::as_str)
+}
+
+/// Verifies that the contents of the input at `path` match the expected sha256 digest
+pub fn verify(path: &Path, data: &[u8], expected: &str) -> Result<()> {
+    let expected = expected.trim().trim_start_matches("0x").to_lowercase();
+    let actual = sha256_hex(data);
+    if actual != expected {
+        eyre::bail!(
+            "Checksum mismatch for {}: expected {expected}, got {actual}. The file may be truncated \
+             or corrupted.",
+            path.display()
+        );
+    }
+    tracing::info!(target: "reth::cli", path = %path.display(), "Checksum verified");
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use std::io;
+
+use clap::Parser;
+use clap_complete::Shell;
+use eyre::Result;
+
+/// Print the completions of the op-reth commands for a shell.
+///
+/// Besides the commands and flags, the values of flags taking one of a fixed set, like the
+/// `--chain` presets and the `--format` layouts, are completed. For example, in bash:
+///
+///     op-reth completions bash > ~/.local/share/bash-completion/completions/op-reth
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The shell to print the completions for
+    #[arg(value_enum, verbatim_doc_comment)]
+    shell: Shell,
+}
+
+impl Command {
+    /// Execute the command, printing the completions of the given command tree to stdout
+    pub fn execute(self, mut cli: clap::Command) -> Result<()> {
+        let name = cli.get_name().to_string();
+        clap_complete::generate(self.shell, &mut cli, name, &mut io::stdout());
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+use eyre::Result;
+use flate2::read::MultiGzDecoder;
+
+/// The bytes every gzip file starts with
+pub const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The bytes every zstd frame starts with
+pub const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// The compression of an input file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// gzip, as written by `gzip` or `pigz`
+    Gzip,
+    /// Zstandard, as written by `zstd`
+    Zstd,
+}
+
+impl Compression {
+    /// Detects the compression from the leading bytes of a file
+    pub fn detect(head: &[u8]) -> Option<Self> {
+        if head.starts_with(&GZIP_MAGIC) {
+            Some(Self::Gzip)
+        } else if head.starts_with(&ZSTD_MAGIC) {
+            Some(Self::Zstd)
+        } else {
+            None
+        }
+    }
+
+    /// Infers the compression from the extension of a file, like `export_0_1000.gz`
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "gz" | "gzip" => Some(Self::Gzip),
+            "zst" | "zstd" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// Decompresses the contents of the file at `path` if they are compressed, detecting the
+/// compression from their leading bytes. Uncompressed contents are returned as they are, unless
+/// the extension of the file claims a compression.
+pub fn decompress(path: &Path, data: Vec<u8>) -> Result<Vec<u8>> {
+    Ok(decompress_slice(path, &data)?.unwrap_or(data))
+}
+
+/// Like [decompress], but borrows the contents and returns `None` if they are not compressed, so
+/// that uncompressed contents are not copied
+pub fn decompress_slice(path: &Path, data: &[u8]) -> Result<Option<Vec<u8>>> {
+    let Some(compression) = Compression::detect(data) else {
+        if let Some(expected) = Compression::from_extension(path) {
+            eyre::bail!(
+                "{} is named like a {expected:?} file, but is not compressed",
+                path.display()
+            );
+        }
+        return Ok(None)
+    };
+    tracing::debug!(target: "reth::cli", path = %path.display(), ?compression, "Decompressing input");
+    let mut decompressed = Vec::with_capacity(data.len() * 4);
+    decoder(compression, data)?
+        .read_to_end(&mut decompressed)
+        .map_err(|err| eyre::eyre!("Unable to decompress {}: {err}", path.display()))?;
+    Ok(Some(decompressed))
+}
+
+/// Reads the file at `path`, decompressing it if it is compressed
+pub fn read(path: impl AsRef<Path>) -> Result<Vec<u8>> {
+    let path = path.as_ref();
+    decompress(path, std::fs::read(path)?)
+}
+
+/// Opens the file at `path` for reading its decompressed contents
+pub fn open(path: &Path) -> Result<Box<dyn Read>> {
+    reader(path, BufReader::new(File::open(path)?))
+}
+
+/// Reads the decompressed contents of the file at `path` from the given reader of the file,
+/// failing like [decompress] if the extension of the file claims a compression it doesn't have
+pub fn reader<'a>(path: &Path, mut reader: impl BufRead + 'a) -> Result<Box<dyn Read + 'a>> {
+    match Compression::detect(reader.fill_buf()?) {
+        Some(compression) => decoder(compression, reader),
+        None => {
+            if let Some(expected) = Compression::from_extension(path) {
+                eyre::bail!(
+                    "{} is named like a {expected:?} file, but is not compressed",
+                    path.display()
+                );
+            }
+            Ok(Box::new(reader))
+        }
+    }
+}
+
+/// Wraps the reader of compressed contents into a decoder
+fn decoder<'a, R: BufRead + 'a>(compression: Compression, reader: R) -> Result<Box<dyn Read + 'a>> {
+    Ok(match compression {
+        // Parallel compressors like pigz write several gzip members
+        Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+        Compression::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(reader)?),
+    })
+}
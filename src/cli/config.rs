@@ -0,0 +1,165 @@
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use eyre::Result;
+use toml::{Table, Value};
+
+/// The config file read from the working directory when no other one is selected
+pub const CONFIG_FILE: &str = "op-reth.toml";
+
+/// The environment variable selecting the config file, overridden by `--config-file`
+pub const CONFIG_ENV: &str = "OP_RETH_CONFIG";
+
+/// The flag selecting the config file
+const CONFIG_FLAG: &str = "--config-file";
+
+/// Returns the path of the config file for the given command line: the file given with
+/// `--config-file` or in `OP_RETH_CONFIG`, otherwise `op-reth.toml` in the working directory if it
+/// exists
+pub fn config_path(args: &[OsString]) -> Option<PathBuf> {
+    let mut args = args.iter().map(|arg| arg.to_string_lossy());
+    while let Some(arg) = args.next() {
+        if arg == CONFIG_FLAG {
+            return args.next().map(|path| PathBuf::from(path.as_ref()))
+        }
+        if let Some(path) = arg.strip_prefix(CONFIG_FLAG).and_then(|arg| arg.strip_prefix('=')) {
+            return Some(PathBuf::from(path))
+        }
+    }
+    if let Some(path) = std::env::var_os(CONFIG_ENV) {
+        return Some(PathBuf::from(path))
+    }
+    Some(PathBuf::from(CONFIG_FILE)).filter(|path| path.is_file())
+}
+
+/// Reads a config file
+pub fn read(path: &Path) -> Result<Table> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| eyre::eyre!("Unable to read config file {}: {err}", path.display()))?;
+    contents
+        .parse::<Table>()
+        .map_err(|err| eyre::eyre!("Invalid config file {}: {err}", path.display()))
+}
+
+/// Adds the flags the config sets for the invoked command to the command line `args`, unless they
+/// are given on the command line already, which overrides the config.
+///
+/// Keys are the long flag names without their dashes. Keys at the top of the config apply to every
+/// command accepting the flag, and keys in the table of a command, like `[blocks.import]`, to that
+/// command and its subcommands, overriding the keys of the tables around it. Keys of the table of
+/// the invoked command that it does not accept are refused, to catch typos.
+pub fn apply(
+    command: &clap::Command,
+    mut args: Vec<OsString>,
+    config: &Table,
+) -> Result<Vec<OsString>> {
+    let (path, command) = invoked_command(command, &args);
+    let mut values = Table::new();
+    let mut table = Some(config);
+    for depth in 0..=path.len() {
+        let Some(current) = table else { break };
+        for (key, value) in current {
+            if !value.is_table() {
+                values.insert(key.clone(), value.clone());
+            }
+        }
+        if depth == path.len() && !path.is_empty() {
+            // The table of the invoked command must only hold its flags and subcommands
+            for (key, value) in current {
+                if !accepts(command, key) && !(value.is_table() && has_subcommand(command, key)) {
+                    eyre::bail!("`{}` does not accept `{key}` set in the config", path.join(" "));
+                }
+            }
+            break
+        }
+        table = path.get(depth).and_then(|name| current.get(name)).and_then(Value::as_table);
+    }
+
+    for (key, value) in values {
+        let Some(arg) = command.get_arguments().find(|arg| arg.get_long() == Some(key.as_str()))
+        else {
+            // Keys of the enclosing tables apply to the commands accepting them only
+            continue
+        };
+        if given(arg, &args) {
+            continue
+        }
+        let flag = OsString::from(format!("--{key}"));
+        let takes_value = arg.get_action().takes_values();
+        let values = match value {
+            Value::Array(values) => values,
+            value => vec![value],
+        };
+        for value in values {
+            match value {
+                Value::Boolean(set) if !takes_value => {
+                    if set {
+                        args.push(flag.clone());
+                    }
+                }
+                Value::String(value) => args.extend([flag.clone(), value.into()]),
+                Value::Integer(_) | Value::Float(_) | Value::Boolean(_) | Value::Datetime(_) => {
+                    args.extend([flag.clone(), value.to_string().into()])
+                }
+                Value::Array(_) | Value::Table(_) => {
+                    eyre::bail!("`{key}` must be set to a value or a list of values in the config")
+                }
+            }
+        }
+    }
+    Ok(args)
+}
+
+/// Finds the subcommand invoked by the command line, returning the names leading to it
+fn invoked_command<'a>(
+    command: &'a clap::Command,
+    args: &[OsString],
+) -> (Vec<String>, &'a clap::Command) {
+    let mut path = Vec::new();
+    let mut command = command;
+    let mut args = args.iter().skip(1).map(|arg| arg.to_string_lossy());
+    while let Some(arg) = args.next() {
+        if let Some(flag) = arg.strip_prefix("--") {
+            // Skip the value of flags taking one
+            let takes_value = !flag.contains('=') &&
+                command
+                    .get_arguments()
+                    .find(|candidate| candidate.get_long() == Some(flag))
+                    .map_or(false, |arg| arg.get_action().takes_values());
+            if takes_value {
+                args.next();
+            }
+            continue
+        }
+        if arg.starts_with('-') {
+            continue
+        }
+        let Some(subcommand) = command.find_subcommand(arg.as_ref()) else { break };
+        path.push(subcommand.get_name().to_string());
+        command = subcommand;
+    }
+    (path, command)
+}
+
+/// Whether the command has a flag with the given long name
+fn accepts(command: &clap::Command, key: &str) -> bool {
+    command.get_arguments().any(|arg| arg.get_long() == Some(key))
+}
+
+fn has_subcommand(command: &clap::Command, name: &str) -> bool {
+    command.find_subcommand(name).is_some()
+}
+
+/// Whether the flag is given on the command line, by its long name, an alias or its short name
+fn given(arg: &clap::Arg, args: &[OsString]) -> bool {
+    let longs = arg.get_long().into_iter().chain(arg.get_all_aliases().unwrap_or_default());
+    let longs = longs.map(|long| format!("--{long}")).collect::<Vec<_>>();
+    let short = arg.get_short().map(|short| format!("-{short}"));
+    args.iter().map(|arg| arg.to_string_lossy()).any(|arg| {
+        longs.iter().any(|long| arg == *long || arg.starts_with(&format!("{long}="))) ||
+            short.as_deref() == Some(arg.as_ref())
+    })
+}
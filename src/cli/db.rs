@@ -1,15 +1,344 @@
-use std::path::{Path, PathBuf};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+};
 
+use clap::{Parser, Subcommand};
 use eyre::Result;
+use reth::runner::CliContext;
 use reth_db::mdbx::{Env, EnvKind, WriteMap};
+use tempfile::TempDir;
 
-use crate::cli::{blocks, genesis, receipts, state};
+use crate::cli::{
+    args::ImportArgs,
+    blocks, dirs, genesis,
+    l1_fee::L1FeeStore,
+    receipts,
+    shutdown::{self, ShutdownGuard, ShutdownPhase},
+    state,
+};
+
+pub mod attest;
+pub mod copy_table;
+pub mod diff;
+pub mod finalize;
+pub mod head;
+pub mod logs;
+pub mod prune;
+pub mod stats;
+
+/// Database command
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(subcommand)]
+    command: Subcommands,
+}
+
+/// Database subcommands
+#[derive(Debug, Subcommand)]
+pub enum Subcommands {
+    /// Report per-table entry counts, page usage and size, and the highest block and tx number
+    #[command(name = "stats")]
+    Stats(stats::Command),
+    /// Compare selected tables of two databases and report divergent entries
+    #[command(name = "diff")]
+    Diff(diff::Command),
+    /// Copy a single table, or the entries of a block range, from another database
+    #[command(name = "copy-table")]
+    CopyTable(copy_table::Command),
+    /// Show or set the canonical head, safe and finalized blocks handed to op-node
+    #[command(name = "head")]
+    Head(head::Command),
+    /// Write total difficulties and stage checkpoints so reth continues syncing from the tip
+    #[command(name = "finalize")]
+    Finalize(finalize::Command),
+    /// Query the logs of the imported receipts like `eth_getLogs`, as JSON lines
+    #[command(name = "logs")]
+    Logs(logs::Command),
+    /// Drop receipts, bodies or transaction lookup entries below given heights
+    #[command(name = "prune")]
+    Prune(prune::Command),
+    /// Print a hash over the canonical chain, its receipts and the state to compare migrations
+    #[command(name = "attest")]
+    Attest(attest::Command),
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, ctx: CliContext) -> Result<()> {
+        match self.command {
+            Subcommands::Stats(command) => command.execute(ctx).await,
+            Subcommands::Diff(command) => command.execute(ctx).await,
+            Subcommands::CopyTable(command) => command.execute(ctx).await,
+            Subcommands::Head(command) => command.execute(ctx).await,
+            Subcommands::Finalize(command) => command.execute(ctx).await,
+            Subcommands::Logs(command) => command.execute(ctx).await,
+            Subcommands::Prune(command) => command.execute(ctx).await,
+            Subcommands::Attest(command) => command.execute(ctx).await,
+        }
+    }
+}
 
 /// Helper that opens a read/write MDBX db at the given path
 pub fn open_rw_env(path: &Path) -> Result<Env<WriteMap>> {
     Env::open(path, EnvKind::RW).map_err(|e| eyre::eyre!(e))
 }
 
+/// Helper that opens a read-only MDBX db at the given path.
+///
+/// Unlike [open_rw_env], this never creates or modifies the database, and works while another
+/// process such as a running node writes it. Write transactions on the environment fail.
+pub fn open_ro_env(path: &Path) -> Result<Env<WriteMap>> {
+    if !path.exists() {
+        eyre::bail!("No database found at {}", path.display());
+    }
+    Env::open(path, EnvKind::RO).map_err(|e| eyre::eyre!(e))
+}
+
+/// The file below a database path locked by the commands writing the database
+pub const LOCK_FILE: &str = "op-reth.lock";
+
+/// An exclusive lock on a database, keeping other op-reth processes from writing it at the same
+/// time. Released when dropped.
+#[derive(Debug)]
+pub struct DbLock {
+    _file: File,
+}
+
+/// Locks the database at `path` exclusively, failing if another process holds the lock.
+///
+/// MDBX allows several processes to write the same environment, which would interleave two
+/// imports. The lock is an advisory `flock` on Unix, including macOS, and a file opened without
+/// sharing on Windows, so it is released by the operating system if the process dies. The
+/// holder writes its process id into the lock file, so a contender can name it in its error.
+pub fn lock_db(path: &Path) -> Result<DbLock> {
+    fs::create_dir_all(path)?;
+    let lock_path = path.join(LOCK_FILE);
+    match try_lock(&lock_path) {
+        Ok(mut file) => {
+            file.set_len(0)?;
+            writeln!(file, "{}", std::process::id())?;
+            Ok(DbLock { _file: file })
+        }
+        Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+            let holder = fs::read_to_string(&lock_path)
+                .ok()
+                .and_then(|pid| pid.trim().parse::<u32>().ok())
+                .map_or_else(
+                    || "another op-reth process".to_string(),
+                    |pid| format!("op-reth process {pid}"),
+                );
+            eyre::bail!(
+                "The database at {} is in use by {holder}, wait for it to finish",
+                path.display()
+            )
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(unix)]
+fn try_lock(path: &Path) -> io::Result<File> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = OpenOptions::new().read(true).write(true).create(true).open(path)?;
+    // SAFETY: the descriptor is valid as long as `file` is alive
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+        return Err(io::Error::last_os_error())
+    }
+    Ok(file)
+}
+
+#[cfg(windows)]
+fn try_lock(path: &Path) -> io::Result<File> {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    /// The error opening a file another process opened without sharing
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+
+    OpenOptions::new().read(true).write(true).create(true).share_mode(0).open(path).map_err(|err| {
+        match err.raw_os_error() {
+            Some(ERROR_SHARING_VIOLATION) => io::ErrorKind::WouldBlock.into(),
+            _ => err,
+        }
+    })
+}
+
+#[cfg(not(any(unix, windows)))]
+fn try_lock(path: &Path) -> io::Result<File> {
+    OpenOptions::new().read(true).write(true).create(true).open(path)
+}
+
+/// The lock file MDBX keeps next to the data file of an environment
+const MDBX_LOCK_FILE: &str = "mdbx.lck";
+
+/// A read/write database in a temporary directory, for tests and dry runs.
+///
+/// The directory is removed when the database is dropped, and by a shutdown hook if the process
+/// is interrupted before, so no scratch databases are left behind. Dereferences to its
+/// environment.
+#[derive(Debug)]
+pub struct TempDatabase {
+    // Fields drop in order, the environment has to be closed before its directory is removed
+    env: Env<WriteMap>,
+    _shutdown: ShutdownGuard<'static>,
+    dir: TempDir,
+}
+
+impl TempDatabase {
+    /// Creates an empty database in the system temporary directory
+    pub fn new() -> Result<Self> {
+        Self::open(tempfile::tempdir()?)
+    }
+
+    /// Creates an empty database in a temporary directory below `parent`
+    pub fn new_in(parent: &Path) -> Result<Self> {
+        fs::create_dir_all(parent)?;
+        Self::open(tempfile::tempdir_in(parent)?)
+    }
+
+    /// Creates a database holding a copy of the database at `path` and the files next to it.
+    ///
+    /// The copy is made next to the database, on the same disk, which needs as much free space as
+    /// the database takes. The database is locked while it is copied.
+    pub fn copy_of(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            eyre::bail!("No database found at {}", path.display());
+        }
+        let _lock = lock_db(path)?;
+        let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty());
+        let dir = tempfile::tempdir_in(parent.unwrap_or_else(|| Path::new(".")))?;
+        copy_dir(path, dir.path())?;
+        Self::open(dir)
+    }
+
+    fn open(dir: TempDir) -> Result<Self> {
+        let env = open_rw_env(dir.path())?;
+        let path = dir.path().to_path_buf();
+        let shutdown =
+            shutdown::registry().register("temporary database", ShutdownPhase::Database, {
+                move || async move { Ok(fs::remove_dir_all(&path)?) }
+            });
+        Ok(Self { env, _shutdown: shutdown, dir })
+    }
+
+    /// The path of the database
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+impl Deref for TempDatabase {
+    type Target = Env<WriteMap>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.env
+    }
+}
+
+impl DerefMut for TempDatabase {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.env
+    }
+}
+
+/// Copies the files of the database at `from` into `to`, leaving out the lock files
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if [LOCK_FILE, MDBX_LOCK_FILE].iter().any(|skip| name == *skip) {
+            continue
+        }
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &to.join(&name))?;
+        } else {
+            fs::copy(entry.path(), to.join(&name))?;
+        }
+    }
+    Ok(())
+}
+
+/// The directory next to a database root holding the per-chain namespaces, `<ROOT>.chains`.
+///
+/// It lives beside the root rather than inside it, so the environment at the root only ever holds
+/// its own files and copying or measuring it needs no special cases.
+fn namespaces_dir(root: &Path) -> PathBuf {
+    let name = root.file_name().map(|name| name.to_string_lossy()).unwrap_or_else(|| "db".into());
+    root.with_file_name(format!("{name}.chains"))
+}
+
+/// Returns the path of the database environment for the given namespace.
+///
+/// reth opens its tables by their fixed names and sizes an environment for exactly one set of
+/// them, so the chains can't be named sub-databases of one environment without forking reth's
+/// database layer. Every chain namespace is a separate environment instead, in the namespaces
+/// directory next to the database root and keyed by chain id. Without a namespace the root itself
+/// is the environment.
+pub fn namespaced_path(root: &Path, namespace: Option<u64>) -> PathBuf {
+    match namespace {
+        Some(chain_id) => namespaces_dir(root).join(chain_id.to_string()),
+        None => root.to_path_buf(),
+    }
+}
+
+/// Helper that opens a read/write MDBX db for the given namespace of the database root
+pub fn open_rw_namespaced_env(root: &Path, namespace: Option<u64>) -> Result<Env<WriteMap>> {
+    let path = namespaced_path(root, namespace);
+    fs::create_dir_all(&path)?;
+    open_rw_env(&path)
+}
+
+/// The file below a database path recording where its static data lives
+const STATIC_PATH_FILE: &str = "static-path";
+
+/// Resolves the root below which the static data of the database at `db_path` lives.
+///
+/// A given root is recorded in the database, so that later commands find it without being told.
+/// Without one, the recorded root is used, or the database path itself if none was recorded.
+pub fn resolve_static_root(db_path: &Path, static_root: Option<&Path>) -> Result<PathBuf> {
+    let record = db_path.join(STATIC_PATH_FILE);
+    let recorded = match fs::read_to_string(&record) {
+        Ok(path) => Some(PathBuf::from(path.trim())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err.into()),
+    };
+
+    match (static_root, recorded) {
+        (Some(root), Some(recorded)) if !dirs::same_path(root, &recorded) => eyre::bail!(
+            "The static data of {} lives below {}, move it before passing another static path",
+            db_path.display(),
+            recorded.display()
+        ),
+        (Some(root), None) => {
+            fs::create_dir_all(db_path)?;
+            fs::write(&record, root.to_string_lossy().as_bytes())?;
+            tracing::info!(target: "reth::cli", db = %db_path.display(), static_path = %root.display(), "Recorded static data path");
+            Ok(root.to_path_buf())
+        }
+        (_, Some(recorded)) => Ok(recorded),
+        (None, None) => Ok(db_path.to_path_buf()),
+    }
+}
+
+/// Lists the chain ids that have a namespace next to the given database root
+pub fn list_namespaces(root: &Path) -> Result<Vec<u64>> {
+    let dir = namespaces_dir(root);
+    if !dir.exists() {
+        return Ok(vec![])
+    }
+    let mut namespaces = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u64>().ok())
+        .collect::<Vec<_>>();
+    namespaces.sort_unstable();
+    Ok(namespaces)
+}
+
 /// Construct the full op-reth database
 pub async fn construct() -> eyre::Result<Env<WriteMap>> {
     // Create a database at a new location
@@ -18,28 +347,29 @@ pub async fn construct() -> eyre::Result<Env<WriteMap>> {
     println!("Created db");
 
     // Apply genesis state
-    if let Err(e) = genesis::apply(&mut db, None).await {
+    if let Err(e) = genesis::apply(&mut db, None, &ImportArgs::default()).await {
         eprintln!("Error while applying genesis to mdbx: {}", e);
         return Err(e)
     }
     println!("Applied genesis state!");
 
     // Apply blocks
-    if let Err(e) = blocks::apply(&mut db, None).await {
+    if let Err(e) = blocks::apply(&mut db, None, &ImportArgs::default()).await {
         eprintln!("Error while applying blocks to mdbx: {}", e);
         return Err(e)
     }
     println!("Applied blocks!");
 
     // Apply receipts
-    if let Err(e) = receipts::apply(&mut db, None).await {
+    let fees = L1FeeStore::open(&db_path)?;
+    if let Err(e) = receipts::apply(&mut db, &fees, None, &ImportArgs::default()).await {
         eprintln!("Error while applying receips to mdbx: {}", e);
         return Err(e)
     }
     println!("Applied receipts!");
 
     // Apply state
-    if let Err(e) = state::apply(&mut db, None).await {
+    if let Err(e) = state::apply(&mut db, None, &ImportArgs::default()).await {
         eprintln!("Error while applying state to mdbx: {}", e);
         return Err(e)
     }
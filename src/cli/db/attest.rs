@@ -0,0 +1,110 @@
+use clap::Parser;
+use eyre::Result;
+use reth::runner::CliContext;
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTx,
+};
+use reth_primitives::{proofs, H256};
+
+use crate::cli::{
+    args::DatabaseArgs,
+    db::prune::{PruneCheckpoints, PrunedData},
+    keccak::keccak256,
+    state::{stream_database_state, stream_state_root},
+};
+
+/// Compute the attestation of the migrated database, a single hash two operators can compare to
+/// confirm they produced the same migration from the same inputs.
+///
+/// The chain digest folds the hash of every canonical block, from the genesis on, together with
+/// the root of its imported receipts, starting from the zero hash:
+/// `digest = keccak256(digest ‖ block hash ‖ receipts root)`. The attestation is
+/// `keccak256(chain digest ‖ state root ‖ tip)`, with the state root of the plain state and the
+/// tip as a big-endian u64.
+///
+/// A database whose receipts were pruned with `db prune` is refused, as its digest can't match the
+/// one of the unpruned migration.
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+}
+
+/// The attestation of a database and the digests it is made of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attestation {
+    /// The highest canonical block
+    pub tip: u64,
+    /// The digest of the canonical block hashes and the roots of their imported receipts
+    pub chain_digest: H256,
+    /// The state root of the plain state
+    pub state_root: H256,
+    /// The hash over the digests and the tip
+    pub attestation: H256,
+}
+
+/// Computes the [Attestation] of the given database. Fails if receipts were pruned according to
+/// the [PruneCheckpoints] of the database.
+pub fn attest(db: &Env<WriteMap>, pruned: &PruneCheckpoints) -> Result<Attestation> {
+    if let Some(below) = pruned.pruned_below(PrunedData::Receipts) {
+        eyre::bail!(
+            "The receipts of the blocks below {below} were pruned, which the chain digest covers"
+        )
+    }
+    let tx = db.tx()?;
+    let mut tip = 0;
+    let mut chain_digest = H256::zero();
+    let mut canonical = tx.cursor_read::<tables::CanonicalHeaders>()?;
+    let mut entry = canonical.first()?;
+    while let Some((number, hash)) = entry {
+        let receipts_root = imported_receipts_root(&tx, number)?;
+        chain_digest = keccak256(
+            [chain_digest.as_bytes(), hash.as_bytes(), receipts_root.as_bytes()].concat(),
+        );
+        tip = number;
+        entry = canonical.next()?;
+    }
+    tracing::info!(target: "reth::cli", tip, ?chain_digest, "Digested the canonical chain");
+
+    let (state_root, _) = stream_state_root(|visit| stream_database_state(&tx, visit))?;
+    let attestation =
+        keccak256([chain_digest.as_bytes(), state_root.as_bytes(), &tip.to_be_bytes()].concat());
+    Ok(Attestation { tip, chain_digest, state_root, attestation })
+}
+
+/// The root of the imported receipts of the given block, the empty root if it has none
+fn imported_receipts_root<'a, TX: DbTx<'a>>(tx: &TX, number: u64) -> Result<H256> {
+    let mut receipts = Vec::new();
+    if let Some(body) = tx.get::<tables::BlockBodies>(number)? {
+        let mut cursor = tx.cursor_read::<tables::Receipts>()?;
+        let mut entry = cursor.seek(body.start_tx_id)?;
+        while let Some((_, receipt)) =
+            entry.filter(|(tx_id, _)| *tx_id < body.start_tx_id + body.tx_count)
+        {
+            receipts.push(receipt);
+            entry = cursor.next()?;
+        }
+    }
+    Ok(proofs::calculate_receipt_root(receipts.iter()))
+}
+
+/// Prints the given attestation
+pub fn print(attestation: &Attestation) {
+    println!("Tip:           {}", attestation.tip);
+    println!("Chain digest:  {:?}", attestation.chain_digest);
+    println!("State root:    {:?}", attestation.state_root);
+    println!("Attestation:   {:?}", attestation.attestation);
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db = self.db.open_ro()?;
+        print(&attest(&db, &PruneCheckpoints::read(&self.db.path())?)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,310 @@
+use std::{
+    ops::{Range, RangeInclusive},
+    path::PathBuf,
+};
+
+use clap::{Parser, ValueEnum};
+use eyre::Result;
+use reth::runner::CliContext;
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    mdbx::{Env, WriteMap},
+    table::Table,
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::TxNumber;
+
+use crate::cli::{
+    args::parse_block_range,
+    db::{open_ro_env, open_rw_env, stats},
+    journal,
+};
+
+/// The number of entries copied or removed per database transaction
+pub const COPY_BATCH_SIZE: usize = 100_000;
+
+/// Copy a single table from one database to another.
+///
+/// Replaces the entries of the table in the destination with those of the source, so that a
+/// table corrupted or left incomplete by one import run can be repaired from another run without
+/// redoing the whole migration. With `--range` only the entries of the given blocks are replaced.
+/// Tables derived from the copied one, like the transaction lookup of the transactions, are not
+/// touched and have to be copied as well.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The path to the database to copy from
+    #[arg(long, value_name = "PATH", verbatim_doc_comment)]
+    from: PathBuf,
+
+    /// The path to the database to copy into
+    #[arg(long, value_name = "PATH", verbatim_doc_comment)]
+    to: PathBuf,
+
+    /// The table to copy
+    #[arg(long, value_enum, verbatim_doc_comment)]
+    table: CopyTable,
+
+    /// Only copy the entries of the blocks in the inclusive range `<FIRST>..<LAST>`. Not
+    /// supported for the state tables.
+    #[arg(long, value_name = "FIRST..LAST", value_parser = parse_block_range, verbatim_doc_comment)]
+    range: Option<RangeInclusive<u64>>,
+}
+
+/// The tables `db copy-table` can copy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CopyTable {
+    /// [tables::CanonicalHeaders]
+    CanonicalHeaders,
+    /// [tables::HeaderTD]
+    HeaderTd,
+    /// [tables::HeaderNumbers]
+    HeaderNumbers,
+    /// [tables::Headers]
+    Headers,
+    /// [tables::BlockBodies]
+    BlockBodies,
+    /// [tables::BlockOmmers]
+    BlockOmmers,
+    /// [tables::Transactions]
+    Transactions,
+    /// [tables::TxHashNumber]
+    TxHashNumber,
+    /// [tables::TxSenders]
+    TxSenders,
+    /// [tables::Receipts]
+    Receipts,
+    /// [tables::PlainAccountState]
+    PlainAccountState,
+    /// [tables::PlainStorageState]
+    PlainStorageState,
+    /// [tables::Bytecodes]
+    Bytecodes,
+}
+
+/// The outcome of copying a table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableCopy {
+    /// The name of the table
+    pub table: &'static str,
+    /// The number of entries removed from the destination before copying
+    pub removed: u64,
+    /// The number of entries copied from the source
+    pub copied: u64,
+}
+
+/// Which entries of a table are copied
+enum Select {
+    Copy,
+    Skip,
+    Stop,
+}
+
+/// Copies `table` from `from` into `to`, replacing the entries of the blocks in `range`, or the
+/// whole table if no range is given
+pub fn copy_table(
+    from: &Env<WriteMap>,
+    to: &Env<WriteMap>,
+    table: CopyTable,
+    range: Option<RangeInclusive<u64>>,
+) -> Result<TableCopy> {
+    let Some(range) = range else {
+        return match table {
+            CopyTable::CanonicalHeaders => copy_all::<tables::CanonicalHeaders>(from, to),
+            CopyTable::HeaderTd => copy_all::<tables::HeaderTD>(from, to),
+            CopyTable::HeaderNumbers => copy_all::<tables::HeaderNumbers>(from, to),
+            CopyTable::Headers => copy_all::<tables::Headers>(from, to),
+            CopyTable::BlockBodies => copy_all::<tables::BlockBodies>(from, to),
+            CopyTable::BlockOmmers => copy_all::<tables::BlockOmmers>(from, to),
+            CopyTable::Transactions => copy_all::<tables::Transactions>(from, to),
+            CopyTable::TxHashNumber => copy_all::<tables::TxHashNumber>(from, to),
+            CopyTable::TxSenders => copy_all::<tables::TxSenders>(from, to),
+            CopyTable::Receipts => copy_all::<tables::Receipts>(from, to),
+            CopyTable::PlainAccountState => copy_all::<tables::PlainAccountState>(from, to),
+            CopyTable::PlainStorageState => copy_all::<tables::PlainStorageState>(from, to),
+            CopyTable::Bytecodes => copy_all::<tables::Bytecodes>(from, to),
+        }
+    };
+    let blocks = *range.start()..*range.end() + 1;
+    match table {
+        CopyTable::CanonicalHeaders => copy_range::<tables::CanonicalHeaders>(from, to, blocks),
+        CopyTable::HeaderTd => copy_range::<tables::HeaderTD>(from, to, blocks),
+        CopyTable::HeaderNumbers => {
+            copy_entries::<tables::HeaderNumbers>(from, to, None, |_, number| {
+                if range.contains(number) {
+                    Select::Copy
+                } else {
+                    Select::Skip
+                }
+            })
+        }
+        CopyTable::Headers => copy_range::<tables::Headers>(from, to, blocks),
+        CopyTable::BlockBodies => copy_range::<tables::BlockBodies>(from, to, blocks),
+        CopyTable::BlockOmmers => copy_range::<tables::BlockOmmers>(from, to, blocks),
+        CopyTable::Transactions => {
+            copy_range::<tables::Transactions>(from, to, tx_range(from, range.clone())?)
+        }
+        CopyTable::TxHashNumber => {
+            let txs = tx_range(from, range.clone())?;
+            copy_entries::<tables::TxHashNumber>(from, to, None, |_, id| {
+                if txs.contains(id) {
+                    Select::Copy
+                } else {
+                    Select::Skip
+                }
+            })
+        }
+        CopyTable::TxSenders => {
+            copy_range::<tables::TxSenders>(from, to, tx_range(from, range.clone())?)
+        }
+        CopyTable::Receipts => {
+            copy_range::<tables::Receipts>(from, to, tx_range(from, range.clone())?)
+        }
+        CopyTable::PlainAccountState | CopyTable::PlainStorageState | CopyTable::Bytecodes => {
+            eyre::bail!("The state tables can only be copied as a whole, drop --range")
+        }
+    }
+}
+
+/// The transaction numbers of the blocks in `blocks`, according to the bodies of `db`
+fn tx_range(db: &Env<WriteMap>, blocks: RangeInclusive<u64>) -> Result<Range<TxNumber>> {
+    db.view(|tx| -> Result<_> {
+        let body = |number| -> Result<_> {
+            tx.get::<tables::BlockBodies>(number)?
+                .ok_or_else(|| eyre::eyre!("Block {number} has no body in the source database"))
+        };
+        let (first, last) = (body(*blocks.start())?, body(*blocks.end())?);
+        Ok(first.start_tx_id..last.start_tx_id + last.tx_count)
+    })?
+}
+
+/// Replaces the whole table of the destination with the table of the source
+fn copy_all<T: Table>(from: &Env<WriteMap>, to: &Env<WriteMap>) -> Result<TableCopy>
+where
+    T::Key: Clone,
+{
+    let stats = stats::collect(to)?;
+    let removed = stats.tables.iter().find(|table| table.name == T::NAME).map_or(0, |t| t.entries);
+    to.update(|tx| tx.clear::<T>())??;
+    let mut copy = copy_entries::<T>(from, to, None, |_, _| Select::Copy)?;
+    copy.removed = removed as u64;
+    Ok(copy)
+}
+
+/// Replaces the entries of a table keyed by block or transaction number whose key is in `range`
+fn copy_range<T: Table<Key = u64>>(
+    from: &Env<WriteMap>,
+    to: &Env<WriteMap>,
+    range: Range<u64>,
+) -> Result<TableCopy> {
+    copy_entries::<T>(from, to, Some(range.start), |key, _| {
+        if *key >= range.end {
+            Select::Stop
+        } else {
+            Select::Copy
+        }
+    })
+}
+
+/// Removes the entries of the destination that `select` picks, then copies the picked entries of
+/// the source, starting at `start` or the first entry
+fn copy_entries<T: Table>(
+    from: &Env<WriteMap>,
+    to: &Env<WriteMap>,
+    start: Option<T::Key>,
+    mut select: impl FnMut(&T::Key, &T::Value) -> Select,
+) -> Result<TableCopy>
+where
+    T::Key: Clone,
+{
+    let mut copy = TableCopy { table: T::NAME, removed: 0, copied: 0 };
+
+    // Entries only present in the destination must not survive the copy
+    let mut resume = start.clone();
+    loop {
+        let tx = to.tx_mut()?;
+        let (keys, next) = read_batch::<T, _>(&tx, resume.take(), &mut select)?;
+        copy.removed += keys.len() as u64;
+        for (key, _) in keys {
+            tx.delete::<T>(key, None)?;
+        }
+        tx.commit()?;
+        match next {
+            Some(key) => resume = Some(key),
+            None => break,
+        }
+    }
+
+    let mut resume = start;
+    loop {
+        let (entries, next) =
+            from.view(|tx| read_batch::<T, _>(tx, resume.take(), &mut select))??;
+        copy.copied += entries.len() as u64;
+        let tx = to.tx_mut()?;
+        for (key, value) in entries {
+            tx.put::<T>(key, value)?;
+        }
+        tx.commit()?;
+        tracing::debug!(target: "reth::cli", table = T::NAME, copied = copy.copied, "Copied entries");
+        match next {
+            Some(key) => resume = Some(key),
+            None => return Ok(copy),
+        }
+    }
+}
+
+/// Reads up to [COPY_BATCH_SIZE] entries from `start` on, returning those picked by `select`
+/// together with the key to continue from, if the table holds more entries
+#[allow(clippy::type_complexity)]
+fn read_batch<'a, T: Table, TX: DbTx<'a>>(
+    tx: &TX,
+    start: Option<T::Key>,
+    select: &mut impl FnMut(&T::Key, &T::Value) -> Select,
+) -> Result<(Vec<(T::Key, T::Value)>, Option<T::Key>)> {
+    let mut cursor = tx.cursor_read::<T>()?;
+    let mut entry = match start {
+        Some(key) => cursor.seek(key)?,
+        None => cursor.first()?,
+    };
+    let mut entries = Vec::new();
+    let mut visited = 0;
+    while let Some((key, value)) = entry {
+        if visited == COPY_BATCH_SIZE {
+            return Ok((entries, Some(key)))
+        }
+        visited += 1;
+        match select(&key, &value) {
+            Select::Copy => entries.push((key, value)),
+            Select::Skip => {}
+            Select::Stop => break,
+        }
+        entry = cursor.next()?;
+    }
+    Ok((entries, None))
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        for path in [&self.from, &self.to] {
+            if !path.exists() {
+                eyre::bail!("No database found at {}", path.display());
+            }
+        }
+        if self.from.canonicalize()? == self.to.canonicalize()? {
+            eyre::bail!("The source and destination are the same database");
+        }
+        journal::record(&self.to, "db copy-table", &[], async {
+            let from = open_ro_env(&self.from)?;
+            let to = open_rw_env(&self.to)?;
+            tracing::info!(target: "reth::cli", table = ?self.table, range = ?self.range, "Copying table");
+            let copy = copy_table(&from, &to, self.table, self.range.clone())?;
+            println!("Table:     {}", copy.table);
+            println!("Removed:   {}", copy.removed);
+            println!("Copied:    {}", copy.copied);
+            Ok(())
+        })
+        .await
+    }
+}
@@ -0,0 +1,276 @@
+use std::{cmp::Ordering, fmt::Debug, path::PathBuf};
+
+use clap::{Parser, ValueEnum};
+use eyre::Result;
+use reth::runner::CliContext;
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    mdbx::{Env, WriteMap},
+    table::Table,
+    tables,
+    transaction::DbTx,
+};
+
+use crate::cli::db::open_ro_env;
+
+/// Compare the contents of two migrated databases
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The path to the first database
+    #[arg(value_name = "PATH_A", verbatim_doc_comment)]
+    path_a: PathBuf,
+
+    /// The path to the second database
+    #[arg(value_name = "PATH_B", verbatim_doc_comment)]
+    path_b: PathBuf,
+
+    /// The tables to compare. Defaults to all of them.
+    #[arg(long, value_enum, value_delimiter = ',', verbatim_doc_comment)]
+    tables: Vec<DiffTable>,
+
+    /// The number of divergent entries printed per table
+    #[arg(long, value_name = "COUNT", default_value_t = 10, verbatim_doc_comment)]
+    examples: usize,
+}
+
+/// The tables `db diff` can compare
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DiffTable {
+    /// [tables::Headers]
+    Headers,
+    /// [tables::PlainAccountState]
+    PlainAccountState,
+    /// [tables::PlainStorageState]
+    PlainStorageState,
+    /// [tables::Receipts]
+    Receipts,
+}
+
+impl DiffTable {
+    /// All comparable tables
+    pub const ALL: [DiffTable; 4] = [
+        DiffTable::Headers,
+        DiffTable::PlainAccountState,
+        DiffTable::PlainStorageState,
+        DiffTable::Receipts,
+    ];
+}
+
+/// How an entry diverges between the two databases
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DivergenceKind {
+    /// The key is only present in the first database
+    OnlyInA,
+    /// The key is only present in the second database
+    OnlyInB,
+    /// The key is present in both databases with different values
+    Different,
+}
+
+/// A single divergent entry, formatted for display
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// How the entry diverges
+    pub kind: DivergenceKind,
+    /// The key of the entry
+    pub key: String,
+    /// The values stored in the first database, if any
+    pub a: Option<String>,
+    /// The values stored in the second database, if any
+    pub b: Option<String>,
+}
+
+/// The divergences found in a single table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableDiff {
+    /// The name of the table
+    pub table: &'static str,
+    /// The number of keys present in both databases
+    pub compared: u64,
+    /// The number of keys only present in the first database
+    pub only_in_a: u64,
+    /// The number of keys only present in the second database
+    pub only_in_b: u64,
+    /// The number of keys present in both databases with different values
+    pub different: u64,
+    /// The first divergent entries, up to the requested number
+    pub examples: Vec<Divergence>,
+}
+
+impl TableDiff {
+    fn new(table: &'static str) -> Self {
+        Self { table, compared: 0, only_in_a: 0, only_in_b: 0, different: 0, examples: vec![] }
+    }
+
+    /// Whether the table is identical in both databases
+    pub fn is_empty(&self) -> bool {
+        self.only_in_a == 0 && self.only_in_b == 0 && self.different == 0
+    }
+
+    fn record(&mut self, divergence: Divergence, max_examples: usize) {
+        match divergence.kind {
+            DivergenceKind::OnlyInA => self.only_in_a += 1,
+            DivergenceKind::OnlyInB => self.only_in_b += 1,
+            DivergenceKind::Different => self.different += 1,
+        }
+        if self.examples.len() < max_examples {
+            self.examples.push(divergence);
+        }
+    }
+}
+
+/// Compares the given tables of two databases, collecting up to `max_examples` divergent entries
+/// per table
+pub fn diff(
+    a: &Env<WriteMap>,
+    b: &Env<WriteMap>,
+    tables: &[DiffTable],
+    max_examples: usize,
+) -> Result<Vec<TableDiff>> {
+    let tx_a = a.tx()?;
+    let tx_b = b.tx()?;
+    tables
+        .iter()
+        .map(|table| match table {
+            DiffTable::Headers => diff_table::<tables::Headers, _, _>(&tx_a, &tx_b, max_examples),
+            DiffTable::PlainAccountState => {
+                diff_table::<tables::PlainAccountState, _, _>(&tx_a, &tx_b, max_examples)
+            }
+            DiffTable::PlainStorageState => {
+                diff_table::<tables::PlainStorageState, _, _>(&tx_a, &tx_b, max_examples)
+            }
+            DiffTable::Receipts => diff_table::<tables::Receipts, _, _>(&tx_a, &tx_b, max_examples),
+        })
+        .collect()
+}
+
+/// Walks a table of both databases in key order, comparing all values stored under each key so
+/// that dup-sorted tables are compared as a whole per key
+fn diff_table<'a, 'b, T, TA, TB>(tx_a: &TA, tx_b: &TB, max_examples: usize) -> Result<TableDiff>
+where
+    T: Table,
+    T::Key: Ord + Debug,
+    T::Value: PartialEq + Debug,
+    TA: DbTx<'a>,
+    TB: DbTx<'b>,
+{
+    let mut diff = TableDiff::new(T::NAME);
+    let mut cursor_a = tx_a.cursor_read::<T>()?;
+    let mut cursor_b = tx_b.cursor_read::<T>()?;
+    let mut pending_a = cursor_a.first()?;
+    let mut pending_b = cursor_b.first()?;
+    let mut group_a = next_group(&mut cursor_a, &mut pending_a)?;
+    let mut group_b = next_group(&mut cursor_b, &mut pending_b)?;
+
+    loop {
+        let order = match (&group_a, &group_b) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some((key_a, _)), Some((key_b, _))) => key_a.cmp(key_b),
+        };
+        match order {
+            Ordering::Less => {
+                if let Some((key, values)) = group_a {
+                    let divergence = Divergence {
+                        kind: DivergenceKind::OnlyInA,
+                        key: format!("{key:?}"),
+                        a: Some(format!("{values:?}")),
+                        b: None,
+                    };
+                    diff.record(divergence, max_examples);
+                }
+                group_a = next_group(&mut cursor_a, &mut pending_a)?;
+            }
+            Ordering::Greater => {
+                if let Some((key, values)) = group_b {
+                    let divergence = Divergence {
+                        kind: DivergenceKind::OnlyInB,
+                        key: format!("{key:?}"),
+                        a: None,
+                        b: Some(format!("{values:?}")),
+                    };
+                    diff.record(divergence, max_examples);
+                }
+                group_b = next_group(&mut cursor_b, &mut pending_b)?;
+            }
+            Ordering::Equal => {
+                if let (Some((key, values_a)), Some((_, values_b))) = (group_a, group_b) {
+                    diff.compared += 1;
+                    if values_a != values_b {
+                        let divergence = Divergence {
+                            kind: DivergenceKind::Different,
+                            key: format!("{key:?}"),
+                            a: Some(format!("{values_a:?}")),
+                            b: Some(format!("{values_b:?}")),
+                        };
+                        diff.record(divergence, max_examples);
+                    }
+                }
+                group_a = next_group(&mut cursor_a, &mut pending_a)?;
+                group_b = next_group(&mut cursor_b, &mut pending_b)?;
+            }
+        }
+    }
+    Ok(diff)
+}
+
+/// Returns the pending entry together with all following entries sharing its key, and leaves the
+/// first entry of the next key pending
+#[allow(clippy::type_complexity)]
+fn next_group<'a, T, C>(
+    cursor: &mut C,
+    pending: &mut Option<(T::Key, T::Value)>,
+) -> Result<Option<(T::Key, Vec<T::Value>)>>
+where
+    T: Table,
+    T::Key: PartialEq,
+    C: DbCursorRO<'a, T>,
+{
+    let Some((key, value)) = pending.take() else { return Ok(None) };
+    let mut values = vec![value];
+    loop {
+        match cursor.next()? {
+            Some((next_key, next_value)) if next_key == key => values.push(next_value),
+            next => {
+                *pending = next;
+                break
+            }
+        }
+    }
+    Ok(Some((key, values)))
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let a = open_ro_env(&self.path_a)?;
+        let b = open_ro_env(&self.path_b)?;
+        let tables = if self.tables.is_empty() { &DiffTable::ALL[..] } else { &self.tables[..] };
+
+        let diffs = diff(&a, &b, tables, self.examples)?;
+        for diff in &diffs {
+            println!(
+                "{}: {} common keys, {} only in A, {} only in B, {} different",
+                diff.table, diff.compared, diff.only_in_a, diff.only_in_b, diff.different
+            );
+            for example in &diff.examples {
+                println!("    {:?} {}", example.kind, example.key);
+                if let Some(a) = &example.a {
+                    println!("        A: {a}");
+                }
+                if let Some(b) = &example.b {
+                    println!("        B: {b}");
+                }
+            }
+        }
+
+        let divergent = diffs.iter().filter(|diff| !diff.is_empty()).count();
+        if divergent > 0 {
+            eyre::bail!("{divergent} of {} tables differ", diffs.len());
+        }
+        println!("The databases are identical");
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use std::{fs, path::Path};
+
+use clap::Parser;
+use eyre::Result;
+use reth::runner::CliContext;
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::U256;
+use reth_stages::StageId;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{
+    args::DatabaseArgs,
+    db::head::{self, BlockPointer, ForkchoicePointers},
+    journal,
+};
+
+/// The file below a database path recording the tip the migration was finalized at
+pub const TIP_FILE: &str = "migration-tip.json";
+
+/// The stages of reth's sync pipeline, in the order they run
+pub const SYNC_STAGES: [StageId; 13] = [
+    StageId("Headers"),
+    StageId("TotalDifficulty"),
+    StageId("Bodies"),
+    StageId("SenderRecovery"),
+    StageId("Execution"),
+    StageId("MerkleUnwind"),
+    StageId("AccountHashing"),
+    StageId("StorageHashing"),
+    StageId("MerkleExecute"),
+    StageId("TransactionLookup"),
+    StageId("IndexStorageHistory"),
+    StageId("IndexAccountHistory"),
+    StageId("Finish"),
+];
+
+/// The checkpoint set by `state hash-and-trie` once the state of the tip is complete
+const MERKLE: StageId = StageId("MerkleExecute");
+
+/// Prepare the migrated database for reth to continue syncing from its tip.
+///
+/// Recomputes the total difficulty of every canonical block, sets the checkpoints of all sync
+/// stages to the tip and records the tip in `migration-tip.json` next to the database. The safe
+/// and finalized blocks default to the tip, since the legacy chain is final.
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+}
+
+/// The tip the migration was finalized at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationTip {
+    /// The highest canonical block
+    #[serde(flatten)]
+    pub block: BlockPointer,
+    /// The total difficulty of the chain up to the tip
+    pub total_difficulty: U256,
+}
+
+impl MigrationTip {
+    /// Reads the tip recorded next to the database at `db_path`, if the migration was finalized
+    pub fn read(db_path: &Path) -> Result<Option<Self>> {
+        let data = match fs::read(db_path.join(TIP_FILE)) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Some(serde_json::from_slice(&data)?))
+    }
+
+    /// Writes the tip next to the database at `db_path`
+    pub fn write(&self, db_path: &Path) -> Result<()> {
+        fs::create_dir_all(db_path)?;
+        fs::write(db_path.join(TIP_FILE), serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// The outcome of finalizing a migration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FinalizeSummary {
+    /// The tip the migration was finalized at
+    pub tip: MigrationTip,
+    /// The number of blocks whose total difficulty was missing or wrong
+    pub corrected: u64,
+}
+
+/// Writes the total difficulty of every canonical block up to the tip, correcting missing and
+/// wrong entries. Returns the total difficulty of the tip and the number of corrected entries.
+pub fn write_total_difficulties<'a, TX: DbTxMut<'a> + DbTx<'a>>(tx: &TX) -> Result<(U256, u64)> {
+    let (mut td, mut corrected) = (U256::ZERO, 0);
+    let mut canonical = tx.cursor_read::<tables::CanonicalHeaders>()?;
+    let mut entry = canonical.first()?;
+    while let Some((number, _)) = entry {
+        let header = tx
+            .get::<tables::Headers>(number)?
+            .ok_or_else(|| eyre::eyre!("Header of block {number} not found"))?;
+        td += header.difficulty;
+        if tx.get::<tables::HeaderTD>(number)?.map(|stored| stored.0) != Some(td) {
+            tx.put::<tables::HeaderTD>(number, td.into())?;
+            corrected += 1;
+        }
+        entry = canonical.next()?;
+    }
+    Ok((td, corrected))
+}
+
+/// Finalizes the migration of the database at `db_path`: writes the total difficulties, sets the
+/// checkpoints of all sync stages to the tip and records the tip.
+///
+/// The state of the tip has to be complete, so this refuses to run before `state hash-and-trie`
+/// recorded its checkpoint at the tip.
+pub fn finalize(db: &Env<WriteMap>, db_path: &Path) -> Result<FinalizeSummary> {
+    let Some(tip) = head::head(db)? else {
+        eyre::bail!("No canonical blocks found in the database")
+    };
+    let merkle = db.view(|tx| MERKLE.get_progress(tx))??;
+    if merkle != Some(tip.number) {
+        eyre::bail!(
+            "The state of block {} is not complete, run state hash-and-trie first",
+            tip.number
+        );
+    }
+
+    let tx = db.tx_mut()?;
+    let (total_difficulty, corrected) = write_total_difficulties(&tx)?;
+    for stage in SYNC_STAGES {
+        stage.save_progress(&tx, tip.number)?;
+    }
+    tx.commit()?;
+
+    let tip = MigrationTip { block: tip, total_difficulty };
+    tip.write(db_path)?;
+    let mut pointers = ForkchoicePointers::read(db_path)?;
+    if pointers.safe.is_none() || pointers.finalized.is_none() {
+        pointers.safe = pointers.safe.or(Some(tip.block));
+        pointers.finalized = pointers.finalized.or(Some(tip.block));
+        pointers.write(db_path)?;
+    }
+    Ok(FinalizeSummary { tip, corrected })
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db_path = self.db.path();
+        journal::record(&db_path, "db finalize", &[], async {
+            let db = self.db.open_rw()?;
+            let summary = finalize(&db, &db_path)?;
+            tracing::info!(target: "reth::cli", corrected = summary.corrected, "Total difficulties written");
+            println!("Tip:               {} {:?}", summary.tip.block.number, summary.tip.block.hash);
+            println!("Total difficulty:  {}", summary.tip.total_difficulty);
+            println!("Stage checkpoints: {}", summary.tip.block.number);
+            Ok(())
+        })
+        .await
+    }
+}
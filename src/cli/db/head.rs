@@ -0,0 +1,225 @@
+use std::{fs, path::Path};
+
+use clap::Parser;
+use eyre::Result;
+use reth::runner::CliContext;
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTx,
+};
+use reth_primitives::H256;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{args::DatabaseArgs, blocks, db, deposit::DepositStore};
+
+/// The file below a database path holding the safe and finalized block pointers
+pub const POINTERS_FILE: &str = "forkchoice.json";
+
+/// Show or set the canonical head, safe and finalized blocks handed to op-node
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// Move the canonical head down to this block.
+    ///
+    /// All canonical blocks above it are unwound and have to be imported again to restore them,
+    /// so this requires `--force`. The head can not move below the finalized block.
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment)]
+    set_head: Option<u64>,
+
+    /// Mark this canonical block as safe. It must lie between the finalized block and the head.
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment)]
+    set_safe: Option<u64>,
+
+    /// Mark this canonical block as finalized. It must not lie above the safe block.
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment)]
+    set_finalized: Option<u64>,
+
+    /// Unwind the blocks above the block given with `--set-head`
+    #[arg(long, verbatim_doc_comment)]
+    force: bool,
+}
+
+/// A block referenced by number and hash
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockPointer {
+    /// The number of the block
+    pub number: u64,
+    /// The hash of the block
+    pub hash: H256,
+}
+
+/// The safe and finalized blocks of a database, which op-node picks up when it takes over the
+/// imported chain. The head is always the highest canonical block.
+///
+/// reth's table set is fixed, so the pointers live in a file next to the database.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkchoicePointers {
+    /// The safe block, if one was set
+    pub safe: Option<BlockPointer>,
+    /// The finalized block, if one was set
+    pub finalized: Option<BlockPointer>,
+}
+
+impl ForkchoicePointers {
+    /// Reads the pointers stored next to the database at `db_path`, unset if there are none
+    pub fn read(db_path: &Path) -> Result<Self> {
+        let data = match fs::read(db_path.join(POINTERS_FILE)) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// Writes the pointers next to the database at `db_path`
+    pub fn write(&self, db_path: &Path) -> Result<()> {
+        fs::create_dir_all(db_path)?;
+        fs::write(db_path.join(POINTERS_FILE), serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Checks that the finalized block doesn't lie above the safe block, that both don't lie
+    /// above the head and that both are canonical blocks
+    pub fn check(&self, db: &Env<WriteMap>) -> Result<()> {
+        let Some(head) = head(db)? else {
+            eyre::bail!("No canonical blocks found in the database")
+        };
+        for (name, pointer) in [("safe", self.safe), ("finalized", self.finalized)] {
+            let Some(pointer) = pointer else { continue };
+            if pointer.number > head.number {
+                eyre::bail!(
+                    "The {name} block {} lies above the head {}",
+                    pointer.number,
+                    head.number
+                );
+            }
+            if canonical_block(db, pointer.number)? != pointer {
+                eyre::bail!("The {name} block {} is not canonical", pointer.number);
+            }
+        }
+        if let (Some(safe), Some(finalized)) = (self.safe, self.finalized) {
+            if finalized.number > safe.number {
+                eyre::bail!(
+                    "The finalized block {} lies above the safe block {}",
+                    finalized.number,
+                    safe.number
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the highest canonical block, if any
+pub fn head(db: &Env<WriteMap>) -> Result<Option<BlockPointer>> {
+    let head = db.view(|tx| -> Result<Option<(u64, H256)>> {
+        Ok(tx.cursor_read::<tables::CanonicalHeaders>()?.last()?)
+    })??;
+    Ok(head.map(|(number, hash)| BlockPointer { number, hash }))
+}
+
+/// Returns the canonical block with the given number, failing if there is none
+pub fn canonical_block(db: &Env<WriteMap>, number: u64) -> Result<BlockPointer> {
+    let hash = db
+        .view(|tx| tx.get::<tables::CanonicalHeaders>(number))??
+        .ok_or_else(|| eyre::eyre!("Block {number} is not a canonical block of the database"))?;
+    Ok(BlockPointer { number, hash })
+}
+
+/// Moves the canonical head of the database at `db_path` down to the given block, unwinding all
+/// blocks above it. A safe block above the new head is moved down to it. Returns the number of
+/// removed transactions.
+pub fn set_head(
+    db: &Env<WriteMap>,
+    deposits: &DepositStore,
+    db_path: &Path,
+    number: u64,
+) -> Result<u64> {
+    let Some(head) = head(db)? else { eyre::bail!("No canonical blocks found in the database") };
+    if number > head.number {
+        eyre::bail!("Block {number} lies above the head {}, import it instead", head.number);
+    }
+    let mut pointers = ForkchoicePointers::read(db_path)?;
+    if let Some(finalized) = pointers.finalized.filter(|finalized| finalized.number > number) {
+        eyre::bail!(
+            "Block {number} lies below the finalized block {}, lower it with --set-finalized first",
+            finalized.number
+        );
+    }
+    if number == head.number {
+        return Ok(0)
+    }
+
+    let new_head = canonical_block(db, number)?;
+    let tx = db.tx_mut()?;
+    let removed = blocks::unwind_blocks(&tx, Some(deposits), number + 1..=head.number)?;
+    tx.commit()?;
+    tracing::info!(target: "reth::cli", from = number + 1, to = head.number, transactions = removed, "Unwound blocks above the new head");
+
+    if pointers.safe.map_or(false, |safe| safe.number > number) {
+        tracing::warn!(target: "reth::cli", number, "Moved the safe block down to the new head");
+        pointers.safe = Some(new_head);
+        pointers.write(db_path)?;
+    }
+    Ok(removed)
+}
+
+/// Formats a pointer for display, marking pointers to blocks that are no longer canonical
+fn describe(db: &Env<WriteMap>, pointer: Option<BlockPointer>) -> Result<String> {
+    let Some(pointer) = pointer else { return Ok("none".to_string()) };
+    let canonical = db.view(|tx| tx.get::<tables::CanonicalHeaders>(pointer.number))??;
+    let suffix = if canonical == Some(pointer.hash) { "" } else { " (not canonical)" };
+    Ok(format!("{} {:?}{suffix}", pointer.number, pointer.hash))
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db_path = self.db.path();
+        let writes =
+            self.set_head.is_some() || self.set_safe.is_some() || self.set_finalized.is_some();
+        // Only moving the pointers writes the database, showing them works next to a running node
+        let (_lock, db) = if writes {
+            (Some(db::lock_db(&db_path)?), self.db.open_rw()?)
+        } else {
+            (None, self.db.open_ro()?)
+        };
+
+        if let Some(number) = self.set_head {
+            let head = head(&db)?.map_or(0, |head| head.number);
+            if number < head && !self.force {
+                eyre::bail!(
+                    "Moving the head to block {number} unwinds the {} blocks above it, pass \
+                     --force to proceed",
+                    head - number
+                );
+            }
+            let deposits = DepositStore::open(&self.db.static_path()?)?;
+            set_head(&db, &deposits, &db_path, number)?;
+        }
+
+        if self.set_safe.is_some() || self.set_finalized.is_some() {
+            let mut pointers = ForkchoicePointers::read(&db_path)?;
+            if let Some(number) = self.set_safe {
+                pointers.safe = Some(canonical_block(&db, number)?);
+            }
+            if let Some(number) = self.set_finalized {
+                pointers.finalized = Some(canonical_block(&db, number)?);
+            }
+            pointers.check(&db)?;
+            pointers.write(&db_path)?;
+        }
+
+        let pointers = ForkchoicePointers::read(&db_path)?;
+        println!("Head:       {}", describe(&db, head(&db)?)?);
+        println!("Safe:       {}", describe(&db, pointers.safe)?);
+        println!("Finalized:  {}", describe(&db, pointers.finalized)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    ops::RangeInclusive,
+    path::PathBuf,
+};
+
+use clap::Parser;
+use eyre::Result;
+use reth::runner::CliContext;
+use reth_db::{database::Database, tables, transaction::DbTx};
+use reth_primitives::{Address, Log, H256, U256};
+
+use crate::cli::{
+    analytics,
+    args::DatabaseArgs,
+    db::prune::{PruneCheckpoints, PrunedData},
+    deposit::DepositStore,
+    rpc::types::RpcLog,
+};
+
+/// Query the logs of the imported receipts.
+///
+/// Prints the matching logs as JSON lines in the layout of `eth_getLogs`, with their block,
+/// transaction and their index within the block, so log-based indexers can run against the
+/// migrated database.
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The first block to query
+    #[arg(long, value_name = "BLOCK", default_value_t = 0, verbatim_doc_comment)]
+    from_block: u64,
+
+    /// The last block to query. Defaults to the canonical tip.
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment)]
+    to_block: Option<u64>,
+
+    /// Only logs emitted by this contract. Repeat to match any of several contracts.
+    #[arg(long = "address", value_name = "ADDRESS", verbatim_doc_comment)]
+    addresses: Vec<Address>,
+
+    /// Only logs with this first topic, the event signature. Repeat to match any of several.
+    #[arg(long = "topic0", value_name = "TOPIC", verbatim_doc_comment)]
+    topic0: Vec<H256>,
+
+    /// Only logs with this second topic. Repeat to match any of several.
+    #[arg(long = "topic1", value_name = "TOPIC", verbatim_doc_comment)]
+    topic1: Vec<H256>,
+
+    /// Only logs with this third topic. Repeat to match any of several.
+    #[arg(long = "topic2", value_name = "TOPIC", verbatim_doc_comment)]
+    topic2: Vec<H256>,
+
+    /// Only logs with this fourth topic. Repeat to match any of several.
+    #[arg(long = "topic3", value_name = "TOPIC", verbatim_doc_comment)]
+    topic3: Vec<H256>,
+
+    /// Write the logs to this file instead of stdout
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    output: Option<PathBuf>,
+}
+
+/// Selects logs like the filter of `eth_getLogs`. Empty lists match anything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    /// The contracts that may have emitted the log
+    pub addresses: Vec<Address>,
+    /// The values each topic position may have
+    pub topics: [Vec<H256>; 4],
+}
+
+impl LogFilter {
+    /// Whether the log matches the filter
+    pub fn matches(&self, log: &Log) -> bool {
+        if !self.addresses.is_empty() && !self.addresses.contains(&log.address) {
+            return false
+        }
+        self.topics.iter().enumerate().all(|(position, topics)| {
+            topics.is_empty() ||
+                log.topics.get(position).map_or(false, |topic| topics.contains(topic))
+        })
+    }
+}
+
+/// Visits the logs of the canonical blocks in `range` that match the filter, in the order they
+/// were emitted, and returns the number of visited logs.
+///
+/// Log indices count all logs of a block, including the ones not matching the filter. Blocks
+/// without imported receipts have no logs. Logs of deposits carry the hash of the deposit in
+/// `deposits`.
+pub fn query_logs<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    deposits: &DepositStore,
+    range: RangeInclusive<u64>,
+    filter: &LogFilter,
+    mut visit: impl FnMut(RpcLog) -> Result<()>,
+) -> Result<u64> {
+    let mut visited = 0;
+    for number in range {
+        let Some(block_hash) = tx.get::<tables::CanonicalHeaders>(number)? else { break };
+        let Some(body) = tx.get::<tables::BlockBodies>(number)? else { continue };
+        let mut log_index = 0;
+        for (index, tx_id) in (body.start_tx_id..body.start_tx_id + body.tx_count).enumerate() {
+            let Some(receipt) = tx.get::<tables::Receipts>(tx_id)? else { continue };
+            let mut transaction_hash = None;
+            for log in receipt.logs {
+                log_index += 1;
+                if !filter.matches(&log) {
+                    continue
+                }
+                let hash = match transaction_hash {
+                    Some(hash) => hash,
+                    None => {
+                        let transaction = deposits.read(tx, tx_id)?.ok_or_else(|| {
+                            eyre::eyre!("Transaction {tx_id} of block {number} not found")
+                        })?;
+                        *transaction_hash.insert(transaction.hash())
+                    }
+                };
+                visit(RpcLog {
+                    address: log.address,
+                    topics: log.topics,
+                    data: log.data,
+                    block_hash,
+                    block_number: U256::from(number),
+                    transaction_hash: hash,
+                    transaction_index: U256::from(index),
+                    log_index: U256::from(log_index - 1),
+                    removed: false,
+                })?;
+                visited += 1;
+            }
+        }
+    }
+    Ok(visited)
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db = self.db.open_ro()?;
+        let tip = analytics::canonical_tip(&db)?
+            .ok_or_else(|| eyre::eyre!("No canonical blocks found in the database"))?;
+        let to = self.to_block.unwrap_or(tip).min(tip);
+        PruneCheckpoints::read(&self.db.path())?
+            .ensure_available(PrunedData::Receipts, &(self.from_block..=to))?;
+        let filter = LogFilter {
+            addresses: self.addresses,
+            topics: [self.topic0, self.topic1, self.topic2, self.topic3],
+        };
+        let mut out: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(BufWriter::new(io::stdout().lock())),
+        };
+        let deposits = DepositStore::open(&self.db.static_path()?)?;
+        let logs = query_logs(&db.tx()?, &deposits, self.from_block..=to, &filter, |log| {
+            serde_json::to_writer(&mut out, &log)?;
+            writeln!(out)?;
+            Ok(())
+        })?;
+        out.flush()?;
+        tracing::info!(target: "reth::cli", from = self.from_block, to, logs, "Logs queried");
+        Ok(())
+    }
+}
@@ -0,0 +1,350 @@
+use std::{collections::BTreeMap, fmt, fs, ops::RangeInclusive, path::Path};
+
+use clap::Parser;
+use eyre::Result;
+use indicatif::HumanBytes;
+use reth::runner::CliContext;
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    mdbx::{Env, WriteMap},
+    table::Table,
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::{BlockNumber, TxNumber};
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{args::DatabaseArgs, db::stats, journal, l1_fee::L1FeeStore};
+
+/// The file below a database path recording the heights the history was pruned below
+pub const PRUNE_FILE: &str = "prune-checkpoints.json";
+
+/// The default number of table entries visited per database transaction
+pub const DEFAULT_PRUNE_BATCH_SIZE: usize = 100_000;
+
+/// Drop historical data below the given heights, keeping the headers and the state.
+///
+/// Each flag prunes one kind of data of the canonical blocks below the given block number. The
+/// block bodies keep their transaction ranges, so transaction numbers stay valid. The heights are
+/// recorded in `prune-checkpoints.json` next to the database, and the space freed in the pruned
+/// tables is reported. MDBX reuses freed pages for new data instead of shrinking the file.
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// Drop the receipts and L1 fee fields of the transactions of blocks below this block
+    #[arg(long = "receipts.before", value_name = "BLOCK", verbatim_doc_comment)]
+    receipts_before: Option<BlockNumber>,
+
+    /// Drop the transactions, senders and ommers of blocks below this block. The transaction
+    /// lookup entries of the dropped transactions are dropped as well.
+    #[arg(long = "bodies.before", value_name = "BLOCK", verbatim_doc_comment)]
+    bodies_before: Option<BlockNumber>,
+
+    /// Drop the transaction hash lookup entries of the transactions of blocks below this block
+    #[arg(long = "tx-lookup.before", value_name = "BLOCK", verbatim_doc_comment)]
+    tx_lookup_before: Option<BlockNumber>,
+
+    /// Count the entries that would be dropped without dropping them
+    #[arg(long, verbatim_doc_comment)]
+    dry_run: bool,
+}
+
+/// The heights below which data was pruned, as recorded next to the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PruneCheckpoints {
+    /// Receipts are pruned below this block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receipts: Option<BlockNumber>,
+    /// Transactions, senders and ommers are pruned below this block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bodies: Option<BlockNumber>,
+    /// Transaction lookup entries are pruned below this block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_lookup: Option<BlockNumber>,
+}
+
+impl PruneCheckpoints {
+    /// Reads the checkpoints recorded next to the database at `db_path`
+    pub fn read(db_path: &Path) -> Result<Self> {
+        match fs::read(db_path.join(PRUNE_FILE)) {
+            Ok(data) => Ok(serde_json::from_slice(&data)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the checkpoints next to the database at `db_path`
+    pub fn write(&self, db_path: &Path) -> Result<()> {
+        fs::write(db_path.join(PRUNE_FILE), serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The block below which the given data was pruned, if it was. Pruning the bodies drops the
+    /// transaction lookup entries of their transactions as well.
+    pub fn pruned_below(&self, data: PrunedData) -> Option<BlockNumber> {
+        match data {
+            PrunedData::Receipts => self.receipts,
+            PrunedData::Bodies => self.bodies,
+            PrunedData::TxLookup => self.tx_lookup.max(self.bodies),
+        }
+    }
+
+    /// Fails if the given data of any block of `range` was pruned, so that readers refuse pruned
+    /// blocks instead of taking their dropped data for data that was never imported
+    pub fn ensure_available(
+        &self,
+        data: PrunedData,
+        range: &RangeInclusive<BlockNumber>,
+    ) -> Result<()> {
+        match self.pruned_below(data) {
+            Some(below) if *range.start() < below && !range.is_empty() => eyre::bail!(
+                "The {data} of the blocks below {below} were pruned, block {} is not available",
+                range.start()
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Raises the checkpoints to the heights of the given targets
+    pub fn merge(&mut self, targets: &PruneTargets) {
+        let raise = |current: Option<u64>, target: Option<u64>| match (current, target) {
+            (Some(current), Some(target)) => Some(current.max(target)),
+            (current, target) => current.or(target),
+        };
+        self.receipts = raise(self.receipts, targets.receipts);
+        self.bodies = raise(self.bodies, targets.bodies);
+        self.tx_lookup = raise(self.tx_lookup, targets.tx_lookup);
+    }
+}
+
+/// The kinds of data of a block that can be pruned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrunedData {
+    /// The receipts and L1 fee fields of the transactions of a block
+    Receipts,
+    /// The transactions, senders and ommers of a block
+    Bodies,
+    /// The transaction hash lookup entries of the transactions of a block
+    TxLookup,
+}
+
+impl fmt::Display for PrunedData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrunedData::Receipts => write!(f, "receipts"),
+            PrunedData::Bodies => write!(f, "transactions"),
+            PrunedData::TxLookup => write!(f, "transaction lookup entries"),
+        }
+    }
+}
+
+/// The heights to prune below
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PruneTargets {
+    /// Prune receipts below this block
+    pub receipts: Option<BlockNumber>,
+    /// Prune transactions, senders and ommers below this block
+    pub bodies: Option<BlockNumber>,
+    /// Prune transaction lookup entries below this block
+    pub tx_lookup: Option<BlockNumber>,
+}
+
+impl PruneTargets {
+    /// Returns true if no data is selected for pruning
+    pub fn is_empty(&self) -> bool {
+        self.receipts.is_none() && self.bodies.is_none() && self.tx_lookup.is_none()
+    }
+}
+
+/// The outcome of pruning a database
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PruneReport {
+    /// The number of dropped entries per table
+    pub removed: BTreeMap<&'static str, u64>,
+    /// The number of dropped L1 fee entries
+    pub l1_fees: u64,
+    /// The size in bytes the pages of the pruned tables shrank by
+    pub reclaimed: u64,
+}
+
+/// What to do with an entry visited by [prune_table]
+enum Visit {
+    Delete,
+    Keep,
+    Stop,
+}
+
+/// Deletes the entries of a table that `visit` selects, visiting `batch_size` entries per
+/// database transaction. Returns the number of deleted entries, or of the entries that would be
+/// deleted on a dry run.
+fn prune_table<T: Table>(
+    db: &Env<WriteMap>,
+    batch_size: usize,
+    dry_run: bool,
+    mut visit: impl FnMut(&T::Key, &T::Value) -> Visit,
+) -> Result<u64>
+where
+    T::Key: Clone,
+{
+    let mut removed = 0;
+    let mut resume: Option<T::Key> = None;
+    loop {
+        let tx = db.tx_mut()?;
+        let mut keys = Vec::new();
+        let mut next = None;
+        {
+            let mut cursor = tx.cursor_read::<T>()?;
+            let mut entry = match resume.take() {
+                Some(key) => cursor.seek(key)?,
+                None => cursor.first()?,
+            };
+            let mut visited = 0;
+            while let Some((key, value)) = entry {
+                if visited == batch_size.max(1) {
+                    next = Some(key);
+                    break
+                }
+                visited += 1;
+                match visit(&key, &value) {
+                    Visit::Delete => keys.push(key),
+                    Visit::Keep => {}
+                    Visit::Stop => break,
+                }
+                entry = cursor.next()?;
+            }
+        }
+        removed += keys.len() as u64;
+        if !dry_run {
+            for key in keys {
+                tx.delete::<T>(key, None)?;
+            }
+            tx.commit()?;
+        }
+        match next {
+            Some(key) => resume = Some(key),
+            None => return Ok(removed),
+        }
+    }
+}
+
+/// Selects the entries of a table keyed by transaction number below `boundary`
+fn below<V>(boundary: TxNumber) -> impl FnMut(&TxNumber, &V) -> Visit {
+    move |id, _| if *id < boundary { Visit::Delete } else { Visit::Stop }
+}
+
+/// The number of the first transaction of the first block at or above `block`, or the number
+/// following the last transaction if there is no such block
+fn first_tx_at<'a, TX: DbTx<'a>>(tx: &TX, block: BlockNumber) -> Result<TxNumber> {
+    let mut bodies = tx.cursor_read::<tables::BlockBodies>()?;
+    if let Some((_, body)) = bodies.seek(block)? {
+        return Ok(body.start_tx_id)
+    }
+    Ok(bodies.last()?.map_or(0, |(_, body)| body.start_tx_id + body.tx_count))
+}
+
+/// Prunes the database below the given heights. The L1 fee fields of pruned receipts are
+/// dropped from `fees` if given.
+pub fn prune(
+    db: &Env<WriteMap>,
+    fees: Option<&L1FeeStore>,
+    targets: &PruneTargets,
+    batch_size: usize,
+    dry_run: bool,
+) -> Result<PruneReport> {
+    let before = stats::collect(db)?;
+    let mut report = PruneReport::default();
+    let first_tx = |block| db.view(|tx| first_tx_at(tx, block))?;
+
+    // Lookup entries have to go first, while the transactions of their blocks exist
+    let lookup_before = match (targets.tx_lookup, targets.bodies) {
+        (Some(lookup), Some(bodies)) => Some(lookup.max(bodies)),
+        (lookup, bodies) => lookup.or(bodies),
+    };
+    if let Some(block) = lookup_before {
+        let boundary = first_tx(block)?;
+        let removed = prune_table::<tables::TxHashNumber>(db, batch_size, dry_run, |_, id| {
+            if *id < boundary {
+                Visit::Delete
+            } else {
+                Visit::Keep
+            }
+        })?;
+        report.removed.insert(tables::TxHashNumber::NAME, removed);
+    }
+    if let Some(block) = targets.receipts {
+        let boundary = first_tx(block)?;
+        let removed = prune_table::<tables::Receipts>(db, batch_size, dry_run, below(boundary))?;
+        report.removed.insert(tables::Receipts::NAME, removed);
+        if let (Some(fees), false) = (fees, dry_run) {
+            report.l1_fees = fees.remove_before(boundary)?;
+        }
+    }
+    if let Some(block) = targets.bodies {
+        let boundary = first_tx(block)?;
+        let removed =
+            prune_table::<tables::Transactions>(db, batch_size, dry_run, below(boundary))?;
+        report.removed.insert(tables::Transactions::NAME, removed);
+        let removed = prune_table::<tables::TxSenders>(db, batch_size, dry_run, below(boundary))?;
+        report.removed.insert(tables::TxSenders::NAME, removed);
+        let removed = prune_table::<tables::BlockOmmers>(db, batch_size, dry_run, |number, _| {
+            if *number < block {
+                Visit::Delete
+            } else {
+                Visit::Stop
+            }
+        })?;
+        report.removed.insert(tables::BlockOmmers::NAME, removed);
+    }
+
+    let after = stats::collect(db)?;
+    report.reclaimed = before
+        .tables
+        .iter()
+        .zip(&after.tables)
+        .filter(|(table, _)| report.removed.contains_key(table.name))
+        .map(|(before, after)| before.size.saturating_sub(after.size) as u64)
+        .sum();
+    Ok(report)
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let targets = PruneTargets {
+            receipts: self.receipts_before,
+            bodies: self.bodies_before,
+            tx_lookup: self.tx_lookup_before,
+        };
+        if targets.is_empty() {
+            eyre::bail!(
+                "Nothing to prune, pass --receipts.before, --bodies.before or --tx-lookup.before"
+            );
+        }
+        let db_path = self.db.path();
+        journal::record(&db_path, "db prune", &[], async {
+            let db = self.db.open_rw()?;
+            let fees = L1FeeStore::open(&self.db.static_path()?)?;
+            tracing::info!(target: "reth::cli", ?targets, dry_run = self.dry_run, "Pruning history");
+            let report =
+                prune(&db, Some(&fees), &targets, DEFAULT_PRUNE_BATCH_SIZE, self.dry_run)?;
+            for (table, removed) in &report.removed {
+                println!("{table:<20} {removed:>14}");
+            }
+            if self.dry_run {
+                println!("Dry run, nothing was dropped");
+                return Ok(())
+            }
+            println!("{:<20} {:>14}", "L1 fees", report.l1_fees);
+            println!("Reclaimed:           {}", HumanBytes(report.reclaimed));
+            let mut checkpoints = PruneCheckpoints::read(&db_path)?;
+            checkpoints.merge(&targets);
+            checkpoints.write(&db_path)?;
+            Ok(())
+        })
+        .await
+    }
+}
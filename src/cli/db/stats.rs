@@ -0,0 +1,123 @@
+use clap::Parser;
+use eyre::{Result, WrapErr};
+use indicatif::HumanBytes;
+use reth::runner::CliContext;
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTx,
+};
+use reth_primitives::{BlockNumber, TxNumber};
+
+use crate::cli::{args::DatabaseArgs, db::prune::PruneCheckpoints, regenesis::RegenesisBoundaries};
+
+/// Report table sizes and the chain tip of the migrated database
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+}
+
+/// The entry count and page usage of a single table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableStats {
+    /// The name of the table
+    pub name: &'static str,
+    /// The number of entries in the table
+    pub entries: usize,
+    /// The number of branch, leaf and overflow pages used by the table
+    pub pages: usize,
+    /// The size of the pages used by the table in bytes
+    pub size: usize,
+}
+
+/// Statistics of a migrated database environment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseStats {
+    /// The statistics of every reth table
+    pub tables: Vec<TableStats>,
+    /// The highest canonical block number, if any block was imported
+    pub tip: Option<BlockNumber>,
+    /// The highest transaction number, if any transaction was imported
+    pub last_tx: Option<TxNumber>,
+}
+
+impl DatabaseStats {
+    /// The combined size of all tables in bytes
+    pub fn total_size(&self) -> usize {
+        self.tables.iter().map(|table| table.size).sum()
+    }
+}
+
+/// Collects the statistics of the given database
+pub fn collect(db: &Env<WriteMap>) -> Result<DatabaseStats> {
+    let stats = db.view(|tx| -> Result<DatabaseStats> {
+        let mut stats = Vec::with_capacity(tables::TABLES.len());
+        for (_, name) in tables::TABLES.iter() {
+            let table = tx.inner.open_db(Some(name)).wrap_err("Could not open db.")?;
+            let stat =
+                tx.inner.db_stat(&table).wrap_err(format!("Could not find table: {name}"))?;
+            let pages = stat.branch_pages() + stat.leaf_pages() + stat.overflow_pages();
+            stats.push(TableStats {
+                name,
+                entries: stat.entries(),
+                pages,
+                size: pages * stat.page_size() as usize,
+            });
+        }
+
+        let tip = tx.cursor_read::<tables::CanonicalHeaders>()?.last()?.map(|(number, _)| number);
+        let last_tx = tx.cursor_read::<tables::Transactions>()?.last()?.map(|(id, _)| id);
+        Ok(DatabaseStats { tables: stats, tip, last_tx })
+    })??;
+    Ok(stats)
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db = self.db.open_ro()?;
+        let stats = collect(&db)?;
+
+        println!("{:<28} {:>14} {:>12} {:>12}", "Table", "Entries", "Pages", "Size");
+        for table in &stats.tables {
+            println!(
+                "{:<28} {:>14} {:>12} {:>12}",
+                table.name,
+                table.entries,
+                table.pages,
+                HumanBytes(table.size as u64).to_string()
+            );
+        }
+        println!();
+        println!("Total size:     {}", HumanBytes(stats.total_size() as u64));
+        match stats.tip {
+            Some(tip) => println!("Highest block:  {tip}"),
+            None => println!("Highest block:  none"),
+        }
+        match stats.last_tx {
+            Some(id) => println!("Highest tx:     {id}"),
+            None => println!("Highest tx:     none"),
+        }
+        let pruned = PruneCheckpoints::read(&self.db.path())?;
+        let checkpoints = [
+            ("Receipts", pruned.receipts),
+            ("Bodies", pruned.bodies),
+            ("Tx lookup", pruned.tx_lookup),
+        ];
+        for (data, block) in checkpoints {
+            if let Some(block) = block {
+                println!("{data} pruned below block {block}");
+            }
+        }
+        for boundary in RegenesisBoundaries::new(&self.db.path()).read()? {
+            println!(
+                "Segment {:<7} from block {} (anchor {:?})",
+                boundary.segment, boundary.first_block, boundary.anchor_hash
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::report;
+
+/// The file below the chain directory collecting the records skipped by a `--lenient` import
+/// without `--dead-letter`
+pub const ERROR_REPORT_FILE: &str = "import-errors.jsonl";
+
+/// A record of an export that failed to decode, located by its index and, where known, its byte
+/// offset in the export
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportError {
+    /// The kind of record, e.g. `block` or `receipt`
+    pub kind: &'static str,
+    /// The index of the record within its export
+    pub index: usize,
+    /// The byte offset of the record within its export
+    pub offset: Option<u64>,
+    /// The decoding error
+    pub error: String,
+}
+
+impl ImportError {
+    /// Creates the error of the record with the given index failing to decode
+    pub fn new(kind: &'static str, index: usize, error: impl fmt::Display) -> Self {
+        Self { kind, index, offset: None, error: error.to_string() }
+    }
+
+    /// Sets the byte offset of the record within its export
+    pub fn at_offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset as u64);
+        self
+    }
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to decode {} {}", self.kind, self.index)?;
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {offset}")?;
+        }
+        write!(f, ": {}. Pass --lenient to skip it and continue.", self.error)
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Handles a record that failed to decode. Lenient imports, which have a dead-letter file, write
+/// the record to it and continue, strict imports fail with the [ImportError].
+pub fn reject(
+    dead_letter: Option<&mut DeadLetterFile>,
+    error: ImportError,
+    raw: &[u8],
+) -> Result<()> {
+    match dead_letter {
+        Some(dead_letter) => dead_letter.write(&error, raw),
+        None => Err(error.into()),
+    }
+}
+
+/// A record that failed to convert during an import
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// The kind of record, e.g. `block` or `receipt`
+    pub kind: String,
+    /// The index of the record within its export
+    pub index: usize,
+    /// The byte offset of the record within its export, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    /// The conversion error
+    pub error: String,
+    /// The hex-encoded raw bytes of the record
+    pub raw: String,
+}
+
+impl DeadLetter {
+    /// Returns the raw bytes of the record so it can be decoded again
+    pub fn raw_bytes(&self) -> Result<Vec<u8>> {
+        Ok(hex::decode(&self.raw)?)
+    }
+}
+
+/// A line-delimited JSON file collecting [DeadLetter]s, so that a single pathological record
+/// doesn't abort a long running import and can be re-processed later.
+#[derive(Debug)]
+pub struct DeadLetterFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    count: usize,
+}
+
+impl DeadLetterFile {
+    /// Creates a new dead-letter file at the given path, truncating any existing file
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let writer = BufWriter::new(File::create(&path)?);
+        Ok(Self { path, writer, count: 0 })
+    }
+
+    /// Appends a failed record to the file
+    pub fn write(&mut self, error: &ImportError, raw: &[u8]) -> Result<()> {
+        let letter = DeadLetter {
+            kind: error.kind.to_string(),
+            index: error.index,
+            offset: error.offset,
+            error: error.error.clone(),
+            raw: hex::encode(raw),
+        };
+        serde_json::to_writer(&mut self.writer, &letter)?;
+        self.writer.write_all(b"\n")?;
+        self.count += 1;
+        tracing::warn!(target: "reth::cli", kind = error.kind, index = error.index, offset = ?error.offset, error = %error.error, "Record written to dead-letter file");
+        Ok(())
+    }
+
+    /// The number of records written to the file
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Flushes all pending records to disk
+    pub fn finish(mut self) -> Result<()> {
+        self.writer.flush()?;
+        report::record_rejected(self.count as u64);
+        if self.count > 0 {
+            tracing::warn!(target: "reth::cli", path = ?self.path, count = self.count, "Failed records written to dead-letter file");
+        }
+        Ok(())
+    }
+
+    /// Reads all records from a dead-letter file
+    pub fn read(path: impl AsRef<Path>) -> Result<Vec<DeadLetter>> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut letters = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue
+            }
+            letters.push(serde_json::from_str(&line)?);
+        }
+        Ok(letters)
+    }
+}
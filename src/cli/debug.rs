@@ -0,0 +1,387 @@
+use clap::{Parser, Subcommand};
+use eyre::Result;
+use reth::runner::CliContext;
+use reth_db::{
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTx,
+};
+use reth_executor::executor;
+use reth_primitives::{proofs, Address, Block, ChainSpec, Receipt, H256, U256};
+use reth_provider::{HistoricalStateProviderRef, LatestStateProviderRef, StateProvider};
+use reth_revm::database::{State, SubState};
+use serde::Serialize;
+
+use crate::cli::{
+    args::DatabaseArgs,
+    chain::OpChainSpec,
+    db::prune::{PruneCheckpoints, PrunedData},
+    replay,
+};
+
+/// Debug command
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(subcommand)]
+    command: Subcommands,
+}
+
+/// Debug subcommands
+#[derive(Debug, Subcommand)]
+pub enum Subcommands {
+    /// Re-execute an imported block and compare the result to its imported receipts
+    #[command(name = "replay-block")]
+    ReplayBlock(ReplayBlockCommand),
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, ctx: CliContext) -> Result<()> {
+        match self.command {
+            Subcommands::ReplayBlock(command) => command.execute(ctx).await,
+        }
+    }
+}
+
+/// Re-execute an imported legacy block on top of the state of its parent.
+///
+/// The block is executed with the hardforks of the chain spec, so legacy blocks run without a base
+/// fee like they did on l2geth. The status, gas and logs of every transaction are compared to
+/// the imported receipts, and the account and storage changes of every transaction are printed
+/// with `--state-diff`. Nothing is written to the database.
+///
+/// The state of the parent has to be available: either the plain state is the state after the
+/// parent, or the blocks were replayed with `replay` so that the history reaches back to it.
+/// The L1 data fee l2geth charged on top of the gas is not charged, so the balances of senders
+/// and the fee vault differ from the chain, and blocks of the OVM 1.0 era do not execute like
+/// they did on the chain at all.
+#[derive(Debug, Parser)]
+pub struct ReplayBlockCommand {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The number of the block to replay
+    #[arg(value_name = "BLOCK", verbatim_doc_comment)]
+    block: u64,
+
+    /// Print the account and storage changes of every transaction
+    #[arg(long, verbatim_doc_comment)]
+    state_diff: bool,
+
+    /// Print the replay as JSON
+    #[arg(long, verbatim_doc_comment)]
+    json: bool,
+}
+
+/// The outcome of a transaction as recorded in a receipt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiptSummary {
+    /// Whether the transaction succeeded
+    pub success: bool,
+    /// The gas used by the transaction alone
+    pub gas_used: u64,
+    /// The number of logs emitted by the transaction
+    pub logs: usize,
+}
+
+impl ReceiptSummary {
+    /// Summarizes a receipt following a receipt with the given cumulative gas
+    pub fn new(receipt: &Receipt, cumulative_gas_before: u64) -> Self {
+        Self {
+            success: receipt.success,
+            gas_used: receipt.cumulative_gas_used.saturating_sub(cumulative_gas_before),
+            logs: receipt.logs.len(),
+        }
+    }
+}
+
+/// The change of a storage slot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlotDiff {
+    /// The slot
+    pub key: U256,
+    /// The value before the transaction
+    pub before: U256,
+    /// The value after the transaction
+    pub after: U256,
+}
+
+/// The change of an account by a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDiff {
+    /// The address of the account
+    pub address: Address,
+    /// The change of the nonce, balance and code of the account
+    pub account: String,
+    /// The changed storage slots
+    pub storage: Vec<SlotDiff>,
+}
+
+/// The execution of a single transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxTrace {
+    /// The index of the transaction in its block
+    pub index: usize,
+    /// The hash of the transaction
+    pub hash: H256,
+    /// The sender of the transaction
+    pub sender: Address,
+    /// The recipient of the transaction, `None` for contract creations
+    pub to: Option<Address>,
+    /// The outcome of the re-execution
+    pub produced: ReceiptSummary,
+    /// The outcome recorded in the imported receipt, if it was imported
+    pub imported: Option<ReceiptSummary>,
+    /// The state changes of the re-execution
+    pub state: Vec<AccountDiff>,
+}
+
+impl TxTrace {
+    /// The fields in which the re-execution differs from the imported receipt
+    pub fn divergences(&self) -> Vec<String> {
+        let Some(imported) = self.imported else { return vec!["no imported receipt".to_string()] };
+        let mut divergences = Vec::new();
+        if self.produced.success != imported.success {
+            divergences.push(format!(
+                "status {} (imported {})",
+                status(self.produced.success),
+                status(imported.success)
+            ));
+        }
+        if self.produced.gas_used != imported.gas_used {
+            divergences
+                .push(format!("gas {} (imported {})", self.produced.gas_used, imported.gas_used));
+        }
+        if self.produced.logs != imported.logs {
+            divergences.push(format!("logs {} (imported {})", self.produced.logs, imported.logs));
+        }
+        divergences
+    }
+}
+
+/// The re-execution of a block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockReplay {
+    /// The number of the block
+    pub number: u64,
+    /// The hash of the block
+    pub hash: H256,
+    /// The gas used by the re-execution
+    pub gas_used: u64,
+    /// The gas used according to the header
+    pub header_gas_used: u64,
+    /// The receipts root of the receipts of the re-execution
+    pub receipts_root: H256,
+    /// The receipts root according to the header
+    pub header_receipts_root: H256,
+    /// The executed transactions
+    pub transactions: Vec<TxTrace>,
+}
+
+impl BlockReplay {
+    /// The number of transactions whose re-execution differs from their imported receipt
+    pub fn diverging(&self) -> usize {
+        self.transactions.iter().filter(|trace| !trace.divergences().is_empty()).count()
+    }
+
+    /// The first transaction whose re-execution differs from its imported receipt
+    pub fn first_divergence(&self) -> Option<&TxTrace> {
+        self.transactions.iter().find(|trace| !trace.divergences().is_empty())
+    }
+
+    /// Returns true if the re-execution matches the header and the imported receipts
+    pub fn matches(&self) -> bool {
+        self.gas_used == self.header_gas_used &&
+            self.receipts_root == self.header_receipts_root &&
+            self.first_divergence().is_none()
+    }
+}
+
+fn status(success: bool) -> &'static str {
+    if success {
+        "success"
+    } else {
+        "failed"
+    }
+}
+
+/// Re-executes the canonical block with the given number on top of the state of its parent and
+/// compares the outcome of its transactions to the imported receipts
+pub fn replay_block(db: &Env<WriteMap>, chain: &ChainSpec, number: u64) -> Result<BlockReplay> {
+    replay_block_in(&db.tx()?, chain, number, true)
+}
+
+/// Re-executes a block like [replay_block] within the given database transaction. The state
+/// changes of the transactions are only collected if `with_state` is set.
+pub fn replay_block_in<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    chain: &ChainSpec,
+    number: u64,
+    with_state: bool,
+) -> Result<BlockReplay> {
+    if number == 0 {
+        eyre::bail!("The genesis block has no transactions to replay");
+    }
+    let hash = tx
+        .get::<tables::CanonicalHeaders>(number)?
+        .ok_or_else(|| eyre::eyre!("Block {number} not found"))?;
+    let (block, td, senders) = replay::load_block(tx, number)?;
+    let executed = match replay::historical_transition(tx, number - 1)? {
+        None => execute(&block, td, &senders, chain, LatestStateProviderRef::new(tx), with_state)?,
+        Some(transition) => {
+            let provider = HistoricalStateProviderRef::new(tx, transition);
+            execute(&block, td, &senders, chain, provider, with_state)?
+        }
+    };
+    let receipts_root = proofs::calculate_receipt_root(executed.iter().map(|(receipt, _)| receipt));
+
+    let mut imported = Vec::with_capacity(block.body.len());
+    if let Some(body) = tx.get::<tables::BlockBodies>(number)? {
+        for tx_id in body.start_tx_id..body.start_tx_id + body.tx_count {
+            imported.push(tx.get::<tables::Receipts>(tx_id)?);
+        }
+    }
+
+    let mut transactions = Vec::with_capacity(block.body.len());
+    let (mut produced_gas, mut imported_gas) = (0, 0);
+    for (index, ((transaction, sender), (receipt, state))) in
+        block.body.iter().zip(&senders).zip(executed).enumerate()
+    {
+        let produced = ReceiptSummary::new(&receipt, produced_gas);
+        produced_gas = receipt.cumulative_gas_used;
+        let imported = imported.get(index).cloned().flatten().map(|receipt| {
+            let summary = ReceiptSummary::new(&receipt, imported_gas);
+            imported_gas = receipt.cumulative_gas_used;
+            summary
+        });
+        transactions.push(TxTrace {
+            index,
+            hash: transaction.hash(),
+            sender: *sender,
+            to: transaction.to(),
+            produced,
+            imported,
+            state,
+        });
+    }
+    Ok(BlockReplay {
+        number,
+        hash,
+        gas_used: produced_gas,
+        header_gas_used: block.header.gas_used,
+        receipts_root,
+        header_receipts_root: block.header.receipts_root,
+        transactions,
+    })
+}
+
+/// Executes a block on top of the given state without verifying its receipts root. Returns the
+/// receipt of every transaction, and its state changes if `with_state` is set.
+fn execute<DB: StateProvider>(
+    block: &Block,
+    td: U256,
+    senders: &[Address],
+    chain: &ChainSpec,
+    provider: DB,
+    with_state: bool,
+) -> Result<Vec<(Receipt, Vec<AccountDiff>)>> {
+    let mut state = SubState::new(State::new(provider));
+    let result = executor::execute(block, td, Some(senders.to_vec()), chain, &mut state)
+        .map_err(|err| eyre::eyre!("Block {} failed to execute: {err}", block.header.number))?;
+    Ok(result
+        .tx_changesets
+        .into_iter()
+        .map(|changeset| {
+            if !with_state {
+                return (changeset.receipt, vec![])
+            }
+            let state = changeset
+                .changeset
+                .into_iter()
+                .map(|(address, change)| AccountDiff {
+                    address,
+                    account: format!("{:?}", change.account),
+                    storage: change
+                        .storage
+                        .into_iter()
+                        .map(|(key, (before, after))| SlotDiff { key, before, after })
+                        .collect(),
+                })
+                .collect();
+            (changeset.receipt, state)
+        })
+        .collect())
+}
+
+impl ReplayBlockCommand {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db_path = self.db.path();
+        let Some(chain) = OpChainSpec::read(&db_path)? else {
+            eyre::bail!("No chain spec found at {}, import the genesis first", db_path.display())
+        };
+        // Pruned receipts would read like receipts that were never imported
+        let pruned = PruneCheckpoints::read(&db_path)?;
+        pruned.ensure_available(PrunedData::Bodies, &(self.block..=self.block))?;
+        pruned.ensure_available(PrunedData::Receipts, &(self.block..=self.block))?;
+        let db = self.db.open_ro()?;
+        let replay = replay_block(&db, &chain.inner, self.block)?;
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&replay)?);
+        } else {
+            println!("Block:     {} {:?}", replay.number, replay.hash);
+            println!("Gas used:  {} (header {})", replay.gas_used, replay.header_gas_used);
+            println!(
+                "Receipts:  {:?} (header {:?})",
+                replay.receipts_root, replay.header_receipts_root
+            );
+            for trace in &replay.transactions {
+                let divergences = trace.divergences();
+                let outcome = if divergences.is_empty() { "matches" } else { "DIVERGES" };
+                println!(
+                    "#{:<4} {:?} from {:?} to {:?}: {}, gas {}, {} logs, {outcome}",
+                    trace.index,
+                    trace.hash,
+                    trace.sender,
+                    trace.to,
+                    status(trace.produced.success),
+                    trace.produced.gas_used,
+                    trace.produced.logs
+                );
+                for divergence in divergences {
+                    println!("      {divergence}");
+                }
+                if self.state_diff {
+                    for account in &trace.state {
+                        println!("      {:?} {}", account.address, account.account);
+                        for slot in &account.storage {
+                            println!(
+                                "        0x{:x}: 0x{:x} -> 0x{:x}",
+                                slot.key, slot.before, slot.after
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        if !replay.matches() {
+            eyre::bail!(
+                "Block {} diverges from the import in {} transactions, it used {} gas where the \
+                 header records {}",
+                replay.number,
+                replay.diverging(),
+                replay.gas_used,
+                replay.header_gas_used
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use reth_primitives::{SealedBlock, H256};
+
+/// The default rate of false positives of the filters of a [Deduplicator]
+pub const DEFAULT_FALSE_POSITIVE_RATE: f64 = 1e-6;
+
+/// A bloom filter of hashes.
+///
+/// The hashes are keccak digests, so their bytes are uniformly distributed and the bit positions
+/// are derived from them directly by double hashing instead of hashing them again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    hashes: u32,
+}
+
+impl BloomFilter {
+    /// Creates a filter sized to hold `items` hashes with the given rate of false positives
+    pub fn new(items: usize, false_positive_rate: f64) -> Self {
+        let items = items.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-items * false_positive_rate.ln() / (ln2 * ln2)).ceil().max(64.0) as usize;
+        let hashes = ((bits as f64 / items) * ln2).round().clamp(1.0, 32.0) as u32;
+        Self { bits: vec![0; (bits + 63) / 64], hashes }
+    }
+
+    /// Adds the hash to the filter
+    pub fn insert(&mut self, hash: &H256) {
+        for bit in self.positions(hash) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    /// Returns false if the hash was never added, true if it probably was
+    pub fn contains(&self, hash: &H256) -> bool {
+        self.positions(hash).all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// The size of the filter in bytes
+    pub fn size(&self) -> usize {
+        self.bits.len() * 8
+    }
+
+    fn positions(&self, hash: &H256) -> impl Iterator<Item = usize> {
+        let word = |index: usize| {
+            u64::from_le_bytes(hash.0[index * 8..index * 8 + 8].try_into().expect("8 bytes"))
+        };
+        let (first, second) = (word(0), word(1) | 1);
+        let len = self.bits.len() as u64 * 64;
+        (0..self.hashes as u64)
+            .map(move |index| (first.wrapping_add(index.wrapping_mul(second)) % len) as usize)
+    }
+}
+
+/// Detects blocks and transactions that were read before, like the blocks shared by overlapping
+/// export chunks.
+///
+/// Every block and transaction is first looked up in a bloom filter and only compared against the
+/// blocks read before if the filter reports a hit, so the common case of a new block costs no
+/// search.
+#[derive(Debug, Clone)]
+pub struct Deduplicator {
+    blocks: BloomFilter,
+    transactions: BloomFilter,
+    /// The number of skipped duplicate blocks
+    pub skipped_blocks: u64,
+    /// The number of transactions removed from new blocks because an earlier block includes them
+    pub skipped_transactions: u64,
+}
+
+impl Deduplicator {
+    /// Creates a deduplicator sized for the expected number of blocks and transactions
+    pub fn new(blocks: usize, transactions: usize) -> Self {
+        Self {
+            blocks: BloomFilter::new(blocks, DEFAULT_FALSE_POSITIVE_RATE),
+            transactions: BloomFilter::new(transactions, DEFAULT_FALSE_POSITIVE_RATE),
+            skipped_blocks: 0,
+            skipped_transactions: 0,
+        }
+    }
+
+    /// Returns true if `read` holds the block already. `read` has to be ordered by block number.
+    pub fn is_duplicate(&mut self, read: &[SealedBlock], block: &SealedBlock) -> bool {
+        if !self.blocks.contains(&block.hash()) {
+            return false
+        }
+        let duplicate = read
+            .binary_search_by_key(&block.number, |read| read.number)
+            .map_or(false, |index| read[index].hash() == block.hash());
+        if duplicate {
+            tracing::debug!(target: "reth::cli", number = block.number, "Skipping duplicate block");
+            self.skipped_blocks += 1;
+        }
+        duplicate
+    }
+
+    /// Removes the transactions of the block that a block in `read` includes already, searching
+    /// the most recent blocks first. Returns the number of removed transactions.
+    pub fn remove_duplicate_transactions(
+        &mut self,
+        read: &[SealedBlock],
+        block: &mut SealedBlock,
+    ) -> usize {
+        let (number, before) = (block.number, block.body.len());
+        let filter = &self.transactions;
+        block.body.retain(|transaction| {
+            let hash = transaction.hash();
+            if !filter.contains(&hash) {
+                return true
+            }
+            let includes = |read: &&SealedBlock| read.body.iter().any(|tx| tx.hash() == hash);
+            let Some(earlier) = read.iter().rev().find(includes) else { return true };
+            tracing::warn!(target: "reth::cli", ?hash, block = number, earlier = earlier.number, "Skipping transaction included in an earlier block");
+            false
+        });
+        let removed = before - block.body.len();
+        self.skipped_transactions += removed as u64;
+        removed
+    }
+
+    /// Records the block and its transactions as read
+    pub fn insert(&mut self, block: &SealedBlock) {
+        self.blocks.insert(&block.hash());
+        for transaction in &block.body {
+            self.transactions.insert(&transaction.hash());
+        }
+    }
+}
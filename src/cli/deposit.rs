@@ -0,0 +1,361 @@
+use std::{borrow::Cow, fs, path::Path};
+
+use bytes::BufMut;
+use eyre::Result;
+use reth_db::{
+    mdbx::{DatabaseFlags, Env, EnvKind, WriteFlags, WriteMap},
+    tables,
+    transaction::DbTx,
+};
+use reth_primitives::{
+    AccessList, AccessListItem, Address, Bytes, Signature, Transaction, TransactionKind,
+    TransactionSigned, TxEip1559, TxNumber, H256, U256,
+};
+use reth_rlp::{length_of_length, Decodable, DecodeError, Encodable, Header};
+
+use crate::cli::keccak::{self, Keccak};
+
+/// The typed transaction envelope byte of deposit transactions
+pub const DEPOSIT_TX_TYPE: u8 = 0x7E;
+
+/// The directory below the static data path of a database holding the [DepositStore]
+pub const DEPOSITS_DIR: &str = "deposits";
+
+/// The flag of the carrier of a deposit marking it as a system transaction
+const SYSTEM_TX_FLAG: u8 = 1;
+
+/// An OP deposit transaction, derived by op-node from the deposits made on L1 and the L1
+/// attributes of every bedrock block.
+///
+/// Deposits carry no signature: their sender is part of the transaction, and they may mint ETH to
+/// it. They are encoded as the typed envelope `0x7E || rlp([sourceHash, from, to, mint, value,
+/// gas, isSystemTx, data])`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TxDeposit {
+    /// Uniquely identifies the deposit by its origin on L1
+    pub source_hash: H256,
+    /// The sender of the deposit
+    pub from: Address,
+    /// The recipient of the deposit, or a contract creation
+    pub to: TransactionKind,
+    /// The ETH minted to the sender on L2, if any
+    pub mint: Option<u128>,
+    /// The ETH transferred to the recipient
+    pub value: u128,
+    /// The gas limit of the deposit
+    pub gas_limit: u64,
+    /// Whether the deposit is exempt from the L2 gas limit, like the L1 attributes deposit before
+    /// regolith
+    pub is_system_transaction: bool,
+    /// The call data of the deposit
+    pub input: Bytes,
+}
+
+impl TxDeposit {
+    fn fields_len(&self) -> usize {
+        self.source_hash.length() +
+            self.from.length() +
+            self.to.length() +
+            self.mint.unwrap_or_default().length() +
+            self.value.length() +
+            self.gas_limit.length() +
+            self.is_system_transaction.length() +
+            self.input.length()
+    }
+
+    /// Encodes the deposit as its typed envelope
+    pub fn encode_enveloped(&self, out: &mut dyn BufMut) {
+        out.put_u8(DEPOSIT_TX_TYPE);
+        self.encode(out);
+    }
+
+    /// The typed envelope of the deposit
+    pub fn envelope(&self) -> Vec<u8> {
+        let mut envelope = Vec::with_capacity(1 + self.length());
+        self.encode_enveloped(&mut envelope);
+        envelope
+    }
+
+    /// Decodes a deposit from its typed envelope
+    pub fn decode_enveloped(data: &[u8]) -> Result<Self> {
+        let Some((&DEPOSIT_TX_TYPE, mut payload)) = data.split_first() else {
+            eyre::bail!("Not a deposit transaction envelope")
+        };
+        let deposit = Self::decode(&mut payload)
+            .map_err(|err| eyre::eyre!("Invalid deposit transaction: {err:?}"))?;
+        if !payload.is_empty() {
+            eyre::bail!("Invalid deposit transaction: {} trailing bytes", payload.len());
+        }
+        Ok(deposit)
+    }
+
+    /// The hash of the deposit, the keccak-256 hash of its typed envelope
+    pub fn hash(&self) -> H256 {
+        keccak::keccak256(self.envelope())
+    }
+
+    /// Converts the deposit into the transaction carrying it through reth's block and pool types.
+    ///
+    /// reth's transaction types have no place for deposits, so they are carried as an unsigned
+    /// EIP-1559 transaction for chain id 0 without fees. Its access list holds the fields the
+    /// EIP-1559 transaction lacks: the sender, keyed by the source hash, the minted amount and the
+    /// system transaction flag. The carrier keeps the hash of the deposit.
+    ///
+    /// Carriers only live in memory. The database holds the [TxDeposit::stand_in] of a deposit,
+    /// and the deposit itself is kept in the [DepositStore].
+    pub fn into_carrier(self) -> TransactionSigned {
+        let mut flags = H256::zero();
+        if self.is_system_transaction {
+            flags.0[31] = SYSTEM_TX_FLAG;
+        }
+        let mint = H256(U256::from(self.mint.unwrap_or_default()).to_be_bytes());
+        let access_list = AccessList(vec![AccessListItem {
+            address: self.from,
+            storage_keys: vec![self.source_hash, mint, flags],
+        }]);
+        self.unsigned(access_list)
+    }
+
+    /// The transaction the deposit is stored as in the `Transactions` table: an unsigned EIP-1559
+    /// transaction for chain id 0 without fees and without an access list, holding only the
+    /// recipient, value, gas limit and input of the deposit. The stand-in keeps the hash of the
+    /// deposit.
+    pub fn stand_in(&self) -> TransactionSigned {
+        self.clone().unsigned(AccessList::default())
+    }
+
+    /// The unsigned EIP-1559 transaction with the given access list standing for the deposit
+    fn unsigned(self, access_list: AccessList) -> TransactionSigned {
+        let hash = self.hash();
+        let transaction = Transaction::Eip1559(TxEip1559 {
+            chain_id: 0,
+            nonce: 0,
+            gas_limit: self.gas_limit,
+            max_fee_per_gas: 0,
+            max_priority_fee_per_gas: 0,
+            to: self.to,
+            value: self.value,
+            input: self.input,
+            access_list,
+        });
+        let signature = Signature { r: U256::ZERO, s: U256::ZERO, odd_y_parity: false };
+        let mut unsigned =
+            TransactionSigned::from_transaction_and_signature(transaction, signature);
+        unsigned.hash = hash;
+        unsigned
+    }
+
+    /// Recovers the deposit from the transaction carrying it, `None` if the transaction is not the
+    /// carrier of a deposit
+    pub fn from_carrier(transaction: &TransactionSigned) -> Option<Self> {
+        let Transaction::Eip1559(tx) = &transaction.transaction else { return None };
+        if tx.chain_id != 0 || transaction.signature.r != U256::ZERO {
+            return None
+        }
+        let [item] = tx.access_list.0.as_slice() else { return None };
+        let [source_hash, mint, flags] = item.storage_keys.as_slice() else { return None };
+        let mint = u128::try_from(U256::from_be_bytes(mint.0)).ok()?;
+        Some(Self {
+            source_hash: *source_hash,
+            from: item.address,
+            to: tx.to,
+            mint: Some(mint).filter(|mint| *mint != 0),
+            value: tx.value,
+            gas_limit: tx.gas_limit,
+            is_system_transaction: flags.0[31] & SYSTEM_TX_FLAG != 0,
+            input: tx.input.clone(),
+        })
+    }
+}
+
+/// RLP encoder for the payload of a [TxDeposit], without its envelope byte. An absent mint is
+/// encoded as zero.
+impl Encodable for TxDeposit {
+    fn encode(&self, out: &mut dyn BufMut) {
+        Header { list: true, payload_length: self.fields_len() }.encode(out);
+        self.source_hash.encode(out);
+        self.from.encode(out);
+        self.to.encode(out);
+        self.mint.unwrap_or_default().encode(out);
+        self.value.encode(out);
+        self.gas_limit.encode(out);
+        self.is_system_transaction.encode(out);
+        self.input.encode(out);
+    }
+
+    fn length(&self) -> usize {
+        let payload_length = self.fields_len();
+        payload_length + length_of_length(payload_length)
+    }
+}
+
+/// RLP decoder for the payload of a [TxDeposit]. A zero mint is decoded as no mint.
+impl Decodable for TxDeposit {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        let header = Header::decode(buf)?;
+        if !header.list {
+            return Err(DecodeError::UnexpectedString)
+        }
+        let remaining = buf.len();
+        let deposit = Self {
+            source_hash: Decodable::decode(buf)?,
+            from: Decodable::decode(buf)?,
+            to: Decodable::decode(buf)?,
+            mint: Some(u128::decode(buf)?).filter(|mint| *mint != 0),
+            value: Decodable::decode(buf)?,
+            gas_limit: Decodable::decode(buf)?,
+            is_system_transaction: Decodable::decode(buf)?,
+            input: Decodable::decode(buf)?,
+        };
+        let consumed = remaining - buf.len();
+        if consumed != header.payload_length {
+            return Err(DecodeError::ListLengthMismatch {
+                expected: header.payload_length,
+                got: consumed,
+            })
+        }
+        Ok(deposit)
+    }
+}
+
+/// Returns true if the transaction is the carrier of a deposit
+pub fn is_deposit(transaction: &TransactionSigned) -> bool {
+    TxDeposit::from_carrier(transaction).is_some()
+}
+
+/// The hash of the transaction, the hash of the deposit for the carrier of a deposit
+pub fn tx_hash(transaction: &TransactionSigned) -> H256 {
+    TxDeposit::from_carrier(transaction)
+        .map_or_else(|| transaction.hash(), |deposit| deposit.hash())
+}
+
+/// The typed envelope byte of the transaction, [DEPOSIT_TX_TYPE] for the carrier of a deposit
+pub fn tx_type(transaction: &TransactionSigned) -> u8 {
+    if is_deposit(transaction) {
+        return DEPOSIT_TX_TYPE
+    }
+    transaction.tx_type() as u8
+}
+
+/// Returns true if the transaction is the [TxDeposit::stand_in] of a deposit, as read from the
+/// `Transactions` table
+pub fn is_stand_in(transaction: &TransactionSigned) -> bool {
+    let Transaction::Eip1559(tx) = &transaction.transaction else { return false };
+    tx.chain_id == 0 && transaction.signature.r == U256::ZERO && tx.access_list.0.is_empty()
+}
+
+/// Decodes a transaction from its envelope, decoding deposits into their carrier
+pub fn decode_tx(data: &[u8]) -> Result<TransactionSigned> {
+    if data.first() == Some(&DEPOSIT_TX_TYPE) {
+        return Ok(TxDeposit::decode_enveloped(data)?.into_carrier())
+    }
+    TransactionSigned::decode_enveloped(Bytes::from(data.to_vec()))
+        .map_err(|err| eyre::eyre!("Invalid transaction envelope: {err:?}"))
+}
+
+/// Encodes a transaction as its envelope, the inverse of [decode_tx]
+pub fn encode_tx(transaction: &TransactionSigned) -> Vec<u8> {
+    if let Some(deposit) = TxDeposit::from_carrier(transaction) {
+        return deposit.envelope()
+    }
+    let mut envelope = Vec::new();
+    transaction.encode_enveloped(&mut envelope);
+    envelope
+}
+
+/// The transactions root of a block body that may hold deposits, computed over the envelopes of
+/// its transactions
+pub fn transactions_root(body: &[TransactionSigned]) -> H256 {
+    triehash::ordered_trie_root::<Keccak, _>(body.iter().map(encode_tx))
+}
+
+/// Extension store holding the deposit transactions of the imported blocks, keyed by transaction
+/// number.
+///
+/// reth's table set is fixed, so the `Transactions` table only holds the [TxDeposit::stand_in] of
+/// a deposit. The deposit itself lives in a separate MDBX environment below the static data path
+/// of the database, like the [L1FeeStore](crate::cli::l1_fee::L1FeeStore).
+///
+/// Deposits are stored before the transaction inserting their block is committed, and entries
+/// left behind by an unwound block or a failed insert are overwritten by the next deposit with the
+/// same number. The store is only consulted for stand-ins, so such entries are never read.
+#[derive(Debug)]
+pub struct DepositStore {
+    env: Env<WriteMap>,
+}
+
+impl DepositStore {
+    /// Opens the store below the given static data path of a database, creating it if necessary.
+    /// Unless configured otherwise, that is the database path itself.
+    pub fn open(static_path: &Path) -> Result<Self> {
+        let path = static_path.join(DEPOSITS_DIR);
+        fs::create_dir_all(&path)?;
+        let env = Env::<WriteMap>::open(&path, EnvKind::RW).map_err(|e| eyre::eyre!(e))?;
+
+        let tx = env.inner.begin_rw_txn()?;
+        tx.create_db(None, DatabaseFlags::default())?;
+        tx.commit()?;
+        Ok(Self { env })
+    }
+
+    /// Stores the given deposits in a single transaction. Returns the number of stored entries.
+    pub fn insert(
+        &self,
+        deposits: impl IntoIterator<Item = (TxNumber, TxDeposit)>,
+    ) -> Result<usize> {
+        let tx = self.env.inner.begin_rw_txn()?;
+        let db = tx.open_db(None)?;
+        let mut count = 0;
+        for (tx_id, deposit) in deposits {
+            let mut value = Vec::with_capacity(deposit.length());
+            deposit.encode(&mut value);
+            tx.put(&db, tx_id.to_be_bytes(), value, WriteFlags::UPSERT)?;
+            count += 1;
+        }
+        tx.commit()?;
+        Ok(count)
+    }
+
+    /// Returns the deposit stored for the given transaction
+    pub fn get(&self, tx_id: TxNumber) -> Result<Option<TxDeposit>> {
+        let tx = self.env.inner.begin_ro_txn()?;
+        let db = tx.open_db(None)?;
+        let Some(value) = tx.get::<Cow<'_, [u8]>>(&db, &tx_id.to_be_bytes())? else {
+            return Ok(None)
+        };
+        Ok(Some(TxDeposit::decode(&mut value.as_ref())?))
+    }
+
+    /// Restores the carrier of the deposit whose stand-in was read from the `Transactions` table
+    /// under the given number. Other transactions are returned unchanged.
+    ///
+    /// Fails if the store holds no deposit for a stand-in, or one that doesn't match it.
+    pub fn restore(
+        &self,
+        tx_id: TxNumber,
+        transaction: TransactionSigned,
+    ) -> Result<TransactionSigned> {
+        if !is_stand_in(&transaction) {
+            return Ok(transaction)
+        }
+        let Some(deposit) = self.get(tx_id)? else {
+            eyre::bail!("Transaction {tx_id} is a deposit, but the deposit store doesn't hold it")
+        };
+        if deposit.stand_in().transaction != transaction.transaction {
+            eyre::bail!("The deposit stored for transaction {tx_id} doesn't match the transaction");
+        }
+        Ok(deposit.into_carrier())
+    }
+
+    /// Reads the transaction with the given number from the database, restoring the carrier of
+    /// deposits
+    pub fn read<'a, TX: DbTx<'a>>(
+        &self,
+        tx: &TX,
+        tx_id: TxNumber,
+    ) -> Result<Option<TransactionSigned>> {
+        tx.get::<tables::Transactions>(tx_id)?
+            .map(|transaction| self.restore(tx_id, transaction))
+            .transpose()
+    }
+}
@@ -0,0 +1,306 @@
+use std::{
+    fs,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use clap::Parser;
+use eyre::{Context, Result};
+use futures::future;
+use reth::runner::CliContext;
+use reth_db::{
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTx,
+};
+use reth_primitives::{Address, U256};
+use reth_rpc::JwtSecret;
+use serde_json::json;
+
+use crate::cli::{
+    args::{DatabaseArgs, ImportArgs},
+    chain::OpChainSpec,
+    db::{self, head::ForkchoicePointers, prune::PruneCheckpoints},
+    deposit::DepositStore,
+    dirs, genesis,
+    l1_fee::L1FeeStore,
+    node::{
+        engine::{self, EngineApi},
+        payload::PayloadBuilder,
+        pool::TxPool,
+    },
+    rpc::{self, EthApi},
+    shutdown::{self, ShutdownPhase},
+    state,
+};
+
+pub mod driver;
+
+use driver::MockDriver;
+
+/// The default chain id of a devnet, the one of the OP Stack devnets
+pub const DEFAULT_CHAIN_ID: u64 = 901;
+
+/// The default balance of every dev account, in ether
+pub const DEFAULT_BALANCE: u64 = 10_000;
+
+/// The default number of seconds between the blocks of the mock driver
+pub const DEFAULT_BLOCK_TIME: u64 = 2;
+
+/// The file next to the database holding the genesis the devnet was created with
+pub const GENESIS_FILE: &str = "devnet-genesis.json";
+
+/// The directory below the data directory holding the devnet, unless a database is given
+const DEVNET_DIR: &str = "devnet";
+
+/// The gas limit of the dev genesis
+const GENESIS_GAS_LIMIT: u64 = 30_000_000;
+
+/// The base fee of the dev genesis, 1 gwei
+const GENESIS_BASE_FEE: u64 = 1_000_000_000;
+
+/// The development accounts of Hardhat and Anvil, derived from the public mnemonic
+/// `test test test test test test test test test test test junk`. Their keys are well known, so
+/// never fund them on a public network.
+pub const DEV_ACCOUNTS: [&str; 10] = [
+    "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+    "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+    "0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC",
+    "0x90F79bf6EB2c4f870365E785982E1f101E93b906",
+    "0x15d34AAf54267DB7D7c367839AAf71A00a2C6A65",
+    "0x9965507D1a55bcC2695C58ba16FB37d819B0A4dc",
+    "0x976EA74026E726554dB657fA54763abd0C3a0aa9",
+    "0x14dC79964da2C08b23698B3D3cc7Ca32193d9955",
+    "0x23618e81E3f5cdF7f54C3d65f7FBc0aBf5B21E8f",
+    "0xa0Ee7A142d267C1f36714E4a8F75612F20a79720",
+];
+
+/// Run a local devnet: create a dev chain and serve the Engine API and JSON-RPC on it
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The chain id of the devnet
+    #[arg(long = "dev.chain-id", value_name = "CHAIN_ID", default_value_t = DEFAULT_CHAIN_ID)]
+    chain_id: u64,
+
+    /// The balance every dev account is funded with in the genesis, in ether
+    #[arg(long = "dev.balance", value_name = "ETHER", default_value_t = DEFAULT_BALANCE)]
+    balance: u64,
+
+    /// Delete the devnet database and start over from a new genesis
+    #[arg(long, verbatim_doc_comment)]
+    reset: bool,
+
+    /// Produce empty blocks through the Engine API, standing in for op-node
+    #[arg(long = "dev.mock-driver", verbatim_doc_comment)]
+    mock_driver: bool,
+
+    /// The number of seconds between the blocks of the mock driver
+    #[arg(long = "dev.block-time", value_name = "SECONDS", default_value_t = DEFAULT_BLOCK_TIME)]
+    block_time: u64,
+
+    /// The local port the JSON-RPC server listens on
+    #[arg(long = "http.port", value_name = "PORT", default_value_t = 8545)]
+    http_port: u16,
+
+    /// The local port the Engine API listens on
+    #[arg(long = "authrpc.port", value_name = "PORT", default_value_t = 8551)]
+    auth_port: u16,
+
+    /// The path to the hex-encoded JWT secret of the Engine API.
+    ///
+    /// A new secret is generated at this path if the file doesn't exist. Defaults to `jwt.hex`
+    /// within the database directory.
+    #[arg(long = "authrpc.jwtsecret", value_name = "PATH", verbatim_doc_comment)]
+    auth_jwtsecret: Option<PathBuf>,
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(mut self, _ctx: CliContext) -> Result<()> {
+        if self.db.chain.is_some() {
+            eyre::bail!("A devnet is not one of the chain presets, select it with --dev.chain-id");
+        }
+        // Keep the devnet apart from the migrated chain of the default chain directory
+        if self.db.database.is_none() {
+            let path = self.db.data_dir().join(DEVNET_DIR).join(dirs::DB_DIR);
+            self.db.database = Some(path.display().to_string());
+        }
+        let db_path = self.db.path();
+        if self.reset {
+            reset(&db_path)?;
+        }
+        let _lock = db::lock_db(&db_path)?;
+
+        let mut db = self.db.open_rw()?;
+        let balance = U256::from(self.balance) * U256::from(10u64.pow(18));
+        let chain = init(&mut db, &db_path, self.chain_id, balance).await?;
+        tracing::info!(target: "reth::cli", path = ?db_path, chain_id = chain.chain_id(), genesis = ?chain.inner.genesis_hash(), "Devnet initialized");
+        let db = Arc::new(db);
+
+        let jwt_path = self.auth_jwtsecret.clone().unwrap_or_else(|| db_path.join("jwt.hex"));
+        let secret = JwtSecret::try_create(&jwt_path)
+            .wrap_err_with(|| format!("Could not load JWT secret from {jwt_path:?}"))?;
+
+        let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let auth_addr = SocketAddr::new(localhost, self.auth_port);
+        let fees = Arc::new(L1FeeStore::open(&self.db.static_path()?)?);
+        fees.recover_staged(|hash| Ok(db::head::head(&db)?.map(|head| head.hash) == Some(hash)))?;
+        let deposits = Arc::new(DepositStore::open(&self.db.static_path()?)?);
+        let pool = Arc::new(TxPool::new(db.clone(), chain.clone()));
+        let builder =
+            Arc::new(PayloadBuilder::new(db.clone(), chain.clone()).with_pool(pool.clone()));
+        let engine = EngineApi::new(db.clone(), chain)
+            .with_l1_fees(fees.clone())
+            .with_deposits(deposits.clone())
+            .with_pool(pool.clone())
+            .with_payload_builder(builder)
+            .with_forkchoice_pointers(db_path.clone(), ForkchoicePointers::read(&db_path)?);
+        let auth_handle = engine::start_server(auth_addr, secret, engine.clone()).await?;
+        tracing::info!(target: "reth::cli", addr = %auth_addr, jwt = ?jwt_path, "Engine API started");
+
+        let http_addr = SocketAddr::new(localhost, self.http_port);
+        let api = EthApi::new(db.clone(), fees, deposits)
+            .with_prune_checkpoints(PruneCheckpoints::read(&db_path)?)
+            .with_pool(pool);
+        let http_handle = rpc::start_server(http_addr, api).await?;
+        tracing::info!(target: "reth::cli", addr = %http_addr, "JSON-RPC server started");
+
+        let _shutdown = shutdown::registry().register("devnet servers", ShutdownPhase::Rpc, {
+            let (auth_handle, http_handle) = (auth_handle.clone(), http_handle.clone());
+            move || async move {
+                for handle in [auth_handle, http_handle] {
+                    if handle.stop().is_ok() {
+                        handle.stopped().await;
+                    }
+                }
+                Ok(())
+            }
+        });
+
+        let driver = if self.mock_driver {
+            let driver = MockDriver::from_database(engine, &db)?;
+            tracing::info!(target: "reth::cli", head = driver.head().number, block_time = self.block_time, "Mock driver started");
+            future::Either::Left(driver.run(Duration::from_secs(self.block_time)))
+        } else {
+            future::Either::Right(future::pending::<Result<()>>())
+        };
+        tokio::select! {
+            result = driver => result?,
+            _ = future::join(auth_handle.stopped(), http_handle.stopped()) => {}
+        }
+        tracing::info!(target: "reth::cli", "Devnet stopped");
+        Ok(())
+    }
+}
+
+/// The genesis of a devnet with the given chain id, with every hardfork and the OP hardforks
+/// active from the genesis block on and the [DEV_ACCOUNTS] funded with `balance` wei each
+pub fn dev_genesis(chain_id: u64, balance: U256, timestamp: u64) -> serde_json::Value {
+    let alloc = DEV_ACCOUNTS
+        .iter()
+        .map(|account| (account.to_lowercase(), json!({ "balance": balance })))
+        .collect::<serde_json::Map<_, _>>();
+    json!({
+        "config": {
+            "ChainName": "devnet",
+            "chainId": chain_id,
+            "homesteadBlock": 0,
+            "eip150Block": 0,
+            "eip155Block": 0,
+            "eip158Block": 0,
+            "byzantiumBlock": 0,
+            "constantinopleBlock": 0,
+            "petersburgBlock": 0,
+            "istanbulBlock": 0,
+            "muirGlacierBlock": 0,
+            "berlinBlock": 0,
+            "londonBlock": 0,
+            "arrowGlacierBlock": 0,
+            "grayGlacierBlock": 0,
+            "mergeNetsplitBlock": 0,
+            "bedrockBlock": 0,
+            "shanghaiTime": 0,
+            "regolithTime": 0,
+            "canyonTime": 0,
+            "terminalTotalDifficulty": 0,
+            "terminalTotalDifficultyPassed": true,
+        },
+        "difficulty": "0x0",
+        "gasLimit": format!("{GENESIS_GAS_LIMIT:#x}"),
+        "extraData": "0x",
+        "timestamp": format!("{timestamp:#x}"),
+        "baseFeePerGas": format!("{GENESIS_BASE_FEE:#x}"),
+        "alloc": alloc,
+    })
+}
+
+/// The addresses of the [DEV_ACCOUNTS]
+pub fn dev_accounts() -> Vec<Address> {
+    DEV_ACCOUNTS.iter().map(|account| Address::from_str(account).expect("valid address")).collect()
+}
+
+/// Initializes the devnet database at `db_path` from a new dev genesis, written to
+/// [GENESIS_FILE] next to it. A database holding a devnet already is reused as it is, failing if
+/// it was created with another chain id. Returns the chain spec of the devnet, with regolith and
+/// canyon active from the genesis on.
+pub async fn init(
+    db: &mut Env<WriteMap>,
+    db_path: &Path,
+    chain_id: u64,
+    balance: U256,
+) -> Result<OpChainSpec> {
+    db.create_tables()?;
+    let genesis_path = db_path.join(GENESIS_FILE);
+    if db.view(|tx| tx.get::<tables::Headers>(0))??.is_some() {
+        if !genesis_path.exists() {
+            eyre::bail!(
+                "The database at {} holds a chain that is not a devnet, select another one with \
+                 --datadir or --database",
+                db_path.display()
+            );
+        }
+        let chain = OpChainSpec::read(db_path)?
+            .ok_or_else(|| eyre::eyre!("No chain spec found next to the devnet database"))?;
+        if chain.chain_id() != chain_id {
+            eyre::bail!(
+                "The devnet at {} has chain id {}, not {chain_id}. Pass --reset to start over.",
+                db_path.display(),
+                chain.chain_id()
+            );
+        }
+        return Ok(chain)
+    }
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let genesis = dev_genesis(chain_id, balance, timestamp);
+    fs::write(&genesis_path, serde_json::to_string_pretty(&genesis)?)?;
+    let args = ImportArgs { quiet: true, ..Default::default() };
+    let chain = genesis::apply(db, Some(&genesis_path.display().to_string()), &args).await?;
+    // Blocks are executed on top of the trie of the genesis
+    state::hash_and_trie(db)?;
+    chain.write(db_path)?;
+    Ok(chain)
+}
+
+/// Deletes the devnet database at `db_path`, refusing to delete a database that doesn't hold a
+/// devnet
+fn reset(db_path: &Path) -> Result<()> {
+    if !db_path.exists() {
+        return Ok(())
+    }
+    if !db_path.join(GENESIS_FILE).exists() {
+        eyre::bail!("Refusing to reset {}, it doesn't hold a devnet", db_path.display());
+    }
+    // Fail instead of deleting a devnet that is running
+    drop(db::lock_db(db_path)?);
+    fs::remove_dir_all(db_path)?;
+    tracing::info!(target: "reth::cli", path = ?db_path, "Devnet database deleted");
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use eyre::Result;
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTx,
+};
+use reth_primitives::{
+    proofs::EMPTY_ROOT, Address, Header, SealedBlock, SealedHeader, EMPTY_OMMER_ROOT, H256, U64,
+};
+use reth_rpc_types::engine::{ForkchoiceState, PayloadStatusEnum};
+
+use crate::cli::{
+    keccak,
+    node::{
+        engine::{EngineApi, EngineApiServer},
+        payload::OpPayloadAttributes,
+    },
+};
+
+/// Produces blocks on top of the canonical head through the Engine API handler, standing in for
+/// op-node on a devnet.
+///
+/// The blocks are built by the payload builder of the handler, which fills them with the best
+/// transactions of its transaction pool. Without a builder the blocks are empty, keep the state
+/// root of their parent and their base fee falls like it does for empty blocks under EIP-1559.
+#[derive(Debug)]
+pub struct MockDriver {
+    engine: EngineApi,
+    head: SealedHeader,
+}
+
+impl MockDriver {
+    /// Creates a driver building on top of `head`
+    pub fn new(engine: EngineApi, head: SealedHeader) -> Self {
+        Self { engine, head }
+    }
+
+    /// Creates a driver building on top of the canonical head of the database the Engine API
+    /// handler writes to
+    pub fn from_database(engine: EngineApi, db: &Env<WriteMap>) -> Result<Self> {
+        let head = db.view(|tx| -> Result<SealedHeader> {
+            let (number, hash) = tx
+                .cursor_read::<tables::CanonicalHeaders>()?
+                .last()?
+                .ok_or_else(|| eyre::eyre!("No canonical blocks found in the database"))?;
+            let header = tx
+                .get::<tables::Headers>(number)?
+                .ok_or_else(|| eyre::eyre!("Header for canonical block {number} not found"))?;
+            Ok(SealedHeader::new(header, hash))
+        })??;
+        Ok(Self::new(engine, head))
+    }
+
+    /// The header of the last block the driver produced, or the one it started from
+    pub fn head(&self) -> &SealedHeader {
+        &self.head
+    }
+
+    /// Builds the empty block following the head. The timestamp is raised to one second after the
+    /// head if it doesn't follow it. Blocks from canyon on carry an empty list of withdrawals.
+    pub fn next_block(&self, timestamp: u64) -> SealedBlock {
+        let parent = &self.head;
+        let chain = self.engine.chain();
+        let number = parent.number + 1;
+        let timestamp = timestamp.max(parent.timestamp + 1);
+        let denominator = chain.eip1559_denominator_at(number, timestamp);
+        let withdrawals = chain.is_canyon_active(number, timestamp).then(Vec::new);
+        let header = Header {
+            parent_hash: parent.hash(),
+            ommers_hash: EMPTY_OMMER_ROOT,
+            state_root: parent.state_root,
+            transactions_root: EMPTY_ROOT,
+            receipts_root: EMPTY_ROOT,
+            number,
+            gas_limit: parent.gas_limit,
+            timestamp,
+            base_fee_per_gas: parent.base_fee_per_gas.map(|fee| fee - fee / denominator),
+            withdrawals_root: withdrawals.as_ref().map(|_| EMPTY_ROOT),
+            ..Default::default()
+        };
+        SealedBlock { header: keccak::seal(header), body: vec![], ommers: vec![], withdrawals }
+    }
+
+    /// Builds the block following the head with the payload builder of the handler, the empty
+    /// [MockDriver::next_block] without one. The timestamp is raised like for the empty block.
+    pub fn build_block(&self, timestamp: u64) -> Result<SealedBlock> {
+        let Some(builder) = self.engine.payload_builder() else {
+            return Ok(self.next_block(timestamp))
+        };
+        let parent = &self.head;
+        let timestamp = timestamp.max(parent.timestamp + 1);
+        let canyon = self.engine.chain().is_canyon_active(parent.number + 1, timestamp);
+        let attributes = OpPayloadAttributes {
+            timestamp: U64::from(timestamp),
+            prev_randao: H256::zero(),
+            suggested_fee_recipient: Address::zero(),
+            withdrawals: canyon.then(Vec::new),
+            transactions: vec![],
+            no_tx_pool: false,
+            gas_limit: None,
+        };
+        let id = builder.build(parent.hash(), &attributes)?;
+        let payload = builder
+            .payload(id)
+            .ok_or_else(|| eyre::eyre!("Payload {id:?} was dropped by the builder"))?;
+        Ok(payload.block)
+    }
+
+    /// Produces the block following the head: submits it with `engine_newPayloadV1` and makes it
+    /// the head, safe and finalized block with `engine_forkchoiceUpdatedV1`
+    pub async fn produce_block(&mut self, timestamp: u64) -> Result<SealedHeader> {
+        let block = self.build_block(timestamp)?;
+        let hash = block.hash();
+        let status = self.engine.new_payload_v1(block.clone().into()).await?;
+        if !matches!(status.status, PayloadStatusEnum::Valid) {
+            eyre::bail!("Block {} was not accepted: {:?}", block.number, status.status);
+        }
+
+        let state = ForkchoiceState {
+            head_block_hash: hash,
+            safe_block_hash: hash,
+            finalized_block_hash: hash,
+        };
+        let updated = self.engine.fork_choice_updated_v1(state, None).await?;
+        if !matches!(updated.payload_status.status, PayloadStatusEnum::Valid) {
+            eyre::bail!(
+                "Forkchoice update to block {} failed: {:?}",
+                block.number,
+                updated.payload_status.status
+            );
+        }
+        self.head = block.header;
+        Ok(self.head.clone())
+    }
+
+    /// Produces a block every `block_time` until an error occurs
+    pub async fn run(mut self, block_time: Duration) -> Result<()> {
+        let mut interval = tokio::time::interval(block_time);
+        // The first tick completes immediately
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+            let header = self.produce_block(timestamp).await?;
+            tracing::debug!(target: "reth::devnet", number = header.number, hash = ?header.hash(), "Produced block");
+        }
+    }
+}
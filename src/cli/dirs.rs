@@ -1,5 +1,22 @@
 use reth::dirs::{data_dir, XdgPath};
-use std::path::PathBuf;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use eyre::Result;
+
+use crate::cli::{chain::ChainPreset, preflight::ImportStage};
+
+/// The directory below a chain directory holding the database
+pub const DB_DIR: &str = "db";
+
+/// The directory below the data directory holding the data of commands run without `--chain`
+pub const DEFAULT_CHAIN_DIR: &str = "default";
+
+/// Whether the default file systems of the platform ignore the case of paths, like NTFS on Windows
+/// and APFS on macOS
+pub const CASE_INSENSITIVE_PATHS: bool = cfg!(any(windows, target_os = "macos"));
 
 #[derive(Default, Debug, Clone)]
 pub struct HeadersDbPath;
@@ -18,3 +35,62 @@ impl XdgPath for StateDbPath {
         data_dir().map(|root| root.join("state-db"))
     }
 }
+
+/// The default data directory of op-reth, below the one of reth in the platform data directory:
+/// `$XDG_DATA_HOME` on Linux, `~/Library/Application Support` on macOS and `%APPDATA%` on
+/// Windows
+#[derive(Default, Debug, Clone)]
+pub struct DataDirPath;
+
+impl XdgPath for DataDirPath {
+    fn resolve() -> Option<PathBuf> {
+        data_dir().map(|root| root.join("op-reth"))
+    }
+}
+
+/// The default data directory. Panics if the platform data directory can't be determined, like
+/// the default paths of reth.
+pub fn default_data_dir() -> PathBuf {
+    DataDirPath::resolve().expect("Could not resolve the default data directory, pass --datadir")
+}
+
+/// The directory holding the database and the inputs of the given chain: `<DATA_DIR>/<CHAIN>`
+pub fn chain_dir(data_dir: &Path, chain: Option<ChainPreset>) -> PathBuf {
+    data_dir.join(chain.map_or(DEFAULT_CHAIN_DIR, |chain| chain.name()))
+}
+
+/// The input the given stage reads from the chain directory if no path is given. Without a chain
+/// the file names of the OP Goerli exports are used.
+pub fn default_input(
+    chain_dir: &Path,
+    chain: Option<ChainPreset>,
+    stage: ImportStage,
+) -> Result<PathBuf> {
+    let preset = chain.unwrap_or(ChainPreset::OpGoerli);
+    match preset.default_input(stage) {
+        Some(name) => Ok(chain_dir.join(name)),
+        None => eyre::bail!("{preset} has no default {stage} input, pass its path explicitly"),
+    }
+}
+
+/// Whether two paths name the same file or directory.
+///
+/// Existing paths are compared after resolving links, others after normalizing their separators,
+/// redundant `.` components and trailing separators. Case is ignored on Windows and macOS.
+pub fn same_path(a: &Path, b: &Path) -> bool {
+    normalize_path(a) == normalize_path(b)
+}
+
+/// Normalizes a path for comparisons, see [same_path]
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.components().collect());
+    if CASE_INSENSITIVE_PATHS {
+        return PathBuf::from(path.to_string_lossy().to_lowercase())
+    }
+    path
+}
+
+/// Whether the extension of the file is `extension`, ignoring its case
+pub fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().map_or(false, |ext| ext.to_string_lossy().eq_ignore_ascii_case(extension))
+}
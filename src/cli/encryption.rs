@@ -0,0 +1,142 @@
+use std::{fs, path::Path};
+
+use aes_gcm::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use eyre::Result;
+use hmac::Hmac;
+use sha2::Sha256;
+
+/// The bytes every encrypted file starts with
+pub const MAGIC: &[u8; 8] = b"OPRETHE1";
+
+/// The PBKDF2-HMAC-SHA256 rounds deriving the key of a passphrase
+pub const PBKDF2_ROUNDS: u32 = 600_000;
+
+/// The length of the salt of a passphrase
+const SALT_LEN: usize = 16;
+
+/// The length of an AES-GCM nonce
+const NONCE_LEN: usize = 12;
+
+/// The length of the header of an encrypted file: the magic bytes, the key derivation, the
+/// PBKDF2 rounds, the salt and the nonce
+const HEADER_LEN: usize = MAGIC.len() + 1 + 4 + SALT_LEN + NONCE_LEN;
+
+/// The key derivation marking files encrypted with a key file
+const KDF_KEY: u8 = 0;
+
+/// The key derivation marking files encrypted with a passphrase
+const KDF_PASSPHRASE: u8 = 1;
+
+/// The secret chain data is encrypted with
+#[derive(Clone)]
+pub enum SnapshotKey {
+    /// A 256 bit AES key
+    Key([u8; 32]),
+    /// A passphrase the key is derived from with PBKDF2
+    Passphrase(Vec<u8>),
+}
+
+impl SnapshotKey {
+    /// Reads a key file holding 32 raw bytes or 64 hex digits, as written by `openssl rand -hex 32`
+    pub fn from_key_file(path: &Path) -> Result<Self> {
+        let data = fs::read(path)?;
+        let key = match data.len() {
+            32 => data,
+            _ => {
+                let text = std::str::from_utf8(&data)?.trim();
+                hex::decode(text.trim_start_matches("0x"))?
+            }
+        };
+        let key = key.try_into().map_err(|_| {
+            eyre::eyre!("The key file {} does not hold a 32 byte key", path.display())
+        })?;
+        Ok(Self::Key(key))
+    }
+
+    /// Reads the passphrase on the first line of a file
+    pub fn from_passphrase_file(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path)?;
+        let passphrase = data.lines().next().unwrap_or_default();
+        if passphrase.is_empty() {
+            eyre::bail!("The passphrase file {} is empty", path.display());
+        }
+        Ok(Self::Passphrase(passphrase.as_bytes().to_vec()))
+    }
+
+    /// Derives the AES key with the given salt and rounds
+    fn derive(&self, salt: &[u8], rounds: u32) -> [u8; 32] {
+        match self {
+            SnapshotKey::Key(key) => *key,
+            SnapshotKey::Passphrase(passphrase) => {
+                let mut key = [0; 32];
+                pbkdf2::pbkdf2::<Hmac<Sha256>>(passphrase, salt, rounds, &mut key);
+                key
+            }
+        }
+    }
+}
+
+/// Whether the data is encrypted
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Encrypts the data with AES-256-GCM. The header of the result holds everything but the secret
+/// needed to decrypt it, and is authenticated together with the ciphertext.
+pub fn encrypt(data: &[u8], key: &SnapshotKey) -> Result<Vec<u8>> {
+    let (kdf, rounds) = match key {
+        SnapshotKey::Key(_) => (KDF_KEY, 0),
+        SnapshotKey::Passphrase(_) => (KDF_PASSPHRASE, PBKDF2_ROUNDS),
+    };
+    let mut salt = [0; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(MAGIC);
+    header.push(kdf);
+    header.extend_from_slice(&rounds.to_be_bytes());
+    header.extend_from_slice(&salt);
+    header.extend_from_slice(&nonce);
+
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.derive(&salt, rounds)));
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: data, aad: &header })
+        .map_err(|_| eyre::eyre!("Encryption failed"))?;
+    Ok([header, ciphertext].concat())
+}
+
+/// Decrypts data encrypted with [encrypt], failing if the key is wrong or the data was altered
+pub fn decrypt(data: &[u8], key: &SnapshotKey) -> Result<Vec<u8>> {
+    if !is_encrypted(data) || data.len() < HEADER_LEN {
+        eyre::bail!("The data is not encrypted");
+    }
+    let (header, ciphertext) = data.split_at(HEADER_LEN);
+    let kdf = header[MAGIC.len()];
+    let rounds = u32::from_be_bytes(header[MAGIC.len() + 1..MAGIC.len() + 5].try_into()?);
+    let salt = &header[MAGIC.len() + 5..MAGIC.len() + 5 + SALT_LEN];
+    let nonce = &header[HEADER_LEN - NONCE_LEN..];
+    match (kdf, key) {
+        (KDF_KEY, SnapshotKey::Key(_)) | (KDF_PASSPHRASE, SnapshotKey::Passphrase(_)) => {}
+        (KDF_KEY, _) => eyre::bail!("The data is encrypted with a key file, not a passphrase"),
+        (KDF_PASSPHRASE, _) => eyre::bail!("The data is encrypted with a passphrase, not a key"),
+        _ => eyre::bail!("Unknown key derivation {kdf}"),
+    }
+
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.derive(salt, rounds)));
+    cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: header })
+        .map_err(|_| eyre::eyre!("Decryption failed, the key is wrong or the data was altered"))
+}
+
+/// Encrypts the file at `path` in place
+pub fn encrypt_file(path: &Path, key: &SnapshotKey) -> Result<()> {
+    let encrypted = encrypt(&fs::read(path)?, key)?;
+    let tmp = path.with_extension("encrypting");
+    fs::write(&tmp, encrypted)?;
+    fs::rename(tmp, path)?;
+    Ok(())
+}
@@ -0,0 +1,347 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use eyre::Result;
+use flate2::{write::GzEncoder, Compression};
+use reth::runner::CliContext;
+use reth_db::{
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTx,
+};
+use reth_primitives::{
+    bloom::logs_bloom,
+    create_address,
+    rpc::{H160, H256},
+    U256,
+};
+use reth_rlp::{Encodable, Header as RlpHeader};
+
+use crate::cli::{
+    analytics,
+    args::{DatabaseArgs, EncryptionArgs},
+    blocks::{self, BlockFormat},
+    checkpoints::{CheckpointFile, DEFAULT_CHECKPOINT_INTERVAL},
+    deposit::{self, DepositStore},
+    dirs, encryption,
+    l1_fee::{L1FeeInfo, L1FeeStore},
+    progress::ImportProgress,
+    receipts::{Receipt, ReceiptLog},
+};
+
+/// The byte receipt exports start with, which the receipt importer skips before the receipt list
+pub const RECEIPTS_PREFIX: u8 = 0;
+
+/// Export the migrated chain in the formats the importers consume
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// Write the blocks to this file, in the layout of Erigon's block export
+    #[arg(
+        long,
+        value_name = "FILE",
+        required_unless_present_any = ["receipts", "checkpoints"],
+        verbatim_doc_comment
+    )]
+    blocks: Option<PathBuf>,
+
+    /// Write the receipts to this file, in the layout of Erigon's receipt export
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    receipts: Option<PathBuf>,
+
+    /// Write the hashes of every `--checkpoint-interval`th block and of the last block to this
+    /// file, which other imports are checked against with `verify checkpoints`
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    checkpoints: Option<PathBuf>,
+
+    /// The distance between the blocks of the checkpoint file
+    #[arg(
+        long,
+        value_name = "BLOCKS",
+        verbatim_doc_comment,
+        default_value_t = DEFAULT_CHECKPOINT_INTERVAL
+    )]
+    checkpoint_interval: u64,
+
+    /// The first block to export.
+    ///
+    /// The block importer expects exports to start at the genesis block.
+    #[arg(long, value_name = "BLOCK", default_value_t = 0, verbatim_doc_comment)]
+    from: u64,
+
+    /// The last block to export (inclusive). Defaults to the highest canonical block.
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment)]
+    to: Option<u64>,
+
+    /// Do not render progress bars
+    #[arg(long, short, verbatim_doc_comment)]
+    quiet: bool,
+
+    #[clap(flatten)]
+    encryption: EncryptionArgs,
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db = self.db.open_ro()?;
+        let tip = analytics::canonical_tip(&db)?
+            .ok_or_else(|| eyre::eyre!("No canonical blocks found in the database"))?;
+        let to = self.to.unwrap_or(tip);
+        if self.from > to || to > tip {
+            eyre::bail!("Invalid block range {}..={to}, the database tip is {tip}", self.from);
+        }
+        let range = self.from..=to;
+        let key = self.encryption.key()?;
+        let progress =
+            if self.quiet { ImportProgress::default() } else { ImportProgress::with_bar() };
+        let deposits = DepositStore::open(&self.db.static_path()?)?;
+
+        if let Some(path) = &self.blocks {
+            let count = export_blocks(&db, &deposits, path, range.clone(), &progress)?;
+            if let Some(key) = &key {
+                encryption::encrypt_file(path, key)?;
+            }
+            tracing::info!(target: "reth::cli", path = %path.display(), blocks = count, "Blocks exported");
+        }
+        if let Some(path) = &self.checkpoints {
+            let file = CheckpointFile::collect(&db.tx()?, range.clone(), self.checkpoint_interval)?;
+            file.write(path)?;
+            tracing::info!(target: "reth::cli", path = %path.display(), checkpoints = file.checkpoints.len(), digest = ?file.digest, "Checkpoints exported");
+        }
+        if let Some(path) = &self.receipts {
+            let fees = L1FeeStore::open(&self.db.static_path()?)?;
+            let count = export_receipts(&db, &fees, &deposits, path, range, &progress)?;
+            if let Some(key) = &key {
+                encryption::encrypt_file(path, key)?;
+            }
+            tracing::info!(target: "reth::cli", path = %path.display(), receipts = count, "Receipts exported");
+        }
+        progress.finish();
+        Ok(())
+    }
+}
+
+/// Writes the canonical blocks in the given range to `path` in the layout of Erigon's block
+/// export, which [blocks::apply] imports, restoring their deposits from `deposits`. Returns the
+/// number of exported blocks.
+pub fn export_blocks(
+    db: &Env<WriteMap>,
+    deposits: &DepositStore,
+    path: &Path,
+    range: RangeInclusive<u64>,
+    progress: &ImportProgress,
+) -> Result<u64> {
+    progress.set_stage("export blocks");
+    progress.set_total(range.end().saturating_sub(*range.start()) + 1);
+    let tx = db.tx()?;
+    let mut count = 0;
+    write_rlp_list(path, &[], |out| {
+        for number in range {
+            let Some(block) = blocks::load_erigon_block(&tx, deposits, number)? else {
+                eyre::bail!("Block {number} not found in the database")
+            };
+            out.write_all(&rlp::encode(&block))?;
+            count += 1;
+            progress.set_block(number);
+            progress.advance(1)?;
+        }
+        Ok(())
+    })?;
+    Ok(count)
+}
+
+/// Writes the canonical blocks in the given range to `path` in the given layout. Returns the
+/// number of exported blocks.
+pub fn export_blocks_as(
+    db: &Env<WriteMap>,
+    deposits: &DepositStore,
+    path: &Path,
+    range: RangeInclusive<u64>,
+    format: BlockFormat,
+    progress: &ImportProgress,
+) -> Result<u64> {
+    match format {
+        BlockFormat::Erigon => export_blocks(db, deposits, path, range, progress),
+        BlockFormat::Geth | BlockFormat::RlpStandard => {
+            export_standard_blocks(db, path, range, format == BlockFormat::RlpStandard, progress)
+        }
+    }
+}
+
+/// Writes the canonical blocks in the given range to `path` as concatenated blocks in the
+/// standard devp2p encoding, the framing `geth export` writes and `geth import` reads. With
+/// `as_hex` the blocks are written as one hex string instead. A path ending in `.gz` is
+/// gzip-compressed, as geth does. Returns the number of exported blocks.
+pub fn export_standard_blocks(
+    db: &Env<WriteMap>,
+    path: &Path,
+    range: RangeInclusive<u64>,
+    as_hex: bool,
+    progress: &ImportProgress,
+) -> Result<u64> {
+    progress.set_stage("export blocks");
+    progress.set_total(range.end().saturating_sub(*range.start()) + 1);
+    let tx = db.tx()?;
+    let mut count = 0;
+    let write_blocks = |out: &mut dyn Write| -> Result<()> {
+        if as_hex {
+            out.write_all(b"0x")?;
+        }
+        let mut encoded = Vec::new();
+        for number in range {
+            let Some(block) = blocks::load_standard_block(&tx, number)? else {
+                eyre::bail!("Block {number} not found in the database")
+            };
+            encoded.clear();
+            block.encode(&mut encoded);
+            if as_hex {
+                out.write_all(hex::encode(&encoded).as_bytes())?;
+            } else {
+                out.write_all(&encoded)?;
+            }
+            count += 1;
+            progress.set_block(number);
+            progress.advance(1)?;
+        }
+        if as_hex {
+            out.write_all(b"\n")?;
+        }
+        Ok(())
+    };
+
+    let file = BufWriter::new(File::create(path)?);
+    if dirs::has_extension(path, "gz") {
+        let mut out = GzEncoder::new(file, Compression::default());
+        write_blocks(&mut out)?;
+        out.finish()?.flush()?;
+    } else {
+        let mut out = file;
+        write_blocks(&mut out)?;
+        out.flush()?;
+    }
+    Ok(count)
+}
+
+/// Writes the receipts of the canonical blocks in the given range to `path` in the layout of
+/// Erigon's receipt export, a list holding the list of receipts of every block, which
+/// [receipts::apply](crate::cli::receipts::apply) imports. Returns the number of exported receipts.
+pub fn export_receipts(
+    db: &Env<WriteMap>,
+    fees: &L1FeeStore,
+    deposits: &DepositStore,
+    path: &Path,
+    range: RangeInclusive<u64>,
+    progress: &ImportProgress,
+) -> Result<u64> {
+    progress.set_stage("export receipts");
+    progress.set_total(range.end().saturating_sub(*range.start()) + 1);
+    let tx = db.tx()?;
+    let mut count = 0;
+    write_rlp_list(path, &[RECEIPTS_PREFIX], |out| {
+        for number in range {
+            let receipts = load_block_receipts(&tx, fees, deposits, number)?;
+            count += receipts.len() as u64;
+            out.write_all(&rlp::encode_list::<Receipt, _>(&receipts))?;
+            progress.set_block(number);
+            progress.advance(1)?;
+        }
+        Ok(())
+    })?;
+    Ok(count)
+}
+
+/// Loads the receipts of the canonical block with the given number in the layout of Erigon's
+/// receipt export
+fn load_block_receipts<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    fees: &L1FeeStore,
+    deposits: &DepositStore,
+    number: u64,
+) -> Result<Vec<Receipt>> {
+    let Some(body) = tx.get::<tables::BlockBodies>(number)? else { return Ok(vec![]) };
+    let block_hash = tx.get::<tables::CanonicalHeaders>(number)?.unwrap_or_default();
+
+    let mut receipts = Vec::with_capacity(body.tx_count as usize);
+    let mut previous_gas_used = 0;
+    for (index, tx_id) in (body.start_tx_id..body.start_tx_id + body.tx_count).enumerate() {
+        let Some(receipt) = tx.get::<tables::Receipts>(tx_id)? else { continue };
+        let transaction = deposits
+            .read(tx, tx_id)?
+            .ok_or_else(|| eyre::eyre!("Transaction {tx_id} of block {number} not found"))?;
+        let ty = deposit::tx_type(&transaction);
+
+        let contract_address = match (transaction.to(), tx.get::<tables::TxSenders>(tx_id)?) {
+            (None, Some(sender)) => create_address(sender, transaction.nonce()),
+            _ => Default::default(),
+        };
+        let logs = receipt
+            .logs
+            .iter()
+            .map(|log| ReceiptLog {
+                address: H160::from_slice(&log.address.0),
+                topics: log.topics.iter().map(|topic| H256::from_slice(&topic.0)).collect(),
+                data: log.data.to_vec(),
+            })
+            .collect::<Vec<_>>();
+        let fee = fees
+            .get(tx_id)?
+            .unwrap_or_else(|| L1FeeInfo { l1_fee_scalar: "0".to_string(), ..Default::default() });
+
+        receipts.push(Receipt {
+            ty,
+            post_state: vec![],
+            status: receipt.success as u64,
+            cumulative_gas_used: receipt.cumulative_gas_used,
+            bloom: logs_bloom(receipt.logs.iter()).0.to_vec(),
+            logs: rlp::encode_list::<ReceiptLog, _>(&logs).to_vec(),
+            tx_hash: H256::from_slice(&transaction.hash().0),
+            contract_address: format!("{contract_address:?}"),
+            gas_used: receipt.cumulative_gas_used.saturating_sub(previous_gas_used),
+            block_hash: H256::from_slice(&block_hash.0),
+            block_number: U256::from(number),
+            transaction_index: index as u64,
+            l1_gas_price: fee.l1_gas_price,
+            l1_gas_used: fee.l1_gas_used,
+            l1_fee: fee.l1_fee,
+            l1_fee_scalar: fee.l1_fee_scalar,
+        });
+        previous_gas_used = receipt.cumulative_gas_used;
+    }
+    Ok(receipts)
+}
+
+/// Writes `prefix` and an RLP list of the items written by `write_items` to `path`.
+///
+/// The list header depends on the total length of the items, so they are staged in a temporary
+/// file next to the output first.
+fn write_rlp_list(
+    path: &Path,
+    prefix: &[u8],
+    write_items: impl FnOnce(&mut BufWriter<File>) -> Result<()>,
+) -> Result<()> {
+    let staging = path.with_extension("partial");
+    let mut items = BufWriter::new(File::create(&staging)?);
+    write_items(&mut items)?;
+    items.flush()?;
+    drop(items);
+
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(prefix)?;
+    let mut header = Vec::new();
+    RlpHeader { list: true, payload_length: fs::metadata(&staging)?.len() as usize }
+        .encode(&mut header);
+    out.write_all(&header)?;
+    io::copy(&mut File::open(&staging)?, &mut out)?;
+    out.flush()?;
+    fs::remove_file(&staging)?;
+    Ok(())
+}
@@ -1,46 +1,99 @@
-use std::{
-    collections::HashMap,
-    fs::File,
-    io::BufReader,
-    path::{Path, PathBuf},
-};
+use std::{collections::BTreeMap, fs::File, io::BufReader, path::Path, sync::Arc};
 
 use clap::Parser;
 use eyre::Result;
 use reth::runner::CliContext;
 use reth_db::{database::Database, mdbx::WriteMap, tables, transaction::DbTxMut};
 use reth_primitives::{
-    keccak256, Account as RethAccount, Address, Bytes, Header, SealedBlock, SealedHeader,
-    StorageEntry, H256, U256,
+    Account as RethAccount, Address, Bytes, Chain, ChainSpecBuilder, ForkCondition, Hardfork,
+    Header, SealedBlock, SealedHeader, StorageEntry, H256, U256,
 };
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
-use crate::cli::db;
+use crate::cli::{
+    args::{DatabaseArgs, ImportArgs},
+    chain::{
+        genesis_from_header, ChainPreset, OpChainSpec, OP_MAINNET_EIP1559_DENOMINATOR,
+        OP_MAINNET_EIP1559_DENOMINATOR_CANYON, OP_MAINNET_EIP1559_ELASTICITY,
+        OP_MAINNET_REGOLITH_TIME,
+    },
+    compression, journal,
+    keccak::{self, keccak256},
+    preflight::ImportStage,
+    state::{state_root_hash, storage_root, ExportedAccount, State},
+};
 
 /// Genesis command
 #[derive(Debug, Parser)]
 pub struct Command {
-    /// The path to the genesis file
-    #[arg(long, value_name = "GENESIS", verbatim_doc_comment, default_value = "genesis.json")]
-    path: String,
-    /// The path to the database
-    #[arg(long, value_name = "DATABASE_PATH", verbatim_doc_comment)]
-    database: String,
+    /// The path to the genesis file. Defaults to the genesis of the chain selected with `--chain`.
+    #[arg(long, value_name = "GENESIS", verbatim_doc_comment)]
+    path: Option<String>,
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The expected hash of the genesis block. Nothing is written if the hash of the genesis
+    /// block built from the file differs. Defaults to the genesis hash of the chain selected with
+    /// `--chain`.
+    #[arg(long, value_name = "HASH", verbatim_doc_comment)]
+    expected_hash: Option<H256>,
+
+    #[clap(flatten)]
+    import: ImportArgs,
 }
 
-/// Apply genesis state to the given database
-pub async fn apply(db: &mut reth_db::mdbx::Env<WriteMap>, path: Option<&str>) -> Result<()> {
-    let genesis = Genesis::from_file(path.unwrap_or("data/genesis.json"))?;
+/// Apply genesis state to the given database. Returns the chain spec described by the genesis.
+pub async fn apply(
+    db: &mut reth_db::mdbx::Env<WriteMap>,
+    path: Option<&str>,
+    args: &ImportArgs,
+) -> Result<OpChainSpec> {
+    apply_checked(db, path, None, args).await
+}
+
+/// Apply genesis state to the given database, failing before anything is written if the hash of
+/// the genesis block differs from `expected_hash`. Returns the chain spec described by the genesis.
+pub async fn apply_checked(
+    db: &mut reth_db::mdbx::Env<WriteMap>,
+    path: Option<&str>,
+    expected_hash: Option<H256>,
+    args: &ImportArgs,
+) -> Result<OpChainSpec> {
+    let (progress, _monitors) = args.watch()?;
+    progress.set_stage("read genesis");
+    let file_path = args.input_path(path, ImportStage::Genesis)?;
+    let limiter = args.io_limiter();
+    let data = args.read_input(&file_path, limiter.as_ref())?;
+    progress.set_offset(data.len() as u64);
+    progress.set_stage("verify checksum");
+    args.verify_checksum(Path::new(&file_path), &data)?;
+    let data = args.decrypt(Path::new(&file_path), data)?;
+    let data = compression::decompress(Path::new(&file_path), data)?;
+    let (genesis, format) = Genesis::decode(&data)?;
+    tracing::debug!(target: "reth::cli", ?format, "Genesis format detected");
+    progress.set_stage("compute genesis state root");
+    let genesis_header: Header = genesis.to_header()?;
+    let chain = genesis.config.chain_spec(&genesis_header);
+    let header: SealedHeader = keccak::seal(genesis_header);
+    tracing::info!(target: "reth::cli", hash = ?header.hash(), state_root = ?header.state_root, "Genesis block built");
+    if let Some(expected) = expected_hash.filter(|expected| *expected != header.hash()) {
+        eyre::bail!("The genesis block hash is {:?}, expected {expected:?}", header.hash());
+    }
+    if let Some(mut validator) = args.validator() {
+        validator.validate_genesis(&genesis, header.hash());
+        validator.finish()?;
+    }
     db.create_tables()?;
     db.update(|tx| {
-        let genesis_header: Header = genesis.to_header();
-        let header: SealedHeader = genesis_header.seal_slow();
         let genesis_block = SealedBlock { header, body: vec![], ommers: vec![], withdrawals: None };
         let _ = reth_provider::insert_canonical_block(tx, &genesis_block, false);
     })?;
 
+    progress.set_stage("insert genesis alloc");
+    progress.set_total(genesis.alloc.len() as u64);
     db.update(|tx| {
         let _ = genesis.alloc.iter().try_for_each(|(address, account)| -> eyre::Result<()> {
+            progress.advance(1)?;
             let has_code = !account.code.clone().unwrap_or_default().is_empty();
             let code_hash =
                 if has_code { Some(keccak256(&account.code.clone().unwrap())) } else { None };
@@ -73,77 +126,228 @@ pub async fn apply(db: &mut reth_db::mdbx::Env<WriteMap>, path: Option<&str>) ->
             Ok(())
         });
     })?;
+    progress.finish();
 
-    Ok(())
+    Ok(chain)
 }
 
 impl Command {
     /// Execute the command
-    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
-        let db_path = PathBuf::from(self.database);
-        let mut db = db::open_rw_env(db_path.as_path())?;
-        apply(&mut db, Some(&self.path)).await
+    pub async fn execute(mut self, _ctx: CliContext) -> Result<()> {
+        self.import = self.import.with_database(&self.db)?;
+        let db_path = self.db.path();
+        let path = self.import.input_path(self.path.as_deref(), ImportStage::Genesis)?;
+        let expected_hash =
+            self.expected_hash.or_else(|| self.db.chain.map(|chain| chain.genesis_hash()));
+        journal::record(&db_path, "genesis", &[Path::new(&path)], async {
+            self.import.preflight(&db_path, ImportStage::Genesis, Path::new(&path))?;
+            let mut db = self.db.open_rw()?;
+            let chain = apply_checked(&mut db, Some(&path), expected_hash, &self.import).await?;
+            chain.write(&db_path)?;
+            tracing::info!(target: "reth::cli", chain_id = chain.chain_id(), bedrock_block = chain.bedrock_block, "Chain spec written");
+            Ok(())
+        })
+        .await
     }
 }
 
 /// Optimism Object
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Optimism {
-    #[serde(rename = "eip1559Elasticity")]
+    #[serde(rename = "eip1559Elasticity", deserialize_with = "deserialize_quantity")]
     pub eip1559_elasticity: u64,
-    #[serde(rename = "eip1559Denominator")]
+    #[serde(rename = "eip1559Denominator", deserialize_with = "deserialize_quantity")]
     pub eip1559_denominator: u64,
+    #[serde(
+        rename = "eip1559DenominatorCanyon",
+        default,
+        deserialize_with = "deserialize_optional_quantity"
+    )]
+    pub eip1559_denominator_canyon: Option<u64>,
 }
 
-/// The genesis inner config object
+/// Genesis files without an `optimism` block use the parameters of OP Mainnet
+impl Default for Optimism {
+    fn default() -> Self {
+        Self {
+            eip1559_elasticity: OP_MAINNET_EIP1559_ELASTICITY,
+            eip1559_denominator: OP_MAINNET_EIP1559_DENOMINATOR,
+            eip1559_denominator_canyon: None,
+        }
+    }
+}
+
+/// The genesis inner config object.
+///
+/// Forks missing from the config never activate, as in geth.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GenesisConfig {
-    #[serde(rename = "ChainName")]
+    #[serde(rename = "ChainName", default)]
     pub chain_name: String,
-    #[serde(rename = "chainId")]
+    #[serde(rename = "chainId", deserialize_with = "deserialize_quantity")]
     pub chain_id: u64,
-    #[serde(rename = "homesteadBlock")]
-    pub homestead_block: u64,
-    #[serde(rename = "eip150Block")]
-    pub eip150_block: u64,
-    #[serde(rename = "eip150Hash")]
+    #[serde(
+        rename = "homesteadBlock",
+        default,
+        deserialize_with = "deserialize_optional_quantity"
+    )]
+    pub homestead_block: Option<u64>,
+    #[serde(rename = "eip150Block", default, deserialize_with = "deserialize_optional_quantity")]
+    pub eip150_block: Option<u64>,
+    #[serde(rename = "eip150Hash", default)]
     pub eip150_hash: String,
-    #[serde(rename = "eip155Block")]
-    pub eip155_block: u64,
-    #[serde(rename = "eip158Block")]
-    pub eip158_block: u64,
-    #[serde(rename = "byzantiumBlock")]
-    pub byzantium_block: u64,
-    #[serde(rename = "constantinopleBlock")]
-    pub constantinople_block: u64,
-    #[serde(rename = "petersburgBlock")]
-    pub petersburg_block: u64,
-    #[serde(rename = "istanbulBlock")]
-    pub istanbul_block: u64,
-    #[serde(rename = "muirGlacierBlock")]
-    pub muir_glacier_block: u64,
-    #[serde(rename = "berlinBlock")]
-    pub berlin_block: u64,
-    #[serde(rename = "londonBlock")]
-    pub london_block: u64,
-    #[serde(rename = "arrowGlacierBlock")]
-    pub arrow_glacier_block: u64,
-    #[serde(rename = "grayGlacierBlock")]
-    pub gray_glacier_block: u64,
-    #[serde(rename = "mergeNetsplitBlock")]
-    pub merge_netsplit_block: u64,
-    #[serde(rename = "bedrockBlock")]
+    #[serde(rename = "eip155Block", default, deserialize_with = "deserialize_optional_quantity")]
+    pub eip155_block: Option<u64>,
+    #[serde(rename = "eip158Block", default, deserialize_with = "deserialize_optional_quantity")]
+    pub eip158_block: Option<u64>,
+    #[serde(
+        rename = "byzantiumBlock",
+        default,
+        deserialize_with = "deserialize_optional_quantity"
+    )]
+    pub byzantium_block: Option<u64>,
+    #[serde(
+        rename = "constantinopleBlock",
+        default,
+        deserialize_with = "deserialize_optional_quantity"
+    )]
+    pub constantinople_block: Option<u64>,
+    #[serde(
+        rename = "petersburgBlock",
+        default,
+        deserialize_with = "deserialize_optional_quantity"
+    )]
+    pub petersburg_block: Option<u64>,
+    #[serde(rename = "istanbulBlock", default, deserialize_with = "deserialize_optional_quantity")]
+    pub istanbul_block: Option<u64>,
+    #[serde(
+        rename = "muirGlacierBlock",
+        default,
+        deserialize_with = "deserialize_optional_quantity"
+    )]
+    pub muir_glacier_block: Option<u64>,
+    #[serde(rename = "berlinBlock", default, deserialize_with = "deserialize_optional_quantity")]
+    pub berlin_block: Option<u64>,
+    #[serde(rename = "londonBlock", default, deserialize_with = "deserialize_optional_quantity")]
+    pub london_block: Option<u64>,
+    #[serde(
+        rename = "arrowGlacierBlock",
+        default,
+        deserialize_with = "deserialize_optional_quantity"
+    )]
+    pub arrow_glacier_block: Option<u64>,
+    #[serde(
+        rename = "grayGlacierBlock",
+        default,
+        deserialize_with = "deserialize_optional_quantity"
+    )]
+    pub gray_glacier_block: Option<u64>,
+    #[serde(
+        rename = "mergeNetsplitBlock",
+        default,
+        deserialize_with = "deserialize_optional_quantity"
+    )]
+    pub merge_netsplit_block: Option<u64>,
+    #[serde(rename = "bedrockBlock", default, deserialize_with = "deserialize_quantity")]
     pub bedrock_block: u64,
-    #[serde(rename = "terminalTotalDifficulty")]
-    pub terminal_total_difficulty: u64,
-    #[serde(rename = "terminalTotalDifficultyPassed")]
+    #[serde(rename = "shanghaiTime", default, deserialize_with = "deserialize_optional_quantity")]
+    pub shanghai_time: Option<u64>,
+    #[serde(rename = "regolithTime", default, deserialize_with = "deserialize_optional_quantity")]
+    pub regolith_time: Option<u64>,
+    #[serde(rename = "canyonTime", default, deserialize_with = "deserialize_optional_quantity")]
+    pub canyon_time: Option<u64>,
+    #[serde(
+        rename = "terminalTotalDifficulty",
+        default,
+        deserialize_with = "deserialize_optional_quantity"
+    )]
+    pub terminal_total_difficulty: Option<u64>,
+    #[serde(rename = "terminalTotalDifficultyPassed", default)]
     pub terminal_total_difficulty_passed: bool,
+    #[serde(default)]
     pub optimism: Optimism,
 }
 
 impl GenesisConfig {
-    pub fn map(&self) -> HashMap<String, Vec<u8>> {
-        let mut map = HashMap::new();
+    /// The activation conditions of the Ethereum hardforks configured in the genesis. Paris is
+    /// configured by the terminal total difficulty, Shanghai by its timestamp.
+    pub fn hardforks(&self) -> BTreeMap<Hardfork, ForkCondition> {
+        let blocks = [
+            (Hardfork::Homestead, self.homestead_block),
+            (Hardfork::Tangerine, self.eip150_block),
+            (Hardfork::SpuriousDragon, self.eip155_block),
+            (Hardfork::Byzantium, self.byzantium_block),
+            (Hardfork::Constantinople, self.constantinople_block),
+            (Hardfork::Petersburg, self.petersburg_block),
+            (Hardfork::Istanbul, self.istanbul_block),
+            (Hardfork::MuirGlacier, self.muir_glacier_block),
+            (Hardfork::Berlin, self.berlin_block),
+            (Hardfork::London, self.london_block),
+            (Hardfork::ArrowGlacier, self.arrow_glacier_block),
+            (Hardfork::GrayGlacier, self.gray_glacier_block),
+        ];
+        let mut forks = BTreeMap::from([(Hardfork::Frontier, ForkCondition::Block(0))]);
+        forks.extend(
+            blocks
+                .into_iter()
+                .filter_map(|(fork, block)| block.map(|block| (fork, ForkCondition::Block(block)))),
+        );
+        if let Some(total_difficulty) = self.terminal_total_difficulty {
+            forks.insert(
+                Hardfork::Paris,
+                ForkCondition::TTD {
+                    fork_block: self.merge_netsplit_block,
+                    total_difficulty: U256::from(total_difficulty),
+                },
+            );
+        }
+        if let Some(timestamp) = self.shanghai_time {
+            forks.insert(Hardfork::Shanghai, ForkCondition::Timestamp(timestamp));
+        }
+        forks
+    }
+
+    /// Builds the chain spec of the chain with the given genesis header, activating the
+    /// configured hardforks, the OP hardforks and the optimism EIP-1559 parameters.
+    ///
+    /// The legacy genesis of a migrated chain doesn't schedule the OP hardforks after bedrock, so
+    /// they default to the ones of the known chain with the same chain id. Shanghai activates
+    /// with canyon unless it is configured.
+    pub fn chain_spec(&self, genesis_header: &Header) -> OpChainSpec {
+        let preset = ChainPreset::from_chain_id(self.chain_id);
+        let regolith_time = self
+            .regolith_time
+            .or(preset.map(|preset| preset.regolith_time()))
+            .unwrap_or(OP_MAINNET_REGOLITH_TIME);
+        let canyon_time = self.canyon_time.or(preset.map(|preset| preset.canyon_time()));
+
+        let mut forks = self.hardforks();
+        if let Some(timestamp) = canyon_time {
+            forks.entry(Hardfork::Shanghai).or_insert(ForkCondition::Timestamp(timestamp));
+        }
+        let builder = ChainSpecBuilder::default()
+            .chain(Chain::Id(self.chain_id))
+            .genesis(genesis_from_header(genesis_header));
+        let inner = forks
+            .into_iter()
+            .fold(builder, |builder, (fork, condition)| builder.with_fork(fork, condition))
+            .build();
+        OpChainSpec {
+            inner: Arc::new(inner),
+            bedrock_block: self.bedrock_block,
+            regolith_time,
+            canyon_time,
+            eip1559_elasticity: self.optimism.eip1559_elasticity,
+            eip1559_denominator: self.optimism.eip1559_denominator,
+            eip1559_denominator_canyon: self
+                .optimism
+                .eip1559_denominator_canyon
+                .unwrap_or(OP_MAINNET_EIP1559_DENOMINATOR_CANYON),
+        }
+    }
+
+    pub fn map(&self) -> BTreeMap<String, Vec<u8>> {
+        let mut map = BTreeMap::new();
         let mut difficulty = vec![0u8];
         if self.terminal_total_difficulty_passed {
             difficulty = vec![1u8];
@@ -151,30 +355,63 @@ impl GenesisConfig {
         map.insert("ChainName".to_string(), self.chain_name.as_bytes().to_vec());
         map.insert("chainId".to_string(), self.chain_id.to_le_bytes().into());
         map.insert("homesteadBlock".to_string(), self.chain_id.to_le_bytes().into());
-        map.insert("eip150Block".to_string(), self.eip150_block.to_le_bytes().into());
+        map.insert(
+            "eip150Block".to_string(),
+            self.eip150_block.unwrap_or_default().to_le_bytes().into(),
+        );
         map.insert("eip150Hash".to_string(), self.eip150_hash.as_bytes().to_vec());
-        map.insert("eip155Block".to_string(), self.eip155_block.to_le_bytes().into());
-        map.insert("eip158Block".to_string(), self.eip158_block.to_le_bytes().into());
-        map.insert("byzantiumBlock".to_string(), self.byzantium_block.to_le_bytes().into());
+        map.insert(
+            "eip155Block".to_string(),
+            self.eip155_block.unwrap_or_default().to_le_bytes().into(),
+        );
+        map.insert(
+            "eip158Block".to_string(),
+            self.eip158_block.unwrap_or_default().to_le_bytes().into(),
+        );
+        map.insert(
+            "byzantiumBlock".to_string(),
+            self.byzantium_block.unwrap_or_default().to_le_bytes().into(),
+        );
         map.insert(
             "constantinopleBlock".to_string(),
-            self.constantinople_block.to_le_bytes().into(),
+            self.constantinople_block.unwrap_or_default().to_le_bytes().into(),
+        );
+        map.insert(
+            "petersburgBlock".to_string(),
+            self.petersburg_block.unwrap_or_default().to_le_bytes().into(),
+        );
+        map.insert(
+            "istanbulBlock".to_string(),
+            self.istanbul_block.unwrap_or_default().to_le_bytes().into(),
+        );
+        map.insert(
+            "muirGlacierBlock".to_string(),
+            self.muir_glacier_block.unwrap_or_default().to_le_bytes().into(),
+        );
+        map.insert(
+            "berlinBlock".to_string(),
+            self.berlin_block.unwrap_or_default().to_le_bytes().into(),
+        );
+        map.insert(
+            "londonBlock".to_string(),
+            self.london_block.unwrap_or_default().to_le_bytes().into(),
+        );
+        map.insert(
+            "arrowGlacierBlock".to_string(),
+            self.arrow_glacier_block.unwrap_or_default().to_le_bytes().into(),
+        );
+        map.insert(
+            "grayGlacierBlock".to_string(),
+            self.gray_glacier_block.unwrap_or_default().to_le_bytes().into(),
         );
-        map.insert("petersburgBlock".to_string(), self.petersburg_block.to_le_bytes().into());
-        map.insert("istanbulBlock".to_string(), self.istanbul_block.to_le_bytes().into());
-        map.insert("muirGlacierBlock".to_string(), self.muir_glacier_block.to_le_bytes().into());
-        map.insert("berlinBlock".to_string(), self.berlin_block.to_le_bytes().into());
-        map.insert("londonBlock".to_string(), self.london_block.to_le_bytes().into());
-        map.insert("arrowGlacierBlock".to_string(), self.arrow_glacier_block.to_le_bytes().into());
-        map.insert("grayGlacierBlock".to_string(), self.gray_glacier_block.to_le_bytes().into());
         map.insert(
             "mergeNetsplitBlock".to_string(),
-            self.merge_netsplit_block.to_le_bytes().into(),
+            self.merge_netsplit_block.unwrap_or_default().to_le_bytes().into(),
         );
         map.insert("bedrockBlock".to_string(), self.bedrock_block.to_le_bytes().into());
         map.insert(
             "terminalTotalDifficulty".to_string(),
-            self.terminal_total_difficulty.to_le_bytes().into(),
+            self.terminal_total_difficulty.unwrap_or_default().to_le_bytes().into(),
         );
         map.insert("terminalTotalDifficultyPassed".to_string(), difficulty);
         map.insert(
@@ -196,36 +433,168 @@ pub struct Genesis {
     pub difficulty: String,
     #[serde(rename = "gasLimit")]
     pub gas_limit: String,
+    #[serde(alias = "extraData", default)]
     pub extradata: String,
-    pub alloc: HashMap<Address, ErigonGenesisAccount>,
+    #[serde(default)]
+    pub number: Option<String>,
+    #[serde(default)]
+    pub timestamp: Option<String>,
+    #[serde(default)]
+    pub nonce: Option<String>,
+    #[serde(alias = "mixHash", default)]
+    pub mixhash: Option<H256>,
+    #[serde(default)]
+    pub coinbase: Option<Address>,
+    #[serde(rename = "baseFeePerGas", default)]
+    pub base_fee_per_gas: Option<String>,
+    pub alloc: BTreeMap<Address, ErigonGenesisAccount>,
+}
+
+/// The layout of a genesis file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenesisFormat {
+    /// The genesis of the Erigon state import, with `extradata` and a decimal difficulty
+    Erigon,
+    /// A geth `genesis.json`, with `extraData`, `mixHash` and hex-encoded quantities
+    Geth,
+}
+
+impl GenesisFormat {
+    /// Detects the layout of a genesis file from its fields
+    pub fn detect(genesis: &serde_json::Value) -> Self {
+        let geth_field = ["extraData", "mixHash", "baseFeePerGas"]
+            .iter()
+            .any(|field| genesis.get(field).is_some());
+        let hex_difficulty = genesis
+            .get("difficulty")
+            .and_then(serde_json::Value::as_str)
+            .map_or(false, |difficulty| difficulty.starts_with("0x"));
+        if geth_field || hex_difficulty {
+            GenesisFormat::Geth
+        } else {
+            GenesisFormat::Erigon
+        }
+    }
 }
 
 /// An Erigon Genesis Account
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ErigonGenesisAccount {
+    #[serde(default, deserialize_with = "deserialize_optional_quantity")]
     pub nonce: Option<u64>,
     pub balance: U256,
     pub code: Option<Bytes>,
-    pub storage: Option<HashMap<H256, H256>>,
+    pub storage: Option<BTreeMap<H256, H256>>,
 }
 
 impl Genesis {
-    pub fn to_header(&self) -> Header {
-        Header {
-            difficulty: self.difficulty.parse().unwrap(),
-            gas_limit: self.gas_limit.parse().unwrap(),
-            extra_data: reth_primitives::Bytes::from(
-                hex::decode(self.extradata.strip_prefix("0x").unwrap_or(&self.extradata)).unwrap(),
-            ),
+    /// Builds the genesis block header, committing to the state root of the allocation
+    pub fn to_header(&self) -> Result<Header> {
+        let quantity = |value: &Option<String>| value.as_deref().map_or(Ok(0), parse_quantity);
+        Ok(Header {
+            number: quantity(&self.number)?,
+            timestamp: quantity(&self.timestamp)?,
+            nonce: quantity(&self.nonce)?,
+            mix_hash: self.mixhash.unwrap_or_default(),
+            beneficiary: self.coinbase.unwrap_or_default(),
+            state_root: self.state_root()?,
+            difficulty: parse_big_quantity(&self.difficulty)?,
+            gas_limit: parse_quantity(&self.gas_limit)?,
+            base_fee_per_gas: self.base_fee_per_gas.as_deref().map(parse_quantity).transpose()?,
+            extra_data: reth_primitives::Bytes::from(hex::decode(
+                self.extradata.strip_prefix("0x").unwrap_or(&self.extradata),
+            )?),
             ..Default::default()
+        })
+    }
+
+    /// The allocation in the layout of a state export
+    pub fn exported_state(&self) -> State {
+        self.alloc
+            .iter()
+            .map(|(address, account)| {
+                let code = account.code.as_ref().filter(|code| !code.is_empty());
+                let storage = account.storage.as_ref().map(|storage| {
+                    storage
+                        .iter()
+                        .map(|(key, value)| (*key, U256::from_be_bytes(value.0)))
+                        .collect::<BTreeMap<_, _>>()
+                });
+                let exported = ExportedAccount {
+                    balance: account.balance,
+                    code_hash: code.map(keccak256),
+                    code: code.map(hex::encode),
+                    nonce: account.nonce,
+                    root: storage.as_ref().map(storage_root),
+                    storage,
+                };
+                (*address, exported)
+            })
+            .collect()
+    }
+
+    /// Computes the state root of the allocation with the state root machinery of the state
+    /// import
+    pub fn state_root(&self) -> Result<H256> {
+        state_root_hash(&self.exported_state())
+    }
+}
+
+/// Parses a quantity of the genesis file, given either as a decimal or as a `0x`-prefixed hex
+/// number
+fn parse_quantity(value: &str) -> Result<u64> {
+    Ok(match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16)?,
+        None => value.parse()?,
+    })
+}
+
+/// Parses a quantity like [parse_quantity] that may exceed 64 bits
+fn parse_big_quantity(value: &str) -> Result<U256> {
+    Ok(match value.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16)?,
+        None => U256::from_str_radix(value, 10)?,
+    })
+}
+
+/// A quantity of the genesis config, given as a JSON number or as a string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Quantity {
+    Number(u64),
+    String(String),
+}
+
+impl Quantity {
+    fn parse<E: serde::de::Error>(self) -> Result<u64, E> {
+        match self {
+            Quantity::Number(value) => Ok(value),
+            Quantity::String(value) => parse_quantity(&value).map_err(E::custom),
         }
     }
 }
 
+fn deserialize_quantity<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    Quantity::deserialize(deserializer)?.parse()
+}
+
+fn deserialize_optional_quantity<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u64>, D::Error> {
+    Option::<Quantity>::deserialize(deserializer)?.map(Quantity::parse).transpose()
+}
+
 impl Genesis {
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
         let file = File::open(path)?;
         let reader = BufReader::new(file);
         Ok(serde_json::from_reader(reader)?)
     }
+
+    /// Decodes a genesis file in either [GenesisFormat], returning the detected one
+    pub fn decode(data: &[u8]) -> Result<(Self, GenesisFormat)> {
+        let genesis: serde_json::Value = serde_json::from_slice(data)?;
+        let format = GenesisFormat::detect(&genesis);
+        Ok((serde_json::from_value(genesis)?, format))
+    }
 }
@@ -0,0 +1,306 @@
+use std::{
+    fmt, fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use eyre::Result;
+use reth::runner::CliContext;
+use reth_db::mdbx::{Env, WriteMap};
+
+use crate::cli::{
+    args::{DatabaseArgs, ImportArgs},
+    blocks::{self, BlockFormat},
+    compression,
+    db::{attest, prune::PruneCheckpoints, TempDatabase},
+    dirs, genesis, journal,
+    l1_fee::L1FeeStore,
+    preflight::ImportStage,
+    receipts,
+    regenesis::RegenesisBoundaries,
+    source::{
+        era1::Era1Source,
+        file::FileSource,
+        freezer::FreezerSource,
+        shards::{self, ShardedSource},
+        BlockSource,
+    },
+    state,
+};
+
+/// The number of bytes read from the start of a file to detect its format
+const SNIFF_LEN: u64 = 64 * 1024;
+
+/// The e2store version record every era1 archive starts with
+const E2STORE_VERSION: [u8; 8] = [0x65, 0x32, 0, 0, 0, 0, 0, 0];
+
+/// The format of an import input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// An rlp-encoded erigon block export
+    Blocks,
+    /// An rlp-encoded erigon receipt export
+    Receipts,
+    /// A JSON world state dump, keyed by address
+    State,
+    /// A JSON genesis file
+    Genesis,
+    /// A geth ancient store (freezer) directory
+    Freezer,
+    /// An era1 archive, or a directory of them
+    Era1,
+}
+
+impl InputFormat {
+    /// The import stage writing inputs of this format
+    pub fn stage(&self) -> ImportStage {
+        match self {
+            InputFormat::Blocks | InputFormat::Freezer | InputFormat::Era1 => ImportStage::Blocks,
+            InputFormat::Receipts => ImportStage::Receipts,
+            InputFormat::State => ImportStage::State,
+            InputFormat::Genesis => ImportStage::Genesis,
+        }
+    }
+}
+
+impl fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            InputFormat::Blocks => "erigon block export",
+            InputFormat::Receipts => "erigon receipt export",
+            InputFormat::State => "state JSON",
+            InputFormat::Genesis => "genesis JSON",
+            InputFormat::Freezer => "geth freezer directory",
+            InputFormat::Era1 => "era1 archive",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Import command, detecting the format of the input and running the matching importer
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The file or directory to import
+    #[arg(long, value_name = "PATH", verbatim_doc_comment)]
+    path: PathBuf,
+
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// Import blocks as a legacy segment following a regenesis, see `blocks import --regenesis`
+    #[arg(long, verbatim_doc_comment)]
+    regenesis: bool,
+
+    /// Import into a temporary copy of the database, removed afterwards, to check that the input
+    /// imports cleanly without writing the database. The copy needs as much free space next to
+    /// the database as the database takes.
+    #[arg(long, verbatim_doc_comment)]
+    dry_run: bool,
+
+    /// Start the dry run from an empty database instead of a copy of the database
+    #[arg(long, requires = "dry_run", verbatim_doc_comment)]
+    scratch_db: bool,
+
+    /// Print the attestation of the database after the import, see `db attest`
+    #[arg(long, verbatim_doc_comment)]
+    attest: bool,
+
+    #[clap(flatten)]
+    import: ImportArgs,
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(mut self, _ctx: CliContext) -> Result<()> {
+        self.import = self.import.with_database(&self.db)?;
+        if self.dry_run {
+            let mut scratch = if self.scratch_db {
+                TempDatabase::new()?
+            } else {
+                TempDatabase::copy_of(&self.db.path())?
+            };
+            tracing::info!(target: "reth::cli", path = %scratch.path().display(), "Importing into a temporary database");
+            let db_path = scratch.path().to_path_buf();
+            self.run(&mut scratch, &db_path, &db_path).await?;
+            if self.attest {
+                let pruned = PruneCheckpoints::read(&db_path)?;
+                attest::print(&attest::attest(&scratch, &pruned)?);
+            }
+            tracing::info!(target: "reth::cli", "Dry run finished, the database was not written");
+            return Ok(())
+        }
+        journal::record(&self.db.path(), "import", &[self.path.as_path()], async {
+            let mut db = self.db.open_rw()?;
+            self.run(&mut db, &self.db.path(), &self.db.static_path()?).await?;
+            if self.attest {
+                let pruned = PruneCheckpoints::read(&self.db.path())?;
+                attest::print(&attest::attest(&db, &pruned)?);
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Imports the input into `db`, whose chain spec and regenesis boundaries live at `db_path`
+    /// and whose L1 fees live below `static_path`
+    async fn run(&self, db: &mut Env<WriteMap>, db_path: &Path, static_path: &Path) -> Result<()> {
+        let format = detect_format(&self.path)?;
+        tracing::info!(target: "reth::cli", path = %self.path.display(), %format, "Detected input format");
+
+        self.import.preflight(db_path, format.stage(), &self.path)?;
+
+        let path = self.path.to_str();
+        if self.regenesis {
+            let mut source: Box<dyn BlockSource> = match format {
+                InputFormat::Blocks if shards::is_sharded(&self.path.display().to_string()) => {
+                    Box::new(ShardedSource::new(self.path.display().to_string()))
+                }
+                InputFormat::Blocks => Box::new(FileSource::new(self.path.display().to_string())),
+                InputFormat::Freezer => Box::new(FreezerSource::new(&self.path)),
+                InputFormat::Era1 => Box::new(Era1Source::new(&self.path)),
+                _ => eyre::bail!("A {format} can not be imported as a regenesis segment"),
+            };
+            let boundaries = RegenesisBoundaries::new(db_path);
+            blocks::apply_segment(db, source.as_mut(), &boundaries, &self.import).await?;
+            return Ok(())
+        }
+        match format {
+            InputFormat::Blocks => blocks::apply(db, path, &self.import).await,
+            InputFormat::Receipts => {
+                let fees = L1FeeStore::open(static_path)?;
+                receipts::apply(db, &fees, path, &self.import).await
+            }
+            InputFormat::State => state::apply(db, path, &self.import).await,
+            InputFormat::Genesis => {
+                let chain = genesis::apply(db, path, &self.import).await?;
+                chain.write(db_path)
+            }
+            InputFormat::Freezer => {
+                blocks::apply_from(db, &mut FreezerSource::new(&self.path), &self.import).await
+            }
+            InputFormat::Era1 => {
+                blocks::apply_from(db, &mut Era1Source::new(&self.path), &self.import).await
+            }
+        }
+    }
+}
+
+/// Detects the format of the given import input from its layout and leading bytes. The format of
+/// a glob pattern is the one of the first file it matches.
+pub fn detect_format(path: &Path) -> Result<InputFormat> {
+    if let Some(pattern) = path.to_str().filter(|path| shards::is_pattern(path)) {
+        let Some(first) = shards::input_files(pattern)?.into_iter().next() else {
+            eyre::bail!("No files match {pattern}")
+        };
+        return detect_format(&first)
+    }
+    if path.is_dir() {
+        return detect_dir_format(path)
+    }
+    if dirs::has_extension(path, "era1") {
+        return Ok(InputFormat::Era1)
+    }
+
+    let mut head = Vec::new();
+    compression::open(path)?.take(SNIFF_LEN).read_to_end(&mut head)?;
+    detect_bytes_format(&head)
+        .ok_or_else(|| eyre::eyre!("Unable to detect the format of {}", path.display()))
+}
+
+/// Detects the format of an input directory from the files it contains
+fn detect_dir_format(path: &Path) -> Result<InputFormat> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(path)? {
+        names.push(entry?.file_name().to_string_lossy().into_owned());
+    }
+    // The freezer keeps an index file per table, `.cidx` for compressed and `.ridx` for raw tables
+    if names.iter().any(|name| name.ends_with(".cidx") || name.ends_with(".ridx")) {
+        return Ok(InputFormat::Freezer)
+    }
+    if names.iter().any(|name| name.ends_with(".era1")) {
+        return Ok(InputFormat::Era1)
+    }
+    // An export split into block ranges has the format of its shards
+    if names.iter().any(|name| shards::parse_range(Path::new(name)).is_some()) {
+        let shards = shards::list_shards(&path.display().to_string())?;
+        return detect_format(&shards[0].path)
+    }
+    eyre::bail!("{} is neither a freezer, an era1 nor a sharded export directory", path.display())
+}
+
+/// Detects the format of a file from its leading bytes
+pub fn detect_bytes_format(head: &[u8]) -> Option<InputFormat> {
+    if head.starts_with(&E2STORE_VERSION) {
+        return Some(InputFormat::Era1)
+    }
+
+    let start = head.iter().position(|byte| !byte.is_ascii_whitespace())?;
+    if head[start] == b'{' {
+        // State dumps are keyed by address, genesis files by their fields
+        let key = first_json_key(&head[start + 1..])?;
+        return Some(if key.starts_with(b"0x") { InputFormat::State } else { InputFormat::Genesis })
+    }
+
+    if detect_block_format(head).is_some() {
+        return Some(InputFormat::Blocks)
+    }
+    // Receipt exports carry a single byte before the rlp list of receipts
+    match rlp_header(head.get(1..)?) {
+        Some((true, _)) => Some(InputFormat::Receipts),
+        _ => None,
+    }
+}
+
+/// Detects the layout of a block export from its leading bytes
+pub fn detect_block_format(head: &[u8]) -> Option<BlockFormat> {
+    let start = head.iter().position(|byte| !byte.is_ascii_whitespace())?;
+    let text = &head[start..];
+    let text = text.strip_prefix(b"0x").unwrap_or(text);
+    // Hex-encoded blocks start with the hex digits of the block and header list prefixes
+    let digits = text.iter().take(32).take_while(|byte| byte.is_ascii_hexdigit()).count();
+    if digits >= 8 {
+        let bytes = hex::decode(&text[..digits - digits % 2]).ok()?;
+        return (lists_before_parent_hash(&bytes)? == 2).then_some(BlockFormat::RlpStandard)
+    }
+
+    match lists_before_parent_hash(head)? {
+        // The export list, the first block and its header
+        3 => Some(BlockFormat::Erigon),
+        // The first block and its header
+        2 => Some(BlockFormat::Geth),
+        _ => None,
+    }
+}
+
+/// Returns the number of nested lists the bytes start with, if they are followed by a 32 byte
+/// string holding the parent hash of a header
+fn lists_before_parent_hash(head: &[u8]) -> Option<usize> {
+    let mut offset = 0;
+    let mut lists = 0;
+    while let (true, header_len) = rlp_header(head.get(offset..)?)? {
+        offset += header_len;
+        lists += 1;
+    }
+    (head.get(offset) == Some(&(0x80 + 32))).then_some(lists)
+}
+
+/// Decodes an rlp header, returning whether it starts a list and the length of the header
+fn rlp_header(bytes: &[u8]) -> Option<(bool, usize)> {
+    let prefix = *bytes.first()?;
+    match prefix {
+        0x00..=0x7f => Some((false, 0)),
+        0x80..=0xb7 => Some((false, 1)),
+        0xb8..=0xbf => Some((false, 1 + (prefix - 0xb7) as usize)),
+        0xc0..=0xf7 => Some((true, 1)),
+        0xf8..=0xff => Some((true, 1 + (prefix - 0xf7) as usize)),
+    }
+}
+
+/// Returns the first key of a JSON object, given the bytes following its opening brace
+fn first_json_key(bytes: &[u8]) -> Option<&[u8]> {
+    let start = bytes.iter().position(|byte| !byte.is_ascii_whitespace())?;
+    let rest = bytes[start..].strip_prefix(b"\"")?;
+    let end = rest.iter().position(|byte| *byte == b'"')?;
+    Some(&rest[..end])
+}
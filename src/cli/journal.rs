@@ -0,0 +1,278 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    future::Future,
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use clap::Parser;
+use eyre::Result;
+use reth::runner::CliContext;
+use reth_db::{database::Database, tables, transaction::DbTx};
+use reth_primitives::H256;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::cli::{
+    analytics,
+    args::DatabaseArgs,
+    db,
+    report::{self, ImportReport},
+    shutdown::{self, ShutdownPhase},
+    source::shards,
+};
+
+/// The file below a database path journaling the commands that wrote or verified it
+pub const JOURNAL_FILE: &str = "import-journal.jsonl";
+
+/// An input read by a journaled command
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalInput {
+    /// The path of the input as given on the command line
+    pub path: String,
+    /// The size of the input in bytes
+    pub size: u64,
+    /// The hex-encoded sha256 digest of the input, unless it is a directory
+    pub sha256: Option<String>,
+}
+
+impl JournalInput {
+    /// Hashes the input at the given path
+    pub fn read(path: &Path) -> Result<Self> {
+        let metadata = fs::metadata(path)?;
+        let sha256 = if metadata.is_dir() {
+            None
+        } else {
+            let mut hasher = Sha256::new();
+            io::copy(&mut File::open(path)?, &mut hasher)?;
+            Some(hex::encode(hasher.finalize()))
+        };
+        Ok(Self { path: path.display().to_string(), size: metadata.len(), sha256 })
+    }
+}
+
+/// A single invocation of a command that imported into or verified a database
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    /// When the command started, in seconds since the unix epoch
+    pub started_at: u64,
+    /// The name of the command, e.g. `blocks import`
+    pub command: String,
+    /// The full command line of the invocation
+    pub args: Vec<String>,
+    /// The inputs the command read
+    pub inputs: Vec<JournalInput>,
+    /// How long the command ran, in milliseconds
+    pub duration_ms: u64,
+    /// The error the command failed with, if any
+    pub error: Option<String>,
+    /// The highest canonical block in the database after the command succeeded
+    pub tip: Option<u64>,
+}
+
+impl JournalEntry {
+    /// Whether the command succeeded
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// A line-delimited JSON file next to a database, recording every import and verification that
+/// ran against it as its provenance trail.
+#[derive(Debug, Clone)]
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    /// The journal of the database at the given path
+    pub fn new(db_path: &Path) -> Self {
+        Self { path: db_path.join(JOURNAL_FILE) }
+    }
+
+    /// Appends an entry to the journal
+    pub fn append(&self, entry: &JournalEntry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Reads all entries of the journal, oldest first
+    pub fn entries(&self) -> Result<Vec<JournalEntry>> {
+        if !self.path.exists() {
+            return Ok(vec![])
+        }
+        let reader = BufReader::new(File::open(&self.path)?);
+        let mut entries = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue
+            }
+            entries.push(serde_json::from_str(&line)?);
+        }
+        Ok(entries)
+    }
+}
+
+/// Runs a command against the database at `db_path` and records the invocation, the digests of
+/// its inputs, its duration, its result and the resulting tip in the database's [Journal], and
+/// writes its [ImportReport]. Invocations cut short by a shutdown are recorded as interrupted.
+///
+/// The command holds the exclusive [lock](db::lock_db) of the database while it runs. Glob
+/// patterns are recorded as the files they match. Failing to write the journal is logged but
+/// doesn't fail the command.
+pub async fn record<F>(db_path: &Path, command: &str, inputs: &[&Path], run: F) -> Result<()>
+where
+    F: Future<Output = Result<()>>,
+{
+    let lock = db::lock_db(db_path)?;
+    let inputs = inputs
+        .iter()
+        .flat_map(|path| match path.to_str().filter(|path| shards::is_pattern(path)) {
+            Some(pattern) => shards::input_files(pattern).unwrap_or_default(),
+            None => vec![path.to_path_buf()],
+        })
+        .filter_map(|path| match JournalInput::read(&path) {
+            Ok(input) => Some(input),
+            Err(error) => {
+                tracing::debug!(target: "reth::cli", path = %path.display(), %error, "Unable to hash input for the journal");
+                None
+            }
+        })
+        .collect();
+    let started_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+    let start = Instant::now();
+    let journal = Journal::new(db_path);
+    let entry = JournalEntry {
+        started_at,
+        command: command.to_string(),
+        args: std::env::args().collect(),
+        inputs,
+        duration_ms: 0,
+        error: None,
+        tip: None,
+    };
+
+    // Record the invocation as interrupted if the process is shut down before it finishes
+    let _shutdown = shutdown::registry().register("import journal", ShutdownPhase::Database, {
+        let (journal, entry) = (journal.clone(), entry.clone());
+        move || async move {
+            let duration_ms = start.elapsed().as_millis() as u64;
+            let error = Some("Interrupted".to_string());
+            journal.append(&JournalEntry { duration_ms, error, ..entry })
+        }
+    });
+
+    report::reset();
+    let result = run.await;
+    drop(lock);
+
+    let duration_ms = start.elapsed().as_millis() as u64;
+    // The command dropped its environment when it finished, so it can be opened again
+    let tip = match &result {
+        Ok(()) => read_tip(db_path).unwrap_or_else(|error| {
+            tracing::debug!(target: "reth::cli", %error, "Unable to read the tip for the journal");
+            None
+        }),
+        Err(_) => None,
+    };
+    let error = result.as_ref().err().map(|error| format!("{error:#}"));
+    let entry = JournalEntry { duration_ms, error, tip: tip.map(|(tip, ..)| tip), ..entry };
+    if let Err(error) = journal.append(&entry) {
+        tracing::warn!(target: "reth::cli", %error, "Failed to write the import journal");
+    }
+
+    let collected = report::take();
+    let report = ImportReport {
+        command: entry.command,
+        args: entry.args,
+        started_at: entry.started_at,
+        duration_ms,
+        inputs: entry.inputs,
+        stages: collected.stages,
+        writes: collected.writes,
+        rejected: collected.rejected,
+        error: entry.error,
+        tip: entry.tip,
+        tip_hash: tip.map(|(_, hash, _)| hash),
+        state_root: tip.map(|(.., root)| root),
+    };
+    match report.write(db_path) {
+        Ok(path) => {
+            tracing::info!(target: "reth::cli", path = %path.display(), "Import report written")
+        }
+        Err(error) => {
+            tracing::warn!(target: "reth::cli", %error, "Failed to write the import report")
+        }
+    }
+    result
+}
+
+/// Reads the highest canonical block of the database at `db_path`, its hash and its state root
+fn read_tip(db_path: &Path) -> Result<Option<(u64, H256, H256)>> {
+    let db = db::open_ro_env(db_path)?;
+    let Some(tip) = analytics::canonical_tip(&db)? else { return Ok(None) };
+    let tx = db.tx()?;
+    let hash = tx.get::<tables::CanonicalHeaders>(tip)?.unwrap_or_default();
+    let state_root = tx.get::<tables::Headers>(tip)?.map(|header| header.state_root);
+    Ok(Some((tip, hash, state_root.unwrap_or_default())))
+}
+
+/// Show the journal of imports and verifications that ran against the database
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// Print the entries as line-delimited JSON
+    #[arg(long, verbatim_doc_comment)]
+    json: bool,
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let entries = Journal::new(&self.db.path()).entries()?;
+        if entries.is_empty() {
+            println!("No journaled commands found");
+            return Ok(())
+        }
+
+        for entry in entries {
+            if self.json {
+                println!("{}", serde_json::to_string(&entry)?);
+                continue
+            }
+
+            let started_at = UNIX_EPOCH + Duration::from_secs(entry.started_at);
+            let duration = Duration::from_secs(entry.duration_ms / 1000);
+            let result = entry.error.as_deref().unwrap_or("ok");
+            println!(
+                "{} {} ({}) -> {}",
+                humantime::format_rfc3339_seconds(started_at),
+                entry.command,
+                humantime::format_duration(duration),
+                result
+            );
+            for input in &entry.inputs {
+                let digest = input.sha256.as_deref().unwrap_or("-");
+                println!("    input {} ({} bytes) sha256 {digest}", input.path, input.size);
+            }
+            if let Some(tip) = entry.tip {
+                println!("    tip   {tip}");
+            }
+            println!("    args  {}", entry.args.join(" "));
+        }
+        Ok(())
+    }
+}
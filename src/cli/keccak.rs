@@ -0,0 +1,45 @@
+use hash_db::Hasher;
+use reth_primitives::{proofs::KeccakHasher, Header, SealedHeader, H256};
+use reth_rlp::Encodable;
+
+/// The keccak implementation selected at build time.
+///
+/// The `asm-keccak` feature hashes with the `sha3` crate, which uses the SHA-3 instructions of
+/// ARMv8.2 CPUs that have them, detected at runtime. Other CPUs fall back to the portable
+/// implementation of the same crate. It is not known to be faster than the default backend.
+pub const BACKEND: &str = if cfg!(feature = "asm-keccak") { "sha3-asm" } else { "tiny-keccak" };
+
+/// Computes the keccak-256 hash of the input with the selected [BACKEND]
+#[cfg(feature = "asm-keccak")]
+pub fn keccak256(data: impl AsRef<[u8]>) -> H256 {
+    use sha3::Digest;
+    H256(sha3::Keccak256::digest(data.as_ref()).into())
+}
+
+/// Computes the keccak-256 hash of the input with the selected [BACKEND]
+#[cfg(not(feature = "asm-keccak"))]
+pub fn keccak256(data: impl AsRef<[u8]>) -> H256 {
+    reth_primitives::keccak256(data)
+}
+
+/// Seals the header with its hash computed by [keccak256]
+pub fn seal(header: Header) -> SealedHeader {
+    let mut encoded = Vec::with_capacity(header.length());
+    header.encode(&mut encoded);
+    let hash = keccak256(&encoded);
+    SealedHeader::new(header, hash)
+}
+
+/// The hasher of the state and storage tries, hashing with [keccak256]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Keccak;
+
+impl Hasher for Keccak {
+    type Out = H256;
+    type StdHasher = <KeccakHasher as Hasher>::StdHasher;
+    const LENGTH: usize = 32;
+
+    fn hash(data: &[u8]) -> H256 {
+        keccak256(data)
+    }
+}
@@ -0,0 +1,194 @@
+use eyre::Result;
+use reth_db::{cursor::DbDupCursorRO, tables, transaction::DbTx};
+use reth_primitives::{SealedBlock, TransactionKind, TransactionSigned, H256, U256};
+
+use crate::cli::{
+    chain::{self, OpChainSpec},
+    deposit::{self, TxDeposit},
+    l1_fee::L1FeeInfo,
+};
+
+/// The storage slot of the L1 base fee in the L1Block predeploy
+pub const L1_BASE_FEE_SLOT: u8 = 1;
+
+/// The storage slot of the L1 fee overhead in the L1Block predeploy
+pub const L1_FEE_OVERHEAD_SLOT: u8 = 5;
+
+/// The storage slot of the L1 fee scalar in the L1Block predeploy
+pub const L1_FEE_SCALAR_SLOT: u8 = 6;
+
+/// The selector of `setL1BlockValues`, called by the L1 attributes deposit of every bedrock block
+pub const SET_L1_BLOCK_VALUES_SELECTOR: [u8; 4] = [0x01, 0x5d, 0x8e, 0xb9];
+
+/// The L1 gas charged for a zero byte of a transaction
+pub const ZERO_BYTE_GAS: u64 = 4;
+
+/// The L1 gas charged for a non-zero byte of a transaction
+pub const NON_ZERO_BYTE_GAS: u64 = 16;
+
+/// The L1 gas charged for the signature of a transaction before regolith, on top of its bytes
+pub const SIGNATURE_GAS: u64 = 68 * NON_ZERO_BYTE_GAS;
+
+/// The fee scalar is a fixed-point number with this many decimals
+pub const FEE_SCALAR_DECIMALS: u32 = 6;
+
+/// The L1 fee parameters of a bedrock block, set by its L1 attributes deposit in the L1Block
+/// predeploy.
+///
+/// Every transaction except deposits pays for the L1 gas its data takes when batched to L1:
+/// `(data gas + overhead) * L1 base fee * scalar / 10^6`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct L1BlockInfo {
+    /// The base fee of the L1 origin of the block
+    pub l1_base_fee: U256,
+    /// The L1 gas added to the data gas of every transaction
+    pub l1_fee_overhead: U256,
+    /// The fee scalar, with [FEE_SCALAR_DECIMALS] decimals
+    pub l1_fee_scalar: U256,
+}
+
+impl L1BlockInfo {
+    /// Reads the L1 fee parameters from the storage of the L1Block predeploy
+    pub fn read<'a, TX: DbTx<'a>>(tx: &TX) -> Result<Self> {
+        let l1_block = chain::l1_block_address();
+        let mut storage = tx.cursor_dup_read::<tables::PlainStorageState>()?;
+        let mut slot = |index: u8| -> Result<U256> {
+            let key = H256::from_low_u64_be(index as u64);
+            let entry = storage.seek_by_key_subkey(l1_block, key)?;
+            Ok(entry.filter(|entry| entry.key == key).map_or(U256::ZERO, |entry| entry.value))
+        };
+        Ok(Self {
+            l1_base_fee: slot(L1_BASE_FEE_SLOT)?,
+            l1_fee_overhead: slot(L1_FEE_OVERHEAD_SLOT)?,
+            l1_fee_scalar: slot(L1_FEE_SCALAR_SLOT)?,
+        })
+    }
+
+    /// Decodes the L1 fee parameters from the call data of an L1 attributes deposit, a call to
+    /// `setL1BlockValues(number, timestamp, basefee, hash, sequenceNumber, batcherHash,
+    /// l1FeeOverhead, l1FeeScalar)`
+    pub fn from_attributes(input: &[u8]) -> Result<Self> {
+        let Some(args) = input.strip_prefix(&SET_L1_BLOCK_VALUES_SELECTOR) else {
+            eyre::bail!("Not a call to setL1BlockValues")
+        };
+        if args.len() != 8 * 32 {
+            eyre::bail!("setL1BlockValues takes 8 arguments, got {} bytes", args.len());
+        }
+        let word = |index: usize| U256::from_be_slice(&args[index * 32..(index + 1) * 32]);
+        Ok(Self { l1_base_fee: word(2), l1_fee_overhead: word(6), l1_fee_scalar: word(7) })
+    }
+
+    /// Reads the L1 fee parameters of a bedrock block from its L1 attributes deposit, which is
+    /// the first transaction of the block. Returns `None` if the block doesn't start with one.
+    pub fn from_block(block: &SealedBlock) -> Result<Option<Self>> {
+        let Some(deposit) = block.body.first().and_then(TxDeposit::from_carrier) else {
+            return Ok(None)
+        };
+        if deposit.to != TransactionKind::Call(chain::l1_block_address()) {
+            return Ok(None)
+        }
+        Self::from_attributes(&deposit.input).map(Some)
+    }
+
+    /// The L1 gas of a transaction: the gas of its data plus the overhead
+    pub fn l1_gas_used(&self, envelope: &[u8], regolith: bool) -> U256 {
+        U256::from(data_gas(envelope, regolith)) + self.l1_fee_overhead
+    }
+
+    /// The L1 fee of the transaction with the given envelope
+    pub fn l1_cost(&self, envelope: &[u8], regolith: bool) -> U256 {
+        self.l1_gas_used(envelope, regolith) * self.l1_base_fee * self.l1_fee_scalar /
+            U256::from(10u64.pow(FEE_SCALAR_DECIMALS))
+    }
+
+    /// The L1 fee fields of the receipt of a transaction, `None` for deposits, which pay no L1
+    /// fee
+    pub fn fee_info(&self, transaction: &TransactionSigned, regolith: bool) -> Option<L1FeeInfo> {
+        if deposit::is_deposit(transaction) {
+            return None
+        }
+        let envelope = deposit::encode_tx(transaction);
+        Some(L1FeeInfo {
+            l1_gas_price: self.l1_base_fee,
+            l1_gas_used: self.l1_gas_used(&envelope, regolith),
+            l1_fee: self.l1_cost(&envelope, regolith),
+            l1_fee_scalar: format_scalar(self.l1_fee_scalar),
+        })
+    }
+}
+
+/// The L1 gas of the bytes of a transaction envelope. Before regolith the gas of a signature is
+/// added.
+pub fn data_gas(envelope: &[u8], regolith: bool) -> u64 {
+    let zeroes = envelope.iter().filter(|byte| **byte == 0).count() as u64;
+    let non_zeroes = envelope.len() as u64 - zeroes;
+    let gas = zeroes * ZERO_BYTE_GAS + non_zeroes * NON_ZERO_BYTE_GAS;
+    if regolith {
+        gas
+    } else {
+        gas + SIGNATURE_GAS
+    }
+}
+
+/// Computes the L1 fee fields of the receipts of a bedrock block, keyed by the index of their
+/// transaction. Deposits pay no L1 fee and are left out. Empty if the block is not a bedrock
+/// block or doesn't start with an L1 attributes deposit.
+pub fn block_fee_infos(
+    block: &SealedBlock,
+    chain: &OpChainSpec,
+) -> Result<Vec<(usize, L1FeeInfo)>> {
+    if !chain.is_bedrock_active_at_block(block.number) {
+        return Ok(vec![])
+    }
+    let Some(info) = L1BlockInfo::from_block(block)? else { return Ok(vec![]) };
+    let regolith = chain.is_regolith_active(block.number, block.timestamp);
+    Ok(block
+        .body
+        .iter()
+        .enumerate()
+        .filter_map(|(index, transaction)| Some((index, info.fee_info(transaction, regolith)?)))
+        .collect())
+}
+
+/// Formats a fee scalar with [FEE_SCALAR_DECIMALS] decimals as the decimal string of receipts,
+/// e.g. `1.5`
+pub fn format_scalar(scalar: U256) -> String {
+    let unit = U256::from(10u64.pow(FEE_SCALAR_DECIMALS));
+    let (integer, fraction) = (scalar / unit, scalar % unit);
+    if fraction == U256::ZERO {
+        return integer.to_string()
+    }
+    let fraction =
+        format!("{:0>width$}", fraction.to_string(), width = FEE_SCALAR_DECIMALS as usize);
+    format!("{integer}.{}", fraction.trim_end_matches('0'))
+}
+
+/// Parses the decimal fee scalar of a receipt, such as `1.5`, into a numerator and a power of ten
+/// denominator
+pub fn parse_scalar(scalar: &str) -> Result<(U256, U256)> {
+    let (integer, fraction) = scalar.split_once('.').unwrap_or((scalar, ""));
+    let digits = format!("{integer}{fraction}");
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        eyre::bail!("Invalid L1 fee scalar {scalar:?}");
+    }
+    let numerator = U256::from_str_radix(&digits, 10)
+        .map_err(|err| eyre::eyre!("Invalid L1 fee scalar {scalar:?}: {err}"))?;
+    let denominator = U256::from(10u64).pow(U256::from(fraction.len()));
+    Ok((numerator, denominator))
+}
+
+/// The L1 fee implied by the other L1 fee fields of a receipt: `l1GasPrice * l1GasUsed *
+/// l1FeeScalar`, rounded down
+pub fn expected_l1_fee(info: &L1FeeInfo) -> Result<U256> {
+    let (numerator, denominator) = parse_scalar(&info.l1_fee_scalar)?;
+    Ok(info.l1_gas_price * info.l1_gas_used * numerator / denominator)
+}
+
+/// Checks that the L1 fee of a receipt matches its other L1 fee fields. l2geth computed the fee
+/// with floating point numbers, so it may be off by one wei.
+pub fn check_l1_fee(info: &L1FeeInfo) -> Result<bool> {
+    let expected = expected_l1_fee(info)?;
+    let difference =
+        if expected > info.l1_fee { expected - info.l1_fee } else { info.l1_fee - expected };
+    Ok(difference <= U256::from(1))
+}
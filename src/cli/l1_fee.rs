@@ -0,0 +1,290 @@
+use std::{borrow::Cow, fs, path::Path};
+
+use eyre::Result;
+use reth_db::mdbx::{DatabaseFlags, Env, EnvKind, WriteFlags, WriteMap};
+use reth_primitives::{TxNumber, H256, U256};
+use reth_rlp::{Decodable, Encodable, RlpDecodable, RlpEncodable};
+use serde::{Deserialize, Serialize};
+
+/// The directory below the static data path of a database holding the [L1FeeStore]
+pub const L1_FEES_DIR: &str = "l1-fees";
+
+/// The sub-database of the [L1FeeStore] recording the transactions whose fee fields were committed
+/// before their receipts, see [L1FeeStore::reconcile]
+const PENDING_DB: &str = "pending";
+
+/// The sub-database of the [L1FeeStore] recording the transactions whose fee fields were staged
+/// with a database transaction that is not committed yet, see [L1FeeStore::stage]
+const STAGED_DB: &str = "staged";
+
+/// The sub-database of the [L1FeeStore] keeping the fee fields the staged ones replaced
+const REPLACED_DB: &str = "replaced";
+
+/// The sub-database of the [L1FeeStore] holding the head block the staged fee fields belong to
+const STAGED_HEAD_DB: &str = "staged-head";
+
+/// The key of the head block in the [STAGED_HEAD_DB]
+const STAGED_HEAD_KEY: &[u8] = b"head";
+
+/// The L1 data fee fields of an OP receipt.
+///
+/// Legacy l2geth returns these with every receipt, but the reth receipt has no place for them.
+#[derive(
+    Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, RlpEncodable, RlpDecodable,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct L1FeeInfo {
+    /// The L1 gas price at the time the transaction was submitted
+    pub l1_gas_price: U256,
+    /// The L1 gas used for the transaction data
+    pub l1_gas_used: U256,
+    /// The L1 fee paid by the transaction
+    pub l1_fee: U256,
+    /// The fee scalar applied to the L1 fee, a decimal string such as `1.5`
+    pub l1_fee_scalar: String,
+}
+
+/// Extension store holding the [L1FeeInfo] of OP receipts, keyed by transaction number.
+///
+/// reth's table set is fixed, so the fee fields live in a separate MDBX environment below the
+/// static data path of the database, which defaults to the database path.
+#[derive(Debug)]
+pub struct L1FeeStore {
+    env: Env<WriteMap>,
+}
+
+impl L1FeeStore {
+    /// Opens the store below the given static data path of a database, creating it if necessary.
+    /// Unless configured otherwise, that is the database path itself.
+    pub fn open(static_path: &Path) -> Result<Self> {
+        let path = static_path.join(L1_FEES_DIR);
+        fs::create_dir_all(&path)?;
+        let env = Env::<WriteMap>::open(&path, EnvKind::RW).map_err(|e| eyre::eyre!(e))?;
+
+        let tx = env.inner.begin_rw_txn()?;
+        tx.create_db(None, DatabaseFlags::default())?;
+        tx.create_db(Some(PENDING_DB), DatabaseFlags::default())?;
+        for name in [STAGED_DB, REPLACED_DB, STAGED_HEAD_DB] {
+            tx.create_db(Some(name), DatabaseFlags::default())?;
+        }
+        tx.commit()?;
+        Ok(Self { env })
+    }
+
+    /// Stores the fee fields of the given transactions in a single transaction. Returns the number
+    /// of stored entries.
+    pub fn insert(&self, fees: impl IntoIterator<Item = (TxNumber, L1FeeInfo)>) -> Result<usize> {
+        let tx = self.env.inner.begin_rw_txn()?;
+        let db = tx.open_db(None)?;
+        let mut count = 0;
+        for (tx_id, info) in fees {
+            let mut value = Vec::with_capacity(info.length());
+            info.encode(&mut value);
+            tx.put(&db, tx_id.to_be_bytes(), value, WriteFlags::UPSERT)?;
+            count += 1;
+        }
+        tx.commit()?;
+        Ok(count)
+    }
+
+    /// Stores the fee fields of a batch of receipts before the receipts are committed, recording
+    /// their transactions as pending in the same transaction. Returns the number of stored
+    /// entries.
+    ///
+    /// The store is a separate environment, so the fees and the receipts can't be committed
+    /// atomically. Once the receipts are committed, [L1FeeStore::clear_pending] marks the fees as
+    /// final, otherwise [L1FeeStore::reconcile] removes them on the next import.
+    pub fn insert_pending(&self, fees: &[(TxNumber, L1FeeInfo)]) -> Result<usize> {
+        let tx = self.env.inner.begin_rw_txn()?;
+        let db = tx.open_db(None)?;
+        let pending = tx.open_db(Some(PENDING_DB))?;
+        for (tx_id, info) in fees {
+            let mut value = Vec::with_capacity(info.length());
+            info.encode(&mut value);
+            tx.put(&db, tx_id.to_be_bytes(), value, WriteFlags::UPSERT)?;
+            tx.put(&pending, tx_id.to_be_bytes(), b"", WriteFlags::UPSERT)?;
+        }
+        tx.commit()?;
+        Ok(fees.len())
+    }
+
+    /// Marks the pending fee fields as final, once the receipts of their batch are committed
+    pub fn clear_pending(&self) -> Result<()> {
+        let tx = self.env.inner.begin_rw_txn()?;
+        let pending = tx.open_db(Some(PENDING_DB))?;
+        tx.clear_db(&pending)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Removes the pending fee fields of the transactions for which `has_receipt` returns false,
+    /// left behind by an import interrupted between committing the fees and the receipts of a
+    /// batch, and marks the others as final. Returns the number of removed entries.
+    pub fn reconcile(&self, mut has_receipt: impl FnMut(TxNumber) -> Result<bool>) -> Result<u64> {
+        let tx = self.env.inner.begin_rw_txn()?;
+        let db = tx.open_db(None)?;
+        let pending = tx.open_db(Some(PENDING_DB))?;
+        let mut tx_ids = Vec::new();
+        {
+            let mut cursor = tx.cursor(&pending)?;
+            for entry in cursor.iter_start::<Cow<'_, [u8]>, Cow<'_, [u8]>>() {
+                let (key, _) = entry?;
+                tx_ids.push(TxNumber::from_be_bytes(key.as_ref().try_into()?));
+            }
+        }
+        let mut removed = 0;
+        for tx_id in tx_ids {
+            if !has_receipt(tx_id)? && tx.del(&db, tx_id.to_be_bytes(), None)? {
+                removed += 1;
+            }
+        }
+        tx.clear_db(&pending)?;
+        tx.commit()?;
+        Ok(removed)
+    }
+
+    /// Replaces the fee fields of the transactions numbered from `from` on with the given ones,
+    /// before the database transaction that unwinds and inserts their blocks with `head` as the new
+    /// canonical head is committed.
+    ///
+    /// The store is a separate environment, so the replaced fee fields are kept until
+    /// [L1FeeStore::commit_staged] drops them once the blocks are committed, or
+    /// [L1FeeStore::rollback_staged] restores them if they aren't. After a crash in between,
+    /// [L1FeeStore::recover_staged] does either depending on whether `head` became the head.
+    pub fn stage(&self, from: TxNumber, fees: &[(TxNumber, L1FeeInfo)], head: H256) -> Result<()> {
+        let tx = self.env.inner.begin_rw_txn()?;
+        let db = tx.open_db(None)?;
+        let staged = tx.open_db(Some(STAGED_DB))?;
+        let replaced = tx.open_db(Some(REPLACED_DB))?;
+        let staged_head = tx.open_db(Some(STAGED_HEAD_DB))?;
+        if tx.get::<Cow<'_, [u8]>>(&staged_head, STAGED_HEAD_KEY)?.is_some() {
+            eyre::bail!(
+                "The L1 fees staged for an earlier head were neither committed nor rolled back"
+            )
+        }
+
+        let mut entries = Vec::new();
+        {
+            let mut cursor = tx.cursor(&db)?;
+            for entry in cursor.iter_from::<Cow<'_, [u8]>, Cow<'_, [u8]>>(&from.to_be_bytes()) {
+                let (key, value) = entry?;
+                entries.push((key.into_owned(), value.into_owned()));
+            }
+        }
+        for (key, value) in &entries {
+            tx.put(&replaced, key, value, WriteFlags::UPSERT)?;
+            tx.del(&db, key, None)?;
+        }
+        for (tx_id, info) in fees {
+            let mut value = Vec::with_capacity(info.length());
+            info.encode(&mut value);
+            tx.put(&db, tx_id.to_be_bytes(), value, WriteFlags::UPSERT)?;
+            tx.put(&staged, tx_id.to_be_bytes(), b"", WriteFlags::UPSERT)?;
+        }
+        tx.put(&staged_head, STAGED_HEAD_KEY, head.as_bytes(), WriteFlags::UPSERT)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Drops the fee fields replaced by [L1FeeStore::stage], once the blocks of the staged fee
+    /// fields are committed
+    pub fn commit_staged(&self) -> Result<()> {
+        let tx = self.env.inner.begin_rw_txn()?;
+        for name in [STAGED_DB, REPLACED_DB, STAGED_HEAD_DB] {
+            let db = tx.open_db(Some(name))?;
+            tx.clear_db(&db)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Removes the fee fields staged by [L1FeeStore::stage] and restores the ones they replaced,
+    /// if the blocks of the staged fee fields were not committed
+    pub fn rollback_staged(&self) -> Result<()> {
+        let tx = self.env.inner.begin_rw_txn()?;
+        let db = tx.open_db(None)?;
+        let staged = tx.open_db(Some(STAGED_DB))?;
+        let replaced = tx.open_db(Some(REPLACED_DB))?;
+        let staged_head = tx.open_db(Some(STAGED_HEAD_DB))?;
+        let mut keys = Vec::new();
+        {
+            let mut cursor = tx.cursor(&staged)?;
+            for entry in cursor.iter_start::<Cow<'_, [u8]>, Cow<'_, [u8]>>() {
+                keys.push(entry?.0.into_owned());
+            }
+        }
+        for key in &keys {
+            tx.del(&db, key, None)?;
+        }
+        let mut entries = Vec::new();
+        {
+            let mut cursor = tx.cursor(&replaced)?;
+            for entry in cursor.iter_start::<Cow<'_, [u8]>, Cow<'_, [u8]>>() {
+                let (key, value) = entry?;
+                entries.push((key.into_owned(), value.into_owned()));
+            }
+        }
+        for (key, value) in &entries {
+            tx.put(&db, key, value, WriteFlags::UPSERT)?;
+        }
+        for db in [staged, replaced, staged_head] {
+            tx.clear_db(&db)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Resolves fee fields left staged by a crash between [L1FeeStore::stage] and committing
+    /// their blocks: they are kept if `is_head` says their head block became the head of the
+    /// database, and rolled back otherwise. Returns whether there were staged fee fields.
+    pub fn recover_staged(&self, is_head: impl FnOnce(H256) -> Result<bool>) -> Result<bool> {
+        let head = {
+            let tx = self.env.inner.begin_ro_txn()?;
+            let staged_head = tx.open_db(Some(STAGED_HEAD_DB))?;
+            let head = tx.get::<Cow<'_, [u8]>>(&staged_head, STAGED_HEAD_KEY)?;
+            head.map(|head| H256::from_slice(&head))
+        };
+        let Some(head) = head else { return Ok(false) };
+        if is_head(head)? {
+            self.commit_staged()?;
+        } else {
+            self.rollback_staged()?;
+        }
+        Ok(true)
+    }
+
+    /// Returns the fee fields of the given transaction
+    pub fn get(&self, tx_id: TxNumber) -> Result<Option<L1FeeInfo>> {
+        let tx = self.env.inner.begin_ro_txn()?;
+        let db = tx.open_db(None)?;
+        let Some(value) = tx.get::<Cow<'_, [u8]>>(&db, &tx_id.to_be_bytes())? else {
+            return Ok(None)
+        };
+        Ok(Some(L1FeeInfo::decode(&mut value.as_ref())?))
+    }
+
+    /// Removes the fee fields of the transactions numbered below `tx_id`. Returns the number of
+    /// removed entries.
+    pub fn remove_before(&self, tx_id: TxNumber) -> Result<u64> {
+        let tx = self.env.inner.begin_rw_txn()?;
+        let db = tx.open_db(None)?;
+        let boundary = tx_id.to_be_bytes();
+        let mut keys = Vec::new();
+        {
+            let mut cursor = tx.cursor(&db)?;
+            for entry in cursor.iter_start::<Cow<'_, [u8]>, Cow<'_, [u8]>>() {
+                let (key, _) = entry?;
+                // Keys are big-endian, so they sort like the numbers they encode
+                if key.as_ref() >= &boundary[..] {
+                    break
+                }
+                keys.push(key.into_owned());
+            }
+        }
+        for key in &keys {
+            tx.del(&db, key, None)?;
+        }
+        tx.commit()?;
+        Ok(keys.len() as u64)
+    }
+}
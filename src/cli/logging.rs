@@ -0,0 +1,33 @@
+use clap::ValueEnum;
+use reth_tracing::BoxedLayer;
+use tracing::Subscriber;
+use tracing_subscriber::{filter::Directive, registry::LookupSpan, EnvFilter, Layer};
+
+/// The format of the logs written to stdout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, with the fields of every event
+    Json,
+}
+
+impl LogFormat {
+    /// The layer writing the logs to stdout in this format, filtered by `RUST_LOG` with the given
+    /// default directive
+    pub fn stdout<S>(self, default_directive: Directive) -> BoxedLayer<S>
+    where
+        S: Subscriber,
+        for<'a> S: LookupSpan<'a>,
+    {
+        match self {
+            LogFormat::Text => reth_tracing::stdout(default_directive),
+            LogFormat::Json => {
+                let filter =
+                    EnvFilter::builder().with_default_directive(default_directive).from_env_lossy();
+                tracing_subscriber::fmt::layer().json().with_filter(filter).boxed()
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use eyre::{Result, WrapErr};
+use metrics::{describe_counter, describe_gauge, Unit};
+use metrics_exporter_prometheus::PrometheusBuilder;
+use once_cell::sync::OnceCell;
+use reth_db::{table::Table, tables};
+
+use crate::cli::{
+    progress::ImportProgress,
+    shutdown::{self, ShutdownGuard, ShutdownPhase},
+};
+
+/// How often the [MetricsReporter] samples the progress of an import
+pub const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The address the exporter listens on, set once it was installed
+static EXPORTER: OnceCell<SocketAddr> = OnceCell::new();
+
+/// Starts the Prometheus exporter serving the metrics of the process on `addr`.
+///
+/// The exporter is installed once per process, later calls with the same address do nothing and
+/// calls with another address fail.
+pub fn install(addr: SocketAddr) -> Result<()> {
+    let installed = EXPORTER.get_or_try_init(|| -> Result<SocketAddr> {
+        PrometheusBuilder::new()
+            .with_http_listener(addr)
+            .install()
+            .wrap_err_with(|| format!("Could not start the metrics exporter on {addr}"))?;
+        describe();
+        tracing::info!(target: "reth::cli", %addr, "Serving metrics");
+        Ok(addr)
+    })?;
+    if *installed != addr {
+        eyre::bail!("The metrics exporter is serving on {installed} already, not on {addr}");
+    }
+    Ok(())
+}
+
+fn describe() {
+    describe_counter!("import_items_total", "Items processed by an import stage");
+    describe_counter!("import_blocks_total", "Blocks imported");
+    describe_counter!("import_receipts_total", "Receipts imported");
+    describe_counter!("import_accounts_total", "Accounts written");
+    describe_counter!("import_table_inserts_total", "Records inserted into a database table");
+    describe_counter!(
+        "import_table_bytes_total",
+        Unit::Bytes,
+        "Approximate bytes written to a database table"
+    );
+    describe_gauge!("import_items_per_second", "Items processed per second by the current stage");
+    describe_gauge!("import_block", "The number of the block being processed");
+    describe_gauge!("import_db_size_bytes", Unit::Bytes, "The size of the database files");
+    describe_counter!("rpc_cache_hits_total", "JSON-RPC responses served from a cache");
+    describe_counter!("rpc_cache_misses_total", "JSON-RPC responses loaded from the database");
+    describe_gauge!("rpc_cache_entries", "The number of entries held by a JSON-RPC cache");
+}
+
+/// Counts `items` processed items of the given stage
+pub fn record_items(stage: &'static str, items: u64) {
+    metrics::counter!("import_items_total", items, "stage" => stage);
+}
+
+/// Counts the records written to the given table. Headers, receipts and plain accounts are also
+/// counted as imported blocks, receipts and written accounts.
+pub fn record_writes(table: &'static str, inserts: u64, bytes: u64) {
+    metrics::counter!("import_table_inserts_total", inserts, "table" => table);
+    metrics::counter!("import_table_bytes_total", bytes, "table" => table);
+    if table == tables::Headers::NAME {
+        metrics::counter!("import_blocks_total", inserts);
+    } else if table == tables::Receipts::NAME {
+        metrics::counter!("import_receipts_total", inserts);
+    } else if table == tables::PlainAccountState::NAME {
+        metrics::counter!("import_accounts_total", inserts);
+    }
+}
+
+/// The total size of the files in the database directory at `path`
+pub fn db_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else { return 0 };
+    entries
+        .filter_map(|entry| entry.ok()?.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Publishes the throughput of an import, its current block and the size of its database as
+/// gauges, sampled from an [ImportProgress] on a background thread. The reporter stops when
+/// dropped or when the process shuts down.
+#[derive(Debug)]
+pub struct MetricsReporter {
+    shutdown: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+    _hook: ShutdownGuard<'static>,
+}
+
+impl MetricsReporter {
+    /// Spawns a reporter sampling the given progress and the database at `db_path`, if known
+    pub fn spawn(progress: Arc<ImportProgress>, db_path: Option<PathBuf>) -> Self {
+        let (shutdown, stop) = mpsc::channel();
+        let handle = thread::Builder::new()
+            .name("import-metrics".to_string())
+            .spawn(move || report(&progress, db_path.as_deref(), stop))
+            .expect("failed to spawn metrics thread");
+        let hook = shutdown::registry().register("metrics reporter", ShutdownPhase::Metrics, {
+            let shutdown = shutdown.clone();
+            move || async move {
+                let _ = shutdown.send(());
+                Ok(())
+            }
+        });
+        Self { shutdown: Some(shutdown), handle: Some(handle), _hook: hook }
+    }
+}
+
+impl Drop for MetricsReporter {
+    fn drop(&mut self) {
+        drop(self.shutdown.take());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn report(progress: &ImportProgress, db_path: Option<&Path>, stop: mpsc::Receiver<()>) {
+    let mut last = (progress.stage(), progress.items(), Instant::now());
+    loop {
+        match stop.recv_timeout(REPORT_INTERVAL) {
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            _ => return,
+        }
+        let (stage, items, now) = (progress.stage(), progress.items(), Instant::now());
+        let (last_stage, last_items, sampled_at) = last;
+        let elapsed = now.duration_since(sampled_at).as_secs_f64().max(f64::EPSILON);
+        let rate = items.saturating_sub(last_items) as f64 / elapsed;
+        if last_stage != stage {
+            metrics::gauge!("import_items_per_second", 0.0, "stage" => last_stage);
+        }
+        metrics::gauge!("import_items_per_second", rate, "stage" => stage);
+        metrics::gauge!("import_block", progress.block() as f64);
+        if let Some(path) = db_path {
+            metrics::gauge!("import_db_size_bytes", db_size(path) as f64);
+        }
+        last = (stage, items, now);
+    }
+}
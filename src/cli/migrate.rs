@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use eyre::Result;
+use reth::runner::CliContext;
+
+use crate::cli::{
+    args::{DatabaseArgs, ImportArgs},
+    blocks, journal,
+    l1_fee::L1FeeStore,
+    receipts,
+    source::erigon::ErigonSource,
+    state,
+};
+
+/// Migrate the chain of an Erigon node directly from its database.
+///
+/// The canonical blocks, their receipts and the world state are read from the Erigon database and
+/// written to the reth database in one run, without the intermediate block, receipt and state
+/// exports. The Erigon database is opened read-only, so stop the node first to migrate a
+/// consistent chain. The genesis has to be loaded first, see `genesis`.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The data directory of the Erigon node, or its `chaindata` directory
+    #[arg(long = "from-erigon", value_name = "ERIGON_DATADIR", verbatim_doc_comment)]
+    from_erigon: PathBuf,
+
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    #[clap(flatten)]
+    import: ImportArgs,
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(mut self, _ctx: CliContext) -> Result<()> {
+        self.import = self.import.with_database(&self.db)?;
+        journal::record(&self.db.path(), "migrate", &[self.from_erigon.as_path()], async {
+            let mut db = self.db.open_rw()?;
+            let mut source = ErigonSource::new(&self.from_erigon);
+            tracing::info!(target: "reth::cli", chaindata = %source.chaindata().display(), "Migrating from Erigon");
+            blocks::apply_from(&mut db, &mut source, &self.import).await?;
+            let fees = L1FeeStore::open(&self.db.static_path()?)?;
+            receipts::apply_from(&mut db, &fees, &mut source, &self.import).await?;
+            state::apply_from(&mut db, &mut source, &self.import).await
+        })
+        .await
+    }
+}
@@ -1,4 +1,6 @@
-use clap::{Parser, Subcommand};
+use std::{ffi::OsString, path::PathBuf};
+
+use clap::{CommandFactory, Parser, Subcommand};
 use reth::{
     cli::{Logs, Verbosity},
     runner::CliRunner,
@@ -6,19 +8,67 @@ use reth::{
 
 pub mod db;
 
+pub mod analytics;
+pub mod args;
+pub mod bench;
+pub mod block_headers;
 pub mod blocks;
+pub mod chain;
+pub mod checkpoints;
+pub mod checksum;
+pub mod completions;
+pub mod compression;
+pub mod config;
+pub mod dead_letter;
+pub mod debug;
+pub mod dedup;
+pub mod deposit;
+pub mod devnet;
 pub mod dirs;
+pub mod encryption;
+pub mod export;
 pub mod genesis;
+pub mod import;
+pub mod journal;
+pub mod keccak;
+pub mod l1_cost;
+pub mod l1_fee;
+pub mod logging;
+pub mod metrics;
+pub mod migrate;
 pub mod node;
+pub mod pipeline;
+pub mod preflight;
+pub mod progress;
 pub mod receipts;
+pub mod regenesis;
+pub mod replay;
+pub mod report;
+pub mod retry;
+pub mod rpc;
+pub mod shutdown;
+pub mod source;
 pub mod state;
+pub mod throttle;
+pub mod transition;
+pub mod validate;
+pub mod verify;
+pub mod watchdog;
 
 pub fn run() -> eyre::Result<()> {
     dotenv::dotenv().ok();
-    let opt = Cli::parse();
+    let args: Vec<OsString> = std::env::args_os().collect();
+    let args = match config::config_path(&args) {
+        Some(path) => config::apply(&Cli::command(), args, &config::read(&path)?)?,
+        None => args,
+    };
+    let opt = Cli::parse_from(args);
 
     let (layer, _guard) = opt.logs.layer();
-    reth_tracing::init(vec![layer, reth_tracing::stdout(opt.verbosity.directive())]);
+    reth_tracing::init(vec![layer, opt.log_format.stdout(opt.verbosity.directive())]);
+    if let Some(path) = opt.report_file {
+        report::set_output(path);
+    }
 
     let runner = CliRunner::default();
 
@@ -27,10 +77,38 @@ pub fn run() -> eyre::Result<()> {
         Commands::Receipts(command) => runner.run_command_until_exit(|ctx| command.execute(ctx)),
         Commands::State(command) => runner.run_command_until_exit(|ctx| command.execute(ctx)),
         Commands::Blocks(command) => runner.run_command_until_exit(|ctx| command.execute(ctx)),
-        Commands::Run => runner.run_command_until_exit(|_| node::run()),
+        Commands::BlockHeaders(command) => {
+            runner.run_command_until_exit(|ctx| command.execute(ctx))
+        }
+        Commands::Import(command) => runner.run_command_until_exit(|ctx| command.execute(ctx)),
+        Commands::Migrate(command) => runner.run_command_until_exit(|ctx| command.execute(ctx)),
+        Commands::Db(command) => runner.run_command_until_exit(|ctx| command.execute(ctx)),
+        Commands::Export(command) => runner.run_command_until_exit(|ctx| command.execute(ctx)),
+        Commands::History(command) => runner.run_command_until_exit(|ctx| command.execute(ctx)),
+        Commands::Analytics(command) => runner.run_command_until_exit(|ctx| command.execute(ctx)),
+        Commands::Replay(command) => runner.run_command_until_exit(|ctx| command.execute(ctx)),
+        Commands::IndexHistory(command) => {
+            runner.run_command_until_exit(|ctx| command.execute(ctx))
+        }
+        Commands::Verify(command) => runner.run_command_until_exit(|ctx| command.execute(ctx)),
+        Commands::ValidateTransition(command) => {
+            runner.run_command_until_exit(|ctx| command.execute(ctx))
+        }
+        Commands::Run(command) => runner.run_command_until_exit(|ctx| command.execute(ctx)),
+        Commands::Rpc(command) => runner.run_command_until_exit(|ctx| command.execute(ctx)),
+        Commands::Bench(command) => runner.run_command_until_exit(|ctx| command.execute(ctx)),
+        Commands::Devnet(command) => runner.run_command_until_exit(|ctx| command.execute(ctx)),
+        Commands::Debug(command) => runner.run_command_until_exit(|ctx| command.execute(ctx)),
+        Commands::Completions(command) => command.execute(Cli::command()),
     }
 }
 
+/// Returns the definition of the op-reth command tree, from which its help and completions are
+/// generated
+pub fn command() -> clap::Command {
+    Cli::command()
+}
+
 /// Commands to be executed
 #[derive(Subcommand)]
 pub enum Commands {
@@ -46,18 +124,97 @@ pub enum Commands {
     /// Load Blocks
     #[command(name = "blocks")]
     Blocks(blocks::Command),
-    /// Run
+    /// Load the headers of a block export without their bodies
+    #[command(name = "block-headers")]
+    BlockHeaders(block_headers::Command),
+    /// Import a file or directory, detecting its format
+    #[command(name = "import")]
+    Import(import::Command),
+    /// Migrate the chain of another client directly from its database
+    #[command(name = "migrate")]
+    Migrate(migrate::Command),
+    /// Inspect the migrated database
+    #[command(name = "db")]
+    Db(db::Command),
+    /// Export the migrated chain as block and receipt exports the importers consume
+    #[command(name = "export")]
+    Export(export::Command),
+    /// Show the imports and verifications that ran against the database
+    #[command(name = "history")]
+    History(journal::Command),
+    /// Audit the migrated chain
+    #[command(name = "analytics")]
+    Analytics(analytics::Command),
+    /// Re-execute the imported blocks to build the state history
+    #[command(name = "replay")]
+    Replay(replay::Command),
+    /// Build the account and storage history indices from the changesets of the replay
+    #[command(name = "index-history")]
+    IndexHistory(replay::IndexHistoryCommand),
+    /// Compare the imported blocks against reference nodes
+    #[command(name = "verify")]
+    Verify(verify::Command),
+    /// Check that the database ends at the bedrock transition block and hand it to op-node
+    #[command(name = "validate-transition")]
+    ValidateTransition(transition::Command),
+    /// Run the op-reth node on top of the migrated database
     #[command(name = "run")]
-    Run,
+    Run(node::Command),
+    /// Serve read-only JSON-RPC over the migrated database
+    #[command(name = "rpc")]
+    Rpc(rpc::Command),
+    /// Time slices of the imports against a temporary database
+    #[command(name = "bench")]
+    Bench(bench::Command),
+    /// Run a local devnet with the Engine API and JSON-RPC on local ports
+    #[command(name = "devnet")]
+    Devnet(devnet::Command),
+    /// Re-execute imported blocks to debug discrepancies of the migration
+    #[command(name = "debug")]
+    Debug(debug::Command),
+    /// Print the completions of the commands for bash, zsh, fish, elvish or PowerShell
+    #[command(name = "completions")]
+    Completions(completions::Command),
 }
 
+/// Examples listed at the end of `--help`
+const EXAMPLES: &str = "\
+Examples:
+  Migrate a legacy chain into a new database:
+    op-reth import --chain optimism-mainnet --path ./exports
+  Replay the imported blocks to serve historical state:
+    op-reth replay --chain optimism-mainnet && op-reth rpc --chain optimism-mainnet
+  Install the bash completions:
+    op-reth completions bash > ~/.local/share/bash-completion/completions/op-reth";
+
 #[derive(Parser)]
-#[command(author, version = "0.1", about = "Reth", long_about = None)]
+#[command(author, version = "0.1", about = "Reth", long_about = None, after_help = EXAMPLES)]
 struct Cli {
     /// The command to run
     #[clap(subcommand)]
     command: Commands,
 
+    /// A TOML file setting the flags of the commands, overridden by the flags given on the
+    /// command line. Defaults to `OP_RETH_CONFIG`, or `op-reth.toml` in the working directory if
+    /// it exists.
+    #[arg(long, value_name = "FILE", global = true, verbatim_doc_comment)]
+    config_file: Option<PathBuf>,
+
+    /// The format of the logs written to stdout
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = LogFormat::Text,
+        global = true
+    )]
+    log_format: LogFormat,
+
+    /// Write the report of the command to this file instead of `import-reports` next to the
+    /// database
+    #[arg(long, value_name = "FILE", global = true, verbatim_doc_comment)]
+    report_file: Option<PathBuf>,
+
     #[clap(flatten)]
     logs: Logs,
 
@@ -1,10 +1,355 @@
-use eyre::Result;
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+};
 
-use crate::cli::db;
+use clap::{crate_version, Parser};
+use eyre::{Context, Result};
+use fdlimit::raise_fd_limit;
+use futures::{future, pin_mut, StreamExt};
+use reth::{
+    args::NetworkArgs,
+    dirs::{ConfigPath, PlatformPath},
+    node::events,
+    runner::CliContext,
+};
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTx,
+};
+use reth_network::{error::NetworkError, NetworkConfig, NetworkHandle, NetworkManager};
+use reth_network_api::NetworkInfo;
+use reth_primitives::{Genesis, Head};
+use reth_provider::{BlockProvider, HeaderProvider, ShareableDatabase};
+use reth_rpc::JwtSecret;
+use reth_staged_sync::Config;
+use reth_tasks::TaskExecutor;
+use tokio::sync::mpsc;
+use tracing::*;
+
+use crate::cli::{
+    args::DatabaseArgs,
+    chain::{genesis_from_header, OpChainSpec},
+    db::{self, head::ForkchoicePointers, prune::PruneCheckpoints},
+    deposit::DepositStore,
+    l1_fee::L1FeeStore,
+    rpc::{self, EthApi},
+};
+
+pub mod engine;
+pub mod execution;
+pub mod history;
+pub mod payload;
+pub mod pool;
+
+use history::HistoryServer;
+use payload::PayloadBuilder;
+use pool::TxPool;
 
 /// Run the op-reth node
-pub async fn run() -> Result<()> {
-    let _db = db::construct().await?;
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The path to the reth configuration file
+    #[arg(long, value_name = "FILE", verbatim_doc_comment, default_value_t)]
+    config: PlatformPath<ConfigPath>,
+
+    /// The block at which bedrock activates.
+    ///
+    /// Defaults to the chain spec written by the genesis import, or to the chain selected with
+    /// `--chain` if there is none.
+    #[arg(long = "rollup.bedrock-block", value_name = "BLOCK", verbatim_doc_comment)]
+    bedrock_block: Option<u64>,
+
+    /// The timestamp at which regolith activates.
+    ///
+    /// Defaults to the chain spec written by the genesis import, or to the chain selected with
+    /// `--chain` if there is none.
+    #[arg(long = "rollup.regolith-time", value_name = "TIMESTAMP", verbatim_doc_comment)]
+    regolith_time: Option<u64>,
+
+    /// The timestamp at which canyon activates, together with Shanghai.
+    ///
+    /// Defaults to the chain spec written by the genesis import, or to the chain selected with
+    /// `--chain` if there is none.
+    #[arg(long = "rollup.canyon-time", value_name = "TIMESTAMP", verbatim_doc_comment)]
+    canyon_time: Option<u64>,
+
+    /// Enable devp2p networking, serving the legacy headers, bodies and receipts below the
+    /// bedrock block to peers syncing the history. The node is driven by op-node through the
+    /// Engine API, so networking is disabled by default.
+    #[arg(long, verbatim_doc_comment)]
+    p2p: bool,
+
+    #[clap(flatten)]
+    network: NetworkArgs,
+
+    /// The address the Engine API listens on
+    #[arg(
+        long = "authrpc.addr",
+        value_name = "ADDR",
+        default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST)
+    )]
+    auth_addr: IpAddr,
+
+    /// The port the Engine API listens on
+    #[arg(long = "authrpc.port", value_name = "PORT", default_value_t = 8551)]
+    auth_port: u16,
+
+    /// The path to the hex-encoded JWT secret shared with op-node.
+    ///
+    /// A new secret is generated at this path if the file doesn't exist. Defaults to `jwt.hex`
+    /// within the database directory.
+    #[arg(long = "authrpc.jwtsecret", value_name = "PATH", verbatim_doc_comment)]
+    auth_jwtsecret: Option<PathBuf>,
+
+    /// Serve JSON-RPC over HTTP, accepting transactions into the transaction pool with
+    /// `eth_sendRawTransaction`
+    #[arg(long, verbatim_doc_comment)]
+    http: bool,
+
+    /// The address the HTTP server listens on
+    #[arg(
+        long = "http.addr",
+        value_name = "ADDR",
+        default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST)
+    )]
+    http_addr: IpAddr,
+
+    /// The port the HTTP server listens on
+    #[arg(long = "http.port", value_name = "PORT", default_value_t = 8545)]
+    http_port: u16,
+
+    /// The number of transactions the transaction pool holds before it refuses new ones
+    #[arg(
+        long = "txpool.max-count",
+        value_name = "COUNT",
+        default_value_t = pool::DEFAULT_MAX_TRANSACTIONS
+    )]
+    txpool_max_count: usize,
+}
+
+impl Command {
+    /// Execute `run` command
+    pub async fn execute(self, ctx: CliContext) -> Result<()> {
+        info!(target: "reth::cli", "op-reth {} starting", crate_version!());
+
+        // Raise the fd limit of the process. Does not do anything on windows.
+        raise_fd_limit();
+
+        // The node writes the blocks of op-node, so no import may write the database meanwhile
+        let _lock = db::lock_db(&self.db.path())?;
+        let db = Arc::new(self.db.open_rw()?);
+        info!(target: "reth::cli", path = ?self.db.path(), "Database opened");
+
+        let (genesis, head) = lookup_genesis_and_head(&db)?;
+        info!(target: "reth::cli", number = head.number, hash = ?head.hash, "Loaded head from database");
+        let pointers = ForkchoicePointers::read(&self.db.path())?;
+        info!(target: "reth::cli", safe = ?pointers.safe.map(|safe| safe.number), finalized = ?pointers.finalized.map(|finalized| finalized.number), "Loaded safe and finalized blocks");
+
+        let mut chain = match (OpChainSpec::read(&self.db.path())?, self.db.chain) {
+            (Some(chain), Some(preset)) if chain.chain_id() != preset.chain_id() => {
+                eyre::bail!(
+                    "The database holds chain {}, not {preset} ({})",
+                    chain.chain_id(),
+                    preset.chain_id()
+                )
+            }
+            (Some(chain), _) => chain,
+            (None, Some(preset)) => OpChainSpec::from_preset(preset, genesis),
+            (None, None) => {
+                warn!(target: "reth::cli", "No chain spec found in the database, assuming OP Mainnet. Run the genesis import to write one or pass --chain.");
+                OpChainSpec::op_mainnet(genesis)
+            }
+        };
+        if let Some(block) = self.bedrock_block {
+            chain = chain.with_bedrock_block(block);
+        }
+        if let Some(timestamp) = self.regolith_time {
+            chain = chain.with_regolith_time(timestamp);
+        }
+        if let Some(timestamp) = self.canyon_time {
+            chain = chain.with_canyon_time(timestamp);
+        }
+        info!(target: "reth::cli", chain_id = chain.chain_id(), bedrock_block = chain.bedrock_block, regolith_time = chain.regolith_time, canyon_time = ?chain.canyon_time, "Chain spec configured");
+
+        if self.p2p {
+            let config = self.load_config()?;
+            info!(target: "reth::cli", path = %self.config, "Configuration loaded");
+
+            info!(target: "reth::cli", "Connecting to P2P network");
+            let network_config = self.load_network_config(
+                &config,
+                &chain,
+                head,
+                db.clone(),
+                ctx.task_executor.clone(),
+            );
+            let history = HistoryServer::new(db.clone(), chain.bedrock_block);
+            let network = self.start_network(network_config, history, &ctx.task_executor).await?;
+            info!(target: "reth::cli", peer_id = %network.peer_id(), local_addr = %network.local_addr(), "Connected to P2P network");
+            info!(target: "reth::cli", bedrock_block = chain.bedrock_block, "Serving the legacy history below the bedrock block to peers");
+
+            ctx.task_executor.spawn(events::handle_events(
+                Some(network.clone()),
+                network.event_listener().map(Into::into),
+            ));
+        } else {
+            info!(target: "reth::cli", "P2P networking disabled");
+        }
+
+        let jwt_path =
+            self.auth_jwtsecret.clone().unwrap_or_else(|| self.db.path().join("jwt.hex"));
+        let secret = JwtSecret::try_create(&jwt_path)
+            .wrap_err_with(|| format!("Could not load JWT secret from {jwt_path:?}"))?;
+        info!(target: "reth::cli", path = ?jwt_path, "JWT secret loaded");
+
+        let auth_addr = SocketAddr::new(self.auth_addr, self.auth_port);
+        let fees = Arc::new(L1FeeStore::open(&self.db.static_path()?)?);
+        if fees
+            .recover_staged(|hash| Ok(db::head::head(&db)?.map(|head| head.hash) == Some(hash)))?
+        {
+            warn!(target: "reth::cli", "Resolved the L1 fees staged by an interrupted forkchoice update");
+        }
+        let deposits = Arc::new(DepositStore::open(&self.db.static_path()?)?);
+        let pool = Arc::new(
+            TxPool::new(db.clone(), chain.clone()).with_max_transactions(self.txpool_max_count),
+        );
+        let builder =
+            Arc::new(PayloadBuilder::new(db.clone(), chain.clone()).with_pool(pool.clone()));
+        let engine = engine::EngineApi::new(db.clone(), chain)
+            .with_l1_fees(fees.clone())
+            .with_deposits(deposits.clone())
+            .with_pool(pool.clone())
+            .with_payload_builder(builder)
+            .with_forkchoice_pointers(self.db.path(), pointers);
+        let handle = engine::start_server(auth_addr, secret, engine).await?;
+        info!(target: "reth::cli", addr = %auth_addr, "Engine API started");
+
+        if self.http {
+            let http_addr = SocketAddr::new(self.http_addr, self.http_port);
+            let api = EthApi::new(db.clone(), fees, deposits)
+                .with_prune_checkpoints(PruneCheckpoints::read(&self.db.path())?)
+                .with_pool(pool);
+            let http_handle = rpc::start_server(http_addr, api).await?;
+            info!(target: "reth::cli", addr = %http_addr, max_transactions = self.txpool_max_count, "JSON-RPC server started with a transaction pool");
+            future::join(handle.stopped(), http_handle.stopped()).await;
+        } else {
+            handle.stopped().await;
+        }
+        info!(target: "reth::cli", "Engine API stopped");
+
+        Ok(())
+    }
+
+    fn load_config(&self) -> Result<Config> {
+        confy::load_path::<Config>(&self.config).wrap_err("Could not load config")
+    }
+
+    /// Spawns the configured network and associated tasks and returns the [NetworkHandle] connected
+    /// to that network. The eth requests of peers are answered by the [HistoryServer].
+    async fn start_network<C>(
+        &self,
+        config: NetworkConfig<C>,
+        history: HistoryServer,
+        task_executor: &TaskExecutor,
+    ) -> Result<NetworkHandle, NetworkError>
+    where
+        C: BlockProvider + HeaderProvider + Clone + Unpin + 'static,
+    {
+        let mut network = NetworkManager::new(config).await?;
+        let (requests_tx, requests) = mpsc::unbounded_channel();
+        network.set_eth_request_handler(requests_tx);
+        let handle = network.handle().clone();
+
+        let known_peers_file = self.network.persistent_peers_file();
+        task_executor.spawn_critical_with_signal("p2p network task", |shutdown| async move {
+            run_network_until_shutdown(shutdown, network, known_peers_file).await
+        });
+
+        task_executor.spawn_critical("p2p eth request handler", history.run(requests));
+
+        Ok(handle)
+    }
+
+    fn load_network_config(
+        &self,
+        config: &Config,
+        chain: &OpChainSpec,
+        head: Head,
+        db: Arc<Env<WriteMap>>,
+        executor: TaskExecutor,
+    ) -> NetworkConfig<ShareableDatabase<Arc<Env<WriteMap>>>> {
+        let chain_spec = (*chain.inner).clone();
+        self.network
+            .network_config(config, chain_spec.clone())
+            .with_task_executor(Box::new(executor))
+            .set_head(head)
+            .build(ShareableDatabase::new(db, chain_spec))
+    }
+}
+
+/// Reads the genesis header and the canonical head from the database
+fn lookup_genesis_and_head(db: &Env<WriteMap>) -> Result<(Genesis, Head)> {
+    db.view(|tx| -> Result<(Genesis, Head)> {
+        let genesis = tx.get::<tables::Headers>(0)?.ok_or_else(|| {
+            eyre::eyre!("Genesis block not found! Please import the legacy chain first.")
+        })?;
+        let (number, hash) = tx
+            .cursor_read::<tables::CanonicalHeaders>()?
+            .last()?
+            .ok_or_else(|| eyre::eyre!("No canonical blocks found in the database"))?;
+        let header = tx
+            .get::<tables::Headers>(number)?
+            .ok_or_else(|| eyre::eyre!("Header for canonical block {number} not found"))?;
+        let total_difficulty =
+            tx.get::<tables::HeaderTD>(number)?.map(|td| td.0).unwrap_or(header.difficulty);
+
+        let head = Head {
+            number,
+            hash,
+            timestamp: header.timestamp,
+            difficulty: header.difficulty,
+            total_difficulty,
+        };
+        Ok((genesis_from_header(&genesis), head))
+    })?
+}
+
+/// Drives the [NetworkManager] future until a [Shutdown](reth_tasks::shutdown::Shutdown) signal is
+/// received. If configured, this writes known peers to `persistent_peers_file` afterwards.
+async fn run_network_until_shutdown<C>(
+    shutdown: reth_tasks::shutdown::Shutdown,
+    network: NetworkManager<C>,
+    persistent_peers_file: Option<PathBuf>,
+) where
+    C: BlockProvider + HeaderProvider + Clone + Unpin + 'static,
+{
+    pin_mut!(network, shutdown);
+
+    tokio::select! {
+        _ = &mut network => {},
+        _ = shutdown => {},
+    }
 
-    Ok(())
+    if let Some(file_path) = persistent_peers_file {
+        let known_peers = network.all_peers().collect::<Vec<_>>();
+        if let Ok(known_peers) = serde_json::to_string_pretty(&known_peers) {
+            trace!(target : "reth::cli", peers_file =?file_path, num_peers=%known_peers.len(), "Saving current peers");
+            match std::fs::write(&file_path, known_peers) {
+                Ok(_) => {
+                    info!(target: "reth::cli", peers_file=?file_path, "Wrote network peers to file");
+                }
+                Err(err) => {
+                    warn!(target: "reth::cli", ?err, peers_file=?file_path, "Failed to write network peers to file");
+                }
+            }
+        }
+    }
 }
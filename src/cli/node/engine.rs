@@ -0,0 +1,727 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use eyre::Result;
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    proc_macros::rpc,
+    server::{ServerBuilder, ServerHandle},
+    types::error::{CallError, ErrorObject},
+};
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::{
+    proofs::EMPTY_ROOT, Header, Receipt, SealedBlock, SealedHeader, EMPTY_OMMER_ROOT, H256, H64,
+};
+use reth_provider::{LatestStateProviderRef, Transaction};
+use reth_revm::database::{State, SubState};
+use reth_rpc::{AuthLayer, JwtAuthValidator, JwtSecret};
+use reth_rpc_types::engine::{
+    ExecutionPayload, ForkchoiceState, ForkchoiceUpdated, PayloadStatus, PayloadStatusEnum,
+};
+use tower::ServiceBuilder;
+
+use crate::cli::{
+    blocks,
+    chain::OpChainSpec,
+    db::head::{BlockPointer, ForkchoicePointers},
+    deposit::{self, DepositStore, DEPOSIT_TX_TYPE},
+    keccak, l1_cost,
+    l1_fee::L1FeeStore,
+    node::{
+        execution::{self, BlockExecutor},
+        payload::{BuiltPayload, ExecutionPayloadEnvelope, OpPayloadAttributes, PayloadBuilder},
+        pool::TxPool,
+    },
+    replay,
+    rpc::internal_error,
+    state::HASH_AND_TRIE_STAGES,
+};
+
+/// The Engine API methods supported by the node
+pub const CAPABILITIES: [&str; 7] = [
+    "engine_exchangeCapabilities",
+    "engine_forkchoiceUpdatedV1",
+    "engine_forkchoiceUpdatedV2",
+    "engine_getPayloadV1",
+    "engine_getPayloadV2",
+    "engine_newPayloadV1",
+    "engine_newPayloadV2",
+];
+
+/// The Engine API error code of `engine_getPayload` for an unknown payload id
+pub const UNKNOWN_PAYLOAD_CODE: i32 = -38001;
+
+/// The Engine API error code of `engine_forkchoiceUpdated` for a forkchoice state the node refuses,
+/// such as a head unwinding finalized blocks
+pub const INVALID_FORKCHOICE_STATE_CODE: i32 = -38002;
+
+/// The maximum number of payloads kept until a forkchoice update makes them canonical
+pub const MAX_PENDING_BLOCKS: usize = 64;
+
+/// The Engine API error code of `engine_forkchoiceUpdated` for payload attributes no block can be
+/// built for
+pub const INVALID_PAYLOAD_ATTRIBUTES_CODE: i32 = -38003;
+
+/// The Engine API used by op-node to drive the chain
+#[rpc(server, namespace = "engine")]
+pub trait EngineApi {
+    /// Returns the Engine API methods supported by the node
+    #[method(name = "exchangeCapabilities")]
+    fn exchange_capabilities(&self, capabilities: Vec<String>) -> RpcResult<Vec<String>>;
+
+    /// Validates and imports a new execution payload
+    #[method(name = "newPayloadV1")]
+    async fn new_payload_v1(&self, payload: ExecutionPayload) -> RpcResult<PayloadStatus>;
+
+    /// Validates and imports a new execution payload, which carries withdrawals from canyon on
+    #[method(name = "newPayloadV2")]
+    async fn new_payload_v2(&self, payload: ExecutionPayload) -> RpcResult<PayloadStatus>;
+
+    /// Updates the forkchoice state of the node
+    #[method(name = "forkchoiceUpdatedV1")]
+    async fn fork_choice_updated_v1(
+        &self,
+        state: ForkchoiceState,
+        attributes: Option<OpPayloadAttributes>,
+    ) -> RpcResult<ForkchoiceUpdated>;
+
+    /// Updates the forkchoice state of the node, with payload attributes carrying withdrawals
+    /// from canyon on
+    #[method(name = "forkchoiceUpdatedV2")]
+    async fn fork_choice_updated_v2(
+        &self,
+        state: ForkchoiceState,
+        attributes: Option<OpPayloadAttributes>,
+    ) -> RpcResult<ForkchoiceUpdated>;
+
+    /// Returns the payload built for the attributes of a forkchoice update
+    #[method(name = "getPayloadV1")]
+    async fn get_payload_v1(&self, payload_id: H64) -> RpcResult<ExecutionPayload>;
+
+    /// Returns the payload built for the attributes of a forkchoice update, with the fees it pays
+    /// to its beneficiary
+    #[method(name = "getPayloadV2")]
+    async fn get_payload_v2(&self, payload_id: H64) -> RpcResult<ExecutionPayloadEnvelope>;
+}
+
+/// A forkchoice state the handler refuses, reported with [INVALID_FORKCHOICE_STATE_CODE]
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InvalidForkchoiceState(String);
+
+impl fmt::Display for InvalidForkchoiceState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for InvalidForkchoiceState {}
+
+/// A payload received with `engine_newPayload` that no forkchoice update made canonical yet
+#[derive(Debug, Clone)]
+struct PendingBlock {
+    block: SealedBlock,
+    /// Whether the block was executed on top of the canonical head and found valid
+    validated: bool,
+}
+
+/// The Engine API handler, backed by the migrated database.
+///
+/// `engine_newPayload` converts payloads into [SealedBlock]s and keeps them until a forkchoice
+/// update makes them canonical. A payload building on the canonical head is executed right away in
+/// a database transaction that is dropped afterwards, and only reported valid if its state root,
+/// receipts root, logs bloom and gas used match. Payloads building on other known blocks are
+/// accepted without execution, and payloads with an unknown parent make the node report that it is
+/// syncing.
+///
+/// `engine_forkchoiceUpdated` applies the head in a single database transaction: the canonical
+/// blocks above the common ancestor of the head are unwound with their state, and the pending
+/// blocks up to the head are inserted and executed with their receipts. Blocks up to the bedrock
+/// block and the finalized block are never unwound. The safe and finalized blocks are kept as the
+/// [ForkchoicePointers] of the database. The L1 fees of the transactions of inserted blocks are
+/// staged in the [L1FeeStore] until the transaction is committed, and their deposits are kept in
+/// the [DepositStore]. Transactions included by inserted blocks are dropped from the transaction
+/// pool, and payload attributes start building a block with the [PayloadBuilder].
+#[derive(Debug, Clone)]
+pub struct EngineApi {
+    db: Arc<Env<WriteMap>>,
+    chain: OpChainSpec,
+    fees: Option<Arc<L1FeeStore>>,
+    deposits: Option<Arc<DepositStore>>,
+    pool: Option<Arc<TxPool>>,
+    builder: Option<Arc<PayloadBuilder>>,
+    pending: Arc<Mutex<VecDeque<PendingBlock>>>,
+    forkchoice: Arc<Mutex<ForkchoicePointers>>,
+    forkchoice_path: Option<PathBuf>,
+}
+
+impl EngineApi {
+    /// Creates a new Engine API handler
+    pub fn new(db: Arc<Env<WriteMap>>, chain: OpChainSpec) -> Self {
+        Self {
+            db,
+            chain,
+            fees: None,
+            deposits: None,
+            pool: None,
+            builder: None,
+            pending: Default::default(),
+            forkchoice: Default::default(),
+            forkchoice_path: None,
+        }
+    }
+
+    /// Stores the L1 fees of the transactions of inserted bedrock blocks in the given store
+    pub fn with_l1_fees(mut self, fees: Arc<L1FeeStore>) -> Self {
+        self.fees = Some(fees);
+        self
+    }
+
+    /// Keeps the deposits of inserted blocks in the given store, which is required for blocks
+    /// holding deposits
+    pub fn with_deposits(mut self, deposits: Arc<DepositStore>) -> Self {
+        self.deposits = Some(deposits);
+        self
+    }
+
+    /// Drops the transactions included by inserted blocks from the given pool
+    pub fn with_pool(mut self, pool: Arc<TxPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Builds blocks for the payload attributes of forkchoice updates with the given builder
+    pub fn with_payload_builder(mut self, builder: Arc<PayloadBuilder>) -> Self {
+        self.builder = Some(builder);
+        self
+    }
+
+    /// Starts from the given safe and finalized blocks, read from the database at `db_path`, and
+    /// writes the ones of forkchoice updates back there
+    pub fn with_forkchoice_pointers(
+        mut self,
+        db_path: PathBuf,
+        pointers: ForkchoicePointers,
+    ) -> Self {
+        self.forkchoice = Arc::new(Mutex::new(pointers));
+        self.forkchoice_path = Some(db_path);
+        self
+    }
+
+    /// The transaction pool of the node, if it accepts transactions
+    pub fn pool(&self) -> Option<&Arc<TxPool>> {
+        self.pool.as_ref()
+    }
+
+    /// The payload builder of the node, if it builds blocks
+    pub fn payload_builder(&self) -> Option<&Arc<PayloadBuilder>> {
+        self.builder.as_ref()
+    }
+
+    /// The chain spec the handler was configured with
+    pub fn chain(&self) -> &OpChainSpec {
+        &self.chain
+    }
+
+    /// The safe and finalized blocks of the last forkchoice update
+    pub fn forkchoice_pointers(&self) -> ForkchoicePointers {
+        self.forkchoice.lock().expect("poisoned").clone()
+    }
+
+    /// Returns the pending block with the given hash
+    fn pending_block(&self, hash: H256) -> Option<PendingBlock> {
+        let pending = self.pending.lock().expect("poisoned");
+        pending.iter().find(|pending| pending.block.hash() == hash).cloned()
+    }
+
+    /// Keeps the block until a forkchoice update makes it canonical, dropping the oldest pending
+    /// block if there are [MAX_PENDING_BLOCKS]
+    fn keep_pending(&self, block: SealedBlock, validated: bool) {
+        let mut pending = self.pending.lock().expect("poisoned");
+        pending.retain(|pending| pending.block.hash() != block.hash());
+        if pending.len() >= MAX_PENDING_BLOCKS {
+            pending.pop_front();
+        }
+        pending.push_back(PendingBlock { block, validated });
+    }
+
+    /// Executes the block on top of its parent, the state of the database transaction, and writes
+    /// its state changes. Returns the receipts of the block, or why it is invalid.
+    fn execute_block(
+        &self,
+        tx: &Transaction<'_, Env<WriteMap>>,
+        block: &SealedBlock,
+        parent: &SealedHeader,
+    ) -> Result<Result<Vec<Receipt>, String>> {
+        let total_difficulty =
+            tx.get::<tables::HeaderTD>(parent.number)?.map(|td| td.0).unwrap_or_default();
+        let executed = {
+            let mut state = SubState::new(State::new(LatestStateProviderRef::new(&**tx)));
+            let mut executor =
+                BlockExecutor::new(&self.chain, &block.header, total_difficulty, &mut state);
+            for transaction in &block.body {
+                if let Err(err) = executor.execute(transaction) {
+                    return Ok(Err(format!(
+                        "Transaction {:?} of block {} failed: {err}",
+                        deposit::tx_hash(transaction),
+                        block.number
+                    )))
+                }
+            }
+            executor.finish()
+        };
+        if let Err(validation_error) = executed.check(&block.header) {
+            return Ok(Err(validation_error))
+        }
+        let receipts = executed.reth_receipts();
+        let state_root = execution::write_state(tx, &self.chain, parent, executed.result)?;
+        if state_root != block.state_root {
+            return Ok(Err(format!(
+                "State root mismatch of block {}: header has {:?}, computed {state_root:?}",
+                block.number, block.state_root
+            )))
+        }
+        Ok(Ok(receipts))
+    }
+
+    fn new_payload(&self, payload: ExecutionPayload) -> Result<PayloadStatus> {
+        let block_hash = payload.block_hash;
+        if self.db.view(|tx| canonical_header(tx, block_hash))??.is_some() {
+            return Ok(PayloadStatus::new(PayloadStatusEnum::Valid, Some(block_hash)))
+        }
+        if let Some(pending) = self.pending_block(block_hash) {
+            if pending.validated {
+                return Ok(PayloadStatus::new(PayloadStatusEnum::Valid, Some(block_hash)))
+            }
+            return Ok(PayloadStatus::from_status(PayloadStatusEnum::Accepted))
+        }
+
+        let block = match decode_payload(payload) {
+            Ok(block) => block,
+            Err(validation_error) => {
+                return Ok(PayloadStatus::from_status(PayloadStatusEnum::InvalidBlockHash {
+                    validation_error,
+                }))
+            }
+        };
+        if let Err(validation_error) = self.chain.check_hardfork_fields(&block) {
+            return Ok(PayloadStatus::new(
+                PayloadStatusEnum::Invalid { validation_error },
+                Some(block.parent_hash),
+            ))
+        }
+
+        let (parent, tip, trie) = self.db.view(|tx| -> Result<_> {
+            Ok((canonical_header(tx, block.parent_hash)?, tip(tx)?, replay::trie_block(tx)?))
+        })??;
+        let parent = match parent
+            .or_else(|| self.pending_block(block.parent_hash).map(|pending| pending.block.header))
+        {
+            Some(parent) => parent,
+            None => {
+                tracing::debug!(target: "reth::engine", parent = ?block.parent_hash, "Unknown parent, syncing");
+                return Ok(PayloadStatus::from_status(PayloadStatusEnum::Syncing))
+            }
+        };
+        if block.number != parent.number + 1 {
+            return Ok(PayloadStatus::new(
+                PayloadStatusEnum::Invalid {
+                    validation_error: format!(
+                        "Block number {} does not follow parent number {}",
+                        block.number, parent.number
+                    ),
+                },
+                Some(parent.hash()),
+            ))
+        }
+
+        // Only payloads on top of the head can be executed, the others wait for a forkchoice update
+        if parent.hash() != tip.hash() || trie != Some(tip.number) {
+            tracing::info!(target: "reth::engine", number = block.number, hash = ?block_hash, "Accepted payload");
+            self.keep_pending(block, false);
+            return Ok(PayloadStatus::from_status(PayloadStatusEnum::Accepted))
+        }
+        let tx = Transaction::new(self.db.as_ref())?;
+        let executed = self.execute_block(&tx, &block, &parent)?;
+        drop(tx);
+        if let Err(validation_error) = executed {
+            tracing::warn!(target: "reth::engine", number = block.number, hash = ?block_hash, %validation_error, "Invalid payload");
+            return Ok(PayloadStatus::new(
+                PayloadStatusEnum::Invalid { validation_error },
+                Some(parent.hash()),
+            ))
+        }
+        tracing::info!(target: "reth::engine", number = block.number, hash = ?block_hash, txs = block.body.len(), "Validated payload");
+        self.keep_pending(block, true);
+        Ok(PayloadStatus::new(PayloadStatusEnum::Valid, Some(block_hash)))
+    }
+
+    /// Unwinds the canonical blocks above `ancestor` up to the tip with their state, receipts and
+    /// history
+    fn unwind(
+        &self,
+        tx: &Transaction<'_, Env<WriteMap>>,
+        ancestor: &SealedHeader,
+        tip: &SealedHeader,
+    ) -> Result<()> {
+        let start = replay::transition_after(&**tx, ancestor.number)?;
+        let end = replay::transition_after(&**tx, tip.number)?;
+        let (replayed, indexed) = (replay::replayed_block(&**tx)?, replay::indexed_block(&**tx)?);
+        if indexed > ancestor.number {
+            replay::unindex_history(&**tx, start)?;
+        }
+        let root = execution::unwind_state(tx, start..end, tip.state_root)?;
+        if root != ancestor.state_root {
+            eyre::bail!(
+                "Unwinding to block {} restored the state root {root:?}, not {:?}",
+                ancestor.number,
+                ancestor.state_root
+            );
+        }
+        let range = ancestor.number + 1..=tip.number;
+        let removed = blocks::unwind_blocks(&**tx, self.deposits.as_deref(), range)?;
+
+        for stage in HASH_AND_TRIE_STAGES {
+            stage.save_progress(&**tx, ancestor.number)?;
+        }
+        replay::EXECUTION.save_progress(&**tx, replayed.min(ancestor.number))?;
+        for stage in replay::HISTORY_STAGES {
+            stage.save_progress(&**tx, indexed.min(ancestor.number))?;
+        }
+        tracing::info!(target: "reth::engine", from = ancestor.number + 1, to = tip.number, transactions = removed, "Unwound canonical blocks for reorg");
+        Ok(())
+    }
+
+    fn fork_choice_updated(
+        &self,
+        state: ForkchoiceState,
+        attributes: Option<OpPayloadAttributes>,
+    ) -> RpcResult<ForkchoiceUpdated> {
+        let status = match self.update_forkchoice(&state) {
+            Ok(status) => status,
+            Err(err) => match err.downcast_ref::<InvalidForkchoiceState>() {
+                Some(invalid) => return Err(engine_error(INVALID_FORKCHOICE_STATE_CODE, invalid)),
+                None => return Err(internal_error(err)),
+            },
+        };
+        let valid = matches!(status.status, PayloadStatusEnum::Valid);
+        let updated = ForkchoiceUpdated::new(status);
+        if !valid {
+            return Ok(updated)
+        }
+        let Some(attributes) = attributes else { return Ok(updated) };
+        let Some(builder) = &self.builder else {
+            tracing::warn!(target: "reth::engine", "Payload building is disabled, ignoring payload attributes");
+            return Ok(updated)
+        };
+        let payload_id = builder
+            .build(state.head_block_hash, &attributes)
+            .map_err(|err| engine_error(INVALID_PAYLOAD_ATTRIBUTES_CODE, err))?;
+        Ok(updated.with_payload_id(payload_id))
+    }
+
+    /// Makes the head of the forkchoice state canonical and keeps its safe and finalized blocks.
+    /// Fails with [InvalidForkchoiceState] for states that are refused without any change.
+    fn update_forkchoice(&self, state: &ForkchoiceState) -> Result<PayloadStatus> {
+        let mut pointers = self.forkchoice.lock().expect("poisoned");
+        let head_hash = state.head_block_hash;
+
+        // The pending blocks from the head down to its canonical ancestor
+        let mut pending = Vec::new();
+        let mut hash = head_hash;
+        let ancestor = loop {
+            if let Some(header) = self.db.view(|tx| canonical_header(tx, hash))?? {
+                break header
+            }
+            let Some(block) = self.pending_block(hash) else {
+                tracing::debug!(target: "reth::engine", head = ?head_hash, missing = ?hash, "Unknown head, syncing");
+                return Ok(PayloadStatus::from_status(PayloadStatusEnum::Syncing))
+            };
+            hash = block.block.parent_hash;
+            pending.push(block.block);
+        };
+        pending.reverse();
+
+        let mut tx = Transaction::new(self.db.as_ref())?;
+        let tip = tip(&*tx)?;
+        let changed = !pending.is_empty() || ancestor.number < tip.number;
+        let mut fees = Vec::new();
+        if changed {
+            let finalized = pointers.finalized.map_or(0, |finalized| finalized.number);
+            let floor = self.chain.bedrock_block.max(finalized);
+            if ancestor.number < tip.number && ancestor.number < floor {
+                return Err(InvalidForkchoiceState(format!(
+                    "The head {head_hash:?} would unwind block {}, but blocks up to the bedrock \
+                     or finalized block {floor} are final",
+                    ancestor.number + 1
+                ))
+                .into())
+            }
+            if replay::trie_block(&*tx)? != Some(tip.number) {
+                tracing::warn!(target: "reth::engine", tip = tip.number, "The trie is not up to date with the head, run state hash-and-trie");
+                return Ok(PayloadStatus::from_status(PayloadStatusEnum::Syncing))
+            }
+            if ancestor.number < tip.number {
+                self.unwind(&tx, &ancestor, &tip)?;
+            }
+
+            let (mut replayed, mut indexed) =
+                (replay::replayed_block(&*tx)?, replay::indexed_block(&*tx)?);
+            let mut parent = ancestor.clone();
+            for block in &pending {
+                let start = replay::transition_after(&*tx, parent.number)?;
+                blocks::insert_block(&*tx, block, self.deposits.as_deref())?;
+                let receipts = match self.execute_block(&tx, block, &parent)? {
+                    Ok(receipts) => receipts,
+                    Err(validation_error) => {
+                        tracing::warn!(target: "reth::engine", number = block.number, hash = ?block.hash(), %validation_error, "Invalid block");
+                        let hash = block.hash();
+                        self.pending.lock().expect("poisoned").retain(|p| p.block.hash() != hash);
+                        return Ok(PayloadStatus::new(
+                            PayloadStatusEnum::Invalid { validation_error },
+                            Some(parent.hash()),
+                        ))
+                    }
+                };
+                let body = tx
+                    .get::<tables::BlockBodies>(block.number)?
+                    .ok_or_else(|| eyre::eyre!("Body of block {} not found", block.number))?;
+                for (index, receipt) in receipts.into_iter().enumerate() {
+                    tx.put::<tables::Receipts>(body.start_tx_id + index as u64, receipt)?;
+                }
+                let infos = l1_cost::block_fee_infos(block, &self.chain)?;
+                fees.extend(
+                    infos.into_iter().map(|(index, info)| (body.start_tx_id + index as u64, info)),
+                );
+
+                for stage in HASH_AND_TRIE_STAGES {
+                    stage.save_progress(&*tx, block.number)?;
+                }
+                // The changesets and history stay complete only if they were before
+                if replayed == parent.number {
+                    replay::EXECUTION.save_progress(&*tx, block.number)?;
+                    replayed = block.number;
+                }
+                if indexed == parent.number {
+                    let end = replay::transition_after(&*tx, block.number)?;
+                    replay::index_history(&*tx, start..end)?;
+                    for stage in replay::HISTORY_STAGES {
+                        stage.save_progress(&*tx, block.number)?;
+                    }
+                    indexed = block.number;
+                }
+                parent = block.header.clone();
+            }
+        }
+
+        let pointer = |name: &str, hash: H256| -> Result<Option<BlockPointer>> {
+            if hash.is_zero() {
+                return Ok(None)
+            }
+            match canonical_header(&*tx, hash)? {
+                Some(header) => Ok(Some(BlockPointer { number: header.number, hash })),
+                None => Err(InvalidForkchoiceState(format!(
+                    "The {name} block {hash:?} is not a canonical block"
+                ))
+                .into()),
+            }
+        };
+        let updated = ForkchoicePointers {
+            safe: pointer("safe", state.safe_block_hash)?,
+            finalized: pointer("finalized", state.finalized_block_hash)?,
+        };
+        if let (Some(safe), Some(finalized)) = (updated.safe, updated.finalized) {
+            if finalized.number > safe.number {
+                return Err(InvalidForkchoiceState(format!(
+                    "The finalized block {} lies above the safe block {}",
+                    finalized.number, safe.number
+                ))
+                .into())
+            }
+        }
+
+        if changed {
+            let first_tx_id = tx
+                .get::<tables::BlockBodies>(ancestor.number)?
+                .map_or(0, |body| body.start_tx_id + body.tx_count);
+            if let Some(store) = &self.fees {
+                store.stage(first_tx_id, &fees, head_hash)?;
+            }
+            if let Err(err) = tx.commit() {
+                if let Some(store) = &self.fees {
+                    store.rollback_staged()?;
+                }
+                return Err(err.into())
+            }
+            if let Some(store) = &self.fees {
+                store.commit_staged()?;
+            }
+            tracing::info!(target: "reth::engine", number = pending.last().map_or(ancestor.number, |block| block.number), hash = ?head_hash, inserted = pending.len(), "Updated canonical head");
+
+            if let Some(pool) = &self.pool {
+                for block in &pending {
+                    let dropped = pool.on_canonical_block(block)?;
+                    tracing::debug!(target: "reth::engine", number = block.number, dropped, pending = pool.len(), "Updated transaction pool");
+                }
+            }
+            let applied = pending.iter().map(|block| block.hash()).collect::<HashSet<_>>();
+            self.pending.lock().expect("poisoned").retain(|p| !applied.contains(&p.block.hash()));
+        } else {
+            drop(tx);
+        }
+
+        if updated != *pointers {
+            if let Some(path) = &self.forkchoice_path {
+                updated.write(path)?;
+            }
+            *pointers = updated;
+        }
+        Ok(PayloadStatus::new(PayloadStatusEnum::Valid, Some(head_hash)))
+    }
+
+    fn get_payload(&self, payload_id: H64) -> RpcResult<BuiltPayload> {
+        self.builder
+            .as_ref()
+            .and_then(|builder| builder.payload(payload_id))
+            .ok_or_else(|| engine_error(UNKNOWN_PAYLOAD_CODE, "Unknown payload"))
+    }
+}
+
+/// Returns the header of the canonical block with the given hash
+fn canonical_header<'a, TX: DbTx<'a>>(tx: &TX, hash: H256) -> Result<Option<SealedHeader>> {
+    let Some(number) = tx.get::<tables::HeaderNumbers>(hash)? else { return Ok(None) };
+    if tx.get::<tables::CanonicalHeaders>(number)? != Some(hash) {
+        return Ok(None)
+    }
+    Ok(tx.get::<tables::Headers>(number)?.map(|header| header.seal(hash)))
+}
+
+/// Returns the header of the last canonical block
+fn tip<'a, TX: DbTx<'a>>(tx: &TX) -> Result<SealedHeader> {
+    let (number, hash) = tx
+        .cursor_read::<tables::CanonicalHeaders>()?
+        .last()?
+        .ok_or_else(|| eyre::eyre!("The database holds no canonical blocks"))?;
+    let header = tx
+        .get::<tables::Headers>(number)?
+        .ok_or_else(|| eyre::eyre!("Header of canonical block {number} not found"))?;
+    Ok(header.seal(hash))
+}
+
+/// Creates an Engine API error with the given code
+fn engine_error(code: i32, err: impl std::fmt::Display) -> jsonrpsee::core::Error {
+    CallError::Custom(ErrorObject::owned(code, err.to_string(), None::<()>)).into()
+}
+
+/// Converts a payload into a [SealedBlock], checking its block hash.
+///
+/// Deposit transactions are unknown to reth, so payloads holding them are decoded with their
+/// deposits converted into their carriers, and hashed over the original transaction envelopes.
+pub fn decode_payload(payload: ExecutionPayload) -> Result<SealedBlock, String> {
+    if !payload.transactions.iter().any(|tx| tx.first() == Some(&DEPOSIT_TX_TYPE)) {
+        return SealedBlock::try_from(payload).map_err(|err| err.to_string())
+    }
+    let body = payload
+        .transactions
+        .iter()
+        .map(|tx| deposit::decode_tx(tx))
+        .collect::<Result<Vec<_>>>()
+        .map_err(|err| err.to_string())?;
+    let withdrawals = match &payload.withdrawals {
+        Some(withdrawals) if !withdrawals.is_empty() => {
+            return Err(format!("OP Stack blocks carry no withdrawals, got {}", withdrawals.len()))
+        }
+        Some(_) => Some(vec![]),
+        None => None,
+    };
+    let base_fee_per_gas = u64::try_from(payload.base_fee_per_gas)
+        .map_err(|_| format!("Base fee {} exceeds 64 bits", payload.base_fee_per_gas))?;
+    let header = Header {
+        parent_hash: payload.parent_hash,
+        ommers_hash: EMPTY_OMMER_ROOT,
+        beneficiary: payload.fee_recipient,
+        state_root: payload.state_root,
+        transactions_root: deposit::transactions_root(&body),
+        receipts_root: payload.receipts_root,
+        logs_bloom: payload.logs_bloom,
+        number: payload.block_number.as_u64(),
+        gas_limit: payload.gas_limit.as_u64(),
+        gas_used: payload.gas_used.as_u64(),
+        timestamp: payload.timestamp.as_u64(),
+        mix_hash: payload.prev_randao,
+        base_fee_per_gas: Some(base_fee_per_gas),
+        withdrawals_root: withdrawals.as_ref().map(|_| EMPTY_ROOT),
+        extra_data: payload.extra_data,
+        ..Default::default()
+    };
+    let header = keccak::seal(header);
+    if header.hash() != payload.block_hash {
+        return Err(format!(
+            "Block hash mismatch: payload has {:?}, computed {:?}",
+            payload.block_hash,
+            header.hash()
+        ))
+    }
+    Ok(SealedBlock { header, body, ommers: vec![], withdrawals })
+}
+
+#[async_trait]
+impl EngineApiServer for EngineApi {
+    fn exchange_capabilities(&self, _capabilities: Vec<String>) -> RpcResult<Vec<String>> {
+        Ok(CAPABILITIES.iter().map(ToString::to_string).collect())
+    }
+
+    async fn new_payload_v1(&self, payload: ExecutionPayload) -> RpcResult<PayloadStatus> {
+        self.new_payload(payload).map_err(internal_error)
+    }
+
+    async fn new_payload_v2(&self, payload: ExecutionPayload) -> RpcResult<PayloadStatus> {
+        self.new_payload(payload).map_err(internal_error)
+    }
+
+    async fn fork_choice_updated_v1(
+        &self,
+        state: ForkchoiceState,
+        attributes: Option<OpPayloadAttributes>,
+    ) -> RpcResult<ForkchoiceUpdated> {
+        self.fork_choice_updated(state, attributes)
+    }
+
+    async fn fork_choice_updated_v2(
+        &self,
+        state: ForkchoiceState,
+        attributes: Option<OpPayloadAttributes>,
+    ) -> RpcResult<ForkchoiceUpdated> {
+        self.fork_choice_updated(state, attributes)
+    }
+
+    async fn get_payload_v1(&self, payload_id: H64) -> RpcResult<ExecutionPayload> {
+        Ok(self.get_payload(payload_id)?.execution_payload())
+    }
+
+    async fn get_payload_v2(&self, payload_id: H64) -> RpcResult<ExecutionPayloadEnvelope> {
+        Ok(self.get_payload(payload_id)?.envelope())
+    }
+}
+
+/// Starts the Engine API server on the given address. Every request has to carry a JWT signed
+/// with the given secret.
+pub async fn start_server(
+    addr: SocketAddr,
+    secret: JwtSecret,
+    api: EngineApi,
+) -> Result<ServerHandle> {
+    let middleware = ServiceBuilder::new().layer(AuthLayer::new(JwtAuthValidator::new(secret)));
+    let server = ServerBuilder::default().set_middleware(middleware).build(addr).await?;
+    Ok(server.start(api.into_rpc())?)
+}
@@ -0,0 +1,638 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use eyre::Result;
+use reth_db::{
+    cursor::{DbCursorRO, DbCursorRW, DbDupCursorRO, DbDupCursorRW},
+    models::TransitionIdAddress,
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_executor::{
+    execution_result::{
+        AccountChangeSet, AccountInfoChangeSet, ExecutionResult, TransactionChangeSet,
+    },
+    executor::commit_changes,
+};
+use reth_primitives::{
+    bloom::logs_bloom, Address, Bloom, Header, Log, Receipt, SealedHeader, StorageEntry,
+    TransactionSigned, TxType, H256, U256,
+};
+use reth_provider::{trie::DBTrieLoader, StateProvider, Transaction};
+use reth_revm::{
+    database::SubState,
+    env::{fill_cfg_and_block_env, fill_tx_env},
+    into_reth_log,
+    revm::{
+        db::AccountState,
+        primitives::{AccountInfo, ExecutionResult as EvmResult, ResultAndState},
+        Database as _, EVM,
+    },
+    to_reth_acc,
+};
+use reth_rlp::{Encodable, Header as RlpHeader};
+
+use crate::cli::{
+    chain::{self, OpChainSpec},
+    deposit::{self, TxDeposit, DEPOSIT_TX_TYPE},
+    keccak::{self, Keccak},
+    l1_cost::{L1BlockInfo, L1_BASE_FEE_SLOT, L1_FEE_OVERHEAD_SLOT, L1_FEE_SCALAR_SLOT},
+};
+
+/// Executes the transactions of an OP Stack block one by one with the rules of the chain.
+///
+/// User transactions pay the L1 fee of their envelope to the L1 fee vault on top of their gas, and
+/// their base fee goes to the base fee vault instead of being burnt. The L1 fee parameters are
+/// read from the L1Block predeploy, which the L1 attributes deposit the block starts with sets.
+///
+/// Deposits mint their ETH to their sender first, then run as a call without nonce check and gas
+/// price. A deposit that fails keeps its mint and still increments the nonce of its sender. Before
+/// regolith deposits use their whole gas limit, or no gas for system transactions; from regolith
+/// on successful deposits use the gas they spent and system transactions are gone.
+#[derive(Debug)]
+pub struct BlockExecutor<'a, DB: StateProvider> {
+    chain: &'a OpChainSpec,
+    header: &'a Header,
+    total_difficulty: U256,
+    state: &'a mut SubState<DB>,
+    regolith: bool,
+    canyon: bool,
+    gas_used: u64,
+    changesets: Vec<TransactionChangeSet>,
+    receipts: Vec<OpReceipt>,
+}
+
+/// The outcome of executing a block with a [BlockExecutor]
+#[derive(Debug)]
+pub struct ExecutedBlock {
+    /// The changes of the transactions of the block
+    pub result: ExecutionResult,
+    /// The receipts of the transactions of the block
+    pub receipts: Vec<OpReceipt>,
+    /// The gas used by the block
+    pub gas_used: u64,
+}
+
+impl ExecutedBlock {
+    /// The receipts root of the block
+    pub fn receipts_root(&self) -> H256 {
+        receipts_root(&self.receipts)
+    }
+
+    /// The logs bloom of the block
+    pub fn logs_bloom(&self) -> Bloom {
+        logs_bloom(self.receipts.iter().flat_map(|receipt| &receipt.receipt.logs))
+    }
+
+    /// The receipts of the block as stored in the `Receipts` table
+    pub fn reth_receipts(&self) -> Vec<Receipt> {
+        self.receipts.iter().map(|receipt| receipt.receipt.clone()).collect()
+    }
+
+    /// Checks the gas used, receipts root and logs bloom of the given header against the block
+    pub fn check(&self, header: &Header) -> Result<(), String> {
+        if header.gas_used != self.gas_used {
+            return Err(format!(
+                "Block {} uses {} gas, execution used {}",
+                header.number, header.gas_used, self.gas_used
+            ))
+        }
+        let receipts_root = self.receipts_root();
+        if header.receipts_root != receipts_root {
+            return Err(format!(
+                "Receipts root mismatch of block {}: header has {:?}, computed {receipts_root:?}",
+                header.number, header.receipts_root
+            ))
+        }
+        if header.logs_bloom != self.logs_bloom() {
+            return Err(format!("Logs bloom mismatch of block {}", header.number))
+        }
+        Ok(())
+    }
+}
+
+impl<'a, DB: StateProvider> BlockExecutor<'a, DB> {
+    /// Creates an executor for the block with the given header on top of `state`, the state after
+    /// its parent
+    pub fn new(
+        chain: &'a OpChainSpec,
+        header: &'a Header,
+        total_difficulty: U256,
+        state: &'a mut SubState<DB>,
+    ) -> Self {
+        Self {
+            chain,
+            header,
+            total_difficulty,
+            state,
+            regolith: chain.is_regolith_active(header.number, header.timestamp),
+            canyon: chain.is_canyon_active(header.number, header.timestamp),
+            gas_used: 0,
+            changesets: vec![],
+            receipts: vec![],
+        }
+    }
+
+    /// The gas used by the transactions executed so far
+    pub fn gas_used(&self) -> u64 {
+        self.gas_used
+    }
+
+    /// Executes the next transaction of the block. Returns the gas it used.
+    ///
+    /// An invalid transaction, one its sender can't pay for or one exceeding the gas left in the
+    /// block, fails without changing the state.
+    pub fn execute(&mut self, transaction: &TransactionSigned) -> Result<u64> {
+        match TxDeposit::from_carrier(transaction) {
+            Some(deposit) => self.execute_deposit(deposit),
+            None => self.execute_transaction(transaction),
+        }
+    }
+
+    /// Returns the changes and receipts of the executed transactions
+    pub fn finish(self) -> ExecutedBlock {
+        ExecutedBlock {
+            result: ExecutionResult {
+                tx_changesets: self.changesets,
+                block_changesets: BTreeMap::new(),
+            },
+            receipts: self.receipts,
+            gas_used: self.gas_used,
+        }
+    }
+
+    fn execute_transaction(&mut self, transaction: &TransactionSigned) -> Result<u64> {
+        let hash = transaction.hash();
+        let sender = transaction
+            .recover_signer()
+            .ok_or_else(|| eyre::eyre!("Invalid signature of transaction {hash:?}"))?;
+        self.check_gas(transaction.gas_limit())?;
+
+        let l1_fee = self.l1_info()?.l1_cost(&deposit::encode_tx(transaction), self.regolith);
+        let balance = self.account(sender)?.map_or(U256::ZERO, |account| account.balance);
+        let cost = U256::from(transaction.gas_limit()) * U256::from(transaction.max_fee_per_gas()) +
+            U256::from(transaction.value()) +
+            l1_fee;
+        if balance < cost {
+            eyre::bail!(
+                "Sender {sender:?} of transaction {hash:?} can't pay {cost} wei, including the L1 \
+                 fee of {l1_fee} wei"
+            );
+        }
+
+        let ResultAndState { result, state } = {
+            let mut evm = self.evm();
+            fill_tx_env(&mut evm.env.tx, transaction, sender);
+            evm.transact().map_err(|err| eyre::eyre!("Transaction {hash:?} is invalid: {err:?}"))?
+        };
+        let (mut changeset, new_bytecodes) = commit_changes(self.state, state, true);
+        let (success, gas_used, logs) = outcome(result);
+
+        let base_fee =
+            U256::from(self.header.base_fee_per_gas.unwrap_or_default()) * U256::from(gas_used);
+        self.transfer(&mut changeset, sender, chain::l1_fee_vault_address(), l1_fee)?;
+        self.credit(&mut changeset, chain::base_fee_vault_address(), base_fee)?;
+
+        let receipt = self.push(transaction, success, gas_used, logs, None);
+        self.changesets.push(TransactionChangeSet { receipt, changeset, new_bytecodes });
+        Ok(gas_used)
+    }
+
+    fn execute_deposit(&mut self, deposit: TxDeposit) -> Result<u64> {
+        let hash = deposit.hash();
+        if deposit.is_system_transaction && self.regolith {
+            eyre::bail!("Deposit {hash:?} is a system transaction, which regolith removed");
+        }
+        if !deposit.is_system_transaction {
+            self.check_gas(deposit.gas_limit)?;
+        }
+
+        let nonce = self.account(deposit.from)?.map_or(0, |account| account.nonce);
+        let mut changeset = BTreeMap::new();
+        if let Some(mint) = deposit.mint {
+            self.credit(&mut changeset, deposit.from, U256::from(mint))?;
+        }
+
+        let transaction = deposit.stand_in();
+        let transacted = {
+            let mut evm = self.evm();
+            fill_tx_env(&mut evm.env.tx, &transaction, deposit.from);
+            // Deposits were paid for on L1: they have no nonce, no chain id and no gas price
+            evm.env.tx.chain_id = None;
+            evm.env.tx.nonce = None;
+            evm.env.block.basefee = U256::ZERO;
+            evm.transact()
+        };
+        let mut new_bytecodes = BTreeMap::new();
+        let (success, gas_used, logs) = match transacted {
+            Ok(ResultAndState { result, state }) => {
+                let (changes, bytecodes) = commit_changes(self.state, state, true);
+                merge_changes(&mut changeset, changes);
+                new_bytecodes = bytecodes;
+                outcome(result)
+            }
+            Err(err) => {
+                tracing::debug!(target: "reth::execution", ?hash, ?err, "Deposit failed");
+                self.update(&mut changeset, deposit.from, |account| account.nonce += 1)?;
+                (false, deposit.gas_limit, vec![])
+            }
+        };
+        let gas_used = match (self.regolith, success) {
+            (false, _) if deposit.is_system_transaction => 0,
+            (true, true) => gas_used,
+            _ => deposit.gas_limit,
+        };
+
+        let deposit_nonce = self.regolith.then_some(nonce);
+        let receipt = self.push(&transaction, success, gas_used, logs, deposit_nonce);
+        self.changesets.push(TransactionChangeSet { receipt, changeset, new_bytecodes });
+        Ok(gas_used)
+    }
+
+    /// Fails if the given gas limit exceeds the gas left in the block
+    fn check_gas(&self, gas_limit: u64) -> Result<()> {
+        let available = self.header.gas_limit - self.gas_used;
+        if gas_limit > available {
+            eyre::bail!("Gas limit {gas_limit} exceeds the {available} gas left in the block");
+        }
+        Ok(())
+    }
+
+    /// An EVM for the block on top of the state
+    fn evm(&mut self) -> EVM<&mut SubState<DB>> {
+        let mut evm = EVM::new();
+        evm.database(&mut *self.state);
+        fill_cfg_and_block_env(
+            &mut evm.env.cfg,
+            &mut evm.env.block,
+            &self.chain.inner,
+            self.header,
+            self.total_difficulty,
+        );
+        evm
+    }
+
+    /// Records the receipt of an executed transaction and returns it as stored in the
+    /// `Receipts` table
+    fn push(
+        &mut self,
+        transaction: &TransactionSigned,
+        success: bool,
+        gas_used: u64,
+        logs: Vec<Log>,
+        deposit_nonce: Option<u64>,
+    ) -> Receipt {
+        self.gas_used += gas_used;
+        let receipt = Receipt {
+            tx_type: transaction.tx_type(),
+            success,
+            cumulative_gas_used: self.gas_used,
+            logs,
+        };
+        let deposit = deposit::is_stand_in(transaction);
+        self.receipts.push(OpReceipt {
+            receipt: receipt.clone(),
+            deposit,
+            deposit_nonce,
+            deposit_receipt_version: (deposit && self.canyon).then_some(1),
+        });
+        receipt
+    }
+
+    /// Reads the L1 fee parameters from the storage of the L1Block predeploy
+    fn l1_info(&mut self) -> Result<L1BlockInfo> {
+        let l1_block = chain::l1_block_address();
+        let mut slot = |index: u8| {
+            self.state
+                .storage(l1_block, U256::from(index))
+                .map_err(|err| eyre::eyre!("Failed to load the L1 fee parameters: {err:?}"))
+        };
+        Ok(L1BlockInfo {
+            l1_base_fee: slot(L1_BASE_FEE_SLOT)?,
+            l1_fee_overhead: slot(L1_FEE_OVERHEAD_SLOT)?,
+            l1_fee_scalar: slot(L1_FEE_SCALAR_SLOT)?,
+        })
+    }
+
+    /// Loads the account at the given address
+    fn account(&mut self, address: Address) -> Result<Option<AccountInfo>> {
+        self.state.basic(address).map_err(|err| eyre::eyre!("Failed to load {address:?}: {err:?}"))
+    }
+
+    /// Moves `amount` from one account to another, outside of the EVM
+    fn transfer(
+        &mut self,
+        changeset: &mut BTreeMap<Address, AccountChangeSet>,
+        from: Address,
+        to: Address,
+        amount: U256,
+    ) -> Result<()> {
+        if amount == U256::ZERO {
+            return Ok(())
+        }
+        self.update(changeset, from, |account| account.balance -= amount)?;
+        self.credit(changeset, to, amount)
+    }
+
+    /// Adds `amount` to the balance of an account, outside of the EVM. Nothing is credited, and no
+    /// empty account created, for a zero amount.
+    fn credit(
+        &mut self,
+        changeset: &mut BTreeMap<Address, AccountChangeSet>,
+        address: Address,
+        amount: U256,
+    ) -> Result<()> {
+        if amount == U256::ZERO {
+            return Ok(())
+        }
+        self.update(changeset, address, |account| account.balance += amount)
+    }
+
+    /// Applies `change` to the cached account at the given address and records it in `changeset`
+    fn update(
+        &mut self,
+        changeset: &mut BTreeMap<Address, AccountChangeSet>,
+        address: Address,
+        change: impl FnOnce(&mut AccountInfo),
+    ) -> Result<()> {
+        let before = self.account(address)?;
+        let mut info = before.clone().unwrap_or_default();
+        change(&mut info);
+
+        let cached = self.state.accounts.entry(address).or_default();
+        cached.info = info.clone();
+        if matches!(cached.account_state, AccountState::NotExisting) {
+            cached.account_state = AccountState::Touched;
+        }
+
+        let old = before.as_ref().map(to_reth_acc);
+        let new = to_reth_acc(&info);
+        let account = match old {
+            Some(old) => AccountInfoChangeSet::Changed { new, old },
+            None => AccountInfoChangeSet::Created { new },
+        };
+        let change = AccountChangeSet { account, storage: BTreeMap::new(), wipe_storage: false };
+        merge_changes(changeset, BTreeMap::from([(address, change)]));
+        Ok(())
+    }
+}
+
+/// Merges the later changes of the same transition into `changes`, keeping the values the
+/// accounts and slots had before the earlier changes
+fn merge_changes(
+    changes: &mut BTreeMap<Address, AccountChangeSet>,
+    later: BTreeMap<Address, AccountChangeSet>,
+) {
+    for (address, change) in later {
+        let Some(earlier) = changes.remove(&address) else {
+            changes.insert(address, change);
+            continue
+        };
+        let mut storage = earlier.storage;
+        for (slot, (old, new)) in change.storage {
+            storage.entry(slot).and_modify(|values| values.1 = new).or_insert((old, new));
+        }
+        let account = match (earlier.account, change.account) {
+            (earlier, AccountInfoChangeSet::NoChange { .. }) => earlier,
+            (AccountInfoChangeSet::NoChange { .. }, later) => later,
+            (AccountInfoChangeSet::Created { .. }, AccountInfoChangeSet::Destroyed { .. }) => {
+                AccountInfoChangeSet::NoChange { is_empty: false }
+            }
+            (AccountInfoChangeSet::Created { .. }, later) => match later {
+                AccountInfoChangeSet::Created { new } |
+                AccountInfoChangeSet::Changed { new, .. } => AccountInfoChangeSet::Created { new },
+                later => later,
+            },
+            (
+                AccountInfoChangeSet::Changed { old, .. } | AccountInfoChangeSet::Destroyed { old },
+                later,
+            ) => match later {
+                AccountInfoChangeSet::Created { new } |
+                AccountInfoChangeSet::Changed { new, .. } => {
+                    AccountInfoChangeSet::Changed { new, old }
+                }
+                _ => AccountInfoChangeSet::Destroyed { old },
+            },
+        };
+        let wipe_storage = earlier.wipe_storage || change.wipe_storage;
+        changes.insert(address, AccountChangeSet { account, storage, wipe_storage });
+    }
+}
+
+/// Whether a transaction succeeded, the gas it used and its logs
+fn outcome(result: EvmResult) -> (bool, u64, Vec<Log>) {
+    match result {
+        EvmResult::Success { gas_used, logs, .. } => {
+            (true, gas_used, logs.into_iter().map(into_reth_log).collect())
+        }
+        EvmResult::Revert { gas_used, .. } | EvmResult::Halt { gas_used, .. } => {
+            (false, gas_used, vec![])
+        }
+    }
+}
+
+/// The receipt of a transaction of an OP Stack block.
+///
+/// Receipts of deposits have the deposit type and, from regolith on, the nonce of the sender
+/// before the deposit. From canyon on they also carry a receipt version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpReceipt {
+    /// The receipt as stored in the `Receipts` table
+    pub receipt: Receipt,
+    /// Whether the receipt is the receipt of a deposit
+    pub deposit: bool,
+    /// The nonce of the sender of the deposit before it was executed
+    pub deposit_nonce: Option<u64>,
+    /// The version of the deposit receipt
+    pub deposit_receipt_version: Option<u64>,
+}
+
+impl OpReceipt {
+    /// The typed envelope byte of the receipt, `None` for legacy receipts
+    pub fn tx_type(&self) -> Option<u8> {
+        match self.receipt.tx_type {
+            _ if self.deposit => Some(DEPOSIT_TX_TYPE),
+            TxType::Legacy => None,
+            tx_type => Some(tx_type as u8),
+        }
+    }
+
+    /// The receipt as it is hashed into the receipts root:
+    /// `type || rlp([status, cumulativeGas, bloom, logs, depositNonce?, depositReceiptVersion?])`
+    pub fn envelope(&self) -> Vec<u8> {
+        let mut fields = Vec::new();
+        self.receipt.success.encode(&mut fields);
+        self.receipt.cumulative_gas_used.encode(&mut fields);
+        logs_bloom(self.receipt.logs.iter()).encode(&mut fields);
+        reth_rlp::encode_list(&self.receipt.logs, &mut fields);
+        if let Some(nonce) = self.deposit_nonce {
+            nonce.encode(&mut fields);
+        }
+        if let Some(version) = self.deposit_receipt_version {
+            version.encode(&mut fields);
+        }
+
+        let mut envelope = Vec::with_capacity(fields.len() + 10);
+        if let Some(tx_type) = self.tx_type() {
+            envelope.push(tx_type);
+        }
+        RlpHeader { list: true, payload_length: fields.len() }.encode(&mut envelope);
+        envelope.extend_from_slice(&fields);
+        envelope
+    }
+}
+
+/// The receipts root of a block with the given receipts
+pub fn receipts_root(receipts: &[OpReceipt]) -> H256 {
+    triehash::ordered_trie_root::<Keccak, _>(receipts.iter().map(OpReceipt::envelope))
+}
+
+/// Writes the changes of the executed block following `parent` to the plain state and the
+/// changesets, updates the hashed state and returns the state root after the block.
+///
+/// The block itself has to be inserted already, and the trie has to be up to date with the state
+/// after `parent`, so that only the changed accounts are hashed again.
+pub fn write_state<'a, DB: reth_db::database::Database>(
+    tx: &Transaction<'a, DB>,
+    chain: &OpChainSpec,
+    parent: &SealedHeader,
+    result: ExecutionResult,
+) -> Result<H256> {
+    let start = tx.get::<tables::BlockTransitionIndex>(parent.number)?.unwrap_or_default();
+    let end = start + result.tx_changesets.len() as u64;
+
+    let mut accounts = BTreeSet::new();
+    let mut storages = BTreeSet::new();
+    let mut wiped = BTreeSet::new();
+    for changeset in &result.tx_changesets {
+        for (address, change) in &changeset.changeset {
+            accounts.insert(*address);
+            if change.wipe_storage {
+                wiped.insert(*address);
+            }
+            for slot in change.storage.keys() {
+                storages.insert((*address, H256(slot.to_be_bytes())));
+            }
+        }
+    }
+
+    tx.insert_execution_result(vec![result], &chain.inner, parent.number)?;
+    hash_state(&**tx, accounts, storages, wiped)?;
+    DBTrieLoader::default()
+        .update_root(tx, parent.state_root, start..end)
+        .map_err(|err| eyre::eyre!("Failed to update the state root: {err:?}"))
+}
+
+/// Reverts the state transitions in the given range, the transitions of the blocks above the
+/// block whose state is restored. `root` is the state root before the revert, the state root
+/// after the last reverted block. Returns the state root of the restored state.
+///
+/// The plain and hashed state are restored from the changesets, which are removed with the
+/// trie nodes they changed.
+pub fn unwind_state<'a, DB: reth_db::database::Database>(
+    tx: &Transaction<'a, DB>,
+    transitions: std::ops::Range<u64>,
+    root: H256,
+) -> Result<H256> {
+    if transitions.is_empty() {
+        return Ok(root)
+    }
+
+    // The first change of an account or slot holds its value before the range
+    let mut accounts = BTreeMap::new();
+    let mut account_keys = BTreeSet::new();
+    let mut cursor = tx.cursor_read::<tables::AccountChangeSet>()?;
+    let mut entry = cursor.seek(transitions.start)?;
+    while let Some((transition, change)) = entry {
+        accounts.entry(change.address).or_insert(change.info);
+        account_keys.insert(transition);
+        entry = cursor.next()?;
+    }
+    let mut storages = BTreeMap::new();
+    let mut storage_keys = BTreeSet::new();
+    let mut cursor = tx.cursor_read::<tables::StorageChangeSet>()?;
+    let mut entry = cursor.seek(TransitionIdAddress((transitions.start, Address::zero())))?;
+    while let Some((key, change)) = entry {
+        storages.entry((key.0 .1, change.key)).or_insert(change.value);
+        storage_keys.insert(key);
+        entry = cursor.next()?;
+    }
+    for transition in account_keys {
+        tx.delete::<tables::AccountChangeSet>(transition, None)?;
+    }
+    for key in storage_keys {
+        tx.delete::<tables::StorageChangeSet>(key, None)?;
+    }
+
+    for (address, account) in &accounts {
+        match account {
+            Some(account) => tx.put::<tables::PlainAccountState>(*address, *account)?,
+            None => {
+                tx.delete::<tables::PlainAccountState>(*address, None)?;
+            }
+        }
+    }
+    let mut plain = tx.cursor_dup_write::<tables::PlainStorageState>()?;
+    for ((address, key), value) in &storages {
+        if plain.seek_by_key_subkey(*address, *key)?.filter(|entry| entry.key == *key).is_some() {
+            plain.delete_current()?;
+        }
+        if *value != U256::ZERO {
+            plain.upsert(*address, StorageEntry { key: *key, value: *value })?;
+        }
+    }
+    hash_state(
+        &**tx,
+        accounts.into_keys().collect(),
+        storages.into_keys().collect(),
+        BTreeSet::new(),
+    )?;
+
+    DBTrieLoader::default()
+        .update_root(tx, root, transitions)
+        .map_err(|err| eyre::eyre!("Failed to update the state root: {err:?}"))
+}
+
+/// Copies the given accounts and slots of the plain state into the hashed state. The hashed
+/// storage of wiped accounts is dropped first.
+fn hash_state<'a, TX: DbTxMut<'a> + DbTx<'a>>(
+    tx: &TX,
+    accounts: BTreeSet<Address>,
+    storages: BTreeSet<(Address, H256)>,
+    wiped: BTreeSet<Address>,
+) -> Result<()> {
+    for address in accounts {
+        let hashed = keccak::keccak256(address);
+        match tx.get::<tables::PlainAccountState>(address)? {
+            Some(account) => tx.put::<tables::HashedAccount>(hashed, account)?,
+            None => {
+                tx.delete::<tables::HashedAccount>(hashed, None)?;
+            }
+        }
+    }
+
+    let mut hashed_storage = tx.cursor_dup_write::<tables::HashedStorage>()?;
+    for address in wiped {
+        if hashed_storage.seek_exact(keccak::keccak256(address))?.is_some() {
+            hashed_storage.delete_current_duplicates()?;
+        }
+    }
+    let mut plain = tx.cursor_dup_read::<tables::PlainStorageState>()?;
+    for (address, key) in storages {
+        let (hashed_address, hashed_key) = (keccak::keccak256(address), keccak::keccak256(key));
+        let value = plain
+            .seek_by_key_subkey(address, key)?
+            .filter(|entry| entry.key == key)
+            .map_or(U256::ZERO, |entry| entry.value);
+        if hashed_storage
+            .seek_by_key_subkey(hashed_address, hashed_key)?
+            .filter(|entry| entry.key == hashed_key)
+            .is_some()
+        {
+            hashed_storage.delete_current()?;
+        }
+        if value != U256::ZERO {
+            hashed_storage.upsert(hashed_address, StorageEntry { key: hashed_key, value })?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use eyre::Result;
+use reth_db::{
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTx,
+};
+use reth_eth_wire::{
+    BlockBodies, BlockBody, BlockHeaders, GetBlockHeaders, HeadersDirection, Receipts,
+};
+use reth_network::eth_requests::IncomingEthRequest;
+use reth_primitives::{bloom::logs_bloom, BlockHashOrNumber, Header, ReceiptWithBloom, H256};
+use reth_rlp::Encodable;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::cli::blocks;
+
+/// The maximum number of headers served per request
+pub const MAX_HEADERS_SERVE: usize = 1024;
+
+/// The maximum number of block bodies served per request
+pub const MAX_BODIES_SERVE: usize = 1024;
+
+/// The maximum number of blocks whose receipts are served per request
+pub const MAX_RECEIPTS_SERVE: usize = 1024;
+
+/// The size in bytes after which a response stops growing, even if it holds fewer items than
+/// requested
+pub const SOFT_RESPONSE_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Answers the eth requests of peers with the legacy headers, bodies and receipts of the
+/// database, so that other nodes can sync the history before bedrock from this one.
+///
+/// Only blocks below the bedrock block are served: bedrock blocks start with deposits, which the
+/// eth protocol can't carry, and are synced through op-node instead. Requests for state are not
+/// answered. Responses stop at the first block that is not served, like they do in geth.
+#[derive(Debug, Clone)]
+pub struct HistoryServer {
+    db: Arc<Env<WriteMap>>,
+    bedrock_block: u64,
+}
+
+impl HistoryServer {
+    /// Creates a server answering with the blocks of the database below `bedrock_block`
+    pub fn new(db: Arc<Env<WriteMap>>, bedrock_block: u64) -> Self {
+        Self { db, bedrock_block }
+    }
+
+    /// The number of the block with the given hash or number, if it is a served block
+    fn served_number<'a, TX: DbTx<'a>>(
+        &self,
+        tx: &TX,
+        block: BlockHashOrNumber,
+    ) -> Result<Option<u64>> {
+        let number = match block {
+            BlockHashOrNumber::Hash(hash) => tx.get::<tables::HeaderNumbers>(hash)?,
+            BlockHashOrNumber::Number(number) => Some(number),
+        };
+        Ok(number.filter(|number| *number < self.bedrock_block))
+    }
+
+    /// The headers requested by a `GetBlockHeaders` message
+    pub fn headers(&self, request: &GetBlockHeaders) -> Result<Vec<Header>> {
+        self.db.view(|tx| -> Result<_> {
+            let mut headers = Vec::new();
+            let Some(mut number) = self.served_number(tx, request.start_block)? else {
+                return Ok(headers)
+            };
+            let limit = (request.limit as usize).min(MAX_HEADERS_SERVE);
+            let step = request.skip as u64 + 1;
+            let mut size = 0;
+            while headers.len() < limit && number < self.bedrock_block {
+                let Some(header) = tx.get::<tables::Headers>(number)? else { break };
+                size += header.length();
+                headers.push(header);
+                if size > SOFT_RESPONSE_LIMIT {
+                    break
+                }
+                let next = match request.direction {
+                    HeadersDirection::Rising => number.checked_add(step),
+                    HeadersDirection::Falling => number.checked_sub(step),
+                };
+                let Some(next) = next else { break };
+                number = next;
+            }
+            Ok(headers)
+        })?
+    }
+
+    /// The bodies of the blocks with the given hashes
+    pub fn bodies(&self, hashes: &[H256]) -> Result<Vec<BlockBody>> {
+        self.db.view(|tx| -> Result<_> {
+            let mut bodies = Vec::new();
+            let mut size = 0;
+            for hash in hashes.iter().take(MAX_BODIES_SERVE) {
+                let Some(number) = self.served_number(tx, BlockHashOrNumber::Hash(*hash))? else {
+                    break
+                };
+                let Some(block) = blocks::load_standard_block(tx, number)? else { break };
+                let body =
+                    BlockBody { transactions: block.body, ommers: block.ommers, withdrawals: None };
+                size += body.length();
+                bodies.push(body);
+                if size > SOFT_RESPONSE_LIMIT {
+                    break
+                }
+            }
+            Ok(bodies)
+        })?
+    }
+
+    /// The receipts of the blocks with the given hashes. Blocks whose receipts were not imported
+    /// end the response.
+    pub fn receipts(&self, hashes: &[H256]) -> Result<Vec<Vec<ReceiptWithBloom>>> {
+        self.db.view(|tx| -> Result<_> {
+            let mut receipts = Vec::new();
+            let mut size = 0;
+            'blocks: for hash in hashes.iter().take(MAX_RECEIPTS_SERVE) {
+                let Some(number) = self.served_number(tx, BlockHashOrNumber::Hash(*hash))? else {
+                    break
+                };
+                let Some(body) = tx.get::<tables::BlockBodies>(number)? else { break };
+                let mut block_receipts = Vec::with_capacity(body.tx_count as usize);
+                for tx_id in body.start_tx_id..body.start_tx_id + body.tx_count {
+                    let Some(receipt) = tx.get::<tables::Receipts>(tx_id)? else { break 'blocks };
+                    let bloom = logs_bloom(receipt.logs.iter());
+                    block_receipts.push(ReceiptWithBloom { receipt, bloom });
+                }
+                size += block_receipts.iter().map(Encodable::length).sum::<usize>();
+                receipts.push(block_receipts);
+                if size > SOFT_RESPONSE_LIMIT {
+                    break
+                }
+            }
+            Ok(receipts)
+        })?
+    }
+
+    /// Answers a request of a peer. Failed reads are logged and leave the request unanswered.
+    pub fn on_request(&self, request: IncomingEthRequest) {
+        let result = match request {
+            IncomingEthRequest::GetBlockHeaders { peer_id, request, response } => {
+                self.headers(&request).map(|headers| {
+                    tracing::trace!(target: "reth::p2p", ?peer_id, headers = headers.len(), "Serving headers");
+                    let _ = response.send(Ok(BlockHeaders(headers)));
+                })
+            }
+            IncomingEthRequest::GetBlockBodies { peer_id, request, response } => {
+                self.bodies(&request.0).map(|bodies| {
+                    tracing::trace!(target: "reth::p2p", ?peer_id, bodies = bodies.len(), "Serving bodies");
+                    let _ = response.send(Ok(BlockBodies(bodies)));
+                })
+            }
+            IncomingEthRequest::GetReceipts { peer_id, request, response } => {
+                self.receipts(&request.0).map(|receipts| {
+                    tracing::trace!(target: "reth::p2p", ?peer_id, blocks = receipts.len(), "Serving receipts");
+                    let _ = response.send(Ok(Receipts(receipts)));
+                })
+            }
+            IncomingEthRequest::GetNodeData { .. } => Ok(()),
+        };
+        if let Err(err) = result {
+            tracing::warn!(target: "reth::p2p", %err, "Failed to answer eth request");
+        }
+    }
+
+    /// Answers the requests forwarded by the network until it shuts down
+    pub async fn run(self, mut requests: UnboundedReceiver<IncomingEthRequest>) {
+        while let Some(request) = requests.recv().await {
+            self.on_request(request);
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use eyre::Result;
+use reth_db::{
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTx,
+};
+use reth_primitives::{
+    proofs::EMPTY_ROOT, Address, Bytes, Header, SealedBlock, SealedHeader, Withdrawal,
+    EMPTY_OMMER_ROOT, H256, H64, U256, U64,
+};
+use reth_provider::{LatestStateProviderRef, Transaction};
+use reth_revm::database::{State, SubState};
+use reth_rpc_types::engine::ExecutionPayload;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{
+    chain::OpChainSpec,
+    deposit, keccak,
+    node::{
+        execution::{self, BlockExecutor, OpReceipt},
+        pool::{self, TxPool},
+    },
+    replay,
+};
+
+/// The number of built payloads the builder keeps for `engine_getPayload`
+pub const MAX_PAYLOADS: usize = 16;
+
+/// The base fee of a block whose parent has none, the initial base fee of EIP-1559
+pub const INITIAL_BASE_FEE: u64 = 1_000_000_000;
+
+/// The payload attributes op-node sends with `engine_forkchoiceUpdated` to start building a
+/// block.
+///
+/// On top of the attributes of the Engine API, op-node forces the transactions the block has to
+/// start with, the L1 attributes deposit and the user deposits derived from L1, and can keep the
+/// builder from adding transactions of the pool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpPayloadAttributes {
+    /// The timestamp of the block
+    pub timestamp: U64,
+    /// The randomness of the block, taken from its L1 origin
+    pub prev_randao: H256,
+    /// The beneficiary of the block
+    pub suggested_fee_recipient: Address,
+    /// The withdrawals of the block, an empty list from canyon on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub withdrawals: Option<Vec<Withdrawal>>,
+    /// The transaction envelopes the block starts with
+    #[serde(default)]
+    pub transactions: Vec<Bytes>,
+    /// Whether the block holds only the forced transactions
+    #[serde(default)]
+    pub no_tx_pool: bool,
+    /// The gas limit of the block, the gas limit of the parent if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_limit: Option<U64>,
+}
+
+/// The payload returned by `engine_getPayloadV2`, with the fees it pays to its beneficiary
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionPayloadEnvelope {
+    /// The built payload
+    pub execution_payload: ExecutionPayload,
+    /// The priority fees the transactions of the payload pay
+    pub block_value: U256,
+}
+
+/// A block built by the [PayloadBuilder]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuiltPayload {
+    /// The id returned to op-node by `engine_forkchoiceUpdated`
+    pub id: H64,
+    /// The built block
+    pub block: SealedBlock,
+    /// The receipts of the transactions of the block
+    pub receipts: Vec<OpReceipt>,
+    /// The priority fees the transactions of the block pay
+    pub fees: U256,
+}
+
+impl BuiltPayload {
+    /// The execution payload of the block, with deposits in their original envelopes
+    pub fn execution_payload(&self) -> ExecutionPayload {
+        let mut payload = ExecutionPayload::from(self.block.clone());
+        payload.transactions =
+            self.block.body.iter().map(|tx| deposit::encode_tx(tx).into()).collect();
+        payload
+    }
+
+    /// The envelope returned by `engine_getPayloadV2`
+    pub fn envelope(&self) -> ExecutionPayloadEnvelope {
+        ExecutionPayloadEnvelope {
+            execution_payload: self.execution_payload(),
+            block_value: self.fees,
+        }
+    }
+}
+
+/// Builds blocks on top of the migrated state for the payload attributes of op-node.
+///
+/// A block starts with the transactions forced by the attributes, followed by the best
+/// transactions of the pool unless the attributes say otherwise. The transactions are executed
+/// one by one with a [BlockExecutor] on top of the state of the canonical tip, and pool
+/// transactions that are invalid are left out. The state root of the block is computed by
+/// writing its changes in a database transaction that is dropped afterwards, which needs the trie
+/// to be up to date with the parent.
+#[derive(Debug)]
+pub struct PayloadBuilder {
+    db: Arc<Env<WriteMap>>,
+    chain: OpChainSpec,
+    pool: Option<Arc<TxPool>>,
+    payloads: Mutex<VecDeque<BuiltPayload>>,
+}
+
+impl PayloadBuilder {
+    /// Creates a builder building on top of the given database
+    pub fn new(db: Arc<Env<WriteMap>>, chain: OpChainSpec) -> Self {
+        Self { db, chain, pool: None, payloads: Mutex::new(VecDeque::new()) }
+    }
+
+    /// Fills blocks with the best transactions of the given pool
+    pub fn with_pool(mut self, pool: Arc<TxPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Returns the payload with the given id, if it was built recently
+    pub fn payload(&self, id: H64) -> Option<BuiltPayload> {
+        let payloads = self.payloads.lock().expect("poisoned");
+        payloads.iter().find(|payload| payload.id == id).cloned()
+    }
+
+    /// Returns the receipts of the recently built block with the given hash
+    pub fn receipts(&self, block_hash: H256) -> Option<Vec<OpReceipt>> {
+        let payloads = self.payloads.lock().expect("poisoned");
+        let payload = payloads.iter().find(|payload| payload.block.hash() == block_hash)?;
+        Some(payload.receipts.clone())
+    }
+
+    /// Builds a block on top of the block with the given hash and keeps it for
+    /// `engine_getPayload`. Returns the id of the payload, the same for the same parent and
+    /// attributes.
+    pub fn build(&self, parent_hash: H256, attributes: &OpPayloadAttributes) -> Result<H64> {
+        let id = payload_id(parent_hash, attributes);
+        if self.payload(id).is_some() {
+            return Ok(id)
+        }
+        let parent = self.db.view(|tx| -> Result<SealedHeader> {
+            let number = tx
+                .get::<tables::HeaderNumbers>(parent_hash)?
+                .ok_or_else(|| eyre::eyre!("Unknown parent block {parent_hash:?}"))?;
+            let header = tx
+                .get::<tables::Headers>(number)?
+                .ok_or_else(|| eyre::eyre!("Header for block {number} not found"))?;
+            Ok(SealedHeader::new(header, parent_hash))
+        })??;
+
+        let (block, receipts, fees) = self.build_block(&parent, attributes)?;
+        tracing::info!(target: "reth::payload", ?id, number = block.number, hash = ?block.hash(), txs = block.body.len(), "Built payload");
+        let mut payloads = self.payloads.lock().expect("poisoned");
+        if payloads.len() >= MAX_PAYLOADS {
+            payloads.pop_front();
+        }
+        payloads.push_back(BuiltPayload { id, block, receipts, fees });
+        Ok(id)
+    }
+
+    /// Assembles and executes the block following `parent`
+    fn build_block(
+        &self,
+        parent: &SealedHeader,
+        attributes: &OpPayloadAttributes,
+    ) -> Result<(SealedBlock, Vec<OpReceipt>, U256)> {
+        let number = parent.number + 1;
+        let timestamp = attributes.timestamp.as_u64();
+        if timestamp <= parent.timestamp {
+            eyre::bail!(
+                "Payload timestamp {timestamp} does not follow the parent timestamp {}",
+                parent.timestamp
+            );
+        }
+        if attributes.withdrawals.as_ref().map_or(false, |withdrawals| !withdrawals.is_empty()) {
+            eyre::bail!("OP Stack blocks carry no withdrawals");
+        }
+        let canyon = self.chain.is_canyon_active(number, timestamp);
+        let denominator = self.chain.eip1559_denominator_at(number, timestamp);
+        let base_fee = next_base_fee(parent, self.chain.eip1559_elasticity, denominator);
+        let header = Header {
+            parent_hash: parent.hash(),
+            ommers_hash: EMPTY_OMMER_ROOT,
+            beneficiary: attributes.suggested_fee_recipient,
+            number,
+            gas_limit: attributes
+                .gas_limit
+                .map_or(parent.gas_limit, |gas_limit| gas_limit.as_u64()),
+            timestamp,
+            mix_hash: attributes.prev_randao,
+            base_fee_per_gas: Some(base_fee),
+            withdrawals_root: canyon.then_some(EMPTY_ROOT),
+            ..Default::default()
+        };
+
+        let forced = attributes
+            .transactions
+            .iter()
+            .map(|tx| deposit::decode_tx(tx))
+            .collect::<Result<Vec<_>>>()?;
+        let candidates = match &self.pool {
+            Some(pool) if !attributes.no_tx_pool => {
+                pool.best_transactions(base_fee, header.gas_limit)?
+            }
+            _ => vec![],
+        };
+
+        let tx = Transaction::new(self.db.as_ref())?;
+        let trie = replay::trie_block(&*tx)?;
+        if trie != Some(parent.number) {
+            eyre::bail!(
+                "The trie is not up to date with block {}, run state hash-and-trie to build on it",
+                parent.number
+            );
+        }
+        let total_difficulty =
+            tx.get::<tables::HeaderTD>(parent.number)?.map(|td| td.0).unwrap_or_default();
+
+        let mut body = Vec::with_capacity(forced.len() + candidates.len());
+        let mut fees = U256::ZERO;
+        let executed = {
+            let mut state = SubState::new(State::new(LatestStateProviderRef::new(&*tx)));
+            let mut executor =
+                BlockExecutor::new(&self.chain, &header, total_difficulty, &mut state);
+            for transaction in forced {
+                executor.execute(&transaction).map_err(|err| {
+                    eyre::eyre!(
+                        "Forced transaction {:?} failed: {err}",
+                        deposit::tx_hash(&transaction)
+                    )
+                })?;
+                body.push(transaction);
+            }
+            for transaction in candidates {
+                if transaction.gas_limit() > header.gas_limit - executor.gas_used() {
+                    continue
+                }
+                let gas_used = match executor.execute(&transaction) {
+                    Ok(gas_used) => gas_used,
+                    Err(err) => {
+                        tracing::debug!(target: "reth::payload", hash = ?transaction.hash(), %err, "Left out invalid transaction");
+                        continue
+                    }
+                };
+                let tip = pool::effective_tip(&transaction, base_fee).unwrap_or_default();
+                fees += U256::from(tip) * U256::from(gas_used);
+                body.push(transaction);
+            }
+            executor.finish()
+        };
+
+        let header = Header {
+            transactions_root: deposit::transactions_root(&body),
+            receipts_root: executed.receipts_root(),
+            logs_bloom: executed.logs_bloom(),
+            gas_used: executed.gas_used,
+            ..header
+        };
+        // The changes are only written to compute the state root, the transaction is dropped
+        let receipts = executed.receipts;
+        let state_root = execution::write_state(&tx, &self.chain, parent, executed.result)?;
+        drop(tx);
+
+        let header = Header { state_root, ..header };
+        let withdrawals = canyon.then(Vec::new);
+        let block = SealedBlock { header: keccak::seal(header), body, ommers: vec![], withdrawals };
+        Ok((block, receipts, fees))
+    }
+}
+
+/// The id of the payload built on top of `parent` for the given attributes: the first 8 bytes of
+/// the hash of both
+pub fn payload_id(parent: H256, attributes: &OpPayloadAttributes) -> H64 {
+    let mut data = parent.as_bytes().to_vec();
+    data.extend_from_slice(&attributes.timestamp.as_u64().to_be_bytes());
+    data.extend_from_slice(attributes.prev_randao.as_bytes());
+    data.extend_from_slice(attributes.suggested_fee_recipient.as_bytes());
+    for transaction in &attributes.transactions {
+        data.extend_from_slice(keccak::keccak256(transaction).as_bytes());
+    }
+    data.push(attributes.no_tx_pool as u8);
+    if let Some(gas_limit) = attributes.gas_limit {
+        data.extend_from_slice(&gas_limit.as_u64().to_be_bytes());
+    }
+    H64::from_slice(&keccak::keccak256(data).as_bytes()[..8])
+}
+
+/// The base fee of the block following `parent` under EIP-1559, with the given elasticity
+/// multiplier and base fee change denominator
+pub fn next_base_fee(parent: &Header, elasticity: u64, denominator: u64) -> u64 {
+    let Some(base_fee) = parent.base_fee_per_gas else { return INITIAL_BASE_FEE };
+    let target = (parent.gas_limit / elasticity.max(1)).max(1) as u128;
+    let used = parent.gas_used as u128;
+    let change =
+        |delta: u128| (base_fee as u128 * delta / target / denominator.max(1) as u128) as u64;
+    if used > target {
+        base_fee + change(used - target).max(1)
+    } else {
+        base_fee - change(target - used)
+    }
+}
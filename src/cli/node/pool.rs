@@ -0,0 +1,408 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use eyre::Result;
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTx,
+};
+use reth_primitives::{Address, Header, SealedBlock, Transaction, TransactionSigned, H256, U256};
+
+use crate::cli::{chain::OpChainSpec, deposit, l1_cost::L1BlockInfo};
+
+/// The default number of transactions the pool holds
+pub const DEFAULT_MAX_TRANSACTIONS: usize = 10_000;
+
+/// The percentage by which a transaction replacing a pooled one with the same nonce has to raise
+/// its fees
+pub const REPLACEMENT_FEE_BUMP: u128 = 10;
+
+/// The gas every transaction pays before its data
+const TX_BASE_GAS: u64 = 21_000;
+
+/// The gas contract creations pay on top of [TX_BASE_GAS]
+const TX_CREATE_GAS: u64 = 32_000;
+
+/// A transaction refused by the pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoolError {
+    /// The transaction envelope could not be decoded
+    Decode(String),
+    /// The transaction is pooled already
+    AlreadyKnown,
+    /// Deposits are derived from L1 by op-node and can not be submitted
+    Deposit,
+    /// The sender could not be recovered from the signature
+    InvalidSignature,
+    /// The transaction is signed for another chain, or without replay protection
+    ChainId { expected: u64, got: Option<u64> },
+    /// The sender sent a transaction with this nonce already
+    NonceTooLow { expected: u64, got: u64 },
+    /// The gas limit doesn't cover the intrinsic gas of the transaction
+    IntrinsicGasTooLow { intrinsic: u64, gas_limit: u64 },
+    /// The gas limit exceeds the gas limit of the blocks
+    GasLimitExceeded { block_gas_limit: u64, gas_limit: u64 },
+    /// The balance of the sender doesn't cover the gas, the value and the L1 fee
+    InsufficientFunds { balance: U256, cost: U256, l1_fee: U256 },
+    /// A pooled transaction with the same sender and nonce pays as much or more
+    ReplacementUnderpriced,
+    /// The pool holds its maximum number of transactions
+    PoolFull,
+}
+
+impl fmt::Display for PoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoolError::Decode(err) => write!(f, "invalid transaction: {err}"),
+            PoolError::AlreadyKnown => write!(f, "already known"),
+            PoolError::Deposit => write!(f, "deposit transactions can not be submitted"),
+            PoolError::InvalidSignature => write!(f, "invalid sender"),
+            PoolError::ChainId { expected, got: Some(got) } => {
+                write!(f, "invalid chain id {got}, expected {expected}")
+            }
+            PoolError::ChainId { expected, got: None } => {
+                write!(f, "only replay-protected transactions for chain {expected} are accepted")
+            }
+            PoolError::NonceTooLow { expected, got } => {
+                write!(f, "nonce too low: next nonce {expected}, tx nonce {got}")
+            }
+            PoolError::IntrinsicGasTooLow { intrinsic, gas_limit } => {
+                write!(f, "intrinsic gas too low: have {gas_limit}, want {intrinsic}")
+            }
+            PoolError::GasLimitExceeded { block_gas_limit, gas_limit } => {
+                write!(f, "gas limit {gas_limit} exceeds the block gas limit {block_gas_limit}")
+            }
+            PoolError::InsufficientFunds { balance, cost, l1_fee } => write!(
+                f,
+                "insufficient funds for gas * price + value + l1 fee: balance {balance}, cost \
+                 {cost} including an L1 fee of {l1_fee}"
+            ),
+            PoolError::ReplacementUnderpriced => write!(f, "replacement transaction underpriced"),
+            PoolError::PoolFull => write!(f, "txpool is full"),
+        }
+    }
+}
+
+impl std::error::Error for PoolError {}
+
+/// A transaction admitted to the pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PooledTransaction {
+    /// The signed transaction
+    pub transaction: TransactionSigned,
+    /// The recovered sender
+    pub sender: Address,
+    /// The L1 fee the transaction paid for when it was admitted
+    pub l1_fee: U256,
+}
+
+impl PooledTransaction {
+    /// The nonce of the transaction
+    pub fn nonce(&self) -> u64 {
+        self.transaction.nonce()
+    }
+
+    /// The tip per gas the transaction pays on top of the given base fee, `None` if its fee cap
+    /// doesn't cover the base fee
+    pub fn effective_tip(&self, base_fee: u64) -> Option<u128> {
+        effective_tip(&self.transaction, base_fee)
+    }
+}
+
+/// The pooled transactions, indexed by hash and by sender and nonce
+#[derive(Debug, Default)]
+struct PoolState {
+    by_hash: HashMap<H256, PooledTransaction>,
+    by_sender: BTreeMap<(Address, u64), H256>,
+}
+
+impl PoolState {
+    fn remove(&mut self, hash: &H256) -> Option<PooledTransaction> {
+        let pooled = self.by_hash.remove(hash)?;
+        self.by_sender.remove(&(pooled.sender, pooled.nonce()));
+        Some(pooled)
+    }
+}
+
+/// The pool of user transactions submitted with `eth_sendRawTransaction`, waiting to be included
+/// by the payload builder.
+///
+/// Transactions are validated against the canonical tip when they are submitted: their chain id,
+/// signature, nonce and gas limit, and whether the sender can afford the gas, the value and the
+/// L1 fee of the transaction. Transactions are dropped once the canonical chain moves the nonce
+/// of their sender past them.
+#[derive(Debug)]
+pub struct TxPool {
+    db: Arc<Env<WriteMap>>,
+    chain: OpChainSpec,
+    max_transactions: usize,
+    state: Mutex<PoolState>,
+}
+
+impl TxPool {
+    /// Creates an empty pool validating against the given database and chain
+    pub fn new(db: Arc<Env<WriteMap>>, chain: OpChainSpec) -> Self {
+        Self {
+            db,
+            chain,
+            max_transactions: DEFAULT_MAX_TRANSACTIONS,
+            state: Mutex::new(PoolState::default()),
+        }
+    }
+
+    /// Sets the number of transactions the pool holds before it refuses new ones
+    pub fn with_max_transactions(mut self, max_transactions: usize) -> Self {
+        self.max_transactions = max_transactions;
+        self
+    }
+
+    /// The number of pooled transactions
+    pub fn len(&self) -> usize {
+        self.state.lock().expect("poisoned").by_hash.len()
+    }
+
+    /// Returns true if the pool holds no transactions
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the pooled transaction with the given hash
+    pub fn get(&self, hash: &H256) -> Option<PooledTransaction> {
+        self.state.lock().expect("poisoned").by_hash.get(hash).cloned()
+    }
+
+    /// Decodes a transaction envelope and adds the transaction to the pool. Returns the hash of
+    /// the transaction, or a [PoolError] if the pool refuses it.
+    pub fn add_raw(&self, data: &[u8]) -> Result<H256> {
+        let transaction =
+            deposit::decode_tx(data).map_err(|err| PoolError::Decode(err.to_string()))?;
+        self.add(transaction)
+    }
+
+    /// Validates a transaction against the canonical tip and adds it to the pool, replacing a
+    /// pooled transaction of the same sender and nonce that pays at least [REPLACEMENT_FEE_BUMP]
+    /// percent less. Returns the hash of the transaction, or a [PoolError] if the pool refuses
+    /// it.
+    pub fn add(&self, transaction: TransactionSigned) -> Result<H256> {
+        let hash = transaction.hash();
+        if self.state.lock().expect("poisoned").by_hash.contains_key(&hash) {
+            return Err(PoolError::AlreadyKnown.into())
+        }
+        let pooled = self.validate(transaction)?;
+
+        let mut state = self.state.lock().expect("poisoned");
+        let key = (pooled.sender, pooled.nonce());
+        match state.by_sender.get(&key).copied() {
+            Some(existing) => {
+                let replaced = &state.by_hash[&existing].transaction;
+                if !outbids(&pooled.transaction, replaced) {
+                    return Err(PoolError::ReplacementUnderpriced.into())
+                }
+                state.remove(&existing);
+                tracing::debug!(target: "reth::txpool", ?hash, ?existing, "Replaced pooled transaction");
+            }
+            None if state.by_hash.len() >= self.max_transactions => {
+                return Err(PoolError::PoolFull.into())
+            }
+            None => {}
+        }
+        state.by_sender.insert(key, hash);
+        state.by_hash.insert(hash, pooled);
+        Ok(hash)
+    }
+
+    /// Checks a transaction against the chain rules and the state of the canonical tip
+    fn validate(&self, transaction: TransactionSigned) -> Result<PooledTransaction> {
+        if deposit::is_deposit(&transaction) {
+            return Err(PoolError::Deposit.into())
+        }
+        let expected = self.chain.chain_id();
+        if transaction.chain_id() != Some(expected) {
+            return Err(PoolError::ChainId { expected, got: transaction.chain_id() }.into())
+        }
+        let sender = transaction.recover_signer().ok_or(PoolError::InvalidSignature)?;
+        let gas_limit = transaction.gas_limit();
+        let intrinsic = intrinsic_gas(&transaction);
+        if gas_limit < intrinsic {
+            return Err(PoolError::IntrinsicGasTooLow { intrinsic, gas_limit }.into())
+        }
+
+        let (head, account, l1_info) = self.db.view(|tx| -> Result<_> {
+            let head = canonical_head(tx)?;
+            let account = tx.get::<tables::PlainAccountState>(sender)?.unwrap_or_default();
+            let l1_info = if self.chain.is_bedrock_active_at_block(head.number + 1) {
+                Some(L1BlockInfo::read(tx)?)
+            } else {
+                None
+            };
+            Ok((head, account, l1_info))
+        })??;
+        if gas_limit > head.gas_limit {
+            return Err(
+                PoolError::GasLimitExceeded { block_gas_limit: head.gas_limit, gas_limit }.into()
+            )
+        }
+        if transaction.nonce() < account.nonce {
+            return Err(
+                PoolError::NonceTooLow { expected: account.nonce, got: transaction.nonce() }.into()
+            )
+        }
+
+        let l1_fee = l1_info.map_or(U256::ZERO, |info| {
+            let regolith = self.chain.is_regolith_active(head.number + 1, head.timestamp);
+            info.l1_cost(&deposit::encode_tx(&transaction), regolith)
+        });
+        let cost = U256::from(gas_limit) * U256::from(transaction.max_fee_per_gas()) +
+            U256::from(transaction.value()) +
+            l1_fee;
+        if cost > account.balance {
+            let balance = account.balance;
+            return Err(PoolError::InsufficientFunds { balance, cost, l1_fee }.into())
+        }
+        Ok(PooledTransaction { transaction, sender, l1_fee })
+    }
+
+    /// The transactions the payload builder should include in a block with the given base fee and
+    /// gas limit, best paying first. Only transactions following the nonce of their sender
+    /// without gaps and covering the base fee are returned, in nonce order per sender.
+    pub fn best_transactions(
+        &self,
+        base_fee: u64,
+        gas_limit: u64,
+    ) -> Result<Vec<TransactionSigned>> {
+        let nonces = self.sender_nonces()?;
+        let state = self.state.lock().expect("poisoned");
+        let mut queues: HashMap<Address, VecDeque<&PooledTransaction>> = HashMap::new();
+        for ((sender, nonce), hash) in &state.by_sender {
+            // Added after the nonces were read
+            let Some(&account_nonce) = nonces.get(sender) else { continue };
+            let queue = queues.entry(*sender).or_default();
+            let next = queue.back().map_or(account_nonce, |last| last.nonce() + 1);
+            let pooled = &state.by_hash[hash];
+            if *nonce == next && pooled.effective_tip(base_fee).is_some() {
+                queue.push_back(pooled);
+            }
+        }
+
+        let mut best = Vec::new();
+        let mut remaining = gas_limit;
+        loop {
+            let best_sender = queues
+                .iter()
+                .filter_map(|(sender, queue)| {
+                    Some((*sender, queue.front()?.effective_tip(base_fee)?))
+                })
+                .max_by_key(|(_, tip)| *tip);
+            let Some((sender, _)) = best_sender else { break };
+            let queue = queues.get_mut(&sender).expect("sender of a queue");
+            let pooled = queue.pop_front().expect("non-empty queue");
+            if pooled.transaction.gas_limit() > remaining {
+                // The later transactions of the sender depend on this one
+                queue.clear();
+                continue
+            }
+            remaining -= pooled.transaction.gas_limit();
+            best.push(pooled.transaction.clone());
+        }
+        Ok(best)
+    }
+
+    /// Drops the transactions a new canonical block included or made stale. Returns the number of
+    /// dropped transactions.
+    pub fn on_canonical_block(&self, block: &SealedBlock) -> Result<usize> {
+        let mut state = self.state.lock().expect("poisoned");
+        let included = block
+            .body
+            .iter()
+            .filter(|transaction| state.remove(&transaction.hash()).is_some())
+            .count();
+        drop(state);
+        Ok(included + self.prune()?)
+    }
+
+    /// Drops the transactions whose nonce the canonical chain moved past. Returns the number of
+    /// dropped transactions.
+    pub fn prune(&self) -> Result<usize> {
+        let nonces = self.sender_nonces()?;
+        let mut state = self.state.lock().expect("poisoned");
+        let stale = state
+            .by_sender
+            .iter()
+            .filter(|((sender, nonce), _)| nonces.get(sender).map_or(false, |next| nonce < next))
+            .map(|(_, hash)| *hash)
+            .collect::<Vec<_>>();
+        for hash in &stale {
+            state.remove(hash);
+        }
+        Ok(stale.len())
+    }
+
+    /// The nonces of the senders of the pooled transactions at the canonical tip
+    fn sender_nonces(&self) -> Result<HashMap<Address, u64>> {
+        let senders = {
+            let state = self.state.lock().expect("poisoned");
+            state.by_hash.values().map(|pooled| pooled.sender).collect::<Vec<_>>()
+        };
+        self.db.view(|tx| -> Result<_> {
+            let mut nonces = HashMap::new();
+            for sender in senders {
+                let account = tx.get::<tables::PlainAccountState>(sender)?;
+                nonces.insert(sender, account.map_or(0, |account| account.nonce));
+            }
+            Ok(nonces)
+        })?
+    }
+}
+
+/// Returns true if `transaction` raises both fees of `replaced` by at least
+/// [REPLACEMENT_FEE_BUMP] percent
+fn outbids(transaction: &TransactionSigned, replaced: &TransactionSigned) -> bool {
+    let bumped = |fee: u128| fee.saturating_mul(100 + REPLACEMENT_FEE_BUMP) / 100;
+    let priority = |transaction: &TransactionSigned| {
+        transaction.max_priority_fee_per_gas().unwrap_or_else(|| transaction.max_fee_per_gas())
+    };
+    transaction.max_fee_per_gas() >= bumped(replaced.max_fee_per_gas()) &&
+        priority(transaction) >= bumped(priority(replaced))
+}
+
+/// The tip per gas a transaction pays on top of the given base fee, `None` if its fee cap doesn't
+/// cover the base fee
+pub fn effective_tip(transaction: &TransactionSigned, base_fee: u64) -> Option<u128> {
+    let max_fee = transaction.max_fee_per_gas();
+    let priority_fee = transaction.max_priority_fee_per_gas().unwrap_or(max_fee);
+    max_fee.checked_sub(base_fee as u128).map(|available| available.min(priority_fee))
+}
+
+/// The gas a transaction pays before it executes: the base cost, the cost of creating a contract,
+/// of its call data and of its access list
+pub fn intrinsic_gas(transaction: &TransactionSigned) -> u64 {
+    let input = transaction.input();
+    let zeroes = input.iter().filter(|byte| **byte == 0).count() as u64;
+    let data_gas = zeroes * 4 + (input.len() as u64 - zeroes) * 16;
+    let create_gas = if transaction.to().is_none() { TX_CREATE_GAS } else { 0 };
+    let access_list = match &transaction.transaction {
+        Transaction::Legacy(_) => None,
+        Transaction::Eip2930(tx) => Some(&tx.access_list),
+        Transaction::Eip1559(tx) => Some(&tx.access_list),
+    };
+    let access_list_gas = access_list.map_or(0, |list| {
+        list.0.iter().map(|item| 2_400 + 1_900 * item.storage_keys.len() as u64).sum()
+    });
+    TX_BASE_GAS + create_gas + data_gas + access_list_gas
+}
+
+/// The header of the canonical tip
+fn canonical_head<'a, TX: DbTx<'a>>(tx: &TX) -> Result<Header> {
+    let (number, _) = tx
+        .cursor_read::<tables::CanonicalHeaders>()?
+        .last()?
+        .ok_or_else(|| eyre::eyre!("No canonical blocks found in the database"))?;
+    tx.get::<tables::Headers>(number)?
+        .ok_or_else(|| eyre::eyre!("Header for canonical block {number} not found"))
+}
@@ -0,0 +1,63 @@
+use std::{sync::mpsc, thread};
+
+use eyre::Result;
+
+/// The default number of decoded chunks every decoder may hold ahead of the writer
+pub const DEFAULT_QUEUE_DEPTH: usize = 2;
+
+/// Decodes the given chunks on `threads` decoder threads and hands the decoded chunks to `write`
+/// on the calling thread, in the order of the chunks.
+///
+/// The chunks are dealt out to the decoders in turn and every decoder sends its results through
+/// its own channel holding at most `depth` decoded chunks, so decoding runs ahead of writing by a
+/// bounded amount and a slow writer stalls the decoders instead of buffering the whole input. The
+/// decoders stop once `write` fails.
+pub fn run<C, T, D, W>(
+    chunks: Vec<C>,
+    threads: usize,
+    depth: usize,
+    decode: D,
+    mut write: W,
+) -> Result<()>
+where
+    C: Send,
+    T: Send,
+    D: Fn(C) -> T + Sync,
+    W: FnMut(T) -> Result<()>,
+{
+    let total = chunks.len();
+    let threads = threads.clamp(1, total.max(1));
+    let mut queues: Vec<Vec<C>> = (0..threads).map(|_| Vec::new()).collect();
+    for (index, chunk) in chunks.into_iter().enumerate() {
+        queues[index % threads].push(chunk);
+    }
+
+    thread::scope(|scope| {
+        let decode = &decode;
+        let mut receivers = Vec::with_capacity(threads);
+        for (worker, queue) in queues.into_iter().enumerate() {
+            let (sender, receiver) = mpsc::sync_channel(depth.max(1));
+            thread::Builder::new().name(format!("decoder-{worker}")).spawn_scoped(
+                scope,
+                move || {
+                    for chunk in queue {
+                        // The writer hung up after failing
+                        if sender.send(decode(chunk)).is_err() {
+                            break
+                        }
+                    }
+                },
+            )?;
+            receivers.push(receiver);
+        }
+
+        for index in 0..total {
+            let worker = index % threads;
+            let decoded = receivers[worker].recv().map_err(|_| {
+                eyre::eyre!("Decoder thread {worker} stopped before decoding chunk {index}")
+            })?;
+            write(decoded)?;
+        }
+        Ok(())
+    })
+}
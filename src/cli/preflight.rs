@@ -0,0 +1,179 @@
+use std::{fmt, fs, path::Path};
+
+use eyre::Result;
+
+use crate::cli::{compression::Compression, source::shards, throttle};
+
+/// The share of the free space that has to remain unused after an import
+const HEADROOM: f64 = 0.1;
+
+/// The assumed ratio between the decompressed and the compressed size of a compressed input
+const COMPRESSION_RATIO: u64 = 4;
+
+/// An import stage writing to the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportStage {
+    /// The genesis block and allocation
+    Genesis,
+    /// Headers, bodies, transactions and their lookup tables
+    Blocks,
+    /// Receipts and their L1 fee fields
+    Receipts,
+    /// The plain world state
+    State,
+}
+
+impl ImportStage {
+    /// The expected database growth per byte of input.
+    ///
+    /// Blocks are stored several times over: headers, bodies, transactions, the hash lookups and
+    /// the senders. State dumps are hex-encoded JSON, which shrinks by about half when decoded.
+    /// All factors include the MDBX page overhead.
+    pub fn growth_factor(&self) -> f64 {
+        match self {
+            ImportStage::Genesis => 1.5,
+            ImportStage::Blocks => 3.0,
+            ImportStage::Receipts => 1.5,
+            ImportStage::State => 1.0,
+        }
+    }
+
+    /// The estimated database growth when importing an input of the given size
+    pub fn estimate_growth(&self, input_len: u64) -> u64 {
+        (input_len as f64 * self.growth_factor()) as u64
+    }
+}
+
+impl fmt::Display for ImportStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ImportStage::Genesis => "genesis",
+            ImportStage::Blocks => "blocks",
+            ImportStage::Receipts => "receipts",
+            ImportStage::State => "state",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Returns the space available to the current user on the volume holding `path`. If `path` does
+/// not exist yet, its closest existing ancestor is used.
+#[cfg(unix)]
+pub fn available_space(path: &Path) -> Result<Option<u64>> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let Some(existing) = path.ancestors().find(|ancestor| ancestor.exists()) else {
+        return Ok(None)
+    };
+    let existing = if existing.as_os_str().is_empty() { Path::new(".") } else { existing };
+    let c_path = CString::new(existing.as_os_str().as_bytes())?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `c_path` is a valid nul-terminated string and `stat` is only read after a
+    // successful call initialized it
+    if unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error().into())
+    }
+    let stat = unsafe { stat.assume_init() };
+    // The field types differ between platforms
+    #[allow(clippy::unnecessary_cast)]
+    let available = stat.f_bavail as u64 * stat.f_frsize as u64;
+    Ok(Some(available))
+}
+
+/// Returns the space available to the current user on the volume holding `path`. If `path` does
+/// not exist yet, its closest existing ancestor is used.
+#[cfg(windows)]
+pub fn available_space(path: &Path) -> Result<Option<u64>> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let Some(existing) = path.ancestors().find(|ancestor| ancestor.exists()) else {
+        return Ok(None)
+    };
+    let existing = if existing.as_os_str().is_empty() { Path::new(".") } else { existing };
+    let wide = existing.as_os_str().encode_wide().chain(Some(0)).collect::<Vec<u16>>();
+    let mut available = 0u64;
+    // SAFETY: `wide` is a valid nul-terminated string and the totals are not requested
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error().into())
+    }
+    Ok(Some(available))
+}
+
+/// Returns the space available on the volume holding `path`, which is unknown on this platform
+#[cfg(not(any(unix, windows)))]
+pub fn available_space(_path: &Path) -> Result<Option<u64>> {
+    Ok(None)
+}
+
+/// Checks that the volume holding the database has room for importing the given input, leaving
+/// some headroom. Fails if it does not, or only warns if `allow_low_space` is set.
+pub fn check_disk_space(
+    db_path: &Path,
+    stage: ImportStage,
+    input: &Path,
+    allow_low_space: bool,
+) -> Result<()> {
+    if throttle::is_stdin(input) {
+        tracing::debug!(target: "reth::cli", "The size of stdin is unknown, skipping the preflight check");
+        return Ok(())
+    }
+    let input_len = input_size(input)?;
+    let required = stage.estimate_growth(input_len);
+    let Some(available) = available_space(db_path)? else {
+        tracing::debug!(target: "reth::cli", "Unable to determine the free disk space, skipping the preflight check");
+        return Ok(())
+    };
+
+    let usable = (available as f64 * (1.0 - HEADROOM)) as u64;
+    if required <= usable {
+        tracing::debug!(target: "reth::cli", %stage, required, available, "Disk space preflight check passed");
+        return Ok(())
+    }
+
+    let message = format!(
+        "Importing {stage} is estimated to grow the database by {} MiB, but only {} MiB are \
+         available at {} (keeping {:.0}% headroom)",
+        required / (1024 * 1024),
+        usable / (1024 * 1024),
+        db_path.display(),
+        HEADROOM * 100.0
+    );
+    if allow_low_space {
+        tracing::warn!(target: "reth::cli", "{message}");
+        return Ok(())
+    }
+    eyre::bail!("{message}. Free up space or pass --allow-low-space to import anyway.")
+}
+
+/// The size of an input file, or of all files in an input directory or matching a glob pattern.
+/// Compressed files count with their estimated decompressed size.
+fn input_size(path: &Path) -> Result<u64> {
+    if let Some(pattern) = path.to_str().filter(|path| shards::is_pattern(path)) {
+        let mut size = 0;
+        for file in shards::input_files(pattern)? {
+            size += input_size(&file)?;
+        }
+        return Ok(size)
+    }
+    let metadata = fs::metadata(path)?;
+    if !metadata.is_dir() {
+        if Compression::from_extension(path).is_some() {
+            return Ok(metadata.len() * COMPRESSION_RATIO)
+        }
+        return Ok(metadata.len())
+    }
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        size += input_size(&entry?.path())?;
+    }
+    Ok(size)
+}
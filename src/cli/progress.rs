@@ -0,0 +1,247 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{metrics, report, watchdog::StalledImport};
+
+/// How many items are processed between refreshes of the progress bar message
+const MESSAGE_INTERVAL: u64 = 1024;
+
+/// How often the per-table write statistics are logged while an import commits batches
+const WRITES_LOG_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The progress bar template of stages with a known number of items
+const BAR_TEMPLATE: &str =
+    "{spinner} [{elapsed_precise}] {wide_bar} {human_pos}/{human_len} ({per_sec}, ETA {eta}) {msg}";
+
+/// The progress bar template of stages with an unknown number of items
+const SPINNER_TEMPLATE: &str = "{spinner} [{elapsed_precise}] {human_pos} ({per_sec}) {msg}";
+
+/// Progress of a running import, updated by the importer.
+///
+/// It is observed by the [Watchdog](crate::cli::watchdog::Watchdog) and, unless the import runs
+/// with `--quiet`, rendered as a progress bar showing the processed items per second, the bytes
+/// read, the current block and the ETA of the stage.
+#[derive(Debug)]
+pub struct ImportProgress {
+    stage: Mutex<&'static str>,
+    stage_started: Mutex<Option<(Instant, u64)>>,
+    items: AtomicU64,
+    offset: AtomicU64,
+    block: AtomicU64,
+    tx_opened_at: Mutex<Option<Instant>>,
+    stalled: AtomicBool,
+    writes: Mutex<BTreeMap<&'static str, TableWrites>>,
+    writes_logged_at: Mutex<Instant>,
+    reported: AtomicBool,
+    bar: Option<ProgressBar>,
+}
+
+/// The records an import inserted into a database table and their approximate size
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableWrites {
+    /// The number of inserted records
+    pub inserts: u64,
+    /// The approximate number of bytes written
+    pub bytes: u64,
+}
+
+impl Default for ImportProgress {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl ImportProgress {
+    /// Creates a new progress, rendered to the given progress bar if any
+    pub fn new(bar: Option<ProgressBar>) -> Self {
+        Self {
+            stage: Mutex::new("startup"),
+            stage_started: Mutex::new(None),
+            items: AtomicU64::new(0),
+            offset: AtomicU64::new(0),
+            block: AtomicU64::new(0),
+            tx_opened_at: Mutex::new(None),
+            stalled: AtomicBool::new(false),
+            writes: Mutex::new(BTreeMap::new()),
+            writes_logged_at: Mutex::new(Instant::now()),
+            reported: AtomicBool::new(false),
+            bar,
+        }
+    }
+
+    /// Creates a new progress rendered to a progress bar on stderr
+    pub fn with_bar() -> Self {
+        let bar = ProgressBar::new_spinner();
+        bar.enable_steady_tick(Duration::from_millis(200));
+        Self::new(Some(bar))
+    }
+
+    /// Enters a new stage of the import, reporting the previous one as completed
+    pub fn set_stage(&self, stage: &'static str) {
+        self.close_stage();
+        *self.stage.lock().expect("poisoned") = stage;
+        *self.stage_started.lock().expect("poisoned") = Some((Instant::now(), self.items()));
+        if let Some(bar) = &self.bar {
+            bar.reset();
+            bar.set_length(0);
+            bar.set_style(style(SPINNER_TEMPLATE));
+            bar.set_message(self.message());
+        }
+    }
+
+    /// Sets the number of items of the current stage, which allows estimating its remaining time
+    pub fn set_total(&self, total: u64) {
+        if let Some(bar) = &self.bar {
+            bar.set_style(style(BAR_TEMPLATE));
+            bar.set_length(total);
+        }
+    }
+
+    /// The stage the import is currently in
+    pub fn stage(&self) -> &'static str {
+        *self.stage.lock().expect("poisoned")
+    }
+
+    /// Records that `items` more records were processed.
+    ///
+    /// Fails with [StalledImport] once the watchdog flagged the import as stalled, so the import
+    /// unwinds instead of continuing after the watchdog gave up on it.
+    pub fn advance(&self, items: u64) -> Result<(), StalledImport> {
+        let before = self.items.fetch_add(items, Ordering::Relaxed);
+        metrics::record_items(self.stage(), items);
+        if let Some(bar) = &self.bar {
+            bar.inc(items);
+            if before / MESSAGE_INTERVAL != (before + items) / MESSAGE_INTERVAL {
+                bar.set_message(self.message());
+            }
+        }
+        self.check()
+    }
+
+    /// Records the offset the reader reached in the file being imported
+    pub fn set_offset(&self, offset: u64) {
+        self.offset.store(offset, Ordering::Relaxed);
+    }
+
+    /// Records the number of the block being processed
+    pub fn set_block(&self, number: u64) {
+        self.block.store(number, Ordering::Relaxed);
+    }
+
+    /// The total number of processed items
+    pub fn items(&self) -> u64 {
+        self.items.load(Ordering::Relaxed)
+    }
+
+    /// The current offset into the file being read
+    pub fn offset(&self) -> u64 {
+        self.offset.load(Ordering::Relaxed)
+    }
+
+    /// The number of the block being processed
+    pub fn block(&self) -> u64 {
+        self.block.load(Ordering::Relaxed)
+    }
+
+    /// Records that a database write transaction was opened
+    pub fn tx_opened(&self) {
+        *self.tx_opened_at.lock().expect("poisoned") = Some(Instant::now());
+    }
+
+    /// Records that the open database write transaction was committed or aborted. Logs the
+    /// per-table write statistics if they were not logged for a while.
+    pub fn tx_closed(&self) {
+        *self.tx_opened_at.lock().expect("poisoned") = None;
+        let mut logged_at = self.writes_logged_at.lock().expect("poisoned");
+        if logged_at.elapsed() >= WRITES_LOG_INTERVAL {
+            *logged_at = Instant::now();
+            self.log_table_writes();
+        }
+    }
+
+    /// Records that `inserts` records with a total of about `bytes` bytes were written to the
+    /// given table
+    pub fn record_writes(&self, table: &'static str, inserts: u64, bytes: u64) {
+        metrics::record_writes(table, inserts, bytes);
+        let mut writes = self.writes.lock().expect("poisoned");
+        let table = writes.entry(table).or_default();
+        table.inserts += inserts;
+        table.bytes += bytes;
+    }
+
+    /// The records written to each table so far
+    pub fn table_writes(&self) -> BTreeMap<&'static str, TableWrites> {
+        self.writes.lock().expect("poisoned").clone()
+    }
+
+    /// Logs the records written to each table so far
+    pub fn log_table_writes(&self) {
+        for (table, writes) in self.table_writes() {
+            tracing::info!(target: "reth::cli", table, inserts = writes.inserts, size = %HumanBytes(writes.bytes), "Table writes");
+        }
+    }
+
+    /// How long the current database write transaction has been open
+    pub fn tx_age(&self) -> Option<Duration> {
+        self.tx_opened_at.lock().expect("poisoned").map(|opened| opened.elapsed())
+    }
+
+    /// Returns an error if the watchdog flagged the import as stalled
+    pub fn check(&self) -> Result<(), StalledImport> {
+        if self.stalled.load(Ordering::Relaxed) {
+            return Err(StalledImport { stage: self.stage(), items: self.items() })
+        }
+        Ok(())
+    }
+
+    /// Flags the import as stalled, failing its next progress update
+    pub(crate) fn mark_stalled(&self) {
+        self.stalled.store(true, Ordering::Relaxed);
+    }
+
+    /// Completes the progress bar, leaving the final state of the last stage on screen, and logs
+    /// the per-table write statistics of the import. The stages and writes are added to the
+    /// [report](crate::cli::report) of the command once.
+    pub fn finish(&self) {
+        if let Some(bar) = &self.bar {
+            bar.set_message(self.message());
+            bar.finish();
+        }
+        self.close_stage();
+        if !self.reported.swap(true, Ordering::Relaxed) {
+            for (table, writes) in self.table_writes() {
+                report::record_writes(table, writes);
+            }
+        }
+        self.log_table_writes();
+    }
+
+    /// Reports the current stage as completed
+    fn close_stage(&self) {
+        if let Some((started, items)) = self.stage_started.lock().expect("poisoned").take() {
+            report::record_stage(self.stage(), self.items() - items, started.elapsed());
+        }
+    }
+
+    fn message(&self) -> String {
+        let mut message = format!("{} | {} read", self.stage(), HumanBytes(self.offset()));
+        let block = self.block();
+        if block > 0 {
+            message.push_str(&format!(" | block {block}"));
+        }
+        message
+    }
+}
+
+fn style(template: &str) -> ProgressStyle {
+    ProgressStyle::with_template(template).expect("valid template").progress_chars("=> ")
+}
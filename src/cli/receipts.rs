@@ -1,57 +1,348 @@
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use eyre::Result;
 use reth::runner::CliContext;
 use reth_db::{
     database::Database,
     mdbx::{Env, WriteMap},
-    Error,
+    table::Table,
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::{
+    bloom::logs_bloom,
+    proofs,
+    rpc::{H160, H256},
+    Bloom, Log, TxType, U256,
 };
-use reth_primitives::{rpc::H256, U256};
 use rlp::Decodable;
 use serde::{Deserialize, Serialize};
 
-use super::db;
+use super::{
+    args::{DatabaseArgs, ImportArgs, RpcSyncArgs},
+    compression,
+    dead_letter::{self, DeadLetterFile, ImportError},
+    journal,
+    l1_fee::{L1FeeInfo, L1FeeStore},
+    preflight::ImportStage,
+    progress::ImportProgress,
+    source::{
+        dead_letter::DeadLetterSource,
+        file::FileSource,
+        freezer::{self, FreezerSource},
+        shards::{self, ShardedSource},
+        ReceiptSource, SourceContext,
+    },
+    validate::{self, ReceiptIssue},
+};
+
+pub mod query;
 
 /// Receipts command
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct Command {
-    /// The path to the receipts export
-    #[arg(
-        long,
-        value_name = "RECEIPTS",
-        verbatim_doc_comment,
-        default_value = "data/export_receipt_0_4061223"
-    )]
-    path: String,
-    /// The path to the database
-    #[arg(long, value_name = "DATABASE_PATH", verbatim_doc_comment)]
-    database: String,
+    #[clap(subcommand)]
+    command: Option<Subcommands>,
+
+    /// The path to the receipts export, or `-` to read it from stdin. Defaults to the receipts
+    /// export of the chain selected with `--chain`.
+    #[arg(long, value_name = "RECEIPTS", verbatim_doc_comment)]
+    path: Option<String>,
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    #[clap(flatten)]
+    import: ImportArgs,
 }
 
-/// Apply receipts to the given database
-pub async fn apply(db: &mut Env<WriteMap>, path: Option<&str>) -> Result<()> {
-    let _receipts = Receipt::from_file(path.unwrap_or("data/export_receipt_0_4061223"))?;
+/// Receipts subcommands
+#[derive(Debug, Subcommand)]
+pub enum Subcommands {
+    /// Print receipts of an export by transaction hash or block, as JSON
+    #[command(name = "query")]
+    Query(query::Command),
+    /// Fetch the receipts of a range of blocks from the JSON-RPC API of a legacy node and insert
+    /// them
+    #[command(name = "sync")]
+    Sync(SyncCommand),
+}
+
+/// Apply receipts to the given database, storing their L1 fee fields in the given [L1FeeStore].
+///
+/// Receipts are matched to their transactions by hash, so the blocks have to be imported first.
+/// Receipts contradicting the imported blocks are reported and skipped, see [cross_check].
+/// Receipts that can't be converted to reth receipts fail the import, or are written to the
+/// dead-letter file of a lenient import. A directory or glob pattern is read as an export split
+/// into block ranges, a geth freezer directory as the receipts of its ancient blocks.
+pub async fn apply(
+    db: &mut Env<WriteMap>,
+    fees: &L1FeeStore,
+    path: Option<&str>,
+    args: &ImportArgs,
+) -> Result<()> {
+    if let Some(dead_letter) = &args.from_dead_letter {
+        return apply_from(db, fees, &mut DeadLetterSource::open(dead_letter)?, args).await
+    }
+    let file_path = args.input_path(path, ImportStage::Receipts)?;
+    if freezer::is_freezer(Path::new(&file_path)) {
+        return apply_from(db, fees, &mut FreezerSource::new(file_path), args).await
+    }
+    if shards::is_sharded(&file_path) {
+        return apply_from(db, fees, &mut ShardedSource::new(file_path), args).await
+    }
+    apply_from(db, fees, &mut FileSource::new(file_path), args).await
+}
+
+/// Apply the receipts read from the given source to the given database. See [apply].
+pub async fn apply_from(
+    db: &mut Env<WriteMap>,
+    fees: &L1FeeStore,
+    source: &mut dyn ReceiptSource,
+    args: &ImportArgs,
+) -> Result<()> {
+    let (progress, _monitors) = args.watch()?;
+    let limiter = args.io_limiter();
+    let mut dead_letter = args.dead_letter_file()?;
+    tracing::info!(target: "reth::cli", source = %source.describe(), "Reading receipts");
+    let receipts = source.read_receipts(&mut SourceContext {
+        args,
+        progress: &progress,
+        limiter: limiter.as_ref(),
+        dead_letter: dead_letter.as_mut(),
+        validator: None,
+    })?;
     db.create_tables()?;
-    match db.update(|_tx| {
-        // TODO: apply receipts to db
-        Ok::<(), Error>(())
-    })? {
-        Ok(_) => tracing::info!(target: "reth::cli", "Receipts inserted! 🎉"),
-        Err(err) => {
-            tracing::error!(target: "reth::cli", "Error inserting receipts into MDBX: {}", err)
+
+    progress.set_stage("cross-check receipts");
+    let (accepted, issues) = db.view(|tx| cross_check(tx, &receipts))??;
+    validate::report_receipt_issues(&issues, args.strict_receipts)?;
+    let total = receipts.len();
+    let receipts: Vec<Receipt> = receipts
+        .into_iter()
+        .zip(accepted)
+        .filter_map(|(receipt, accepted)| accepted.then_some(receipt))
+        .collect();
+    if receipts.len() < total {
+        tracing::warn!(target: "reth::cli", skipped = total - receipts.len(), "Skipped receipts contradicting the imported blocks");
+    }
+
+    // The L1 fees of a batch live in another environment, so they are committed first and recorded
+    // as pending until the receipts are committed too. An import interrupted in between leaves
+    // fees without receipts, which are removed before importing again.
+    let stale =
+        db.view(|tx| fees.reconcile(|tx_id| Ok(tx.get::<tables::Receipts>(tx_id)?.is_some())))??;
+    if stale > 0 {
+        tracing::warn!(target: "reth::cli", stale, "Removed L1 fees left behind by an interrupted import");
+    }
+
+    progress.set_stage("insert receipts");
+    progress.set_total(receipts.len() as u64);
+    let batch_size = args.batch_size.max(1);
+    let retry = args.retry_policy();
+    let mut stored = 0;
+    let mut unknown = 0;
+    for (batch_index, batch) in receipts.chunks(batch_size).enumerate() {
+        let mut converted = Vec::with_capacity(batch.len());
+        let mut l1_fees = Vec::with_capacity(batch.len());
+        let tx = db.tx()?;
+        for (offset, receipt) in batch.iter().enumerate() {
+            progress.set_block(receipt.block_number.as_limbs()[0]);
+            progress.advance(1)?;
+            let hash = reth_primitives::H256::from_slice(&receipt.tx_hash.0);
+            let Some(tx_id) = tx.get::<tables::TxHashNumber>(hash)? else {
+                unknown += 1;
+                continue
+            };
+            let reth_receipt = match receipt.to_reth_receipt() {
+                Ok(reth_receipt) => reth_receipt,
+                Err(err) => {
+                    let error = ImportError::new("receipt", batch_index * batch_size + offset, err);
+                    dead_letter::reject(dead_letter.as_mut(), error, &rlp::encode(receipt))?;
+                    continue
+                }
+            };
+            converted.push((tx_id, reth_receipt, (receipt.logs.len() + 32) as u64));
+            l1_fees.push((tx_id, receipt.l1_fee_info()));
         }
+        tx.commit()?;
+
+        stored += retry.run("insert L1 fees", || fees.insert_pending(&l1_fees))?;
+        retry.run("insert receipts", || {
+            let tx = db.tx_mut()?;
+            progress.tx_opened();
+            for (tx_id, reth_receipt, size) in &converted {
+                if let Some(limiter) = &limiter {
+                    limiter.consume(*size);
+                }
+                tx.put::<tables::Receipts>(*tx_id, reth_receipt.clone())?;
+                progress.record_writes(tables::Receipts::NAME, 1, *size);
+            }
+            tx.commit()?;
+            progress.tx_closed();
+            db.inner.sync(true)?;
+            Ok(())
+        })?;
+        fees.clear_pending()?;
+    }
+    if let Some(dead_letter) = dead_letter {
+        dead_letter.finish()?;
     }
+    progress.finish();
+
+    if unknown > 0 {
+        tracing::warn!(target: "reth::cli", unknown, "Skipped receipts of unknown transactions, were the blocks imported?");
+    }
+    tracing::info!(target: "reth::cli", receipts = stored, "Receipts inserted! 🎉");
     Ok(())
 }
 
+/// Cross-checks the receipts, ordered by block, against the imported blocks: the block hash at
+/// the height of every receipt, the transaction at its index, the progression of the cumulative
+/// gas used within a block, the logs bloom of every receipt and, for blocks whose receipts are
+/// complete, the logs bloom and the receipts root of the header. Receipts without a logs bloom
+/// are not checked against it. Returns whether every receipt is consistent with the blocks, and
+/// the issues found.
+pub fn cross_check<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    receipts: &[Receipt],
+) -> Result<(Vec<bool>, Vec<ReceiptIssue>)> {
+    let mut accepted = vec![true; receipts.len()];
+    let mut issues = Vec::new();
+    let mut start = 0;
+    while start < receipts.len() {
+        let number = receipts[start].block_number;
+        let end = start +
+            receipts[start..].iter().take_while(|receipt| receipt.block_number == number).count();
+        let block = number.as_limbs()[0];
+        let (Some(hash), Some(body), Some(header)) = (
+            tx.get::<tables::CanonicalHeaders>(block)?,
+            tx.get::<tables::BlockBodies>(block)?,
+            tx.get::<tables::Headers>(block)?,
+        ) else {
+            issues.push(ReceiptIssue::MissingBlock { block });
+            accepted[start..end].fill(false);
+            start = end;
+            continue
+        };
+
+        let mut previous_gas = 0;
+        for (offset, receipt) in receipts[start..end].iter().enumerate() {
+            let block_hash = reth_primitives::H256::from_slice(&receipt.block_hash.0);
+            let tx_hash = reth_primitives::H256::from_slice(&receipt.tx_hash.0);
+            let index = receipt.transaction_index;
+            if block_hash != hash {
+                issues.push(ReceiptIssue::BlockHashMismatch {
+                    block,
+                    expected: hash,
+                    actual: block_hash,
+                });
+                accepted[start + offset] = false;
+            } else if index >= body.tx_count ||
+                tx.get::<tables::TxHashNumber>(tx_hash)? != Some(body.start_tx_id + index)
+            {
+                issues.push(ReceiptIssue::TransactionMismatch { block, index, tx_hash });
+                accepted[start + offset] = false;
+            } else if receipt.cumulative_gas_used < previous_gas {
+                issues.push(ReceiptIssue::DecreasingCumulativeGas {
+                    block,
+                    index,
+                    cumulative_gas_used: receipt.cumulative_gas_used,
+                    previous: previous_gas,
+                });
+                accepted[start + offset] = false;
+            } else if !receipt.bloom.is_empty() &&
+                receipt.computed_bloom().map_or(false, |bloom| receipt.bloom != bloom.as_bytes())
+            {
+                issues.push(ReceiptIssue::BloomMismatch { block, index });
+                accepted[start + offset] = false;
+            }
+            previous_gas = receipt.cumulative_gas_used;
+        }
+
+        // The root only covers complete blocks, receipts may be missing from partial exports
+        let complete = (end - start) as u64 == body.tx_count &&
+            receipts[start..end]
+                .iter()
+                .enumerate()
+                .all(|(index, receipt)| receipt.transaction_index == index as u64);
+        if complete && accepted[start..end].iter().all(|accepted| *accepted) {
+            let converted: Result<Vec<_>, _> =
+                receipts[start..end].iter().map(Receipt::to_reth_receipt).collect();
+            if let Ok(converted) = converted {
+                let logs_bloom = logs_bloom(converted.iter().flat_map(|receipt| &receipt.logs));
+                let computed = proofs::calculate_receipt_root(converted.iter());
+                if logs_bloom != header.logs_bloom {
+                    issues.push(ReceiptIssue::LogsBloomMismatch {
+                        block,
+                        expected: header.logs_bloom,
+                        computed: logs_bloom,
+                    });
+                    accepted[start..end].fill(false);
+                } else if computed != header.receipts_root {
+                    issues.push(ReceiptIssue::ReceiptsRootMismatch {
+                        block,
+                        expected: header.receipts_root,
+                        computed,
+                    });
+                    accepted[start..end].fill(false);
+                }
+            }
+        }
+        start = end;
+    }
+    Ok((accepted, issues))
+}
+
+/// Fetch the receipts of a range of blocks from a legacy node over JSON-RPC and insert them, for
+/// when no export can be written on the node's machine.
+///
+/// The receipts are read with `eth_getTransactionReceipt`, including the L1 fee fields of l2geth,
+/// and cross-checked against the imported blocks like the receipts of an export.
+#[derive(Debug, Parser)]
+pub struct SyncCommand {
+    #[clap(flatten)]
+    rpc: RpcSyncArgs,
+
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    #[clap(flatten)]
+    import: ImportArgs,
+}
+
+impl SyncCommand {
+    /// Execute the command
+    pub async fn execute(mut self, _ctx: CliContext) -> Result<()> {
+        self.import = self.import.with_database(&self.db)?;
+        journal::record(&self.db.path(), "receipts sync", &[], async {
+            let mut db = self.db.open_rw()?;
+            let fees = L1FeeStore::open(&self.db.static_path()?)?;
+            apply_from(&mut db, &fees, &mut self.rpc.source(), &self.import).await
+        })
+        .await
+    }
+}
+
 impl Command {
     /// Execute the command
-    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
-        let db_path = PathBuf::from(self.database);
-        let mut db = db::open_rw_env(db_path.as_path())?;
-        apply(&mut db, Some(&self.path)).await
+    pub async fn execute(mut self, ctx: CliContext) -> Result<()> {
+        match self.command {
+            Some(Subcommands::Query(command)) => return command.execute(ctx).await,
+            Some(Subcommands::Sync(command)) => return command.execute(ctx).await,
+            None => {}
+        }
+        self.import = self.import.with_database(&self.db)?;
+        let db_path = self.db.path();
+        let path = self.import.input_path(self.path.as_deref(), ImportStage::Receipts)?;
+        journal::record(&db_path, "receipts", &[Path::new(&path)], async {
+            self.import.preflight(&db_path, ImportStage::Receipts, Path::new(&path))?;
+            let mut db = self.db.open_rw()?;
+            let fees = L1FeeStore::open(&self.db.static_path()?)?;
+            apply(&mut db, &fees, Some(&path), &self.import).await
+        })
+        .await
     }
 }
 
@@ -149,29 +440,175 @@ impl rlp::Decodable for Receipt {
     }
 }
 
+/// RLP encoder for [Receipt], the inverse of its decoder
+impl rlp::Encodable for Receipt {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(16);
+        s.append(&self.ty);
+        s.append(&self.post_state);
+        s.append(&self.status);
+        s.append(&self.cumulative_gas_used);
+        s.append(&self.bloom);
+        s.append_raw(&self.logs, 1);
+        s.append(&self.tx_hash);
+        s.append(&self.contract_address);
+        s.append(&self.gas_used);
+        s.append(&self.block_hash);
+        s.append(&self.block_number);
+        s.append(&self.transaction_index);
+        s.append(&self.l1_gas_price);
+        s.append(&self.l1_gas_used);
+        s.append(&self.l1_fee);
+        s.append(&self.l1_fee_scalar);
+    }
+}
+
+/// A log entry emitted during the execution of a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptLog {
+    /// The contract that emitted the log
+    pub address: H160,
+    /// The indexed topics of the log
+    pub topics: Vec<H256>,
+    /// The non-indexed log data
+    pub data: Vec<u8>,
+}
+
+impl rlp::Decodable for ReceiptLog {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        let address = rlp.val_at(0)?;
+        let topics = rlp.list_at(1)?;
+        let data = rlp.val_at(2)?;
+        Ok(Self { address, topics, data })
+    }
+}
+
+impl rlp::Encodable for ReceiptLog {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(3);
+        s.append(&self.address);
+        s.append_list::<H256, _>(&self.topics);
+        s.append(&self.data);
+    }
+}
+
 impl Receipt {
-    fn decode_receipt_vec(rlp: &rlp::Rlp) -> Result<Vec<Receipt>, rlp::DecoderError> {
+    /// Decodes the raw rlp-encoded logs of the receipt
+    pub fn decode_logs(&self) -> Result<Vec<ReceiptLog>, rlp::DecoderError> {
+        rlp::Rlp::new(&self.logs).as_list()
+    }
+
+    /// Decodes the logs of the receipt into the reth logs stored in the `Receipts` table
+    pub fn reth_logs(&self) -> Result<Vec<Log>, rlp::DecoderError> {
+        Ok(self
+            .decode_logs()?
+            .into_iter()
+            .map(|log| Log {
+                address: reth_primitives::H160::from_slice(&log.address.0),
+                topics: log
+                    .topics
+                    .iter()
+                    .map(|topic| reth_primitives::H256::from_slice(&topic.0))
+                    .collect(),
+                data: log.data.into(),
+            })
+            .collect())
+    }
+
+    /// Recomputes the logs bloom of the receipt from its logs, to be compared to
+    /// [Receipt::bloom]
+    pub fn computed_bloom(&self) -> Result<Bloom, rlp::DecoderError> {
+        Ok(logs_bloom(self.reth_logs()?.iter()))
+    }
+
+    /// Converts the receipt into the reth receipt stored in the `Receipts` table. The L1 fee
+    /// fields are not part of it, see [Receipt::l1_fee_info].
+    pub fn to_reth_receipt(&self) -> Result<reth_primitives::Receipt, rlp::DecoderError> {
+        let tx_type = match self.ty {
+            0 => TxType::Legacy,
+            1 => TxType::EIP2930,
+            2 => TxType::EIP1559,
+            _ => return Err(rlp::DecoderError::Custom("unknown receipt type")),
+        };
+        Ok(reth_primitives::Receipt {
+            tx_type,
+            success: self.status == 1,
+            cumulative_gas_used: self.cumulative_gas_used,
+            logs: self.reth_logs()?,
+        })
+    }
+
+    /// The L1 fee fields of the receipt
+    pub fn l1_fee_info(&self) -> L1FeeInfo {
+        L1FeeInfo {
+            l1_gas_price: self.l1_gas_price,
+            l1_gas_used: self.l1_gas_used,
+            l1_fee: self.l1_fee,
+            l1_fee_scalar: self.l1_fee_scalar.clone(),
+        }
+    }
+
+    fn decode_receipt_vec(
+        rlp: &rlp::Rlp,
+        mut dead_letter: Option<&mut DeadLetterFile>,
+        progress: Option<&ImportProgress>,
+        index: &mut usize,
+    ) -> Result<Vec<Receipt>> {
         let mut receipts = Vec::new();
-        for (_, item) in rlp.iter().enumerate() {
+        for item in rlp.iter() {
+            if let Some(progress) = progress {
+                progress.advance(1)?;
+            }
             if item.is_empty() {
                 continue
             }
-            let r = if let Ok(r) = Receipt::decode(&item) {
-                r
-            } else {
-                let mut inner_vec = Receipt::decode_receipt_vec(&item)?;
-                receipts.append(&mut inner_vec);
-                continue
+            let r = match Receipt::decode(&item) {
+                Ok(r) => r,
+                Err(err)
+                    if !item.is_list() || item.at(0).map_or(true, |first| !first.is_list()) =>
+                {
+                    // Not a nested list of receipts, so the item is a malformed receipt
+                    let error = ImportError::new("receipt", *index, err);
+                    dead_letter::reject(dead_letter.as_deref_mut(), error, item.as_raw())?;
+                    *index += 1;
+                    continue
+                }
+                Err(_) => {
+                    let mut inner_vec = Receipt::decode_receipt_vec(
+                        &item,
+                        dead_letter.as_deref_mut(),
+                        progress,
+                        index,
+                    )?;
+                    receipts.append(&mut inner_vec);
+                    continue
+                }
             };
+            if let Some(progress) = progress {
+                progress.set_block(r.block_number.as_limbs()[0]);
+            }
             receipts.push(r);
+            *index += 1;
         }
         Ok(receipts)
     }
 
     /// Decodes receipts from an rlp-encoded list of receipts file
     pub fn from_file(path: impl AsRef<Path>) -> Result<Vec<Receipt>> {
-        let data = std::fs::read(&path)?;
-        let rlp_data = rlp::Rlp::new(&data[1..]);
+        Self::from_bytes_with(&compression::read(path)?, None, None)
+    }
+
+    /// Decodes receipts from the contents of an rlp-encoded list of receipts file, writing
+    /// receipts that fail to decode to the given dead-letter file and reporting the decoded items
+    /// to the given progress
+    pub fn from_bytes_with(
+        data: &[u8],
+        dead_letter: Option<&mut DeadLetterFile>,
+        progress: Option<&ImportProgress>,
+    ) -> Result<Vec<Receipt>> {
+        // The rlp list follows a leading byte
+        let Some(contents) = data.get(1..) else { eyre::bail!("The receipt export is empty") };
+        let rlp_data = rlp::Rlp::new(contents);
         if rlp_data.is_empty() {
             tracing::warn!(target: "reth::cli", "rlp data is empty!");
         }
@@ -181,7 +618,7 @@ impl Receipt {
         if rlp_data.is_list() {
             tracing::debug!(target: "reth::cli", "decoding rlp data as list");
         }
-        let receipts = Receipt::decode_receipt_vec(&rlp_data).map_err(|e| eyre::eyre!(e))?;
+        let receipts = Receipt::decode_receipt_vec(&rlp_data, dead_letter, progress, &mut 0)?;
         Ok(receipts)
     }
 }
@@ -0,0 +1,129 @@
+use std::str::FromStr;
+
+use clap::Parser;
+use eyre::Result;
+use reth::runner::CliContext;
+use reth_primitives::{Address, Bloom, H256, U256};
+
+use crate::cli::{
+    receipts::Receipt,
+    rpc::types::{RpcLog, RpcReceipt},
+};
+
+/// Print receipts of a receipts export without importing it.
+///
+/// The receipts are printed as JSON in the layout of `eth_getTransactionReceipt`, including the
+/// L1 fee fields of legacy l2geth. The sender and recipient are not part of the export and are
+/// left empty.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The path to the receipts export
+    #[arg(long, value_name = "RECEIPTS", verbatim_doc_comment)]
+    path: String,
+
+    /// Print the receipt of this transaction. Repeat to print several.
+    #[arg(
+        long = "tx-hash",
+        value_name = "HASH",
+        required_unless_present = "blocks",
+        verbatim_doc_comment
+    )]
+    tx_hashes: Vec<H256>,
+
+    /// Print the receipts of this block. Repeat to print several.
+    #[arg(long = "block", value_name = "BLOCK", verbatim_doc_comment)]
+    blocks: Vec<u64>,
+}
+
+/// Returns the receipts of the export that belong to one of the given transactions or blocks, in
+/// the order of the export.
+///
+/// The log indices count the logs of the receipts before a receipt in its block, so they are only
+/// complete if the export holds every receipt of the block.
+pub fn find_receipts(
+    receipts: &[Receipt],
+    tx_hashes: &[H256],
+    blocks: &[u64],
+) -> Result<Vec<RpcReceipt>> {
+    let mut matches = Vec::new();
+    let mut block = None;
+    let mut log_index = 0;
+    for receipt in receipts {
+        let number = receipt.block_number.as_limbs()[0];
+        if block != Some(number) {
+            block = Some(number);
+            log_index = 0;
+        }
+        let tx_hash = H256::from_slice(&receipt.tx_hash.0);
+        if tx_hashes.contains(&tx_hash) || blocks.contains(&number) {
+            matches.push(rpc_receipt(receipt, log_index).map_err(|err| {
+                eyre::eyre!("The logs of the receipt of {tx_hash:?} can not be decoded: {err}")
+            })?);
+        }
+        log_index += receipt.decode_logs().map_or(0, |logs| logs.len() as u64);
+    }
+    Ok(matches)
+}
+
+/// Converts a receipt of the export into its JSON-RPC representation, numbering its logs from
+/// `log_index`
+fn rpc_receipt(receipt: &Receipt, log_index: u64) -> Result<RpcReceipt, rlp::DecoderError> {
+    let block_hash = H256::from_slice(&receipt.block_hash.0);
+    let block_number = U256::from(receipt.block_number.as_limbs()[0]);
+    let transaction_hash = H256::from_slice(&receipt.tx_hash.0);
+    let transaction_index = U256::from(receipt.transaction_index);
+    let logs = receipt.reth_logs()?;
+    // Exports without a bloom of the receipt get the bloom of its logs
+    let logs_bloom = match receipt.bloom.len() {
+        256 => Bloom::from_slice(&receipt.bloom),
+        _ => receipt.computed_bloom()?,
+    };
+    let contract_address =
+        Address::from_str(&receipt.contract_address).ok().filter(|address| !address.is_zero());
+    Ok(RpcReceipt {
+        transaction_hash,
+        transaction_index,
+        block_hash,
+        block_number,
+        from: None,
+        to: None,
+        cumulative_gas_used: U256::from(receipt.cumulative_gas_used),
+        gas_used: U256::from(receipt.gas_used),
+        contract_address,
+        logs: logs
+            .into_iter()
+            .enumerate()
+            .map(|(index, log)| RpcLog {
+                address: log.address,
+                topics: log.topics,
+                data: log.data,
+                block_hash,
+                block_number,
+                transaction_hash,
+                transaction_index,
+                log_index: U256::from(log_index + index as u64),
+                removed: false,
+            })
+            .collect(),
+        logs_bloom,
+        status: U256::from(receipt.status),
+        tx_type: U256::from(receipt.ty),
+        l1_fee: Some(receipt.l1_fee_info()),
+    })
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let receipts = Receipt::from_file(&self.path)?;
+        let matches = find_receipts(&receipts, &self.tx_hashes, &self.blocks)?;
+        if matches.is_empty() {
+            eyre::bail!(
+                "No matching receipts found among the {} receipts of the export",
+                receipts.len()
+            )
+        }
+        println!("{}", serde_json::to_string_pretty(&matches)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use eyre::Result;
+use reth_primitives::H256;
+use serde::{Deserialize, Serialize};
+
+/// The file below a database path holding the boundaries of the imported legacy segments
+pub const BOUNDARIES_FILE: &str = "regenesis-boundaries.json";
+
+/// The start of a legacy segment imported after a regenesis.
+///
+/// A regenesis restarts the chain from a new genesis anchor that carries the state of the previous
+/// chain but doesn't link to its last block, so the hash chain of the database is broken at every
+/// boundary on purpose.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegenesisBoundary {
+    /// The number of the segment, counting the segment of the original genesis as 0
+    pub segment: u64,
+    /// The number of the genesis anchor, the first block of the segment
+    pub first_block: u64,
+    /// The hash of the genesis anchor
+    pub anchor_hash: H256,
+    /// The parent hash the genesis anchor carries
+    pub anchor_parent_hash: H256,
+    /// The state root of the genesis anchor
+    pub anchor_state_root: H256,
+    /// The hash of the last block of the previous segment
+    pub previous_hash: H256,
+}
+
+/// The boundaries of the legacy segments imported into a database, stored next to it in ascending
+/// order of their first block
+#[derive(Debug, Clone)]
+pub struct RegenesisBoundaries {
+    path: PathBuf,
+}
+
+impl RegenesisBoundaries {
+    /// The boundaries of the database at the given path
+    pub fn new(db_path: &Path) -> Self {
+        Self { path: db_path.join(BOUNDARIES_FILE) }
+    }
+
+    /// Reads all boundaries, empty if no segment was imported after the original genesis
+    pub fn read(&self) -> Result<Vec<RegenesisBoundary>> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// Records a boundary following all recorded ones
+    pub fn append(&self, boundary: RegenesisBoundary) -> Result<()> {
+        let mut boundaries = self.read()?;
+        if let Some(last) = boundaries.last() {
+            if boundary.first_block <= last.first_block {
+                eyre::bail!(
+                    "Segment {} starts at block {}, before segment {} at block {}",
+                    boundary.segment,
+                    boundary.first_block,
+                    last.segment,
+                    last.first_block
+                );
+            }
+        }
+        boundaries.push(boundary);
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_vec_pretty(&boundaries)?)?;
+        Ok(())
+    }
+
+    /// The number of the next segment to import
+    pub fn next_segment(&self) -> Result<u64> {
+        Ok(self.read()?.last().map_or(1, |last| last.segment + 1))
+    }
+}
+
+/// Returns the segment the block with the given number belongs to
+pub fn segment_of(boundaries: &[RegenesisBoundary], number: u64) -> u64 {
+    boundaries
+        .iter()
+        .rev()
+        .find(|boundary| boundary.first_block <= number)
+        .map_or(0, |boundary| boundary.segment)
+}
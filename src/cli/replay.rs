@@ -0,0 +1,572 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Range,
+    path::Path,
+};
+
+use clap::Parser;
+use eyre::Result;
+use reth::runner::CliContext;
+use reth_db::{
+    cursor::{DbCursorRO, DbDupCursorRO},
+    database::Database,
+    mdbx::{Env, WriteMap},
+    models::{storage_sharded_key::StorageShardedKey, ShardedKey, TransitionIdAddress},
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_executor::executor::execute_and_verify_receipt;
+use reth_primitives::{Account, Address, Block, ChainSpec, IntegerList, H256, U256};
+use reth_provider::{LatestStateProviderRef, Transaction};
+use reth_revm::database::{State, SubState};
+use reth_stages::StageId;
+
+use crate::cli::{args::DatabaseArgs, chain::OpChainSpec, journal};
+
+/// The stage whose checkpoint records the last replayed block
+pub const EXECUTION: StageId = StageId("Execution");
+
+/// The stages whose checkpoints record the last block covered by the history indices
+pub const HISTORY_STAGES: [StageId; 2] =
+    [StageId("IndexAccountHistory"), StageId("IndexStorageHistory")];
+
+/// The checkpoint of the merkle stage, set to the tip when the state is imported
+const MERKLE: StageId = StageId("MerkleExecute");
+
+/// The maximum number of transitions in a shard of a history index, as used by reth
+pub const HISTORY_SHARD_LEN: usize = 100;
+
+/// The default number of blocks executed per database transaction
+pub const DEFAULT_REPLAY_BATCH: u64 = 1_000;
+
+/// Re-execute the imported blocks on top of the genesis state.
+///
+/// Fills the plain state, the account and storage changesets and the account and storage history
+/// indices, so historical state queries work on the migrated chain. The replay continues from
+/// the last replayed block.
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The last block to replay. Defaults to the canonical tip.
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment)]
+    to: Option<u64>,
+
+    /// The number of blocks executed per database transaction
+    #[arg(
+        long,
+        value_name = "BLOCKS",
+        verbatim_doc_comment,
+        default_value_t = DEFAULT_REPLAY_BATCH
+    )]
+    batch_size: u64,
+
+    /// Only write the changesets, and leave the history indices to `index-history`
+    #[arg(long, verbatim_doc_comment)]
+    skip_history: bool,
+}
+
+/// Build the account and storage history indices from the changesets.
+///
+/// Indexes the changesets written by `replay` that the indices do not cover yet, so that
+/// historical state queries work. Continues from the last indexed block.
+#[derive(Debug, Parser)]
+pub struct IndexHistoryCommand {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// Drop the history indices and index all changesets again
+    #[arg(long, verbatim_doc_comment)]
+    rebuild: bool,
+
+    /// The number of blocks indexed per database transaction
+    #[arg(
+        long,
+        value_name = "BLOCKS",
+        verbatim_doc_comment,
+        default_value_t = DEFAULT_REPLAY_BATCH
+    )]
+    batch_size: u64,
+}
+
+/// The outcome of a replay or of indexing the history
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplaySummary {
+    /// The number of replayed or indexed blocks
+    pub blocks: u64,
+    /// The number of written or indexed account changes
+    pub account_changes: u64,
+    /// The number of written or indexed storage changes
+    pub storage_changes: u64,
+}
+
+/// Returns the last replayed block, 0 if only the genesis state is present
+pub fn replayed_block<'a, TX: DbTx<'a>>(tx: &TX) -> Result<u64> {
+    Ok(EXECUTION.get_progress(tx)?.unwrap_or_default())
+}
+
+/// Returns the last block whose changesets are in the history indices, 0 if none are
+pub fn indexed_block<'a, TX: DbTx<'a>>(tx: &TX) -> Result<u64> {
+    let mut indexed = u64::MAX;
+    for stage in HISTORY_STAGES {
+        indexed = indexed.min(stage.get_progress(tx)?.unwrap_or_default());
+    }
+    Ok(indexed)
+}
+
+/// Re-executes the canonical blocks following the last replayed block up to `to`, `batch_size`
+/// blocks per database transaction, and indexes the changesets they produce unless `index` is
+/// false or the indices lag behind the replayed blocks, which [build_history] catches up on.
+///
+/// The plain state has to be the state after the last replayed block, so the replay refuses to
+/// run on top of an imported state dump. The hashed state and the trie are not updated, run
+/// `state hash-and-trie` afterwards.
+pub fn replay(
+    db: &Env<WriteMap>,
+    chain: &ChainSpec,
+    to: u64,
+    batch_size: u64,
+    index: bool,
+) -> Result<ReplaySummary> {
+    let (replayed, indexed, merkle) = db.view(|tx| -> Result<(u64, u64, Option<u64>)> {
+        Ok((replayed_block(tx)?, indexed_block(tx)?, MERKLE.get_progress(tx)?))
+    })??;
+    if let Some(imported) = merkle.filter(|imported| *imported > replayed) {
+        eyre::bail!(
+            "The database holds the state imported at block {imported}, but the replay needs the \
+             state after block {replayed}. Replay into a database holding only the genesis state."
+        );
+    }
+
+    // Indices have to be appended to in order, so a gap is left for `index-history` to fill
+    let index = index && indexed == replayed;
+
+    let mut summary = ReplaySummary::default();
+    let mut from = replayed + 1;
+    while from <= to {
+        let last = to.min(from + batch_size.max(1) - 1);
+        let mut tx = Transaction::new(db)?;
+        let start = transition_after(&*tx, from - 1)?;
+
+        let mut results = Vec::with_capacity((last - from + 1) as usize);
+        {
+            let mut state = SubState::new(State::new(LatestStateProviderRef::new(&*tx)));
+            for number in from..=last {
+                let (block, td, senders) = load_block(&*tx, number)?;
+                let result =
+                    execute_and_verify_receipt(&block, td, Some(senders), chain, &mut state)
+                        .map_err(|err| eyre::eyre!("Block {number} failed to replay: {err}"))?;
+                results.push(result);
+            }
+        }
+        tx.insert_execution_result(results, chain, from - 1)?;
+
+        let (accounts, storages) = if index {
+            let end = transition_after(&*tx, last)?;
+            let changes = index_history(&*tx, start..end)?;
+            for stage in HISTORY_STAGES {
+                stage.save_progress(&*tx, last)?;
+            }
+            changes
+        } else {
+            (0, 0)
+        };
+        EXECUTION.save_progress(&*tx, last)?;
+        tx.commit()?;
+
+        summary.blocks += last - from + 1;
+        summary.account_changes += accounts;
+        summary.storage_changes += storages;
+        tracing::info!(target: "reth::cli", from, to = last, accounts, storages, "Replayed blocks");
+        from = last + 1;
+    }
+    Ok(summary)
+}
+
+/// Re-executes the imported blocks following the last replayed block up to `to`, or the canonical
+/// tip, with the chain spec recorded at `db_path`. See [replay].
+pub fn replay_imported(
+    db: &Env<WriteMap>,
+    db_path: &Path,
+    to: Option<u64>,
+    batch_size: u64,
+    index: bool,
+) -> Result<ReplaySummary> {
+    let Some(chain) = OpChainSpec::read(db_path)? else {
+        eyre::bail!("No chain spec found at {}, import the genesis first", db_path.display())
+    };
+    let tip = db.view(|tx| tx.cursor_read::<tables::CanonicalHeaders>()?.last())??;
+    let tip = tip.map_or(0, |(number, _)| number);
+    replay(db, &chain.inner, to.unwrap_or(tip).min(tip), batch_size, index)
+}
+
+/// Loads the canonical block with the given number, its total difficulty and the senders of its
+/// transactions. Transactions without a signature were imported as system transactions whose
+/// sender was recorded with them.
+pub fn load_block<'a, TX: DbTx<'a>>(tx: &TX, number: u64) -> Result<(Block, U256, Vec<Address>)> {
+    let header = tx
+        .get::<tables::Headers>(number)?
+        .ok_or_else(|| eyre::eyre!("Header of block {number} not found"))?;
+    let td = tx
+        .get::<tables::HeaderTD>(number)?
+        .ok_or_else(|| eyre::eyre!("Total difficulty of block {number} not found"))?;
+    let mut body = Vec::new();
+    let mut senders = Vec::new();
+    if let Some(indices) = tx.get::<tables::BlockBodies>(number)? {
+        for tx_id in indices.start_tx_id..indices.start_tx_id + indices.tx_count {
+            let transaction = tx
+                .get::<tables::Transactions>(tx_id)?
+                .ok_or_else(|| eyre::eyre!("Transaction {tx_id} of block {number} not found"))?;
+            let sender = match tx.get::<tables::TxSenders>(tx_id)? {
+                Some(sender) => sender,
+                None => transaction.recover_signer().ok_or_else(|| {
+                    eyre::eyre!("Unable to recover the sender of transaction {tx_id}")
+                })?,
+            };
+            body.push(transaction);
+            senders.push(sender);
+        }
+    }
+    let ommers = tx.get::<tables::BlockOmmers>(number)?.map(|ommers| ommers.ommers);
+    let block = Block { header, body, ommers: ommers.unwrap_or_default(), withdrawals: None };
+    Ok((block, td.into(), senders))
+}
+
+/// Returns the state transition following the given block
+pub fn transition_after<'a, TX: DbTx<'a>>(tx: &TX, number: u64) -> Result<u64> {
+    Ok(tx.get::<tables::BlockTransitionIndex>(number)?.unwrap_or_default())
+}
+
+/// Adds the transitions of the account and storage changesets in the given range to the account
+/// and storage history indices. Returns the number of indexed account and storage changes.
+pub fn index_history<'a, TX: DbTxMut<'a> + DbTx<'a>>(
+    tx: &TX,
+    transitions: Range<u64>,
+) -> Result<(u64, u64)> {
+    let mut accounts: BTreeMap<Address, Vec<u64>> = BTreeMap::new();
+    let mut account_changes = 0;
+    let mut cursor = tx.cursor_read::<tables::AccountChangeSet>()?;
+    let mut entry = cursor.seek(transitions.start)?;
+    while let Some((transition, change)) =
+        entry.filter(|(transition, _)| *transition < transitions.end)
+    {
+        accounts.entry(change.address).or_default().push(transition);
+        account_changes += 1;
+        entry = cursor.next()?;
+    }
+
+    let mut storages: BTreeMap<(Address, H256), Vec<u64>> = BTreeMap::new();
+    let mut storage_changes = 0;
+    let mut cursor = tx.cursor_read::<tables::StorageChangeSet>()?;
+    let mut entry = cursor.seek(TransitionIdAddress((transitions.start, Address::zero())))?;
+    while let Some((TransitionIdAddress((transition, address)), change)) =
+        entry.filter(|(key, _)| key.0 .0 < transitions.end)
+    {
+        storages.entry((address, change.key)).or_default().push(transition);
+        storage_changes += 1;
+        entry = cursor.next()?;
+    }
+
+    for (address, indices) in accounts {
+        let last = ShardedKey::new(address, u64::MAX);
+        let mut list =
+            tx.get::<tables::AccountHistory>(last.clone())?.map(to_vec).unwrap_or_default();
+        tx.delete::<tables::AccountHistory>(last, None)?;
+        list.extend(indices);
+        for (highest, shard) in history_shards(list) {
+            tx.put::<tables::AccountHistory>(ShardedKey::new(address, highest), to_list(shard)?)?;
+        }
+    }
+    for ((address, key), indices) in storages {
+        let last = StorageShardedKey::new(address, key, u64::MAX);
+        let mut list =
+            tx.get::<tables::StorageHistory>(last.clone())?.map(to_vec).unwrap_or_default();
+        tx.delete::<tables::StorageHistory>(last, None)?;
+        list.extend(indices);
+        for (highest, shard) in history_shards(list) {
+            let sharded_key = StorageShardedKey::new(address, key, highest);
+            tx.put::<tables::StorageHistory>(sharded_key, to_list(shard)?)?;
+        }
+    }
+    Ok((account_changes, storage_changes))
+}
+
+/// Removes the transitions from `from` on from the account and storage history indices, undoing
+/// [index_history] for the changesets of unwound blocks, which have to be present still. Returns
+/// the number of trimmed account and storage indices.
+pub fn unindex_history<'a, TX: DbTxMut<'a> + DbTx<'a>>(tx: &TX, from: u64) -> Result<(u64, u64)> {
+    let mut accounts = BTreeSet::new();
+    let mut cursor = tx.cursor_read::<tables::AccountChangeSet>()?;
+    let mut entry = cursor.seek(from)?;
+    while let Some((_, change)) = entry {
+        accounts.insert(change.address);
+        entry = cursor.next()?;
+    }
+    let mut storages = BTreeSet::new();
+    let mut cursor = tx.cursor_read::<tables::StorageChangeSet>()?;
+    let mut entry = cursor.seek(TransitionIdAddress((from, Address::zero())))?;
+    while let Some((TransitionIdAddress((_, address)), change)) = entry {
+        storages.insert((address, change.key));
+        entry = cursor.next()?;
+    }
+
+    // Only the shards holding transitions from `from` on are rewritten, earlier shards are full
+    let mut shards = tx.cursor_read::<tables::AccountHistory>()?;
+    for address in &accounts {
+        let (mut kept, mut keys) = (Vec::new(), Vec::new());
+        let mut entry = shards.seek(ShardedKey::new(*address, from))?;
+        while let Some((key, list)) = entry.filter(|(key, _)| key.key == *address) {
+            kept.extend(to_vec(list).into_iter().filter(|transition| *transition < from));
+            keys.push(key);
+            entry = shards.next()?;
+        }
+        for key in keys {
+            tx.delete::<tables::AccountHistory>(key, None)?;
+        }
+        for (highest, shard) in history_shards(kept) {
+            tx.put::<tables::AccountHistory>(ShardedKey::new(*address, highest), to_list(shard)?)?;
+        }
+    }
+    let mut shards = tx.cursor_read::<tables::StorageHistory>()?;
+    for (address, key) in &storages {
+        let (mut kept, mut keys) = (Vec::new(), Vec::new());
+        let mut entry = shards.seek(StorageShardedKey::new(*address, *key, from))?;
+        while let Some((sharded, list)) = entry
+            .filter(|(sharded, _)| sharded.address == *address && sharded.sharded_key.key == *key)
+        {
+            kept.extend(to_vec(list).into_iter().filter(|transition| *transition < from));
+            keys.push(sharded);
+            entry = shards.next()?;
+        }
+        for sharded in keys {
+            tx.delete::<tables::StorageHistory>(sharded, None)?;
+        }
+        for (highest, shard) in history_shards(kept) {
+            let sharded_key = StorageShardedKey::new(*address, *key, highest);
+            tx.put::<tables::StorageHistory>(sharded_key, to_list(shard)?)?;
+        }
+    }
+    Ok((accounts.len() as u64, storages.len() as u64))
+}
+
+/// Indexes the changesets of the replayed blocks the history indices do not cover yet,
+/// `batch_size` blocks per database transaction. With `rebuild` the indices are dropped and all
+/// changesets are indexed again.
+pub fn build_history(db: &Env<WriteMap>, batch_size: u64, rebuild: bool) -> Result<ReplaySummary> {
+    if rebuild {
+        let tx = db.tx_mut()?;
+        tx.clear::<tables::AccountHistory>()?;
+        tx.clear::<tables::StorageHistory>()?;
+        for stage in HISTORY_STAGES {
+            stage.save_progress(&tx, 0)?;
+        }
+        tx.commit()?;
+    }
+
+    let (replayed, indexed) =
+        db.view(|tx| -> Result<(u64, u64)> { Ok((replayed_block(tx)?, indexed_block(tx)?)) })??;
+    if replayed == 0 {
+        eyre::bail!("The database holds no changesets, run replay to derive them from the blocks")
+    }
+
+    let mut summary = ReplaySummary::default();
+    let mut from = indexed + 1;
+    while from <= replayed {
+        let last = replayed.min(from + batch_size.max(1) - 1);
+        let tx = db.tx_mut()?;
+        let transitions = transition_after(&tx, from - 1)?..transition_after(&tx, last)?;
+        let (accounts, storages) = index_history(&tx, transitions)?;
+        for stage in HISTORY_STAGES {
+            stage.save_progress(&tx, last)?;
+        }
+        tx.commit()?;
+
+        summary.blocks += last - from + 1;
+        summary.account_changes += accounts;
+        summary.storage_changes += storages;
+        tracing::info!(target: "reth::cli", from, to = last, accounts, storages, "Indexed history");
+        from = last + 1;
+    }
+    Ok(summary)
+}
+
+/// Returns the block the hashed state and the trie are up to date with, `None` if they were never
+/// built
+pub fn trie_block<'a, TX: DbTx<'a>>(tx: &TX) -> Result<Option<u64>> {
+    Ok(MERKLE.get_progress(tx)?)
+}
+
+/// Returns the block the plain state is the state after: the last replayed block, or the block of
+/// an imported state dump
+pub fn state_block<'a, TX: DbTx<'a>>(tx: &TX) -> Result<u64> {
+    Ok(MERKLE.get_progress(tx)?.unwrap_or_default().max(replayed_block(tx)?))
+}
+
+/// Returns the first state transition after `block` if the state after `block` has to be
+/// reconstructed from the changesets, `None` if the plain state is the state after `block`.
+///
+/// The changesets only cover the replayed blocks, so blocks before an imported state dump and
+/// blocks after the state of the database can not be queried. Neither can any earlier block while
+/// the history indices lag behind the replayed blocks, since changes they miss would go unseen.
+pub fn historical_transition<'a, TX: DbTx<'a>>(tx: &TX, block: u64) -> Result<Option<u64>> {
+    let (replayed, state) = (replayed_block(tx)?, state_block(tx)?);
+    if block == state {
+        return Ok(None)
+    }
+    if block > state {
+        eyre::bail!(
+            "The state of the database is the state after block {state}, {block} lies above it"
+        )
+    }
+    if replayed < state {
+        eyre::bail!(
+            "The state was imported at block {state} and has no history, replay the blocks into a \
+             database holding only the genesis state to query earlier blocks"
+        );
+    }
+    let indexed = indexed_block(tx)?;
+    if indexed < replayed {
+        eyre::bail!(
+            "The history indices cover block {indexed} of the {replayed} replayed blocks, run \
+             index-history to query earlier blocks"
+        );
+    }
+    Ok(Some(transition_after(tx, block)?))
+}
+
+/// Returns the account at the given address after the given block, reconstructed from the
+/// account changesets for blocks before the state of the database
+pub fn account_at<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    address: Address,
+    block: u64,
+) -> Result<Option<Account>> {
+    let Some(transition) = historical_transition(tx, block)? else {
+        return Ok(tx.get::<tables::PlainAccountState>(address)?)
+    };
+    let mut history = tx.cursor_read::<tables::AccountHistory>()?;
+    let change = history
+        .seek(ShardedKey::new(address, transition))?
+        .filter(|(key, _)| key.key == address)
+        .and_then(|(_, list)| first_at(&list, transition));
+    let Some(change) = change else { return Ok(tx.get::<tables::PlainAccountState>(address)?) };
+
+    // The changeset holds the account before the change, which is the account after the block
+    let before = tx
+        .cursor_dup_read::<tables::AccountChangeSet>()?
+        .seek_by_key_subkey(change, address)?
+        .filter(|before| before.address == address)
+        .ok_or_else(|| eyre::eyre!("Change of {address:?} at transition {change} not found"))?;
+    Ok(before.info)
+}
+
+/// Returns the value of the storage slot of the given address after the given block,
+/// reconstructed from the storage changesets for blocks before the state of the database
+pub fn storage_at<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    address: Address,
+    key: H256,
+    block: u64,
+) -> Result<U256> {
+    let plain = |tx: &TX| -> Result<U256> {
+        Ok(tx
+            .cursor_dup_read::<tables::PlainStorageState>()?
+            .seek_by_key_subkey(address, key)?
+            .filter(|entry| entry.key == key)
+            .map(|entry| entry.value)
+            .unwrap_or_default())
+    };
+    let Some(transition) = historical_transition(tx, block)? else { return plain(tx) };
+    let mut history = tx.cursor_read::<tables::StorageHistory>()?;
+    let change = history
+        .seek(StorageShardedKey::new(address, key, transition))?
+        .filter(|(sharded, _)| sharded.address == address && sharded.sharded_key.key == key)
+        .and_then(|(_, list)| first_at(&list, transition));
+    let Some(change) = change else { return plain(tx) };
+
+    let before = tx
+        .cursor_dup_read::<tables::StorageChangeSet>()?
+        .seek_by_key_subkey(TransitionIdAddress((change, address)), key)?
+        .filter(|before| before.key == key)
+        .ok_or_else(|| eyre::eyre!("Change of slot {key:?} at transition {change} not found"))?;
+    Ok(before.value)
+}
+
+/// The addresses of the accounts of the plain state and of the accounts the history indices
+/// record changes of, which covers every account that existed after an earlier block
+pub fn historical_accounts<'a, TX: DbTx<'a>>(tx: &TX) -> Result<BTreeSet<Address>> {
+    let mut addresses = BTreeSet::new();
+    let mut cursor = tx.cursor_read::<tables::PlainAccountState>()?;
+    let mut entry = cursor.first()?;
+    while let Some((address, _)) = entry {
+        addresses.insert(address);
+        entry = cursor.next()?;
+    }
+    let mut cursor = tx.cursor_read::<tables::AccountHistory>()?;
+    let mut entry = cursor.first()?;
+    while let Some((key, _)) = entry {
+        addresses.insert(key.key);
+        entry = cursor.next()?;
+    }
+    Ok(addresses)
+}
+
+/// Returns the first transition of the history index at or after `transition`
+fn first_at(list: &IntegerList, transition: u64) -> Option<u64> {
+    list.iter(0).map(|change| change as u64).find(|change| *change >= transition)
+}
+
+/// Splits the ascending transitions of a history index into shards of at most
+/// [HISTORY_SHARD_LEN] transitions, keyed by their highest transition. The last shard is keyed
+/// by `u64::MAX` so that later transitions are appended to it.
+pub fn history_shards(transitions: Vec<u64>) -> Vec<(u64, Vec<u64>)> {
+    let count = (transitions.len() + HISTORY_SHARD_LEN - 1) / HISTORY_SHARD_LEN;
+    transitions
+        .chunks(HISTORY_SHARD_LEN)
+        .enumerate()
+        .map(|(index, shard)| {
+            let highest = if index + 1 == count { u64::MAX } else { shard[shard.len() - 1] };
+            (highest, shard.to_vec())
+        })
+        .collect()
+}
+
+fn to_vec(list: IntegerList) -> Vec<u64> {
+    list.iter(0).map(|transition| transition as u64).collect()
+}
+
+fn to_list(transitions: Vec<u64>) -> Result<IntegerList> {
+    IntegerList::new(transitions).map_err(|err| eyre::eyre!("Invalid history index: {err:?}"))
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db_path = self.db.path();
+        journal::record(&db_path, "replay", &[], async {
+            let db = self.db.open_rw()?;
+            let index = !self.skip_history;
+            let summary = replay_imported(&db, &db_path, self.to, self.batch_size, index)?;
+            tracing::info!(target: "reth::cli", blocks = summary.blocks, account_changes = summary.account_changes, storage_changes = summary.storage_changes, "Replay finished, run state hash-and-trie to update the state root");
+            Ok(())
+        })
+        .await
+    }
+}
+
+impl IndexHistoryCommand {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db_path = self.db.path();
+        journal::record(&db_path, "index-history", &[], async {
+            let db = self.db.open_rw()?;
+            let summary = build_history(&db, self.batch_size, self.rebuild)?;
+            println!("Blocks:            {}", summary.blocks);
+            println!("Account changes:   {}", summary.account_changes);
+            println!("Storage changes:   {}", summary.storage_changes);
+            Ok(())
+        })
+        .await
+    }
+}
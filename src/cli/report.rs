@@ -0,0 +1,132 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use eyre::Result;
+use once_cell::sync::{Lazy, OnceCell};
+use reth_primitives::H256;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{journal::JournalInput, progress::TableWrites};
+
+/// The directory below a database path holding the reports of the commands that wrote or
+/// verified it
+pub const REPORTS_DIR: &str = "import-reports";
+
+/// The stages, writes and rejected records of the running command
+static COLLECTED: Lazy<Mutex<CollectedReport>> = Lazy::new(Default::default);
+
+/// The file the report is written to instead of the reports directory, set with `--report-file`
+static OUTPUT: OnceCell<PathBuf> = OnceCell::new();
+
+/// A stage of an import, like decoding or inserting the blocks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StageReport {
+    /// The name of the stage, as shown by the progress bar
+    pub name: String,
+    /// The number of items the stage processed
+    pub items: u64,
+    /// How long the stage ran, in milliseconds
+    pub duration_ms: u64,
+}
+
+/// What the importers of the running command reported while it ran
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CollectedReport {
+    /// The completed stages, in the order they ran
+    pub stages: Vec<StageReport>,
+    /// The records written to each table
+    pub writes: BTreeMap<String, TableWrites>,
+    /// The number of records that failed to convert and were written to a dead-letter file
+    pub rejected: u64,
+}
+
+/// The machine-readable summary of a command that wrote or verified a database, written when
+/// the command ends so that migrations can be audited and diffed across runs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    /// The name of the command, e.g. `blocks import`
+    pub command: String,
+    /// The full command line of the invocation
+    pub args: Vec<String>,
+    /// When the command started, in seconds since the unix epoch
+    pub started_at: u64,
+    /// How long the command ran, in milliseconds
+    pub duration_ms: u64,
+    /// The inputs the command read
+    pub inputs: Vec<JournalInput>,
+    /// The stages of the imports the command ran
+    pub stages: Vec<StageReport>,
+    /// The records written to each table
+    pub writes: BTreeMap<String, TableWrites>,
+    /// The number of records written to a dead-letter file instead of the database
+    pub rejected: u64,
+    /// The error the command failed with, if any
+    pub error: Option<String>,
+    /// The highest canonical block after the command succeeded
+    pub tip: Option<u64>,
+    /// The hash of the highest canonical block
+    pub tip_hash: Option<H256>,
+    /// The state root of the highest canonical block
+    pub state_root: Option<H256>,
+}
+
+impl ImportReport {
+    /// Writes the report as pretty-printed JSON to the file given with `--report-file`, or to
+    /// the reports directory of the database at `db_path`. Returns the path of the written file.
+    pub fn write(&self, db_path: &Path) -> Result<PathBuf> {
+        let path = match OUTPUT.get() {
+            Some(path) => path.clone(),
+            None => {
+                let name = format!("{}-{}.json", self.started_at, self.command.replace(' ', "-"));
+                db_path.join(REPORTS_DIR).join(name)
+            }
+        };
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_vec_pretty(self)?)?;
+        Ok(path)
+    }
+}
+
+/// Writes the reports of this process to the given file instead of the reports directory
+pub fn set_output(path: PathBuf) {
+    let _ = OUTPUT.set(path);
+}
+
+/// Discards what was collected so far, when a command starts
+pub fn reset() {
+    *COLLECTED.lock().expect("poisoned") = CollectedReport::default();
+}
+
+/// Takes what was collected since the last [reset]
+pub fn take() -> CollectedReport {
+    std::mem::take(&mut *COLLECTED.lock().expect("poisoned"))
+}
+
+/// Records a completed stage of an import
+pub fn record_stage(name: &str, items: u64, duration: Duration) {
+    let stage =
+        StageReport { name: name.to_string(), items, duration_ms: duration.as_millis() as u64 };
+    COLLECTED.lock().expect("poisoned").stages.push(stage);
+}
+
+/// Records the writes of an import to the given table
+pub fn record_writes(table: &str, writes: TableWrites) {
+    let mut collected = COLLECTED.lock().expect("poisoned");
+    let total = collected.writes.entry(table.to_string()).or_default();
+    total.inserts += writes.inserts;
+    total.bytes += writes.bytes;
+}
+
+/// Records that `count` records were written to a dead-letter file
+pub fn record_rejected(count: u64) {
+    COLLECTED.lock().expect("poisoned").rejected += count;
+}
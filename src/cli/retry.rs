@@ -0,0 +1,113 @@
+use std::{future::Future, io, thread, time::Duration};
+
+use eyre::Result;
+
+/// The longest pause between two attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// MDBX could not grow the memory map to fit a write
+const MDBX_MAP_FULL: i32 = -30792;
+
+/// MDBX could not extend the memory map, e.g. because another process holds it
+const MDBX_UNABLE_EXTEND_MAPSIZE: i32 = -30785;
+
+/// How often and how patiently operations that may fail transiently are retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The number of retries after the first attempt
+    pub retries: u32,
+    /// The pause before the first retry, doubled for every further one
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { retries: 3, backoff: Duration::from_millis(500) }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy failing on the first error
+    pub fn none() -> Self {
+        Self { retries: 0, backoff: Duration::ZERO }
+    }
+
+    /// The pause before the given retry, counted from zero
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.backoff.saturating_mul(2u32.saturating_pow(retry)).min(MAX_BACKOFF)
+    }
+
+    /// Runs `operation` until it succeeds, fails with an error that is not transient or runs out
+    /// of retries
+    pub fn run<T>(&self, name: &str, mut operation: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut retry = 0;
+        loop {
+            match operation() {
+                Ok(value) => return Ok(value),
+                Err(err) if retry < self.retries && is_transient(&err) => {
+                    let backoff = self.backoff(retry);
+                    retry += 1;
+                    tracing::warn!(target: "reth::cli", operation = name, retry, retries = self.retries, ?backoff, %err, "Retrying after transient failure");
+                    thread::sleep(backoff);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Like [RetryPolicy::run], but for asynchronous operations, pausing without blocking the
+    /// thread so that concurrent operations keep going
+    pub async fn run_async<T, F>(&self, name: &str, mut operation: impl FnMut() -> F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let mut retry = 0;
+        loop {
+            match operation().await {
+                Ok(value) => return Ok(value),
+                Err(err) if retry < self.retries && is_transient(&err) => {
+                    let backoff = self.backoff(retry);
+                    retry += 1;
+                    tracing::warn!(target: "reth::cli", operation = name, retry, retries = self.retries, ?backoff, %err, "Retrying after transient failure");
+                    tokio::time::sleep(backoff).await;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+/// Whether the error may go away when the operation is repeated, like interrupted or timed out
+/// I/O and HTTP requests, an overloaded server and a memory map that could not grow in time
+pub fn is_transient(err: &eyre::Report) -> bool {
+    err.chain().any(|cause| {
+        if let Some(err) = cause.downcast_ref::<reqwest::Error>() {
+            return err.is_timeout() ||
+                err.is_connect() ||
+                err.status().map_or(false, |status| {
+                    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                })
+        }
+        if let Some(err) = cause.downcast_ref::<io::Error>() {
+            return matches!(
+                err.kind(),
+                io::ErrorKind::Interrupted |
+                    io::ErrorKind::TimedOut |
+                    io::ErrorKind::WouldBlock |
+                    io::ErrorKind::ConnectionReset |
+                    io::ErrorKind::ConnectionAborted |
+                    io::ErrorKind::BrokenPipe
+            )
+        }
+        let db_err = match cause.downcast_ref::<reth_interfaces::Error>() {
+            Some(reth_interfaces::Error::Database(err)) => Some(err),
+            _ => cause.downcast_ref::<reth_interfaces::db::Error>(),
+        };
+        matches!(
+            db_err,
+            Some(
+                reth_interfaces::db::Error::Write(code) | reth_interfaces::db::Error::Commit(code)
+            ) if *code == MDBX_MAP_FULL || *code == MDBX_UNABLE_EXTEND_MAPSIZE
+        )
+    })
+}
@@ -0,0 +1,626 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
+
+use bytes::BytesMut;
+use clap::Parser;
+use eyre::Result;
+use jsonrpsee::{
+    core::RpcResult,
+    proc_macros::rpc,
+    server::{ServerBuilder, ServerHandle},
+    types::error::{CallError, ErrorObject, INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE},
+};
+use reth::runner::CliContext;
+use reth_db::{
+    cursor::{DbCursorRO, DbDupCursorRO},
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTx,
+};
+use reth_primitives::{
+    bloom::logs_bloom, keccak256, Account, Address, BlockNumberOrTag, Bytes, Header,
+    TransactionSigned, H256, H64, U256,
+};
+use reth_rlp::{Encodable, Header as RlpHeader};
+
+use crate::cli::{
+    args::DatabaseArgs,
+    db::prune::{PruneCheckpoints, PrunedData},
+    deposit::{self, DepositStore},
+    l1_fee::L1FeeStore,
+    metrics,
+    node::pool::{PoolError, TxPool},
+    replay,
+    shutdown::{self, ShutdownPhase},
+};
+
+pub mod cache;
+pub mod types;
+
+use cache::{RpcCache, RpcCacheArgs};
+use types::{BlockTransactions, RpcBlock, RpcLog, RpcReceipt, RpcTransaction};
+
+/// Serve read-only JSON-RPC over the migrated database
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The address the HTTP server listens on
+    #[arg(
+        long = "http.addr",
+        value_name = "ADDR",
+        default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST)
+    )]
+    http_addr: IpAddr,
+
+    /// The port the HTTP server listens on
+    #[arg(long = "http.port", value_name = "PORT", default_value_t = 8545)]
+    http_port: u16,
+
+    #[clap(flatten)]
+    cache: RpcCacheArgs,
+
+    /// Serve Prometheus metrics, including the hits and misses of the response caches, on the
+    /// given address
+    #[arg(long, value_name = "SOCKET")]
+    metrics: Option<SocketAddr>,
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db = Arc::new(self.db.open_ro()?);
+        let fees = Arc::new(L1FeeStore::open(&self.db.static_path()?)?);
+        let deposits = Arc::new(DepositStore::open(&self.db.static_path()?)?);
+        if let Some(addr) = self.metrics {
+            metrics::install(addr)?;
+        }
+        let addr = SocketAddr::new(self.http_addr, self.http_port);
+        let api = EthApi::new(db, fees, deposits)
+            .with_prune_checkpoints(PruneCheckpoints::read(&self.db.path())?)
+            .with_cache(RpcCache::new(self.cache));
+        let handle = start_server(addr, api).await?;
+        tracing::info!(target: "reth::cli", %addr, "JSON-RPC server started");
+
+        let _shutdown = shutdown::registry().register("rpc server", ShutdownPhase::Rpc, {
+            let handle = handle.clone();
+            move || async move {
+                if handle.stop().is_ok() {
+                    handle.stopped().await;
+                }
+                Ok(())
+            }
+        });
+        handle.stopped().await;
+        Ok(())
+    }
+}
+
+/// The subset of the `eth` namespace served from the migrated database. Transactions are only
+/// accepted by nodes with a transaction pool.
+#[rpc(server, namespace = "eth")]
+pub trait EthApi {
+    /// Returns the number of the highest canonical block
+    #[method(name = "blockNumber")]
+    fn block_number(&self) -> RpcResult<U256>;
+
+    /// Returns the block with the given number
+    #[method(name = "getBlockByNumber")]
+    fn block_by_number(&self, number: BlockNumberOrTag, full: bool) -> RpcResult<Option<RpcBlock>>;
+
+    /// Returns the canonical block with the given hash
+    #[method(name = "getBlockByHash")]
+    fn block_by_hash(&self, hash: H256, full: bool) -> RpcResult<Option<RpcBlock>>;
+
+    /// Returns the transaction with the given hash
+    #[method(name = "getTransactionByHash")]
+    fn transaction_by_hash(&self, hash: H256) -> RpcResult<Option<RpcTransaction>>;
+
+    /// Returns the receipt of the transaction with the given hash
+    #[method(name = "getTransactionReceipt")]
+    fn transaction_receipt(&self, hash: H256) -> RpcResult<Option<RpcReceipt>>;
+
+    /// Returns the balance of the given account
+    #[method(name = "getBalance")]
+    fn balance(&self, address: Address, block: Option<BlockNumberOrTag>) -> RpcResult<U256>;
+
+    /// Returns the code of the given account
+    #[method(name = "getCode")]
+    fn code(&self, address: Address, block: Option<BlockNumberOrTag>) -> RpcResult<Bytes>;
+
+    /// Returns the value of a storage slot of the given account
+    #[method(name = "getStorageAt")]
+    fn storage_at(
+        &self,
+        address: Address,
+        slot: U256,
+        block: Option<BlockNumberOrTag>,
+    ) -> RpcResult<H256>;
+
+    /// Adds a signed transaction envelope to the transaction pool and returns its hash
+    #[method(name = "sendRawTransaction")]
+    fn send_raw_transaction(&self, data: Bytes) -> RpcResult<H256>;
+}
+
+/// The location of a transaction within the canonical chain
+#[derive(Debug, Clone, Copy)]
+struct TransactionLocation {
+    tx_id: u64,
+    block_number: u64,
+    block_hash: H256,
+    index: u64,
+}
+
+/// The `eth` API handler, reading from the MDBX tables. Blocks, receipts and contract codes are
+/// kept in an [RpcCache] under their hashes, which stay valid across reorgs.
+#[derive(Debug, Clone)]
+pub struct EthApi {
+    db: Arc<Env<WriteMap>>,
+    fees: Arc<L1FeeStore>,
+    deposits: Arc<DepositStore>,
+    cache: Arc<RpcCache>,
+    pool: Option<Arc<TxPool>>,
+    pruned: PruneCheckpoints,
+}
+
+impl EthApi {
+    /// Creates a new handler reading from the given database and its L1 fee and deposit stores,
+    /// caching with the default limits
+    pub fn new(db: Arc<Env<WriteMap>>, fees: Arc<L1FeeStore>, deposits: Arc<DepositStore>) -> Self {
+        Self {
+            db,
+            fees,
+            deposits,
+            cache: Arc::new(RpcCache::default()),
+            pool: None,
+            pruned: PruneCheckpoints::default(),
+        }
+    }
+
+    /// Accepts transactions with `eth_sendRawTransaction` into the given pool
+    pub fn with_pool(mut self, pool: Arc<TxPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Refuses the transactions and receipts of the blocks pruned below the given checkpoints
+    /// instead of serving them as missing
+    pub fn with_prune_checkpoints(mut self, pruned: PruneCheckpoints) -> Self {
+        self.pruned = pruned;
+        self
+    }
+
+    /// Sets the response caches of the handler
+    pub fn with_cache(mut self, cache: RpcCache) -> Self {
+        self.cache = Arc::new(cache);
+        self
+    }
+
+    /// The response caches of the handler
+    pub fn cache(&self) -> &RpcCache {
+        &self.cache
+    }
+
+    /// Loads the canonical block with the given number through the block cache. The cache is keyed
+    /// by the hash of the block, so a block replaced by a reorg is never served from it.
+    fn block(&self, number: u64, full: bool) -> Result<Option<RpcBlock>> {
+        self.db.view(|tx| -> Result<Option<RpcBlock>> {
+            let Some(hash) = tx.get::<tables::CanonicalHeaders>(number)? else { return Ok(None) };
+            self.cache.blocks.get_or_load((hash, full), || {
+                load_block(tx, &self.deposits, &self.pruned, number, full)
+            })
+        })?
+    }
+
+    /// Returns the highest canonical block number
+    fn tip(&self) -> Result<u64> {
+        Ok(self.db.view(|tx| canonical_tip(tx))??)
+    }
+
+    /// Resolves a block tag against the canonical chain
+    fn resolve(&self, number: BlockNumberOrTag) -> Result<u64> {
+        Ok(match number {
+            BlockNumberOrTag::Number(number) => number,
+            BlockNumberOrTag::Earliest => 0,
+            _ => self.tip()?,
+        })
+    }
+
+    /// Resolves the block a state query reads, `None` for the canonical tip, whose state is the
+    /// plain state. Earlier blocks are reconstructed from the history written by `replay` and
+    /// `index-history`.
+    fn state_block(&self, block: Option<BlockNumberOrTag>) -> RpcResult<Option<u64>> {
+        let Some(block) = block else { return Ok(None) };
+        let number = self.resolve(block).map_err(internal_error)?;
+        let tip = self.tip().map_err(internal_error)?;
+        if number == tip {
+            return Ok(None)
+        }
+        self.db
+            .view(|tx| replay::historical_transition(tx, number))
+            .map_err(internal_error)?
+            .map_err(|err| {
+                invalid_params(format!("The state of block {number} is not available: {err}"))
+            })?;
+        Ok(Some(number))
+    }
+
+    /// Returns the account at the given address in the state of the given block
+    fn account(
+        &self,
+        address: Address,
+        block: Option<BlockNumberOrTag>,
+    ) -> RpcResult<Option<Account>> {
+        let number = self.state_block(block)?;
+        self.db
+            .view(|tx| match number {
+                Some(number) => replay::account_at(tx, address, number),
+                None => Ok(tx.get::<tables::PlainAccountState>(address)?),
+            })
+            .map_err(internal_error)?
+            .map_err(internal_error)
+    }
+}
+
+impl EthApiServer for EthApi {
+    fn block_number(&self) -> RpcResult<U256> {
+        self.tip().map(U256::from).map_err(internal_error)
+    }
+
+    fn block_by_number(&self, number: BlockNumberOrTag, full: bool) -> RpcResult<Option<RpcBlock>> {
+        let number = self.resolve(number).map_err(internal_error)?;
+        self.block(number, full).map_err(internal_error)
+    }
+
+    fn block_by_hash(&self, hash: H256, full: bool) -> RpcResult<Option<RpcBlock>> {
+        let number = self
+            .db
+            .view(|tx| -> Result<Option<u64>> {
+                let Some(number) = tx.get::<tables::HeaderNumbers>(hash)? else { return Ok(None) };
+                let canonical = tx.get::<tables::CanonicalHeaders>(number)?;
+                Ok(Some(number).filter(|_| canonical == Some(hash)))
+            })
+            .map_err(internal_error)?
+            .map_err(internal_error)?;
+        let Some(number) = number else { return Ok(None) };
+        self.block(number, full).map_err(internal_error)
+    }
+
+    fn transaction_by_hash(&self, hash: H256) -> RpcResult<Option<RpcTransaction>> {
+        self.db
+            .view(|tx| -> Result<Option<RpcTransaction>> {
+                let Some(location) = locate_transaction(tx, hash)? else { return Ok(None) };
+                let transaction = self
+                    .deposits
+                    .read(tx, location.tx_id)?
+                    .ok_or_else(|| eyre::eyre!("Transaction {} not found", location.tx_id))?;
+                Ok(Some(rpc_transaction(
+                    &transaction,
+                    tx.get::<tables::TxSenders>(location.tx_id)?,
+                    location.block_hash,
+                    location.block_number,
+                    location.index,
+                )))
+            })
+            .map_err(internal_error)?
+            .map_err(internal_error)
+    }
+
+    fn transaction_receipt(&self, hash: H256) -> RpcResult<Option<RpcReceipt>> {
+        self.db
+            .view(|tx| -> Result<Option<RpcReceipt>> {
+                let Some(location) = locate_transaction(tx, hash)? else { return Ok(None) };
+                // Keyed by the including block too, as a reorg can move the transaction
+                self.cache.receipts.get_or_load((hash, location.block_hash), || {
+                    let Some(mut receipt) =
+                        load_receipt(tx, &self.deposits, &self.pruned, &location)?
+                    else {
+                        return Ok(None)
+                    };
+                    receipt.l1_fee = self.fees.get(location.tx_id)?;
+                    Ok(Some(receipt))
+                })
+            })
+            .map_err(internal_error)?
+            .map_err(internal_error)
+    }
+
+    fn balance(&self, address: Address, block: Option<BlockNumberOrTag>) -> RpcResult<U256> {
+        let account = self.account(address, block)?;
+        Ok(account.map(|account| account.balance).unwrap_or_default())
+    }
+
+    fn code(&self, address: Address, block: Option<BlockNumberOrTag>) -> RpcResult<Bytes> {
+        let code_hash = self.account(address, block)?.and_then(|account| account.bytecode_hash);
+        let Some(code_hash) = code_hash else { return Ok(Bytes::default()) };
+        let code = self
+            .cache
+            .bytecodes
+            .get_or_load(code_hash, || {
+                Ok(self.db.view(|tx| tx.get::<tables::Bytecodes>(code_hash))??.map(Bytes::from))
+            })
+            .map_err(internal_error)?;
+        Ok(code.unwrap_or_default())
+    }
+
+    fn storage_at(
+        &self,
+        address: Address,
+        slot: U256,
+        block: Option<BlockNumberOrTag>,
+    ) -> RpcResult<H256> {
+        let number = self.state_block(block)?;
+        let key = H256::from(slot.to_be_bytes::<32>());
+        self.db
+            .view(|tx| -> Result<H256> {
+                if let Some(number) = number {
+                    let value = replay::storage_at(tx, address, key, number)?;
+                    return Ok(H256::from(value.to_be_bytes::<32>()))
+                }
+                let mut cursor = tx.cursor_dup_read::<tables::PlainStorageState>()?;
+                let value = cursor
+                    .seek_by_key_subkey(address, key)?
+                    .filter(|entry| entry.key == key)
+                    .map(|entry| entry.value)
+                    .unwrap_or_default();
+                Ok(H256::from(value.to_be_bytes::<32>()))
+            })
+            .map_err(internal_error)?
+            .map_err(internal_error)
+    }
+
+    fn send_raw_transaction(&self, data: Bytes) -> RpcResult<H256> {
+        let Some(pool) = &self.pool else {
+            return Err(invalid_params("Transactions are not accepted, the node has no pool"))
+        };
+        let hash = pool.add_raw(&data).map_err(|err| match err.downcast_ref::<PoolError>() {
+            Some(err) => invalid_params(err.to_string()),
+            None => internal_error(err),
+        })?;
+        tracing::debug!(target: "reth::rpc", ?hash, pending = pool.len(), "Transaction added to the pool");
+        Ok(hash)
+    }
+}
+
+/// Starts the JSON-RPC server on the given address
+pub async fn start_server(addr: SocketAddr, api: EthApi) -> Result<ServerHandle> {
+    let server = ServerBuilder::default().build(addr).await?;
+    Ok(server.start(api.into_rpc())?)
+}
+
+/// Returns the highest canonical block number
+fn canonical_tip<'a, TX: DbTx<'a>>(tx: &TX) -> Result<u64> {
+    Ok(tx.cursor_read::<tables::CanonicalHeaders>()?.last()?.map(|(number, _)| number).unwrap_or(0))
+}
+
+/// Finds the number of the block containing the given transaction by binary searching the bodies
+fn block_of_tx<'a, TX: DbTx<'a>>(tx: &TX, tx_id: u64) -> Result<Option<u64>> {
+    let (mut low, mut high) = (0, canonical_tip(tx)?);
+    while low < high {
+        let mid = low + (high - low + 1) / 2;
+        match tx.get::<tables::BlockBodies>(mid)? {
+            Some(body) if body.start_tx_id <= tx_id => low = mid,
+            _ => high = mid - 1,
+        }
+    }
+    Ok(tx
+        .get::<tables::BlockBodies>(low)?
+        .filter(|body| (body.start_tx_id..body.start_tx_id + body.tx_count).contains(&tx_id))
+        .map(|_| low))
+}
+
+/// Looks up the location of the transaction with the given hash
+fn locate_transaction<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    hash: H256,
+) -> Result<Option<TransactionLocation>> {
+    let Some(tx_id) = tx.get::<tables::TxHashNumber>(hash)? else { return Ok(None) };
+    let Some(block_number) = block_of_tx(tx, tx_id)? else { return Ok(None) };
+    let start_tx_id =
+        tx.get::<tables::BlockBodies>(block_number)?.map_or(tx_id, |body| body.start_tx_id);
+    let block_hash = tx.get::<tables::CanonicalHeaders>(block_number)?.unwrap_or_default();
+    Ok(Some(TransactionLocation { tx_id, block_number, block_hash, index: tx_id - start_tx_id }))
+}
+
+/// Loads the canonical block with the given number, restoring its deposits from `deposits`.
+/// Fails if the transactions of the block were pruned.
+fn load_block<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    deposits: &DepositStore,
+    pruned: &PruneCheckpoints,
+    number: u64,
+    full: bool,
+) -> Result<Option<RpcBlock>> {
+    let Some(header) = tx.get::<tables::Headers>(number)? else { return Ok(None) };
+    pruned.ensure_available(PrunedData::Bodies, &(number..=number))?;
+    let hash = tx.get::<tables::CanonicalHeaders>(number)?.unwrap_or_else(|| header.hash_slow());
+    let total_difficulty = tx.get::<tables::HeaderTD>(number)?.map(|td| td.0);
+    let ommers =
+        tx.get::<tables::BlockOmmers>(number)?.map(|ommers| ommers.ommers).unwrap_or_default();
+
+    let mut transactions = Vec::new();
+    let mut senders = Vec::new();
+    if let Some(body) = tx.get::<tables::BlockBodies>(number)? {
+        for tx_id in body.start_tx_id..body.start_tx_id + body.tx_count {
+            let transaction = deposits
+                .read(tx, tx_id)?
+                .ok_or_else(|| eyre::eyre!("Transaction {tx_id} not found"))?;
+            transactions.push(transaction);
+            senders.push(tx.get::<tables::TxSenders>(tx_id)?);
+        }
+    }
+    Ok(Some(rpc_block(header, hash, &transactions, &senders, &ommers, total_difficulty, full)))
+}
+
+/// Converts a block into its JSON-RPC representation, with the full transaction objects or only
+/// their hashes. The senders of the transactions are given in the order of the transactions, as
+/// deposits and unsigned system transactions have no signature to recover them from.
+pub fn rpc_block(
+    header: Header,
+    hash: H256,
+    transactions: &[TransactionSigned],
+    senders: &[Option<Address>],
+    ommers: &[Header],
+    total_difficulty: Option<U256>,
+    full: bool,
+) -> RpcBlock {
+    let transactions = if full {
+        BlockTransactions::Full(
+            transactions
+                .iter()
+                .enumerate()
+                .map(|(index, transaction)| {
+                    let from = senders.get(index).copied().flatten();
+                    rpc_transaction(transaction, from, hash, header.number, index as u64)
+                })
+                .collect(),
+        )
+    } else {
+        BlockTransactions::Hashes(transactions.iter().map(|tx| tx.hash()).collect())
+    };
+
+    RpcBlock {
+        hash,
+        parent_hash: header.parent_hash,
+        sha3_uncles: header.ommers_hash,
+        miner: header.beneficiary,
+        state_root: header.state_root,
+        transactions_root: header.transactions_root,
+        receipts_root: header.receipts_root,
+        logs_bloom: header.logs_bloom,
+        difficulty: header.difficulty,
+        total_difficulty,
+        number: U256::from(header.number),
+        gas_limit: U256::from(header.gas_limit),
+        gas_used: U256::from(header.gas_used),
+        timestamp: U256::from(header.timestamp),
+        extra_data: header.extra_data,
+        mix_hash: header.mix_hash,
+        nonce: H64::from_low_u64_be(header.nonce),
+        base_fee_per_gas: header.base_fee_per_gas.map(U256::from),
+        uncles: ommers.iter().map(|ommer| ommer.hash_slow()).collect(),
+        transactions,
+    }
+}
+
+/// Loads the receipt of the transaction at the given location. The L1 fee fields are left empty.
+/// Fails if the receipts of the block of the transaction were pruned.
+fn load_receipt<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    deposits: &DepositStore,
+    pruned: &PruneCheckpoints,
+    location: &TransactionLocation,
+) -> Result<Option<RpcReceipt>> {
+    pruned
+        .ensure_available(PrunedData::Receipts, &(location.block_number..=location.block_number))?;
+    let Some(receipt) = tx.get::<tables::Receipts>(location.tx_id)? else { return Ok(None) };
+    let transaction = deposits
+        .read(tx, location.tx_id)?
+        .ok_or_else(|| eyre::eyre!("Transaction {} not found", location.tx_id))?;
+
+    // Gas used and log indices are relative to the previous receipts of the block
+    let first_tx_id = location.tx_id - location.index;
+    let mut previous_gas_used = 0;
+    let mut log_index = 0;
+    for tx_id in first_tx_id..location.tx_id {
+        if let Some(previous) = tx.get::<tables::Receipts>(tx_id)? {
+            previous_gas_used = previous.cumulative_gas_used;
+            log_index += previous.logs.len();
+        }
+    }
+
+    let from = tx.get::<tables::TxSenders>(location.tx_id)?;
+    let to = transaction.to();
+    let contract_address = match (from, to) {
+        (Some(sender), None) => Some(create_address(sender, transaction.nonce())),
+        _ => None,
+    };
+
+    let logs = receipt
+        .logs
+        .iter()
+        .enumerate()
+        .map(|(index, log)| RpcLog {
+            address: log.address,
+            topics: log.topics.clone(),
+            data: log.data.clone(),
+            block_hash: location.block_hash,
+            block_number: U256::from(location.block_number),
+            transaction_hash: transaction.hash(),
+            transaction_index: U256::from(location.index),
+            log_index: U256::from(log_index + index),
+            removed: false,
+        })
+        .collect();
+
+    let receipt = RpcReceipt {
+        transaction_hash: transaction.hash(),
+        transaction_index: U256::from(location.index),
+        block_hash: location.block_hash,
+        block_number: U256::from(location.block_number),
+        from,
+        to,
+        cumulative_gas_used: U256::from(receipt.cumulative_gas_used),
+        gas_used: U256::from(receipt.cumulative_gas_used.saturating_sub(previous_gas_used)),
+        contract_address,
+        logs,
+        logs_bloom: logs_bloom(receipt.logs.iter()),
+        status: U256::from(receipt.success as u8),
+        tx_type: U256::from(deposit::tx_type(transaction)),
+        l1_fee: None,
+    };
+    Ok(Some(receipt))
+}
+
+/// Converts a transaction at the given index of a block, sent by `from`, into its JSON-RPC
+/// representation
+fn rpc_transaction(
+    transaction: &TransactionSigned,
+    from: Option<Address>,
+    block_hash: H256,
+    block_number: u64,
+    index: u64,
+) -> RpcTransaction {
+    let chain_id = transaction.chain_id();
+    RpcTransaction {
+        hash: transaction.hash(),
+        nonce: U256::from(transaction.nonce()),
+        block_hash,
+        block_number: U256::from(block_number),
+        transaction_index: U256::from(index),
+        from,
+        to: transaction.to(),
+        value: U256::from(transaction.value()),
+        gas_price: U256::from(transaction.max_fee_per_gas()),
+        gas: U256::from(transaction.gas_limit()),
+        input: transaction.input().clone(),
+        v: U256::from(transaction.signature.v(chain_id)),
+        r: transaction.signature.r,
+        s: transaction.signature.s,
+        chain_id: chain_id.map(U256::from),
+        tx_type: U256::from(deposit::tx_type(transaction)),
+    }
+}
+
+/// Computes the address of a contract created by the given sender and nonce
+pub fn create_address(sender: Address, nonce: u64) -> Address {
+    let mut out = BytesMut::new();
+    RlpHeader { list: true, payload_length: sender.length() + nonce.length() }.encode(&mut out);
+    sender.encode(&mut out);
+    nonce.encode(&mut out);
+    Address::from_slice(&keccak256(&out)[12..])
+}
+
+/// Converts an error into an internal JSON-RPC error
+pub(crate) fn internal_error(err: impl std::fmt::Display) -> jsonrpsee::core::Error {
+    CallError::Custom(ErrorObject::owned(INTERNAL_ERROR_CODE, err.to_string(), None::<()>)).into()
+}
+
+/// Creates an invalid params JSON-RPC error
+pub(crate) fn invalid_params(message: impl Into<String>) -> jsonrpsee::core::Error {
+    CallError::Custom(ErrorObject::owned(INVALID_PARAMS_CODE, message, None::<()>)).into()
+}
@@ -0,0 +1,136 @@
+use std::{hash::Hash, num::NonZeroUsize, sync::Mutex};
+
+use clap::Args;
+use eyre::Result;
+use lru::LruCache;
+use reth_primitives::{Bytes, H256};
+
+use super::types::{RpcBlock, RpcReceipt};
+
+/// The default number of blocks kept by the [RpcCache]
+pub const DEFAULT_MAX_BLOCKS: usize = 5000;
+
+/// The default number of receipts kept by the [RpcCache]
+pub const DEFAULT_MAX_RECEIPTS: usize = 2000;
+
+/// The default number of contract codes kept by the [RpcCache]
+pub const DEFAULT_MAX_BYTECODES: usize = 1000;
+
+/// The limits of the response caches of the JSON-RPC server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Args)]
+pub struct RpcCacheArgs {
+    /// The number of blocks to keep in memory. 0 disables the cache.
+    #[arg(
+        long = "rpc-cache.max-blocks",
+        value_name = "COUNT",
+        default_value_t = DEFAULT_MAX_BLOCKS
+    )]
+    pub max_blocks: usize,
+
+    /// The number of transaction receipts to keep in memory. 0 disables the cache.
+    #[arg(
+        long = "rpc-cache.max-receipts",
+        value_name = "COUNT",
+        default_value_t = DEFAULT_MAX_RECEIPTS
+    )]
+    pub max_receipts: usize,
+
+    /// The number of contract codes to keep in memory. 0 disables the cache.
+    #[arg(
+        long = "rpc-cache.max-bytecodes",
+        value_name = "COUNT",
+        default_value_t = DEFAULT_MAX_BYTECODES
+    )]
+    pub max_bytecodes: usize,
+}
+
+impl Default for RpcCacheArgs {
+    fn default() -> Self {
+        Self {
+            max_blocks: DEFAULT_MAX_BLOCKS,
+            max_receipts: DEFAULT_MAX_RECEIPTS,
+            max_bytecodes: DEFAULT_MAX_BYTECODES,
+        }
+    }
+}
+
+/// A least recently used cache of responses, counting its hits and misses as metrics labelled
+/// with its name.
+///
+/// Only found values are cached, so data imported after a miss is served once it exists.
+#[derive(Debug)]
+pub struct ResponseCache<K: Hash + Eq, V> {
+    name: &'static str,
+    entries: Option<Mutex<LruCache<K, V>>>,
+}
+
+impl<K: Hash + Eq, V: Clone> ResponseCache<K, V> {
+    /// Creates a cache holding up to `capacity` entries, or a disabled one if it is 0
+    pub fn new(name: &'static str, capacity: usize) -> Self {
+        let entries =
+            NonZeroUsize::new(capacity).map(|capacity| Mutex::new(LruCache::new(capacity)));
+        Self { name, entries }
+    }
+
+    /// Returns the cached value of the key, or loads it and caches it if it was found
+    pub fn get_or_load(
+        &self,
+        key: K,
+        load: impl FnOnce() -> Result<Option<V>>,
+    ) -> Result<Option<V>> {
+        let Some(entries) = &self.entries else { return load() };
+        if let Some(value) = entries.lock().expect("poisoned").get(&key) {
+            metrics::counter!("rpc_cache_hits_total", 1, "cache" => self.name);
+            return Ok(Some(value.clone()))
+        }
+        metrics::counter!("rpc_cache_misses_total", 1, "cache" => self.name);
+
+        // Load without holding the lock, so concurrent misses don't wait for each other
+        let value = load()?;
+        if let Some(value) = &value {
+            let mut entries = entries.lock().expect("poisoned");
+            entries.put(key, value.clone());
+            metrics::gauge!("rpc_cache_entries", entries.len() as f64, "cache" => self.name);
+        }
+        Ok(value)
+    }
+
+    /// The number of cached entries
+    pub fn len(&self) -> usize {
+        self.entries.as_ref().map_or(0, |entries| entries.lock().expect("poisoned").len())
+    }
+
+    /// Whether no entries are cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The response caches of the JSON-RPC server. Blocks and receipts are keyed by block hash, so the
+/// entries of blocks replaced by a reorg are never served again.
+#[derive(Debug)]
+pub struct RpcCache {
+    /// Blocks by hash and whether their transactions are included in full
+    pub blocks: ResponseCache<(H256, bool), RpcBlock>,
+    /// Receipts by transaction hash and the hash of their block, including their L1 fees
+    pub receipts: ResponseCache<(H256, H256), RpcReceipt>,
+    /// Contract codes by code hash
+    pub bytecodes: ResponseCache<H256, Bytes>,
+}
+
+impl RpcCache {
+    /// Creates the caches with the given limits
+    pub fn new(args: RpcCacheArgs) -> Self {
+        Self {
+            blocks: ResponseCache::new("blocks", args.max_blocks),
+            receipts: ResponseCache::new("receipts", args.max_receipts),
+            bytecodes: ResponseCache::new("bytecodes", args.max_bytecodes),
+        }
+    }
+}
+
+impl Default for RpcCache {
+    fn default() -> Self {
+        Self::new(RpcCacheArgs::default())
+    }
+}
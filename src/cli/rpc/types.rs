@@ -0,0 +1,103 @@
+use reth_primitives::{Address, Bloom, Bytes, H256, H64, U256};
+use serde::Serialize;
+
+use crate::cli::l1_fee::L1FeeInfo;
+
+/// A block as returned by `eth_getBlockByNumber`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcBlock {
+    pub hash: H256,
+    pub parent_hash: H256,
+    pub sha3_uncles: H256,
+    pub miner: Address,
+    pub state_root: H256,
+    pub transactions_root: H256,
+    pub receipts_root: H256,
+    pub logs_bloom: Bloom,
+    pub difficulty: U256,
+    pub total_difficulty: Option<U256>,
+    pub number: U256,
+    pub gas_limit: U256,
+    pub gas_used: U256,
+    pub timestamp: U256,
+    pub extra_data: Bytes,
+    pub mix_hash: H256,
+    pub nonce: H64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_fee_per_gas: Option<U256>,
+    pub uncles: Vec<H256>,
+    pub transactions: BlockTransactions,
+}
+
+/// The transactions of a [RpcBlock], either as hashes or as full objects
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum BlockTransactions {
+    /// Only the transaction hashes
+    Hashes(Vec<H256>),
+    /// The full transaction objects
+    Full(Vec<RpcTransaction>),
+}
+
+/// A transaction as returned by `eth_getTransactionByHash`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcTransaction {
+    pub hash: H256,
+    pub nonce: U256,
+    pub block_hash: H256,
+    pub block_number: U256,
+    pub transaction_index: U256,
+    pub from: Option<Address>,
+    pub to: Option<Address>,
+    pub value: U256,
+    pub gas_price: U256,
+    pub gas: U256,
+    pub input: Bytes,
+    pub v: U256,
+    pub r: U256,
+    pub s: U256,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<U256>,
+    #[serde(rename = "type")]
+    pub tx_type: U256,
+}
+
+/// A receipt as returned by `eth_getTransactionReceipt`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcReceipt {
+    pub transaction_hash: H256,
+    pub transaction_index: U256,
+    pub block_hash: H256,
+    pub block_number: U256,
+    pub from: Option<Address>,
+    pub to: Option<Address>,
+    pub cumulative_gas_used: U256,
+    pub gas_used: U256,
+    pub contract_address: Option<Address>,
+    pub logs: Vec<RpcLog>,
+    pub logs_bloom: Bloom,
+    pub status: U256,
+    #[serde(rename = "type")]
+    pub tx_type: U256,
+    /// The L1 fee fields returned by legacy l2geth
+    #[serde(flatten)]
+    pub l1_fee: Option<L1FeeInfo>,
+}
+
+/// A log as contained in a [RpcReceipt]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcLog {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Bytes,
+    pub block_hash: H256,
+    pub block_number: U256,
+    pub transaction_hash: H256,
+    pub transaction_index: U256,
+    pub log_index: U256,
+    pub removed: bool,
+}
@@ -0,0 +1,167 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use eyre::Result;
+use futures::future::BoxFuture;
+use once_cell::sync::Lazy;
+
+/// How long each shutdown hook may run before it is abandoned
+pub const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The registry the ctrl-c handler runs
+static REGISTRY: Lazy<ShutdownRegistry> = Lazy::new(ShutdownRegistry::default);
+
+/// The registry of the process, run by the ctrl-c handler
+pub fn registry() -> &'static ShutdownRegistry {
+    &REGISTRY
+}
+
+/// The phases of a shutdown, run in the order they are declared in. Producers of writes stop
+/// before the subsystems they write to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ShutdownPhase {
+    /// Running imports finish their progress and stop writing
+    Import,
+    /// Servers stop accepting requests
+    Rpc,
+    /// Metrics reporters publish their last sample and stop
+    Metrics,
+    /// The databases and the files next to them are brought into a consistent state
+    Database,
+}
+
+/// How a shutdown hook ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookOutcome {
+    /// The hook completed
+    Completed,
+    /// The hook failed with the given error
+    Failed(String),
+    /// The hook didn't complete within the timeout
+    TimedOut,
+}
+
+/// The outcome of one hook of a shutdown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookReport {
+    /// The name the hook was registered with
+    pub name: &'static str,
+    /// The phase the hook ran in
+    pub phase: ShutdownPhase,
+    /// How the hook ended
+    pub outcome: HookOutcome,
+}
+
+type Hook = Box<dyn FnOnce() -> BoxFuture<'static, Result<()>> + Send>;
+
+/// Async cleanup hooks of the long-running subsystems, run in order when the process is
+/// interrupted.
+///
+/// Hooks run phase by phase, and within a phase in the reverse order of their registration, like
+/// destructors. Every hook is registered through a [ShutdownGuard] that removes it again once the
+/// subsystem finished on its own.
+#[derive(Default)]
+pub struct ShutdownRegistry {
+    next_id: AtomicU64,
+    hooks: Mutex<BTreeMap<(ShutdownPhase, u64), (&'static str, Hook)>>,
+}
+
+impl fmt::Debug for ShutdownRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hooks = self.hooks.lock().expect("poisoned");
+        let names = hooks.iter().map(|((phase, _), (name, _))| (phase, name)).collect::<Vec<_>>();
+        f.debug_struct("ShutdownRegistry").field("hooks", &names).finish()
+    }
+}
+
+impl ShutdownRegistry {
+    /// Registers a hook run in the given phase of a shutdown. The hook is removed when the
+    /// returned guard is dropped.
+    pub fn register<F, Fut>(
+        &self,
+        name: &'static str,
+        phase: ShutdownPhase,
+        hook: F,
+    ) -> ShutdownGuard<'_>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        // Later registrations get lower keys, so they run first within their phase
+        let id = u64::MAX - self.next_id.fetch_add(1, Ordering::Relaxed);
+        let hook: Hook = Box::new(move || Box::pin(hook()));
+        self.hooks.lock().expect("poisoned").insert((phase, id), (name, hook));
+        ShutdownGuard { registry: self, key: (phase, id) }
+    }
+
+    /// The number of registered hooks
+    pub fn len(&self) -> usize {
+        self.hooks.lock().expect("poisoned").len()
+    }
+
+    /// Whether no hooks are registered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Runs and removes all registered hooks, giving each at most `timeout`. Failures and
+    /// timeouts are logged and don't stop the hooks after them.
+    pub async fn run(&self, timeout: Duration) -> Vec<HookReport> {
+        let hooks = std::mem::take(&mut *self.hooks.lock().expect("poisoned"));
+        let mut reports = Vec::with_capacity(hooks.len());
+        for ((phase, _), (name, hook)) in hooks {
+            tracing::info!(target: "reth::cli", name, ?phase, "Running shutdown hook");
+            let outcome = match tokio::time::timeout(timeout, hook()).await {
+                Ok(Ok(())) => HookOutcome::Completed,
+                Ok(Err(error)) => {
+                    tracing::warn!(target: "reth::cli", name, %error, "Shutdown hook failed");
+                    HookOutcome::Failed(format!("{error:#}"))
+                }
+                Err(_) => {
+                    tracing::warn!(target: "reth::cli", name, ?timeout, "Shutdown hook timed out");
+                    HookOutcome::TimedOut
+                }
+            };
+            reports.push(HookReport { name, phase, outcome });
+        }
+        reports
+    }
+
+    /// Runs the hooks on a new runtime, for callers outside of one like the ctrl-c handler
+    pub fn run_blocking(&self, timeout: Duration) -> Vec<HookReport> {
+        match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+            Ok(runtime) => runtime.block_on(self.run(timeout)),
+            Err(error) => {
+                tracing::warn!(target: "reth::cli", %error, "Unable to run the shutdown hooks");
+                vec![]
+            }
+        }
+    }
+}
+
+/// Keeps a hook registered in a [ShutdownRegistry] until it is dropped
+#[must_use = "the hook is removed when the guard is dropped"]
+pub struct ShutdownGuard<'a> {
+    registry: &'a ShutdownRegistry,
+    key: (ShutdownPhase, u64),
+}
+
+impl fmt::Debug for ShutdownGuard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShutdownGuard").field("phase", &self.key.0).finish()
+    }
+}
+
+impl Drop for ShutdownGuard<'_> {
+    fn drop(&mut self) {
+        self.registry.hooks.lock().expect("poisoned").remove(&self.key);
+    }
+}
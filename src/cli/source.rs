@@ -0,0 +1,167 @@
+use eyre::Result;
+use reth_primitives::{
+    rpc::{H160, H256},
+    SealedBlock, U256,
+};
+
+use crate::cli::{
+    args::ImportArgs,
+    dead_letter::DeadLetterFile,
+    progress::ImportProgress,
+    receipts::Receipt,
+    rpc::create_address,
+    state::{formats::AccountVisitor, State},
+    throttle::IoLimiter,
+    validate::SystemTxValidator,
+};
+
+pub mod dead_letter;
+pub mod era1;
+pub mod erigon;
+pub mod file;
+pub mod freezer;
+pub mod mapped;
+pub mod rpc;
+pub mod shards;
+
+/// The state of an import a source reads its records with
+pub struct SourceContext<'a> {
+    /// The arguments of the import
+    pub args: &'a ImportArgs,
+    /// The progress of the import
+    pub progress: &'a ImportProgress,
+    /// The limiter of the I/O of the import, if any
+    pub limiter: Option<&'a IoLimiter>,
+    /// The file records failing to decode are written to, if any
+    pub dead_letter: Option<&'a mut DeadLetterFile>,
+    /// The validator checking the decoded records against the selected chain, if any
+    pub validator: Option<&'a mut SystemTxValidator>,
+}
+
+/// A source of the blocks to import.
+///
+/// The importers only read their inputs through the source traits, so supporting a new input
+/// takes an implementation of the matching trait.
+pub trait BlockSource {
+    /// Describes the source in logs
+    fn describe(&self) -> String;
+
+    /// Reads the blocks of the source in ascending order
+    fn read_blocks(&mut self, ctx: &mut SourceContext<'_>) -> Result<Vec<SealedBlock>>;
+}
+
+/// A source of the receipts to import
+pub trait ReceiptSource {
+    /// Describes the source in logs
+    fn describe(&self) -> String;
+
+    /// Reads the receipts of the source
+    fn read_receipts(&mut self, ctx: &mut SourceContext<'_>) -> Result<Vec<Receipt>>;
+}
+
+/// A source of the world state to import
+pub trait StateSource {
+    /// Describes the source in logs
+    fn describe(&self) -> String;
+
+    /// Reads the world state of the source
+    fn read_state(&mut self, ctx: &mut SourceContext<'_>) -> Result<State>;
+
+    /// Passes the accounts of the world state of the source to `visit` one at a time. Sources
+    /// that can decode their accounts as they read them override this to not hold the whole
+    /// state in memory.
+    fn stream_state(
+        &mut self,
+        ctx: &mut SourceContext<'_>,
+        visit: &mut AccountVisitor<'_>,
+    ) -> Result<()> {
+        for (address, account) in self.read_state(ctx)? {
+            visit(address, account)?;
+        }
+        Ok(())
+    }
+}
+
+/// Assembles a block in the standard devp2p encoding from its rlp-encoded header and body, as
+/// stored separately by the freezer and era1 archives
+pub fn assemble_block(header: &[u8], body: &[u8]) -> Result<Vec<u8>> {
+    let body = rlp::Rlp::new(body);
+    let mut block = rlp::RlpStream::new_list(1 + body.item_count()?);
+    block.append_raw(header, 1);
+    for item in body.iter() {
+        block.append_raw(item.as_raw(), 1);
+    }
+    Ok(block.out().to_vec())
+}
+
+/// A receipt as stored by a node, without the fields derived from its block and transaction
+#[derive(Debug, Clone, Default)]
+pub struct StoredReceipt {
+    /// The status, or the post state root before byzantium
+    pub status_or_root: Vec<u8>,
+    /// The gas used by the transaction and the transactions before it in the block
+    pub cumulative_gas_used: u64,
+    /// The rlp-encoded list of logs
+    pub logs: Vec<u8>,
+    /// The L1 gas used, stored by l2geth
+    pub l1_gas_used: U256,
+    /// The L1 gas price, stored by l2geth
+    pub l1_gas_price: U256,
+    /// The L1 fee, stored by l2geth
+    pub l1_fee: U256,
+    /// The L1 fee scalar, stored by l2geth
+    pub l1_fee_scalar: String,
+}
+
+/// Completes the stored receipts of the given block with the fields derived from the block and
+/// its transactions. The logs bloom is recomputed from the logs.
+pub fn derive_receipts(block: &SealedBlock, stored: Vec<StoredReceipt>) -> Result<Vec<Receipt>> {
+    if stored.len() != block.body.len() {
+        eyre::bail!(
+            "Block {} has {} transactions, but {} receipts are stored for it",
+            block.number,
+            block.body.len(),
+            stored.len()
+        );
+    }
+
+    let mut receipts = Vec::with_capacity(stored.len());
+    let mut previous_gas = 0;
+    for (index, (stored, transaction)) in stored.into_iter().zip(&block.body).enumerate() {
+        // Pre-byzantium receipts hold the post state root instead of the status
+        let (post_state, status) = match stored.status_or_root.as_slice() {
+            [] => (vec![], 0),
+            [1] => (vec![], 1),
+            _ => (stored.status_or_root, 0),
+        };
+        let contract_address = match transaction.to() {
+            None => transaction
+                .recover_signer()
+                .map(|sender| create_address(sender, transaction.nonce()))
+                .unwrap_or_default(),
+            Some(_) => Default::default(),
+        };
+        let mut receipt = Receipt {
+            ty: transaction.tx_type() as u8,
+            post_state,
+            status,
+            cumulative_gas_used: stored.cumulative_gas_used,
+            bloom: vec![],
+            logs: stored.logs,
+            tx_hash: H256(transaction.hash().0),
+            contract_address: format!("{:?}", H160(contract_address.0)),
+            gas_used: stored.cumulative_gas_used.saturating_sub(previous_gas),
+            block_hash: H256(block.hash().0),
+            block_number: U256::from(block.number),
+            transaction_index: index as u64,
+            l1_gas_price: stored.l1_gas_price,
+            l1_gas_used: stored.l1_gas_used,
+            l1_fee: stored.l1_fee,
+            l1_fee_scalar: stored.l1_fee_scalar,
+        };
+        receipt.bloom = receipt.computed_bloom()?.as_bytes().to_vec();
+        previous_gas = receipt.cumulative_gas_used;
+        receipts.push(receipt);
+    }
+    Ok(receipts)
+}
@@ -0,0 +1,104 @@
+use std::path::{Path, PathBuf};
+
+use eyre::Result;
+use reth_primitives::SealedBlock;
+
+use crate::cli::{
+    blocks,
+    dead_letter::{DeadLetter, DeadLetterFile},
+    receipts::Receipt,
+    source::{BlockSource, ReceiptSource, SourceContext, StateSource},
+    state::State,
+};
+
+/// Reads the records a lenient import wrote to a dead-letter file, so they can be imported again
+/// once the cause of their failure is fixed.
+///
+/// Each importer only reads the records of its own kind: blocks, receipts or accounts. The records
+/// are decoded like the records of an export, and records failing again are handled by the
+/// `--lenient` and `--strict` policy of the import like any other.
+#[derive(Debug, Clone)]
+pub struct DeadLetterSource {
+    /// The path to the dead-letter file
+    path: PathBuf,
+    /// The records of the file
+    letters: Vec<DeadLetter>,
+}
+
+impl DeadLetterSource {
+    /// Reads the records of the dead-letter file at `path`. The file is read right away, so that
+    /// an import writing its own failures to the same path doesn't truncate it first.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let letters = DeadLetterFile::read(&path)?;
+        Ok(Self { path, letters })
+    }
+
+    /// The raw bytes of the records of the given kind, in the order they were written
+    fn raw(&self, kind: &str) -> Result<Vec<Vec<u8>>> {
+        self.letters
+            .iter()
+            .filter(|letter| letter.kind == kind)
+            .map(DeadLetter::raw_bytes)
+            .collect()
+    }
+}
+
+impl BlockSource for DeadLetterSource {
+    fn describe(&self) -> String {
+        format!("blocks of dead-letter file {}", self.path.display())
+    }
+
+    fn read_blocks(&mut self, ctx: &mut SourceContext<'_>) -> Result<Vec<SealedBlock>> {
+        // The records are complete rlp items, so concatenated they read like an export
+        let contents = self.raw("block")?.concat();
+        if contents.is_empty() {
+            return Ok(Vec::new())
+        }
+        ctx.progress.set_stage("decode blocks");
+        blocks::decode_blocks(
+            &contents,
+            ctx.dead_letter.as_deref_mut(),
+            ctx.validator.as_deref_mut(),
+            Some(ctx.progress),
+        )
+    }
+}
+
+impl ReceiptSource for DeadLetterSource {
+    fn describe(&self) -> String {
+        format!("receipts of dead-letter file {}", self.path.display())
+    }
+
+    fn read_receipts(&mut self, ctx: &mut SourceContext<'_>) -> Result<Vec<Receipt>> {
+        let raw = self.raw("receipt")?;
+        if raw.is_empty() {
+            return Ok(Vec::new())
+        }
+        // Rebuild the layout of a receipt export: a list of the receipts after a leading byte
+        let mut list = rlp::RlpStream::new_list(raw.len());
+        for receipt in &raw {
+            list.append_raw(receipt, 1);
+        }
+        let mut data = vec![0];
+        data.extend_from_slice(&list.out());
+        ctx.progress.set_stage("decode receipts");
+        Receipt::from_bytes_with(&data, ctx.dead_letter.as_deref_mut(), Some(ctx.progress))
+    }
+}
+
+impl StateSource for DeadLetterSource {
+    fn describe(&self) -> String {
+        format!("accounts of dead-letter file {}", self.path.display())
+    }
+
+    fn read_state(&mut self, ctx: &mut SourceContext<'_>) -> Result<State> {
+        ctx.progress.set_stage("decode state");
+        let mut state = State::new();
+        // Every account is recorded as an `alloc` object holding only that account
+        for raw in self.raw("account")? {
+            state.extend(serde_json::from_slice::<State>(&raw)?);
+        }
+        Ok(state)
+    }
+}
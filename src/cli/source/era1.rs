@@ -0,0 +1,119 @@
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use eyre::Result;
+use reth_primitives::SealedBlock;
+
+use crate::cli::{
+    blocks::{self, BlockFormat},
+    dirs,
+    source::{assemble_block, BlockSource, SourceContext},
+};
+
+/// The size of the header of an e2store entry: the type, the data length and a reserved field
+const ENTRY_HEADER_LEN: usize = 8;
+
+/// The type of the entries holding a snappy-compressed header
+pub const COMPRESSED_HEADER: [u8; 2] = [0x03, 0x00];
+
+/// The type of the entries holding a snappy-compressed body
+pub const COMPRESSED_BODY: [u8; 2] = [0x04, 0x00];
+
+/// Reads the blocks of an era1 archive, or of all archives in a directory in the order of their
+/// file names
+#[derive(Debug, Clone)]
+pub struct Era1Source {
+    /// The archive or the directory of archives
+    path: PathBuf,
+}
+
+impl Era1Source {
+    /// Creates a source reading the archive or the directory of archives at `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The archives to read, in the order of their file names
+    fn archives(&self) -> Result<Vec<PathBuf>> {
+        if !self.path.is_dir() {
+            return Ok(vec![self.path.clone()])
+        }
+        let mut archives = Vec::new();
+        for entry in fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            if dirs::has_extension(&path, "era1") {
+                archives.push(path);
+            }
+        }
+        archives.sort();
+        Ok(archives)
+    }
+}
+
+impl BlockSource for Era1Source {
+    fn describe(&self) -> String {
+        format!("era1 {}", self.path.display())
+    }
+
+    fn read_blocks(&mut self, ctx: &mut SourceContext<'_>) -> Result<Vec<SealedBlock>> {
+        ctx.progress.set_stage("read blocks");
+        let mut contents = Vec::new();
+        for archive in self.archives()? {
+            let data = ctx.args.read_input(&archive.display().to_string(), ctx.limiter)?;
+            let data = ctx.args.decrypt(&archive, data)?;
+            for (header, body) in read_archive(&archive, &data)? {
+                contents.extend_from_slice(&assemble_block(&header, &body)?);
+            }
+        }
+
+        ctx.progress.set_stage("decode blocks");
+        blocks::decode_blocks_as(
+            BlockFormat::Geth,
+            &contents,
+            ctx.dead_letter.as_deref_mut(),
+            ctx.validator.as_deref_mut(),
+            Some(ctx.progress),
+        )
+    }
+}
+
+/// Returns the decompressed headers and bodies of the blocks in the given era1 archive. The
+/// receipts, total difficulties and indices of the archive are skipped.
+pub fn read_archive(path: &Path, data: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let mut blocks = Vec::new();
+    let mut header = None;
+    let mut offset = 0;
+    while offset < data.len() {
+        let Some(entry_header) = data.get(offset..offset + ENTRY_HEADER_LEN) else {
+            eyre::bail!("The e2store entry at offset {offset} of {} is truncated", path.display())
+        };
+        let len = u32::from_le_bytes(entry_header[2..6].try_into()?) as usize;
+        let start = offset + ENTRY_HEADER_LEN;
+        let Some(entry) = data.get(start..start + len) else {
+            eyre::bail!("The e2store entry at offset {offset} of {} is truncated", path.display())
+        };
+        offset = start + len;
+
+        match [entry_header[0], entry_header[1]] {
+            COMPRESSED_HEADER => header = Some(decompress(entry)?),
+            COMPRESSED_BODY => {
+                let Some(header) = header.take() else {
+                    eyre::bail!("Body without header at offset {offset} of {}", path.display())
+                };
+                blocks.push((header, decompress(entry)?));
+            }
+            _ => {}
+        }
+    }
+    Ok(blocks)
+}
+
+/// Decompresses the data of an entry, compressed in the snappy framing format
+fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    snap::read::FrameDecoder::new(data).read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+}
@@ -0,0 +1,428 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use ciborium::value::Value;
+use eyre::Result;
+use reth_libmdbx::{Environment, EnvironmentFlags, Mode, NoWriteMap, Transaction, RO};
+use reth_primitives::{
+    rpc::{H160, H256 as RpcH256},
+    Address, SealedBlock, H256, U256,
+};
+
+use crate::cli::{
+    blocks::{self, BlockFormat},
+    receipts::{Receipt, ReceiptLog},
+    source::{
+        derive_receipts, BlockSource, ReceiptSource, SourceContext, StateSource, StoredReceipt,
+    },
+    state::{
+        formats::{self, AccountVisitor},
+        ExportedAccount, State,
+    },
+};
+
+/// The canonical block hash of every block number
+pub const CANONICAL_HEADERS: &str = "CanonicalHeader";
+/// The rlp-encoded headers, keyed by block number and hash
+pub const HEADERS: &str = "Header";
+/// The bodies of the blocks, keyed by block number and hash, locating their transactions
+pub const BLOCK_BODIES: &str = "BlockBody";
+/// The transactions of all blocks, keyed by transaction id
+pub const TRANSACTIONS: &str = "BlockTransaction";
+/// The CBOR-encoded receipts of every block, keyed by block number
+pub const RECEIPTS: &str = "Receipt";
+/// The CBOR-encoded logs of every transaction, keyed by block number and transaction index
+pub const LOGS: &str = "TransactionLog";
+/// The accounts, and the storage of every contract incarnation as duplicate values
+pub const PLAIN_STATE: &str = "PlainState";
+/// The bytecode of the contracts, keyed by code hash
+pub const CODE: &str = "Code";
+/// The progress of every stage of the Erigon sync
+pub const SYNC_STAGES: &str = "SyncStage";
+
+/// The number of named tables the Erigon database is opened with
+const MAX_TABLES: usize = 256;
+
+/// Reads the canonical chain, its receipts and the world state from the database of an Erigon
+/// node, without export files.
+///
+/// The database is opened read-only. The blocks are read up to the progress of the execution
+/// stage, so the state matches the last block read.
+#[derive(Debug, Clone)]
+pub struct ErigonSource {
+    /// The `chaindata` directory holding the database
+    chaindata: PathBuf,
+}
+
+impl ErigonSource {
+    /// Creates a source reading the database of the Erigon data directory `datadir`, or the
+    /// database in `datadir` itself if it has no `chaindata` directory
+    pub fn new(datadir: impl Into<PathBuf>) -> Self {
+        let datadir = datadir.into();
+        let nested = datadir.join("chaindata");
+        Self { chaindata: if nested.is_dir() { nested } else { datadir } }
+    }
+
+    /// The directory holding the database
+    pub fn chaindata(&self) -> &Path {
+        &self.chaindata
+    }
+
+    fn open(&self) -> Result<Environment<NoWriteMap>> {
+        Environment::new()
+            .set_max_dbs(MAX_TABLES)
+            .set_flags(EnvironmentFlags { mode: Mode::ReadOnly, ..Default::default() })
+            .open(&self.chaindata)
+            .map_err(|err| {
+                eyre::eyre!(
+                    "Unable to open the Erigon database in {}: {err}",
+                    self.chaindata.display()
+                )
+            })
+    }
+}
+
+impl BlockSource for ErigonSource {
+    fn describe(&self) -> String {
+        format!("erigon {}", self.chaindata.display())
+    }
+
+    fn read_blocks(&mut self, ctx: &mut SourceContext<'_>) -> Result<Vec<SealedBlock>> {
+        ctx.progress.set_stage("read blocks");
+        let env = self.open()?;
+        let tx = env.begin_ro_txn()?;
+        let mut contents = Vec::new();
+        for number in block_range(&tx)? {
+            let block = read_block(&tx, number)?;
+            if let Some(limiter) = ctx.limiter {
+                limiter.consume(block.len() as u64);
+            }
+            contents.extend_from_slice(&block);
+        }
+
+        ctx.progress.set_stage("decode blocks");
+        blocks::decode_blocks_as(
+            BlockFormat::Geth,
+            &contents,
+            ctx.dead_letter.as_deref_mut(),
+            ctx.validator.as_deref_mut(),
+            Some(ctx.progress),
+        )
+    }
+}
+
+impl ReceiptSource for ErigonSource {
+    fn describe(&self) -> String {
+        format!("erigon {}", self.chaindata.display())
+    }
+
+    fn read_receipts(&mut self, ctx: &mut SourceContext<'_>) -> Result<Vec<Receipt>> {
+        ctx.progress.set_stage("read receipts");
+        let env = self.open()?;
+        let tx = env.begin_ro_txn()?;
+        let range = block_range(&tx)?;
+        ctx.progress.set_total(range.end() - range.start() + 1);
+        let mut receipts = Vec::new();
+        for number in range {
+            // The receipts are stored without the fields derived from their block
+            let block = blocks::decode_block(BlockFormat::Geth, &read_block(&tx, number)?)
+                .map_err(|err| eyre::eyre!("Block {number} can not be decoded: {err}"))?;
+            let stored = read_stored_receipts(&tx, &block)?;
+            if let Some(limiter) = ctx.limiter {
+                limiter.consume(stored.iter().map(|receipt| receipt.logs.len() as u64 + 32).sum());
+            }
+            receipts.extend(derive_receipts(&block, stored)?);
+            ctx.progress.set_block(number);
+            ctx.progress.advance(1)?;
+        }
+        Ok(receipts)
+    }
+}
+
+impl StateSource for ErigonSource {
+    fn describe(&self) -> String {
+        format!("erigon {}", self.chaindata.display())
+    }
+
+    fn read_state(&mut self, ctx: &mut SourceContext<'_>) -> Result<State> {
+        formats::collect_state(|visit| self.stream_state(ctx, visit))
+    }
+
+    fn stream_state(
+        &mut self,
+        ctx: &mut SourceContext<'_>,
+        visit: &mut AccountVisitor<'_>,
+    ) -> Result<()> {
+        ctx.progress.set_stage("read state");
+        let env = self.open()?;
+        let tx = env.begin_ro_txn()?;
+        let table = tx.open_db(Some(PLAIN_STATE))?;
+        let mut cursor = tx.cursor(&table)?;
+        // The account being read with its incarnation, until the storage following it is read
+        let mut current: Option<(Address, u64, ExportedAccount)> = None;
+        for entry in cursor.iter_start::<Vec<u8>, Vec<u8>>() {
+            let (key, value) = entry?;
+            if let Some(limiter) = ctx.limiter {
+                limiter.consume((key.len() + value.len()) as u64);
+            }
+            match key.len() {
+                20 => {
+                    if let Some((address, _, account)) = current.take() {
+                        visit(address, account)?;
+                        ctx.progress.advance(1)?;
+                    }
+                    let address = Address::from_slice(&key);
+                    let (mut account, incarnation) = decode_account(&value).map_err(|err| {
+                        eyre::eyre!("Account {address:?} can not be decoded: {err}")
+                    })?;
+                    if let Some(hash) = account.code_hash {
+                        let Some(code) = get(&tx, CODE, hash.as_bytes())? else {
+                            eyre::bail!("The code of account {address:?} is missing")
+                        };
+                        account.code = Some(format!("0x{}", hex::encode(code)));
+                    }
+                    current = Some((address, incarnation, account));
+                }
+                // Storage is keyed by address and incarnation, holding the slot and its value
+                28 => {
+                    let Some((address, incarnation, account)) = &mut current else { continue };
+                    // Storage of earlier incarnations of self-destructed contracts is stale
+                    if key[..20] != address.0 || key[20..] != incarnation.to_be_bytes() {
+                        continue
+                    }
+                    if value.len() < 32 {
+                        eyre::bail!("A storage slot of account {address:?} is truncated");
+                    }
+                    let slot = H256::from_slice(&value[..32]);
+                    let value = U256::try_from_be_slice(&value[32..]).ok_or_else(|| {
+                        eyre::eyre!("Storage slot {slot:?} of account {address:?} overflows")
+                    })?;
+                    account.storage.get_or_insert_with(BTreeMap::new).insert(slot, value);
+                }
+                len => eyre::bail!("Unexpected key of {len} bytes in the {PLAIN_STATE} table"),
+            }
+        }
+        if let Some((address, _, account)) = current {
+            visit(address, account)?;
+            ctx.progress.advance(1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a value of the given table
+fn get(tx: &Transaction<'_, RO, NoWriteMap>, table: &str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+    let db = tx.open_db(Some(table))?;
+    Ok(tx.get(&db, key)?)
+}
+
+/// The canonical blocks to read: from the first canonical block up to the progress of the
+/// execution stage, or the last canonical block if the stage never ran
+fn block_range(tx: &Transaction<'_, RO, NoWriteMap>) -> Result<std::ops::RangeInclusive<u64>> {
+    let table = tx.open_db(Some(CANONICAL_HEADERS))?;
+    let mut cursor = tx.cursor(&table)?;
+    let (Some((first, _)), Some((last, _))) =
+        (cursor.first::<Vec<u8>, Vec<u8>>()?, cursor.last::<Vec<u8>, Vec<u8>>()?)
+    else {
+        eyre::bail!("The Erigon database holds no canonical blocks")
+    };
+    let last = match get(tx, SYNC_STAGES, b"Execution")? {
+        Some(progress) => be_u64(&progress)?,
+        None => be_u64(&last)?,
+    };
+    Ok(be_u64(&first)?..=last)
+}
+
+/// Assembles the canonical block with the given number in the standard devp2p encoding.
+///
+/// Bodies locate their transactions by the id of the first one and their count, which includes a
+/// system transaction reserved before and after the transactions of every block.
+pub fn read_block(tx: &Transaction<'_, RO, NoWriteMap>, number: u64) -> Result<Vec<u8>> {
+    let Some(hash) = get(tx, CANONICAL_HEADERS, &number.to_be_bytes())? else {
+        eyre::bail!("Block {number} is missing from the canonical chain of the Erigon database")
+    };
+    let key = [&number.to_be_bytes()[..], &hash].concat();
+    let header = get(tx, HEADERS, &key)?
+        .ok_or_else(|| eyre::eyre!("The header of block {number} is missing"))?;
+    let body = get(tx, BLOCK_BODIES, &key)?
+        .ok_or_else(|| eyre::eyre!("The body of block {number} is missing"))?;
+    let body = rlp::Rlp::new(&body);
+    let base_tx_id: u64 = body.val_at(0)?;
+    let count = body.val_at::<u32>(1)?.saturating_sub(2) as u64;
+
+    let mut transactions = rlp::RlpStream::new_list(count as usize);
+    for id in base_tx_id + 1..=base_tx_id + count {
+        let Some(transaction) = get(tx, TRANSACTIONS, &id.to_be_bytes())? else {
+            eyre::bail!("Transaction {id} of block {number} is missing")
+        };
+        // Typed transactions are stored as their envelope, which blocks embed as a string
+        if transaction.first().map_or(false, |byte| *byte >= 0xc0) {
+            transactions.append_raw(&transaction, 1);
+        } else {
+            transactions.append(&transaction);
+        }
+    }
+
+    let mut block = rlp::RlpStream::new_list(3);
+    block.append_raw(&header, 1);
+    block.append_raw(&transactions.out(), 1);
+    block.append_raw(body.at(2)?.as_raw(), 1);
+    Ok(block.out().to_vec())
+}
+
+/// Reads the receipts Erigon stores for the given block and the logs of its transactions.
+///
+/// Receipts are stored in Erigon's CBOR encoding, holding the post state, the status and the
+/// cumulative gas used, and their logs separately per transaction. The L1 fee fields are not part
+/// of it.
+pub fn read_stored_receipts(
+    tx: &Transaction<'_, RO, NoWriteMap>,
+    block: &SealedBlock,
+) -> Result<Vec<StoredReceipt>> {
+    let Some(data) = get(tx, RECEIPTS, &block.number.to_be_bytes())? else {
+        if block.body.is_empty() {
+            return Ok(vec![])
+        }
+        eyre::bail!("The receipts of block {} are missing, were they pruned?", block.number)
+    };
+    let Value::Array(stored) = ciborium::de::from_reader(data.as_slice())? else {
+        eyre::bail!("The receipts of block {} are not a CBOR array", block.number)
+    };
+
+    let mut receipts = Vec::with_capacity(stored.len());
+    for (index, receipt) in stored.iter().enumerate() {
+        let invalid =
+            |field| eyre::eyre!("Receipt {index} of block {} has no valid {field}", block.number);
+        let post_state = field(receipt, 1, "1").and_then(cbor_bytes).unwrap_or_default();
+        let status = field(receipt, 2, "2").and_then(cbor_u64).ok_or_else(|| invalid("status"))?;
+        let cumulative_gas_used = field(receipt, 3, "3")
+            .and_then(cbor_u64)
+            .ok_or_else(|| invalid("cumulative gas used"))?;
+
+        let key = [&block.number.to_be_bytes()[..], &(index as u32).to_be_bytes()].concat();
+        let logs = match get(tx, LOGS, &key)? {
+            Some(data) => decode_logs(&ciborium::de::from_reader(data.as_slice())?)
+                .ok_or_else(|| invalid("logs"))?,
+            None => vec![],
+        };
+
+        receipts.push(StoredReceipt {
+            status_or_root: match (post_state.is_empty(), status) {
+                (false, _) => post_state,
+                (true, 1) => vec![1],
+                (true, _) => vec![],
+            },
+            cumulative_gas_used,
+            logs: rlp::encode_list::<ReceiptLog, _>(&logs).to_vec(),
+            ..Default::default()
+        });
+    }
+    Ok(receipts)
+}
+
+/// Decodes the logs of a transaction from their CBOR encoding
+fn decode_logs(value: &Value) -> Option<Vec<ReceiptLog>> {
+    let Value::Array(logs) = value else { return None };
+    logs.iter()
+        .map(|log| {
+            let address = field(log, 0, "1").and_then(cbor_bytes).filter(|a| a.len() == 20)?;
+            let Value::Array(topics) = field(log, 1, "2")? else { return None };
+            let topics = topics
+                .iter()
+                .map(|topic| {
+                    cbor_bytes(topic).filter(|t| t.len() == 32).map(|t| RpcH256::from_slice(&t))
+                })
+                .collect::<Option<Vec<_>>>()?;
+            let data = field(log, 2, "3").and_then(cbor_bytes).unwrap_or_default();
+            Some(ReceiptLog { address: H160::from_slice(&address), topics, data })
+        })
+        .collect()
+}
+
+/// Decodes an account in Erigon's storage encoding, returning it with its incarnation.
+///
+/// The encoding starts with a bitmask of the fields present, followed by the nonce, the balance,
+/// the incarnation and the code hash, each prefixed with its length in bytes.
+pub fn decode_account(data: &[u8]) -> Result<(ExportedAccount, u64)> {
+    let mut account = ExportedAccount { nonce: Some(0), ..Default::default() };
+    let mut incarnation = 0;
+    let Some((&fields, mut rest)) = data.split_first() else { return Ok((account, incarnation)) };
+    if fields & 1 != 0 {
+        account.nonce = Some(be_uint(take_field(&mut rest)?)?);
+    }
+    if fields & 2 != 0 {
+        account.balance = U256::try_from_be_slice(take_field(&mut rest)?)
+            .ok_or_else(|| eyre::eyre!("The balance overflows"))?;
+    }
+    if fields & 4 != 0 {
+        incarnation = be_uint(take_field(&mut rest)?)?;
+    }
+    if fields & 8 != 0 {
+        let hash = take_field(&mut rest)?;
+        if hash.len() != 32 {
+            eyre::bail!("The code hash has {} bytes", hash.len());
+        }
+        account.code_hash = Some(H256::from_slice(hash));
+    }
+    Ok((account, incarnation))
+}
+
+/// Takes a field prefixed with its length in bytes from the start of `data`
+fn take_field<'a>(data: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let Some((&len, rest)) = data.split_first() else { eyre::bail!("The account is truncated") };
+    if rest.len() < len as usize {
+        eyre::bail!("The account is truncated");
+    }
+    let (field, rest) = rest.split_at(len as usize);
+    *data = rest;
+    Ok(field)
+}
+
+/// Decodes a big-endian integer of up to 8 bytes
+fn be_uint(bytes: &[u8]) -> Result<u64> {
+    if bytes.len() > 8 {
+        eyre::bail!("Integer of {} bytes overflows", bytes.len());
+    }
+    Ok(bytes.iter().fold(0, |value, byte| value << 8 | *byte as u64))
+}
+
+/// Decodes a big-endian block number key or value
+fn be_u64(bytes: &[u8]) -> Result<u64> {
+    let bytes =
+        bytes.get(..8).ok_or_else(|| eyre::eyre!("Block number of {} bytes", bytes.len()))?;
+    Ok(u64::from_be_bytes(bytes.try_into()?))
+}
+
+/// A field of a CBOR-encoded struct, which Erigon encodes as an array of its fields, or as a map
+/// keyed by the codec tags of the fields
+fn field<'a>(value: &'a Value, position: usize, tag: &str) -> Option<&'a Value> {
+    match value {
+        Value::Array(fields) => fields.get(position),
+        Value::Map(entries) => entries
+            .iter()
+            .find(|(key, _)| match key {
+                Value::Text(key) => key == tag,
+                Value::Integer(key) => i128::from(*key).to_string() == tag,
+                _ => false,
+            })
+            .map(|(_, value)| value),
+        _ => None,
+    }
+}
+
+fn cbor_bytes(value: &Value) -> Option<Vec<u8>> {
+    match value {
+        Value::Bytes(bytes) => Some(bytes.clone()),
+        Value::Null => Some(vec![]),
+        _ => None,
+    }
+}
+
+fn cbor_u64(value: &Value) -> Option<u64> {
+    match value {
+        Value::Integer(value) => u64::try_from(i128::from(*value)).ok(),
+        _ => None,
+    }
+}
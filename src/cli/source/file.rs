@@ -0,0 +1,167 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use eyre::Result;
+use reth_primitives::SealedBlock;
+
+use crate::cli::{
+    blocks::{self, BlockFormat},
+    compression, encryption,
+    receipts::Receipt,
+    source::{mapped::Contents, BlockSource, ReceiptSource, SourceContext, StateSource},
+    state::{
+        formats::{self, AccountVisitor, StateFormat},
+        State,
+    },
+    throttle::{self, ThrottledReader},
+};
+
+/// Reads the exports and dumps written by Erigon and geth from a file, or from stdin if the path
+/// is `-`
+#[derive(Debug, Clone)]
+pub struct FileSource {
+    /// The path of the file
+    path: String,
+    /// The layout of a block export. Detected from its contents if not given.
+    format: Option<BlockFormat>,
+    /// The layout of a state dump. Detected from its contents if not given.
+    state_format: Option<StateFormat>,
+}
+
+impl FileSource {
+    /// Creates a source reading the file at `path`
+    pub fn new(path: impl Into<String>) -> Self {
+        Self { path: path.into(), format: None, state_format: None }
+    }
+
+    /// Decodes a block export in the given layout instead of detecting it
+    pub fn with_format(mut self, format: Option<BlockFormat>) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Decodes a state dump in the given layout instead of detecting it
+    pub fn with_state_format(mut self, format: Option<StateFormat>) -> Self {
+        self.state_format = format;
+        self
+    }
+
+    /// Reads the contents of the file, verifies their checksum, decrypts them if they are
+    /// encrypted and decompresses them if they are compressed.
+    ///
+    /// With `--mmap`, files are mapped into memory rather than read, unless they are read from
+    /// stdin, so plain exports are decoded from the mapping instead of a copy of the file.
+    pub fn read(&self, ctx: &SourceContext<'_>) -> Result<Contents> {
+        let path = Path::new(&self.path);
+        let data = if ctx.args.mmap && !throttle::is_stdin(&self.path) && ctx.limiter.is_none() {
+            ctx.args.retry_policy().run("map input", || Contents::map(path))?
+        } else {
+            Contents::Owned(ctx.args.read_input(&self.path, ctx.limiter)?)
+        };
+        ctx.progress.set_stage("verify checksum");
+        ctx.args.verify_checksum(path, &data)?;
+        let data = if encryption::is_encrypted(&data) {
+            Contents::Owned(ctx.args.decrypt(path, data.into_vec())?)
+        } else {
+            data
+        };
+        Ok(match compression::decompress_slice(path, &data)? {
+            Some(decompressed) => Contents::Owned(decompressed),
+            None => data,
+        })
+    }
+
+    /// Whether the file can be decoded while it is read. Stdin, files with a checksum to verify
+    /// and encrypted files are read whole first.
+    fn is_streamable(&self, ctx: &SourceContext<'_>) -> Result<bool> {
+        if throttle::is_stdin(&self.path) ||
+            ctx.args.checksum.is_some() ||
+            ctx.args.checksum_manifest.is_some()
+        {
+            return Ok(false)
+        }
+        let mut head = Vec::with_capacity(encryption::MAGIC.len());
+        File::open(&self.path)?.take(encryption::MAGIC.len() as u64).read_to_end(&mut head)?;
+        Ok(!encryption::is_encrypted(&head))
+    }
+
+    /// Decodes the state dump read from `reader` in the configured or the detected layout
+    fn stream_state_from(&self, reader: impl Read, visit: &mut AccountVisitor<'_>) -> Result<()> {
+        match self.state_format {
+            Some(format) => formats::stream_state_as(format, reader, visit),
+            None => formats::stream_state(reader, visit),
+        }
+    }
+}
+
+impl BlockSource for FileSource {
+    fn describe(&self) -> String {
+        self.path.clone()
+    }
+
+    fn read_blocks(&mut self, ctx: &mut SourceContext<'_>) -> Result<Vec<SealedBlock>> {
+        ctx.progress.set_stage("read blocks");
+        let contents = self.read(ctx)?;
+        ctx.progress.set_stage("decode blocks");
+        let dead_letter = ctx.dead_letter.as_deref_mut();
+        let validator = ctx.validator.as_deref_mut();
+        match self.format {
+            Some(format) => blocks::decode_blocks_as(
+                format,
+                &contents,
+                dead_letter,
+                validator,
+                Some(ctx.progress),
+            ),
+            None => blocks::decode_blocks(&contents, dead_letter, validator, Some(ctx.progress)),
+        }
+    }
+}
+
+impl ReceiptSource for FileSource {
+    fn describe(&self) -> String {
+        self.path.clone()
+    }
+
+    fn read_receipts(&mut self, ctx: &mut SourceContext<'_>) -> Result<Vec<Receipt>> {
+        ctx.progress.set_stage("read receipts");
+        let data = self.read(ctx)?;
+        ctx.progress.set_offset(data.len() as u64);
+        ctx.progress.set_stage("decode receipts");
+        Receipt::from_bytes_with(&data, ctx.dead_letter.as_deref_mut(), Some(ctx.progress))
+    }
+}
+
+impl StateSource for FileSource {
+    fn describe(&self) -> String {
+        self.path.clone()
+    }
+
+    fn read_state(&mut self, ctx: &mut SourceContext<'_>) -> Result<State> {
+        formats::collect_state(|visit| self.stream_state(ctx, visit))
+    }
+
+    fn stream_state(
+        &mut self,
+        ctx: &mut SourceContext<'_>,
+        visit: &mut AccountVisitor<'_>,
+    ) -> Result<()> {
+        if !self.is_streamable(ctx)? {
+            ctx.progress.set_stage("read state");
+            let data = self.read(ctx)?;
+            ctx.progress.set_offset(data.len() as u64);
+            ctx.progress.set_stage("decode state");
+            return self.stream_state_from(&data[..], visit)
+        }
+        let file = File::open(&self.path)?;
+        let file: Box<dyn Read + '_> = match ctx.limiter {
+            Some(limiter) => Box::new(ThrottledReader::new(file, limiter)),
+            None => Box::new(file),
+        };
+        let reader = compression::reader(Path::new(&self.path), BufReader::new(file))?;
+        self.stream_state_from(reader, visit)
+    }
+}
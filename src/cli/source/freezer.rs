@@ -0,0 +1,235 @@
+use std::{
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use eyre::Result;
+use reth_primitives::SealedBlock;
+
+use crate::cli::{
+    blocks::{self, BlockFormat},
+    receipts::Receipt,
+    source::{
+        assemble_block, derive_receipts, BlockSource, ReceiptSource, SourceContext, StoredReceipt,
+    },
+};
+
+/// The size of an entry of a freezer index: the data file number and the end offset of the item
+const INDEX_ENTRY_LEN: usize = 6;
+
+/// Whether `path` is a geth ancient store (freezer) directory, holding the index of its `headers`
+/// table
+pub fn is_freezer(path: &Path) -> bool {
+    path.join("headers.cidx").is_file() || path.join("headers.ridx").is_file()
+}
+
+/// Reads the blocks of a geth ancient store (freezer) directory from its `headers` and `bodies`
+/// tables, and their receipts from its `receipts` table
+#[derive(Debug, Clone)]
+pub struct FreezerSource {
+    /// The freezer directory
+    dir: PathBuf,
+}
+
+impl FreezerSource {
+    /// Creates a source reading the freezer in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl BlockSource for FreezerSource {
+    fn describe(&self) -> String {
+        format!("freezer {}", self.dir.display())
+    }
+
+    fn read_blocks(&mut self, ctx: &mut SourceContext<'_>) -> Result<Vec<SealedBlock>> {
+        ctx.progress.set_stage("read blocks");
+        let mut headers = FreezerTable::open(&self.dir, "headers")?;
+        let mut bodies = FreezerTable::open(&self.dir, "bodies")?;
+        if headers.first() != bodies.first() || headers.len() != bodies.len() {
+            eyre::bail!(
+                "The headers and bodies of {} cover different blocks, was it pruned?",
+                self.dir.display()
+            );
+        }
+
+        let mut contents = Vec::new();
+        for item in 0..headers.len() {
+            let block = assemble_block(&headers.item(item)?, &bodies.item(item)?)?;
+            if let Some(limiter) = ctx.limiter {
+                limiter.consume(block.len() as u64);
+            }
+            contents.extend_from_slice(&block);
+        }
+
+        ctx.progress.set_stage("decode blocks");
+        blocks::decode_blocks_as(
+            BlockFormat::Geth,
+            &contents,
+            ctx.dead_letter.as_deref_mut(),
+            ctx.validator.as_deref_mut(),
+            Some(ctx.progress),
+        )
+    }
+}
+
+impl ReceiptSource for FreezerSource {
+    fn describe(&self) -> String {
+        format!("freezer {}", self.dir.display())
+    }
+
+    fn read_receipts(&mut self, ctx: &mut SourceContext<'_>) -> Result<Vec<Receipt>> {
+        ctx.progress.set_stage("read receipts");
+        let mut headers = FreezerTable::open(&self.dir, "headers")?;
+        let mut bodies = FreezerTable::open(&self.dir, "bodies")?;
+        let mut stored = FreezerTable::open(&self.dir, "receipts")?;
+        let first = headers.first();
+        if [bodies.first(), stored.first()] != [first; 2] ||
+            [bodies.len(), stored.len()] != [headers.len(); 2]
+        {
+            eyre::bail!(
+                "The headers, bodies and receipts of {} cover different blocks, was it pruned?",
+                self.dir.display()
+            );
+        }
+
+        ctx.progress.set_total(headers.len());
+        let mut receipts = Vec::new();
+        for item in 0..headers.len() {
+            // The receipts are stored without the fields derived from their block
+            let block = assemble_block(&headers.item(item)?, &bodies.item(item)?)?;
+            let block = blocks::decode_block(BlockFormat::Geth, &block).map_err(|err| {
+                eyre::eyre!("Block {} of the freezer can not be decoded: {err}", first + item)
+            })?;
+            let data = stored.item(item)?;
+            if let Some(limiter) = ctx.limiter {
+                limiter.consume(data.len() as u64);
+            }
+            receipts.extend(decode_stored_receipts(&block, &data)?);
+            ctx.progress.set_block(block.number);
+            ctx.progress.advance(1)?;
+        }
+        Ok(receipts)
+    }
+}
+
+/// Decodes the receipts the freezer stores for the given block, filling in the fields derived from
+/// the block.
+///
+/// Receipts are stored as `[status, cumulative gas used, logs]`, which l2geth extends with the L1
+/// gas used, the L1 gas price, the L1 fee and the fee scalar.
+pub fn decode_stored_receipts(block: &SealedBlock, data: &[u8]) -> Result<Vec<Receipt>> {
+    let mut receipts = Vec::new();
+    for (index, stored) in rlp::Rlp::new(data).iter().enumerate() {
+        let (l1_gas_used, l1_gas_price, l1_fee, l1_fee_scalar) = match stored.item_count()? {
+            3 => Default::default(),
+            7 => (stored.val_at(3)?, stored.val_at(4)?, stored.val_at(5)?, stored.val_at(6)?),
+            fields => eyre::bail!(
+                "Receipt {index} of block {} has {fields} fields, only the layouts of geth and \
+                 l2geth are supported",
+                block.number
+            ),
+        };
+        receipts.push(StoredReceipt {
+            status_or_root: stored.val_at(0)?,
+            cumulative_gas_used: stored.val_at(1)?,
+            logs: stored.at(2)?.as_raw().to_vec(),
+            l1_gas_used,
+            l1_gas_price,
+            l1_fee,
+            l1_fee_scalar,
+        });
+    }
+    derive_receipts(block, receipts)
+}
+
+/// A table of the freezer: an index of item offsets and the data files holding the items,
+/// snappy-compressed unless the table is raw
+#[derive(Debug)]
+pub struct FreezerTable {
+    /// The directory of the table
+    dir: PathBuf,
+    /// The name of the table
+    name: String,
+    /// Whether the items are snappy-compressed
+    compressed: bool,
+    /// The data file number and the end offset of every item, following the entry locating the
+    /// first item
+    index: Vec<(u32, u64)>,
+    /// The data file read last
+    file: Option<(u32, File)>,
+}
+
+impl FreezerTable {
+    /// Opens the table with the given name in the freezer `dir`
+    pub fn open(dir: &Path, name: &str) -> Result<Self> {
+        let (compressed, index_path) = match dir.join(format!("{name}.cidx")) {
+            path if path.exists() => (true, path),
+            _ => (false, dir.join(format!("{name}.ridx"))),
+        };
+        let data = fs::read(&index_path)
+            .map_err(|err| eyre::eyre!("Unable to read {}: {err}", index_path.display()))?;
+        if data.len() < INDEX_ENTRY_LEN || data.len() % INDEX_ENTRY_LEN != 0 {
+            eyre::bail!("The freezer index {} is corrupt", index_path.display());
+        }
+        let index = data
+            .chunks(INDEX_ENTRY_LEN)
+            .map(|entry| {
+                let file = u16::from_be_bytes([entry[0], entry[1]]) as u32;
+                let offset = u32::from_be_bytes([entry[2], entry[3], entry[4], entry[5]]) as u64;
+                (file, offset)
+            })
+            .collect();
+        Ok(Self { dir: dir.to_path_buf(), name: name.to_string(), compressed, index, file: None })
+    }
+
+    /// The number of the first item, after the items deleted from the tail of the table
+    pub fn first(&self) -> u64 {
+        self.index[0].1
+    }
+
+    /// The number of items in the table
+    pub fn len(&self) -> u64 {
+        self.index.len() as u64 - 1
+    }
+
+    /// Whether the table holds no items
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reads the item at the given position, counted from the first item
+    pub fn item(&mut self, position: u64) -> Result<Vec<u8>> {
+        let position = position as usize;
+        let (Some(&(start_file, start)), Some(&(file, end))) =
+            (self.index.get(position), self.index.get(position + 1))
+        else {
+            eyre::bail!("Item {position} is out of the bounds of the freezer table {}", self.name)
+        };
+        // The first entry holds the tail instead of an offset, and items never span files
+        let start = if position == 0 || start_file != file { 0 } else { start };
+
+        let mut data = vec![0; end.saturating_sub(start) as usize];
+        let data_file = self.data_file(file)?;
+        data_file.seek(SeekFrom::Start(start))?;
+        data_file.read_exact(&mut data)?;
+        if self.compressed {
+            data = snap::raw::Decoder::new().decompress_vec(&data)?;
+        }
+        Ok(data)
+    }
+
+    /// Opens the data file with the given number, reusing the one read last
+    fn data_file(&mut self, number: u32) -> Result<&mut File> {
+        if self.file.as_ref().map_or(true, |(open, _)| *open != number) {
+            let extension = if self.compressed { "cdat" } else { "rdat" };
+            let path = self.dir.join(format!("{}.{number:04}.{extension}", self.name));
+            let file = File::open(&path)
+                .map_err(|err| eyre::eyre!("Unable to open {}: {err}", path.display()))?;
+            self.file = Some((number, file));
+        }
+        Ok(&mut self.file.as_mut().expect("data file opened").1)
+    }
+}
@@ -0,0 +1,62 @@
+use std::{fs::File, ops::Deref, path::Path};
+
+use eyre::Result;
+use memmap2::Mmap;
+
+/// The contents of an input file. With `--mmap`, files read as they are on disk are mapped into
+/// memory, so their pages are loaded on demand. The decoders copy the records they decode out of
+/// the contents either way, so decoding is not zero-copy. Files read without `--mmap` and
+/// contents that had to be decrypted, decompressed or read through a rate limiter are held in
+/// memory.
+#[derive(Debug)]
+pub enum Contents {
+    /// The file, mapped read-only into memory
+    Mapped(Mmap),
+    /// The contents, read into memory
+    Owned(Vec<u8>),
+}
+
+impl Contents {
+    /// Maps the file at `path` read-only into memory. Empty files can't be mapped and are
+    /// returned as empty contents.
+    ///
+    /// Only used with `--mmap`, which leaves keeping the file intact during the import to the
+    /// operator.
+    pub fn map(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        if file.metadata()?.len() == 0 {
+            return Ok(Self::Owned(Vec::new()))
+        }
+        // SAFETY: the mapping is only read, and the operator opted into it with `--mmap`, which
+        // documents that the input must not be modified during the import. Memory safety then
+        // holds, but a file truncated by another process anyway raises SIGBUS on the next read of
+        // a page past its new end, which aborts the import instead of failing it.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(Self::Mapped(map))
+    }
+
+    /// Returns the contents as a vector, copying mapped contents into memory
+    pub fn into_vec(self) -> Vec<u8> {
+        match self {
+            Self::Mapped(map) => map.to_vec(),
+            Self::Owned(data) => data,
+        }
+    }
+}
+
+impl Deref for Contents {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Mapped(map) => map,
+            Self::Owned(data) => data,
+        }
+    }
+}
+
+impl AsRef<[u8]> for Contents {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
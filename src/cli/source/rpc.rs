@@ -0,0 +1,365 @@
+use std::{collections::BTreeMap, future::Future, ops::RangeInclusive, str::FromStr};
+
+use eyre::Result;
+use futures::{stream, StreamExt, TryStreamExt};
+use reth_primitives::{
+    rpc::{H160, H256},
+    Address, Bytes, SealedBlock, U256,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+use crate::cli::{
+    blocks::{self, BlockFormat},
+    receipts::{Receipt, ReceiptLog},
+    retry::RetryPolicy,
+    source::{BlockSource, ReceiptSource, SourceContext, StateSource},
+    state::{ExportedAccount, State},
+};
+
+/// Reads a range of blocks, their receipts and the state at the end of the range from the
+/// JSON-RPC API of a legacy node, such as l2geth.
+///
+/// Blocks are read with `debug_getBlockRlp`, receipts with `eth_getTransactionReceipt` and the
+/// state with `debug_dumpBlock`. Blocks and their receipts are requested for several blocks at a
+/// time, see [RpcSource::with_concurrency], and failed requests are retried according to the
+/// retry policy of the import. The requests block the calling thread, so the source has to be
+/// read on a multi-threaded tokio runtime.
+#[derive(Debug, Clone)]
+pub struct RpcSource {
+    /// The URL of the JSON-RPC endpoint
+    url: String,
+    /// The blocks to read
+    range: RangeInclusive<u64>,
+    /// The number of blocks requested at a time
+    concurrency: usize,
+    /// The HTTP client
+    client: reqwest::Client,
+}
+
+impl RpcSource {
+    /// Creates a source reading the given range of blocks from the endpoint at `url`
+    pub fn new(url: impl Into<String>, range: RangeInclusive<u64>) -> Self {
+        Self { url: url.into(), range, concurrency: 1, client: reqwest::Client::new() }
+    }
+
+    /// Requests the blocks and receipts of up to `concurrency` blocks at a time, one by default
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Calls `method` with the given parameters, failing if the node returns an error or no
+    /// result
+    fn request<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        block_on(self.call(method, params))
+    }
+
+    /// Calls `method` once, see [RpcSource::request]
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response: Value = self
+            .client
+            .post(&self.url)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if let Some(error) = response.get("error") {
+            eyre::bail!("{method} failed: {error}");
+        }
+        match response.get("result") {
+            None | Some(Value::Null) => eyre::bail!("{method} returned no result"),
+            Some(result) => Ok(serde_json::from_value(result.clone())?),
+        }
+    }
+
+    /// Calls `method`, retrying transient failures according to `retry`
+    async fn call_retrying<T: DeserializeOwned>(
+        &self,
+        retry: &RetryPolicy,
+        method: &str,
+        params: Value,
+    ) -> Result<T> {
+        retry.run_async(method, || self.call(method, params.clone())).await
+    }
+
+    /// Reads the hash of the block with the given number and the hashes of its transactions
+    pub fn block_hashes(&self, number: u64) -> Result<(H256, Vec<H256>)> {
+        let block: RpcBlock =
+            self.request("eth_getBlockByNumber", json!([quantity(number), false]))?;
+        Ok((block.hash, block.transactions))
+    }
+
+    /// Runs `fetch` for every block of the range, [RpcSource::with_concurrency] blocks at a time,
+    /// and passes the results to `visit` in block order
+    fn fetch_blocks<T, F>(
+        &self,
+        fetch: impl Fn(u64) -> F,
+        mut visit: impl FnMut(u64, T) -> Result<()>,
+    ) -> Result<()>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        block_on(async {
+            let mut fetched = stream::iter(self.range.clone())
+                .map(|number| {
+                    let fetch = fetch(number);
+                    async move { Ok::<_, eyre::Report>((number, fetch.await?)) }
+                })
+                .buffered(self.concurrency);
+            while let Some((number, value)) = fetched.try_next().await? {
+                visit(number, value)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl BlockSource for RpcSource {
+    fn describe(&self) -> String {
+        format!("{} blocks {}..={}", self.url, self.range.start(), self.range.end())
+    }
+
+    fn read_blocks(&mut self, ctx: &mut SourceContext<'_>) -> Result<Vec<SealedBlock>> {
+        ctx.progress.set_stage("read blocks");
+        let retry = ctx.args.retry_policy();
+        let mut contents = Vec::new();
+        self.fetch_blocks(
+            // geth 1.9, which l2geth forks, serves no `debug_getRawBlock` yet
+            |number| self.call_retrying(&retry, "debug_getBlockRlp", json!([number])),
+            |number, raw: String| {
+                let raw = decode_hex(&raw)?;
+                if let Some(limiter) = ctx.limiter {
+                    limiter.consume(raw.len() as u64);
+                }
+                contents.extend_from_slice(&raw);
+                ctx.progress.set_block(number);
+                Ok(())
+            },
+        )?;
+
+        ctx.progress.set_stage("decode blocks");
+        blocks::decode_blocks_as(
+            BlockFormat::Geth,
+            &contents,
+            ctx.dead_letter.as_deref_mut(),
+            ctx.validator.as_deref_mut(),
+            Some(ctx.progress),
+        )
+    }
+}
+
+impl ReceiptSource for RpcSource {
+    fn describe(&self) -> String {
+        format!("{} receipts {}..={}", self.url, self.range.start(), self.range.end())
+    }
+
+    fn read_receipts(&mut self, ctx: &mut SourceContext<'_>) -> Result<Vec<Receipt>> {
+        ctx.progress.set_stage("read receipts");
+        let retry = ctx.args.retry_policy();
+        let source = &*self;
+        let mut receipts = Vec::new();
+        source.fetch_blocks(
+            |number| {
+                let retry = &retry;
+                async move {
+                    let block: RpcBlock = source
+                        .call_retrying(
+                            retry,
+                            "eth_getBlockByNumber",
+                            json!([quantity(number), false]),
+                        )
+                        .await?;
+                    let mut block_receipts = Vec::with_capacity(block.transactions.len());
+                    for hash in block.transactions {
+                        let receipt: RpcReceipt = source
+                            .call_retrying(retry, "eth_getTransactionReceipt", json!([hash]))
+                            .await?;
+                        block_receipts.push(Receipt::try_from(receipt)?);
+                    }
+                    Ok(block_receipts)
+                }
+            },
+            |number, block_receipts: Vec<Receipt>| {
+                ctx.progress.advance(block_receipts.len() as u64)?;
+                ctx.progress.set_block(number);
+                receipts.extend(block_receipts);
+                Ok(())
+            },
+        )?;
+        Ok(receipts)
+    }
+}
+
+impl StateSource for RpcSource {
+    fn describe(&self) -> String {
+        format!("{} state at block {}", self.url, self.range.end())
+    }
+
+    fn read_state(&mut self, ctx: &mut SourceContext<'_>) -> Result<State> {
+        ctx.progress.set_stage("read state");
+        let dump: RpcDump =
+            self.request("debug_dumpBlock", json!([quantity(*self.range.end())]))?;
+        ctx.progress.set_stage("decode state");
+        dump.accounts
+            .into_iter()
+            .map(|(address, account)| Ok((address, account.try_into()?)))
+            .collect()
+    }
+}
+
+/// Runs the future to completion on the current tokio runtime from synchronous code
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(future))
+}
+
+/// Encodes a number as a JSON-RPC quantity
+fn quantity(number: u64) -> String {
+    format!("{number:#x}")
+}
+
+/// Converts a JSON-RPC quantity to a u64
+fn to_u64(value: U256) -> Result<u64> {
+    u64::try_from(value).map_err(|_| eyre::eyre!("Quantity {value} does not fit into 64 bits"))
+}
+
+/// Decodes hex data with or without a `0x` prefix, as written by the different geth versions
+fn decode_hex(data: &str) -> Result<Vec<u8>> {
+    Ok(hex::decode(data.trim_start_matches("0x"))?)
+}
+
+/// The fields of a block read with `eth_getBlockByNumber` without the full transactions
+#[derive(Debug, Deserialize)]
+struct RpcBlock {
+    hash: H256,
+    transactions: Vec<H256>,
+}
+
+/// A receipt returned by `eth_getTransactionReceipt` of l2geth
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcReceipt {
+    #[serde(rename = "type", default)]
+    ty: U256,
+    #[serde(default)]
+    root: Option<Bytes>,
+    #[serde(default)]
+    status: Option<U256>,
+    cumulative_gas_used: U256,
+    logs_bloom: Bytes,
+    logs: Vec<RpcLog>,
+    transaction_hash: H256,
+    contract_address: Option<H160>,
+    gas_used: U256,
+    block_hash: H256,
+    block_number: U256,
+    transaction_index: U256,
+    #[serde(default)]
+    l1_gas_price: U256,
+    #[serde(default)]
+    l1_gas_used: U256,
+    #[serde(default)]
+    l1_fee: U256,
+    #[serde(default)]
+    l1_fee_scalar: String,
+}
+
+/// A log of an [RpcReceipt]
+#[derive(Debug, Deserialize)]
+struct RpcLog {
+    address: H160,
+    topics: Vec<H256>,
+    data: Bytes,
+}
+
+impl TryFrom<RpcReceipt> for Receipt {
+    type Error = eyre::Report;
+
+    fn try_from(receipt: RpcReceipt) -> Result<Self> {
+        let logs = receipt
+            .logs
+            .into_iter()
+            .map(|log| ReceiptLog {
+                address: log.address,
+                topics: log.topics,
+                data: log.data.to_vec(),
+            })
+            .collect::<Vec<_>>();
+        Ok(Self {
+            ty: to_u64(receipt.ty)? as u8,
+            post_state: receipt.root.map(|root| root.to_vec()).unwrap_or_default(),
+            status: receipt.status.map(to_u64).transpose()?.unwrap_or_default(),
+            cumulative_gas_used: to_u64(receipt.cumulative_gas_used)?,
+            bloom: receipt.logs_bloom.to_vec(),
+            logs: rlp::encode_list::<ReceiptLog, ReceiptLog>(&logs).to_vec(),
+            tx_hash: receipt.transaction_hash,
+            contract_address: format!("{:?}", receipt.contract_address.unwrap_or_default()),
+            gas_used: to_u64(receipt.gas_used)?,
+            block_hash: receipt.block_hash,
+            block_number: receipt.block_number,
+            transaction_index: to_u64(receipt.transaction_index)?,
+            l1_gas_price: receipt.l1_gas_price,
+            l1_gas_used: receipt.l1_gas_used,
+            l1_fee: receipt.l1_fee,
+            l1_fee_scalar: receipt.l1_fee_scalar,
+        })
+    }
+}
+
+/// The state returned by `debug_dumpBlock`
+#[derive(Debug, Deserialize)]
+struct RpcDump {
+    accounts: BTreeMap<Address, RpcDumpAccount>,
+}
+
+/// An account of an [RpcDump]. Balances are decimal, and hashes, code and storage values are hex
+/// encoded without a prefix in older geth versions.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcDumpAccount {
+    balance: String,
+    nonce: u64,
+    #[serde(default)]
+    root: Option<String>,
+    #[serde(default)]
+    code_hash: Option<String>,
+    #[serde(default)]
+    code: Option<String>,
+    #[serde(default)]
+    storage: BTreeMap<String, String>,
+}
+
+impl TryFrom<RpcDumpAccount> for ExportedAccount {
+    type Error = eyre::Report;
+
+    fn try_from(account: RpcDumpAccount) -> Result<Self> {
+        let hash = |hash: &str| -> Result<reth_primitives::H256> {
+            let bytes = decode_hex(hash)?;
+            if bytes.len() != 32 {
+                eyre::bail!("Invalid hash {hash}");
+            }
+            Ok(reth_primitives::H256::from_slice(&bytes))
+        };
+        let storage = account
+            .storage
+            .iter()
+            .map(|(key, value)| {
+                let value = U256::try_from_be_slice(&decode_hex(value)?)
+                    .ok_or_else(|| eyre::eyre!("Invalid storage value {value}"))?;
+                Ok((hash(key)?, value))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?;
+        let code = account.code.as_deref().map(decode_hex).transpose()?;
+        Ok(Self {
+            balance: U256::from_str(&account.balance)?,
+            code_hash: account.code_hash.as_deref().map(hash).transpose()?,
+            code: code.filter(|code| !code.is_empty()).map(hex::encode),
+            nonce: Some(account.nonce),
+            root: account.root.as_deref().map(hash).transpose()?,
+            storage: (!storage.is_empty()).then_some(storage),
+        })
+    }
+}
@@ -0,0 +1,281 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use eyre::Result;
+use reth_primitives::SealedBlock;
+
+use crate::cli::{
+    blocks::BlockFormat,
+    dedup::Deduplicator,
+    receipts::Receipt,
+    source::{file::FileSource, BlockSource, ReceiptSource, SourceContext, StateSource},
+    state::{
+        formats::{self, AccountVisitor, StateFormat},
+        State,
+    },
+};
+
+/// The extensions of the state dumps read from a directory
+const STATE_EXTENSIONS: [&str; 3] = ["json", "jsonl", "csv"];
+
+/// A file of an export split into block ranges, like `export_1000000_2000000`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shard {
+    /// The path of the file
+    pub path: PathBuf,
+    /// The first block of the range, taken from the file name
+    pub from: u64,
+    /// The last block of the range, taken from the file name
+    pub to: u64,
+}
+
+/// Whether the path is a glob pattern rather than a single file or directory
+pub fn is_pattern(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+/// Whether the input at `path` is split into several files: a directory or a glob pattern
+pub fn is_sharded(path: &str) -> bool {
+    is_pattern(path) || Path::new(path).is_dir()
+}
+
+/// Lists the files in the directory or matching the glob pattern at `path`, ordered by name
+pub fn input_files(path: &str) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if is_pattern(path) {
+        for file in glob::glob(path)? {
+            files.push(file?);
+        }
+    } else {
+        for entry in fs::read_dir(path)? {
+            files.push(entry?.path());
+        }
+    }
+    files.retain(|file| file.is_file());
+    files.sort();
+    Ok(files)
+}
+
+/// Infers the block range of a shard from the last two numbers of its file name, e.g. `0` and
+/// `1000000` for `export_0_1000000` or `export_receipt_0_1000000.rlp`
+pub fn parse_range(path: &Path) -> Option<(u64, u64)> {
+    let name = path.file_name()?.to_str()?;
+    let stem = name.split('.').next()?;
+    let numbers: Vec<u64> = stem.split(['_', '-']).filter_map(|part| part.parse().ok()).collect();
+    let [.., from, to] = numbers[..] else { return None };
+    (from <= to).then_some((from, to))
+}
+
+/// Lists the shards in the directory or matching the glob pattern at `path`, ordered by their
+/// ranges. Files without a range in their name, like checksum manifests, are skipped. Fails unless
+/// the ranges are contiguous.
+pub fn list_shards(path: &str) -> Result<Vec<Shard>> {
+    let mut shards = Vec::new();
+    for file in input_files(path)? {
+        match parse_range(&file) {
+            Some((from, to)) => shards.push(Shard { path: file, from, to }),
+            None => {
+                tracing::debug!(target: "reth::cli", file = %file.display(), "Skipping file without a block range")
+            }
+        }
+    }
+    if shards.is_empty() {
+        eyre::bail!(
+            "No shards found at {path}, their file names have to end with their block range like \
+             export_0_1000000"
+        );
+    }
+    shards.sort_by_key(|shard| (shard.from, shard.to));
+    check_contiguous(&shards)?;
+    Ok(shards)
+}
+
+/// Checks that no shard starts after the end of the shards before it. Exporters differ in whether
+/// the last block in a file name is included, so a shard may start at the last block of the
+/// previous one or right after it. Overlapping shards are accepted, the blocks they share are
+/// only read once.
+pub fn check_contiguous(shards: &[Shard]) -> Result<()> {
+    let Some(first) = shards.first() else { return Ok(()) };
+    let mut covering = first;
+    for next in &shards[1..] {
+        if next.from > covering.to + 1 {
+            eyre::bail!(
+                "{} covers blocks {}..={}, leaving a gap after {} covering blocks {}..={}",
+                next.path.display(),
+                next.from,
+                next.to,
+                covering.path.display(),
+                covering.from,
+                covering.to
+            );
+        }
+        if next.to > covering.to {
+            covering = next;
+        }
+    }
+    Ok(())
+}
+
+/// Reads an export split into block ranges from the files in a directory or matching a glob
+/// pattern, in the order of their ranges
+#[derive(Debug, Clone)]
+pub struct ShardedSource {
+    /// The directory or glob pattern
+    path: String,
+    /// The layout of the block exports. Detected from the contents of every file if not given.
+    format: Option<BlockFormat>,
+    /// The layout of the state dumps. Detected from the contents of every file if not given.
+    state_format: Option<StateFormat>,
+}
+
+impl ShardedSource {
+    /// Creates a source reading the shards in the directory or matching the glob pattern `path`
+    pub fn new(path: impl Into<String>) -> Self {
+        Self { path: path.into(), format: None, state_format: None }
+    }
+
+    /// Decodes the block exports in the given layout instead of detecting it
+    pub fn with_format(mut self, format: Option<BlockFormat>) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Decodes the state dumps in the given layout instead of detecting it
+    pub fn with_state_format(mut self, format: Option<StateFormat>) -> Self {
+        self.state_format = format;
+        self
+    }
+
+    /// The sources reading the shards, in the order of their ranges
+    fn shards(&self) -> Result<Vec<(Shard, FileSource)>> {
+        Ok(list_shards(&self.path)?
+            .into_iter()
+            .map(|shard| {
+                let source =
+                    FileSource::new(shard.path.display().to_string()).with_format(self.format);
+                (shard, source)
+            })
+            .collect())
+    }
+}
+
+impl BlockSource for ShardedSource {
+    fn describe(&self) -> String {
+        format!("shards {}", self.path)
+    }
+
+    /// Reads the blocks of all shards. Blocks and transactions shared by overlapping shards are
+    /// only kept once, a block differing from the block with the same number of an earlier shard
+    /// is refused.
+    fn read_blocks(&mut self, ctx: &mut SourceContext<'_>) -> Result<Vec<SealedBlock>> {
+        let shards = self.shards()?;
+        let expected = shards.iter().map(|(shard, _)| (shard.to - shard.from + 1) as usize).sum();
+        // Legacy OP blocks hold a single transaction
+        let mut dedup = Deduplicator::new(expected, expected);
+        let mut blocks: Vec<SealedBlock> = Vec::new();
+        for (shard, mut source) in shards {
+            tracing::info!(target: "reth::cli", shard = %shard.path.display(), from = shard.from, to = shard.to, "Reading shard");
+            let mut first = true;
+            for mut block in source.read_blocks(ctx)? {
+                if dedup.is_duplicate(&blocks, &block) {
+                    continue
+                }
+                if let Some(last) = blocks.last() {
+                    if block.number <= last.number {
+                        eyre::bail!(
+                            "Block {} differs between {} and the shard before it",
+                            block.number,
+                            shard.path.display()
+                        );
+                    }
+                    if first && block.number != last.number + 1 {
+                        eyre::bail!(
+                            "{} continues at block {}, but the shards before it end at block {}",
+                            shard.path.display(),
+                            block.number,
+                            last.number
+                        );
+                    }
+                }
+                first = false;
+                dedup.remove_duplicate_transactions(&blocks, &mut block);
+                dedup.insert(&block);
+                blocks.push(block);
+            }
+        }
+        if dedup.skipped_blocks > 0 || dedup.skipped_transactions > 0 {
+            tracing::info!(target: "reth::cli", blocks = dedup.skipped_blocks, transactions = dedup.skipped_transactions, "Skipped duplicates shared by overlapping shards");
+        }
+        Ok(blocks)
+    }
+}
+
+impl ReceiptSource for ShardedSource {
+    fn describe(&self) -> String {
+        format!("shards {}", self.path)
+    }
+
+    fn read_receipts(&mut self, ctx: &mut SourceContext<'_>) -> Result<Vec<Receipt>> {
+        let mut receipts: Vec<Receipt> = Vec::new();
+        for (shard, mut source) in self.shards()? {
+            tracing::info!(target: "reth::cli", shard = %shard.path.display(), from = shard.from, to = shard.to, "Reading shard");
+            let shard_receipts = source.read_receipts(ctx)?;
+            // Receipts of a block shared with the previous shard are only kept once
+            let position = |receipt: &Receipt| (receipt.block_number, receipt.transaction_index);
+            let last = receipts.last().map(position);
+            receipts.extend(
+                shard_receipts.into_iter().skip_while(|receipt| Some(position(receipt)) <= last),
+            );
+        }
+        Ok(receipts)
+    }
+}
+
+impl StateSource for ShardedSource {
+    fn describe(&self) -> String {
+        format!("state files {}", self.path)
+    }
+
+    fn read_state(&mut self, ctx: &mut SourceContext<'_>) -> Result<State> {
+        formats::collect_state(|visit| self.stream_state(ctx, visit))
+    }
+
+    /// Streams the state dumps of all JSON and CSV files in the directory or all files matching
+    /// the glob pattern, which are split by account instead of block range. An account may only be
+    /// part of one file, so the addresses read so far are kept, taking 20 bytes per account.
+    fn stream_state(
+        &mut self,
+        ctx: &mut SourceContext<'_>,
+        visit: &mut AccountVisitor<'_>,
+    ) -> Result<()> {
+        let mut seen = HashSet::new();
+        // Compressed dumps keep the extension of the dump before their own, like `alloc.json.gz`
+        let dump = |file: &PathBuf| {
+            file.file_name().and_then(|name| name.to_str()).map_or(false, |name| {
+                let name = name.to_ascii_lowercase();
+                STATE_EXTENSIONS.iter().any(|extension| {
+                    name.ends_with(&format!(".{extension}")) ||
+                        name.split('.').nth(1) == Some(*extension)
+                })
+            })
+        };
+        for file in input_files(&self.path)? {
+            if !is_pattern(&self.path) && !dump(&file) {
+                continue
+            }
+            tracing::info!(target: "reth::cli", file = %file.display(), "Reading state file");
+            let mut source =
+                FileSource::new(file.display().to_string()).with_state_format(self.state_format);
+            source.stream_state(ctx, &mut |address, account| {
+                if !seen.insert(address) {
+                    eyre::bail!("Account {address:?} is part of several state files");
+                }
+                visit(address, account)
+            })?;
+        }
+        Ok(())
+    }
+}
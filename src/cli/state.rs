@@ -1,110 +1,616 @@
 use std::{
-    collections::HashMap,
+    collections::BTreeMap,
     path::{Path, PathBuf},
+    time::Instant,
 };
 
-use crate::cli::db;
+use crate::cli::{
+    args::{DatabaseArgs, ImportArgs},
+    compression,
+    dead_letter::{self, ImportError},
+    journal,
+    keccak::{self, keccak256, Keccak},
+    preflight::ImportStage,
+    progress::ImportProgress,
+    replay,
+    retry::RetryPolicy,
+    source::{
+        dead_letter::DeadLetterSource,
+        file::FileSource,
+        shards::{self, ShardedSource},
+        SourceContext, StateSource,
+    },
+    throttle::IoLimiter,
+};
 use bytes::BytesMut;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use eyre::Result;
 use reth::runner::CliContext;
 use reth_db::{
+    cursor::{DbCursorRO, DbDupCursorRO, DbDupCursorRW},
     database::Database,
     mdbx::{Env, WriteMap},
+    table::Table,
     tables,
-    transaction::DbTxMut,
+    transaction::{DbTx, DbTxMut},
 };
 use reth_primitives::{
-    keccak256,
-    proofs::{KeccakHasher, EMPTY_ROOT},
-    Account, Address, Bytes, StorageEntry, H256, KECCAK_EMPTY, U256,
+    proofs::EMPTY_ROOT, Account, Address, Bytes, StorageEntry, H256, KECCAK_EMPTY, U256,
 };
+use reth_provider::{trie::DBTrieLoader, Transaction};
 use reth_rlp::{Encodable, Header};
+use reth_stages::StageId;
 use triehash::sec_trie_root;
 
+pub mod diff;
+pub mod export;
+pub mod formats;
+pub mod proof;
+pub mod snapshot;
+
+use diff::DEFAULT_DIFF_LIMIT;
+use formats::{AccountVisitor, StateFormat};
+
 /// State command
+///
+/// Without a subcommand the state export is imported, like `state import`, so that the
+/// `state --path <STATE>` invocation of earlier versions keeps working.
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct Command {
-    /// The path to the state export
-    #[arg(
-        long,
-        value_name = "STATE",
-        verbatim_doc_comment,
-        default_value = "data/alloc_everything_4061224_final.json"
-    )]
-    path: String,
-    /// The path to the database
-    #[arg(long, value_name = "DATABASE_PATH", verbatim_doc_comment)]
-    database: String,
-}
-
-/// Apply world state to the given database
-pub async fn apply(db: &mut Env<WriteMap>, path: Option<&str>) -> Result<()> {
-    let file_path = path.unwrap_or("data/alloc_everything_4061224_final.json");
-    let state = from_file(file_path)?;
+    #[clap(subcommand)]
+    command: Option<Subcommands>,
+
+    #[clap(flatten)]
+    import: ImportCommand,
+}
+
+/// State subcommands
+#[derive(Debug, Subcommand)]
+pub enum Subcommands {
+    /// Load the world state trie
+    #[command(name = "import")]
+    Import(ImportCommand),
+    /// Hash the plain state and build the merkle trie tables
+    #[command(name = "hash-and-trie")]
+    HashAndTrie(HashAndTrieCommand),
+    /// Show an account and its storage slots as of a block
+    #[command(name = "get")]
+    Get(GetCommand),
+    /// Compare the plain state of the database to a state export
+    #[command(name = "diff")]
+    Diff(DiffCommand),
+    /// Write the state of the database as a genesis `alloc` JSON object
+    #[command(name = "export")]
+    Export(export::Command),
+    /// Compute the state root of a state export or of the database
+    #[command(name = "root")]
+    Root(RootCommand),
+    /// Prove an account and its storage slots from the trie tables
+    #[command(name = "prove")]
+    Prove(ProveCommand),
+    /// Create and restore chunked snapshots of the plain state
+    #[command(name = "snapshot")]
+    Snapshot(snapshot::Command),
+}
+
+/// Build a Merkle proof of an account and its storage slots from the trie tables written by
+/// `state hash-and-trie`, and print it in the layout of an `eth_getProof` response.
+///
+/// The proof is checked against the state root before it is printed, so accounts can be verified
+/// against the state roots published for the legacy chain.
+#[derive(Debug, Parser)]
+pub struct ProveCommand {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The address of the account
+    #[arg(long, value_name = "ADDRESS", verbatim_doc_comment)]
+    address: Address,
+
+    /// A storage slot to prove. Repeat to prove several slots.
+    #[arg(long = "slot", value_name = "SLOT", verbatim_doc_comment)]
+    slots: Vec<H256>,
+
+    /// The state root to prove against. Defaults to the state root of the canonical tip.
+    #[arg(long, value_name = "HASH", verbatim_doc_comment)]
+    root: Option<H256>,
+}
+
+/// Compute the state root of a state export without importing it, or of the plain state of the
+/// database. Prints the root, the number of accounts and the time taken.
+///
+/// Accounts of the export without a storage root get the root of their storage.
+#[derive(Debug, Parser)]
+pub struct RootCommand {
+    /// The path to the state export. Without it, the root of the plain state of the database
+    /// selected with `--database` is computed.
+    #[arg(long, value_name = "STATE", conflicts_with = "database", verbatim_doc_comment)]
+    path: Option<String>,
+
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The layout of the state dump. Detected from its contents by default.
+    #[arg(long, value_enum, value_name = "FORMAT", requires = "path", verbatim_doc_comment)]
+    format: Option<StateFormat>,
+}
+
+/// Compare the accounts of the database to the accounts of a state export, reporting differing
+/// balances, nonces, code hashes and storage slots and accounts missing on either side.
+///
+/// Fails if there are any differences, to debug state root mismatches after a migration.
+#[derive(Debug, Parser)]
+pub struct DiffCommand {
+    /// The path to the state export. Defaults to the state export of the chain selected with
+    /// `--chain`.
+    #[arg(long, value_name = "STATE", verbatim_doc_comment)]
+    path: Option<String>,
+
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The layout of the state dump. Detected from its contents by default.
+    #[arg(long, value_enum, value_name = "FORMAT", verbatim_doc_comment)]
+    format: Option<StateFormat>,
+
+    /// Write the summary and the differences as JSON to this file
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    report: Option<PathBuf>,
+
+    /// The number of differences to list. All differences are counted in the summary.
+    #[arg(long, value_name = "COUNT", default_value_t = DEFAULT_DIFF_LIMIT, verbatim_doc_comment)]
+    limit: usize,
+}
+
+/// Show an account and its storage slots after a block.
+///
+/// Blocks before the state of the database are reconstructed from the changesets written by
+/// `replay`, for auditing balances around incidents on the legacy chain.
+#[derive(Debug, Parser)]
+pub struct GetCommand {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The address of the account
+    #[arg(long, value_name = "ADDRESS", verbatim_doc_comment)]
+    address: Address,
+
+    /// The block after which to show the account. Defaults to the state of the database.
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment)]
+    block: Option<u64>,
+
+    /// A storage slot to show. Repeat to show several slots.
+    #[arg(long = "slot", value_name = "SLOT", verbatim_doc_comment)]
+    slots: Vec<H256>,
+}
+
+/// Hash and trie command
+#[derive(Debug, Parser)]
+pub struct HashAndTrieCommand {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+}
+
+/// State import command
+#[derive(Debug, Parser)]
+pub struct ImportCommand {
+    /// The path to the state export. Defaults to the state export of the chain selected with
+    /// `--chain`.
+    #[arg(long, value_name = "STATE", verbatim_doc_comment)]
+    path: Option<String>,
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The layout of the state dump. Detected from its contents by default.
+    #[arg(long, value_enum, value_name = "FORMAT", verbatim_doc_comment)]
+    format: Option<StateFormat>,
+
+    #[clap(flatten)]
+    import: ImportArgs,
+}
+
+/// Apply world state to the given database, detecting the layout of the dump
+pub async fn apply(db: &mut Env<WriteMap>, path: Option<&str>, args: &ImportArgs) -> Result<()> {
+    apply_as(db, path, None, args).await
+}
+
+/// Apply world state to the given database, decoding the dump in the given layout or the detected
+/// one. The state dumps in a directory or matching a glob pattern are merged.
+pub async fn apply_as(
+    db: &mut Env<WriteMap>,
+    path: Option<&str>,
+    format: Option<StateFormat>,
+    args: &ImportArgs,
+) -> Result<()> {
+    if let Some(dead_letter) = &args.from_dead_letter {
+        return apply_from(db, &mut DeadLetterSource::open(dead_letter)?, args).await
+    }
+    let file_path = args.input_path(path, ImportStage::State)?;
+    if shards::is_sharded(&file_path) {
+        let mut source = ShardedSource::new(file_path).with_state_format(format);
+        return apply_from(db, &mut source, args).await
+    }
+    apply_from(db, &mut FileSource::new(file_path).with_state_format(format), args).await
+}
+
+/// The stage the accounts are written to the database in
+const INSERT_STAGE: &str = "insert state";
+
+/// Apply the world state read from the given source to the given database.
+///
+/// The accounts are written in batches as the source decodes them, so only one batch of accounts
+/// is held in memory. Accounts whose code is not valid hex fail the import, or are written to the
+/// dead-letter file of a lenient import as an `alloc` object holding only that account.
+pub async fn apply_from(
+    db: &mut Env<WriteMap>,
+    source: &mut dyn StateSource,
+    args: &ImportArgs,
+) -> Result<()> {
+    let (progress, _monitors) = args.watch()?;
+    let limiter = args.io_limiter();
+    let retry = args.retry_policy();
+    let batch_size = args.batch_size.max(1);
+    let mut dead_letter = args.dead_letter_file()?;
     db.create_tables()?;
-    db.update(|tx| {
-        for (address, account) in &state {
+    let db = &*db;
+
+    tracing::info!(target: "reth::cli", source = %source.describe(), "Reading state");
+    progress.set_stage(INSERT_STAGE);
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut index = 0;
+    let mut ctx = SourceContext {
+        args,
+        progress: &progress,
+        limiter: limiter.as_ref(),
+        dead_letter: None,
+        validator: None,
+    };
+    source.stream_state(&mut ctx, &mut |address, account| {
+        index += 1;
+        if let Err(err) = decode_code(address, &account) {
+            let error = ImportError::new("account", index - 1, err);
+            let raw = serde_json::to_vec(&State::from([(address, account)]))?;
+            return dead_letter::reject(dead_letter.as_mut(), error, &raw)
+        }
+        batch.push((address, account));
+        if batch.len() == batch_size {
+            write_accounts(db, &batch, &progress, limiter.as_ref(), &retry)?;
+            batch.clear();
+        }
+        Ok(())
+    })?;
+    write_accounts(db, &batch, &progress, limiter.as_ref(), &retry)?;
+    if let Some(dead_letter) = dead_letter {
+        dead_letter.finish()?;
+    }
+    progress.finish();
+    Ok(())
+}
+
+/// Decodes the hex-encoded code of an account
+fn decode_code(address: Address, account: &ExportedAccount) -> Result<Bytes> {
+    let Some(code) = &account.code else { return Ok(Bytes::default()) };
+    let code = hex::decode(code.trim_start_matches("0x"))
+        .map_err(|err| eyre::eyre!("The code of account {address:?} is not valid hex: {err}"))?;
+    Ok(Bytes::from(code))
+}
+
+/// Writes a batch of accounts with their storage and code in one transaction
+fn write_accounts(
+    db: &Env<WriteMap>,
+    accounts: &[(Address, ExportedAccount)],
+    progress: &ImportProgress,
+    limiter: Option<&IoLimiter>,
+    retry: &RetryPolicy,
+) -> Result<()> {
+    if accounts.is_empty() {
+        return Ok(())
+    }
+    // Sources reading their input whole enter stages of their own before the first batch
+    if progress.stage() != INSERT_STAGE {
+        progress.set_stage(INSERT_STAGE);
+    }
+    retry.run("insert state", || {
+        let tx = db.tx_mut()?;
+        progress.tx_opened();
+        let mut storage_cursor = tx.cursor_dup_write::<tables::PlainStorageState>()?;
+        for (address, account) in accounts {
             // Insert account
             let plain_account = Account {
                 nonce: account.nonce.unwrap_or(0),
                 balance: account.balance,
                 bytecode_hash: account.code_hash,
             };
-            tx.put::<tables::PlainAccountState>(*address, plain_account).unwrap();
+            tx.put::<tables::PlainAccountState>(*address, plain_account)?;
+            progress.record_writes(tables::PlainAccountState::NAME, 1, 20 + 72);
+            let mut written = 20 + 72;
 
             // Insert storage
             if let Some(storage) = &account.storage {
-                for (key, value) in storage {
-                    let storage_entry = StorageEntry { key: *key, value: *value };
-                    tx.put::<tables::PlainStorageState>(*address, storage_entry).unwrap();
-                }
+                write_storage(&mut storage_cursor, *address, storage)?;
+                let size = storage.len() as u64 * (20 + 64);
+                progress.record_writes(tables::PlainStorageState::NAME, storage.len() as u64, size);
+                written += size;
             }
 
             // Insert bytecode
             if let Some(hash) = account.code_hash {
-                let bytecode = if let Some(code) = &account.code {
-                    Bytes::from(hex::decode(code).unwrap_or(vec![]))
-                } else {
-                    Bytes::from(vec![])
-                };
-                tx.put::<tables::Bytecodes>(hash, bytecode.to_vec()).unwrap();
+                let bytecode = decode_code(*address, account)?;
+                written += 32 + bytecode.len() as u64;
+                tx.put::<tables::Bytecodes>(hash, bytecode.to_vec())?;
+                progress.record_writes(tables::Bytecodes::NAME, 1, 32 + bytecode.len() as u64);
             }
+
+            if let Some(limiter) = limiter {
+                limiter.consume(written);
+            }
+            progress.advance(1)?;
         }
-    })?;
+        drop(storage_cursor);
+        tx.commit()?;
+        progress.tx_closed();
+        db.inner.sync(true)?;
+        Ok(())
+    })
+}
+
+/// Writes the storage of an account in one pass of the cursor. The slots of the map are sorted, so
+/// they are appended to the duplicates of the account instead of being inserted one by one. The
+/// storage an earlier import wrote for the account is replaced.
+pub fn write_storage<'tx, C>(
+    cursor: &mut C,
+    address: Address,
+    storage: &BTreeMap<H256, U256>,
+) -> Result<()>
+where
+    C: DbCursorRO<'tx, tables::PlainStorageState> + DbDupCursorRW<'tx, tables::PlainStorageState>,
+{
+    if cursor.seek_exact(address)?.is_some() {
+        cursor.delete_current_duplicates()?;
+    }
+    for (key, value) in storage {
+        cursor.append_dup(address, StorageEntry { key: *key, value: *value })?;
+    }
     Ok(())
 }
 
+/// Stages whose work is covered by [hash_and_trie]
+pub const HASH_AND_TRIE_STAGES: [StageId; 3] =
+    [StageId("AccountHashing"), StageId("StorageHashing"), StageId("MerkleExecute")];
+
+/// Populate the hashed state tables from the plain state, build the intermediate trie tables and
+/// return the computed state root.
+///
+/// The stage checkpoints of the hashing and merkle stages are set to the canonical tip so that the
+/// node picks up from the imported state instead of recomputing it.
+pub fn hash_and_trie(db: &Env<WriteMap>) -> Result<H256> {
+    tracing::debug!(target: "reth::cli", backend = keccak::BACKEND, "Hashing state");
+    db.update(|tx| -> Result<()> {
+        tx.clear::<tables::HashedAccount>()?;
+        tx.clear::<tables::HashedStorage>()?;
+
+        let mut accounts = tx.cursor_read::<tables::PlainAccountState>()?;
+        let mut entry = accounts.first()?;
+        while let Some((address, account)) = entry {
+            tx.put::<tables::HashedAccount>(keccak256(address), account)?;
+            entry = accounts.next()?;
+        }
+
+        let mut storage = tx.cursor_dup_read::<tables::PlainStorageState>()?;
+        let mut entry = storage.first()?;
+        while let Some((address, slot)) = entry {
+            if slot.value != U256::ZERO {
+                tx.put::<tables::HashedStorage>(
+                    keccak256(address),
+                    StorageEntry { key: keccak256(slot.key), value: slot.value },
+                )?;
+            }
+            entry = storage.next()?;
+        }
+
+        Ok(())
+    })??;
+    tracing::info!(target: "reth::cli", "Hashed state tables populated");
+
+    let tx = Transaction::new(db)?;
+    let root = DBTrieLoader::default().calculate_root(&tx).map_err(|e| eyre::eyre!(e))?;
+    tx.commit()?;
+    tracing::info!(target: "reth::cli", ?root, "Trie tables populated");
+
+    db.update(|tx| -> Result<()> {
+        let Some((tip, _)) = tx.cursor_read::<tables::CanonicalHeaders>()?.last()? else {
+            tracing::warn!(target: "reth::cli", "No canonical blocks found, stage checkpoints not recorded");
+            return Ok(())
+        };
+        if let Some(header) = tx.get::<tables::Headers>(tip)? {
+            if header.state_root != root {
+                tracing::warn!(target: "reth::cli", tip, expected = ?header.state_root, computed = ?root, "Computed state root does not match the tip header");
+            }
+        }
+        for stage in HASH_AND_TRIE_STAGES {
+            stage.save_progress(tx, tip)?;
+        }
+        tracing::info!(target: "reth::cli", tip, "Stage checkpoints recorded");
+        Ok(())
+    })??;
+
+    Ok(root)
+}
+
 impl Command {
+    /// Execute the command
+    pub async fn execute(self, ctx: CliContext) -> Result<()> {
+        match self.command {
+            Some(Subcommands::Import(command)) => command.execute(ctx).await,
+            Some(Subcommands::HashAndTrie(command)) => command.execute(ctx).await,
+            Some(Subcommands::Get(command)) => command.execute(ctx).await,
+            Some(Subcommands::Diff(command)) => command.execute(ctx).await,
+            Some(Subcommands::Export(command)) => command.execute(ctx).await,
+            Some(Subcommands::Root(command)) => command.execute(ctx).await,
+            Some(Subcommands::Prove(command)) => command.execute(ctx).await,
+            Some(Subcommands::Snapshot(command)) => command.execute(ctx).await,
+            None => self.import.execute(ctx).await,
+        }
+    }
+}
+
+impl HashAndTrieCommand {
     /// Execute the command
     pub async fn execute(self, _ctx: CliContext) -> Result<()> {
-        let db_path = PathBuf::from(self.database);
-        let mut db = db::open_rw_env(db_path.as_path())?;
-        apply(&mut db, Some(&self.path)).await
+        journal::record(&self.db.path(), "state hash-and-trie", &[], async {
+            let db = self.db.open_rw()?;
+            let root = hash_and_trie(&db)?;
+            println!("State root: {root:?}");
+            Ok(())
+        })
+        .await
     }
+}
 
-    /// Extract a portion of the state
-    pub async fn export(&self, max: usize) -> eyre::Result<()> {
-        let raw_data = std::fs::read(&self.path)?;
-        let read_value = serde_json::from_slice::<serde_json::Value>(&raw_data)?;
-        let mut ten_values = Vec::new();
-        if let serde_json::Value::Object(map) = read_value {
-            for (count, (key, value)) in map.into_iter().enumerate() {
-                if count < max {
-                    ten_values.push((key, value));
-                } else {
-                    break
-                }
+impl GetCommand {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db = self.db.open_ro()?;
+        let tx = db.tx()?;
+        let block = match self.block {
+            Some(block) => block,
+            None => replay::state_block(&tx)?,
+        };
+        let account = replay::account_at(&tx, self.address, block)?;
+        println!("Block:      {block}");
+        match account {
+            Some(account) => {
+                println!("Nonce:      {}", account.nonce);
+                println!("Balance:    {}", account.balance);
+                println!("Code hash:  {:?}", account.bytecode_hash.unwrap_or(KECCAK_EMPTY));
             }
+            None => println!("Account:    none"),
+        }
+        for slot in self.slots {
+            let value = replay::storage_at(&tx, self.address, slot, block)?;
+            println!("{slot:?}: {value:#x}");
+        }
+        Ok(())
+    }
+}
+
+impl DiffCommand {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let path = self.db.input_path(self.path.as_deref(), ImportStage::State)?;
+        let db = self.db.open_ro()?;
+        let tx = db.tx()?;
+        tracing::info!(target: "reth::cli", %path, "Comparing the state to the export");
+        let reader = compression::open(Path::new(&path))?;
+        let diff = diff::diff_state(
+            &tx,
+            |visit| match self.format {
+                Some(format) => formats::stream_state_as(format, reader, visit),
+                None => formats::stream_state(reader, visit),
+            },
+            self.limit,
+        )?;
+
+        let summary = &diff.summary;
+        println!("Snapshot accounts:    {}", summary.snapshot_accounts);
+        println!("Database accounts:    {}", summary.database_accounts);
+        println!("Differing accounts:   {}", summary.differing_accounts);
+        println!("Missing in database:  {}", summary.missing_in_database);
+        println!("Missing in snapshot:  {}", summary.missing_in_snapshot);
+        println!("Balances:             {}", summary.balance);
+        println!("Nonces:               {}", summary.nonce);
+        println!("Code hashes:          {}", summary.code_hash);
+        println!("Storage slots:        {}", summary.storage);
+        for entry in &diff.diffs {
+            println!("{:?}: {:?}", entry.address, entry.diff);
         }
-        let file = std::fs::File::create("temp_out.json")?;
-        serde_json::to_writer(file, &ten_values)?;
-        tracing::info!(target: "reth::cli", "Wrote to file temp_out.json");
+        if diff.truncated {
+            println!("Only the first {} differences are listed", self.limit);
+        }
+        if let Some(report) = &self.report {
+            std::fs::write(report, serde_json::to_vec_pretty(&diff)?)?;
+            tracing::info!(target: "reth::cli", path = %report.display(), "Wrote the diff report");
+        }
+        if !diff.is_empty() {
+            eyre::bail!(
+                "The state of the database differs from the export in {} accounts",
+                summary.differing_accounts
+            );
+        }
+        Ok(())
+    }
+}
+
+impl RootCommand {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let started = Instant::now();
+        let (root, accounts) = match &self.path {
+            Some(path) => {
+                tracing::info!(target: "reth::cli", %path, "Computing the state root of the export");
+                let reader = compression::open(Path::new(path))?;
+                stream_state_root(|visit| match self.format {
+                    Some(format) => formats::stream_state_as(format, reader, visit),
+                    None => formats::stream_state(reader, visit),
+                })?
+            }
+            None => {
+                let db = self.db.open_ro()?;
+                let tx = db.tx()?;
+                tracing::info!(target: "reth::cli", path = ?self.db.path(), "Computing the state root of the database");
+                stream_state_root(|visit| stream_database_state(&tx, visit))?
+            }
+        };
+        println!("State root:  {root:?}");
+        println!("Accounts:    {accounts}");
+        println!("Elapsed:     {:?}", started.elapsed());
+        Ok(())
+    }
+}
+
+impl ProveCommand {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db = self.db.open_ro()?;
+        let tx = db.tx()?;
+        let root = match self.root {
+            Some(root) => root,
+            None => {
+                let (tip, _) = tx
+                    .cursor_read::<tables::CanonicalHeaders>()?
+                    .last()?
+                    .ok_or_else(|| eyre::eyre!("No canonical blocks found, pass --root"))?;
+                let header = tx
+                    .get::<tables::Headers>(tip)?
+                    .ok_or_else(|| eyre::eyre!("Header for canonical block {tip} not found"))?;
+                tracing::info!(target: "reth::cli", tip, root = ?header.state_root, "Proving against the state root of the tip");
+                header.state_root
+            }
+        };
+        let proof = proof::prove_account(&tx, root, self.address, &self.slots)?;
+        proof::verify_account_proof(root, &proof)?;
+        println!("{}", serde_json::to_string_pretty(&proof)?);
         Ok(())
     }
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+impl ImportCommand {
+    /// Execute the command
+    pub async fn execute(mut self, _ctx: CliContext) -> Result<()> {
+        self.import = self.import.with_database(&self.db)?;
+        let db_path = self.db.path();
+        let path = self.import.input_path(self.path.as_deref(), ImportStage::State)?;
+        journal::record(&db_path, "state import", &[Path::new(&path)], async {
+            self.import.preflight(&db_path, ImportStage::State, Path::new(&path))?;
+            let mut db = self.db.open_rw()?;
+            apply_as(&mut db, Some(&path), self.format, &self.import).await
+        })
+        .await
+    }
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExportedAccount {
     pub balance: U256,
@@ -112,20 +618,22 @@ pub struct ExportedAccount {
     pub code: Option<String>,
     pub nonce: Option<u64>,
     pub root: Option<H256>,
-    pub storage: Option<HashMap<H256, U256>>,
+    pub storage: Option<BTreeMap<H256, U256>>,
 }
 
 /// ## State
 ///
-/// The world state trie is a key-value store that maps addresses to accounts.
-pub type State = HashMap<Address, ExportedAccount>;
+/// The world state trie is a key-value store that maps addresses to accounts. Accounts and their
+/// storage are ordered by address and slot, so that iterating, serializing and importing a state
+/// yields the same order on every run.
+pub type State = BTreeMap<Address, ExportedAccount>;
 
 pub fn exported_account_payload_len(ea: &ExportedAccount) -> usize {
     let mut len = 0;
     len += ea.nonce.unwrap_or_default().length();
     len += ea.balance.length();
     len += EMPTY_ROOT.length();
-    len += ea.code_hash.as_ref().map_or(KECCAK_EMPTY, keccak256).length();
+    len += ea.code_hash.unwrap_or(KECCAK_EMPTY).length();
     len
 }
 
@@ -135,14 +643,12 @@ pub fn encode_exported_account(ea: &ExportedAccount, out: &mut dyn bytes::BufMut
     ea.nonce.unwrap_or_default().encode(out);
     ea.balance.encode(out);
     ea.root.unwrap_or(EMPTY_ROOT).encode(out);
-    ea.code_hash.as_ref().map_or(KECCAK_EMPTY, keccak256).encode(out);
+    ea.code_hash.unwrap_or(KECCAK_EMPTY).encode(out);
 }
 
-/// Decodes the world state from a json file
+/// Decodes the world state from a state dump, detecting its layout and compression
 pub fn from_file(path: impl AsRef<Path>) -> Result<State> {
-    let data = std::fs::read(&path)?;
-    let state = serde_json::from_slice::<State>(&data)?;
-    Ok(state)
+    formats::decode_state(&compression::read(path)?)
 }
 
 /// Calculate the state root hash
@@ -152,5 +658,65 @@ pub fn state_root_hash(state: &State) -> Result<H256> {
         encode_exported_account(account, &mut acc_rlp);
         (address, Bytes::from(acc_rlp.freeze()))
     });
-    Ok(H256(sec_trie_root::<KeccakHasher, _, _, _>(accounts).0))
+    Ok(H256(sec_trie_root::<Keccak, _, _, _>(accounts).0))
+}
+
+/// Computes the root of the storage trie holding the given slots. Slots set to zero are left out.
+pub fn storage_root(storage: &BTreeMap<H256, U256>) -> H256 {
+    let slots = storage.iter().filter(|(_, value)| **value != U256::ZERO).map(|(key, value)| {
+        let mut encoded = Vec::new();
+        value.encode(&mut encoded);
+        (key, encoded)
+    });
+    H256(sec_trie_root::<Keccak, _, _, _>(slots).0)
+}
+
+/// Calculates the state root of the accounts visited by `stream` and returns it with the number
+/// of accounts. Accounts without a storage root get the root of their storage.
+///
+/// Only the encoded accounts are kept while streaming, not their code or storage.
+pub fn stream_state_root(
+    stream: impl FnOnce(&mut AccountVisitor<'_>) -> Result<()>,
+) -> Result<(H256, u64)> {
+    let mut accounts = Vec::new();
+    stream(&mut |address, mut account| {
+        if account.root.is_none() {
+            account.root = account.storage.as_ref().map(storage_root);
+        }
+        let mut encoded = BytesMut::new();
+        encode_exported_account(&account, &mut encoded);
+        accounts.push((address, encoded.freeze()));
+        Ok(())
+    })?;
+    let count = accounts.len() as u64;
+    Ok((H256(sec_trie_root::<Keccak, _, _, _>(accounts).0), count))
+}
+
+/// Visits the accounts of the plain state of the database with their storage, in the layout of a
+/// state export
+pub fn stream_database_state<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    visit: &mut AccountVisitor<'_>,
+) -> Result<()> {
+    let mut accounts = tx.cursor_read::<tables::PlainAccountState>()?;
+    let mut storage = tx.cursor_dup_read::<tables::PlainStorageState>()?;
+    let mut entry = accounts.first()?;
+    while let Some((address, account)) = entry {
+        let mut slots = BTreeMap::new();
+        let mut slot = storage.seek_by_key_subkey(address, H256::zero())?;
+        while let Some(StorageEntry { key, value }) = slot {
+            slots.insert(key, value);
+            slot = storage.next_dup_val()?;
+        }
+        let exported = ExportedAccount {
+            balance: account.balance,
+            code_hash: account.bytecode_hash,
+            nonce: Some(account.nonce),
+            storage: (!slots.is_empty()).then_some(slots),
+            ..Default::default()
+        };
+        visit(address, exported)?;
+        entry = accounts.next()?;
+    }
+    Ok(())
 }
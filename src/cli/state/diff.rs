@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, HashSet};
+
+use eyre::Result;
+use reth_db::{
+    cursor::{DbCursorRO, DbDupCursorRO},
+    tables,
+    transaction::DbTx,
+};
+use reth_primitives::{Address, H256, KECCAK_EMPTY, U256};
+use serde::Serialize;
+
+use crate::cli::state::formats::AccountVisitor;
+
+/// The default number of differences kept for the report, all of them are counted
+pub const DEFAULT_DIFF_LIMIT: usize = 1000;
+
+/// A difference between an account of the database and the same account of a state snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum AccountDiff {
+    /// The account is part of the snapshot, but not of the database
+    MissingInDatabase,
+    /// The account is part of the database, but not of the snapshot
+    MissingInSnapshot,
+    /// The balances differ
+    Balance {
+        /// The balance of the snapshot
+        expected: U256,
+        /// The balance of the database
+        found: U256,
+    },
+    /// The nonces differ
+    Nonce {
+        /// The nonce of the snapshot
+        expected: u64,
+        /// The nonce of the database
+        found: u64,
+    },
+    /// The code hashes differ. Accounts without code have no code hash.
+    CodeHash {
+        /// The code hash of the snapshot
+        expected: Option<H256>,
+        /// The code hash of the database
+        found: Option<H256>,
+    },
+    /// The values of a storage slot differ. Unset slots have the value zero.
+    Storage {
+        /// The differing slot
+        slot: H256,
+        /// The value of the snapshot
+        expected: U256,
+        /// The value of the database
+        found: U256,
+    },
+}
+
+/// A difference found in the account with the given address
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiffEntry {
+    /// The address of the account
+    pub address: Address,
+    /// The difference
+    #[serde(flatten)]
+    pub diff: AccountDiff,
+}
+
+/// The number of differences found, by kind
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffSummary {
+    /// The accounts of the snapshot
+    pub snapshot_accounts: u64,
+    /// The accounts of the database
+    pub database_accounts: u64,
+    /// The accounts with at least one difference, including missing accounts
+    pub differing_accounts: u64,
+    /// The accounts of the snapshot missing in the database
+    pub missing_in_database: u64,
+    /// The accounts of the database missing in the snapshot
+    pub missing_in_snapshot: u64,
+    /// The accounts with a differing balance
+    pub balance: u64,
+    /// The accounts with a differing nonce
+    pub nonce: u64,
+    /// The accounts with a differing code hash
+    pub code_hash: u64,
+    /// The differing storage slots
+    pub storage: u64,
+}
+
+/// The differences between the plain state of a database and a state snapshot
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateDiff {
+    /// The counts of all differences
+    pub summary: DiffSummary,
+    /// The first differences found, up to the limit the diff was taken with
+    pub diffs: Vec<DiffEntry>,
+    /// Whether differences were left out of [StateDiff::diffs] because of the limit
+    pub truncated: bool,
+}
+
+impl StateDiff {
+    /// Whether the database matches the snapshot
+    pub fn is_empty(&self) -> bool {
+        self.summary.differing_accounts == 0
+    }
+
+    fn record(&mut self, address: Address, diff: AccountDiff, limit: usize) {
+        let summary = &mut self.summary;
+        match diff {
+            AccountDiff::MissingInDatabase => summary.missing_in_database += 1,
+            AccountDiff::MissingInSnapshot => summary.missing_in_snapshot += 1,
+            AccountDiff::Balance { .. } => summary.balance += 1,
+            AccountDiff::Nonce { .. } => summary.nonce += 1,
+            AccountDiff::CodeHash { .. } => summary.code_hash += 1,
+            AccountDiff::Storage { .. } => summary.storage += 1,
+        }
+        if self.diffs.len() < limit {
+            self.diffs.push(DiffEntry { address, diff });
+        } else {
+            self.truncated = true;
+        }
+    }
+}
+
+/// Compares the plain state of the database to the accounts of a state snapshot streamed by
+/// `stream`, keeping the first `limit` differences.
+///
+/// The snapshot is compared one account at a time. The addresses of the snapshot are kept to find
+/// the accounts of the database missing in the snapshot afterwards, taking 20 bytes per account.
+pub fn diff_state<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    stream: impl FnOnce(&mut AccountVisitor<'_>) -> Result<()>,
+    limit: usize,
+) -> Result<StateDiff> {
+    let mut diff = StateDiff::default();
+    let mut seen = HashSet::new();
+    let mut storage = tx.cursor_dup_read::<tables::PlainStorageState>()?;
+    stream(&mut |address, expected| {
+        diff.summary.snapshot_accounts += 1;
+        if !seen.insert(address) {
+            eyre::bail!("Account {address:?} is part of the snapshot twice");
+        }
+        let Some(found) = tx.get::<tables::PlainAccountState>(address)? else {
+            diff.summary.differing_accounts += 1;
+            diff.record(address, AccountDiff::MissingInDatabase, limit);
+            return Ok(())
+        };
+
+        let mut diffs = Vec::new();
+        if expected.balance != found.balance {
+            diffs.push(AccountDiff::Balance { expected: expected.balance, found: found.balance });
+        }
+        let nonce = expected.nonce.unwrap_or_default();
+        if nonce != found.nonce {
+            diffs.push(AccountDiff::Nonce { expected: nonce, found: found.nonce });
+        }
+        let (code_hash, found_code_hash) =
+            (code_hash(expected.code_hash), code_hash(found.bytecode_hash));
+        if code_hash != found_code_hash {
+            diffs.push(AccountDiff::CodeHash { expected: code_hash, found: found_code_hash });
+        }
+        let mut slots = BTreeMap::new();
+        let mut entry = storage.seek_by_key_subkey(address, H256::zero())?;
+        while let Some(slot) = entry {
+            slots.insert(slot.key, slot.value);
+            entry = storage.next_dup_val()?;
+        }
+        diffs.extend(storage_diffs(&expected.storage.unwrap_or_default(), &slots));
+
+        if !diffs.is_empty() {
+            diff.summary.differing_accounts += 1;
+        }
+        for account_diff in diffs {
+            diff.record(address, account_diff, limit);
+        }
+        Ok(())
+    })?;
+
+    let mut accounts = tx.cursor_read::<tables::PlainAccountState>()?;
+    let mut entry = accounts.first()?;
+    while let Some((address, _)) = entry {
+        diff.summary.database_accounts += 1;
+        if !seen.contains(&address) {
+            diff.summary.differing_accounts += 1;
+            diff.record(address, AccountDiff::MissingInSnapshot, limit);
+        }
+        entry = accounts.next()?;
+    }
+    Ok(diff)
+}
+
+/// The code hash of an account, with the hash of the empty code standing for no code
+fn code_hash(hash: Option<H256>) -> Option<H256> {
+    hash.filter(|hash| *hash != KECCAK_EMPTY)
+}
+
+/// Compares the storage of a snapshot account to the slots of the database, in slot order.
+/// Slots set to zero are equal to unset slots.
+fn storage_diffs(
+    expected: &BTreeMap<H256, U256>,
+    found: &BTreeMap<H256, U256>,
+) -> Vec<AccountDiff> {
+    let mut slots = expected.keys().chain(found.keys()).copied().collect::<Vec<_>>();
+    slots.sort_unstable();
+    slots.dedup();
+    slots
+        .into_iter()
+        .filter_map(|slot| {
+            let expected = expected.get(&slot).copied().unwrap_or_default();
+            let found = found.get(&slot).copied().unwrap_or_default();
+            (expected != found).then_some(AccountDiff::Storage { slot, expected, found })
+        })
+        .collect()
+}
@@ -0,0 +1,201 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+
+use clap::Parser;
+use eyre::Result;
+use flate2::{write::GzEncoder, Compression};
+use reth::runner::CliContext;
+use reth_db::{
+    cursor::{DbCursorRO, DbDupCursorRO},
+    database::Database,
+    models::storage_sharded_key::StorageShardedKey,
+    tables,
+    transaction::DbTx,
+};
+use reth_primitives::{Account, Address, Bytes, H256, KECCAK_EMPTY, U256};
+use serde::Serialize;
+
+use crate::cli::{args::DatabaseArgs, dirs, replay};
+
+/// Export the state of the database as a genesis `alloc` JSON object.
+///
+/// Walks the plain state, or the state after `--block` reconstructed from the history written by
+/// `replay`, and writes every account with its balance, nonce, code and storage, keyed by address.
+/// geth reads the file as the `alloc` of a genesis and `state import` imports it again, so the
+/// state can be migrated back out of the database. A path ending in `.gz` is gzip-compressed.
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The file to write the state to
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    out: PathBuf,
+
+    /// Only export the account at this address. Repeat to export several accounts.
+    #[arg(long = "address", value_name = "ADDRESS", verbatim_doc_comment)]
+    addresses: Vec<Address>,
+
+    /// Export the state after this block instead of the state of the database
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment)]
+    block: Option<u64>,
+}
+
+/// An account in the genesis `alloc` layout. Storage values are full words like geth expects
+/// them, and the code hash is kept for `state import`, which geth ignores.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AllocAccount {
+    balance: U256,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nonce: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code_hash: Option<H256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<Bytes>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    storage: BTreeMap<H256, H256>,
+}
+
+/// The outcome of a state export
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StateExport {
+    /// The number of exported accounts
+    pub accounts: u64,
+    /// The number of exported non-zero storage slots
+    pub slots: u64,
+}
+
+/// Writes the state to `out` as a genesis `alloc` JSON object: the state after `block`, or the
+/// plain state without one, of the accounts at `addresses`, or of every account if none are
+/// given. Accounts are written in the order of their addresses.
+pub fn export_state<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    addresses: &[Address],
+    block: Option<u64>,
+    out: &mut dyn Write,
+) -> Result<StateExport> {
+    // The plain state is the state after the block the database ends at
+    let historical = match block {
+        Some(block) => replay::historical_transition(tx, block)?.map(|_| block),
+        None => None,
+    };
+
+    let mut export = StateExport::default();
+    out.write_all(b"{")?;
+    let mut write = |address: Address, account: Account| -> Result<()> {
+        let storage = storage(tx, address, historical)?;
+        let code = match account.bytecode_hash.filter(|hash| *hash != KECCAK_EMPTY) {
+            Some(hash) => Some(
+                tx.get::<tables::Bytecodes>(hash)?
+                    .ok_or_else(|| eyre::eyre!("Code {hash:?} of account {address:?} not found"))?,
+            ),
+            None => None,
+        };
+        let account = AllocAccount {
+            balance: account.balance,
+            nonce: (account.nonce != 0).then_some(account.nonce),
+            code_hash: code.as_ref().and(account.bytecode_hash),
+            code: code.map(Bytes::from),
+            storage,
+        };
+        if export.accounts > 0 {
+            out.write_all(b",")?;
+        }
+        write!(out, "\n  \"{address:?}\": ")?;
+        serde_json::to_writer(&mut *out, &account)?;
+        export.accounts += 1;
+        export.slots += account.storage.len() as u64;
+        Ok(())
+    };
+
+    if addresses.is_empty() && historical.is_none() {
+        let mut cursor = tx.cursor_read::<tables::PlainAccountState>()?;
+        let mut entry = cursor.first()?;
+        while let Some((address, account)) = entry {
+            write(address, account)?;
+            entry = cursor.next()?;
+        }
+    } else {
+        let addresses = if addresses.is_empty() {
+            replay::historical_accounts(tx)?
+        } else {
+            addresses.iter().copied().collect()
+        };
+        for address in addresses {
+            let account = match historical {
+                Some(block) => replay::account_at(tx, address, block)?,
+                None => tx.get::<tables::PlainAccountState>(address)?,
+            };
+            if let Some(account) = account {
+                write(address, account)?;
+            }
+        }
+    }
+    out.write_all(b"\n}\n")?;
+    Ok(export)
+}
+
+/// The non-zero storage slots of the account at `address` in the plain state, or after the
+/// `historical` block
+fn storage<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    address: Address,
+    historical: Option<u64>,
+) -> Result<BTreeMap<H256, H256>> {
+    let mut slots = BTreeMap::new();
+    let mut plain = tx.cursor_dup_read::<tables::PlainStorageState>()?;
+    let mut entry = plain.seek_by_key_subkey(address, H256::zero())?;
+    while let Some(slot) = entry {
+        slots.insert(slot.key, slot.value);
+        entry = plain.next_dup_val()?;
+    }
+
+    if let Some(block) = historical {
+        // Slots cleared since the block only appear in the history
+        let mut history = tx.cursor_read::<tables::StorageHistory>()?;
+        let mut entry = history.seek(StorageShardedKey::new(address, H256::zero(), 0))?;
+        let mut keys = slots.keys().copied().collect::<BTreeSet<_>>();
+        while let Some((key, _)) = entry.filter(|(key, _)| key.address == address) {
+            keys.insert(key.sharded_key.key);
+            entry = history.next()?;
+        }
+        slots.clear();
+        for key in keys {
+            slots.insert(key, replay::storage_at(tx, address, key, block)?);
+        }
+    }
+    Ok(slots
+        .into_iter()
+        .filter(|(_, value)| *value != U256::ZERO)
+        .map(|(key, value)| (key, H256::from(value.to_be_bytes::<32>())))
+        .collect())
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db = self.db.open_ro()?;
+        let tx = db.tx()?;
+        tracing::info!(target: "reth::cli", out = %self.out.display(), block = ?self.block, "Exporting state");
+        let file = BufWriter::new(File::create(&self.out)?);
+        let export = if dirs::has_extension(&self.out, "gz") {
+            let mut out = GzEncoder::new(file, Compression::default());
+            let export = export_state(&tx, &self.addresses, self.block, &mut out)?;
+            out.finish()?.flush()?;
+            export
+        } else {
+            let mut out = file;
+            let export = export_state(&tx, &self.addresses, self.block, &mut out)?;
+            out.flush()?;
+            export
+        };
+        println!("Accounts:        {}", export.accounts);
+        println!("Storage slots:   {}", export.slots);
+        Ok(())
+    }
+}
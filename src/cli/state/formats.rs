@@ -0,0 +1,380 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{BufRead, BufReader, Read},
+    marker::PhantomData,
+};
+
+use clap::ValueEnum;
+use eyre::Result;
+use reth_primitives::{Address, H256, KECCAK_EMPTY, U256};
+use serde::{
+    de::{self, DeserializeOwned, DeserializeSeed, IgnoredAny, MapAccess, Visitor},
+    Deserialize, Deserializer as _,
+};
+
+use crate::cli::state::{ExportedAccount, State};
+
+/// The number of leading bytes the layout of a streamed state dump is detected from
+const DETECT_LEN: u64 = 4096;
+
+/// Receives the accounts of a state dump one at a time, in the order they are dumped in
+pub type AccountVisitor<'a> = dyn FnMut(Address, ExportedAccount) -> Result<()> + 'a;
+
+/// The layout of a state dump
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StateFormat {
+    /// A JSON object of the accounts keyed by address, in the genesis `alloc` layout
+    Alloc,
+    /// The JSON object written by `geth dump`, holding the state root and the accounts
+    GethDump,
+    /// The line-delimited JSON written by `geth dump --iterative`, a line with the state root
+    /// followed by a line per account
+    GethDumpLines,
+    /// A CSV file with an `address`, `nonce`, `balance`, `code_hash`, `code`, `storage_key` and
+    /// `storage_value` column, holding a row per account and a row per storage slot
+    ErigonCsv,
+}
+
+/// Detects the layout of a state dump from its first key or its CSV header
+pub fn detect_state_format(contents: &[u8]) -> Option<StateFormat> {
+    let start = contents.iter().position(|byte| !byte.is_ascii_whitespace())?;
+    let contents = &contents[start..];
+    if contents.starts_with(b"address") {
+        return Some(StateFormat::ErigonCsv)
+    }
+    if contents[0] != b'{' {
+        return None
+    }
+    let key = contents[1..].iter().position(|byte| *byte == b'"').and_then(|quote| {
+        let key = &contents[quote + 2..];
+        Some(&key[..key.iter().position(|byte| *byte == b'"')?])
+    })?;
+    if key != b"root" {
+        return Some(StateFormat::Alloc)
+    }
+    // The iterative dump writes the root on a line of its own
+    let first_line = contents.split(|byte| *byte == b'\n').next().unwrap_or_default();
+    match serde_json::from_slice::<DumpRoot>(first_line) {
+        Ok(_) => Some(StateFormat::GethDumpLines),
+        Err(_) => Some(StateFormat::GethDump),
+    }
+}
+
+/// Decodes a state dump, detecting its layout. See [decode_state_as].
+pub fn decode_state(contents: &[u8]) -> Result<State> {
+    collect_state(|visit| stream_state(contents, visit))
+}
+
+/// Decodes a state dump in the given layout. See [stream_state_as].
+pub fn decode_state_as(format: StateFormat, contents: &[u8]) -> Result<State> {
+    collect_state(|visit| stream_state_as(format, contents, visit))
+}
+
+/// Collects the accounts streamed by `stream` into a state, refusing accounts dumped twice
+pub fn collect_state(stream: impl FnOnce(&mut AccountVisitor<'_>) -> Result<()>) -> Result<State> {
+    let mut state = State::new();
+    stream(&mut |address, account| {
+        if state.insert(address, account).is_some() {
+            eyre::bail!("Account {address:?} is dumped twice");
+        }
+        Ok(())
+    })?;
+    Ok(state)
+}
+
+/// Streams the accounts of a state dump to `visit`, detecting its layout from its leading bytes.
+/// See [stream_state_as].
+pub fn stream_state(mut reader: impl Read, visit: &mut AccountVisitor<'_>) -> Result<()> {
+    let mut head = Vec::new();
+    reader.by_ref().take(DETECT_LEN).read_to_end(&mut head)?;
+    let format = detect_state_format(&head)
+        .ok_or_else(|| eyre::eyre!("Unable to detect the layout of the state dump"))?;
+    tracing::debug!(target: "reth::cli", ?format, "Detected state dump layout");
+    stream_state_as(format, head.as_slice().chain(reader), visit)
+}
+
+/// Streams the accounts of a state dump in the given layout to `visit`, decoding one account at a
+/// time, so the memory used doesn't grow with the size of the dump.
+///
+/// The dumps of geth and Erigon key accounts by address and storage by slot only if they were
+/// written with preimages. Accounts without an address are refused. The rows of an account in an
+/// Erigon CSV dump have to be adjacent.
+pub fn stream_state_as(
+    format: StateFormat,
+    reader: impl Read,
+    visit: &mut AccountVisitor<'_>,
+) -> Result<()> {
+    let reader = BufReader::new(reader);
+    match format {
+        StateFormat::Alloc => stream_alloc(reader, visit),
+        StateFormat::GethDump => stream_geth_dump(reader, visit),
+        StateFormat::GethDumpLines => stream_geth_dump_lines(reader, visit),
+        StateFormat::ErigonCsv => stream_erigon_csv(reader, visit),
+    }
+}
+
+/// Visits the entries of a JSON object of accounts one at a time instead of collecting them.
+///
+/// Errors of the account visitor are kept in `error`, since the deserializer only reports its own.
+struct AccountsSeed<'a, 'b, K, A> {
+    visit: &'a mut AccountVisitor<'b>,
+    error: &'a mut Option<eyre::Report>,
+    convert: fn(K, A) -> Result<(Address, ExportedAccount)>,
+    _entry: PhantomData<(K, A)>,
+}
+
+impl<'a, 'b, K, A> AccountsSeed<'a, 'b, K, A> {
+    fn new(
+        visit: &'a mut AccountVisitor<'b>,
+        error: &'a mut Option<eyre::Report>,
+        convert: fn(K, A) -> Result<(Address, ExportedAccount)>,
+    ) -> Self {
+        Self { visit, error, convert, _entry: PhantomData }
+    }
+}
+
+impl<'de, K: DeserializeOwned, A: DeserializeOwned> Visitor<'de> for AccountsSeed<'_, '_, K, A> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an object of accounts")
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<(), M::Error> {
+        while let Some(key) = map.next_key::<K>()? {
+            let account = map.next_value::<A>()?;
+            let visited = (self.convert)(key, account)
+                .and_then(|(address, account)| (self.visit)(address, account));
+            if let Err(err) = visited {
+                *self.error = Some(err);
+                return Err(de::Error::custom("aborted"))
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'de, K: DeserializeOwned, A: DeserializeOwned> DeserializeSeed<'de>
+    for AccountsSeed<'_, '_, K, A>
+{
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+/// Visits the `accounts` of a geth dump, skipping its other fields
+struct GethDumpVisitor<'a, 'b>(AccountsSeed<'a, 'b, String, GethDumpAccount>);
+
+impl<'de> Visitor<'de> for GethDumpVisitor<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a geth dump")
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<(), M::Error> {
+        let mut accounts = Some(self.0);
+        while let Some(key) = map.next_key::<String>()? {
+            if key != "accounts" {
+                map.next_value::<IgnoredAny>()?;
+                continue
+            }
+            let Some(seed) = accounts.take() else {
+                return Err(de::Error::duplicate_field("accounts"))
+            };
+            map.next_value_seed(seed)?;
+        }
+        match accounts {
+            Some(_) => Err(de::Error::missing_field("accounts")),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns the error of the account visitor if it aborted the deserialization, otherwise the
+/// result of the deserializer
+fn finish(result: serde_json::Result<()>, error: Option<eyre::Report>) -> Result<()> {
+    match error {
+        Some(err) => Err(err),
+        None => Ok(result?),
+    }
+}
+
+fn stream_alloc(reader: impl Read, visit: &mut AccountVisitor<'_>) -> Result<()> {
+    let mut error = None;
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let seed =
+        AccountsSeed::new(visit, &mut error, |address: Address, account: ExportedAccount| {
+            Ok((address, account))
+        });
+    let result = (&mut deserializer).deserialize_map(seed).and_then(|_| deserializer.end());
+    finish(result, error)
+}
+
+fn stream_geth_dump(reader: impl Read, visit: &mut AccountVisitor<'_>) -> Result<()> {
+    let mut error = None;
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let seed = AccountsSeed::new(visit, &mut error, |key: String, account: GethDumpAccount| {
+        account.into_exported(Some(&key))
+    });
+    let result =
+        (&mut deserializer).deserialize_map(GethDumpVisitor(seed)).and_then(|_| deserializer.end());
+    finish(result, error)
+}
+
+/// The first line of an iterative geth dump
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DumpRoot {
+    #[allow(dead_code)]
+    root: H256,
+}
+
+/// An account of a geth dump. Balances are decimal, storage values are hex without a prefix.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GethDumpAccount {
+    balance: String,
+    #[serde(default)]
+    nonce: u64,
+    root: Option<H256>,
+    code_hash: Option<H256>,
+    code: Option<String>,
+    storage: Option<BTreeMap<H256, String>>,
+    address: Option<Address>,
+}
+
+impl GethDumpAccount {
+    /// Converts the account, falling back to `key` as its address
+    fn into_exported(self, key: Option<&str>) -> Result<(Address, ExportedAccount)> {
+        let address = match (self.address, key) {
+            (Some(address), _) => address,
+            (None, Some(key)) => key.parse().map_err(|_| missing_address(key))?,
+            (None, None) => return Err(missing_address("of the dump")),
+        };
+        let storage = self
+            .storage
+            .map(|storage| {
+                storage
+                    .into_iter()
+                    .map(|(slot, value)| Ok((slot, parse_hex(&value)?)))
+                    .collect::<Result<BTreeMap<_, _>>>()
+            })
+            .transpose()?;
+        let account = ExportedAccount {
+            balance: parse_quantity(&self.balance)?,
+            code_hash: self.code_hash.filter(|hash| *hash != KECCAK_EMPTY),
+            code: self.code.map(|code| code.trim_start_matches("0x").to_string()),
+            nonce: Some(self.nonce),
+            root: self.root,
+            storage: storage.filter(|storage| !storage.is_empty()),
+        };
+        Ok((address, account))
+    }
+}
+
+fn missing_address(key: &str) -> eyre::Report {
+    eyre::eyre!("Account {key} has no address, dump the state with preimages")
+}
+
+fn stream_geth_dump_lines(reader: impl BufRead, visit: &mut AccountVisitor<'_>) -> Result<()> {
+    for (index, line) in reader.split(b'\n').enumerate() {
+        let line = line?;
+        if line.iter().all(u8::is_ascii_whitespace) || (index == 0 && is_root(&line)) {
+            continue
+        }
+        let account: GethDumpAccount = serde_json::from_slice(&line)
+            .map_err(|err| eyre::eyre!("Invalid account on line {}: {err}", index + 1))?;
+        let (address, account) = account.into_exported(None)?;
+        visit(address, account)?;
+    }
+    Ok(())
+}
+
+fn is_root(line: &[u8]) -> bool {
+    serde_json::from_slice::<DumpRoot>(line).is_ok()
+}
+
+fn stream_erigon_csv(reader: impl BufRead, visit: &mut AccountVisitor<'_>) -> Result<()> {
+    let mut lines = reader.lines().enumerate();
+    let header = lines.next().map(|(_, header)| header).transpose()?.unwrap_or_default();
+    let columns = header.split(',').map(str::trim).collect::<Vec<_>>();
+    let column = |name: &str| columns.iter().position(|column| *column == name);
+    let Some(address_column) = column("address") else {
+        eyre::bail!("The CSV header has no address column")
+    };
+
+    // The rows of an account are merged until the rows of the next account start
+    let mut current: Option<(Address, ExportedAccount)> = None;
+    for (index, line) in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue
+        }
+        let invalid = |err: eyre::Report| eyre::eyre!("Invalid row on line {}: {err}", index + 1);
+        let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+        let field = |name: &str| {
+            column(name).and_then(|column| fields.get(column)).copied().filter(|v| !v.is_empty())
+        };
+        let address: Address = fields
+            .get(address_column)
+            .ok_or_else(|| eyre::eyre!("Missing address"))
+            .and_then(|address| address.parse().map_err(|_| eyre::eyre!("Invalid address")))
+            .map_err(invalid)?;
+        if current.as_ref().map_or(true, |(current, _)| *current != address) {
+            if let Some((address, account)) = current.replace((address, Default::default())) {
+                visit(address, account)?;
+            }
+        }
+        let (_, account) = current.as_mut().expect("account of the row");
+        merge_row(account, field).map_err(invalid)?;
+    }
+    if let Some((address, account)) = current {
+        visit(address, account)?;
+    }
+    Ok(())
+}
+
+/// Merges a row of an Erigon CSV dump into its account, given the fields of the row by column
+fn merge_row<'a>(
+    account: &mut ExportedAccount,
+    field: impl Fn(&str) -> Option<&'a str>,
+) -> Result<()> {
+    if let Some(slot) = field("storage_key") {
+        let slot: H256 = slot.parse().map_err(|_| eyre::eyre!("Invalid slot {slot}"))?;
+        let value = field("storage_value").map(parse_hex).transpose()?.unwrap_or_default();
+        account.storage.get_or_insert_with(BTreeMap::new).insert(slot, value);
+        return Ok(())
+    }
+    if let Some(balance) = field("balance") {
+        account.balance = parse_quantity(balance)?;
+    }
+    account.nonce = field("nonce").map(|nonce| nonce.parse()).transpose()?;
+    account.code_hash = field("code_hash")
+        .map(|hash| hash.parse().map_err(|_| eyre::eyre!("Invalid code hash {hash}")))
+        .transpose()?
+        .filter(|hash| *hash != KECCAK_EMPTY);
+    account.code = field("code").map(|code| code.trim_start_matches("0x").to_string());
+    Ok(())
+}
+
+/// Parses a decimal or `0x`-prefixed hex quantity
+fn parse_quantity(value: &str) -> Result<U256> {
+    match value.strip_prefix("0x") {
+        Some(_) => parse_hex(value),
+        None => U256::from_str_radix(value, 10)
+            .map_err(|err| eyre::eyre!("Invalid quantity {value}: {err}")),
+    }
+}
+
+/// Parses a hex quantity with or without a `0x` prefix
+fn parse_hex(value: &str) -> Result<U256> {
+    let digits = value.trim_start_matches("0x");
+    if digits.is_empty() {
+        return Ok(U256::ZERO)
+    }
+    U256::from_str_radix(digits, 16).map_err(|err| eyre::eyre!("Invalid quantity {value}: {err}"))
+}
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+
+use eyre::Result;
+use reth_db::{cursor::DbDupCursorRO, tables, transaction::DbTx};
+use reth_primitives::{proofs::EMPTY_ROOT, Address, Bytes, H256, KECCAK_EMPTY, U256};
+use serde::Serialize;
+
+use crate::cli::keccak::keccak256;
+
+/// A Merkle proof of an account and some of its storage slots against a state root, in the
+/// layout of an `eth_getProof` response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProof {
+    /// The address of the account
+    pub address: Address,
+    /// The balance of the account, zero if it doesn't exist
+    pub balance: U256,
+    /// The nonce of the account, zero if it doesn't exist
+    pub nonce: u64,
+    /// The hash of the code of the account, the hash of the empty code if it has none
+    pub code_hash: H256,
+    /// The root of the storage trie of the account
+    pub storage_hash: H256,
+    /// The trie nodes from the state root to the account, or to where it would be
+    pub account_proof: Vec<Bytes>,
+    /// The proofs of the requested storage slots
+    pub storage_proof: Vec<StorageProof>,
+}
+
+/// A Merkle proof of a storage slot against the storage root of its account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StorageProof {
+    /// The slot
+    pub key: H256,
+    /// The value of the slot, zero if it is unset
+    pub value: U256,
+    /// The trie nodes from the storage root to the slot, or to where it would be
+    pub proof: Vec<Bytes>,
+}
+
+/// Builds the proof of an account and the given storage slots from the trie tables written by
+/// `state hash-and-trie`, starting at `state_root`.
+///
+/// Accounts and slots that don't exist are proven absent, with the nodes up to where they would
+/// be. Fails if a node on the way is missing, like when the trie tables were built for another
+/// state root.
+pub fn prove_account<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    state_root: H256,
+    address: Address,
+    slots: &[H256],
+) -> Result<AccountProof> {
+    let (account_proof, leaf) =
+        prove(state_root, keccak256(address), |hash| Ok(tx.get::<tables::AccountsTrie>(hash)?))?;
+    let mut proof = AccountProof {
+        address,
+        balance: U256::ZERO,
+        nonce: 0,
+        code_hash: KECCAK_EMPTY,
+        storage_hash: EMPTY_ROOT,
+        account_proof,
+        storage_proof: Vec::with_capacity(slots.len()),
+    };
+    if let Some(leaf) = leaf {
+        let account = rlp::Rlp::new(&leaf);
+        proof.nonce = account.val_at(0)?;
+        proof.balance = decode_u256(account.at(1)?.data()?)?;
+        proof.storage_hash = decode_h256(account.at(2)?.data()?)?;
+        proof.code_hash = decode_h256(account.at(3)?.data()?)?;
+    }
+
+    let hashed_address = keccak256(address);
+    let mut storage = tx.cursor_dup_read::<tables::StoragesTrie>()?;
+    for slot in slots {
+        let (nodes, leaf) = prove(proof.storage_hash, keccak256(slot), |hash| {
+            let entry = storage.seek_by_key_subkey(hashed_address, hash)?;
+            Ok(entry.filter(|entry| entry.hash == hash).map(|entry| entry.node))
+        })?;
+        let value = match leaf {
+            Some(leaf) => decode_u256(rlp::Rlp::new(&leaf).data()?)?,
+            None => U256::ZERO,
+        };
+        proof.storage_proof.push(StorageProof { key: *slot, value, proof: nodes });
+    }
+    Ok(proof)
+}
+
+/// Checks the proof of an account and its storage slots against `state_root`, independent of the
+/// database. Fails if a proof doesn't lead from its root to the claimed value.
+pub fn verify_account_proof(state_root: H256, proof: &AccountProof) -> Result<()> {
+    let leaf = verify_proof(state_root, keccak256(proof.address), &proof.account_proof)?;
+    let (nonce, balance, storage_hash, code_hash) = match leaf {
+        Some(leaf) => {
+            let account = rlp::Rlp::new(&leaf);
+            (
+                account.val_at(0)?,
+                decode_u256(account.at(1)?.data()?)?,
+                decode_h256(account.at(2)?.data()?)?,
+                decode_h256(account.at(3)?.data()?)?,
+            )
+        }
+        None => (0, U256::ZERO, EMPTY_ROOT, KECCAK_EMPTY),
+    };
+    if (nonce, balance, storage_hash, code_hash) !=
+        (proof.nonce, proof.balance, proof.storage_hash, proof.code_hash)
+    {
+        eyre::bail!("The account proof of {:?} proves a different account", proof.address);
+    }
+    for slot in &proof.storage_proof {
+        let value = match verify_proof(proof.storage_hash, keccak256(slot.key), &slot.proof)? {
+            Some(leaf) => decode_u256(rlp::Rlp::new(&leaf).data()?)?,
+            None => U256::ZERO,
+        };
+        if value != slot.value {
+            eyre::bail!("The storage proof of slot {:?} proves the value {value}", slot.key);
+        }
+    }
+    Ok(())
+}
+
+/// Walks the trie with the given root down to `key`, loading the nodes referenced by hash with
+/// `load`. Returns the nodes on the way, starting with the root, and the value stored at the key.
+///
+/// Nodes shorter than 32 bytes are embedded in their parent and not part of the proof, like in
+/// the proofs of geth.
+pub fn prove(
+    root: H256,
+    key: H256,
+    mut load: impl FnMut(H256) -> Result<Option<Vec<u8>>>,
+) -> Result<(Vec<Bytes>, Option<Vec<u8>>)> {
+    let mut proof = Vec::new();
+    if root == EMPTY_ROOT {
+        return Ok((proof, None))
+    }
+    let nibbles =
+        key.as_bytes().iter().flat_map(|byte| [byte >> 4, byte & 0x0f]).collect::<Vec<_>>();
+    let mut path = &nibbles[..];
+    let mut node = load(root)?.ok_or_else(|| eyre::eyre!("Trie node {root:?} not found"))?;
+    proof.push(Bytes::from(node.clone()));
+    loop {
+        let item = rlp::Rlp::new(&node);
+        let child = match item.item_count()? {
+            17 => match path.split_first() {
+                Some((nibble, rest)) => {
+                    path = rest;
+                    item.at(*nibble as usize)?
+                }
+                None => {
+                    let value = item.at(16)?.data()?;
+                    return Ok((proof, (!value.is_empty()).then(|| value.to_vec())))
+                }
+            },
+            2 => {
+                let (is_leaf, node_path) = decode_path(item.at(0)?.data()?)?;
+                if is_leaf {
+                    let value =
+                        if node_path == path { Some(item.at(1)?.data()?.to_vec()) } else { None };
+                    return Ok((proof, value))
+                }
+                if !path.starts_with(&node_path) {
+                    return Ok((proof, None))
+                }
+                path = &path[node_path.len()..];
+                item.at(1)?
+            }
+            count => eyre::bail!("Malformed trie node with {count} items"),
+        };
+
+        node = if child.is_list() {
+            // Embedded node
+            child.as_raw().to_vec()
+        } else if child.is_empty() {
+            return Ok((proof, None))
+        } else {
+            let hash = decode_h256(child.data()?)?;
+            let node = load(hash)?.ok_or_else(|| eyre::eyre!("Trie node {hash:?} not found"))?;
+            proof.push(Bytes::from(node.clone()));
+            node
+        };
+    }
+}
+
+/// Walks the given proof from `root` down to `key` and returns the value it proves, or `None` if
+/// it proves that the key is absent. Fails if the proof is missing a node on the way.
+pub fn verify_proof(root: H256, key: H256, proof: &[Bytes]) -> Result<Option<Vec<u8>>> {
+    let nodes = proof.iter().map(|node| (keccak256(node), node)).collect::<HashMap<_, _>>();
+    let (walked, value) = prove(root, key, |hash| Ok(nodes.get(&hash).map(|node| node.to_vec())))?;
+    if walked.len() != proof.len() {
+        eyre::bail!(
+            "The proof holds {} nodes not on the path to the key",
+            proof.len() - walked.len()
+        );
+    }
+    Ok(value)
+}
+
+/// Decodes the hex-prefix encoded path of a leaf or extension node into its nibbles, returning
+/// whether the node is a leaf
+fn decode_path(encoded: &[u8]) -> Result<(bool, Vec<u8>)> {
+    let Some((first, rest)) = encoded.split_first() else {
+        eyre::bail!("Empty path in a trie node")
+    };
+    let flag = first >> 4;
+    if flag > 3 {
+        eyre::bail!("Invalid path prefix {flag} in a trie node");
+    }
+    let mut nibbles = Vec::with_capacity(rest.len() * 2 + 1);
+    if flag & 1 == 1 {
+        nibbles.push(first & 0x0f);
+    }
+    nibbles.extend(rest.iter().flat_map(|byte| [byte >> 4, byte & 0x0f]));
+    Ok((flag & 2 == 2, nibbles))
+}
+
+fn decode_h256(data: &[u8]) -> Result<H256> {
+    if data.len() != 32 {
+        eyre::bail!("Expected a 32 byte hash, got {} bytes", data.len());
+    }
+    Ok(H256::from_slice(data))
+}
+
+fn decode_u256(data: &[u8]) -> Result<U256> {
+    U256::try_from_be_slice(data)
+        .ok_or_else(|| eyre::eyre!("Value of {} bytes exceeds 256 bits", data.len()))
+}
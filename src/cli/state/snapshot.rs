@@ -0,0 +1,480 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::{Parser, Subcommand};
+use eyre::Result;
+use reth::runner::CliContext;
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::{Account, Address, H256, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{
+    args::DatabaseArgs,
+    checksum, compression, journal,
+    state::{stream_database_state, stream_state_root, write_storage, ExportedAccount},
+};
+
+/// The version of the snapshot format written by `state snapshot create`
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// The name of the manifest within a snapshot directory
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// The default size in bytes of the records of a chunk before compression
+pub const DEFAULT_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+/// The zstd level chunks are compressed with
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// The tag of an account record, followed by the account and its storage
+const ACCOUNT_RECORD: u8 = 1;
+
+/// The tag of a bytecode record, followed by the code hash and the code
+const BYTECODE_RECORD: u8 = 2;
+
+/// State snapshot command
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(subcommand)]
+    command: Subcommands,
+}
+
+/// State snapshot subcommands
+#[derive(Debug, Subcommand)]
+pub enum Subcommands {
+    /// Write the plain state of the database into a chunked snapshot
+    #[command(name = "create")]
+    Create(CreateCommand),
+    /// Restore the plain state of a snapshot into an empty database
+    #[command(name = "restore")]
+    Restore(RestoreCommand),
+}
+
+/// Write the accounts, storage and bytecodes of the plain state into a snapshot directory, so that
+/// migrated databases can be distributed without shipping MDBX directories.
+///
+/// The snapshot is a set of zstd-compressed binary chunks with a `manifest.json` listing the
+/// sha256 digest of every chunk, the state root and the canonical tip of the database.
+#[derive(Debug, Parser)]
+pub struct CreateCommand {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The directory to write the snapshot to. Created if it doesn't exist, and must not hold a
+    /// snapshot already.
+    #[arg(long, value_name = "DIR", verbatim_doc_comment)]
+    out: PathBuf,
+
+    /// The size in bytes of the records of a chunk before compression. An account with more
+    /// storage than fits is written into a chunk of its own.
+    #[arg(
+        long = "chunk-size",
+        value_name = "BYTES",
+        default_value_t = DEFAULT_CHUNK_SIZE,
+        verbatim_doc_comment
+    )]
+    chunk_size: usize,
+}
+
+/// Restore the plain state of a snapshot written by `state snapshot create`.
+///
+/// Every chunk is checked against the digest of the manifest before anything is written, and the
+/// state root of the restored accounts against the root of the manifest. The database must not
+/// hold any accounts yet. Run `state hash-and-trie` afterwards to build the trie tables.
+#[derive(Debug, Parser)]
+pub struct RestoreCommand {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The snapshot directory holding `manifest.json`
+    #[arg(long, value_name = "DIR", verbatim_doc_comment)]
+    path: PathBuf,
+}
+
+/// The manifest of a snapshot, listing its chunks in the order they are restored
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotManifest {
+    /// The version of the snapshot format
+    pub version: u32,
+    /// The canonical tip of the database the snapshot was created from
+    pub block: Option<u64>,
+    /// The hash of the canonical tip
+    pub block_hash: Option<H256>,
+    /// The state root of the accounts of the snapshot
+    pub state_root: H256,
+    /// The number of accounts
+    pub accounts: u64,
+    /// The number of storage slots
+    pub storage_slots: u64,
+    /// The number of bytecodes
+    pub bytecodes: u64,
+    /// The chunks of the snapshot
+    pub chunks: Vec<SnapshotChunk>,
+}
+
+/// A chunk of a snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotChunk {
+    /// The name of the chunk file within the snapshot directory
+    pub file: String,
+    /// The number of records of the chunk
+    pub records: u64,
+    /// The size of the compressed chunk in bytes
+    pub size: u64,
+    /// The hex-encoded sha256 digest of the compressed chunk
+    pub sha256: String,
+}
+
+impl SnapshotManifest {
+    /// Reads the manifest of the snapshot in the given directory
+    pub fn read(dir: &Path) -> Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        let manifest: Self = serde_json::from_slice(&fs::read(&path)?)
+            .map_err(|err| eyre::eyre!("Invalid snapshot manifest {}: {err}", path.display()))?;
+        if manifest.version != SNAPSHOT_VERSION {
+            eyre::bail!(
+                "Unsupported snapshot version {}, expected {SNAPSHOT_VERSION}",
+                manifest.version
+            );
+        }
+        Ok(manifest)
+    }
+}
+
+/// A record of a snapshot chunk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotRecord {
+    /// An account of the plain state with its storage
+    Account { address: Address, account: Account, storage: BTreeMap<H256, U256> },
+    /// A bytecode with its hash
+    Bytecode { hash: H256, code: Vec<u8> },
+}
+
+impl SnapshotRecord {
+    /// Appends the binary encoding of the record. Balances and storage values are written as
+    /// their big-endian bytes without leading zeroes, prefixed by their length.
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            SnapshotRecord::Account { address, account, storage } => {
+                out.push(ACCOUNT_RECORD);
+                out.extend_from_slice(address.as_bytes());
+                out.extend_from_slice(&account.nonce.to_be_bytes());
+                encode_u256(account.balance, out);
+                match account.bytecode_hash {
+                    Some(hash) => {
+                        out.push(1);
+                        out.extend_from_slice(hash.as_bytes());
+                    }
+                    None => out.push(0),
+                }
+                out.extend_from_slice(&(storage.len() as u32).to_be_bytes());
+                for (key, value) in storage {
+                    out.extend_from_slice(key.as_bytes());
+                    encode_u256(*value, out);
+                }
+            }
+            SnapshotRecord::Bytecode { hash, code } => {
+                out.push(BYTECODE_RECORD);
+                out.extend_from_slice(hash.as_bytes());
+                out.extend_from_slice(&(code.len() as u32).to_be_bytes());
+                out.extend_from_slice(code);
+            }
+        }
+    }
+
+    /// Decodes the records of an uncompressed chunk
+    pub fn decode_all(mut data: &[u8]) -> Result<Vec<Self>> {
+        let mut records = Vec::new();
+        while !data.is_empty() {
+            records.push(Self::decode(&mut data)?);
+        }
+        Ok(records)
+    }
+
+    /// Decodes the record at the start of `data` and advances past it
+    fn decode(data: &mut &[u8]) -> Result<Self> {
+        match take(data, 1)?[0] {
+            ACCOUNT_RECORD => {
+                let address = Address::from_slice(take(data, 20)?);
+                let nonce = u64::from_be_bytes(take(data, 8)?.try_into()?);
+                let balance = decode_u256(data)?;
+                let bytecode_hash = match take(data, 1)?[0] {
+                    0 => None,
+                    1 => Some(H256::from_slice(take(data, 32)?)),
+                    flag => eyre::bail!("Invalid code hash flag {flag} of account {address:?}"),
+                };
+                let slots = u32::from_be_bytes(take(data, 4)?.try_into()?);
+                let mut storage = BTreeMap::new();
+                for _ in 0..slots {
+                    let key = H256::from_slice(take(data, 32)?);
+                    storage.insert(key, decode_u256(data)?);
+                }
+                let account = Account { nonce, balance, bytecode_hash };
+                Ok(SnapshotRecord::Account { address, account, storage })
+            }
+            BYTECODE_RECORD => {
+                let hash = H256::from_slice(take(data, 32)?);
+                let len = u32::from_be_bytes(take(data, 4)?.try_into()?);
+                let code = take(data, len as usize)?.to_vec();
+                Ok(SnapshotRecord::Bytecode { hash, code })
+            }
+            tag => eyre::bail!("Unknown snapshot record tag {tag}"),
+        }
+    }
+}
+
+/// Takes the next `len` bytes of `data`
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if data.len() < len {
+        eyre::bail!("Truncated snapshot record: needed {len} bytes, {} left", data.len());
+    }
+    let (head, tail) = data.split_at(len);
+    *data = tail;
+    Ok(head)
+}
+
+fn encode_u256(value: U256, out: &mut Vec<u8>) {
+    let bytes = value.to_be_bytes::<32>();
+    let trimmed = &bytes[bytes.iter().take_while(|byte| **byte == 0).count()..];
+    out.push(trimmed.len() as u8);
+    out.extend_from_slice(trimmed);
+}
+
+fn decode_u256(data: &mut &[u8]) -> Result<U256> {
+    let len = take(data, 1)?[0] as usize;
+    if len > 32 {
+        eyre::bail!("Invalid snapshot value of {len} bytes");
+    }
+    Ok(U256::from_be_slice(take(data, len)?))
+}
+
+/// Collects records into chunks and writes every full chunk into the snapshot directory
+#[derive(Debug)]
+struct ChunkWriter<'a> {
+    dir: &'a Path,
+    chunk_size: usize,
+    buffer: Vec<u8>,
+    records: u64,
+    chunks: Vec<SnapshotChunk>,
+}
+
+impl<'a> ChunkWriter<'a> {
+    fn new(dir: &'a Path, chunk_size: usize) -> Self {
+        Self { dir, chunk_size: chunk_size.max(1), buffer: Vec::new(), records: 0, chunks: vec![] }
+    }
+
+    fn push(&mut self, record: &SnapshotRecord) -> Result<()> {
+        record.encode(&mut self.buffer);
+        self.records += 1;
+        if self.buffer.len() >= self.chunk_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if self.records == 0 {
+            return Ok(())
+        }
+        let compressed = zstd::stream::encode_all(&self.buffer[..], COMPRESSION_LEVEL)?;
+        let file = format!("chunk-{:05}.bin.zst", self.chunks.len());
+        fs::write(self.dir.join(&file), &compressed)?;
+        tracing::debug!(target: "reth::cli", %file, records = self.records, size = compressed.len(), "Wrote snapshot chunk");
+        self.chunks.push(SnapshotChunk {
+            file,
+            records: self.records,
+            size: compressed.len() as u64,
+            sha256: checksum::sha256_hex(&compressed),
+        });
+        self.buffer.clear();
+        self.records = 0;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<SnapshotChunk>> {
+        self.flush()?;
+        Ok(self.chunks)
+    }
+}
+
+/// Writes the plain state of the database into a snapshot in `dir` and returns its manifest.
+///
+/// Accounts are written in address order, followed by the bytecodes. The state root is computed
+/// while the accounts are written.
+pub fn create_snapshot(
+    db: &Env<WriteMap>,
+    dir: &Path,
+    chunk_size: usize,
+) -> Result<SnapshotManifest> {
+    if dir.join(MANIFEST_FILE).exists() {
+        eyre::bail!("{} holds a snapshot already", dir.display());
+    }
+    fs::create_dir_all(dir)?;
+    let tx = db.tx()?;
+    let tip = tx.cursor_read::<tables::CanonicalHeaders>()?.last()?;
+    let mut writer = ChunkWriter::new(dir, chunk_size);
+    let mut storage_slots = 0;
+    let (state_root, accounts) = stream_state_root(|visit| {
+        stream_database_state(&tx, &mut |address, exported| {
+            let storage = exported.storage.clone().unwrap_or_default();
+            storage_slots += storage.len() as u64;
+            let account = Account {
+                nonce: exported.nonce.unwrap_or_default(),
+                balance: exported.balance,
+                bytecode_hash: exported.code_hash,
+            };
+            writer.push(&SnapshotRecord::Account { address, account, storage })?;
+            visit(address, exported)
+        })
+    })?;
+
+    let mut bytecodes = 0;
+    let mut cursor = tx.cursor_read::<tables::Bytecodes>()?;
+    let mut entry = cursor.first()?;
+    while let Some((hash, code)) = entry {
+        writer.push(&SnapshotRecord::Bytecode { hash, code })?;
+        bytecodes += 1;
+        entry = cursor.next()?;
+    }
+
+    let manifest = SnapshotManifest {
+        version: SNAPSHOT_VERSION,
+        block: tip.map(|(number, _)| number),
+        block_hash: tip.map(|(_, hash)| hash),
+        state_root,
+        accounts,
+        storage_slots,
+        bytecodes,
+        chunks: writer.finish()?,
+    };
+    fs::write(dir.join(MANIFEST_FILE), serde_json::to_vec_pretty(&manifest)?)?;
+    Ok(manifest)
+}
+
+/// Checks every chunk of a snapshot against the digest of its manifest
+pub fn verify_chunks(dir: &Path, manifest: &SnapshotManifest) -> Result<()> {
+    for chunk in &manifest.chunks {
+        let path = dir.join(&chunk.file);
+        let data = fs::read(&path)
+            .map_err(|err| eyre::eyre!("Missing snapshot chunk {}: {err}", path.display()))?;
+        checksum::verify(&path, &data, &chunk.sha256)?;
+    }
+    Ok(())
+}
+
+/// Restores the plain state of the snapshot in `dir` into a database without accounts, one
+/// transaction per chunk, and returns the manifest of the snapshot.
+///
+/// Nothing is written unless every chunk matches its digest. Fails if the state root of the
+/// restored accounts doesn't match the manifest.
+pub fn restore_snapshot(db: &Env<WriteMap>, dir: &Path) -> Result<SnapshotManifest> {
+    let manifest = SnapshotManifest::read(dir)?;
+    verify_chunks(dir, &manifest)?;
+    if db.view(|tx| tx.cursor_read::<tables::PlainAccountState>()?.first())??.is_some() {
+        eyre::bail!("The database holds accounts already, restore into an empty database");
+    }
+
+    let (state_root, accounts) = stream_state_root(|visit| {
+        for chunk in &manifest.chunks {
+            let path = dir.join(&chunk.file);
+            let records = SnapshotRecord::decode_all(&compression::read(&path)?)
+                .map_err(|err| eyre::eyre!("Invalid snapshot chunk {}: {err}", path.display()))?;
+            let tx = db.tx_mut()?;
+            let mut storage_cursor = tx.cursor_dup_write::<tables::PlainStorageState>()?;
+            for record in records {
+                match record {
+                    SnapshotRecord::Account { address, account, storage } => {
+                        tx.put::<tables::PlainAccountState>(address, account)?;
+                        write_storage(&mut storage_cursor, address, &storage)?;
+                        let exported = ExportedAccount {
+                            balance: account.balance,
+                            code_hash: account.bytecode_hash,
+                            nonce: Some(account.nonce),
+                            storage: (!storage.is_empty()).then_some(storage),
+                            ..Default::default()
+                        };
+                        visit(address, exported)?;
+                    }
+                    SnapshotRecord::Bytecode { hash, code } => {
+                        tx.put::<tables::Bytecodes>(hash, code)?;
+                    }
+                }
+            }
+            drop(storage_cursor);
+            tx.commit()?;
+            tracing::info!(target: "reth::cli", file = %chunk.file, records = chunk.records, "Restored snapshot chunk");
+        }
+        Ok(())
+    })?;
+
+    if accounts != manifest.accounts {
+        eyre::bail!("Restored {accounts} accounts, the manifest lists {}", manifest.accounts);
+    }
+    if state_root != manifest.state_root {
+        eyre::bail!(
+            "The restored state root {state_root:?} does not match the root {:?} of the manifest",
+            manifest.state_root
+        );
+    }
+    Ok(manifest)
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, ctx: CliContext) -> Result<()> {
+        match self.command {
+            Subcommands::Create(command) => command.execute(ctx).await,
+            Subcommands::Restore(command) => command.execute(ctx).await,
+        }
+    }
+}
+
+impl CreateCommand {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db = self.db.open_ro()?;
+        tracing::info!(target: "reth::cli", out = %self.out.display(), "Creating state snapshot");
+        let manifest = create_snapshot(&db, &self.out, self.chunk_size)?;
+        print_manifest(&manifest);
+        Ok(())
+    }
+}
+
+impl RestoreCommand {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db_path = self.db.path();
+        let manifest_path = self.path.join(MANIFEST_FILE);
+        journal::record(&db_path, "state snapshot restore", &[manifest_path.as_path()], async {
+            let mut db = self.db.open_rw()?;
+            db.create_tables()?;
+            tracing::info!(target: "reth::cli", path = %self.path.display(), "Restoring state snapshot");
+            let manifest = restore_snapshot(&db, &self.path)?;
+            print_manifest(&manifest);
+            Ok(())
+        })
+        .await
+    }
+}
+
+fn print_manifest(manifest: &SnapshotManifest) {
+    if let (Some(block), Some(hash)) = (manifest.block, manifest.block_hash) {
+        println!("Block:          {block} ({hash:?})");
+    }
+    println!("State root:     {:?}", manifest.state_root);
+    println!("Accounts:       {}", manifest.accounts);
+    println!("Storage slots:  {}", manifest.storage_slots);
+    println!("Bytecodes:      {}", manifest.bytecodes);
+    println!("Chunks:         {}", manifest.chunks.len());
+}
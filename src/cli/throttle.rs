@@ -0,0 +1,116 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The largest chunk read from a throttled file at once
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Limits the combined throughput of file reads and database writes.
+///
+/// Callers report the bytes they are about to process and are put to sleep whenever they get ahead
+/// of the configured rate. Unused budget is not carried over for more than a second, so an idle
+/// period does not allow a burst afterwards.
+#[derive(Debug)]
+pub struct IoLimiter {
+    bytes_per_sec: u64,
+    window: Mutex<Window>,
+}
+
+#[derive(Debug)]
+struct Window {
+    start: Instant,
+    bytes: u64,
+}
+
+impl IoLimiter {
+    /// Creates a limiter allowing the given number of bytes per second
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec: bytes_per_sec.max(1),
+            window: Mutex::new(Window { start: Instant::now(), bytes: 0 }),
+        }
+    }
+
+    /// Creates a limiter allowing the given number of megabytes per second
+    pub fn from_mb_per_sec(mb_per_sec: u64) -> Self {
+        Self::new(mb_per_sec.saturating_mul(1024 * 1024))
+    }
+
+    /// The number of bytes allowed per second
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.bytes_per_sec
+    }
+
+    /// Accounts for `bytes` processed bytes, sleeping until they fit into the configured rate
+    pub fn consume(&self, bytes: u64) {
+        let mut window = self.window.lock().expect("poisoned");
+        let elapsed = window.start.elapsed();
+        let budget = Duration::from_secs_f64(window.bytes as f64 / self.bytes_per_sec as f64);
+        if elapsed > budget + Duration::from_secs(1) {
+            *window = Window { start: Instant::now(), bytes: 0 };
+        }
+
+        window.bytes += bytes;
+        let target = Duration::from_secs_f64(window.bytes as f64 / self.bytes_per_sec as f64);
+        let elapsed = window.start.elapsed();
+        if target > elapsed {
+            thread::sleep(target - elapsed);
+        }
+    }
+}
+
+/// A reader whose throughput is limited by an [IoLimiter]
+#[derive(Debug)]
+pub struct ThrottledReader<'a, R> {
+    inner: R,
+    limiter: &'a IoLimiter,
+}
+
+impl<'a, R> ThrottledReader<'a, R> {
+    /// Wraps the given reader
+    pub fn new(inner: R, limiter: &'a IoLimiter) -> Self {
+        Self { inner, limiter }
+    }
+}
+
+impl<R: Read> Read for ThrottledReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(READ_CHUNK_SIZE);
+        let read = self.inner.read(&mut buf[..len])?;
+        self.limiter.consume(read as u64);
+        Ok(read)
+    }
+}
+
+/// The input path that stands for stdin
+pub const STDIN_PATH: &str = "-";
+
+/// Whether the input path stands for stdin
+pub fn is_stdin(path: impl AsRef<Path>) -> bool {
+    path.as_ref() == Path::new(STDIN_PATH)
+}
+
+/// Reads stdin to its end, limited by the given limiter if any
+pub fn read_stdin(limiter: Option<&IoLimiter>) -> io::Result<Vec<u8>> {
+    let mut contents = Vec::new();
+    let stdin = io::stdin().lock();
+    match limiter {
+        Some(limiter) => ThrottledReader::new(stdin, limiter).read_to_end(&mut contents)?,
+        None => BufReader::new(stdin).read_to_end(&mut contents)?,
+    };
+    Ok(contents)
+}
+
+/// Reads the whole file at `path`, limited by the given limiter if any
+pub fn read_file(path: impl AsRef<Path>, limiter: Option<&IoLimiter>) -> io::Result<Vec<u8>> {
+    let Some(limiter) = limiter else { return std::fs::read(path) };
+    let file = File::open(path)?;
+    let mut contents = Vec::with_capacity(file.metadata().map(|m| m.len() as usize).unwrap_or(0));
+    ThrottledReader::new(file, limiter).read_to_end(&mut contents)?;
+    Ok(contents)
+}
@@ -0,0 +1,314 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use eyre::Result;
+use reth::runner::CliContext;
+use reth_db::{
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTx,
+};
+use reth_primitives::H256;
+use reth_stages::StageId;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{
+    args::DatabaseArgs,
+    chain::{ChainPreset, OpChainSpec},
+    db::head::{self, BlockPointer, ForkchoicePointers},
+    journal,
+};
+
+/// The file below a database path recording the bedrock transition block op-node starts from
+pub const TRANSITION_FILE: &str = "bedrock-transition.json";
+
+/// The rollup config of op-node read from the chain directory if no path is given
+pub const ROLLUP_CONFIG_FILE: &str = "rollup.json";
+
+/// The checkpoint set by `state hash-and-trie` once the state of the tip is complete
+const MERKLE: StageId = StageId("MerkleExecute");
+
+/// Check that the migrated database ends at the bedrock transition block and hand it to op-node.
+///
+/// The tip of the database must be the transition block published with the OP migration
+/// artifacts: its number, hash and state root, and the timestamp op-node starts the L2 chain at.
+/// On success the transition block is recorded in `bedrock-transition.json` next to the database
+/// and becomes the safe and finalized block unless they were set before.
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The rollup config of op-node, whose `genesis.l2` block is the transition block. Defaults
+    /// to `rollup.json` in the chain directory if it exists.
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    rollup_config: Option<PathBuf>,
+
+    /// The expected hash of the transition block. Defaults to the hash in the rollup config, or
+    /// the published one of the chain selected with `--chain`.
+    #[arg(long, value_name = "HASH", verbatim_doc_comment)]
+    hash: Option<H256>,
+
+    /// The expected state root of the transition block. Without it only the completeness of the
+    /// state is checked.
+    #[arg(long, value_name = "HASH", verbatim_doc_comment)]
+    state_root: Option<H256>,
+}
+
+/// The parts of op-node's rollup config describing the transition block
+#[derive(Debug, Clone, Deserialize)]
+pub struct RollupConfig {
+    /// The blocks the rollup starts from
+    pub genesis: RollupGenesis,
+}
+
+/// The L1 and L2 blocks the rollup starts from
+#[derive(Debug, Clone, Deserialize)]
+pub struct RollupGenesis {
+    /// The first L2 block derived by op-node, the bedrock transition block of a migrated chain
+    pub l2: BlockPointer,
+    /// The timestamp of the L2 block
+    pub l2_time: u64,
+}
+
+impl RollupConfig {
+    /// Reads the rollup config at the given path
+    pub fn read(path: &Path) -> Result<Self> {
+        let data = fs::read(path)
+            .map_err(|err| eyre::eyre!("Unable to read rollup config {}: {err}", path.display()))?;
+        serde_json::from_slice(&data)
+            .map_err(|err| eyre::eyre!("Invalid rollup config {}: {err}", path.display()))
+    }
+}
+
+/// The transition block a database is expected to end at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpectedTransition {
+    /// The number of the transition block
+    pub number: u64,
+    /// The hash of the transition block, if known
+    pub hash: Option<H256>,
+    /// The state root of the transition block, if known
+    pub state_root: Option<H256>,
+    /// The timestamp of the transition block, if known
+    pub timestamp: Option<u64>,
+}
+
+impl ExpectedTransition {
+    /// The transition block of a rollup config
+    pub fn from_rollup_config(config: &RollupConfig) -> Self {
+        Self {
+            number: config.genesis.l2.number,
+            hash: Some(config.genesis.l2.hash),
+            state_root: None,
+            timestamp: Some(config.genesis.l2_time),
+        }
+    }
+
+    /// The published transition block of a known chain
+    pub fn from_preset(preset: ChainPreset) -> Self {
+        Self {
+            number: preset.bedrock_block(),
+            hash: Some(preset.bedrock_block_hash()),
+            state_root: None,
+            timestamp: None,
+        }
+    }
+}
+
+/// The bedrock transition block recorded next to a database, the block op-node takes over at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockTransition {
+    /// The transition block
+    #[serde(flatten)]
+    pub block: BlockPointer,
+    /// The state root of the transition block
+    pub state_root: H256,
+    /// The timestamp of the transition block, the `l2_time` of the rollup config
+    pub timestamp: u64,
+}
+
+impl BedrockTransition {
+    /// Reads the transition recorded next to the database at `db_path`, if it was validated
+    pub fn read(db_path: &Path) -> Result<Option<Self>> {
+        let data = match fs::read(db_path.join(TRANSITION_FILE)) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Some(serde_json::from_slice(&data)?))
+    }
+
+    /// Writes the transition next to the database at `db_path`
+    pub fn write(&self, db_path: &Path) -> Result<()> {
+        fs::create_dir_all(db_path)?;
+        fs::write(db_path.join(TRANSITION_FILE), serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// A deviation of the tip of the database from the expected transition block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionMismatch {
+    /// The tip is not the transition block
+    Number { expected: u64, actual: u64 },
+    /// The tip has another hash
+    Hash { expected: H256, actual: H256 },
+    /// The tip has another state root
+    StateRoot { expected: H256, actual: H256 },
+    /// The tip has another timestamp
+    Timestamp { expected: u64, actual: u64 },
+    /// The state trie was not computed for the tip
+    IncompleteState { checkpoint: Option<u64> },
+}
+
+impl fmt::Display for TransitionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransitionMismatch::Number { expected, actual } => {
+                write!(f, "the tip is block {actual}, expected the transition block {expected}")
+            }
+            TransitionMismatch::Hash { expected, actual } => {
+                write!(f, "the tip has hash {actual:?}, expected {expected:?}")
+            }
+            TransitionMismatch::StateRoot { expected, actual } => {
+                write!(f, "the tip has state root {actual:?}, expected {expected:?}")
+            }
+            TransitionMismatch::Timestamp { expected, actual } => {
+                write!(f, "the tip has timestamp {actual}, expected {expected}")
+            }
+            TransitionMismatch::IncompleteState { checkpoint } => match checkpoint {
+                Some(checkpoint) => write!(
+                    f,
+                    "the state trie was computed at block {checkpoint}, run state hash-and-trie"
+                ),
+                None => write!(f, "the state trie was not computed, run state hash-and-trie"),
+            },
+        }
+    }
+}
+
+/// Compares the tip of the database with the expected transition block. Returns the tip and its
+/// deviations from the expected block, empty if it is the transition block.
+pub fn check(
+    db: &Env<WriteMap>,
+    expected: &ExpectedTransition,
+) -> Result<(BedrockTransition, Vec<TransitionMismatch>)> {
+    let Some(tip) = head::head(db)? else {
+        eyre::bail!("No canonical blocks found in the database")
+    };
+    let tx = db.tx()?;
+    let header = tx
+        .get::<tables::Headers>(tip.number)?
+        .ok_or_else(|| eyre::eyre!("Header of block {} not found", tip.number))?;
+    let checkpoint = MERKLE.get_progress(&tx)?;
+    drop(tx);
+
+    let mut mismatches = Vec::new();
+    if tip.number != expected.number {
+        mismatches
+            .push(TransitionMismatch::Number { expected: expected.number, actual: tip.number });
+    }
+    if let Some(hash) = expected.hash.filter(|hash| *hash != tip.hash) {
+        mismatches.push(TransitionMismatch::Hash { expected: hash, actual: tip.hash });
+    }
+    if let Some(root) = expected.state_root.filter(|root| *root != header.state_root) {
+        mismatches
+            .push(TransitionMismatch::StateRoot { expected: root, actual: header.state_root });
+    }
+    if let Some(timestamp) = expected.timestamp.filter(|timestamp| *timestamp != header.timestamp) {
+        mismatches
+            .push(TransitionMismatch::Timestamp { expected: timestamp, actual: header.timestamp });
+    }
+    if checkpoint != Some(tip.number) {
+        mismatches.push(TransitionMismatch::IncompleteState { checkpoint });
+    }
+    let transition = BedrockTransition {
+        block: tip,
+        state_root: header.state_root,
+        timestamp: header.timestamp,
+    };
+    Ok((transition, mismatches))
+}
+
+/// Validates that the database at `db_path` ends at the expected transition block, then records
+/// the transition next to it and makes it the safe and finalized block unless they were set.
+pub fn validate(
+    db: &Env<WriteMap>,
+    db_path: &Path,
+    expected: &ExpectedTransition,
+) -> Result<BedrockTransition> {
+    let (transition, mismatches) = check(db, expected)?;
+    if !mismatches.is_empty() {
+        for mismatch in &mismatches {
+            tracing::warn!(target: "reth::cli", %mismatch, "Transition block mismatch");
+        }
+        eyre::bail!(
+            "The database does not end at the bedrock transition block: {}",
+            mismatches.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    transition.write(db_path)?;
+    let mut pointers = ForkchoicePointers::read(db_path)?;
+    if pointers.safe.is_none() || pointers.finalized.is_none() {
+        pointers.safe = pointers.safe.or(Some(transition.block));
+        pointers.finalized = pointers.finalized.or(Some(transition.block));
+        pointers.write(db_path)?;
+    }
+    Ok(transition)
+}
+
+impl Command {
+    /// The transition block expected by the flags, the rollup config and the chain
+    fn expected(&self, db_path: &Path) -> Result<ExpectedTransition> {
+        let default_config = self.db.chain_dir().join(ROLLUP_CONFIG_FILE);
+        let rollup_config = match &self.rollup_config {
+            Some(path) => Some(RollupConfig::read(path)?),
+            None if default_config.is_file() => Some(RollupConfig::read(&default_config)?),
+            None => None,
+        };
+        let mut expected = match (&rollup_config, self.db.chain) {
+            (Some(config), _) => ExpectedTransition::from_rollup_config(config),
+            (None, Some(preset)) => ExpectedTransition::from_preset(preset),
+            (None, None) => {
+                let Some(spec) = OpChainSpec::read(db_path)? else {
+                    eyre::bail!("Unknown transition block, pass --rollup-config or --chain")
+                };
+                ExpectedTransition {
+                    number: spec.bedrock_block,
+                    hash: None,
+                    state_root: None,
+                    timestamp: None,
+                }
+            }
+        };
+        expected.hash = self.hash.or(expected.hash);
+        expected.state_root = self.state_root.or(expected.state_root);
+        if expected.hash.is_none() {
+            eyre::bail!("Unknown hash of transition block {}, pass --hash", expected.number);
+        }
+        Ok(expected)
+    }
+
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db_path = self.db.path();
+        let expected = self.expected(&db_path)?;
+        journal::record(&db_path, "validate-transition", &[], async {
+            let db = self.db.open_rw()?;
+            let transition = validate(&db, &db_path, &expected)?;
+            tracing::info!(target: "reth::cli", number = transition.block.number, "Bedrock transition block validated! 🎉");
+            println!("Transition block: {} {:?}", transition.block.number, transition.block.hash);
+            println!("State root:       {:?}", transition.state_root);
+            println!("Timestamp:        {}", transition.timestamp);
+            Ok(())
+        })
+        .await
+    }
+}
@@ -0,0 +1,569 @@
+use std::fmt;
+
+use eyre::Result;
+use reth_primitives::{Address, Bloom, SealedBlock, TransactionSigned, H256, U256};
+
+use crate::cli::{
+    blocks::{ErigonBlock, ErigonTx},
+    chain::{ChainPreset, SystemAddresses},
+    deposit,
+    genesis::Genesis,
+};
+
+/// The number of issues kept for the final report, the rest is only counted
+const MAX_REPORTED_ISSUES: usize = 20;
+
+/// The default largest gap in seconds between the timestamps of consecutive blocks, a day
+pub const DEFAULT_MAX_BLOCK_GAP: u64 = 24 * 60 * 60;
+
+/// How many seconds block timestamps may lie ahead of the local clock
+pub const MAX_FUTURE_DRIFT: u64 = 15 * 60;
+
+/// A deviation of the imported data from the patterns expected on a [ChainPreset]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// A transaction is signed for another chain
+    ChainIdMismatch {
+        /// The block of the transaction
+        block: u64,
+        /// The index of the transaction within its block
+        index: usize,
+        /// The chain id the transaction is signed for
+        chain_id: u64,
+    },
+    /// The genesis belongs to another chain
+    GenesisMismatch {
+        /// The chain id of the genesis
+        chain_id: u64,
+        /// The hash of the genesis block
+        hash: H256,
+    },
+    /// The genesis lacks an OP system predeploy
+    MissingPredeploy {
+        /// The name of the predeploy
+        name: &'static str,
+        /// The address of the predeploy
+        address: Address,
+    },
+    /// An unsigned L1 to L2 message is not sent to the L2 cross domain messenger
+    UnexpectedMessageTarget {
+        /// The block of the message
+        block: u64,
+        /// The index of the message within its block
+        index: usize,
+        /// The target of the message, if any
+        to: Option<Address>,
+    },
+    /// A legacy transaction calls the bedrock L1 block predeploy
+    BedrockSystemTx {
+        /// The block of the transaction
+        block: u64,
+        /// The index of the transaction within its block
+        index: usize,
+    },
+}
+
+impl ValidationIssue {
+    /// Whether the issue means the data was taken from another network, which fails the import
+    pub fn is_wrong_network(&self) -> bool {
+        matches!(
+            self,
+            ValidationIssue::ChainIdMismatch { .. } |
+                ValidationIssue::GenesisMismatch { .. } |
+                ValidationIssue::MissingPredeploy { .. }
+        )
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::ChainIdMismatch { block, index, chain_id } => {
+                write!(f, "transaction {index} of block {block} is signed for chain {chain_id}")
+            }
+            ValidationIssue::GenesisMismatch { chain_id, hash } => {
+                write!(f, "the genesis of chain {chain_id} has the unexpected hash {hash:?}")
+            }
+            ValidationIssue::MissingPredeploy { name, address } => {
+                write!(f, "the genesis lacks the {name} predeploy at {address:?}")
+            }
+            ValidationIssue::UnexpectedMessageTarget { block, index, to } => write!(
+                f,
+                "unsigned message {index} of block {block} is sent to {to:?} instead of the L2 \
+                 cross domain messenger"
+            ),
+            ValidationIssue::BedrockSystemTx { block, index } => write!(
+                f,
+                "transaction {index} of block {block} calls the bedrock L1 block predeploy"
+            ),
+        }
+    }
+}
+
+/// Checks imported data against the system addresses and identifiers of a [ChainPreset], to catch
+/// exports taken from the wrong network before they are written.
+#[derive(Debug)]
+pub struct SystemTxValidator {
+    preset: ChainPreset,
+    addresses: SystemAddresses,
+    issues: Vec<ValidationIssue>,
+    total: usize,
+    wrong_network: usize,
+    first_wrong_network: Option<ValidationIssue>,
+}
+
+impl SystemTxValidator {
+    /// Creates a validator for the given chain
+    pub fn new(preset: ChainPreset) -> Self {
+        Self {
+            preset,
+            addresses: preset.system_addresses(),
+            issues: vec![],
+            total: 0,
+            wrong_network: 0,
+            first_wrong_network: None,
+        }
+    }
+
+    /// Checks the transactions of a block of an Erigon export.
+    ///
+    /// EIP-155 signatures must carry the chain id of the preset, unsigned L1 to L2 messages must
+    /// be sent to the L2 cross domain messenger and no transaction may call the bedrock L1 block
+    /// predeploy, which only exists after the migration.
+    pub fn validate_block(&mut self, block: &ErigonBlock) {
+        for (index, tx) in block.txs.iter().enumerate() {
+            match tx {
+                ErigonTx::Legacy(tx) => {
+                    let to = tx.to.map(|to| Address::from_slice(&to.0));
+                    let unsigned = tx.r == U256::ZERO && tx.s == U256::ZERO;
+                    let chain_id = eip155_chain_id(tx.v);
+                    self.validate_tx(block.header.number, index, to, chain_id, unsigned);
+                }
+                ErigonTx::Typed(tx) => self.validate_signed_tx(block.header.number, index, tx),
+            }
+        }
+    }
+
+    /// Checks the transactions of a block decoded from the standard encoding, like
+    /// [SystemTxValidator::validate_block]
+    pub fn validate_sealed_block(&mut self, block: &SealedBlock) {
+        for (index, tx) in block.body.iter().enumerate() {
+            self.validate_signed_tx(block.number, index, tx);
+        }
+    }
+
+    fn validate_signed_tx(&mut self, block: u64, index: usize, tx: &TransactionSigned) {
+        // Deposits carry no chain id and are checked by op-node when it derives them
+        if deposit::is_deposit(tx) {
+            return
+        }
+        let unsigned = tx.signature.r == U256::ZERO && tx.signature.s == U256::ZERO;
+        self.validate_tx(block, index, tx.to(), tx.chain_id(), unsigned);
+    }
+
+    fn validate_tx(
+        &mut self,
+        block: u64,
+        index: usize,
+        to: Option<Address>,
+        chain_id: Option<u64>,
+        unsigned: bool,
+    ) {
+        if let Some(chain_id) = chain_id.filter(|chain_id| *chain_id != self.preset.chain_id()) {
+            self.report(ValidationIssue::ChainIdMismatch { block, index, chain_id });
+        }
+        if unsigned && to != Some(self.addresses.l2_cross_domain_messenger) {
+            self.report(ValidationIssue::UnexpectedMessageTarget { block, index, to });
+        }
+        if to == Some(self.addresses.l1_block) {
+            self.report(ValidationIssue::BedrockSystemTx { block, index });
+        }
+    }
+
+    /// Checks a genesis and the hash of its block against the preset
+    pub fn validate_genesis(&mut self, genesis: &Genesis, hash: H256) {
+        let chain_id = genesis.config.chain_id;
+        if chain_id != self.preset.chain_id() || hash != self.preset.genesis_hash() {
+            self.report(ValidationIssue::GenesisMismatch { chain_id, hash });
+        }
+        let address = self.addresses.sequencer_fee_vault;
+        let has_code = genesis
+            .alloc
+            .get(&address)
+            .and_then(|account| account.code.as_ref())
+            .map_or(false, |code| !code.is_empty());
+        if !has_code {
+            self.report(ValidationIssue::MissingPredeploy { name: "sequencer fee vault", address });
+        }
+    }
+
+    /// The first issues found so far
+    pub fn issues(&self) -> &[ValidationIssue] {
+        &self.issues
+    }
+
+    /// Reports the issues found. Fails if any of them shows that the data belongs to another
+    /// network.
+    pub fn finish(self) -> Result<()> {
+        for issue in self.issues.iter().filter(|issue| !issue.is_wrong_network()) {
+            tracing::warn!(target: "reth::cli", chain = %self.preset, %issue, "Unexpected system transaction pattern");
+        }
+        if self.total > self.issues.len() {
+            tracing::warn!(target: "reth::cli", omitted = self.total - self.issues.len(), "Further validation issues omitted");
+        }
+        if let Some(issue) = &self.first_wrong_network {
+            eyre::bail!(
+                "The input does not belong to {}: {issue} ({} such issues). Was the export taken \
+                 from another network?",
+                self.preset,
+                self.wrong_network
+            );
+        }
+        Ok(())
+    }
+
+    fn report(&mut self, issue: ValidationIssue) {
+        self.total += 1;
+        if issue.is_wrong_network() {
+            self.wrong_network += 1;
+            self.first_wrong_network.get_or_insert_with(|| issue.clone());
+        }
+        if self.issues.len() < MAX_REPORTED_ISSUES {
+            self.issues.push(issue);
+        }
+    }
+}
+
+/// Returns the chain id encoded in an EIP-155 signature value, if any
+pub fn eip155_chain_id(v: U256) -> Option<u64> {
+    if v < U256::from(35) || v > U256::from(u64::MAX) {
+        return None
+    }
+    Some((v.as_limbs()[0] - 35) / 2)
+}
+
+/// A block timestamp breaking the progression of the chain. Exports with clock anomalies break the
+/// activation of timestamp-based hardforks like regolith.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimestampIssue {
+    /// The block is older than the block before it
+    Decreasing {
+        /// The number of the block
+        block: u64,
+        /// The timestamp of the block
+        timestamp: u64,
+        /// The timestamp of the block before it
+        parent_timestamp: u64,
+    },
+    /// The block is further ahead of the block before it than the blocks between them allow
+    Gap {
+        /// The number of the block
+        block: u64,
+        /// The timestamp of the block
+        timestamp: u64,
+        /// The timestamp of the block before it
+        parent_timestamp: u64,
+    },
+    /// The block lies ahead of the local clock
+    Future {
+        /// The number of the block
+        block: u64,
+        /// The timestamp of the block
+        timestamp: u64,
+    },
+}
+
+impl fmt::Display for TimestampIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimestampIssue::Decreasing { block, timestamp, parent_timestamp } => write!(
+                f,
+                "block {block} has timestamp {timestamp}, before the timestamp \
+                 {parent_timestamp} of the block before it"
+            ),
+            TimestampIssue::Gap { block, timestamp, parent_timestamp } => write!(
+                f,
+                "block {block} has timestamp {timestamp}, {} seconds after the block before it",
+                timestamp - parent_timestamp
+            ),
+            TimestampIssue::Future { block, timestamp } => {
+                write!(f, "block {block} has timestamp {timestamp}, which lies in the future")
+            }
+        }
+    }
+}
+
+/// Checks that the timestamps of the given blocks, ordered by number, never decrease, advance by
+/// at most `max_gap` seconds per block number and do not lie further ahead of `now` than
+/// [MAX_FUTURE_DRIFT]
+pub fn check_timestamps(blocks: &[SealedBlock], max_gap: u64, now: u64) -> Vec<TimestampIssue> {
+    let mut issues = Vec::new();
+    for (index, block) in blocks.iter().enumerate() {
+        let (number, timestamp) = (block.number, block.timestamp);
+        if timestamp > now.saturating_add(MAX_FUTURE_DRIFT) {
+            issues.push(TimestampIssue::Future { block: number, timestamp });
+        }
+        let Some(parent) = index.checked_sub(1).map(|index| &blocks[index]) else { continue };
+        let parent_timestamp = parent.timestamp;
+        if timestamp < parent_timestamp {
+            issues.push(TimestampIssue::Decreasing { block: number, timestamp, parent_timestamp });
+        } else if timestamp - parent_timestamp >
+            max_gap.saturating_mul(number.saturating_sub(parent.number).max(1))
+        {
+            issues.push(TimestampIssue::Gap { block: number, timestamp, parent_timestamp });
+        }
+    }
+    issues
+}
+
+/// Reports the given timestamp issues. Fails if there are any and `strict` is set.
+pub fn report_timestamp_issues(issues: &[TimestampIssue], strict: bool) -> Result<()> {
+    for issue in issues.iter().take(MAX_REPORTED_ISSUES) {
+        tracing::warn!(target: "reth::cli", %issue, "Block timestamp anomaly");
+    }
+    if issues.len() > MAX_REPORTED_ISSUES {
+        tracing::warn!(target: "reth::cli", omitted = issues.len() - MAX_REPORTED_ISSUES, "Further timestamp anomalies omitted");
+    }
+    if strict && !issues.is_empty() {
+        eyre::bail!(
+            "The export has {} block timestamp anomalies, the first being: {}",
+            issues.len(),
+            issues[0]
+        );
+    }
+    Ok(())
+}
+
+/// A receipt contradicting the blocks imported before it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReceiptIssue {
+    /// No block was imported at the height of the receipt
+    MissingBlock {
+        /// The block number of the receipt
+        block: u64,
+    },
+    /// The receipt names another block hash than the imported block at its height
+    BlockHashMismatch {
+        /// The block number of the receipt
+        block: u64,
+        /// The hash of the imported block
+        expected: H256,
+        /// The block hash of the receipt
+        actual: H256,
+    },
+    /// The transaction of the receipt is not the one at its index in the imported block
+    TransactionMismatch {
+        /// The block number of the receipt
+        block: u64,
+        /// The transaction index of the receipt
+        index: u64,
+        /// The transaction hash of the receipt
+        tx_hash: H256,
+    },
+    /// The cumulative gas used decreases from one receipt of a block to the next
+    DecreasingCumulativeGas {
+        /// The block number of the receipt
+        block: u64,
+        /// The transaction index of the receipt
+        index: u64,
+        /// The cumulative gas used of the receipt
+        cumulative_gas_used: u64,
+        /// The cumulative gas used of the receipt before it
+        previous: u64,
+    },
+    /// The logs bloom of the receipt is not the bloom of its logs
+    BloomMismatch {
+        /// The block number of the receipt
+        block: u64,
+        /// The transaction index of the receipt
+        index: u64,
+    },
+    /// The blooms of the receipts of a block don't combine to the logs bloom of the imported
+    /// header
+    LogsBloomMismatch {
+        /// The number of the block
+        block: u64,
+        /// The logs bloom of the imported header
+        expected: Bloom,
+        /// The bloom computed from the logs of the receipts
+        computed: Bloom,
+    },
+    /// The receipts of a block don't hash to the receipts root of the imported header
+    ReceiptsRootMismatch {
+        /// The number of the block
+        block: u64,
+        /// The receipts root of the imported header
+        expected: H256,
+        /// The root computed from the receipts
+        computed: H256,
+    },
+}
+
+impl fmt::Display for ReceiptIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReceiptIssue::MissingBlock { block } => {
+                write!(f, "the receipts of block {block} belong to a block that was not imported")
+            }
+            ReceiptIssue::BlockHashMismatch { block, expected, actual } => write!(
+                f,
+                "a receipt of block {block} names block hash {actual:?}, the imported block is \
+                 {expected:?}"
+            ),
+            ReceiptIssue::TransactionMismatch { block, index, tx_hash } => write!(
+                f,
+                "transaction {tx_hash:?} is not at index {index} of the imported block {block}"
+            ),
+            ReceiptIssue::DecreasingCumulativeGas {
+                block,
+                index,
+                cumulative_gas_used,
+                previous,
+            } => {
+                write!(
+                    f,
+                    "receipt {index} of block {block} has cumulative gas used \
+                     {cumulative_gas_used}, less than the {previous} of the receipt before it"
+                )
+            }
+            ReceiptIssue::BloomMismatch { block, index } => write!(
+                f,
+                "receipt {index} of block {block} has a logs bloom that doesn't match its logs"
+            ),
+            ReceiptIssue::LogsBloomMismatch { block, .. } => write!(
+                f,
+                "the logs of the receipts of block {block} don't match the logs bloom of the \
+                 imported header"
+            ),
+            ReceiptIssue::ReceiptsRootMismatch { block, expected, computed } => write!(
+                f,
+                "the receipts of block {block} hash to {computed:?}, the imported header has \
+                 receipts root {expected:?}"
+            ),
+        }
+    }
+}
+
+/// Reports the given receipt issues. Fails if there are any and `strict` is set.
+pub fn report_receipt_issues(issues: &[ReceiptIssue], strict: bool) -> Result<()> {
+    for issue in issues.iter().take(MAX_REPORTED_ISSUES) {
+        tracing::warn!(target: "reth::cli", %issue, "Receipt mismatch");
+    }
+    if issues.len() > MAX_REPORTED_ISSUES {
+        tracing::warn!(target: "reth::cli", omitted = issues.len() - MAX_REPORTED_ISSUES, "Further receipt mismatches omitted");
+    }
+    if strict && !issues.is_empty() {
+        eyre::bail!(
+            "The receipts contradict the imported blocks {} times, the first being: {}",
+            issues.len(),
+            issues[0]
+        );
+    }
+    Ok(())
+}
+
+/// A break in the sequence of blocks to insert, as left behind by blocks that failed to decode or
+/// are missing from the export
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContinuityIssue {
+    /// The block does not directly follow the block before it
+    Gap {
+        /// The number of the block
+        block: u64,
+        /// The number the block was expected to have
+        expected: u64,
+    },
+    /// The block before the first block to insert is not in the database
+    MissingParent {
+        /// The number of the block
+        block: u64,
+    },
+    /// The parent hash of the block is not the hash of the block before it
+    BrokenLink {
+        /// The number of the block
+        block: u64,
+        /// The parent hash of the block
+        parent_hash: H256,
+        /// The hash of the block before it
+        expected: H256,
+    },
+}
+
+impl fmt::Display for ContinuityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContinuityIssue::Gap { block, expected } => {
+                write!(f, "block {block} follows block {}, expected block {expected}", expected - 1)
+            }
+            ContinuityIssue::MissingParent { block } => {
+                write!(f, "block {block} follows block {}, which is not in the database", block - 1)
+            }
+            ContinuityIssue::BrokenLink { block, parent_hash, expected } => write!(
+                f,
+                "block {block} has parent hash {parent_hash:?}, the block before it is \
+                 {expected:?}"
+            ),
+        }
+    }
+}
+
+/// The error refusing blocks that do not continue the chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenContinuity {
+    /// The breaks in the sequence of the refused blocks
+    pub issues: Vec<ContinuityIssue>,
+}
+
+impl fmt::Display for BrokenContinuity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The blocks to insert have {} gaps or broken links, the first being: {}. Pass \
+             --allow-gaps to insert them anyway.",
+            self.issues.len(),
+            self.issues[0]
+        )
+    }
+}
+
+impl std::error::Error for BrokenContinuity {}
+
+/// Checks that the given blocks, ordered by number, follow the block with the given number and
+/// hash and each other without gaps. Without a parent the first block is not checked.
+pub fn check_continuity(
+    parent: Option<(u64, H256)>,
+    blocks: &[SealedBlock],
+) -> Vec<ContinuityIssue> {
+    let mut issues = Vec::new();
+    let mut parent = parent;
+    for block in blocks {
+        if let Some((number, hash)) = parent {
+            if block.number != number + 1 {
+                issues.push(ContinuityIssue::Gap { block: block.number, expected: number + 1 });
+            } else if block.parent_hash != hash {
+                issues.push(ContinuityIssue::BrokenLink {
+                    block: block.number,
+                    parent_hash: block.parent_hash,
+                    expected: hash,
+                });
+            }
+        }
+        parent = Some((block.number, block.hash()));
+    }
+    issues
+}
+
+/// Reports the given continuity issues. Fails with [BrokenContinuity] if there are any, unless
+/// `allow_gaps` is set.
+pub fn report_continuity_issues(issues: Vec<ContinuityIssue>, allow_gaps: bool) -> Result<()> {
+    for issue in issues.iter().take(MAX_REPORTED_ISSUES) {
+        tracing::warn!(target: "reth::cli", %issue, "Block sequence broken");
+    }
+    if issues.len() > MAX_REPORTED_ISSUES {
+        tracing::warn!(target: "reth::cli", omitted = issues.len() - MAX_REPORTED_ISSUES, "Further breaks in the block sequence omitted");
+    }
+    if !allow_gaps && !issues.is_empty() {
+        return Err(BrokenContinuity { issues }.into())
+    }
+    Ok(())
+}
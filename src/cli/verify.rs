@@ -0,0 +1,970 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::RangeInclusive,
+    path::PathBuf,
+    thread,
+};
+
+use clap::{Parser, Subcommand};
+use eyre::Result;
+use reth::runner::CliContext;
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTx,
+};
+use reth_primitives::{
+    bloom::logs_bloom, Address, Bloom, ChainSpec, SealedBlock, SealedHeader, H256, KECCAK_EMPTY,
+    U256,
+};
+
+use crate::cli::{
+    analytics,
+    args::{parse_block_range, DatabaseArgs},
+    chain::OpChainSpec,
+    checkpoints::CheckpointFile,
+    db::prune::{PruneCheckpoints, PrunedData},
+    debug,
+    deposit::DepositStore,
+    journal,
+    keccak::keccak256,
+    l1_cost,
+    l1_fee::{L1FeeInfo, L1FeeStore},
+    replay,
+    source::rpc::RpcSource,
+};
+
+/// Verify command
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(subcommand)]
+    command: Subcommands,
+}
+
+/// Verify subcommands
+#[derive(Debug, Subcommand)]
+pub enum Subcommands {
+    /// Compare a range of blocks against one or more reference nodes on parallel workers
+    #[command(name = "blocks")]
+    Blocks(BlocksCommand),
+    /// Binary-search for the first block where the database diverges from a reference node
+    #[command(name = "bisect")]
+    Bisect(BisectCommand),
+    /// Compare the database against a checkpoint file exported by another import
+    #[command(name = "checkpoints")]
+    Checkpoints(CheckpointsCommand),
+    /// Recompute the hashes of the stored contract codes
+    #[command(name = "bytecodes")]
+    Bytecodes(BytecodesCommand),
+    /// Recompute the logs blooms of the blocks from the logs of their receipts
+    #[command(name = "blooms")]
+    Blooms(BloomsCommand),
+    /// Recompute the L1 fees of the imported receipts
+    #[command(name = "l1-fees")]
+    L1Fees(L1FeesCommand),
+    /// Re-execute a range of blocks on parallel workers and compare them to their receipts
+    #[command(name = "execute")]
+    Execute(ExecuteCommand),
+}
+
+/// Recompute the keccak256 hash of every stored contract code and check it against the hash it is
+/// stored under and the code hashes of the accounts.
+///
+/// The state import stores codes under the hashes of the export, so a code that doesn't match its
+/// hash or an account whose code is missing points to a corrupted export. Fails if there are any.
+#[derive(Debug, Parser)]
+pub struct BytecodesCommand {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+}
+
+/// Recompute the logs bloom of every block from the logs of its imported receipts and compare it
+/// to the logs bloom of the header.
+///
+/// Blocks with missing receipts are counted and skipped. Fails if the bloom of any block doesn't
+/// match, which means its receipts lost or changed logs.
+#[derive(Debug, Parser)]
+pub struct BloomsCommand {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The first block to verify
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment, default_value_t = 1)]
+    from: u64,
+
+    /// The last block to verify. Defaults to the canonical tip.
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment)]
+    to: Option<u64>,
+}
+
+/// Recompute the L1 fee of every imported receipt and compare it to the stored one.
+///
+/// The fee of legacy receipts is checked against their L1 gas price, L1 gas used and fee scalar.
+/// Receipts of bedrock blocks are recomputed from the transaction and the L1 attributes deposit of
+/// their block. Fails if any fee doesn't match.
+#[derive(Debug, Parser)]
+pub struct L1FeesCommand {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The first block to verify
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment, default_value_t = 1)]
+    from: u64,
+
+    /// The last block to verify. Defaults to the canonical tip.
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment)]
+    to: Option<u64>,
+}
+
+/// Compare the imported blocks against the legacy chain served by one or more reference nodes.
+///
+/// The block range is split into one part per worker. Every worker reads its part in its own
+/// database transaction and queries its own endpoint of the pool, and the findings of all workers
+/// are merged into one report.
+#[derive(Debug, Parser)]
+pub struct BlocksCommand {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The JSON-RPC endpoint of a reference node, such as l2geth.
+    ///
+    /// Repeat to spread the requests over a pool of endpoints. The workers are assigned to the
+    /// endpoints in turn.
+    #[arg(long = "rpc-url", value_name = "URL", required = true, verbatim_doc_comment)]
+    rpc_urls: Vec<String>,
+
+    /// The first block to verify
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment, default_value_t = 1)]
+    from: u64,
+
+    /// The last block to verify. Defaults to the canonical tip.
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment)]
+    to: Option<u64>,
+
+    /// The number of workers verifying parts of the block range in parallel. Defaults to the
+    /// number of available cores.
+    #[arg(long, value_name = "WORKERS", verbatim_doc_comment)]
+    workers: Option<usize>,
+}
+
+/// Find the earliest block whose hash differs from the one of a reference node.
+///
+/// Block hashes commit to their parent, so once the database diverges all later blocks differ as
+/// well. This takes a logarithmic number of requests instead of comparing every block.
+#[derive(Debug, Parser)]
+pub struct BisectCommand {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The JSON-RPC endpoint of the reference node, such as l2geth
+    #[arg(long, value_name = "URL", verbatim_doc_comment)]
+    against: String,
+
+    /// The first block to search from
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment, default_value_t = 1)]
+    from: u64,
+
+    /// The last block to search up to. Defaults to the canonical tip.
+    #[arg(long, value_name = "BLOCK", verbatim_doc_comment)]
+    to: Option<u64>,
+}
+
+/// Compare the canonical blocks against the block hashes of a checkpoint file.
+///
+/// The file is written by `export --checkpoints` from an import already verified, so further
+/// imports of the same chain are checked cheaply and without a reference node.
+#[derive(Debug, Parser)]
+pub struct CheckpointsCommand {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The checkpoint file
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    file: PathBuf,
+}
+
+/// Re-execute the imported blocks of a range and check the result against their headers and
+/// receipts.
+///
+/// The range is split into one part per job. Every job re-executes its blocks on its own thread
+/// in its own read transaction, on top of the state of the parent of each block, so the state of
+/// the database has to reach back to the first block: it has to be the state after the parent of
+/// the first block, or the blocks have to be replayed with `replay`. The receipts root and the gas
+/// used of every block are compared to its header and the first transaction diverging from its
+/// imported receipt is reported. See `debug replay-block` for the limits of the re-execution.
+#[derive(Debug, Parser)]
+pub struct ExecuteCommand {
+    #[clap(flatten)]
+    db: DatabaseArgs,
+
+    /// The inclusive range of blocks to re-execute, as `<FIRST>..<LAST>`
+    #[arg(long, value_name = "FIRST..LAST", value_parser = parse_block_range, verbatim_doc_comment)]
+    range: RangeInclusive<u64>,
+
+    /// The number of blocks re-executed in parallel. Defaults to the number of available cores.
+    #[arg(long, value_name = "JOBS", verbatim_doc_comment)]
+    jobs: Option<usize>,
+}
+
+/// A difference between a block of the database and its reference
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// The database has no canonical block with this number
+    Missing,
+    /// The canonical block of the database has a different hash
+    Hash {
+        /// The hash of the reference block
+        expected: H256,
+        /// The hash of the canonical block of the database
+        found: H256,
+    },
+    /// The header matches, but the stored transactions differ from the ones of the reference
+    /// block, starting at `index`
+    Transaction {
+        /// The index of the first differing transaction in the block
+        index: usize,
+        /// The hash of the transaction of the reference block, if it has one at `index`
+        expected: Option<H256>,
+        /// The hash of the stored transaction, if there is one at `index`
+        found: Option<H256>,
+    },
+    /// The block failed to re-execute
+    ExecutionFailed {
+        /// The error of the execution
+        error: String,
+    },
+    /// The re-execution of the block differs from its header or its imported receipts
+    Execution {
+        /// The receipts root of the header
+        expected_receipts_root: H256,
+        /// The receipts root of the re-execution
+        receipts_root: H256,
+        /// The gas used according to the header
+        expected_gas_used: u64,
+        /// The gas used by the re-execution
+        gas_used: u64,
+        /// The index and hash of the first transaction diverging from its imported receipt,
+        /// and how it diverges
+        first_divergence: Option<(usize, H256, String)>,
+    },
+}
+
+/// The outcome of a verification
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// The number of verified blocks
+    pub checked: u64,
+    /// The mismatching blocks, ordered by block number
+    pub mismatches: Vec<(u64, Mismatch)>,
+}
+
+impl VerifyReport {
+    /// Adds the findings of another worker to the report
+    pub fn merge(&mut self, other: Self) {
+        self.checked += other.checked;
+        self.mismatches.extend(other.mismatches);
+        self.mismatches.sort_by_key(|(number, _)| *number);
+    }
+
+    /// Returns true if all verified blocks match the reference
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Splits the block range into at most `parts` contiguous ranges of nearly equal length
+pub fn partition(range: RangeInclusive<u64>, parts: usize) -> Vec<RangeInclusive<u64>> {
+    let (start, end) = range.into_inner();
+    if start > end {
+        return Vec::new()
+    }
+    let len = end - start + 1;
+    let parts = (parts.max(1) as u64).min(len);
+    let (size, rest) = (len / parts, len % parts);
+    let mut from = start;
+    (0..parts)
+        .map(|part| {
+            let to = from + size - 1 + u64::from(part < rest);
+            let range = from..=to;
+            from = to + 1;
+            range
+        })
+        .collect()
+}
+
+/// Compares the canonical block with the given number to the reference block with the given hash
+/// and transaction hashes. Deposits are compared by the hash of the deposit in `deposits`.
+pub fn check_block<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    deposits: &DepositStore,
+    number: u64,
+    hash: H256,
+    transactions: &[H256],
+) -> Result<Option<Mismatch>> {
+    let Some(found) = tx.get::<tables::CanonicalHeaders>(number)? else {
+        return Ok(Some(Mismatch::Missing))
+    };
+    if found != hash {
+        return Ok(Some(Mismatch::Hash { expected: hash, found }))
+    }
+
+    let tx_ids = tx
+        .get::<tables::BlockBodies>(number)?
+        .map_or(0..0, |indices| indices.start_tx_id..indices.start_tx_id + indices.tx_count);
+    let mut stored = Vec::with_capacity(transactions.len());
+    for tx_id in tx_ids {
+        let transaction = deposits
+            .read(tx, tx_id)?
+            .ok_or_else(|| eyre::eyre!("Transaction {tx_id} of block {number} not found"))?;
+        stored.push(transaction.hash());
+    }
+    let first_difference = (0..transactions.len().max(stored.len()))
+        .find(|index| transactions.get(*index) != stored.get(*index));
+    Ok(first_difference.map(|index| Mismatch::Transaction {
+        index,
+        expected: transactions.get(index).copied(),
+        found: stored.get(index).copied(),
+    }))
+}
+
+/// Verifies the blocks in the given range on `workers` threads.
+///
+/// Every worker opens its own read transaction and is assigned one of the reference clients of
+/// the pool in turn. `fetch` reads the hash and the transaction hashes of a block from a client.
+pub fn verify<R, F>(
+    db: &Env<WriteMap>,
+    deposits: &DepositStore,
+    range: RangeInclusive<u64>,
+    workers: usize,
+    pool: &[R],
+    fetch: F,
+) -> Result<VerifyReport>
+where
+    R: Sync,
+    F: Fn(&R, u64) -> Result<(H256, Vec<H256>)> + Sync,
+{
+    if pool.is_empty() {
+        eyre::bail!("No reference endpoints given")
+    }
+
+    thread::scope(|scope| {
+        let fetch = &fetch;
+        let mut handles = Vec::new();
+        for (worker, part) in partition(range, workers).into_iter().enumerate() {
+            let client = &pool[worker % pool.len()];
+            let handle = thread::Builder::new().name(format!("verifier-{worker}")).spawn_scoped(
+                scope,
+                move || -> Result<VerifyReport> {
+                    let tx = db.tx()?;
+                    let mut report = VerifyReport::default();
+                    for number in part {
+                        let (hash, transactions) = fetch(client, number)
+                            .map_err(|err| eyre::eyre!("Block {number}: {err}"))?;
+                        let mismatch = check_block(&tx, deposits, number, hash, &transactions)?;
+                        if let Some(mismatch) = mismatch {
+                            tracing::warn!(target: "reth::cli", number, ?mismatch, "Block differs from the reference");
+                            report.mismatches.push((number, mismatch));
+                        }
+                        report.checked += 1;
+                    }
+                    Ok(report)
+                },
+            )?;
+            handles.push(handle);
+        }
+
+        let mut report = VerifyReport::default();
+        for (worker, handle) in handles.into_iter().enumerate() {
+            let part =
+                handle.join().map_err(|_| eyre::eyre!("Verifier thread {worker} panicked"))??;
+            report.merge(part);
+        }
+        Ok(report)
+    })
+}
+
+/// Re-executes the blocks in the given range on `jobs` threads, each reading its part in its own
+/// database transaction, and compares them to their headers and imported receipts
+pub fn verify_execution(
+    db: &Env<WriteMap>,
+    chain: &ChainSpec,
+    range: RangeInclusive<u64>,
+    jobs: usize,
+) -> Result<VerifyReport> {
+    let first = (*range.start()).max(1);
+    // Fail once instead of in every block if the state does not reach back to the range
+    db.view(|tx| replay::historical_transition(tx, first - 1))??;
+
+    thread::scope(|scope| {
+        let mut handles = Vec::new();
+        for (job, part) in partition(first..=*range.end(), jobs).into_iter().enumerate() {
+            let handle = thread::Builder::new().name(format!("executor-{job}")).spawn_scoped(
+                scope,
+                move || -> Result<VerifyReport> {
+                    let tx = db.tx()?;
+                    let mut report = VerifyReport::default();
+                    for number in part {
+                        let mismatch = match debug::replay_block_in(&tx, chain, number, false) {
+                            Ok(replay) if replay.matches() => None,
+                            Ok(replay) => Some(Mismatch::Execution {
+                                expected_receipts_root: replay.header_receipts_root,
+                                receipts_root: replay.receipts_root,
+                                expected_gas_used: replay.header_gas_used,
+                                gas_used: replay.gas_used,
+                                first_divergence: replay.first_divergence().map(|trace| {
+                                    (trace.index, trace.hash, trace.divergences().join(", "))
+                                }),
+                            }),
+                            Err(err) => {
+                                Some(Mismatch::ExecutionFailed { error: format!("{err:#}") })
+                            }
+                        };
+                        if let Some(mismatch) = mismatch {
+                            tracing::warn!(target: "reth::cli", number, ?mismatch, "Block diverges on re-execution");
+                            report.mismatches.push((number, mismatch));
+                        }
+                        report.checked += 1;
+                    }
+                    Ok(report)
+                },
+            )?;
+            handles.push(handle);
+        }
+
+        let mut report = VerifyReport::default();
+        for (job, handle) in handles.into_iter().enumerate() {
+            let part =
+                handle.join().map_err(|_| eyre::eyre!("Executor thread {job} panicked"))??;
+            report.merge(part);
+        }
+        Ok(report)
+    })
+}
+
+/// A corrupted contract code found by [verify_bytecodes]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BytecodeIssue {
+    /// The code stored under `hash` hashes to `computed`
+    HashMismatch {
+        /// The hash the code is stored under
+        hash: H256,
+        /// The hash of the stored code
+        computed: H256,
+        /// The length of the stored code
+        len: usize,
+    },
+    /// The account has a code hash, but no code is stored under it
+    MissingCode {
+        /// The address of the account
+        address: Address,
+        /// The code hash of the account
+        hash: H256,
+    },
+    /// The code stored under the code hash of the account hashes to another hash
+    CorruptedCode {
+        /// The address of the account
+        address: Address,
+        /// The code hash of the account
+        hash: H256,
+    },
+}
+
+/// The outcome of a bytecode verification
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BytecodeReport {
+    /// The number of stored codes
+    pub bytecodes: u64,
+    /// The number of accounts with code
+    pub accounts: u64,
+    /// The corrupted codes and the accounts referencing them, codes first
+    pub issues: Vec<BytecodeIssue>,
+}
+
+impl BytecodeReport {
+    /// Returns true if every code matches its hash and every account's code is stored
+    pub fn is_consistent(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Recomputes the hash of every code in the bytecodes table, then checks the code hash of every
+/// account with code against the stored codes. Accounts with the hash of the empty code have no
+/// code.
+pub fn verify_bytecodes<'a, TX: DbTx<'a>>(tx: &TX) -> Result<BytecodeReport> {
+    let mut report = BytecodeReport::default();
+    let mut corrupted = HashSet::new();
+    let mut codes = tx.cursor_read::<tables::Bytecodes>()?;
+    let mut entry = codes.first()?;
+    while let Some((hash, code)) = entry {
+        report.bytecodes += 1;
+        let computed = keccak256(&code);
+        if computed != hash {
+            tracing::warn!(target: "reth::cli", ?hash, ?computed, len = code.len(), "Stored code doesn't match its hash");
+            corrupted.insert(hash);
+            report.issues.push(BytecodeIssue::HashMismatch { hash, computed, len: code.len() });
+        }
+        entry = codes.next()?;
+    }
+
+    let mut accounts = tx.cursor_read::<tables::PlainAccountState>()?;
+    let mut entry = accounts.first()?;
+    while let Some((address, account)) = entry {
+        entry = accounts.next()?;
+        let Some(hash) = account.bytecode_hash.filter(|hash| *hash != KECCAK_EMPTY) else {
+            continue
+        };
+        report.accounts += 1;
+        if corrupted.contains(&hash) {
+            report.issues.push(BytecodeIssue::CorruptedCode { address, hash });
+        } else if tx.get::<tables::Bytecodes>(hash)?.is_none() {
+            report.issues.push(BytecodeIssue::MissingCode { address, hash });
+        }
+    }
+    Ok(report)
+}
+
+/// A block whose receipts don't match the logs bloom of its header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloomMismatch {
+    /// The number of the block
+    pub block: u64,
+    /// The logs bloom of the header
+    pub expected: Bloom,
+    /// The bloom computed from the logs of the receipts
+    pub computed: Bloom,
+}
+
+/// The outcome of a logs bloom verification
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BloomReport {
+    /// The number of blocks whose bloom was recomputed
+    pub blocks: u64,
+    /// The number of blocks skipped because receipts of their transactions are missing
+    pub incomplete: u64,
+    /// The blocks whose bloom doesn't match, in block order
+    pub mismatches: Vec<BloomMismatch>,
+}
+
+impl BloomReport {
+    /// Returns true if the bloom of every verified block matches its header
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Recomputes the logs bloom of the canonical blocks in `range` from the logs of their receipts
+/// and compares it to the logs bloom of their headers. Stops at the first block that is not
+/// imported.
+pub fn verify_blooms<'a, TX: DbTx<'a>>(tx: &TX, range: RangeInclusive<u64>) -> Result<BloomReport> {
+    let mut report = BloomReport::default();
+    'blocks: for block in range {
+        let (Some(header), Some(body)) =
+            (tx.get::<tables::Headers>(block)?, tx.get::<tables::BlockBodies>(block)?)
+        else {
+            break
+        };
+        let mut receipts = Vec::with_capacity(body.tx_count as usize);
+        for tx_id in body.start_tx_id..body.start_tx_id + body.tx_count {
+            let Some(receipt) = tx.get::<tables::Receipts>(tx_id)? else {
+                report.incomplete += 1;
+                continue 'blocks
+            };
+            receipts.push(receipt);
+        }
+        report.blocks += 1;
+        let computed = logs_bloom(receipts.iter().flat_map(|receipt| &receipt.logs));
+        if computed != header.logs_bloom {
+            tracing::warn!(target: "reth::cli", block, "Receipts don't match the logs bloom of the header");
+            report.mismatches.push(BloomMismatch { block, expected: header.logs_bloom, computed });
+        }
+    }
+    Ok(report)
+}
+
+/// A receipt whose L1 fee fields don't match the recomputed ones
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L1FeeMismatch {
+    /// The number of the block
+    pub block: u64,
+    /// The index of the transaction in the block
+    pub index: u64,
+    /// The stored L1 fee fields
+    pub stored: L1FeeInfo,
+    /// The recomputed L1 fee
+    pub expected: U256,
+}
+
+/// The outcome of an L1 fee verification
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct L1FeeReport {
+    /// The number of receipts whose L1 fee was recomputed
+    pub receipts: u64,
+    /// The number of transactions without stored L1 fee fields, like deposits
+    pub missing: u64,
+    /// The receipts whose L1 fee doesn't match, in block order
+    pub mismatches: Vec<L1FeeMismatch>,
+}
+
+impl L1FeeReport {
+    /// Returns true if the L1 fee of every verified receipt matches
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Recomputes the L1 fees of the receipts of the canonical blocks in `range` and compares them to
+/// the fees stored in `fees`. Receipts of bedrock blocks of `chain` are recomputed from their
+/// transaction and the L1 attributes deposit restored from `deposits`, the others from their
+/// stored L1 gas price, L1 gas used and fee scalar. Stops at the first block that is not imported.
+pub fn verify_l1_fees<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    fees: &L1FeeStore,
+    deposits: &DepositStore,
+    chain: Option<&OpChainSpec>,
+    range: RangeInclusive<u64>,
+) -> Result<L1FeeReport> {
+    let mut report = L1FeeReport::default();
+    for block in range {
+        let (Some(header), Some(body)) =
+            (tx.get::<tables::Headers>(block)?, tx.get::<tables::BlockBodies>(block)?)
+        else {
+            break
+        };
+        let bedrock = chain.filter(|chain| chain.is_bedrock_active_at_block(block));
+        let computed = match bedrock {
+            Some(chain) => {
+                let mut transactions = Vec::with_capacity(body.tx_count as usize);
+                for tx_id in body.start_tx_id..body.start_tx_id + body.tx_count {
+                    let transaction = deposits
+                        .read(tx, tx_id)?
+                        .ok_or_else(|| eyre::eyre!("Transaction {tx_id} not found"))?;
+                    transactions.push(transaction);
+                }
+                let hash = tx.get::<tables::CanonicalHeaders>(block)?.unwrap_or_default();
+                let sealed = SealedBlock {
+                    header: SealedHeader::new(header, hash),
+                    body: transactions,
+                    ommers: vec![],
+                    withdrawals: None,
+                };
+                Some(
+                    l1_cost::block_fee_infos(&sealed, chain)?
+                        .into_iter()
+                        .collect::<HashMap<_, _>>(),
+                )
+            }
+            None => None,
+        };
+
+        for index in 0..body.tx_count {
+            let Some(stored) = fees.get(body.start_tx_id + index)? else {
+                report.missing += 1;
+                continue
+            };
+            report.receipts += 1;
+            let consistent = match &computed {
+                Some(computed) => computed.get(&(index as usize)) == Some(&stored),
+                None => l1_cost::check_l1_fee(&stored)?,
+            };
+            if !consistent {
+                let expected = match &computed {
+                    Some(computed) => {
+                        computed.get(&(index as usize)).map_or(U256::ZERO, |info| info.l1_fee)
+                    }
+                    None => l1_cost::expected_l1_fee(&stored)?,
+                };
+                tracing::warn!(target: "reth::cli", block, index, "L1 fee mismatch");
+                report.mismatches.push(L1FeeMismatch { block, index, stored, expected });
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Finds the first block of the range for which `matches` returns false, assuming that all blocks
+/// before it match and all blocks after it don't. Returns `None` if the last block of the range
+/// matches.
+pub fn bisect(
+    range: RangeInclusive<u64>,
+    mut matches: impl FnMut(u64) -> Result<bool>,
+) -> Result<Option<u64>> {
+    let (mut low, mut high) = range.into_inner();
+    if low > high || matches(high)? {
+        return Ok(None)
+    }
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if matches(middle)? {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    Ok(Some(high))
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self, ctx: CliContext) -> Result<()> {
+        match self.command {
+            Subcommands::Blocks(command) => command.execute(ctx).await,
+            Subcommands::Bisect(command) => command.execute(ctx).await,
+            Subcommands::Checkpoints(command) => command.execute(ctx).await,
+            Subcommands::Bytecodes(command) => command.execute(ctx).await,
+            Subcommands::Blooms(command) => command.execute(ctx).await,
+            Subcommands::L1Fees(command) => command.execute(ctx).await,
+            Subcommands::Execute(command) => command.execute(ctx).await,
+        }
+    }
+}
+
+impl BlocksCommand {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db_path = self.db.path();
+        journal::record(&db_path, "verify blocks", &[], async {
+            let db = self.db.open_ro()?;
+            let tip = analytics::canonical_tip(&db)?
+                .ok_or_else(|| eyre::eyre!("No canonical blocks found in the database"))?;
+            let to = self.to.unwrap_or(tip);
+            PruneCheckpoints::read(&db_path)?
+                .ensure_available(PrunedData::Bodies, &(self.from..=to))?;
+            let workers = self.workers.unwrap_or_else(|| {
+                thread::available_parallelism().map_or(1, |threads| threads.get())
+            });
+
+            let pool = self
+                .rpc_urls
+                .iter()
+                .map(|url| RpcSource::new(url.as_str(), self.from..=to))
+                .collect::<Vec<_>>();
+            // The workers run outside of the runtime, but the clients need it for their requests
+            let runtime = tokio::runtime::Handle::current();
+            tracing::info!(target: "reth::cli", from = self.from, to, workers, endpoints = pool.len(), "Verifying blocks");
+            let deposits = DepositStore::open(&self.db.static_path()?)?;
+            let report = tokio::task::block_in_place(|| {
+                verify(&db, &deposits, self.from..=to, workers, &pool, |source, number| {
+                    let _runtime = runtime.enter();
+                    source.block_hashes(number)
+                })
+            })?;
+
+            for (number, mismatch) in &report.mismatches {
+                println!("{number}: {mismatch:?}");
+            }
+            println!("Checked:    {}", report.checked);
+            println!("Mismatches: {}", report.mismatches.len());
+            if !report.is_consistent() {
+                eyre::bail!(
+                    "{} of {} blocks differ from the reference",
+                    report.mismatches.len(),
+                    report.checked
+                )
+            }
+            Ok(())
+        })
+        .await
+    }
+}
+
+impl BisectCommand {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db_path = self.db.path();
+        journal::record(&db_path, "verify bisect", &[], async {
+            let db = self.db.open_ro()?;
+            let tip = analytics::canonical_tip(&db)?
+                .ok_or_else(|| eyre::eyre!("No canonical blocks found in the database"))?;
+            let to = self.to.unwrap_or(tip);
+            let reference = RpcSource::new(self.against.as_str(), self.from..=to);
+
+            let mut hashes = Vec::new();
+            let divergent = bisect(self.from..=to, |number| {
+                let (expected, _) = reference.block_hashes(number)?;
+                let found = db.view(|tx| tx.get::<tables::CanonicalHeaders>(number))??;
+                tracing::debug!(target: "reth::cli", number, ?expected, ?found, "Compared block");
+                hashes.push((number, expected, found));
+                Ok(found == Some(expected))
+            })?;
+            tracing::info!(target: "reth::cli", requests = hashes.len(), "Bisection finished");
+
+            let Some(number) = divergent else {
+                println!("Blocks {}..={to} match the reference", self.from);
+                return Ok(())
+            };
+            let (_, expected, found) = hashes
+                .into_iter()
+                .find(|(compared, _, _)| *compared == number)
+                .expect("the divergent block was compared");
+            println!("First divergent block: {number}");
+            println!("Reference:  {expected:?}");
+            match found {
+                Some(found) => println!("Database:   {found:?}"),
+                None => println!("Database:   missing"),
+            }
+            if number > self.from {
+                println!("Last matching block: {}", number - 1);
+            }
+            Ok(())
+        })
+        .await
+    }
+}
+
+impl CheckpointsCommand {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db_path = self.db.path();
+        journal::record(&db_path, "verify checkpoints", &[self.file.as_path()], async {
+            let file = CheckpointFile::read(&self.file)?;
+            let db = self.db.open_ro()?;
+            let mismatches = file.verify(&db.tx()?)?;
+            for (number, mismatch) in &mismatches {
+                println!("{number}: {mismatch:?}");
+            }
+            println!("Checkpoints: {}", file.checkpoints.len());
+            println!("Tip:         {} {:?}", file.tip.number, file.tip.hash);
+            println!("Mismatches:  {}", mismatches.len());
+            if let Some((number, _)) = mismatches.first() {
+                eyre::bail!("The database differs from the checkpoints starting at block {number}")
+            }
+            Ok(())
+        })
+        .await
+    }
+}
+
+impl BytecodesCommand {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db_path = self.db.path();
+        journal::record(&db_path, "verify bytecodes", &[], async {
+            let db = self.db.open_ro()?;
+            let report = verify_bytecodes(&db.tx()?)?;
+            for issue in &report.issues {
+                println!("{issue:?}");
+            }
+            println!("Bytecodes:  {}", report.bytecodes);
+            println!("Accounts:   {}", report.accounts);
+            println!("Issues:     {}", report.issues.len());
+            if !report.is_consistent() {
+                eyre::bail!("{} corrupted codes or accounts found", report.issues.len())
+            }
+            Ok(())
+        })
+        .await
+    }
+}
+
+impl L1FeesCommand {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db_path = self.db.path();
+        journal::record(&db_path, "verify l1-fees", &[], async {
+            let db = self.db.open_ro()?;
+            let fees = L1FeeStore::open(&self.db.static_path()?)?;
+            let deposits = DepositStore::open(&self.db.static_path()?)?;
+            let chain = OpChainSpec::read(&db_path)?;
+            let tip = analytics::canonical_tip(&db)?
+                .ok_or_else(|| eyre::eyre!("No canonical blocks found in the database"))?;
+            let to = self.to.unwrap_or(tip);
+            let pruned = PruneCheckpoints::read(&db_path)?;
+            pruned.ensure_available(PrunedData::Receipts, &(self.from..=to))?;
+            pruned.ensure_available(PrunedData::Bodies, &(self.from..=to))?;
+            let report =
+                verify_l1_fees(&db.tx()?, &fees, &deposits, chain.as_ref(), self.from..=to)?;
+            for mismatch in &report.mismatches {
+                println!(
+                    "{}/{}: L1 fee {} expected {}",
+                    mismatch.block, mismatch.index, mismatch.stored.l1_fee, mismatch.expected
+                );
+            }
+            println!("Receipts:    {}", report.receipts);
+            println!("Missing:     {}", report.missing);
+            println!("Mismatches:  {}", report.mismatches.len());
+            if let Some(mismatch) = report.mismatches.first() {
+                eyre::bail!(
+                    "{} receipts don't match their L1 fee, the first being in block {}",
+                    report.mismatches.len(),
+                    mismatch.block
+                )
+            }
+            Ok(())
+        })
+        .await
+    }
+}
+
+impl BloomsCommand {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db_path = self.db.path();
+        journal::record(&db_path, "verify blooms", &[], async {
+            let db = self.db.open_ro()?;
+            let tip = analytics::canonical_tip(&db)?
+                .ok_or_else(|| eyre::eyre!("No canonical blocks found in the database"))?;
+            let to = self.to.unwrap_or(tip);
+            PruneCheckpoints::read(&db_path)?
+                .ensure_available(PrunedData::Receipts, &(self.from..=to))?;
+            let report = verify_blooms(&db.tx()?, self.from..=to)?;
+            for mismatch in &report.mismatches {
+                println!("{}: logs bloom mismatch", mismatch.block);
+            }
+            println!("Blocks:      {}", report.blocks);
+            println!("Incomplete:  {}", report.incomplete);
+            println!("Mismatches:  {}", report.mismatches.len());
+            if let Some(mismatch) = report.mismatches.first() {
+                eyre::bail!(
+                    "{} blocks don't match their logs bloom, the first being block {}",
+                    report.mismatches.len(),
+                    mismatch.block
+                )
+            }
+            Ok(())
+        })
+        .await
+    }
+}
+
+impl ExecuteCommand {
+    /// Execute the command
+    pub async fn execute(self, _ctx: CliContext) -> Result<()> {
+        let db_path = self.db.path();
+        journal::record(&db_path, "verify execute", &[], async {
+            let Some(chain) = OpChainSpec::read(&db_path)? else {
+                eyre::bail!(
+                    "No chain spec found at {}, import the genesis first",
+                    db_path.display()
+                )
+            };
+            let pruned = PruneCheckpoints::read(&db_path)?;
+            pruned.ensure_available(PrunedData::Bodies, &self.range)?;
+            pruned.ensure_available(PrunedData::Receipts, &self.range)?;
+            let db = self.db.open_ro()?;
+            let jobs = self.jobs.unwrap_or_else(|| {
+                thread::available_parallelism().map_or(1, |threads| threads.get())
+            });
+            tracing::info!(target: "reth::cli", range = ?self.range, jobs, "Re-executing blocks");
+            let report = tokio::task::block_in_place(|| {
+                verify_execution(&db, &chain.inner, self.range.clone(), jobs)
+            })?;
+
+            for (number, mismatch) in &report.mismatches {
+                println!("{number}: {mismatch:?}");
+            }
+            println!("Checked:    {}", report.checked);
+            println!("Mismatches: {}", report.mismatches.len());
+            if let Some((first, _)) = report.mismatches.first() {
+                eyre::bail!(
+                    "{} of {} blocks diverge on re-execution, the first being block {first}",
+                    report.mismatches.len(),
+                    report.checked
+                )
+            }
+            Ok(())
+        })
+        .await
+    }
+}
@@ -0,0 +1,157 @@
+use std::{
+    fmt,
+    sync::{mpsc, Arc},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::cli::progress::ImportProgress;
+
+/// The exit code used when the watchdog has to kill a stalled import that does not resume.
+/// `EX_TEMPFAIL` signals supervisors that the import can be retried.
+pub const STALLED_EXIT_CODE: i32 = 75;
+
+/// The error returned by an import that was aborted by the [Watchdog]. The import made no progress
+/// for too long, but nothing indicates the input is invalid, so it can be retried.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StalledImport {
+    /// The stage the import stalled in
+    pub stage: &'static str,
+    /// The number of items processed before the import stalled
+    pub items: u64,
+}
+
+impl fmt::Display for StalledImport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Import stalled in stage {} after {} items, aborted by the watchdog. Retry the import.",
+            self.stage, self.items
+        )
+    }
+}
+
+impl std::error::Error for StalledImport {}
+
+/// Monitors an [ImportProgress] on a background thread.
+///
+/// If no items are processed for `timeout`, the watchdog logs the state of the import. With
+/// `abort` set, it flags the import as stalled so it fails with [StalledImport] on its next
+/// progress update, and exits the process with [STALLED_EXIT_CODE] if the import stays stuck for
+/// another `timeout`. The watchdog stops when dropped.
+#[derive(Debug)]
+pub struct Watchdog {
+    shutdown: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    /// Spawns a watchdog observing the given progress
+    pub fn spawn(progress: Arc<ImportProgress>, timeout: Duration, abort: bool) -> Self {
+        let (shutdown, stop) = mpsc::channel();
+        let handle = thread::Builder::new()
+            .name("import-watchdog".to_string())
+            .spawn(move || watch(&progress, timeout, abort, stop))
+            .expect("failed to spawn watchdog thread");
+        Self { shutdown: Some(shutdown), handle: Some(handle) }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        drop(self.shutdown.take());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn watch(progress: &ImportProgress, timeout: Duration, abort: bool, stop: mpsc::Receiver<()>) {
+    let poll = (timeout / 4).max(Duration::from_millis(10));
+    let mut last = (progress.stage(), progress.items());
+    let mut last_change = Instant::now();
+    let mut reported = false;
+
+    loop {
+        match stop.recv_timeout(poll) {
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            _ => return,
+        }
+
+        // Entering a new stage counts as progress as well
+        let current = (progress.stage(), progress.items());
+        if current != last {
+            if reported {
+                tracing::info!(target: "reth::cli", stage = current.0, items = current.1, "Import resumed");
+            }
+            last = current;
+            last_change = Instant::now();
+            reported = false;
+            continue
+        }
+
+        let idle = last_change.elapsed();
+        if idle < timeout {
+            continue
+        }
+
+        if !reported {
+            dump_diagnostics(progress, idle);
+            reported = true;
+            if abort {
+                tracing::error!(target: "reth::cli", "Aborting stalled import");
+                progress.mark_stalled();
+            }
+        } else if abort && idle >= timeout * 2 {
+            tracing::error!(target: "reth::cli", idle = ?idle, "Stalled import did not unwind, exiting");
+            std::process::exit(STALLED_EXIT_CODE);
+        }
+    }
+}
+
+/// Logs the state of a stalled import
+fn dump_diagnostics(progress: &ImportProgress, idle: Duration) {
+    tracing::warn!(
+        target: "reth::cli",
+        stage = progress.stage(),
+        items = progress.items(),
+        offset = progress.offset(),
+        tx_age = ?progress.tx_age(),
+        idle = ?idle,
+        "Import made no progress"
+    );
+    for thread in thread_states() {
+        tracing::warn!(target: "reth::cli", "{thread}");
+    }
+}
+
+/// Describes the threads of the process. Kernel stacks are included where the platform exposes
+/// them to the current user.
+fn thread_states() -> Vec<String> {
+    let Ok(tasks) = std::fs::read_dir("/proc/self/task") else { return Vec::new() };
+    let mut threads = Vec::new();
+    for task in tasks.flatten() {
+        let path = task.path();
+        let read = |file: &str| std::fs::read_to_string(path.join(file)).ok();
+        let name = read("comm").unwrap_or_default();
+        let state = read("status")
+            .and_then(|status| {
+                status.lines().find(|line| line.starts_with("State:")).map(String::from)
+            })
+            .unwrap_or_default();
+        let wchan = read("wchan").unwrap_or_default();
+        let mut line = format!(
+            "thread {} ({}) {} wchan={}",
+            task.file_name().to_string_lossy(),
+            name.trim(),
+            state.trim(),
+            wchan.trim()
+        );
+        if let Some(stack) = read("stack") {
+            line.push('\n');
+            line.push_str(stack.trim_end());
+        }
+        threads.push(line);
+    }
+    threads
+}
@@ -8,10 +8,12 @@ fn main() {
     }
 }
 
-/// Registers a basic ctrl-c handler that will exit the process.
+/// Registers a ctrl-c handler that runs the hooks of the
+/// [shutdown registry](cli::shutdown::registry) before it exits the process.
 pub fn register_shutdown_handler() {
     ctrlc::set_handler(move || {
         tracing::info!(target: "reth::cli", "shutting down... received ctrl-c input");
+        cli::shutdown::registry().run_blocking(cli::shutdown::DEFAULT_HOOK_TIMEOUT);
         std::process::exit(0);
     })
     .expect("could not register shutdown handler");
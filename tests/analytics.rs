@@ -0,0 +1,69 @@
+use std::str::FromStr;
+
+use reth_primitives::{
+    keccak256,
+    rpc::{H160, H256},
+    U256,
+};
+use rlp::RlpStream;
+
+use op_reth::cli::{analytics, receipts::Receipt};
+
+fn receipt_with_logs(block_number: u64, logs: &[(H160, H256, U256)]) -> Receipt {
+    let mut stream = RlpStream::new_list(logs.len());
+    for (address, topic, amount) in logs {
+        stream.begin_list(3);
+        stream.append(address);
+        stream.begin_list(2).append(topic).append(&H256::zero());
+        stream.append(&amount.to_be_bytes::<32>().to_vec());
+    }
+
+    Receipt {
+        ty: 0,
+        post_state: vec![],
+        status: 1,
+        cumulative_gas_used: 0,
+        bloom: vec![],
+        logs: stream.out().to_vec(),
+        tx_hash: H256::zero(),
+        contract_address: String::new(),
+        gas_used: 0,
+        block_hash: H256::zero(),
+        block_number: U256::from(block_number),
+        transaction_index: 0,
+        l1_gas_price: U256::ZERO,
+        l1_gas_used: U256::ZERO,
+        l1_fee: U256::ZERO,
+        l1_fee_scalar: String::new(),
+    }
+}
+
+#[test]
+fn test_deposit_totals() {
+    let ovm_eth = H160::from_str(analytics::OVM_ETH_ADDRESS).unwrap();
+    let mint = H256(keccak256("Mint(address,uint256)").0);
+    let burn = H256(keccak256("Burn(address,uint256)").0);
+    let receipts = vec![
+        receipt_with_logs(1, &[(ovm_eth, mint, U256::from(100))]),
+        receipt_with_logs(
+            2,
+            &[(ovm_eth, burn, U256::from(40)), (H160::zero(), mint, U256::from(7))],
+        ),
+        receipt_with_logs(3, &[(ovm_eth, mint, U256::from(1000))]),
+    ];
+
+    let totals = analytics::deposit_totals(&receipts, 2).unwrap();
+    assert_eq!(U256::from(100), totals.minted);
+    assert_eq!(U256::from(40), totals.burned);
+    assert_eq!(1, totals.deposits);
+    assert_eq!(1, totals.withdrawals);
+    assert_eq!(Some(U256::from(60)), totals.net());
+
+    let report = analytics::SupplyReport {
+        block: 2,
+        state_supply: U256::from(65),
+        genesis_supply: U256::from(5),
+        totals,
+    };
+    assert!(report.is_consistent());
+}
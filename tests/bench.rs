@@ -0,0 +1,41 @@
+use clap::Parser;
+
+use op_reth::cli::bench::Command;
+
+const BLOCKS_PATH: &str = "tests/fixtures/blocks.rlp";
+const GENESIS_PATH: &str = "tests/fixtures/genesis.json";
+const RECEIPTS_PATH: &str = "tests/fixtures/receipts.rlp";
+const STATE_PATH: &str = "tests/fixtures/state.json";
+
+#[tokio::test]
+async fn test_bench() {
+    let command = Command::parse_from([
+        "bench",
+        "--genesis",
+        GENESIS_PATH,
+        "--blocks",
+        BLOCKS_PATH,
+        "--receipts",
+        RECEIPTS_PATH,
+        "--state",
+        STATE_PATH,
+        "--to-block",
+        "1",
+        "--max-accounts",
+        "1",
+    ]);
+    let dir = tempfile::tempdir().unwrap();
+    let report = command.run(dir.path()).await.unwrap();
+
+    // Every import runs on its slice of the inputs
+    let items = report.stages.iter().map(|stage| (stage.stage, stage.items)).collect::<Vec<_>>();
+    assert_eq!(vec![("genesis", 2), ("blocks", 1), ("receipts", 1), ("state", 1)], items);
+    assert!(report.stages.iter().all(|stage| stage.items_per_second() > 0.0));
+    if cfg!(target_os = "linux") {
+        assert!(report.peak_memory.unwrap() > 0);
+    }
+
+    // The blocks build on the genesis
+    let command = Command::parse_from(["bench", "--blocks", BLOCKS_PATH]);
+    assert!(command.run(dir.path()).await.is_err());
+}
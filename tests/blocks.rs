@@ -1,51 +1,531 @@
-use std::{path::PathBuf, str::FromStr};
+use std::str::FromStr;
 
-use reth_db::{database::Database, tables, transaction::DbTx};
-use reth_primitives::{Bytes, Header, TxHash, U256};
+use reth_db::{
+    database::Database,
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::{
+    Address, Block, Header, Receipt, Signature, Transaction, TransactionKind, TransactionSigned,
+    TxEip1559, TxHash, TxType, U256,
+};
+use reth_rlp::Encodable;
 
-use op_reth::cli::{blocks, db};
+use op_reth::cli::{
+    args::ImportArgs,
+    block_headers::{self, HeaderRange, HeadersOnlyRanges},
+    blocks::{self, query, BlockFormat, ErigonBlock, ErigonHeader, ErigonTx, LegacyTx},
+    chain::{self, UnsignedTxPolicy},
+    db,
+    dead_letter::{DeadLetterFile, ImportError},
+    deposit::DepositStore,
+    genesis,
+    import::detect_block_format,
+    source::rpc::RpcSource,
+    validate::{BrokenContinuity, ContinuityIssue},
+};
 
-const BLOCKS_PATH: &str = "data/export_0_4061224";
+const BLOCKS_PATH: &str = "tests/fixtures/blocks.rlp";
+const GENESIS_PATH: &str = "tests/fixtures/genesis.json";
 
 #[test]
 fn test_blocks_from_file() {
     let blocks = blocks::read_blocks(BLOCKS_PATH).unwrap();
-    let unsealed_first_header = blocks[0].header.clone().unseal();
+    assert_eq!(vec![0, 1, 2], blocks.iter().map(|block| block.number).collect::<Vec<_>>());
+
+    // The export starts at the genesis block and chains the following blocks onto it
+    let genesis = genesis::Genesis::from_file(GENESIS_PATH).unwrap();
+    assert_eq!(genesis.to_header().unwrap().seal_slow(), blocks[0].header);
+    assert_eq!(blocks[0].hash(), blocks[1].parent_hash);
+    assert_eq!(blocks[1].hash(), blocks[2].parent_hash);
+
+    assert_eq!(0, blocks[0].body.len());
+    assert_eq!(0, blocks[0].ommers.len());
     assert_eq!(
-        TxHash::from_str("0x7334ddc1f6beaf66892c25cffdecec275cdfabaf4def047f0c3ce20e6f6483e8")
+        TxHash::from_str("0x023ef02785eb858dc48e52a4a13ea0be63740a31e66ae4f6fcf4bb14eb22fd8a")
             .unwrap(),
-        unsealed_first_header.parent_hash
+        blocks[1].body[0].hash()
     );
+    assert_eq!(Some(420), blocks[2].body[0].chain_id());
+}
+
+#[tokio::test]
+async fn test_read_write_blocks() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut db, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut db, Some(BLOCKS_PATH), &args).await.unwrap();
+
+    let tx = db.tx().unwrap();
+    let expected = blocks::read_blocks(BLOCKS_PATH).unwrap();
+    for block in &expected {
+        assert_eq!(Some(block.hash()), tx.get::<tables::CanonicalHeaders>(block.number).unwrap());
+        assert_eq!(
+            Some(block.header.clone().unseal()),
+            tx.get::<tables::Headers>(block.number).unwrap()
+        );
+    }
+    let transaction = &expected[2].body[0];
+    assert_eq!(Some(1), tx.get::<tables::TxHashNumber>(transaction.hash()).unwrap());
+    assert_eq!(Some(transaction.clone()), tx.get::<tables::Transactions>(1).unwrap());
+}
+
+#[tokio::test]
+async fn test_reimport() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut db, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut db, Some(BLOCKS_PATH), &args).await.unwrap();
+    let deposits = DepositStore::open(dir.path()).unwrap();
+    blocks::reimport(&db, &deposits, BLOCKS_PATH, 1..=2).unwrap();
+
+    // The export has no receipts, so blocks whose receipts were imported are not unwound
+    let tx = db.tx_mut().unwrap();
+    let receipt =
+        Receipt { tx_type: TxType::Legacy, success: true, cumulative_gas_used: 0, logs: vec![] };
+    tx.put::<tables::Receipts>(1, receipt).unwrap();
+    tx.commit().unwrap();
+    let err = blocks::reimport(&db, &deposits, BLOCKS_PATH, 1..=2).unwrap_err();
+    assert!(err.to_string().contains("Block 2 has receipts"), "{err}");
+    assert!(db.tx().unwrap().get::<tables::Receipts>(1).unwrap().is_some());
+    blocks::reimport(&db, &deposits, BLOCKS_PATH, 1..=1).unwrap();
+}
+
+#[tokio::test]
+async fn test_headers_only_import() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut db, Some(GENESIS_PATH), &args).await.unwrap();
+    let range =
+        block_headers::apply(&mut db, dir.path(), Some(BLOCKS_PATH), None, &args).await.unwrap();
+    assert_eq!(Some(HeaderRange { from: 1, to: 2 }), range);
+
+    let expected = blocks::read_blocks(BLOCKS_PATH).unwrap();
+    {
+        let tx = db.tx().unwrap();
+        assert_eq!(Some(expected[2].hash()), tx.get::<tables::CanonicalHeaders>(2).unwrap());
+        assert_eq!(None, tx.get::<tables::BlockBodies>(1).unwrap());
+        assert_eq!(None, tx.get::<tables::Transactions>(1).unwrap());
+    }
+    let ranges = HeadersOnlyRanges::new(dir.path());
+    assert_eq!(vec![HeaderRange { from: 1, to: 2 }], ranges.read().unwrap());
+
+    let backfilled =
+        blocks::backfill_bodies(&mut db, dir.path(), Some(BLOCKS_PATH), None, &args).await.unwrap();
+    assert_eq!(2, backfilled);
+    assert!(ranges.read().unwrap().is_empty());
+    assert!(blocks::backfill_bodies(&mut db, dir.path(), Some(BLOCKS_PATH), None, &args)
+        .await
+        .is_err());
+    let tx = db.tx().unwrap();
+    assert_eq!(Some(expected[2].body[0].clone()), tx.get::<tables::Transactions>(1).unwrap());
+}
+
+#[test]
+fn test_headers_only_ranges() {
+    let dir = tempfile::tempdir().unwrap();
+    let ranges = HeadersOnlyRanges::new(dir.path());
+    ranges.add(HeaderRange { from: 10, to: 19 }).unwrap();
+    ranges.add(HeaderRange { from: 20, to: 29 }).unwrap();
+    ranges.add(HeaderRange { from: 40, to: 49 }).unwrap();
     assert_eq!(
-        TxHash::from_str("0x7334ddc1f6beaf66892c25cffdecec275cdfabaf4def047f0c3ce20e6f6483e8")
-            .unwrap(),
-        unsealed_first_header.ommers_hash
+        vec![HeaderRange { from: 10, to: 29 }, HeaderRange { from: 40, to: 49 }],
+        ranges.read().unwrap()
     );
 
-    assert_eq!(0, blocks[0].body.len());
-    assert_eq!(0, blocks[0].ommers.len());
+    ranges.remove(15, 44).unwrap();
+    assert_eq!(
+        vec![HeaderRange { from: 10, to: 14 }, HeaderRange { from: 45, to: 49 }],
+        ranges.read().unwrap()
+    );
+    ranges.remove(0, 100).unwrap();
+    assert!(ranges.read().unwrap().is_empty());
 }
 
 #[tokio::test]
-async fn test_read_write_blocks() {
-    let db_path = PathBuf::from("temp-blocks");
-    let mut db = db::open_rw_env(db_path.as_path()).unwrap();
-    blocks::apply(&mut db, Some(BLOCKS_PATH)).await.unwrap();
+async fn test_refuse_gaps() {
+    // An export missing block 1, as left behind by a lenient import skipping it
+    let data = std::fs::read(BLOCKS_PATH).unwrap();
+    let list = rlp::Rlp::new(&data);
+    let mut export = rlp::RlpStream::new_list(2);
+    export.append_raw(list.at(0).unwrap().as_raw(), 1);
+    export.append_raw(list.at(2).unwrap().as_raw(), 1);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("export_gap");
+    std::fs::write(&path, export.out()).unwrap();
+    let path = path.display().to_string();
+
+    for decode_threads in [None, Some(2)] {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = db::open_rw_env(dir.path()).unwrap();
+        let args = ImportArgs { decode_threads, ..Default::default() };
+        genesis::apply(&mut db, Some(GENESIS_PATH), &args).await.unwrap();
+        let err = blocks::apply(&mut db, Some(&path), &args).await.unwrap_err();
+        assert_eq!(
+            vec![ContinuityIssue::MissingParent { block: 2 }],
+            err.downcast::<BrokenContinuity>().unwrap().issues
+        );
+        assert_eq!(None, db.tx().unwrap().get::<tables::CanonicalHeaders>(2).unwrap());
+    }
+}
+
+#[tokio::test]
+async fn test_from_dead_letter() {
+    // An export holding blocks 0 and 1, with block 2 left in a dead-letter file
+    let data = std::fs::read(BLOCKS_PATH).unwrap();
+    let list = rlp::Rlp::new(&data);
+    let mut export = rlp::RlpStream::new_list(2);
+    export.append_raw(list.at(0).unwrap().as_raw(), 1);
+    export.append_raw(list.at(1).unwrap().as_raw(), 1);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("export");
+    std::fs::write(&path, export.out()).unwrap();
+    let dead_letter_path = dir.path().join("dead-letter.jsonl");
+    let mut dead_letter = DeadLetterFile::create(&dead_letter_path).unwrap();
+    let block = list.at(2).unwrap().as_raw();
+    dead_letter.write(&ImportError::new("block", 2, "stalled"), block).unwrap();
+    dead_letter.write(&ImportError::new("receipt", 0, "invalid"), &[0xC0]).unwrap();
+    dead_letter.finish().unwrap();
+
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut db, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut db, Some(&path.display().to_string()), &args).await.unwrap();
+    assert_eq!(None, db.tx().unwrap().get::<tables::CanonicalHeaders>(2).unwrap());
+
+    // Reprocessing the file imports the block records and ignores the others
+    let args = ImportArgs { from_dead_letter: Some(dead_letter_path), ..Default::default() };
+    blocks::apply(&mut db, None, &args).await.unwrap();
+    assert!(db.tx().unwrap().get::<tables::CanonicalHeaders>(2).unwrap().is_some());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sync_range() {
+    use jsonrpsee::{server::ServerBuilder, RpcModule};
+
+    // A legacy node serving the blocks of the export like l2geth does
+    let server = ServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", server.local_addr().unwrap());
+    let mut module = RpcModule::new(blocks::read_blocks(BLOCKS_PATH).unwrap());
+    module
+        .register_method("debug_getBlockRlp", |params, blocks| {
+            let number: u64 = params.one()?;
+            let mut encoded = Vec::new();
+            blocks[number as usize].clone().unseal().encode(&mut encoded);
+            Ok(hex::encode(encoded))
+        })
+        .unwrap();
+    let _handle = server.start(module).unwrap();
+
+    // Ranges starting above the genesis block keep their first block
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut db, Some(GENESIS_PATH), &args).await.unwrap();
+    for range in [1..=1, 2..=2] {
+        blocks::apply_from(&mut db, &mut RpcSource::new(&url, range), &args).await.unwrap();
+    }
+    let expected = blocks::read_blocks(BLOCKS_PATH).unwrap();
+    for block in &expected {
+        assert_eq!(
+            Some(block.hash()),
+            db.tx().unwrap().get::<tables::CanonicalHeaders>(block.number).unwrap()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_insert_errors_fail_import() {
+    // Blocks that can't be inserted fail the import instead of being logged
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let err = blocks::apply(&mut db, Some(BLOCKS_PATH), &ImportArgs::default()).await.unwrap_err();
+    assert!(err.to_string().contains("Genesis block not found"), "{err}");
+}
+
+#[tokio::test]
+async fn test_pipelined_import() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs { batch_size: 1, decode_threads: Some(2), ..Default::default() };
+    genesis::apply(&mut db, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut db, Some(BLOCKS_PATH), &args).await.unwrap();
 
     let tx = db.tx().unwrap();
-    let expected_difficulty = U256::from_str("0x01").unwrap();
-    let expected_gas_limit = 15000000;
-    let expected_extra_data = Bytes::from_str("0x000000000000000000000000000000000000000000000000000000000000000027770a9694e4B4b1E130Ab91Bc327C36855f612E0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000").unwrap();
-    let header = tx.get::<tables::Headers>(0u64).unwrap().unwrap();
-    assert_eq!(
-        Header {
-            difficulty: expected_difficulty,
-            gas_limit: expected_gas_limit,
-            extra_data: expected_extra_data,
-            ..Default::default()
-        },
-        header
+    let expected = blocks::read_blocks(BLOCKS_PATH).unwrap();
+    for block in &expected {
+        assert_eq!(Some(block.hash()), tx.get::<tables::CanonicalHeaders>(block.number).unwrap());
+    }
+    let transaction = &expected[2].body[0];
+    assert_eq!(Some(1), tx.get::<tables::TxHashNumber>(transaction.hash()).unwrap());
+}
+
+#[tokio::test]
+async fn test_query_block() {
+    let contents = std::fs::read(BLOCKS_PATH).unwrap();
+    let expected = blocks::read_blocks(BLOCKS_PATH).unwrap();
+    let found = query::find_block(BlockFormat::Erigon, &contents, 2).unwrap().unwrap();
+    assert_eq!(Ok(expected[2].clone()), found.block);
+    assert!(query::find_block(BlockFormat::Erigon, &contents, 3).unwrap().is_none());
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut db, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut db, Some(BLOCKS_PATH), &args).await.unwrap();
+    let tx = db.tx().unwrap();
+    let stored = query::load_block(&tx, 2).unwrap().unwrap();
+    assert_eq!(Ok(expected[2].clone()), stored.block);
+    assert!(query::load_block(&tx, 3).unwrap().is_none());
+
+    // A block that fails to decode is found by the number following the block before it
+    let mut standard = Vec::new();
+    standard.extend(query::load_block(&tx, 0).unwrap().unwrap().raw);
+    standard.extend(query::load_block(&tx, 1).unwrap().unwrap().raw);
+    standard.push(0xc0);
+    let broken = query::find_block(BlockFormat::Geth, &standard, 2).unwrap().unwrap();
+    assert_eq!(vec![0xc0], broken.raw);
+    assert!(broken.block.is_err());
+
+    let decoded = query::DecodedBlock::from(expected[2].clone());
+    let json = serde_json::to_value(&decoded).unwrap();
+    assert_eq!("0x2", json["number"]);
+    assert_eq!(1, json["transactions"].as_array().unwrap().len());
+    assert!(json["ommers"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn test_split_blocks() {
+    let contents = std::fs::read(BLOCKS_PATH).unwrap();
+    let expected = blocks::read_blocks(BLOCKS_PATH).unwrap();
+    let raw = blocks::split_blocks(BlockFormat::Erigon, &contents).unwrap();
+    let decoded: Vec<_> =
+        raw.iter().map(|raw| blocks::decode_block(BlockFormat::Erigon, raw).unwrap()).collect();
+    assert_eq!(expected, decoded);
+
+    let mut standard = Vec::new();
+    for block in &expected {
+        Block {
+            header: block.header.clone().unseal(),
+            body: block.body.clone(),
+            ommers: vec![],
+            withdrawals: None,
+        }
+        .encode(&mut standard);
+    }
+    let raw = blocks::split_blocks(BlockFormat::Geth, &standard).unwrap();
+    assert_eq!(expected[2], blocks::decode_block(BlockFormat::Geth, raw[2]).unwrap());
+    assert!(blocks::split_blocks(BlockFormat::Geth, &standard[..standard.len() - 1]).is_err());
+}
+
+#[test]
+fn test_decode_standard_blocks() {
+    let block = |number| Block {
+        header: Header { number, ..Default::default() },
+        body: vec![],
+        ommers: vec![],
+        withdrawals: None,
+    };
+    let mut contents = Vec::new();
+    block(1).encode(&mut contents);
+    block(2).encode(&mut contents);
+
+    assert_eq!(Some(BlockFormat::Geth), detect_block_format(&contents));
+    let blocks = blocks::decode_blocks(&contents, None, None, None).unwrap();
+    assert_eq!(vec![1, 2], blocks.iter().map(|block| block.number).collect::<Vec<_>>());
+
+    let hex = format!("0x{}\n", hex::encode(&contents));
+    assert_eq!(Some(BlockFormat::RlpStandard), detect_block_format(hex.as_bytes()));
+    assert_eq!(blocks, blocks::decode_blocks(hex.as_bytes(), None, None, None).unwrap());
+}
+
+#[test]
+fn test_decode_typed_transactions() {
+    let header = ErigonHeader::from(&Header { number: 1, ..Default::default() });
+    let transaction = Transaction::Eip1559(TxEip1559 {
+        chain_id: 420,
+        nonce: 1,
+        gas_limit: 21_000,
+        max_fee_per_gas: 2,
+        max_priority_fee_per_gas: 1,
+        to: TransactionKind::Call(Default::default()),
+        value: 5,
+        ..Default::default()
+    });
+    let signature = Signature { r: U256::from(1), s: U256::from(2), odd_y_parity: true };
+    let typed = TransactionSigned::from_transaction_and_signature(transaction, signature);
+    let block = ErigonBlock { header, txs: vec![ErigonTx::from(&typed)], uncles: vec![] };
+    assert!(matches!(block.txs[0], ErigonTx::Typed(_)));
+
+    let encoded = rlp::encode_list::<ErigonBlock, _>(&[block]);
+    let decoded =
+        blocks::decode_blocks_as(BlockFormat::Erigon, &encoded, None, None, None).unwrap();
+    assert_eq!(vec![typed], decoded[0].body);
+
+    // Deposit transactions have no reth counterpart, so their blocks fail to decode
+    let mut deposit = rlp::RlpStream::new_list(3);
+    deposit.append(&ErigonHeader::from(&Header { number: 2, ..Default::default() }));
+    deposit.begin_list(1).append(&vec![0x7Eu8, 0xC0]);
+    deposit.begin_list(0);
+    let mut export = rlp::RlpStream::new_list(1);
+    export.append_raw(&deposit.out(), 1);
+    let export = export.out();
+    let err = blocks::decode_blocks_as(BlockFormat::Erigon, &export, None, None, None).unwrap_err();
+    let err = err.downcast::<ImportError>().unwrap();
+    assert_eq!(("block", 0, Some(3)), (err.kind, err.index, err.offset));
+
+    // Lenient imports skip them and write them to the dead-letter file
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dead-letter.jsonl");
+    let mut dead_letter = DeadLetterFile::create(&path).unwrap();
+    let decoded =
+        blocks::decode_blocks_as(BlockFormat::Erigon, &export, Some(&mut dead_letter), None, None)
+            .unwrap();
+    assert!(decoded.is_empty());
+    dead_letter.finish().unwrap();
+    let letters = DeadLetterFile::read(&path).unwrap();
+    assert_eq!(vec![(0, Some(3))], letters.iter().map(|l| (l.index, l.offset)).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_malformed_export() {
+    // Bytes following the list of blocks are not silently dropped
+    let data = std::fs::read(BLOCKS_PATH).unwrap();
+    let trailing = [&data[..], &[0x01, 0x02]].concat();
+    let err =
+        blocks::decode_blocks_as(BlockFormat::Erigon, &trailing, None, None, None).unwrap_err();
+    let err = err.downcast::<ImportError>().unwrap();
+    assert_eq!((3, Some(data.len() as u64)), (err.index, err.offset));
+    assert!(blocks::split_blocks(BlockFormat::Erigon, &trailing).is_err());
+
+    let mut contents = Vec::new();
+    for number in 1..=3 {
+        let header = Header { number, ..Default::default() };
+        Block { header, body: vec![], ommers: vec![], withdrawals: None }.encode(&mut contents);
+    }
+    contents.pop();
+    let err = blocks::decode_blocks_as(BlockFormat::Geth, &contents, None, None, None).unwrap_err();
+    assert_eq!(2, err.downcast::<ImportError>().unwrap().index);
+}
+
+/// Legacy transactions signed by the key `0x4646..46`, before and after EIP-155, with their
+/// chain ids and hashes
+const SIGNED_TXS: [(&str, Option<u64>, &str); 5] = [
+    (
+        "f86380843b9aca0082520894353535353535353535353535353535353535353501801ca035407eb4abb96b6df9cdb1f572f562b6127934efd719fddda9f4cf91739761eba05b043e15bac72c8bdc1b6ffb67b6f227b03aa6764d1c43bbd0fe86ae23afbac9",
+        None,
+        "fc9647c0df9745ee3c5d4235a7e238dbdad9625316a4dafb1bab0ba9107e3c6d",
+    ),
+    (
+        "f86306843b9aca0082520894353535353535353535353535353535353535353501801ba0ba00265886f3df5b81dadf29e98792fe08c74852b5735eeec6841d894ee3c8b1a0189c2970892e4b2e22716179b1c8e45d312708e7a4e189f7d021d487dbe8fb9e",
+        None,
+        "2de0dff05e85441ae2808928f71f6fe2e3d31d9fb3721fe3b882e7b0f430ca9b",
+    ),
+    // The example of EIP-155
+    (
+        "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+        Some(1),
+        "33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788",
+    ),
+    (
+        "f86502843b9aca00825208943535353535353535353535353535353535353535018082036ca0d06fd7fc55668a302e23b878edd5ac7684a70fd62cd470c58a02977428d34e8ea00826e2e88874e39fbfc047aa68e548249c0abc7bb976c7ff459f47f4ee437c0f",
+        Some(420),
+        "023ef02785eb858dc48e52a4a13ea0be63740a31e66ae4f6fcf4bb14eb22fd8a",
+    ),
+    (
+        "f86505843b9aca00825208943535353535353535353535353535353535353535018082036ba0d7d054b02615f2b9d130da607fa1f18d43eb7a24008764527b9d3c0cb66f0f0aa07bd93e7f180229b8579e5e873c56aed9fddf7cba07b96e56c2b84c05f7926e6b",
+        Some(420),
+        "40e91874cec3bed15d8bcadaad0a991de9ade35fd6d35840e43870ae4cc9aa97",
+    ),
+];
+
+#[test]
+fn test_legacy_signatures() {
+    let sender = Address::from_str("0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f").unwrap();
+    for (raw, chain_id, hash) in SIGNED_TXS {
+        let raw = hex::decode(raw).unwrap();
+        let legacy: LegacyTx = rlp::decode(&raw).unwrap();
+        let tx = TransactionSigned::from(legacy);
+        assert_eq!(chain_id, tx.chain_id());
+        assert_eq!(TxHash::from_str(hash).unwrap(), tx.hash());
+        assert_eq!(Some(sender), tx.recover_signer());
+
+        // Exporting the transaction yields the original encoding
+        assert_eq!(raw, rlp::encode(&LegacyTx::try_from(&tx).unwrap()).to_vec());
+    }
+
+    assert_eq!((None, false), blocks::parse_v(U256::ZERO));
+    assert_eq!((None, true), blocks::parse_v(U256::from(28)));
+    assert_eq!((Some(420), false), blocks::parse_v(U256::from(875)));
+}
+
+#[test]
+fn test_unsigned_tx_policy() {
+    let unsigned = TransactionSigned::from_transaction_and_signature(
+        Transaction::Legacy(Default::default()),
+        Signature { r: U256::ZERO, s: U256::ZERO, odd_y_parity: false },
     );
+    let mut block = blocks::read_blocks(BLOCKS_PATH).unwrap().remove(1);
+    let signed = block.body[0].clone();
+    block.body.insert(0, unsigned.clone());
+    assert!(blocks::is_unsigned(&unsigned));
+    assert!(!blocks::is_unsigned(&signed));
+
+    let err =
+        blocks::apply_unsigned_tx_policy(&mut [block.clone()], UnsignedTxPolicy::Fail).unwrap_err();
+    assert!(err.to_string().contains("Transaction 0 of block 1 has no signature"));
+
+    let mut system = [block.clone()];
+    assert_eq!(1, blocks::apply_unsigned_tx_policy(&mut system, UnsignedTxPolicy::System).unwrap());
+    assert_eq!(vec![unsigned, signed.clone()], system[0].body);
+
+    let mut skipped = [block];
+    assert_eq!(1, blocks::apply_unsigned_tx_policy(&mut skipped, UnsignedTxPolicy::Skip).unwrap());
+    assert_eq!(vec![signed], skipped[0].body);
+}
 
-    std::fs::remove_dir_all(db_path).unwrap();
+#[tokio::test]
+async fn test_import_unsigned_as_system_tx() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs { unsigned_txs: Some(UnsignedTxPolicy::System), ..Default::default() };
+    genesis::apply(&mut db, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut db, Some(BLOCKS_PATH), &args).await.unwrap();
+
+    // A block following the fixture that starts with an enqueued message, like block 1 of OP
+    // Mainnet, followed by a signed transaction
+    let last = blocks::read_blocks(BLOCKS_PATH).unwrap().pop().unwrap();
+    let unsigned = TransactionSigned::from_transaction_and_signature(
+        Transaction::Legacy(Default::default()),
+        Signature { r: U256::ZERO, s: U256::ZERO, odd_y_parity: false },
+    );
+    let signed = last.body[0].clone();
+    let header = Header {
+        parent_hash: last.hash(),
+        number: last.number + 1,
+        timestamp: last.timestamp + 1,
+        ..Default::default()
+    };
+    let block = ErigonBlock {
+        header: ErigonHeader::from(&header),
+        txs: vec![ErigonTx::from(&unsigned), ErigonTx::from(&signed)],
+        uncles: vec![],
+    };
+    let path = dir.path().join("unsigned.rlp");
+    std::fs::write(&path, rlp::encode_list::<ErigonBlock, _>(&[block])).unwrap();
+    blocks::apply(&mut db, Some(path.to_str().unwrap()), &args).await.unwrap();
+
+    // The unsigned transaction is inserted with the system sender instead of a recovered one
+    let tx = db.tx().unwrap();
+    let body = tx.get::<tables::BlockBodies>(header.number).unwrap().unwrap();
+    assert_eq!(2, body.tx_count);
+    let stored = tx.get::<tables::Transactions>(body.start_tx_id).unwrap().unwrap();
+    assert!(blocks::is_unsigned(&stored));
+    let senders = (body.start_tx_id..body.start_tx_id + 2)
+        .map(|tx_id| tx.get::<tables::TxSenders>(tx_id).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![Some(chain::unsigned_tx_sender()), signed.recover_signer()], senders);
 }
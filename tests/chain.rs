@@ -0,0 +1,96 @@
+use clap::ValueEnum;
+use reth_primitives::{proofs::EMPTY_ROOT, ForkCondition, Hardfork, Header, SealedBlock};
+
+use op_reth::cli::{
+    chain::{
+        genesis_from_header, ChainPreset, OpChainSpec, UnsignedTxPolicy, OP_GOERLI_BEDROCK_BLOCK,
+        OP_GOERLI_CANYON_TIME, OP_GOERLI_REGOLITH_TIME, OP_MAINNET_EIP1559_DENOMINATOR,
+        OP_MAINNET_EIP1559_DENOMINATOR_CANYON,
+    },
+    keccak,
+};
+
+#[test]
+fn test_preset_names() {
+    let parse = |name| ChainPreset::from_str(name, false).unwrap();
+    assert_eq!(ChainPreset::OpMainnet, parse("optimism-mainnet"));
+    assert_eq!(ChainPreset::OpMainnet, parse("op-mainnet"));
+    assert_eq!(ChainPreset::OpGoerli, parse("optimism-goerli"));
+    assert_eq!(ChainPreset::OpGoerli, parse("op-goerli"));
+    assert_eq!(ChainPreset::BaseMainnet, parse("base-mainnet"));
+    assert!(ChainPreset::from_str("base-goerli", false).is_err());
+
+    // The names of the presets match their command line values
+    for preset in ChainPreset::value_variants() {
+        assert_eq!(*preset, parse(preset.name()));
+    }
+}
+
+#[test]
+fn test_chain_spec_from_preset() {
+    let chain =
+        OpChainSpec::from_preset(ChainPreset::OpGoerli, genesis_from_header(&Header::default()));
+    assert_eq!(420, chain.chain_id());
+    assert_eq!(OP_GOERLI_BEDROCK_BLOCK, chain.bedrock_block);
+    assert_eq!(OP_GOERLI_REGOLITH_TIME, chain.regolith_time);
+    assert!(!chain.is_bedrock_active_at_block(OP_GOERLI_BEDROCK_BLOCK - 1));
+
+    let chain =
+        OpChainSpec::from_preset(ChainPreset::BaseMainnet, genesis_from_header(&Header::default()));
+    assert_eq!(8453, chain.chain_id());
+    assert!(chain.is_regolith_active(0, 0));
+}
+
+#[test]
+fn test_op_hardforks() {
+    let chain =
+        OpChainSpec::from_preset(ChainPreset::OpGoerli, genesis_from_header(&Header::default()));
+    assert_eq!(Some(OP_GOERLI_CANYON_TIME), chain.canyon_time);
+    assert_eq!(Some(ChainPreset::OpGoerli), ChainPreset::from_chain_id(420));
+    assert_eq!(None, ChainPreset::from_chain_id(901));
+
+    // Canyon activates with Shanghai and changes the EIP-1559 denominator
+    let bedrock = OP_GOERLI_BEDROCK_BLOCK;
+    assert!(!chain.is_canyon_active(bedrock, OP_GOERLI_CANYON_TIME - 1));
+    assert!(chain.is_canyon_active(bedrock, OP_GOERLI_CANYON_TIME));
+    assert!(!chain.is_canyon_active(bedrock - 1, OP_GOERLI_CANYON_TIME));
+    assert_eq!(
+        ForkCondition::Timestamp(OP_GOERLI_CANYON_TIME),
+        chain.inner.fork(Hardfork::Shanghai)
+    );
+    assert_eq!(OP_MAINNET_EIP1559_DENOMINATOR, chain.eip1559_denominator_at(bedrock, 0));
+    assert_eq!(
+        OP_MAINNET_EIP1559_DENOMINATOR_CANYON,
+        chain.eip1559_denominator_at(bedrock, OP_GOERLI_CANYON_TIME)
+    );
+    let chain = chain.with_canyon_time(100);
+    assert!(chain.is_canyon_active(bedrock, 100));
+    assert_eq!(ForkCondition::Timestamp(100), chain.inner.fork(Hardfork::Shanghai));
+
+    // Blocks from canyon on carry an empty list of withdrawals, blocks before it none
+    let block = |timestamp, withdrawals: Option<Vec<_>>| {
+        let header = Header {
+            number: bedrock,
+            timestamp,
+            base_fee_per_gas: Some(1),
+            withdrawals_root: withdrawals.as_ref().map(|_| EMPTY_ROOT),
+            ..Default::default()
+        };
+        SealedBlock { header: keccak::seal(header), body: vec![], ommers: vec![], withdrawals }
+    };
+    assert!(chain.check_hardfork_fields(&block(99, None)).is_ok());
+    assert!(chain.check_hardfork_fields(&block(99, Some(vec![]))).is_err());
+    assert!(chain.check_hardfork_fields(&block(100, Some(vec![]))).is_ok());
+    assert!(chain.check_hardfork_fields(&block(100, None)).is_err());
+    let mut without_base_fee = block(99, None);
+    without_base_fee.header =
+        keccak::seal(Header { number: bedrock, timestamp: 99, ..Default::default() });
+    assert!(chain.check_hardfork_fields(&without_base_fee).is_err());
+}
+
+#[test]
+fn test_unsigned_tx_policy_defaults() {
+    assert_eq!(UnsignedTxPolicy::System, ChainPreset::OpMainnet.unsigned_tx_policy());
+    assert_eq!(UnsignedTxPolicy::System, ChainPreset::OpGoerli.unsigned_tx_policy());
+    assert_eq!(UnsignedTxPolicy::Fail, ChainPreset::BaseMainnet.unsigned_tx_policy());
+}
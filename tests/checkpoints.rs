@@ -0,0 +1,46 @@
+use std::fs;
+
+use reth_db::database::Database;
+
+use op_reth::cli::{
+    args::ImportArgs, blocks, checkpoints::CheckpointFile, db, db::head, deposit::DepositStore,
+    genesis, verify::Mismatch,
+};
+
+const BLOCKS_PATH: &str = "tests/fixtures/blocks.rlp";
+const GENESIS_PATH: &str = "tests/fixtures/genesis.json";
+
+#[tokio::test]
+async fn test_checkpoints() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut env, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut env, Some(BLOCKS_PATH), &args).await.unwrap();
+    let expected = blocks::read_blocks(BLOCKS_PATH).unwrap();
+
+    // Every second block and the tip
+    let file = CheckpointFile::collect(&env.tx().unwrap(), 1..=2, 2).unwrap();
+    assert_eq!(vec![2], file.checkpoints.iter().map(|pointer| pointer.number).collect::<Vec<_>>());
+    assert_eq!(expected[2].hash(), file.tip.hash);
+    let file = CheckpointFile::collect(&env.tx().unwrap(), 0..=1, 2).unwrap();
+    assert_eq!(vec![0], file.checkpoints.iter().map(|pointer| pointer.number).collect::<Vec<_>>());
+    assert_eq!(1, file.tip.number);
+    assert!(file.verify(&env.tx().unwrap()).unwrap().is_empty());
+
+    // The file round-trips, but altering it breaks its digest
+    let path = dir.path().join("checkpoints.json");
+    file.write(&path).unwrap();
+    assert_eq!(file, CheckpointFile::read(&path).unwrap());
+    let mut altered = file.clone();
+    altered.tip.hash = expected[2].hash();
+    altered.write(&path).unwrap();
+    assert!(CheckpointFile::read(&path).is_err());
+    fs::remove_file(&path).unwrap();
+
+    // A database missing the tip of the file
+    let file = CheckpointFile::collect(&env.tx().unwrap(), 0..=2, 1).unwrap();
+    let deposits = DepositStore::open(dir.path()).unwrap();
+    head::set_head(&env, &deposits, dir.path(), 1).unwrap();
+    assert_eq!(vec![(2, Mismatch::Missing)], file.verify(&env.tx().unwrap()).unwrap());
+}
@@ -0,0 +1,29 @@
+use std::path::Path;
+
+use op_reth::cli::checksum;
+
+const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+#[test]
+fn test_verify() {
+    let path = Path::new("data/hello");
+    checksum::verify(path, b"hello", HELLO_SHA256).unwrap();
+    checksum::verify(path, b"hello", &HELLO_SHA256.to_uppercase()).unwrap();
+    assert!(checksum::verify(path, b"hell", HELLO_SHA256).is_err());
+}
+
+#[test]
+fn test_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest_path = dir.path().join("SHA256SUMS");
+    std::fs::write(
+        &manifest_path,
+        format!("# exports\n{HELLO_SHA256}  hello\n{HELLO_SHA256} *data/world\n"),
+    )
+    .unwrap();
+
+    let manifest = checksum::read_manifest(&manifest_path).unwrap();
+    assert_eq!(Some(HELLO_SHA256), checksum::manifest_entry(&manifest, Path::new("/tmp/hello")));
+    assert_eq!(Some(HELLO_SHA256), checksum::manifest_entry(&manifest, Path::new("data/world")));
+    assert_eq!(None, checksum::manifest_entry(&manifest, Path::new("other")));
+}
@@ -0,0 +1,34 @@
+use clap_complete::Shell;
+
+use op_reth::cli;
+
+#[test]
+fn test_command_tree() {
+    // Panics on conflicting flags or names anywhere below the root command
+    cli::command().debug_assert();
+}
+
+#[test]
+fn test_completions() {
+    for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+        let mut script = Vec::new();
+        clap_complete::generate(shell, &mut cli::command(), "op-reth", &mut script);
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("index-history"), "{shell}");
+
+        // The values of `--chain` and `--format` are completed
+        assert!(script.contains("optimism-mainnet"), "{shell}");
+        assert!(script.contains("rlp-standard"), "{shell}");
+    }
+}
+
+#[test]
+fn test_import_without_subcommand() {
+    // `state --path` and `blocks --path` import the export like earlier versions did
+    let parse = |args: &[&str]| cli::command().try_get_matches_from(args);
+    assert!(parse(&["op-reth", "state", "--path", "state.json"]).is_ok());
+    assert!(parse(&["op-reth", "state", "import", "--path", "state.json"]).is_ok());
+    assert!(parse(&["op-reth", "state", "--path", "state.json", "hash-and-trie"]).is_err());
+    assert!(parse(&["op-reth", "blocks", "--path", "blocks.rlp"]).is_ok());
+    assert!(parse(&["op-reth", "blocks", "import", "--path", "blocks.rlp"]).is_ok());
+}
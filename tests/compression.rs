@@ -0,0 +1,75 @@
+use std::{fs, io::Write, path::Path};
+
+use flate2::write::GzEncoder;
+
+use op_reth::cli::{
+    blocks,
+    compression::{self, Compression},
+    import::{detect_format, InputFormat},
+    receipts::Receipt,
+    state,
+};
+
+const BLOCKS_PATH: &str = "tests/fixtures/blocks.rlp";
+const RECEIPTS_PATH: &str = "tests/fixtures/receipts.rlp";
+const STATE_PATH: &str = "tests/fixtures/state.json";
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test]
+fn test_detect_compression() {
+    assert_eq!(Some(Compression::Gzip), Compression::detect(&gzip(b"{}")));
+    assert_eq!(
+        Some(Compression::Zstd),
+        Compression::detect(&zstd::encode_all(&b"{}"[..], 0).unwrap())
+    );
+    assert_eq!(None, Compression::detect(&fs::read(BLOCKS_PATH).unwrap()));
+    assert_eq!(Some(Compression::Zstd), Compression::from_extension(Path::new("export_0_2.zst")));
+    assert_eq!(None, Compression::from_extension(Path::new("export_0_2")));
+}
+
+#[test]
+fn test_read_compressed_exports() {
+    let dir = tempfile::tempdir().unwrap();
+    let blocks_data = fs::read(BLOCKS_PATH).unwrap();
+    let blocks_path = dir.path().join("export_0_2.gz");
+    fs::write(&blocks_path, gzip(&blocks_data)).unwrap();
+    assert_eq!(
+        blocks::read_blocks(BLOCKS_PATH).unwrap(),
+        blocks::read_blocks(&blocks_path).unwrap()
+    );
+    assert_eq!(InputFormat::Blocks, detect_format(&blocks_path).unwrap());
+
+    // Members written by parallel compressors are concatenated
+    let (first, second) = blocks_data.split_at(blocks_data.len() / 2);
+    fs::write(&blocks_path, [gzip(first), gzip(second)].concat()).unwrap();
+    assert_eq!(blocks_data, compression::read(&blocks_path).unwrap());
+
+    let receipts_path = dir.path().join("export_receipt_0_2.zst");
+    let receipts_data = zstd::encode_all(&fs::read(RECEIPTS_PATH).unwrap()[..], 0).unwrap();
+    fs::write(&receipts_path, receipts_data).unwrap();
+    assert_eq!(
+        Receipt::from_file(RECEIPTS_PATH).unwrap().len(),
+        Receipt::from_file(&receipts_path).unwrap().len()
+    );
+    assert_eq!(InputFormat::Receipts, detect_format(&receipts_path).unwrap());
+
+    let state_path = dir.path().join("alloc.json.zst");
+    fs::write(&state_path, zstd::encode_all(&fs::read(STATE_PATH).unwrap()[..], 0).unwrap())
+        .unwrap();
+    let expected = state::from_file(STATE_PATH).unwrap();
+    let read = state::from_file(&state_path).unwrap();
+    assert_eq!(expected.keys().collect::<Vec<_>>(), read.keys().collect::<Vec<_>>());
+}
+
+#[test]
+fn test_refuse_uncompressed_with_extension() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("export_0_2.gz");
+    fs::copy(BLOCKS_PATH, &path).unwrap();
+    assert!(compression::read(&path).is_err());
+}
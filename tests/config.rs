@@ -0,0 +1,66 @@
+use std::{ffi::OsString, path::PathBuf};
+
+use clap::{Arg, ArgAction, Command};
+use op_reth::cli::config;
+
+fn cli() -> Command {
+    let import = Command::new("import")
+        .arg(Arg::new("path").long("path"))
+        .arg(Arg::new("batch-size").long("batch-size").short('b'))
+        .arg(Arg::new("datadir").long("datadir"))
+        .arg(Arg::new("lenient").long("lenient").action(ArgAction::SetTrue))
+        .arg(Arg::new("checksum").long("checksum").action(ArgAction::Append));
+    Command::new("op-reth")
+        .arg(Arg::new("config-file").long("config-file").global(true))
+        .subcommand(Command::new("blocks").subcommand(import))
+}
+
+fn args(args: &[&str]) -> Vec<OsString> {
+    args.iter().map(OsString::from).collect()
+}
+
+#[test]
+fn test_apply_config() {
+    let table = r#"
+        datadir = "/data"
+        chain-id = 10
+
+        [blocks.import]
+        path = "blocks.rlp"
+        batch-size = 500
+        lenient = true
+        checksum = ["aa", "bb"]
+    "#
+    .parse()
+    .unwrap();
+
+    // Flags given on the command line override the config, keys other commands accept are skipped
+    let command_line =
+        args(&["op-reth", "--config-file", "c.toml", "blocks", "import", "-b", "100"]);
+    let applied = config::apply(&cli(), command_line, &table).unwrap();
+    let matches = cli().try_get_matches_from(applied).unwrap();
+    let (_, blocks) = matches.subcommand().unwrap();
+    let (_, import) = blocks.subcommand().unwrap();
+    assert_eq!(import.get_one::<String>("batch-size").unwrap(), "100");
+    assert_eq!(import.get_one::<String>("datadir").unwrap(), "/data");
+    assert_eq!(import.get_one::<String>("path").unwrap(), "blocks.rlp");
+    assert!(import.get_flag("lenient"));
+    let checksums = import.get_many::<String>("checksum").unwrap().collect::<Vec<_>>();
+    assert_eq!(checksums, ["aa", "bb"]);
+
+    // Flags the invoked command doesn't accept are refused in its table
+    let typo = "[blocks.import]\nbach-size = 1".parse().unwrap();
+    assert!(config::apply(&cli(), args(&["op-reth", "blocks", "import"]), &typo).is_err());
+}
+
+#[test]
+fn test_config_path() {
+    assert_eq!(
+        config::config_path(&args(&["op-reth", "--config-file=migration.toml", "import"])),
+        Some(PathBuf::from("migration.toml"))
+    );
+    assert_eq!(
+        config::config_path(&args(&["op-reth", "import", "--config-file", "migration.toml"])),
+        Some(PathBuf::from("migration.toml"))
+    );
+}
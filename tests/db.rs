@@ -0,0 +1,361 @@
+use reth_db::{
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::{Account, Header, H160, U256};
+
+use op_reth::cli::{
+    args::ImportArgs,
+    blocks,
+    db::{
+        self, attest,
+        copy_table::{self, CopyTable},
+        diff::{DiffTable, DivergenceKind},
+        finalize::{self, MigrationTip},
+        head::{self, ForkchoicePointers},
+        prune::{self, PruneCheckpoints, PruneTargets, PrunedData},
+    },
+    deposit::DepositStore,
+    genesis,
+    l1_fee::{L1FeeInfo, L1FeeStore},
+    receipts, state,
+};
+
+const BLOCKS_PATH: &str = "tests/fixtures/blocks.rlp";
+const GENESIS_PATH: &str = "tests/fixtures/genesis.json";
+const RECEIPTS_PATH: &str = "tests/fixtures/receipts.rlp";
+
+#[test]
+fn test_namespaces() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("db");
+    assert!(db::list_namespaces(&root).unwrap().is_empty());
+    assert_eq!(root, db::namespaced_path(&root, None));
+
+    db::open_rw_namespaced_env(&root, Some(420)).unwrap();
+    db::open_rw_namespaced_env(&root, Some(10)).unwrap();
+    assert_eq!(dir.path().join("db.chains").join("420"), db::namespaced_path(&root, Some(420)));
+    assert_eq!(vec![10, 420], db::list_namespaces(&root).unwrap());
+
+    // The namespaces live next to the root, so they are not part of its copies
+    std::fs::create_dir_all(&root).unwrap();
+    db::open_rw_env(&root).unwrap();
+    let copy = db::TempDatabase::copy_of(&root).unwrap();
+    assert!(std::fs::read_dir(copy.path()).unwrap().all(|entry| entry.unwrap().path().is_file()));
+}
+
+#[test]
+fn test_stats() {
+    let dir = tempfile::tempdir().unwrap();
+    let env = db::open_rw_env(dir.path()).unwrap();
+
+    let stats = db::stats::collect(&env).unwrap();
+    assert!(stats.tables.iter().any(|table| table.name == "Headers"));
+    assert!(stats.tables.iter().all(|table| table.entries == 0));
+    assert_eq!(None, stats.tip);
+    assert_eq!(None, stats.last_tx);
+}
+
+#[test]
+fn test_diff() {
+    let (dir_a, dir_b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    let a = db::open_rw_env(dir_a.path()).unwrap();
+    let b = db::open_rw_env(dir_b.path()).unwrap();
+
+    a.update(|tx| {
+        tx.put::<tables::Headers>(0, Header::default()).unwrap();
+        tx.put::<tables::Headers>(1, Header { number: 1, ..Default::default() }).unwrap();
+        tx.put::<tables::PlainAccountState>(
+            H160::zero(),
+            Account { nonce: 1, ..Default::default() },
+        )
+        .unwrap();
+    })
+    .unwrap();
+    b.update(|tx| {
+        tx.put::<tables::Headers>(0, Header::default()).unwrap();
+        tx.put::<tables::PlainAccountState>(
+            H160::zero(),
+            Account { nonce: 2, ..Default::default() },
+        )
+        .unwrap();
+    })
+    .unwrap();
+
+    let diffs = db::diff::diff(&a, &b, &DiffTable::ALL, 10).unwrap();
+    let headers = &diffs[0];
+    assert_eq!(
+        (1, 1, 0, 0),
+        (headers.compared, headers.only_in_a, headers.only_in_b, headers.different)
+    );
+    assert_eq!(DivergenceKind::OnlyInA, headers.examples[0].kind);
+    let accounts = &diffs[1];
+    assert_eq!(1, accounts.different);
+    assert!(diffs[2].is_empty() && diffs[3].is_empty());
+}
+
+#[test]
+fn test_resolve_static_root() {
+    let (db_dir, static_dir) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    let db_path = db_dir.path();
+    assert_eq!(db_path, db::resolve_static_root(db_path, None).unwrap());
+
+    // A given root is recorded for later runs
+    let static_root = static_dir.path();
+    assert_eq!(static_root, db::resolve_static_root(db_path, Some(static_root)).unwrap());
+    assert_eq!(static_root, db::resolve_static_root(db_path, None).unwrap());
+    assert!(db::resolve_static_root(db_path, Some(db_path)).is_err());
+    // The same root written differently is accepted
+    let same_root = static_root.join(".");
+    assert_eq!(static_root, db::resolve_static_root(db_path, Some(&same_root)).unwrap());
+}
+
+#[test]
+fn test_lock_db() {
+    let dir = tempfile::tempdir().unwrap();
+    let lock = db::lock_db(dir.path()).unwrap();
+    assert!(dir.path().join(db::LOCK_FILE).exists());
+
+    // The lock is exclusive until it is released
+    assert!(db::lock_db(dir.path()).is_err());
+    drop(lock);
+    let holder = std::fs::read_to_string(dir.path().join(db::LOCK_FILE)).unwrap();
+    assert_eq!(std::process::id().to_string(), holder.trim());
+    db::lock_db(dir.path()).unwrap();
+}
+
+#[tokio::test]
+async fn test_head_pointers() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut env, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut env, Some(BLOCKS_PATH), &args).await.unwrap();
+    let expected = blocks::read_blocks(BLOCKS_PATH).unwrap();
+
+    assert_eq!(Some(2), head::head(&env).unwrap().map(|head| head.number));
+    assert_eq!(ForkchoicePointers::default(), ForkchoicePointers::read(dir.path()).unwrap());
+
+    let pointers = ForkchoicePointers {
+        safe: Some(head::canonical_block(&env, 2).unwrap()),
+        finalized: Some(head::canonical_block(&env, 1).unwrap()),
+    };
+    assert_eq!(expected[1].hash(), pointers.finalized.unwrap().hash);
+    pointers.check(&env).unwrap();
+    pointers.write(dir.path()).unwrap();
+    assert_eq!(pointers, ForkchoicePointers::read(dir.path()).unwrap());
+
+    // The finalized block may not lie above the safe block
+    let swapped = ForkchoicePointers { safe: pointers.finalized, finalized: pointers.safe };
+    assert!(swapped.check(&env).is_err());
+    assert!(head::canonical_block(&env, 3).is_err());
+
+    // The head can not move below the finalized block, and pulls the safe block down with it
+    let deposits = DepositStore::open(dir.path()).unwrap();
+    assert!(head::set_head(&env, &deposits, dir.path(), 0).is_err());
+    assert_eq!(1, head::set_head(&env, &deposits, dir.path(), 1).unwrap());
+    assert_eq!(Some(expected[1].hash()), head::head(&env).unwrap().map(|head| head.hash));
+    let pointers = ForkchoicePointers::read(dir.path()).unwrap();
+    assert_eq!(Some(1), pointers.safe.map(|safe| safe.number));
+    pointers.check(&env).unwrap();
+}
+
+#[tokio::test]
+async fn test_finalize() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut env, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut env, Some(BLOCKS_PATH), &args).await.unwrap();
+    let expected = blocks::read_blocks(BLOCKS_PATH).unwrap();
+
+    // The state of the tip has to be complete
+    assert!(finalize::finalize(&env, dir.path()).is_err());
+    state::hash_and_trie(&env).unwrap();
+
+    let tx = env.tx_mut().unwrap();
+    tx.delete::<tables::HeaderTD>(2, None).unwrap();
+    tx.commit().unwrap();
+    let summary = finalize::finalize(&env, dir.path()).unwrap();
+    assert!(summary.corrected >= 1);
+    let total_difficulty = expected.iter().map(|block| block.difficulty).sum::<U256>();
+    assert_eq!(
+        MigrationTip { block: head::head(&env).unwrap().unwrap(), total_difficulty },
+        summary.tip
+    );
+    assert_eq!(Some(summary.tip), MigrationTip::read(dir.path()).unwrap());
+
+    let tx = env.tx().unwrap();
+    assert_eq!(Some(total_difficulty), tx.get::<tables::HeaderTD>(2).unwrap().map(|td| td.0));
+    for stage in finalize::SYNC_STAGES {
+        assert_eq!(Some(2), stage.get_progress(&tx).unwrap());
+    }
+    drop(tx);
+
+    // The legacy chain is final, and finalizing again changes nothing
+    let pointers = ForkchoicePointers::read(dir.path()).unwrap();
+    assert_eq!(Some(summary.tip.block), pointers.safe);
+    assert_eq!(Some(summary.tip.block), pointers.finalized);
+    assert_eq!(0, finalize::finalize(&env, dir.path()).unwrap().corrected);
+}
+
+#[tokio::test]
+async fn test_prune() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(dir.path()).unwrap();
+    let fees = L1FeeStore::open(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut env, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut env, Some(BLOCKS_PATH), &args).await.unwrap();
+    receipts::apply(&mut env, &fees, Some(RECEIPTS_PATH), &args).await.unwrap();
+    fees.insert([(0, L1FeeInfo::default()), (1, L1FeeInfo::default())]).unwrap();
+    let entries = |env: &Env<WriteMap>, name| {
+        let stats = db::stats::collect(env).unwrap();
+        stats.tables.iter().find(|table| table.name == name).unwrap().entries
+    };
+    let count = |env| {
+        (entries(env, "Receipts"), entries(env, "Transactions"), entries(env, "TxHashNumber"))
+    };
+    assert_eq!((2, 2, 2), count(&env));
+
+    // A dry run only counts the entries of the transaction of block 1
+    let targets = PruneTargets { receipts: Some(2), ..Default::default() };
+    let report = prune::prune(&env, Some(&fees), &targets, 1, true).unwrap();
+    assert_eq!(Some(&1), report.removed.get("Receipts"));
+    assert_eq!((2, 2, 2), count(&env));
+
+    let report = prune::prune(&env, Some(&fees), &targets, 1, false).unwrap();
+    assert_eq!(Some(&1), report.removed.get("Receipts"));
+    assert_eq!(1, report.l1_fees);
+    assert!(fees.get(0).unwrap().is_none());
+    assert!(fees.get(1).unwrap().is_some());
+    assert_eq!((1, 2, 2), count(&env));
+
+    // Pruning the bodies drops the lookup entries of their transactions, but keeps the headers
+    let targets = PruneTargets { bodies: Some(3), tx_lookup: Some(2), ..Default::default() };
+    let report = prune::prune(&env, None, &targets, 1, false).unwrap();
+    assert_eq!(Some(&2), report.removed.get("Transactions"));
+    assert_eq!(Some(&2), report.removed.get("TxHashNumber"));
+    assert_eq!((1, 0, 0), count(&env));
+    assert_eq!(3, entries(&env, "Headers"));
+    assert_eq!(3, entries(&env, "BlockBodies"));
+
+    let mut checkpoints = PruneCheckpoints::read(dir.path()).unwrap();
+    assert_eq!(PruneCheckpoints::default(), checkpoints);
+    checkpoints.merge(&PruneTargets { receipts: Some(2), ..Default::default() });
+    checkpoints.merge(&PruneTargets { receipts: Some(1), bodies: Some(3), ..Default::default() });
+    checkpoints.write(dir.path()).unwrap();
+    let expected = PruneCheckpoints { receipts: Some(2), bodies: Some(3), tx_lookup: None };
+    assert_eq!(expected, PruneCheckpoints::read(dir.path()).unwrap());
+
+    // The lookup entries go with the bodies of their transactions
+    assert_eq!(Some(3), expected.pruned_below(PrunedData::TxLookup));
+    assert!(expected.ensure_available(PrunedData::Receipts, &(2..=3)).is_ok());
+    assert!(expected.ensure_available(PrunedData::Receipts, &(1..=3)).is_err());
+    assert!(expected.ensure_available(PrunedData::TxLookup, &(2..=3)).is_err());
+}
+
+#[tokio::test]
+async fn test_copy_table() {
+    let (dir_a, dir_b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    let mut a = db::open_rw_env(dir_a.path()).unwrap();
+    let b = db::open_rw_env(dir_b.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut a, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut a, Some(BLOCKS_PATH), &args).await.unwrap();
+    b.update(|tx| tx.put::<tables::Headers>(7, Header::default()).unwrap()).unwrap();
+
+    // A whole table replaces the entries of the destination
+    let copy = copy_table::copy_table(&a, &b, CopyTable::Headers, None).unwrap();
+    assert_eq!((1, 3), (copy.removed, copy.copied));
+    assert!(db::diff::diff(&a, &b, &[DiffTable::Headers], 10).unwrap()[0].is_empty());
+
+    // A range of transactions follows the bodies of the source
+    let copy = copy_table::copy_table(&a, &b, CopyTable::Transactions, Some(2..=2)).unwrap();
+    assert_eq!((0, 1), (copy.removed, copy.copied));
+    let tx = b.tx().unwrap();
+    assert!(tx.get::<tables::Transactions>(0).unwrap().is_none());
+    assert_eq!(
+        a.tx().unwrap().get::<tables::Transactions>(1).unwrap(),
+        tx.get::<tables::Transactions>(1).unwrap()
+    );
+    drop(tx);
+    let copy = copy_table::copy_table(&a, &b, CopyTable::Transactions, Some(0..=2)).unwrap();
+    assert_eq!((1, 2), (copy.removed, copy.copied));
+
+    assert!(copy_table::copy_table(&a, &b, CopyTable::PlainAccountState, Some(0..=1)).is_err());
+    assert!(copy_table::copy_table(&a, &b, CopyTable::Receipts, Some(0..=5)).is_err());
+}
+
+#[test]
+fn test_open_ro_env() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(db::open_ro_env(&dir.path().join("missing")).is_err());
+    let env = db::open_rw_env(dir.path()).unwrap();
+    env.update(|tx| tx.put::<tables::Headers>(0, Header::default()).unwrap()).unwrap();
+    drop(env);
+
+    // A read-only environment reads the database, but can't write it
+    let env = db::open_ro_env(dir.path()).unwrap();
+    assert_eq!(Some(Header::default()), env.tx().unwrap().get::<tables::Headers>(0).unwrap());
+    assert!(env.tx_mut().is_err());
+}
+
+#[tokio::test]
+async fn test_temp_database() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut scratch = db::TempDatabase::new_in(dir.path()).unwrap();
+    let path = scratch.path().to_path_buf();
+    genesis::apply(&mut scratch, Some(GENESIS_PATH), &ImportArgs::default()).await.unwrap();
+    assert!(scratch.view(|tx| tx.get::<tables::Headers>(0)).unwrap().unwrap().is_some());
+    drop(scratch);
+    assert!(!path.exists());
+
+    // A copy holds the database, and writing it leaves the database untouched
+    let source = dir.path().join("db");
+    let env = db::open_rw_env(&source).unwrap();
+    env.update(|tx| tx.put::<tables::Headers>(0, Header::default()).unwrap()).unwrap();
+    drop(env);
+    let copy = db::TempDatabase::copy_of(&source).unwrap();
+    assert!(!copy.path().join(db::LOCK_FILE).exists());
+    assert!(copy.view(|tx| tx.get::<tables::Headers>(0)).unwrap().unwrap().is_some());
+    copy.update(|tx| tx.delete::<tables::Headers>(0, None).unwrap()).unwrap();
+    drop(copy);
+    let env = db::open_ro_env(&source).unwrap();
+    assert!(env.view(|tx| tx.get::<tables::Headers>(0)).unwrap().unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_attest() {
+    let args = ImportArgs::default();
+    let mut attestations = Vec::new();
+    let mut dbs = Vec::new();
+    for _ in 0..2 {
+        let mut db = db::TempDatabase::new().unwrap();
+        let fees = L1FeeStore::open(db.path()).unwrap();
+        genesis::apply(&mut db, Some(GENESIS_PATH), &args).await.unwrap();
+        blocks::apply(&mut db, Some(BLOCKS_PATH), &args).await.unwrap();
+        receipts::apply(&mut db, &fees, Some(RECEIPTS_PATH), &args).await.unwrap();
+        attestations.push(attest::attest(&db, &PruneCheckpoints::default()).unwrap());
+        dbs.push(db);
+    }
+
+    // The same inputs produce the same attestation
+    assert_eq!(attestations[0], attestations[1]);
+    assert_eq!(2, attestations[0].tip);
+
+    // A missing receipt changes the chain digest
+    dbs[1].update(|tx| tx.delete::<tables::Receipts>(1, None).unwrap()).unwrap();
+    let changed = attest::attest(&dbs[1], &PruneCheckpoints::default()).unwrap();
+    assert_ne!(attestations[0].chain_digest, changed.chain_digest);
+    assert_eq!(attestations[0].state_root, changed.state_root);
+    assert_ne!(attestations[0].attestation, changed.attestation);
+
+    // Pruned receipts are refused, other pruned data doesn't enter the digest
+    let pruned = PruneCheckpoints { receipts: Some(2), ..Default::default() };
+    assert!(attest::attest(&dbs[0], &pruned).is_err());
+    let pruned = PruneCheckpoints { bodies: Some(2), tx_lookup: Some(2), ..Default::default() };
+    assert_eq!(attestations[0], attest::attest(&dbs[0], &pruned).unwrap());
+}
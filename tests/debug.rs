@@ -0,0 +1,48 @@
+use reth_primitives::{Address, H256, MAINNET};
+
+use op_reth::cli::{
+    args::ImportArgs,
+    blocks, db,
+    debug::{self, ReceiptSummary, TxTrace},
+    genesis,
+};
+
+const BLOCKS_PATH: &str = "tests/fixtures/blocks.rlp";
+const GENESIS_PATH: &str = "tests/fixtures/genesis.json";
+
+#[test]
+fn test_divergences() {
+    let summary = ReceiptSummary { success: true, gas_used: 21_000, logs: 0 };
+    let mut trace = TxTrace {
+        index: 0,
+        hash: H256::zero(),
+        sender: Address::zero(),
+        to: None,
+        produced: summary,
+        imported: Some(summary),
+        state: vec![],
+    };
+    assert!(trace.divergences().is_empty());
+
+    trace.imported = Some(ReceiptSummary { success: false, gas_used: 25_000, ..summary });
+    let divergences = trace.divergences();
+    assert_eq!(2, divergences.len());
+    assert_eq!("gas 21000 (imported 25000)", divergences[1]);
+
+    trace.imported = None;
+    assert_eq!(vec!["no imported receipt".to_string()], trace.divergences());
+}
+
+#[tokio::test]
+async fn test_replay_block_needs_parent_state() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut env, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut env, Some(BLOCKS_PATH), &args).await.unwrap();
+
+    // Only the genesis state is present, which is not the parent state of block 2
+    assert!(debug::replay_block(&env, &MAINNET, 2).is_err());
+    assert!(debug::replay_block(&env, &MAINNET, 0).is_err());
+    assert!(debug::replay_block(&env, &MAINNET, 3).is_err());
+}
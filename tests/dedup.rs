@@ -0,0 +1,46 @@
+use reth_primitives::{keccak256, H256};
+
+use op_reth::cli::{
+    blocks,
+    dedup::{BloomFilter, Deduplicator, DEFAULT_FALSE_POSITIVE_RATE},
+    keccak,
+};
+
+const BLOCKS_PATH: &str = "tests/fixtures/blocks.rlp";
+
+#[test]
+fn test_bloom_filter() {
+    let hash = |index: u64| keccak256(index.to_be_bytes());
+    let mut filter = BloomFilter::new(10_000, DEFAULT_FALSE_POSITIVE_RATE);
+    for index in 0..10_000 {
+        filter.insert(&hash(index));
+    }
+
+    // There are no false negatives and barely any false positives
+    assert!((0..10_000).all(|index| filter.contains(&hash(index))));
+    let false_positives = (10_000..110_000).filter(|index| filter.contains(&hash(*index))).count();
+    assert!(false_positives <= 2, "{false_positives} false positives");
+    assert!(!BloomFilter::new(0, 0.01).contains(&H256::zero()));
+}
+
+#[test]
+fn test_deduplicator() {
+    let read = blocks::read_blocks(BLOCKS_PATH).unwrap();
+    let mut dedup = Deduplicator::new(read.len(), read.len());
+    for block in &read {
+        assert!(!dedup.is_duplicate(&read, block));
+        dedup.insert(block);
+    }
+    assert!(read.iter().all(|block| dedup.is_duplicate(&read, block)));
+    assert_eq!(read.len() as u64, dedup.skipped_blocks);
+
+    // A new block including a transaction of an earlier block loses it
+    let mut block = read[2].clone();
+    let mut header = block.header.clone().unseal();
+    header.number = 3;
+    block.header = keccak::seal(header);
+    assert!(!dedup.is_duplicate(&read, &block));
+    assert_eq!(1, dedup.remove_duplicate_transactions(&read, &mut block));
+    assert!(block.body.is_empty());
+    assert_eq!(1, dedup.skipped_transactions);
+}
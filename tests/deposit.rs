@@ -0,0 +1,193 @@
+use std::sync::Arc;
+
+use reth_db::{database::Database, tables, transaction::DbTx};
+use reth_primitives::{Address, Bytes, Header, TransactionKind, H256, U256, U64};
+use reth_rpc_types::engine::{ForkchoiceState, PayloadStatusEnum};
+
+use op_reth::cli::{
+    args::ImportArgs,
+    blocks::{self, ErigonBlock, ErigonHeader, ErigonTx},
+    db,
+    deposit::{self, DepositStore, TxDeposit, DEPOSIT_TX_TYPE},
+    devnet::{self, driver::MockDriver},
+    genesis, keccak,
+    l1_fee::L1FeeStore,
+    node::{
+        engine::{self, EngineApi, EngineApiServer},
+        payload::{OpPayloadAttributes, PayloadBuilder},
+    },
+    rpc::{EthApi, EthApiServer},
+};
+
+const BLOCKS_PATH: &str = "tests/fixtures/blocks.rlp";
+const GENESIS_PATH: &str = "tests/fixtures/genesis.json";
+
+fn deposit() -> TxDeposit {
+    TxDeposit {
+        source_hash: H256::repeat_byte(0x11),
+        from: Address::repeat_byte(0x22),
+        to: TransactionKind::Call(Address::repeat_byte(0x33)),
+        mint: Some(1_000_000),
+        value: 500,
+        gas_limit: 100_000,
+        is_system_transaction: false,
+        input: Bytes::from(vec![0xde, 0xad]),
+    }
+}
+
+#[test]
+fn test_deposit_encoding() {
+    let deposit = deposit();
+    let envelope = deposit.envelope();
+    assert_eq!(envelope[0], DEPOSIT_TX_TYPE);
+    assert_eq!(TxDeposit::decode_enveloped(&envelope).unwrap(), deposit);
+    assert_eq!(deposit.hash(), keccak::keccak256(&envelope));
+
+    // An absent mint is encoded as zero
+    let system = TxDeposit { mint: None, is_system_transaction: true, ..deposit.clone() };
+    assert_eq!(TxDeposit::decode_enveloped(&system.envelope()).unwrap(), system);
+    assert!(TxDeposit::decode_enveloped(&envelope[1..]).is_err());
+    let mut trailing = envelope.clone();
+    trailing.push(0);
+    assert!(TxDeposit::decode_enveloped(&trailing).is_err());
+
+    // Deposits are carried by a transaction keeping all their fields and their hash
+    let carrier = deposit::decode_tx(&envelope).unwrap();
+    assert_eq!(carrier.hash(), deposit.hash());
+    assert!(deposit::is_deposit(&carrier));
+    assert!(!blocks::is_unsigned(&carrier));
+    assert_eq!(TxDeposit::from_carrier(&carrier), Some(deposit.clone()));
+    assert_eq!(deposit::encode_tx(&carrier), envelope);
+    assert_eq!(TxDeposit::from_carrier(&system.clone().into_carrier()), Some(system));
+}
+
+#[test]
+fn test_deposit_store() {
+    let dir = tempfile::tempdir().unwrap();
+    let deposits = DepositStore::open(dir.path()).unwrap();
+    let deposit = deposit();
+
+    // The stand-in stored in the transactions table carries none of the deposit fields
+    let stand_in = deposit.stand_in();
+    assert!(deposit::is_stand_in(&stand_in));
+    assert!(!deposit::is_deposit(&stand_in));
+    assert!(!deposit::is_stand_in(&deposit.clone().into_carrier()));
+
+    // Stand-ins are restored from the store only
+    assert!(deposits.restore(7, stand_in.clone()).is_err());
+    assert_eq!(1, deposits.insert([(7, deposit.clone())]).unwrap());
+    let restored = deposits.restore(7, stand_in.clone()).unwrap();
+    assert_eq!(restored.hash(), deposit.hash());
+    assert_eq!(TxDeposit::from_carrier(&restored), Some(deposit.clone()));
+
+    // A stored deposit not matching the stand-in is refused
+    deposits.insert([(8, TxDeposit { value: 1, ..deposit })]).unwrap();
+    assert!(deposits.restore(8, stand_in).is_err());
+}
+
+#[tokio::test]
+async fn test_deposit_payload() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(dir.path()).unwrap();
+    let chain = devnet::init(&mut env, dir.path(), 901, U256::from(1000)).await.unwrap();
+    let env = Arc::new(env);
+    let deposits = Arc::new(DepositStore::open(dir.path()).unwrap());
+    let builder = Arc::new(PayloadBuilder::new(env.clone(), chain.clone()));
+    let engine = EngineApi::new(env.clone(), chain)
+        .with_deposits(deposits.clone())
+        .with_payload_builder(builder);
+    let head = MockDriver::from_database(engine.clone(), &env).unwrap().head().clone();
+    let state = |head_block_hash| ForkchoiceState {
+        head_block_hash,
+        safe_block_hash: head.hash(),
+        finalized_block_hash: head.hash(),
+    };
+
+    // A block holding a deposit, hashed over the deposit envelope
+    let deposit = deposit();
+    let attributes = OpPayloadAttributes {
+        timestamp: U64::from(head.timestamp + 1),
+        prev_randao: H256::zero(),
+        suggested_fee_recipient: Address::zero(),
+        withdrawals: Some(vec![]),
+        transactions: vec![deposit.envelope().into()],
+        no_tx_pool: true,
+        gas_limit: None,
+    };
+    let updated = engine.fork_choice_updated_v1(state(head.hash()), Some(attributes)).await;
+    let mut payload = engine.get_payload_v1(updated.unwrap().payload_id.unwrap()).await.unwrap();
+    assert_eq!(vec![Bytes::from(deposit.envelope())], payload.transactions);
+    let block = engine::decode_payload(payload.clone()).unwrap();
+    assert_eq!(block.transactions_root, deposit::transactions_root(&block.body));
+
+    let status = engine.new_payload_v1(payload.clone()).await.unwrap();
+    assert!(matches!(status.status, PayloadStatusEnum::Valid), "{:?}", status.status);
+    let updated = engine.fork_choice_updated_v1(state(block.hash()), None).await.unwrap();
+    assert!(matches!(updated.payload_status.status, PayloadStatusEnum::Valid));
+    let tx = env.tx().unwrap();
+    let tx_id = tx.get::<tables::TxHashNumber>(deposit.hash()).unwrap().unwrap();
+    assert_eq!(tx.get::<tables::TxSenders>(tx_id).unwrap(), Some(deposit.from));
+    let stored = tx.get::<tables::Transactions>(tx_id).unwrap().unwrap();
+    assert_eq!(stored.transaction, deposit.stand_in().transaction);
+    let restored = deposits.read(&tx, tx_id).unwrap().unwrap();
+    assert_eq!(TxDeposit::from_carrier(&restored), Some(deposit.clone()));
+    drop(tx);
+
+    // A payload whose hash doesn't match its deposits is refused
+    payload.block_hash = H256::zero();
+    let status = engine.new_payload_v1(payload).await.unwrap();
+    assert!(matches!(status.status, PayloadStatusEnum::InvalidBlockHash { .. }));
+
+    // Unwinding the block drops the lookup of the deposit hash, which needs the deposit store
+    let tx = env.tx_mut().unwrap();
+    assert!(blocks::unwind_blocks(&tx, None, block.number..=block.number).is_err());
+    let removed = blocks::unwind_blocks(&tx, Some(&deposits), block.number..=block.number);
+    assert_eq!(1, removed.unwrap());
+    assert!(tx.get::<tables::TxHashNumber>(deposit.hash()).unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_import_deposit_block() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs { static_path: Some(dir.path().to_path_buf()), ..Default::default() };
+    genesis::apply(&mut env, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut env, Some(BLOCKS_PATH), &args).await.unwrap();
+
+    // An export of a block following the fixture, holding a deposit and a signed transaction
+    let last = blocks::read_blocks(BLOCKS_PATH).unwrap().pop().unwrap();
+    let signed = last.body[0].clone();
+    let header = Header {
+        parent_hash: last.hash(),
+        number: last.number + 1,
+        timestamp: last.timestamp + 1,
+        ..Default::default()
+    };
+    let block = ErigonBlock {
+        header: ErigonHeader::from(&header),
+        txs: vec![ErigonTx::from(&deposit().into_carrier()), ErigonTx::from(&signed)],
+        uncles: vec![],
+    };
+    let path = dir.path().join("deposit.rlp");
+    std::fs::write(&path, rlp::encode_list::<ErigonBlock, _>(&[block])).unwrap();
+    blocks::apply(&mut env, Some(path.to_str().unwrap()), &args).await.unwrap();
+
+    // The deposit is recorded with its own sender, next to the recovered one of the signed one
+    let deposit = deposit();
+    let tx = env.tx().unwrap();
+    let tx_id = tx.get::<tables::TxHashNumber>(deposit.hash()).unwrap().unwrap();
+    assert_eq!(Some(deposit.from), tx.get::<tables::TxSenders>(tx_id).unwrap());
+    assert_eq!(signed.recover_signer(), tx.get::<tables::TxSenders>(tx_id + 1).unwrap());
+    let stored = tx.get::<tables::Transactions>(tx_id).unwrap().unwrap();
+    assert_eq!(deposit.stand_in().transaction, stored.transaction);
+    let deposits = DepositStore::open(dir.path()).unwrap();
+    let restored = deposits.read(&tx, tx_id).unwrap().unwrap();
+    assert_eq!(Some(deposit.clone()), TxDeposit::from_carrier(&restored));
+    drop(tx);
+
+    // JSON-RPC reports the recorded sender, as there is no signature to recover it from
+    let fees = Arc::new(L1FeeStore::open(dir.path()).unwrap());
+    let api = EthApi::new(Arc::new(env), fees, Arc::new(deposits));
+    let transaction = api.transaction_by_hash(deposit.hash()).unwrap().unwrap();
+    assert_eq!(Some(deposit.from), transaction.from);
+}
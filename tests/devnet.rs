@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use reth_db::{database::Database, tables, transaction::DbTx};
+use reth_primitives::U256;
+
+use op_reth::cli::{
+    analytics, db,
+    devnet::{self, driver::MockDriver},
+    genesis::{Genesis, GenesisFormat},
+    node::engine::EngineApi,
+};
+
+#[tokio::test]
+async fn test_devnet() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path();
+    let mut env = db::open_rw_env(db_path).unwrap();
+    let balance = U256::from(1000);
+    let chain = devnet::init(&mut env, db_path, 901, balance).await.unwrap();
+    assert_eq!(chain.chain_id(), 901);
+    assert_eq!((chain.bedrock_block, chain.regolith_time, chain.canyon_time), (0, 0, Some(0)));
+
+    // The genesis is kept next to the database and funds the dev accounts
+    let data = std::fs::read(db_path.join(devnet::GENESIS_FILE)).unwrap();
+    let (genesis, format) = Genesis::decode(&data).unwrap();
+    assert_eq!(format, GenesisFormat::Geth);
+    assert_eq!(genesis.alloc.len(), devnet::DEV_ACCOUNTS.len());
+    let tx = env.tx().unwrap();
+    for address in devnet::dev_accounts() {
+        let account = tx.get::<tables::PlainAccountState>(address).unwrap().unwrap();
+        assert_eq!(account.balance, balance);
+    }
+    drop(tx);
+
+    // The devnet is reused, but not under another chain id
+    devnet::init(&mut env, db_path, 901, balance).await.unwrap();
+    let err = devnet::init(&mut env, db_path, 902, balance).await.unwrap_err();
+    assert!(err.to_string().contains("has chain id 901"), "{err}");
+
+    // The mock driver extends the canonical chain through the Engine API handler
+    let env = Arc::new(env);
+    let engine = EngineApi::new(env.clone(), chain);
+    let mut driver = MockDriver::from_database(engine, &env).unwrap();
+    let genesis_header = driver.head().clone();
+    assert_eq!(genesis_header.number, 0);
+    let mut parent = genesis_header.clone();
+    for _ in 0..3 {
+        // A timestamp before the parent is moved past it
+        let header = driver.produce_block(0).await.unwrap();
+        assert_eq!(header.number, parent.number + 1);
+        assert_eq!(header.parent_hash, parent.hash());
+        assert_eq!(header.timestamp, parent.timestamp + 1);
+        assert_eq!(header.state_root, genesis_header.state_root);
+        assert!(header.base_fee_per_gas < parent.base_fee_per_gas);
+        parent = header;
+    }
+    assert_eq!(analytics::canonical_tip(&env).unwrap(), Some(3));
+    let tx = env.tx().unwrap();
+    assert_eq!(tx.get::<tables::CanonicalHeaders>(3).unwrap(), Some(parent.hash()));
+
+    // A database that doesn't hold a devnet is refused
+    let other = tempfile::tempdir().unwrap();
+    let mut other_env = db::open_rw_env(other.path()).unwrap();
+    devnet::init(&mut other_env, other.path(), 901, balance).await.unwrap();
+    std::fs::remove_file(other.path().join(devnet::GENESIS_FILE)).unwrap();
+    let err = devnet::init(&mut other_env, other.path(), 901, balance).await.unwrap_err();
+    assert!(err.to_string().contains("not a devnet"), "{err}");
+}
@@ -0,0 +1,58 @@
+use std::path::Path;
+
+use op_reth::cli::{
+    args::ImportArgs,
+    chain::ChainPreset,
+    dirs::{self, DEFAULT_CHAIN_DIR},
+    preflight::ImportStage,
+};
+
+#[test]
+fn test_chain_dirs() {
+    let data_dir = Path::new("/data");
+    assert_eq!(
+        Path::new("/data/optimism-goerli"),
+        dirs::chain_dir(data_dir, Some(ChainPreset::OpGoerli))
+    );
+    assert_eq!(data_dir.join(DEFAULT_CHAIN_DIR), dirs::chain_dir(data_dir, None));
+}
+
+#[test]
+fn test_default_inputs() {
+    let chain_dir = Path::new("/data/optimism-goerli");
+    let mut args = ImportArgs { chain_dir: Some(chain_dir.to_path_buf()), ..Default::default() };
+
+    // Without a preset the file names of the OP Goerli exports are used
+    assert_eq!(
+        "/data/optimism-goerli/genesis.json",
+        args.input_path(None, ImportStage::Genesis).unwrap()
+    );
+    assert_eq!("blocks.rlp", args.input_path(Some("blocks.rlp"), ImportStage::Blocks).unwrap());
+
+    args.chain = Some(ChainPreset::OpGoerli);
+    assert_eq!(
+        "/data/optimism-goerli/export_0_4061224",
+        args.input_path(None, ImportStage::Blocks).unwrap()
+    );
+
+    // Chains without published exports need explicit paths
+    args.chain = Some(ChainPreset::BaseMainnet);
+    assert!(args.input_path(None, ImportStage::State).is_err());
+    assert_eq!("state.json", args.input_path(Some("state.json"), ImportStage::State).unwrap());
+}
+
+#[test]
+fn test_same_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path();
+    assert!(dirs::same_path(path, &path.join(".")));
+    assert!(dirs::same_path(Path::new("/data/db/"), Path::new("/data/./db")));
+    assert!(!dirs::same_path(Path::new("/data/db"), Path::new("/data/static")));
+    assert_eq!(
+        dirs::CASE_INSENSITIVE_PATHS,
+        dirs::same_path(Path::new("/data/DB"), Path::new("/data/db"))
+    );
+
+    assert!(dirs::has_extension(Path::new("mainnet-00000.ERA1"), "era1"));
+    assert!(!dirs::has_extension(Path::new("mainnet-00000.era"), "era1"));
+}
@@ -0,0 +1,93 @@
+use std::{fs, path::Path};
+
+use op_reth::cli::{
+    args::{EncryptionArgs, ImportArgs},
+    blocks,
+    encryption::{self, SnapshotKey},
+    progress::ImportProgress,
+    source::{file::FileSource, BlockSource, SourceContext},
+};
+
+const BLOCKS_PATH: &str = "tests/fixtures/blocks.rlp";
+
+const KEY_HEX: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+#[test]
+fn test_key_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let expected = hex::decode(KEY_HEX).unwrap();
+    let hex_path = dir.path().join("hex.key");
+    fs::write(&hex_path, format!("0x{KEY_HEX}\n")).unwrap();
+    let raw_path = dir.path().join("raw.key");
+    fs::write(&raw_path, &expected).unwrap();
+    for path in [&hex_path, &raw_path] {
+        let SnapshotKey::Key(key) = SnapshotKey::from_key_file(path).unwrap() else {
+            panic!("not a key")
+        };
+        assert_eq!(expected, key);
+    }
+
+    let short_path = dir.path().join("short.key");
+    fs::write(&short_path, &KEY_HEX[2..]).unwrap();
+    assert!(SnapshotKey::from_key_file(&short_path).is_err());
+}
+
+#[test]
+fn test_roundtrip() {
+    let key = SnapshotKey::Key(hex::decode(KEY_HEX).unwrap().try_into().unwrap());
+    let encrypted = encryption::encrypt(b"chain data", &key).unwrap();
+    assert!(encryption::is_encrypted(&encrypted));
+    assert!(!encryption::is_encrypted(b"chain data"));
+    assert_eq!(b"chain data".to_vec(), encryption::decrypt(&encrypted, &key).unwrap());
+
+    // Every encryption uses a fresh nonce
+    assert_ne!(encrypted, encryption::encrypt(b"chain data", &key).unwrap());
+
+    let passphrase = SnapshotKey::Passphrase(b"correct horse".to_vec());
+    let encrypted = encryption::encrypt(b"chain data", &passphrase).unwrap();
+    assert_eq!(b"chain data".to_vec(), encryption::decrypt(&encrypted, &passphrase).unwrap());
+}
+
+#[test]
+fn test_wrong_key() {
+    let key = SnapshotKey::Key([1; 32]);
+    let mut encrypted = encryption::encrypt(b"chain data", &key).unwrap();
+    assert!(encryption::decrypt(&encrypted, &SnapshotKey::Key([2; 32])).is_err());
+    assert!(encryption::decrypt(&encrypted, &SnapshotKey::Passphrase(b"key".to_vec())).is_err());
+
+    // Altered data is refused
+    let last = encrypted.len() - 1;
+    encrypted[last] ^= 1;
+    assert!(encryption::decrypt(&encrypted, &key).is_err());
+}
+
+/// Reads the blocks of the file at `path` with the given import arguments
+fn read(path: &Path, args: &ImportArgs) -> eyre::Result<Vec<reth_primitives::SealedBlock>> {
+    let progress = ImportProgress::default();
+    FileSource::new(path.display().to_string()).read_blocks(&mut SourceContext {
+        args,
+        progress: &progress,
+        limiter: None,
+        dead_letter: None,
+        validator: None,
+    })
+}
+
+#[test]
+fn test_import_encrypted() {
+    let dir = tempfile::tempdir().unwrap();
+    let key_path = dir.path().join("snapshot.key");
+    fs::write(&key_path, KEY_HEX).unwrap();
+    let path = dir.path().join("blocks.rlp");
+    fs::copy(BLOCKS_PATH, &path).unwrap();
+    encryption::encrypt_file(&path, &SnapshotKey::from_key_file(&key_path).unwrap()).unwrap();
+
+    // Without a key the import is refused
+    assert!(read(&path, &ImportArgs::default()).is_err());
+
+    let args = ImportArgs {
+        encryption: EncryptionArgs { key_file: Some(key_path), passphrase_file: None },
+        ..Default::default()
+    };
+    assert_eq!(blocks::read_blocks(BLOCKS_PATH).unwrap(), read(&path, &args).unwrap());
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use jsonrpsee::{core::Error, types::error::CallError};
+use reth_primitives::{SealedBlock, H256, U256};
+use reth_rpc_types::engine::{ForkchoiceState, PayloadStatusEnum};
+
+use op_reth::cli::{
+    analytics, db,
+    db::head::{BlockPointer, ForkchoicePointers},
+    devnet::{self, driver::MockDriver},
+    keccak,
+    node::engine::{EngineApi, EngineApiServer, INVALID_FORKCHOICE_STATE_CODE},
+};
+
+/// Whether the error refuses a forkchoice state
+fn is_invalid_state(err: &Error) -> bool {
+    let Error::Call(CallError::Custom(err)) = err else { return false };
+    err.code() == INVALID_FORKCHOICE_STATE_CODE
+}
+
+#[tokio::test]
+async fn test_forkchoice_updates() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(dir.path()).unwrap();
+    let chain = devnet::init(&mut env, dir.path(), 901, U256::from(1000)).await.unwrap();
+    let env = Arc::new(env);
+    let engine = EngineApi::new(env.clone(), chain)
+        .with_forkchoice_pointers(dir.path().to_path_buf(), ForkchoicePointers::default());
+    let driver = MockDriver::from_database(engine.clone(), &env).unwrap();
+    let genesis = driver.head().clone();
+    let state = |head_block_hash, safe_block_hash, finalized_block_hash| ForkchoiceState {
+        head_block_hash,
+        safe_block_hash,
+        finalized_block_hash,
+    };
+
+    // A payload is executed against its header, and invalid with a wrong state root
+    let block = driver.next_block(0);
+    let mut header = block.header.clone().unseal();
+    header.state_root = H256::repeat_byte(0x11);
+    let wrong = SealedBlock { header: keccak::seal(header), ..block.clone() };
+    let status = engine.new_payload_v1(wrong.into()).await.unwrap();
+    assert!(matches!(status.status, PayloadStatusEnum::Invalid { .. }), "{:?}", status.status);
+    assert_eq!(Some(genesis.hash()), status.latest_valid_hash);
+
+    // A valid payload only becomes canonical with a forkchoice update
+    let status = engine.new_payload_v1(block.clone().into()).await.unwrap();
+    assert!(matches!(status.status, PayloadStatusEnum::Valid), "{:?}", status.status);
+    assert_eq!(analytics::canonical_tip(&env).unwrap(), Some(0));
+    let updated = engine
+        .fork_choice_updated_v1(state(block.hash(), block.hash(), genesis.hash()), None)
+        .await
+        .unwrap();
+    assert!(matches!(updated.payload_status.status, PayloadStatusEnum::Valid));
+    assert_eq!(analytics::canonical_tip(&env).unwrap(), Some(1));
+
+    // The safe and finalized blocks are kept next to the database
+    let pointers = ForkchoicePointers {
+        safe: Some(BlockPointer { number: 1, hash: block.hash() }),
+        finalized: Some(BlockPointer { number: 0, hash: genesis.hash() }),
+    };
+    assert_eq!(pointers, engine.forkchoice_pointers());
+    assert_eq!(pointers, ForkchoicePointers::read(dir.path()).unwrap());
+
+    // Safe blocks that aren't canonical or lie below the finalized block are refused
+    let unknown = H256::repeat_byte(0x22);
+    let err = engine
+        .fork_choice_updated_v1(state(block.hash(), unknown, genesis.hash()), None)
+        .await
+        .unwrap_err();
+    assert!(is_invalid_state(&err), "{err}");
+    let err = engine
+        .fork_choice_updated_v1(state(block.hash(), genesis.hash(), block.hash()), None)
+        .await
+        .unwrap_err();
+    assert!(is_invalid_state(&err), "{err}");
+    assert_eq!(pointers, engine.forkchoice_pointers());
+
+    // A sibling of the finalized block is accepted, but can't replace it
+    let updated = engine
+        .fork_choice_updated_v1(state(block.hash(), block.hash(), block.hash()), None)
+        .await
+        .unwrap();
+    assert!(matches!(updated.payload_status.status, PayloadStatusEnum::Valid));
+    let sibling = MockDriver::new(engine.clone(), genesis.clone()).next_block(block.timestamp + 1);
+    let status = engine.new_payload_v1(sibling.clone().into()).await.unwrap();
+    assert!(matches!(status.status, PayloadStatusEnum::Accepted), "{:?}", status.status);
+    let err = engine
+        .fork_choice_updated_v1(state(sibling.hash(), genesis.hash(), genesis.hash()), None)
+        .await
+        .unwrap_err();
+    assert!(is_invalid_state(&err), "{err}");
+    assert_eq!(analytics::canonical_tip(&env).unwrap(), Some(1));
+
+    // An unknown head makes the node sync
+    let updated = engine
+        .fork_choice_updated_v1(state(unknown, block.hash(), block.hash()), None)
+        .await
+        .unwrap();
+    assert!(matches!(updated.payload_status.status, PayloadStatusEnum::Syncing));
+}
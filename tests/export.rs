@@ -0,0 +1,74 @@
+use reth_primitives::{rpc::H160, Header, SealedBlock, U256};
+
+use op_reth::cli::blocks::{self, ErigonBlock, ErigonHeader, LegacyTx};
+
+fn block(number: u64) -> ErigonBlock {
+    let header = Header { number, gas_limit: 15_000_000, nonce: 7, ..Default::default() };
+    let tx = LegacyTx {
+        nonce: number,
+        gas_price: 1,
+        gas: 21_000,
+        to: (number > 0).then(|| H160::repeat_byte(1)),
+        value: 5,
+        data: vec![1, 2, 3],
+        v: U256::from(27),
+        r: U256::from(1),
+        s: U256::from(2),
+    };
+    ErigonBlock { header: ErigonHeader::from(&header), txs: vec![tx.into()], uncles: vec![] }
+}
+
+#[test]
+fn test_erigon_block_roundtrip() {
+    let encoded = rlp::encode_list::<ErigonBlock, _>(&[block(0), block(1)]);
+    let decoded = blocks::decode_blocks(&encoded, None, None, None).unwrap();
+    let expected: Vec<SealedBlock> = vec![block(0).into(), block(1).into()];
+    assert_eq!(expected, decoded);
+
+    // Converting the stored transactions back yields the exported transactions
+    for (number, sealed) in decoded.iter().enumerate() {
+        let tx = LegacyTx::try_from(&sealed.body[0]).unwrap();
+        assert_eq!(rlp::encode(&block(number as u64).txs[0]), rlp::encode(&tx));
+    }
+}
+
+#[tokio::test]
+async fn test_export_geth_blocks() {
+    use op_reth::cli::{
+        analytics, args::ImportArgs, blocks::BlockFormat, db, deposit::DepositStore, export,
+        genesis, import::detect_block_format, progress::ImportProgress,
+    };
+    use reth_db::{database::Database, tables, transaction::DbTx};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut db, Some("tests/fixtures/genesis.json"), &args).await.unwrap();
+    blocks::apply(&mut db, Some("tests/fixtures/blocks.rlp"), &args).await.unwrap();
+    let tip = analytics::canonical_tip(&db).unwrap().unwrap();
+    let deposits = DepositStore::open(dir.path()).unwrap();
+
+    let out = tempfile::tempdir().unwrap();
+    for (format, name) in [
+        (BlockFormat::Geth, "blocks.rlp"),
+        (BlockFormat::Geth, "blocks.rlp.gz"),
+        (BlockFormat::RlpStandard, "blocks.hex"),
+    ] {
+        let path = out.path().join(name);
+        let progress = ImportProgress::default();
+        let count =
+            export::export_blocks_as(&db, &deposits, &path, 0..=tip, format, &progress).unwrap();
+        assert_eq!(count, tip + 1);
+
+        // The export reads back as the canonical chain of the database
+        let contents = op_reth::cli::compression::read(&path).unwrap();
+        assert_eq!(detect_block_format(&contents), Some(format), "{name}");
+        let exported = blocks::read_blocks(&path).unwrap();
+        let tx = db.tx().unwrap();
+        for block in &exported {
+            let canonical = tx.get::<tables::CanonicalHeaders>(block.number).unwrap();
+            assert_eq!(Some(block.hash()), canonical, "{name}");
+        }
+        assert_eq!(exported.len() as u64, tip + 1);
+    }
+}
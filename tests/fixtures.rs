@@ -0,0 +1,21 @@
+use std::{fs, path::Path};
+
+#[allow(dead_code)]
+#[path = "../examples/generate_fixtures.rs"]
+mod generate_fixtures;
+
+const FIXTURES_DIR: &str = "tests/fixtures";
+
+#[test]
+fn test_fixtures_match_generator() {
+    let fixtures = Path::new(FIXTURES_DIR);
+    let dir = tempfile::tempdir().unwrap();
+    generate_fixtures::generate(&fixtures.join("genesis.json"), dir.path()).unwrap();
+    for name in ["state.json", "blocks.rlp", "receipts.rlp"] {
+        let generated = fs::read(dir.path().join(name)).unwrap();
+        assert!(
+            fs::read(fixtures.join(name)).unwrap() == generated,
+            "{name} differs from the output of `cargo run --example generate_fixtures`"
+        );
+    }
+}
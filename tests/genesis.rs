@@ -1,25 +1,39 @@
-use std::{path::PathBuf, str::FromStr};
+use std::str::FromStr;
 
 use reth_db::{database::Database, tables, transaction::DbTx};
 use reth_primitives::*;
 
-use op_reth::cli::{db, genesis};
+use op_reth::cli::{args::ImportArgs, chain::OpChainSpec, db, genesis};
+
+const GENESIS_PATH: &str = "tests/fixtures/genesis.json";
+
+/// The hash of the genesis block of the fixture
+const GENESIS_HASH: &str = "0xcb8f9ef46d9b3be38be00c99a80c2bb0159b245668a6aecb96f0edc21a230195";
 
 #[test]
 fn test_from_file() {
-    let genesis = genesis::Genesis::from_file("data/genesis.json").unwrap();
+    let genesis = genesis::Genesis::from_file(GENESIS_PATH).unwrap();
     assert_eq!(genesis.config.chain_id, 420);
     assert_eq!(genesis.difficulty, "1");
     assert_eq!(genesis.gas_limit, "15000000");
     assert_eq!(genesis.extradata.len(), 236);
-    assert_eq!(genesis.alloc.len(), 10);
+    assert_eq!(genesis.alloc.len(), 2);
 }
 
 #[tokio::test]
 async fn test_write_read_genesis_db() {
-    let db_path = PathBuf::from("temp-genesis-db");
-    let mut db = db::open_rw_env(db_path.as_path()).unwrap();
-    genesis::apply(&mut db, Some("data/genesis.json")).await.unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path();
+    let mut db = db::open_rw_env(db_path).unwrap();
+    let chain = genesis::apply(&mut db, Some(GENESIS_PATH), &ImportArgs::default()).await.unwrap();
+
+    // The chain spec follows the genesis config and survives a roundtrip through the database
+    assert_eq!(420, chain.chain_id());
+    chain.write(db_path).unwrap();
+    let stored = OpChainSpec::read(db_path).unwrap().unwrap();
+    assert_eq!(chain.inner, stored.inner);
+    assert_eq!(chain.bedrock_block, stored.bedrock_block);
+    assert_eq!(chain.eip1559_denominator, stored.eip1559_denominator);
 
     // Read account from genesis state
     let tx = db.tx().unwrap();
@@ -57,25 +71,120 @@ async fn test_write_read_genesis_db() {
 
     let tx = db.tx().unwrap();
     let block_hash = tx.get::<tables::CanonicalHeaders>(0u64).unwrap().unwrap();
-    let expected_block_hash =
-        H256::from_str("0xad7e4e683df9b4b187b52e921d9d88e380c879b3956f6fb4d183baec76012bd0")
-            .unwrap();
-    assert_eq!(expected_block_hash, block_hash);
+    assert_eq!(H256::from_str(GENESIS_HASH).unwrap(), block_hash);
 
     let tx = db.tx().unwrap();
     let expected_difficulty = U256::from_str("0x01").unwrap();
     let expected_gas_limit = 15000000;
     let expected_extra_data = Bytes::from_str("0x000000000000000000000000000000000000000000000000000000000000000027770a9694e4B4b1E130Ab91Bc327C36855f612E0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000").unwrap();
     let header = tx.get::<tables::Headers>(0u64).unwrap().unwrap();
+    let genesis = genesis::Genesis::from_file(GENESIS_PATH).unwrap();
     assert_eq!(
         Header {
             difficulty: expected_difficulty,
             gas_limit: expected_gas_limit,
             extra_data: expected_extra_data,
+            state_root: genesis.state_root().unwrap(),
             ..Default::default()
         },
         header
     );
+    assert_ne!(EMPTY_ROOT, header.state_root);
+}
+
+const MINIMAL_GENESIS: &str = r#"{
+    "config": {
+        "ChainName": "devnet", "chainId": 901, "homesteadBlock": 0, "eip150Block": 0,
+        "eip150Hash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+        "eip155Block": 0, "eip158Block": 0, "byzantiumBlock": 0, "constantinopleBlock": 0,
+        "petersburgBlock": 0, "istanbulBlock": 0, "muirGlacierBlock": 0, "berlinBlock": 0,
+        "londonBlock": 0, "arrowGlacierBlock": 0, "grayGlacierBlock": 0, "mergeNetsplitBlock": 0,
+        "bedrockBlock": 0, "terminalTotalDifficulty": 0, "terminalTotalDifficultyPassed": true,
+        "optimism": { "eip1559Elasticity": 10, "eip1559Denominator": 50 }
+    },
+    "difficulty": "1",
+    "gasLimit": "0x1c9c380",
+    "extradata": "0x",
+    "timestamp": "0x64",
+    "nonce": "0x2a",
+    "alloc": {}
+}"#;
+
+#[tokio::test]
+async fn test_genesis_expected_hash() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("genesis.json");
+    std::fs::write(&path, MINIMAL_GENESIS).unwrap();
+    let path = path.to_str();
+
+    let header =
+        serde_json::from_str::<genesis::Genesis>(MINIMAL_GENESIS).unwrap().to_header().unwrap();
+    assert_eq!((100, 42, 30_000_000), (header.timestamp, header.nonce, header.gas_limit));
+    assert_eq!(EMPTY_ROOT, header.state_root);
+    let hash = header.seal_slow().hash();
+
+    // Nothing is written if the hash differs
+    let mut db = db::open_rw_env(&dir.path().join("db")).unwrap();
+    db.create_tables().unwrap();
+    let args = ImportArgs::default();
+    let wrong = H256::repeat_byte(1);
+    assert!(genesis::apply_checked(&mut db, path, Some(wrong), &args).await.is_err());
+    assert_eq!(None, db.view(|tx| tx.get::<tables::CanonicalHeaders>(0)).unwrap().unwrap());
+
+    let chain = genesis::apply_checked(&mut db, path, Some(hash), &args).await.unwrap();
+    assert_eq!(901, chain.chain_id());
+    assert_eq!(10, chain.eip1559_elasticity);
+    assert_eq!(Some(hash), db.view(|tx| tx.get::<tables::CanonicalHeaders>(0)).unwrap().unwrap());
+}
+
+/// A genesis in the layout written by op-node for new OP Stack chains
+const GETH_GENESIS: &str = r#"{
+    "config": {
+        "chainId": "0x385", "homesteadBlock": 0, "eip150Block": 0, "eip155Block": 0,
+        "eip158Block": 0, "byzantiumBlock": 0, "constantinopleBlock": 0, "petersburgBlock": 0,
+        "istanbulBlock": 0, "berlinBlock": 0, "londonBlock": 0, "mergeNetsplitBlock": 0,
+        "bedrockBlock": 0, "terminalTotalDifficulty": 0, "terminalTotalDifficultyPassed": true,
+        "optimism": { "eip1559Elasticity": 6, "eip1559Denominator": 50 }
+    },
+    "nonce": "0x0",
+    "timestamp": "0x64",
+    "extraData": "0x4f50",
+    "gasLimit": "0x1c9c380",
+    "difficulty": "0x0",
+    "mixHash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+    "coinbase": "0x4200000000000000000000000000000000000011",
+    "baseFeePerGas": "0x3b9aca00",
+    "alloc": {
+        "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f": { "balance": "0x1", "nonce": "0x2" }
+    }
+}"#;
+
+#[test]
+fn test_geth_genesis() {
+    let (genesis, format) = genesis::Genesis::decode(GETH_GENESIS.as_bytes()).unwrap();
+    assert_eq!(genesis::GenesisFormat::Geth, format);
+    assert_eq!(901, genesis.config.chain_id);
+    assert_eq!(None, genesis.config.muir_glacier_block);
+
+    let header = genesis.to_header().unwrap();
+    assert_eq!(100, header.timestamp);
+    assert_eq!(30_000_000, header.gas_limit);
+    assert_eq!(U256::ZERO, header.difficulty);
+    assert_eq!(Bytes::from_str("0x4f50").unwrap(), header.extra_data);
+    assert_eq!(H256::from_low_u64_be(1), header.mix_hash);
+    assert_eq!(Some(1_000_000_000), header.base_fee_per_gas);
+    let address = H160::from_str("0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f").unwrap();
+    assert_eq!(Some(2), genesis.alloc[&address].nonce);
+
+    // Forks missing from the config never activate
+    let chain = genesis.config.chain_spec(&header);
+    assert_eq!(ForkCondition::Block(0), chain.inner.fork(Hardfork::London));
+    assert_eq!(ForkCondition::Never, chain.inner.fork(Hardfork::MuirGlacier));
+    assert_eq!(6, chain.eip1559_elasticity);
+    // Canyon is not scheduled for unknown chains unless configured
+    assert_eq!((0, None), (chain.regolith_time, chain.canyon_time));
+    assert_eq!(ForkCondition::Never, chain.inner.fork(Hardfork::Shanghai));
 
-    std::fs::remove_dir_all(db_path).unwrap();
+    let (_, format) = genesis::Genesis::decode(MINIMAL_GENESIS.as_bytes()).unwrap();
+    assert_eq!(genesis::GenesisFormat::Erigon, format);
 }
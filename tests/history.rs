@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use reth_db::{database::Database, tables, transaction::DbTx};
+use reth_eth_wire::{GetBlockHeaders, HeadersDirection};
+use reth_primitives::{BlockHashOrNumber, H256};
+
+use op_reth::cli::{
+    args::ImportArgs, blocks, db, genesis, l1_fee::L1FeeStore, node::history::HistoryServer,
+    receipts,
+};
+
+fn numbers(server: &HistoryServer, start: BlockHashOrNumber, skip: u32, rising: bool) -> Vec<u64> {
+    let direction = if rising { HeadersDirection::Rising } else { HeadersDirection::Falling };
+    let request = GetBlockHeaders { start_block: start, limit: 10, skip, direction };
+    server.headers(&request).unwrap().iter().map(|header| header.number).collect()
+}
+
+#[tokio::test]
+async fn test_history_server() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let fees = L1FeeStore::open(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut db, Some("tests/fixtures/genesis.json"), &args).await.unwrap();
+    blocks::apply(&mut db, Some("tests/fixtures/blocks.rlp"), &args).await.unwrap();
+    let hashes = db
+        .view(|tx| {
+            (0..=2)
+                .map(|number| tx.get::<tables::CanonicalHeaders>(number).unwrap().unwrap())
+                .collect::<Vec<_>>()
+        })
+        .unwrap();
+
+    // Receipts are only served once they are imported
+    let db = Arc::new(db);
+    let server = HistoryServer::new(db.clone(), u64::MAX);
+    assert!(server.receipts(&hashes[1..]).unwrap().is_empty());
+    drop(server);
+    let mut db = Arc::try_unwrap(db).unwrap();
+    receipts::apply(&mut db, &fees, Some("tests/fixtures/receipts.rlp"), &args).await.unwrap();
+    let db = Arc::new(db);
+    let server = HistoryServer::new(db.clone(), u64::MAX);
+    let served = server.receipts(&hashes).unwrap();
+    assert_eq!(vec![0, 1, 1], served.iter().map(Vec::len).collect::<Vec<_>>());
+    assert!(served[1][0].receipt.success);
+
+    assert_eq!(numbers(&server, BlockHashOrNumber::Number(0), 0, true), [0, 1, 2]);
+    assert_eq!(numbers(&server, BlockHashOrNumber::Number(0), 1, true), [0, 2]);
+    assert_eq!(numbers(&server, BlockHashOrNumber::Hash(hashes[2]), 0, false), [2, 1, 0]);
+    assert!(numbers(&server, BlockHashOrNumber::Hash(H256::zero()), 0, true).is_empty());
+
+    // Bodies hold the imported transactions and stop at the first unknown block
+    let bodies = server.bodies(&[hashes[1], hashes[2], H256::zero(), hashes[0]]).unwrap();
+    assert_eq!(vec![1, 1], bodies.iter().map(|body| body.transactions.len()).collect::<Vec<_>>());
+
+    // Blocks from bedrock on are not served
+    let server = HistoryServer::new(db, 2);
+    assert_eq!(numbers(&server, BlockHashOrNumber::Number(0), 0, true), [0, 1]);
+    assert_eq!(1, server.bodies(&hashes[1..]).unwrap().len());
+    assert_eq!(1, server.receipts(&hashes[1..]).unwrap().len());
+}
@@ -0,0 +1,47 @@
+use op_reth::cli::{
+    blocks::BlockFormat,
+    import::{detect_block_format, detect_bytes_format, detect_format, InputFormat},
+};
+
+#[test]
+fn test_detect_bytes_format() {
+    // A list of blocks whose first header starts with a 32 byte parent hash
+    let mut blocks = vec![0xe3, 0xe2, 0xe1, 0xa0];
+    blocks.extend([0x11; 32]);
+    assert_eq!(Some(InputFormat::Blocks), detect_bytes_format(&blocks));
+
+    assert_eq!(Some(InputFormat::Receipts), detect_bytes_format(&[0x01, 0xc2, 0xc1, 0x80]));
+    assert_eq!(
+        Some(InputFormat::State),
+        detect_bytes_format(b"{\n  \"0x4200000000000000000000000000000000000000\": {")
+    );
+    assert_eq!(Some(InputFormat::Genesis), detect_bytes_format(b"{\"config\": {\"chainId\": 10}"));
+    assert_eq!(Some(InputFormat::Era1), detect_bytes_format(&[0x65, 0x32, 0, 0, 0, 0, 0, 0]));
+    assert_eq!(None, detect_bytes_format(b"hello"));
+}
+
+#[test]
+fn test_detect_dir_format() {
+    let freezer = tempfile::tempdir().unwrap();
+    std::fs::write(freezer.path().join("headers.cidx"), []).unwrap();
+    assert_eq!(InputFormat::Freezer, detect_format(freezer.path()).unwrap());
+
+    let era = tempfile::tempdir().unwrap();
+    std::fs::write(era.path().join("mainnet-00000-5ec1ffb8.era1"), []).unwrap();
+    assert_eq!(InputFormat::Era1, detect_format(era.path()).unwrap());
+
+    assert!(detect_format(tempfile::tempdir().unwrap().path()).is_err());
+}
+
+#[test]
+fn test_detect_block_format() {
+    let mut header = vec![0xa0];
+    header.extend([0x11; 32]);
+    let erigon = [&[0xe3, 0xe2, 0xe1][..], &header].concat();
+    let geth = [&[0xe2, 0xe1][..], &header].concat();
+    assert_eq!(Some(BlockFormat::Erigon), detect_block_format(&erigon));
+    assert_eq!(Some(BlockFormat::Geth), detect_block_format(&geth));
+    let hex = format!("0x{}", hex::encode(&geth));
+    assert_eq!(Some(BlockFormat::RlpStandard), detect_block_format(hex.as_bytes()));
+    assert_eq!(Some(InputFormat::Blocks), detect_bytes_format(hex.as_bytes()));
+}
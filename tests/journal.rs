@@ -0,0 +1,29 @@
+use std::io::Write;
+
+use op_reth::cli::journal::{self, Journal};
+
+#[tokio::test]
+async fn test_record() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut input = tempfile::NamedTempFile::new().unwrap();
+    input.write_all(b"hello").unwrap();
+
+    journal::record(dir.path(), "blocks import", &[input.path()], async { Ok(()) }).await.unwrap();
+    let failed = journal::record(dir.path(), "receipts", &[], async { eyre::bail!("corrupt") });
+    assert!(failed.await.is_err());
+
+    let entries = Journal::new(dir.path()).entries().unwrap();
+    assert_eq!(2, entries.len());
+
+    assert_eq!("blocks import", entries[0].command);
+    assert!(entries[0].succeeded());
+    assert_eq!(None, entries[0].tip);
+    assert_eq!(5, entries[0].inputs[0].size);
+    assert_eq!(
+        Some("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"),
+        entries[0].inputs[0].sha256.as_deref()
+    );
+
+    assert_eq!("receipts", entries[1].command);
+    assert_eq!(Some("corrupt"), entries[1].error.as_deref());
+}
@@ -0,0 +1,24 @@
+use reth_primitives::{proofs::EMPTY_ROOT, Header, KECCAK_EMPTY};
+
+use op_reth::cli::keccak::{self, Keccak};
+
+#[test]
+fn test_keccak256() {
+    assert_eq!(KECCAK_EMPTY, keccak::keccak256([]));
+    assert_eq!(reth_primitives::keccak256("op-reth"), keccak::keccak256("op-reth"));
+    assert_eq!(EMPTY_ROOT, triehash::sec_trie_root::<Keccak, Vec<u8>, Vec<u8>, _>(vec![]));
+}
+
+#[test]
+fn test_seal() {
+    let header = Header {
+        number: 7,
+        gas_limit: 15_000_000,
+        base_fee_per_gas: Some(7),
+        ..Default::default()
+    };
+    let expected = header.clone().seal_slow();
+    let sealed = keccak::seal(header);
+    assert_eq!(expected.hash(), sealed.hash());
+    assert_eq!(expected, sealed);
+}
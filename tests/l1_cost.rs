@@ -0,0 +1,66 @@
+use reth_primitives::{Address, Bytes, TransactionKind, H256, U256};
+
+use op_reth::cli::{
+    chain,
+    deposit::TxDeposit,
+    l1_cost::{self, L1BlockInfo, SET_L1_BLOCK_VALUES_SELECTOR, SIGNATURE_GAS},
+    l1_fee::L1FeeInfo,
+};
+
+fn attributes(base_fee: u64, overhead: u64, scalar: u64) -> Vec<u8> {
+    let mut input = SET_L1_BLOCK_VALUES_SELECTOR.to_vec();
+    for word in [16_000_000, 1_680_000_000, base_fee, 0, 3, 0, overhead, scalar] {
+        input.extend_from_slice(&U256::from(word).to_be_bytes::<32>());
+    }
+    input
+}
+
+#[test]
+fn test_l1_cost() {
+    assert_eq!(l1_cost::data_gas(&[0, 0, 1, 2], true), 2 * 4 + 2 * 16);
+    assert_eq!(l1_cost::data_gas(&[0, 0, 1, 2], false), 2 * 4 + 2 * 16 + SIGNATURE_GAS);
+
+    let info = L1BlockInfo::from_attributes(&attributes(30_000_000_000, 188, 684_000)).unwrap();
+    assert_eq!(info.l1_base_fee, U256::from(30_000_000_000u64));
+    assert_eq!(info.l1_fee_overhead, U256::from(188));
+    assert_eq!(info.l1_fee_scalar, U256::from(684_000));
+    assert!(L1BlockInfo::from_attributes(&[0xde, 0xad, 0xbe, 0xef]).is_err());
+
+    // (10 * 16 + 188) * 30 gwei * 0.684
+    let envelope = [1u8; 10];
+    assert_eq!(info.l1_gas_used(&envelope, true), U256::from(348));
+    assert_eq!(info.l1_cost(&envelope, true), U256::from(7_140_960_000_000u64));
+
+    let deposit = TxDeposit {
+        source_hash: H256::repeat_byte(0x11),
+        from: Address::repeat_byte(0x22),
+        to: TransactionKind::Call(chain::l1_block_address()),
+        mint: None,
+        value: 0,
+        gas_limit: 1_000_000,
+        is_system_transaction: true,
+        input: Bytes::from(attributes(1, 2, 3)),
+    };
+    assert_eq!(info.fee_info(&deposit.into_carrier(), true), None);
+}
+
+#[test]
+fn test_l1_fee_scalar() {
+    assert_eq!(l1_cost::format_scalar(U256::from(1_500_000)), "1.5");
+    assert_eq!(l1_cost::format_scalar(U256::from(684_000)), "0.684");
+    assert_eq!(l1_cost::format_scalar(U256::from(2_000_000)), "2");
+    assert_eq!(l1_cost::parse_scalar("1.5").unwrap(), (U256::from(15), U256::from(10)));
+    assert!(l1_cost::parse_scalar("1.5e3").is_err());
+
+    let mut info = L1FeeInfo {
+        l1_gas_price: U256::from(1_000_000_000),
+        l1_gas_used: U256::from(3_000),
+        l1_fee: U256::from(4_500_000_000_000u64),
+        l1_fee_scalar: "1.5".to_string(),
+    };
+    assert!(l1_cost::check_l1_fee(&info).unwrap());
+    info.l1_fee += U256::from(1);
+    assert!(l1_cost::check_l1_fee(&info).unwrap());
+    info.l1_fee += U256::from(1);
+    assert!(!l1_cost::check_l1_fee(&info).unwrap());
+}
@@ -0,0 +1,39 @@
+use std::{net::TcpListener, sync::Arc, time::Duration};
+
+use op_reth::cli::{
+    metrics::{self, MetricsReporter},
+    progress::ImportProgress,
+};
+
+#[tokio::test]
+async fn test_metrics_exporter() {
+    let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    metrics::install(addr).unwrap();
+    // Installing again is a no-op, another address is refused
+    metrics::install(addr).unwrap();
+    assert!(metrics::install("127.0.0.1:1".parse().unwrap()).is_err());
+
+    let progress = Arc::new(ImportProgress::default());
+    progress.set_stage("insert blocks");
+    progress.advance(2).unwrap();
+    progress.record_writes("Headers", 2, 1024);
+    progress.record_writes("PlainAccountState", 3, 276);
+    drop(MetricsReporter::spawn(progress, None));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let body = reqwest::get(format!("http://{addr}/metrics")).await.unwrap().text().await.unwrap();
+    assert!(body.contains("import_items_total{stage=\"insert blocks\"} 2"));
+    assert!(body.contains("import_blocks_total 2"));
+    assert!(body.contains("import_accounts_total 3"));
+    assert!(body.contains("import_table_bytes_total{table=\"Headers\"} 1024"));
+}
+
+#[test]
+fn test_db_size() {
+    let dir = tempfile::tempdir().unwrap();
+    assert_eq!(metrics::db_size(&dir.path().join("missing")), 0);
+    std::fs::write(dir.path().join("mdbx.dat"), [0; 100]).unwrap();
+    std::fs::write(dir.path().join("mdbx.lck"), [0; 20]).unwrap();
+    std::fs::create_dir(dir.path().join("nested")).unwrap();
+    assert_eq!(metrics::db_size(dir.path()), 120);
+}
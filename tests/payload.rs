@@ -0,0 +1,139 @@
+use std::{str::FromStr, sync::Arc};
+
+use reth_db::{
+    database::Database,
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::{Account, Address, Bytes, Header, H256, H64, U256, U64};
+use reth_rpc_types::engine::{ForkchoiceState, PayloadStatusEnum};
+
+use op_reth::cli::{
+    db,
+    deposit::{TxDeposit, DEPOSIT_TX_TYPE},
+    devnet::{self, driver::MockDriver},
+    node::{
+        engine::{EngineApi, EngineApiServer},
+        execution,
+        payload::{self, OpPayloadAttributes, PayloadBuilder},
+        pool::TxPool,
+    },
+    state,
+};
+
+/// The sender of the transaction below, signed with the key `0x4646..46`
+const SENDER: &str = "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f";
+
+/// A transfer of 1 wei at a gas price of 1 gwei with nonce 2 for chain 420
+const NONCE_2: &str = "f86502843b9aca00825208943535353535353535353535353535353535353535018082036ca0d06fd7fc55668a302e23b878edd5ac7684a70fd62cd470c58a02977428d34e8ea00826e2e88874e39fbfc047aa68e548249c0abc7bb976c7ff459f47f4ee437c0f";
+
+#[test]
+fn test_next_base_fee() {
+    let parent = |gas_used| Header {
+        gas_limit: 30_000_000,
+        gas_used,
+        base_fee_per_gas: Some(1_000_000_000),
+        ..Default::default()
+    };
+    assert_eq!(payload::next_base_fee(&parent(5_000_000), 6, 50), 1_000_000_000);
+    assert_eq!(payload::next_base_fee(&parent(0), 6, 50), 980_000_000);
+    assert_eq!(payload::next_base_fee(&parent(30_000_000), 6, 50), 1_100_000_000);
+    assert_eq!(payload::next_base_fee(&parent(5_000_001), 6, 50), 1_000_000_001);
+    assert_eq!(payload::next_base_fee(&Header::default(), 6, 50), payload::INITIAL_BASE_FEE);
+}
+
+#[tokio::test]
+async fn test_payload_builder() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(dir.path()).unwrap();
+    let chain = devnet::init(&mut env, dir.path(), 420, U256::from(1000)).await.unwrap();
+    let env = Arc::new(env);
+
+    let tx = env.tx_mut().unwrap();
+    let account =
+        Account { nonce: 2, balance: U256::from(10).pow(U256::from(18)), bytecode_hash: None };
+    tx.put::<tables::PlainAccountState>(Address::from_str(SENDER).unwrap(), account).unwrap();
+    tx.commit().unwrap();
+
+    let pool = Arc::new(TxPool::new(env.clone(), chain.clone()));
+    let pooled = pool.add_raw(&hex::decode(NONCE_2).unwrap()).unwrap();
+    let builder = Arc::new(PayloadBuilder::new(env.clone(), chain.clone()).with_pool(pool.clone()));
+    let engine =
+        EngineApi::new(env.clone(), chain).with_pool(pool.clone()).with_payload_builder(builder);
+    let head = MockDriver::from_database(engine.clone(), &env).unwrap().head().clone();
+    let state = || ForkchoiceState {
+        head_block_hash: head.hash(),
+        safe_block_hash: head.hash(),
+        finalized_block_hash: head.hash(),
+    };
+
+    let deposit = TxDeposit { gas_limit: 100_000, ..Default::default() };
+    let mut attributes = OpPayloadAttributes {
+        timestamp: U64::from(head.timestamp + 2),
+        prev_randao: H256::repeat_byte(0x11),
+        suggested_fee_recipient: Address::repeat_byte(0x22),
+        withdrawals: Some(vec![]),
+        transactions: vec![Bytes::from(deposit.envelope())],
+        no_tx_pool: true,
+        gas_limit: None,
+    };
+
+    // Without the pool the block only holds the forced deposit
+    let updated = engine.fork_choice_updated_v2(state(), Some(attributes.clone())).await.unwrap();
+    assert!(matches!(updated.payload_status.status, PayloadStatusEnum::Valid));
+    let id = updated.payload_id.unwrap();
+    let again = engine.fork_choice_updated_v2(state(), Some(attributes.clone())).await.unwrap();
+    assert_eq!(Some(id), again.payload_id);
+    let payload = engine.get_payload_v1(id).await.unwrap();
+    assert_eq!(vec![Bytes::from(deposit.envelope())], payload.transactions);
+    // From regolith on the deposit uses the gas of the contract creation, not its gas limit
+    assert_eq!(U64::from(53_000), payload.gas_used);
+    assert_ne!(head.state_root, payload.state_root);
+
+    // With the pool the pooled transaction follows the deposit
+    attributes.no_tx_pool = false;
+    let updated = engine.fork_choice_updated_v2(state(), Some(attributes.clone())).await.unwrap();
+    let envelope = engine.get_payload_v2(updated.payload_id.unwrap()).await.unwrap();
+    let payload = envelope.execution_payload;
+    assert_eq!(2, payload.transactions.len());
+    assert_eq!(U64::from(74_000), payload.gas_used);
+    assert_eq!(Address::repeat_byte(0x22), payload.fee_recipient);
+    assert_eq!(head.hash(), payload.parent_hash);
+
+    // The deposit has a deposit receipt, with the nonce of its sender and the canyon version
+    let hash = payload.block_hash;
+    let built = engine.payload_builder().unwrap().receipts(hash).unwrap();
+    assert_eq!(Some(DEPOSIT_TX_TYPE), built[0].tx_type());
+    assert_eq!((Some(0), Some(1)), (built[0].deposit_nonce, built[0].deposit_receipt_version));
+    assert_eq!(None, built[1].tx_type());
+    assert_eq!(payload.receipts_root, execution::receipts_root(&built));
+
+    // Submitting the payload back executes it, but only the forkchoice update makes it canonical
+    let state_root = payload.state_root;
+    let status = engine.new_payload_v2(payload).await.unwrap();
+    assert!(matches!(status.status, PayloadStatusEnum::Valid), "{:?}", status.status);
+    assert!(env.view(|tx| tx.get::<tables::HeaderNumbers>(hash)).unwrap().unwrap().is_none());
+    assert!(pool.get(&pooled).is_some());
+    let new_head = || ForkchoiceState { head_block_hash: hash, ..state() };
+    let updated = engine.fork_choice_updated_v2(new_head(), None).await.unwrap();
+    assert!(matches!(updated.payload_status.status, PayloadStatusEnum::Valid));
+
+    // The head is inserted with its receipts and state, and the included transaction is dropped
+    assert!(pool.get(&pooled).is_none());
+    let receipts = env
+        .view(|tx| {
+            let number = tx.get::<tables::HeaderNumbers>(hash).unwrap().unwrap();
+            let body = tx.get::<tables::BlockBodies>(number).unwrap().unwrap();
+            (0..body.tx_count)
+                .map(|index| tx.get::<tables::Receipts>(body.start_tx_id + index).unwrap())
+                .collect::<Vec<_>>()
+        })
+        .unwrap();
+    let gas = receipts.iter().map(|receipt| receipt.as_ref().unwrap().cumulative_gas_used);
+    assert_eq!(vec![53_000, 74_000], gas.collect::<Vec<_>>());
+    assert_eq!(state_root, state::hash_and_trie(&env).unwrap());
+
+    // Payloads have to follow their parent, and unknown payloads are refused
+    assert!(engine.fork_choice_updated_v2(new_head(), Some(attributes)).await.is_err());
+    assert!(engine.get_payload_v1(H64::repeat_byte(0x33)).await.is_err());
+}
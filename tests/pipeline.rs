@@ -0,0 +1,47 @@
+use std::{thread, time::Duration};
+
+use op_reth::cli::pipeline;
+
+#[test]
+fn test_pipeline_keeps_order() {
+    let chunks: Vec<Vec<u64>> = (0..20).map(|chunk| (chunk * 5..chunk * 5 + 5).collect()).collect();
+    let mut written = Vec::new();
+    pipeline::run(
+        chunks,
+        3,
+        1,
+        |chunk: Vec<u64>| {
+            // Decoders finishing out of order must not reorder the output
+            thread::sleep(Duration::from_millis(chunk[0] % 3));
+            chunk.into_iter().map(|item| item * 2).collect::<Vec<_>>()
+        },
+        |decoded| {
+            written.extend(decoded);
+            Ok(())
+        },
+    )
+    .unwrap();
+    assert_eq!((0..100).map(|item| item * 2).collect::<Vec<_>>(), written);
+}
+
+#[test]
+fn test_pipeline_stops_on_write_error() {
+    let mut writes = 0;
+    let result = pipeline::run(
+        (0..50).collect(),
+        4,
+        1,
+        |chunk: u64| chunk,
+        |_| {
+            writes += 1;
+            if writes == 3 {
+                eyre::bail!("disk full")
+            }
+            Ok(())
+        },
+    );
+    assert!(result.is_err());
+    assert_eq!(3, writes);
+
+    pipeline::run(Vec::<u64>::new(), 4, 1, |chunk| chunk, |_| Ok(())).unwrap();
+}
@@ -0,0 +1,109 @@
+use std::{str::FromStr, sync::Arc};
+
+use reth_db::{
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTxMut,
+};
+use reth_primitives::{Account, Address, StorageEntry, H256, U256};
+
+use op_reth::cli::{
+    chain, db,
+    deposit::TxDeposit,
+    devnet::{self, driver::MockDriver},
+    node::{
+        engine::EngineApi,
+        payload::PayloadBuilder,
+        pool::{PoolError, TxPool},
+    },
+};
+
+/// The sender of the transactions below, signed with the key `0x4646..46`
+const SENDER: &str = "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f";
+
+/// Transfers of 1 wei at a gas price of 1 gwei: without replay protection, for chain 1 and with
+/// nonces 2 and 5 for chain 420
+const UNPROTECTED: &str = "f86380843b9aca0082520894353535353535353535353535353535353535353501801ca035407eb4abb96b6df9cdb1f572f562b6127934efd719fddda9f4cf91739761eba05b043e15bac72c8bdc1b6ffb67b6f227b03aa6764d1c43bbd0fe86ae23afbac9";
+const MAINNET: &str = "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83";
+const NONCE_2: &str = "f86502843b9aca00825208943535353535353535353535353535353535353535018082036ca0d06fd7fc55668a302e23b878edd5ac7684a70fd62cd470c58a02977428d34e8ea00826e2e88874e39fbfc047aa68e548249c0abc7bb976c7ff459f47f4ee437c0f";
+const NONCE_5: &str = "f86505843b9aca00825208943535353535353535353535353535353535353535018082036ba0d7d054b02615f2b9d130da607fa1f18d43eb7a24008764527b9d3c0cb66f0f0aa07bd93e7f180229b8579e5e873c56aed9fddf7cba07b96e56c2b84c05f7926e6b";
+
+const GWEI: u64 = 1_000_000_000;
+
+fn set_account(env: &Env<WriteMap>, nonce: u64, balance: U256) {
+    let tx = env.tx_mut().unwrap();
+    let sender = Address::from_str(SENDER).unwrap();
+    tx.put::<tables::PlainAccountState>(sender, Account { nonce, balance, bytecode_hash: None })
+        .unwrap();
+    tx.commit().unwrap();
+}
+
+#[tokio::test]
+async fn test_tx_pool() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(dir.path()).unwrap();
+    let chain = devnet::init(&mut env, dir.path(), 420, U256::from(1000)).await.unwrap();
+    let env = Arc::new(env);
+
+    // The L1 fee parameters of the L1Block predeploy
+    let tx = env.tx_mut().unwrap();
+    for (slot, value) in [(1, GWEI), (5, 188), (6, 1_000_000)] {
+        let entry = StorageEntry { key: H256::from_low_u64_be(slot), value: U256::from(value) };
+        tx.put::<tables::PlainStorageState>(chain::l1_block_address(), entry).unwrap();
+    }
+    tx.commit().unwrap();
+
+    let pool = Arc::new(TxPool::new(env.clone(), chain.clone()));
+    let refused = |raw: &str| {
+        let err = pool.add_raw(&hex::decode(raw).unwrap()).unwrap_err();
+        err.downcast::<PoolError>().unwrap()
+    };
+
+    // Deposits and transactions for other chains are refused
+    let deposit = TxDeposit { gas_limit: 21_000, ..Default::default() };
+    let err = pool.add_raw(&deposit.envelope()).unwrap_err();
+    assert_eq!(Some(&PoolError::Deposit), err.downcast_ref::<PoolError>());
+    assert_eq!(PoolError::ChainId { expected: 420, got: None }, refused(UNPROTECTED));
+    assert_eq!(PoolError::ChainId { expected: 420, got: Some(1) }, refused(MAINNET));
+
+    // The balance has to cover the L1 fee on top of the gas and the value
+    set_account(&env, 2, U256::from(21_000 * GWEI + 1));
+    let err = refused(NONCE_2);
+    assert!(
+        matches!(err, PoolError::InsufficientFunds { l1_fee, .. } if l1_fee > U256::ZERO),
+        "{err}"
+    );
+    set_account(&env, 2, U256::from(GWEI * GWEI));
+    let hash = pool.add_raw(&hex::decode(NONCE_2).unwrap()).unwrap();
+    assert_eq!(
+        H256::from_str("023ef02785eb858dc48e52a4a13ea0be63740a31e66ae4f6fcf4bb14eb22fd8a").unwrap(),
+        hash
+    );
+    assert!(pool.get(&hash).unwrap().l1_fee > U256::ZERO);
+    assert_eq!(PoolError::AlreadyKnown, refused(NONCE_2));
+
+    // Transactions after a nonce gap are pooled, but not included
+    pool.add_raw(&hex::decode(NONCE_5).unwrap()).unwrap();
+    assert_eq!(2, pool.len());
+    let best = pool.best_transactions(GWEI, 30_000_000).unwrap();
+    assert_eq!(vec![hash], best.iter().map(|tx| tx.hash()).collect::<Vec<_>>());
+    assert!(pool.best_transactions(2 * GWEI, 30_000_000).unwrap().is_empty());
+    assert!(pool.best_transactions(GWEI, 20_000).unwrap().is_empty());
+
+    // The driver includes the pooled transaction and the engine drops it from the pool
+    let builder = Arc::new(PayloadBuilder::new(env.clone(), chain.clone()).with_pool(pool.clone()));
+    let engine =
+        EngineApi::new(env.clone(), chain).with_pool(pool.clone()).with_payload_builder(builder);
+    let mut driver = MockDriver::from_database(engine, &env).unwrap();
+    assert_eq!(1, driver.build_block(1).unwrap().body.len());
+    driver.produce_block(1).await.unwrap();
+    assert_eq!(1, pool.len());
+    assert!(pool.get(&hash).is_none());
+
+    // Transactions whose nonce the chain moved past are dropped and refused
+    set_account(&env, 6, U256::from(GWEI * GWEI));
+    assert_eq!(1, pool.prune().unwrap());
+    assert!(pool.is_empty());
+    assert_eq!(PoolError::NonceTooLow { expected: 6, got: 5 }, refused(NONCE_5));
+}
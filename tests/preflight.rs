@@ -0,0 +1,24 @@
+use std::{io::Write, path::Path};
+
+use op_reth::cli::preflight::{check_disk_space, ImportStage};
+
+#[test]
+fn test_estimate_growth() {
+    assert_eq!(ImportStage::Blocks.estimate_growth(1000), 3000);
+    assert_eq!(ImportStage::Receipts.estimate_growth(1000), 1500);
+    assert_eq!(ImportStage::State.estimate_growth(1000), 1000);
+}
+
+#[test]
+fn test_check_disk_space() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut input = tempfile::NamedTempFile::new().unwrap();
+    input.write_all(&[0u8; 1024]).unwrap();
+
+    // The database does not exist yet, so its closest existing ancestor is checked
+    let db_path = dir.path().join("db");
+    check_disk_space(&db_path, ImportStage::Blocks, input.path(), false).unwrap();
+
+    // The size of stdin is unknown, so it is not checked
+    check_disk_space(&db_path, ImportStage::Blocks, Path::new("-"), false).unwrap();
+}
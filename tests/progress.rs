@@ -0,0 +1,15 @@
+use op_reth::cli::progress::{ImportProgress, TableWrites};
+
+#[test]
+fn test_table_writes() {
+    let progress = ImportProgress::default();
+    progress.record_writes("Headers", 1, 500);
+    progress.record_writes("Bytecodes", 1, 24_000);
+    progress.record_writes("Headers", 2, 1000);
+    progress.tx_closed();
+
+    let writes = progress.table_writes();
+    assert_eq!(2, writes.len());
+    assert_eq!(TableWrites { inserts: 3, bytes: 1500 }, writes["Headers"]);
+    assert_eq!(TableWrites { inserts: 1, bytes: 24_000 }, writes["Bytecodes"]);
+}
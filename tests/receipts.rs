@@ -1,45 +1,316 @@
-use std::{path::PathBuf, str::FromStr};
+use std::str::FromStr;
 
-// use reth_db::{tables, transaction::DbTx, database::Database};
-use reth_primitives::{rpc::H256, U256};
+use reth_db::{
+    database::Database,
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::{rpc::H256, Bytes, Log, H160, U256};
 
-use op_reth::cli::{db, receipts};
+use op_reth::cli::{
+    args::ImportArgs,
+    blocks,
+    db::{self, logs::LogFilter},
+    dead_letter::DeadLetterFile,
+    deposit::DepositStore,
+    genesis,
+    l1_fee::{L1FeeInfo, L1FeeStore},
+    receipts,
+    validate::ReceiptIssue,
+};
 
-const RECEIPTS_PATH: &str = "data/export_receipt_0_4061223";
+const RECEIPTS_PATH: &str = "tests/fixtures/receipts.rlp";
+const BLOCKS_PATH: &str = "tests/fixtures/blocks.rlp";
+const GENESIS_PATH: &str = "tests/fixtures/genesis.json";
+
+fn l1_fee_info() -> L1FeeInfo {
+    L1FeeInfo {
+        l1_gas_price: U256::from(1),
+        l1_gas_used: U256::from(0x1b62),
+        l1_fee: U256::from(0x2913),
+        l1_fee_scalar: "1.5".to_string(),
+    }
+}
 
 #[test]
 fn test_receipts_from_file() {
     let receipts = receipts::Receipt::from_file(RECEIPTS_PATH).unwrap();
+    assert_eq!(2, receipts.len());
     assert_eq!(0, receipts[0].ty);
     assert_eq!(1, receipts[0].status);
-    assert_eq!(151191, receipts[0].cumulative_gas_used);
+    assert_eq!(21000, receipts[0].cumulative_gas_used);
     assert_eq!(
-        H256::from_str("0x7334ddc1f6beaf66892c25cffdecec275cdfabaf4def047f0c3ce20e6f6483e8")
+        H256::from_str("0x023ef02785eb858dc48e52a4a13ea0be63740a31e66ae4f6fcf4bb14eb22fd8a")
             .unwrap(),
         receipts[0].tx_hash
     );
-    assert_eq!(151191, receipts[0].gas_used);
+    assert_eq!(21000, receipts[0].gas_used);
     assert_eq!(
-        H256::from_str("0x15d55041e8f7b0d1f303b6d4cefe2d2efc257d67acd9f17307261a8f7d786e0e")
+        H256::from_str("0x4ee28c4037abc8a971a5269cbe5b8724043512e0311a0706ee30110abffe93be")
             .unwrap(),
         receipts[0].block_hash
     );
     assert_eq!(U256::from(1), receipts[0].block_number);
     assert_eq!(0, receipts[0].transaction_index);
-    assert_eq!(U256::from(1), receipts[0].l1_gas_price);
-    assert_eq!(U256::from_str("0x1b62").unwrap(), receipts[0].l1_gas_used);
-    assert_eq!(U256::from_str("0x2913").unwrap(), receipts[0].l1_fee);
-    assert_eq!("1.5", receipts[0].l1_fee_scalar);
-    assert_eq!(4029549, receipts.len());
+    assert_eq!(l1_fee_info(), receipts[0].l1_fee_info());
+    assert!(receipts[0].decode_logs().unwrap().is_empty());
+    assert_eq!(U256::from(2), receipts[1].block_number);
+}
+
+#[test]
+fn test_find_receipts() {
+    let receipts = receipts::Receipt::from_file(RECEIPTS_PATH).unwrap();
+    let tx_hash = reth_primitives::H256::from_slice(&receipts[0].tx_hash.0);
+    let found = receipts::query::find_receipts(&receipts, &[tx_hash], &[]).unwrap();
+    assert_eq!(1, found.len());
+    assert_eq!(tx_hash, found[0].transaction_hash);
+    assert_eq!(U256::from(1), found[0].block_number);
+    assert_eq!(Some(l1_fee_info()), found[0].l1_fee);
+
+    let found = receipts::query::find_receipts(&receipts, &[tx_hash], &[2]).unwrap();
+    let blocks = found.iter().map(|receipt| receipt.block_number).collect::<Vec<_>>();
+    assert_eq!(vec![U256::from(1), U256::from(2)], blocks);
+    assert!(receipts::query::find_receipts(&receipts, &[], &[3]).unwrap().is_empty());
+
+    // The L1 fee fields are part of the JSON output
+    let json = serde_json::to_value(&found[0]).unwrap();
+    assert_eq!("1.5", json["l1FeeScalar"]);
+    assert_eq!(tx_hash, serde_json::from_value(json["transactionHash"].clone()).unwrap());
 }
 
 #[tokio::test]
 async fn test_read_write_receipts() {
-    let db_path = PathBuf::from("temp-receipts");
-    let mut db = db::open_rw_env(db_path.as_path()).unwrap();
-    receipts::apply(&mut db, Some(RECEIPTS_PATH)).await.unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let fees = L1FeeStore::open(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut db, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut db, Some(BLOCKS_PATH), &args).await.unwrap();
+    receipts::apply(&mut db, &fees, Some(RECEIPTS_PATH), &args).await.unwrap();
+
+    // The receipts are matched to the transactions of the imported blocks
+    let tx = db.tx().unwrap();
+    for tx_id in 0..2 {
+        let receipt = tx.get::<tables::Receipts>(tx_id).unwrap().unwrap();
+        assert!(receipt.success);
+        assert_eq!(21000, receipt.cumulative_gas_used);
+        assert_eq!(Some(l1_fee_info()), fees.get(tx_id).unwrap());
+    }
+    assert_eq!(None, tx.get::<tables::Receipts>(2).unwrap());
+}
+
+#[tokio::test]
+async fn test_cross_check_receipts() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut db, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut db, Some(BLOCKS_PATH), &args).await.unwrap();
+
+    let receipts = receipts::Receipt::from_file(RECEIPTS_PATH).unwrap();
+    let tx = db.tx().unwrap();
+    let (accepted, issues) = receipts::cross_check(&tx, &receipts).unwrap();
+    assert_eq!(vec![true, true], accepted);
+    assert!(issues.is_empty());
+
+    // A receipt naming another block hash
+    let mut tampered = receipts.clone();
+    tampered[0].block_hash = H256::repeat_byte(1);
+    let (accepted, issues) = receipts::cross_check(&tx, &tampered).unwrap();
+    assert_eq!(vec![false, true], accepted);
+    assert!(matches!(issues[..], [ReceiptIssue::BlockHashMismatch { block: 1, .. }]));
+
+    // A receipt of the transaction of another block
+    let mut tampered = receipts.clone();
+    tampered[1].tx_hash = receipts[0].tx_hash;
+    let (accepted, issues) = receipts::cross_check(&tx, &tampered).unwrap();
+    assert_eq!(vec![true, false], accepted);
+    assert!(matches!(issues[..], [ReceiptIssue::TransactionMismatch { block: 2, index: 0, .. }]));
+
+    // A receipt that doesn't hash to the receipts root of its block
+    let mut tampered = receipts.clone();
+    tampered[1].cumulative_gas_used += 1;
+    let (accepted, issues) = receipts::cross_check(&tx, &tampered).unwrap();
+    assert_eq!(vec![true, false], accepted);
+    assert!(matches!(issues[..], [ReceiptIssue::ReceiptsRootMismatch { block: 2, .. }]));
+
+    // A receipt whose logs bloom doesn't match its logs
+    let mut tampered = receipts.clone();
+    tampered[0].bloom[0] = 0xff;
+    let (accepted, issues) = receipts::cross_check(&tx, &tampered).unwrap();
+    assert_eq!(vec![false, true], accepted);
+    assert_eq!(vec![ReceiptIssue::BloomMismatch { block: 1, index: 0 }], issues);
+
+    // A receipt of a block that was not imported
+    let mut tampered = receipts;
+    tampered[1].block_number = U256::from(3);
+    let (accepted, issues) = receipts::cross_check(&tx, &tampered).unwrap();
+    assert_eq!(vec![true, false], accepted);
+    assert_eq!(vec![ReceiptIssue::MissingBlock { block: 3 }], issues);
+}
+
+#[tokio::test]
+async fn test_strict_receipts() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let fees = L1FeeStore::open(dir.path()).unwrap();
+    let args = ImportArgs { strict_receipts: true, ..Default::default() };
+    genesis::apply(&mut db, Some(GENESIS_PATH), &args).await.unwrap();
+
+    // Without the blocks every receipt contradicts the database
+    assert!(receipts::apply(&mut db, &fees, Some(RECEIPTS_PATH), &args).await.is_err());
+    blocks::apply(&mut db, Some(BLOCKS_PATH), &args).await.unwrap();
+    receipts::apply(&mut db, &fees, Some(RECEIPTS_PATH), &args).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_dead_letter_unconvertible_receipts() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let fees = L1FeeStore::open(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut db, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut db, Some(BLOCKS_PATH), &args).await.unwrap();
+
+    // A receipt of a type reth doesn't know decodes, but can't be converted
+    let mut receipts = receipts::Receipt::from_file(RECEIPTS_PATH).unwrap();
+    receipts[0].ty = 5;
+    let mut export = vec![0];
+    export.extend_from_slice(&rlp::encode_list::<receipts::Receipt, _>(&receipts));
+    let path = dir.path().join("receipts_unknown_type");
+    std::fs::write(&path, export).unwrap();
+    let path = path.display().to_string();
+
+    let err = receipts::apply(&mut db, &fees, Some(&path), &args).await.unwrap_err();
+    assert!(format!("{err:#}").contains("unknown receipt type"), "{err:#}");
+
+    let dead_letter = dir.path().join("dead-letter.jsonl");
+    let args = ImportArgs { dead_letter: Some(dead_letter.clone()), ..Default::default() };
+    receipts::apply(&mut db, &fees, Some(&path), &args).await.unwrap();
+    let tx = db.tx().unwrap();
+    assert_eq!(None, tx.get::<tables::Receipts>(0).unwrap());
+    assert!(tx.get::<tables::Receipts>(1).unwrap().is_some());
+    assert_eq!((None, Some(l1_fee_info())), (fees.get(0).unwrap(), fees.get(1).unwrap()));
+    let letters = DeadLetterFile::read(&dead_letter).unwrap();
+    assert_eq!(
+        vec![("receipt", 0)],
+        letters.iter().map(|l| (l.kind.as_str(), l.index)).collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn test_query_logs() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let fees = L1FeeStore::open(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut db, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut db, Some(BLOCKS_PATH), &args).await.unwrap();
+    receipts::apply(&mut db, &fees, Some(RECEIPTS_PATH), &args).await.unwrap();
+
+    let (token, pool) = (H160::repeat_byte(1), H160::repeat_byte(2));
+    let (transfer, swap) =
+        (reth_primitives::H256::repeat_byte(3), reth_primitives::H256::repeat_byte(4));
+    let log = |address, topic| Log { address, topics: vec![topic], data: Bytes::from(vec![1, 2]) };
+    let tx = db.tx_mut().unwrap();
+    for (tx_id, logs) in
+        [(0, vec![log(token, transfer), log(pool, swap)]), (1, vec![log(token, swap)])]
+    {
+        let mut receipt = tx.get::<tables::Receipts>(tx_id).unwrap().unwrap();
+        receipt.logs = logs;
+        tx.put::<tables::Receipts>(tx_id, receipt).unwrap();
+    }
+    tx.commit().unwrap();
+
+    let deposits = DepositStore::open(dir.path()).unwrap();
+    let query = |filter: LogFilter| {
+        let mut logs = Vec::new();
+        let count = db::logs::query_logs(&db.tx().unwrap(), &deposits, 0..=2, &filter, |log| {
+            logs.push(log);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(count, logs.len() as u64);
+        logs
+    };
+
+    let logs = query(LogFilter::default());
+    assert_eq!(3, logs.len());
+    let positions = logs
+        .iter()
+        .map(|log| (log.block_number.to::<u64>(), log.log_index.to::<u64>()))
+        .collect::<Vec<_>>();
+    assert_eq!(vec![(1, 0), (1, 1), (2, 0)], positions);
+    assert_eq!(Bytes::from(vec![1, 2]), logs[0].data);
+
+    // Log indices count the logs left out by the filter
+    let logs = query(LogFilter { addresses: vec![pool], ..Default::default() });
+    assert_eq!(1, logs.len());
+    assert_eq!(U256::from(1), logs[0].log_index);
+
+    let logs =
+        query(LogFilter { topics: [vec![swap], vec![], vec![], vec![]], ..Default::default() });
+    assert_eq!(vec![pool, token], logs.iter().map(|log| log.address).collect::<Vec<_>>());
+
+    // Topics past the end of the log never match
+    let filter = LogFilter { topics: [vec![], vec![swap], vec![], vec![]], ..Default::default() };
+    assert!(query(filter).is_empty());
+}
+
+#[test]
+fn test_l1_fee_store() {
+    let dir = tempfile::tempdir().unwrap();
+    let fees = L1FeeStore::open(dir.path()).unwrap();
+    let info = l1_fee_info();
+    assert_eq!(1, fees.insert([(7, info.clone())]).unwrap());
+    assert_eq!(Some(info), fees.get(7).unwrap());
+    assert_eq!(None, fees.get(8).unwrap());
+}
+
+#[test]
+fn test_reconcile_l1_fees() {
+    let dir = tempfile::tempdir().unwrap();
+    let fees = L1FeeStore::open(dir.path()).unwrap();
+    let info = l1_fee_info();
+
+    // Fees of a batch whose receipts were committed are final
+    assert_eq!(1, fees.insert_pending(&[(7, info.clone())]).unwrap());
+    fees.clear_pending().unwrap();
+    assert_eq!(0, fees.reconcile(|_| Ok(false)).unwrap());
+    assert_eq!(Some(info.clone()), fees.get(7).unwrap());
+
+    // An interrupted batch leaves fees without receipts, which are removed
+    fees.insert_pending(&[(8, info.clone()), (9, info.clone())]).unwrap();
+    assert_eq!(1, fees.reconcile(|tx_id| Ok(tx_id == 8)).unwrap());
+    assert_eq!((Some(info), None), (fees.get(8).unwrap(), fees.get(9).unwrap()));
+    assert_eq!(0, fees.reconcile(|_| Ok(false)).unwrap());
+}
+
+#[test]
+fn test_stage_l1_fees() {
+    let dir = tempfile::tempdir().unwrap();
+    let fees = L1FeeStore::open(dir.path()).unwrap();
+    let (info, other) = (l1_fee_info(), L1FeeInfo { l1_gas_price: U256::from(2), ..l1_fee_info() });
+    let head = H256::repeat_byte(0x11);
+    fees.insert([(7, info.clone()), (8, info.clone())]).unwrap();
+
+    // Staging replaces the fees from the first unwound transaction on until it is committed
+    fees.stage(8, &[(9, other.clone())], head).unwrap();
+    assert!(fees.stage(8, &[], head).is_err());
+    assert_eq!((None, Some(other.clone())), (fees.get(8).unwrap(), fees.get(9).unwrap()));
+    fees.rollback_staged().unwrap();
+    assert_eq!((Some(info.clone()), None), (fees.get(8).unwrap(), fees.get(9).unwrap()));
 
-    // TODO: receipts::apply doesn't write anything to the database yet
+    fees.stage(8, &[(8, other.clone())], head).unwrap();
+    fees.commit_staged().unwrap();
+    assert_eq!(Some(other.clone()), fees.get(8).unwrap());
+    assert!(!fees.recover_staged(|_| unreachable!()).unwrap());
 
-    std::fs::remove_dir_all(db_path).unwrap();
+    // Fees left staged by a crash are kept only if their head became the head of the database
+    fees.stage(7, &[], head).unwrap();
+    assert!(fees.recover_staged(|hash| Ok(hash != head)).unwrap());
+    assert_eq!((Some(info), Some(other)), (fees.get(7).unwrap(), fees.get(8).unwrap()));
+    fees.stage(7, &[], head).unwrap();
+    assert!(fees.recover_staged(|hash| Ok(hash == head)).unwrap());
+    assert_eq!((None, None), (fees.get(7).unwrap(), fees.get(8).unwrap()));
 }
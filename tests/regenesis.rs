@@ -0,0 +1,114 @@
+use reth_db::{database::Database, tables, transaction::DbTx};
+use reth_primitives::{Header, SealedBlock, H256};
+
+use op_reth::cli::{
+    args::ImportArgs,
+    blocks, db, genesis,
+    regenesis::{self, RegenesisBoundaries},
+    source::{BlockSource, SourceContext},
+};
+
+const BLOCKS_PATH: &str = "tests/fixtures/blocks.rlp";
+const GENESIS_PATH: &str = "tests/fixtures/genesis.json";
+
+/// A source holding the given blocks
+struct Blocks(Vec<SealedBlock>);
+
+impl BlockSource for Blocks {
+    fn describe(&self) -> String {
+        "test blocks".to_string()
+    }
+
+    fn read_blocks(&mut self, _ctx: &mut SourceContext<'_>) -> eyre::Result<Vec<SealedBlock>> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Builds a legacy segment of `len` empty blocks starting at `first`, anchored on a parent hash
+/// that is not part of the database
+fn segment(first: u64, len: u64) -> Vec<SealedBlock> {
+    let mut parent_hash = H256::repeat_byte(0xaa);
+    (first..first + len)
+        .map(|number| {
+            let header =
+                Header { number, parent_hash, timestamp: number * 2, ..Default::default() };
+            let block = SealedBlock {
+                header: header.seal_slow(),
+                body: vec![],
+                ommers: vec![],
+                withdrawals: None,
+            };
+            parent_hash = block.hash();
+            block
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_import_segments() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut db, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut db, Some(BLOCKS_PATH), &args).await.unwrap();
+    let boundaries = RegenesisBoundaries::new(dir.path());
+    assert!(boundaries.read().unwrap().is_empty());
+
+    // The segment must continue the block numbering
+    let first = segment(3, 2);
+    let mut gap = Blocks(segment(4, 2));
+    assert!(blocks::apply_segment(&mut db, &mut gap, &boundaries, &args).await.is_err());
+
+    let boundary = blocks::apply_segment(&mut db, &mut Blocks(first.clone()), &boundaries, &args)
+        .await
+        .unwrap();
+    assert_eq!(1, boundary.segment);
+    assert_eq!(3, boundary.first_block);
+    assert_eq!(first[0].hash(), boundary.anchor_hash);
+    assert_eq!(H256::repeat_byte(0xaa), boundary.anchor_parent_hash);
+    let tip_hash = blocks::read_blocks(BLOCKS_PATH).unwrap()[2].hash();
+    assert_eq!(tip_hash, boundary.previous_hash);
+
+    let second = segment(5, 1);
+    blocks::apply_segment(&mut db, &mut Blocks(second.clone()), &boundaries, &args).await.unwrap();
+
+    let tx = db.tx().unwrap();
+    for block in first.iter().chain(&second) {
+        assert_eq!(Some(block.hash()), tx.get::<tables::CanonicalHeaders>(block.number).unwrap());
+    }
+
+    let recorded = boundaries.read().unwrap();
+    assert_eq!(
+        vec![3, 5],
+        recorded.iter().map(|boundary| boundary.first_block).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![0, 0, 1, 1, 2],
+        (1..=5).map(|number| regenesis::segment_of(&recorded, number)).collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn test_refuse_linked_segment() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut db, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut db, Some(BLOCKS_PATH), &args).await.unwrap();
+    let boundaries = RegenesisBoundaries::new(dir.path());
+
+    // A segment linking to the database tip is not a regenesis
+    let mut linked = segment(3, 1);
+    let header = Header {
+        parent_hash: blocks::read_blocks(BLOCKS_PATH).unwrap()[2].hash(),
+        ..linked[0].header.clone().unseal()
+    };
+    linked[0].header = header.seal_slow();
+    assert!(blocks::apply_segment(&mut db, &mut Blocks(linked), &boundaries, &args).await.is_err());
+
+    // Segments whose blocks don't link to the anchor are refused as well
+    let mut broken = segment(3, 2);
+    broken[1] = segment(4, 1).remove(0);
+    assert!(blocks::apply_segment(&mut db, &mut Blocks(broken), &boundaries, &args).await.is_err());
+    assert!(boundaries.read().unwrap().is_empty());
+}
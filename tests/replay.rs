@@ -0,0 +1,185 @@
+use reth_db::{
+    database::Database,
+    models::{AccountBeforeTx, ShardedKey, TransitionIdAddress},
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::{Account, StorageEntry, H160, H256, MAINNET, U256};
+
+use op_reth::cli::{
+    analytics,
+    args::ImportArgs,
+    blocks, db, genesis,
+    replay::{self, HISTORY_SHARD_LEN},
+    state,
+};
+
+const BLOCKS_PATH: &str = "tests/fixtures/blocks.rlp";
+const GENESIS_PATH: &str = "tests/fixtures/genesis.json";
+
+#[test]
+fn test_history_shards() {
+    assert!(replay::history_shards(vec![]).is_empty());
+    assert_eq!(vec![(u64::MAX, vec![1, 5])], replay::history_shards(vec![1, 5]));
+
+    let transitions: Vec<u64> = (0..HISTORY_SHARD_LEN as u64 * 2 + 1).collect();
+    let shards = replay::history_shards(transitions);
+    assert_eq!(
+        vec![HISTORY_SHARD_LEN as u64 - 1, HISTORY_SHARD_LEN as u64 * 2 - 1, u64::MAX],
+        shards.iter().map(|(highest, _)| *highest).collect::<Vec<_>>()
+    );
+    assert_eq!(vec![HISTORY_SHARD_LEN as u64 * 2], shards[2].1);
+}
+
+#[test]
+fn test_index_history() {
+    let dir = tempfile::tempdir().unwrap();
+    let env = db::open_rw_env(dir.path()).unwrap();
+    let (a, b) = (H160::repeat_byte(0xaa), H160::repeat_byte(0xbb));
+    let slot = H256::repeat_byte(1);
+
+    let tx = env.tx_mut().unwrap();
+    for (transition, address) in [(0, a), (1, b), (3, a), (7, a)] {
+        let change = AccountBeforeTx { address, info: Some(Account::default()) };
+        tx.put::<tables::AccountChangeSet>(transition, change).unwrap();
+    }
+    for transition in [1, 3] {
+        tx.put::<tables::StorageChangeSet>(
+            TransitionIdAddress((transition, b)),
+            StorageEntry { key: slot, value: U256::from(transition) },
+        )
+        .unwrap();
+    }
+
+    // Changes outside of the range are left for a later batch
+    assert_eq!((3, 2), replay::index_history(&tx, 0..7).unwrap());
+    assert_eq!((1, 0), replay::index_history(&tx, 7..8).unwrap());
+    let history = tx.get::<tables::AccountHistory>(ShardedKey::new(a, u64::MAX)).unwrap().unwrap();
+    assert_eq!(vec![0, 3, 7], history.iter(0).collect::<Vec<_>>());
+    let history = tx.get::<tables::AccountHistory>(ShardedKey::new(b, u64::MAX)).unwrap().unwrap();
+    assert_eq!(vec![1], history.iter(0).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_refuse_imported_state() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut env, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut env, Some(BLOCKS_PATH), &args).await.unwrap();
+    assert_eq!(0, env.view(|tx| replay::replayed_block(tx)).unwrap().unwrap());
+
+    // The imported state is the state after the tip, not the one the replay starts from
+    state::hash_and_trie(&env).unwrap();
+    assert!(replay::replay(&env, &MAINNET, 2, 1, true).is_err());
+    assert_eq!(0, env.view(|tx| replay::replayed_block(tx)).unwrap().unwrap());
+}
+
+#[test]
+fn test_state_at_block() {
+    let dir = tempfile::tempdir().unwrap();
+    let env = db::open_rw_env(dir.path()).unwrap();
+    let address = H160::repeat_byte(0xaa);
+    let slot = H256::repeat_byte(1);
+    let balance = |balance: u64| Account { balance: U256::from(balance), ..Default::default() };
+
+    // Block 1 creates the account at transition 0, block 2 changes it and its slot at transition 1
+    let tx = env.tx_mut().unwrap();
+    for (block, transition) in [(0, 0), (1, 1), (2, 2)] {
+        tx.put::<tables::BlockTransitionIndex>(block, transition).unwrap();
+    }
+    tx.put::<tables::AccountChangeSet>(0, AccountBeforeTx { address, info: None }).unwrap();
+    let change = AccountBeforeTx { address, info: Some(balance(1)) };
+    tx.put::<tables::AccountChangeSet>(1, change).unwrap();
+    tx.put::<tables::StorageChangeSet>(
+        TransitionIdAddress((1, address)),
+        StorageEntry { key: slot, value: U256::from(5) },
+    )
+    .unwrap();
+    replay::index_history(&tx, 0..2).unwrap();
+    tx.put::<tables::PlainAccountState>(address, balance(2)).unwrap();
+    tx.put::<tables::PlainStorageState>(address, StorageEntry { key: slot, value: U256::from(6) })
+        .unwrap();
+    replay::EXECUTION.save_progress(&tx, 2).unwrap();
+    for stage in replay::HISTORY_STAGES {
+        stage.save_progress(&tx, 2).unwrap();
+    }
+
+    assert_eq!(None, replay::account_at(&tx, address, 0).unwrap());
+    assert_eq!(Some(balance(1)), replay::account_at(&tx, address, 1).unwrap());
+    assert_eq!(Some(balance(2)), replay::account_at(&tx, address, 2).unwrap());
+    assert_eq!(U256::from(5), replay::storage_at(&tx, address, slot, 1).unwrap());
+    assert_eq!(U256::from(6), replay::storage_at(&tx, address, slot, 2).unwrap());
+    assert_eq!(None, replay::account_at(&tx, H160::repeat_byte(0xbb), 1).unwrap());
+
+    // The history ends at the state of the database
+    assert!(replay::account_at(&tx, address, 3).is_err());
+    tx.commit().unwrap();
+
+    // The supply after an earlier block sums the balances of the accounts after that block
+    assert_eq!(U256::ZERO, analytics::state_supply(&env, Some(0)).unwrap());
+    assert_eq!(U256::from(1), analytics::state_supply(&env, Some(1)).unwrap());
+    assert_eq!(U256::from(2), analytics::state_supply(&env, None).unwrap());
+    assert!(analytics::state_supply(&env, Some(3)).is_err());
+}
+
+#[test]
+fn test_build_history() {
+    let dir = tempfile::tempdir().unwrap();
+    let env = db::open_rw_env(dir.path()).unwrap();
+    let address = H160::repeat_byte(0xaa);
+    let slot = H256::repeat_byte(1);
+    let balance = |balance: u64| Account { balance: U256::from(balance), ..Default::default() };
+    assert!(replay::build_history(&env, 1, false).is_err());
+
+    // Changesets of two replayed blocks, written without indexing them
+    let tx = env.tx_mut().unwrap();
+    for (block, transition) in [(0, 0), (1, 1), (2, 2)] {
+        tx.put::<tables::BlockTransitionIndex>(block, transition).unwrap();
+    }
+    tx.put::<tables::AccountChangeSet>(0, AccountBeforeTx { address, info: None }).unwrap();
+    let change = AccountBeforeTx { address, info: Some(balance(1)) };
+    tx.put::<tables::AccountChangeSet>(1, change).unwrap();
+    tx.put::<tables::StorageChangeSet>(
+        TransitionIdAddress((1, address)),
+        StorageEntry { key: slot, value: U256::from(5) },
+    )
+    .unwrap();
+    tx.put::<tables::PlainAccountState>(address, balance(2)).unwrap();
+    replay::EXECUTION.save_progress(&tx, 2).unwrap();
+    tx.commit().unwrap();
+
+    // Without indices the history would silently miss the changes
+    let account = |block| env.view(|tx| replay::account_at(tx, address, block)).unwrap();
+    assert!(account(1).is_err());
+    assert_eq!(Some(balance(2)), account(2).unwrap());
+
+    let summary = replay::build_history(&env, 1, false).unwrap();
+    assert_eq!((2, 2, 1), (summary.blocks, summary.account_changes, summary.storage_changes));
+    assert_eq!(2, env.view(|tx| replay::indexed_block(tx)).unwrap().unwrap());
+    assert_eq!(None, account(0).unwrap());
+    assert_eq!(Some(balance(1)), account(1).unwrap());
+    let value = env.view(|tx| replay::storage_at(tx, address, slot, 1)).unwrap().unwrap();
+    assert_eq!(U256::from(5), value);
+
+    // Indexing continues from the last indexed block, a rebuild starts over
+    assert_eq!(0, replay::build_history(&env, 1, false).unwrap().blocks);
+    let summary = replay::build_history(&env, 10, true).unwrap();
+    assert_eq!((2, 2, 1), (summary.blocks, summary.account_changes, summary.storage_changes));
+    let history =
+        env.view(|tx| tx.get::<tables::AccountHistory>(ShardedKey::new(address, u64::MAX)));
+    assert_eq!(vec![0, 1], history.unwrap().unwrap().unwrap().iter(0).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_replay_imported_needs_chain_spec() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut env, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut env, Some(BLOCKS_PATH), &args).await.unwrap();
+
+    // The chain spec is recorded by the genesis command, not by importing the genesis state
+    assert!(replay::replay_imported(&env, dir.path(), None, 1, true).is_err());
+    assert_eq!(0, env.view(|tx| replay::replayed_block(tx)).unwrap().unwrap());
+}
@@ -0,0 +1,44 @@
+use op_reth::cli::{
+    progress::{ImportProgress, TableWrites},
+    report::{self, ImportReport, REPORTS_DIR},
+};
+
+#[test]
+fn test_collect_report() {
+    report::reset();
+    let progress = ImportProgress::default();
+    progress.set_stage("decode blocks");
+    progress.advance(3).unwrap();
+    progress.set_stage("insert blocks");
+    progress.advance(2).unwrap();
+    progress.record_writes("Headers", 2, 1000);
+    progress.finish();
+    // Finishing twice doesn't report the writes twice
+    progress.finish();
+
+    let collected = report::take();
+    let stages = collected.stages.iter().map(|stage| (stage.name.as_str(), stage.items));
+    assert_eq!(stages.collect::<Vec<_>>(), [("decode blocks", 3), ("insert blocks", 2)]);
+    assert_eq!(collected.writes["Headers"], TableWrites { inserts: 2, bytes: 1000 });
+    assert_eq!(report::take(), Default::default());
+
+    let dir = tempfile::tempdir().unwrap();
+    let report = ImportReport {
+        command: "blocks import".to_string(),
+        args: vec![],
+        started_at: 1700000000,
+        duration_ms: 10,
+        inputs: vec![],
+        stages: collected.stages,
+        writes: collected.writes,
+        rejected: 0,
+        error: None,
+        tip: Some(2),
+        tip_hash: None,
+        state_root: None,
+    };
+    let path = report.write(dir.path()).unwrap();
+    assert_eq!(path, dir.path().join(REPORTS_DIR).join("1700000000-blocks-import.json"));
+    let written: ImportReport = serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+    assert_eq!(written, report);
+}
@@ -0,0 +1,80 @@
+use std::{cell::Cell, io, time::Duration};
+
+use op_reth::cli::retry::{is_transient, RetryPolicy};
+
+fn policy(retries: u32) -> RetryPolicy {
+    RetryPolicy { retries, backoff: Duration::from_millis(1) }
+}
+
+#[test]
+fn test_backoff_doubles_up_to_cap() {
+    let policy = RetryPolicy { retries: 10, backoff: Duration::from_millis(500) };
+    assert_eq!(policy.backoff(0), Duration::from_millis(500));
+    assert_eq!(policy.backoff(1), Duration::from_secs(1));
+    assert_eq!(policy.backoff(2), Duration::from_secs(2));
+    assert_eq!(policy.backoff(40), Duration::from_secs(30));
+}
+
+#[test]
+fn test_retries_transient_failures() {
+    let attempts = Cell::new(0);
+    let result = policy(3).run("test", || {
+        attempts.set(attempts.get() + 1);
+        if attempts.get() < 3 {
+            return Err(io::Error::from(io::ErrorKind::Interrupted).into())
+        }
+        Ok(attempts.get())
+    });
+    assert_eq!(result.unwrap(), 3);
+
+    let attempts = Cell::new(0);
+    let result = policy(2).run("test", || -> eyre::Result<()> {
+        attempts.set(attempts.get() + 1);
+        Err(io::Error::from(io::ErrorKind::TimedOut).into())
+    });
+    assert!(result.is_err());
+    assert_eq!(attempts.get(), 3);
+}
+
+#[test]
+fn test_fails_fast_on_permanent_failures() {
+    let attempts = Cell::new(0);
+    let result = policy(3).run("test", || -> eyre::Result<()> {
+        attempts.set(attempts.get() + 1);
+        Err(io::Error::from(io::ErrorKind::NotFound).into())
+    });
+    assert!(result.is_err());
+    assert_eq!(attempts.get(), 1);
+
+    assert!(!is_transient(&eyre::eyre!("invalid rlp")));
+    let wrapped = eyre::Report::from(io::Error::from(io::ErrorKind::ConnectionReset))
+        .wrap_err("reading the export");
+    assert!(is_transient(&wrapped));
+}
+
+#[tokio::test]
+async fn test_retries_async_failures() {
+    let attempts = Cell::new(0);
+    let result = policy(3)
+        .run_async("test", || {
+            attempts.set(attempts.get() + 1);
+            async {
+                if attempts.get() < 3 {
+                    return Err(io::Error::from(io::ErrorKind::ConnectionReset).into())
+                }
+                Ok(attempts.get())
+            }
+        })
+        .await;
+    assert_eq!(result.unwrap(), 3);
+
+    let attempts = Cell::new(0);
+    let result = policy(3)
+        .run_async("test", || {
+            attempts.set(attempts.get() + 1);
+            async { eyre::Result::<()>::Err(eyre::eyre!("method not found")) }
+        })
+        .await;
+    assert!(result.is_err());
+    assert_eq!(attempts.get(), 1);
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use reth_primitives::{BlockNumberOrTag, U256};
+use reth_rpc_types::engine::{ForkchoiceState, PayloadStatusEnum};
+
+use op_reth::cli::{
+    db,
+    deposit::DepositStore,
+    devnet::{self, driver::MockDriver},
+    l1_fee::L1FeeStore,
+    node::engine::{EngineApi, EngineApiServer},
+    rpc::{cache::ResponseCache, EthApi, EthApiServer},
+};
+
+#[test]
+fn test_response_cache() {
+    let cache = ResponseCache::<u64, String>::new("test", 2);
+    let mut loads = 0;
+    let mut load = |key: u64| {
+        cache
+            .get_or_load(key, || {
+                loads += 1;
+                Ok((key < 10).then(|| key.to_string()))
+            })
+            .unwrap()
+    };
+
+    assert_eq!(load(1), Some("1".to_string()));
+    assert_eq!(load(1), Some("1".to_string()));
+    assert_eq!(load(2), Some("2".to_string()));
+    // Missing values are loaded every time
+    assert_eq!(load(10), None);
+    assert_eq!(load(10), None);
+    // The least recently used entry is evicted
+    assert_eq!(load(3), Some("3".to_string()));
+    assert_eq!(load(1), Some("1".to_string()));
+    drop(load);
+    assert_eq!(loads, 6);
+    assert_eq!(cache.len(), 2);
+
+    // A cache without capacity loads every time
+    let disabled = ResponseCache::<u64, u64>::new("disabled", 0);
+    assert_eq!(disabled.get_or_load(1, || Ok(Some(1))).unwrap(), Some(1));
+    assert!(disabled.is_empty());
+}
+
+#[tokio::test]
+async fn test_block_cache_after_reorg() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(dir.path()).unwrap();
+    let chain = devnet::init(&mut env, dir.path(), 901, U256::from(1000)).await.unwrap();
+    let env = Arc::new(env);
+    let fees = Arc::new(L1FeeStore::open(dir.path()).unwrap());
+    let deposits = Arc::new(DepositStore::open(dir.path()).unwrap());
+    let api = EthApi::new(env.clone(), fees, deposits);
+    let engine = EngineApi::new(env.clone(), chain);
+    let driver = MockDriver::from_database(engine.clone(), &env).unwrap();
+    let genesis = driver.head().clone();
+
+    // Block 1 is served and cached, keeping genesis finalized
+    let first = driver.next_block(0);
+    let status = engine.new_payload_v1(first.clone().into()).await.unwrap();
+    assert!(matches!(status.status, PayloadStatusEnum::Valid), "{:?}", status.status);
+    let state = |head_block_hash| ForkchoiceState {
+        head_block_hash,
+        safe_block_hash: genesis.hash(),
+        finalized_block_hash: genesis.hash(),
+    };
+    let updated = engine.fork_choice_updated_v1(state(first.hash()), None).await.unwrap();
+    assert!(matches!(updated.payload_status.status, PayloadStatusEnum::Valid));
+    let block = api.block_by_number(BlockNumberOrTag::Number(1), false).unwrap().unwrap();
+    assert_eq!(block.hash, first.hash());
+
+    // A sibling with another timestamp replaces it
+    let sibling = MockDriver::new(engine.clone(), genesis.clone()).next_block(first.timestamp + 1);
+    let status = engine.new_payload_v1(sibling.clone().into()).await.unwrap();
+    assert!(matches!(status.status, PayloadStatusEnum::Accepted), "{:?}", status.status);
+    let updated = engine.fork_choice_updated_v1(state(sibling.hash()), None).await.unwrap();
+    assert!(matches!(updated.payload_status.status, PayloadStatusEnum::Valid));
+
+    // The cached block of the replaced hash isn't served for the number anymore
+    let block = api.block_by_number(BlockNumberOrTag::Number(1), false).unwrap().unwrap();
+    assert_eq!(block.hash, sibling.hash());
+    assert!(api.block_by_hash(first.hash(), false).unwrap().is_none());
+}
@@ -0,0 +1,157 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use op_reth::cli::{
+    args::ImportArgs,
+    blocks,
+    import::{detect_format, InputFormat},
+    progress::ImportProgress,
+    receipts::Receipt,
+    source::{
+        shards::{self, Shard, ShardedSource},
+        BlockSource, ReceiptSource, SourceContext, StateSource,
+    },
+    state::{self, State},
+};
+
+const BLOCKS_PATH: &str = "tests/fixtures/blocks.rlp";
+const RECEIPTS_PATH: &str = "tests/fixtures/receipts.rlp";
+const STATE_PATH: &str = "tests/fixtures/state.json";
+
+/// Runs `read` with a context without a dead-letter file or validator
+fn with_context<T>(read: impl FnOnce(&mut SourceContext<'_>) -> T) -> T {
+    let args = ImportArgs::default();
+    let progress = ImportProgress::default();
+    read(&mut SourceContext {
+        args: &args,
+        progress: &progress,
+        limiter: None,
+        dead_letter: None,
+        validator: None,
+    })
+}
+
+/// Writes the items at the given indices of the rlp list in `data` to `path` as a list, after
+/// `prefix`
+fn write_items(path: &Path, prefix: &[u8], data: &[u8], indices: &[usize]) {
+    let list = rlp::Rlp::new(data);
+    let mut stream = rlp::RlpStream::new_list(indices.len());
+    for index in indices {
+        stream.append_raw(list.at(*index).unwrap().as_raw(), 1);
+    }
+    fs::write(path, [prefix, &stream.out()].concat()).unwrap();
+}
+
+fn shard(path: &str, from: u64, to: u64) -> Shard {
+    Shard { path: PathBuf::from(path), from, to }
+}
+
+#[test]
+fn test_parse_range() {
+    assert_eq!(Some((0, 4061224)), shards::parse_range(Path::new("export_0_4061224")));
+    assert_eq!(
+        Some((1000000, 1999999)),
+        shards::parse_range(Path::new("dir/export_receipt_1000000_1999999.rlp"))
+    );
+    assert_eq!(None, shards::parse_range(Path::new("alloc_everything_4061224_final.json")));
+    assert_eq!(None, shards::parse_range(Path::new("export_2000_1000")));
+    assert_eq!(None, shards::parse_range(Path::new("SHA256SUMS")));
+}
+
+#[test]
+fn test_check_contiguous() {
+    // Both exclusive and inclusive range ends are accepted
+    shards::check_contiguous(&[shard("a", 0, 100), shard("b", 100, 200)]).unwrap();
+    shards::check_contiguous(&[shard("a", 0, 99), shard("b", 100, 199)]).unwrap();
+    assert!(shards::check_contiguous(&[shard("a", 0, 99), shard("b", 101, 200)]).is_err());
+
+    // Overlapping shards are accepted, gaps after the shards covering the most blocks are not
+    shards::check_contiguous(&[shard("a", 0, 100), shard("b", 50, 200)]).unwrap();
+    shards::check_contiguous(&[shard("a", 0, 100), shard("b", 10, 20), shard("c", 101, 200)])
+        .unwrap();
+    assert!(shards::check_contiguous(&[
+        shard("a", 0, 100),
+        shard("b", 10, 20),
+        shard("c", 102, 200)
+    ])
+    .is_err());
+}
+
+#[test]
+fn test_sharded_blocks() {
+    let data = fs::read(BLOCKS_PATH).unwrap();
+    let expected = blocks::read_blocks(BLOCKS_PATH).unwrap();
+
+    // Files without a block range are skipped
+    let dir = tempfile::tempdir().unwrap();
+    write_items(&dir.path().join("export_2_2"), &[], &data, &[2]);
+    write_items(&dir.path().join("export_0_1"), &[], &data, &[0, 1]);
+    fs::write(dir.path().join("SHA256SUMS"), "").unwrap();
+    let path = dir.path().display().to_string();
+    assert_eq!(InputFormat::Blocks, detect_format(dir.path()).unwrap());
+    let read = with_context(|ctx| ShardedSource::new(path.as_str()).read_blocks(ctx)).unwrap();
+    assert_eq!(expected, read);
+
+    // A block shared by consecutive shards is only read once
+    let dir = tempfile::tempdir().unwrap();
+    write_items(&dir.path().join("export_0_1"), &[], &data, &[0, 1]);
+    write_items(&dir.path().join("export_1_2"), &[], &data, &[1, 2]);
+    let pattern = format!("{}/export_*", dir.path().display());
+    assert_eq!(InputFormat::Blocks, detect_format(Path::new(&pattern)).unwrap());
+    let read = with_context(|ctx| ShardedSource::new(pattern.as_str()).read_blocks(ctx)).unwrap();
+    assert_eq!(expected, read);
+
+    // Blocks shared by overlapping shards are only read once
+    let dir = tempfile::tempdir().unwrap();
+    write_items(&dir.path().join("export_0_2"), &[], &data, &[0, 1, 2]);
+    write_items(&dir.path().join("export_1_2"), &[], &data, &[1, 2]);
+    let path = dir.path().display().to_string();
+    let read = with_context(|ctx| ShardedSource::new(path.as_str()).read_blocks(ctx)).unwrap();
+    assert_eq!(expected, read);
+
+    // Shards missing a block are refused
+    let dir = tempfile::tempdir().unwrap();
+    write_items(&dir.path().join("export_0_0"), &[], &data, &[0]);
+    write_items(&dir.path().join("export_1_2"), &[], &data, &[2]);
+    let path = dir.path().display().to_string();
+    assert!(with_context(|ctx| ShardedSource::new(path.as_str()).read_blocks(ctx)).is_err());
+}
+
+#[test]
+fn test_sharded_receipts() {
+    let data = fs::read(RECEIPTS_PATH).unwrap();
+    let expected = Receipt::from_file(RECEIPTS_PATH).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    write_items(&dir.path().join("export_receipt_0_1"), &data[..1], &data[1..], &[0, 1]);
+    write_items(&dir.path().join("export_receipt_1_2"), &data[..1], &data[1..], &[1, 2]);
+    let path = dir.path().display().to_string();
+    let read = with_context(|ctx| ShardedSource::new(path.as_str()).read_receipts(ctx)).unwrap();
+    assert_eq!(
+        expected.iter().map(|receipt| receipt.tx_hash).collect::<Vec<_>>(),
+        read.iter().map(|receipt| receipt.tx_hash).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_sharded_state() {
+    let expected = state::from_file(STATE_PATH).unwrap();
+    let (mut first, mut second) = (State::new(), State::new());
+    for (index, (address, account)) in expected.clone().into_iter().enumerate() {
+        let part = if index % 2 == 0 { &mut first } else { &mut second };
+        part.insert(address, account);
+    }
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("alloc_0.json"), serde_json::to_vec(&first).unwrap()).unwrap();
+    fs::write(dir.path().join("alloc_1.json"), serde_json::to_vec(&second).unwrap()).unwrap();
+    fs::write(dir.path().join("SHA256SUMS"), "").unwrap();
+    let path = dir.path().display().to_string();
+    let read = with_context(|ctx| ShardedSource::new(path.as_str()).read_state(ctx)).unwrap();
+    assert_eq!(expected.keys().collect::<Vec<_>>(), read.keys().collect::<Vec<_>>());
+
+    // An account may only be part of one file
+    fs::write(dir.path().join("alloc_2.json"), serde_json::to_vec(&first).unwrap()).unwrap();
+    assert!(with_context(|ctx| ShardedSource::new(path.as_str()).read_state(ctx)).is_err());
+}
@@ -0,0 +1,59 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use op_reth::cli::shutdown::{HookOutcome, ShutdownPhase, ShutdownRegistry};
+
+#[tokio::test]
+async fn test_shutdown_hooks_run_in_order() {
+    let registry = ShutdownRegistry::default();
+    let ran = Arc::new(Mutex::new(Vec::new()));
+    let hook = |name: &'static str| {
+        let ran = ran.clone();
+        move || async move {
+            ran.lock().unwrap().push(name);
+            Ok(())
+        }
+    };
+
+    let _database = registry.register("database", ShutdownPhase::Database, hook("database"));
+    let _rpc = registry.register("rpc", ShutdownPhase::Rpc, hook("rpc"));
+    let _first = registry.register("first import", ShutdownPhase::Import, hook("first import"));
+    let _second = registry.register("second import", ShutdownPhase::Import, hook("second import"));
+    // Hooks of finished subsystems don't run
+    drop(registry.register("finished", ShutdownPhase::Import, hook("finished")));
+    assert_eq!(registry.len(), 4);
+
+    let reports = registry.run(Duration::from_secs(1)).await;
+    assert!(reports.iter().all(|report| report.outcome == HookOutcome::Completed));
+    // Phases run in order, the hooks of a phase in reverse order of registration
+    assert_eq!(*ran.lock().unwrap(), vec!["second import", "first import", "rpc", "database"]);
+    // Hooks run once
+    assert!(registry.is_empty());
+    assert!(registry.run(Duration::from_secs(1)).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_shutdown_hook_failures() {
+    let registry = ShutdownRegistry::default();
+    let _failing = registry
+        .register("failing", ShutdownPhase::Import, || async { eyre::bail!("flush failed") });
+    let _stuck = registry.register("stuck", ShutdownPhase::Rpc, || async {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        Ok(())
+    });
+    let _database = registry.register("database", ShutdownPhase::Database, || async { Ok(()) });
+
+    // Failing and stuck hooks don't keep the later hooks from running
+    let reports = registry.run(Duration::from_millis(50)).await;
+    let outcomes = reports.into_iter().map(|report| (report.name, report.outcome));
+    assert_eq!(
+        outcomes.collect::<Vec<_>>(),
+        vec![
+            ("failing", HookOutcome::Failed("flush failed".to_string())),
+            ("stuck", HookOutcome::TimedOut),
+            ("database", HookOutcome::Completed),
+        ]
+    );
+}
@@ -0,0 +1,372 @@
+use std::{fs, io::Write, path::Path};
+
+use ciborium::value::Value;
+use reth_libmdbx::{DatabaseFlags, Environment, NoWriteMap, WriteFlags};
+use reth_primitives::{Address, SealedBlock, H256, U256};
+use reth_rlp::Encodable;
+
+use op_reth::cli::{
+    args::{parse_block_range, ImportArgs},
+    blocks,
+    progress::ImportProgress,
+    source::{
+        assemble_block,
+        era1::{Era1Source, COMPRESSED_BODY, COMPRESSED_HEADER},
+        erigon::{self, ErigonSource},
+        file::FileSource,
+        freezer::{self, FreezerSource},
+        mapped::Contents,
+        BlockSource, ReceiptSource, SourceContext, StateSource,
+    },
+};
+
+const BLOCKS_PATH: &str = "tests/fixtures/blocks.rlp";
+
+/// Reads the blocks of the given source without a dead-letter file or validator
+fn read(source: &mut dyn BlockSource) -> eyre::Result<Vec<SealedBlock>> {
+    let args = ImportArgs::default();
+    let progress = ImportProgress::default();
+    source.read_blocks(&mut SourceContext {
+        args: &args,
+        progress: &progress,
+        limiter: None,
+        dead_letter: None,
+        validator: None,
+    })
+}
+
+/// Splits the fixture blocks into their rlp-encoded headers and bodies
+fn headers_and_bodies() -> Vec<(Vec<u8>, Vec<u8>)> {
+    blocks::read_blocks(BLOCKS_PATH)
+        .unwrap()
+        .into_iter()
+        .map(|block| {
+            let mut encoded = Vec::new();
+            block.unseal().encode(&mut encoded);
+            let block = rlp::Rlp::new(&encoded);
+            let mut body = rlp::RlpStream::new_list(block.item_count().unwrap() - 1);
+            for item in block.iter().skip(1) {
+                body.append_raw(item.as_raw(), 1);
+            }
+            (block.at(0).unwrap().as_raw().to_vec(), body.out().to_vec())
+        })
+        .collect()
+}
+
+/// Writes a snappy-compressed freezer table holding the given items
+fn write_freezer_table(dir: &Path, name: &str, items: &[&[u8]]) {
+    let mut index = vec![0; 6];
+    let mut data = Vec::new();
+    for item in items {
+        data.extend(snap::raw::Encoder::new().compress_vec(item).unwrap());
+        index.extend([0, 0]);
+        index.extend((data.len() as u32).to_be_bytes());
+    }
+    fs::write(dir.join(format!("{name}.cidx")), index).unwrap();
+    fs::write(dir.join(format!("{name}.0000.cdat")), data).unwrap();
+}
+
+/// Appends an e2store entry of the given type holding the snappy-framed data
+fn write_era1_entry(archive: &mut Vec<u8>, ty: [u8; 2], data: &[u8]) {
+    let mut encoder = snap::write::FrameEncoder::new(Vec::new());
+    encoder.write_all(data).unwrap();
+    let compressed = encoder.into_inner().unwrap();
+    archive.extend(ty);
+    archive.extend((compressed.len() as u32).to_le_bytes());
+    archive.extend([0, 0]);
+    archive.extend(compressed);
+}
+
+#[test]
+fn test_file_source() {
+    assert_eq!(
+        blocks::read_blocks(BLOCKS_PATH).unwrap(),
+        read(&mut FileSource::new(BLOCKS_PATH)).unwrap()
+    );
+}
+
+#[test]
+fn test_mapped_contents() {
+    let args = ImportArgs::default();
+    let progress = ImportProgress::default();
+    let ctx = SourceContext {
+        args: &args,
+        progress: &progress,
+        limiter: None,
+        dead_letter: None,
+        validator: None,
+    };
+    let expected = fs::read(BLOCKS_PATH).unwrap();
+
+    // Files are read unless mapping them was asked for
+    let contents = FileSource::new(BLOCKS_PATH).read(&ctx).unwrap();
+    assert!(matches!(contents, Contents::Owned(_)));
+    assert_eq!(expected, &contents[..]);
+
+    // Plain files are decoded from the mapping, compressed ones from their decompressed contents
+    let args = ImportArgs { mmap: true, ..Default::default() };
+    let ctx = SourceContext { args: &args, ..ctx };
+    let contents = FileSource::new(BLOCKS_PATH).read(&ctx).unwrap();
+    assert!(matches!(contents, Contents::Mapped(_)));
+    assert_eq!(expected, &contents[..]);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("export_0_2.zst");
+    fs::write(&path, zstd::encode_all(&expected[..], 0).unwrap()).unwrap();
+    let contents = FileSource::new(path.display().to_string()).read(&ctx).unwrap();
+    assert!(matches!(contents, Contents::Owned(_)));
+    assert_eq!(expected, contents.into_vec());
+
+    let path = dir.path().join("empty.rlp");
+    fs::write(&path, b"").unwrap();
+    assert!(Contents::map(&path).unwrap().is_empty());
+}
+
+#[test]
+fn test_assemble_block() {
+    let expected = blocks::read_blocks(BLOCKS_PATH).unwrap();
+    let (header, body) = &headers_and_bodies()[1];
+    let decoded =
+        blocks::decode_blocks(&assemble_block(header, body).unwrap(), None, None, None).unwrap();
+    assert_eq!(vec![expected[1].clone()], decoded);
+}
+
+#[test]
+fn test_freezer_source() {
+    let dir = tempfile::tempdir().unwrap();
+    let (headers, bodies): (Vec<_>, Vec<_>) = headers_and_bodies().into_iter().unzip();
+    write_freezer_table(
+        dir.path(),
+        "headers",
+        &headers.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+    );
+    write_freezer_table(
+        dir.path(),
+        "bodies",
+        &bodies.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+    );
+
+    let expected = blocks::read_blocks(BLOCKS_PATH).unwrap();
+    assert_eq!(expected, read(&mut FreezerSource::new(dir.path())).unwrap());
+
+    // Tables covering different blocks are refused
+    write_freezer_table(dir.path(), "bodies", &[&bodies[0]]);
+    assert!(read(&mut FreezerSource::new(dir.path())).is_err());
+}
+
+#[test]
+fn test_freezer_receipts() {
+    let dir = tempfile::tempdir().unwrap();
+    let (headers, bodies): (Vec<_>, Vec<_>) = headers_and_bodies().into_iter().unzip();
+    let blocks = blocks::read_blocks(BLOCKS_PATH).unwrap();
+    // Receipts in the l2geth layout, spending 21000 gas per transaction
+    let stored = blocks
+        .iter()
+        .map(|block| {
+            let mut receipts = rlp::RlpStream::new_list(block.body.len());
+            for index in 0..block.body.len() {
+                receipts.begin_list(7);
+                receipts.append(&vec![1u8]);
+                receipts.append(&(21000 * (index as u64 + 1)));
+                receipts.begin_list(0);
+                receipts.append(&1000u64).append(&7u64).append(&10500u64).append(&"1.5");
+            }
+            receipts.out().to_vec()
+        })
+        .collect::<Vec<_>>();
+    for (name, items) in [("headers", &headers), ("bodies", &bodies), ("receipts", &stored)] {
+        write_freezer_table(dir.path(), name, &items.iter().map(Vec::as_slice).collect::<Vec<_>>());
+    }
+    assert!(freezer::is_freezer(dir.path()));
+
+    let args = ImportArgs::default();
+    let progress = ImportProgress::default();
+    let receipts = FreezerSource::new(dir.path())
+        .read_receipts(&mut SourceContext {
+            args: &args,
+            progress: &progress,
+            limiter: None,
+            dead_letter: None,
+            validator: None,
+        })
+        .unwrap();
+    let transactions = blocks.iter().flat_map(|block| &block.body).collect::<Vec<_>>();
+    assert_eq!(receipts.len(), transactions.len());
+    for (receipt, transaction) in receipts.iter().zip(transactions) {
+        assert_eq!(receipt.tx_hash.0, transaction.hash().0);
+        assert_eq!(receipt.status, 1);
+        assert_eq!(receipt.gas_used, 21000);
+        assert_eq!(receipt.l1_fee.as_limbs()[0], 10500);
+        assert_eq!(receipt.l1_fee_scalar, "1.5");
+        assert_eq!(receipt.bloom, vec![0; 256]);
+    }
+
+    // Receipts stored for a different number of transactions are refused
+    let block = blocks.iter().find(|block| !block.body.is_empty()).unwrap();
+    assert!(freezer::decode_stored_receipts(block, &[0xc0]).is_err());
+}
+
+/// Writes a table of an Erigon database
+fn write_erigon_table(
+    env: &Environment<NoWriteMap>,
+    name: &str,
+    flags: DatabaseFlags,
+    entries: &[(Vec<u8>, Vec<u8>)],
+) {
+    let tx = env.begin_rw_txn().unwrap();
+    let table = tx.create_db(Some(name), flags).unwrap();
+    for (key, value) in entries {
+        tx.put(&table, key, value, WriteFlags::empty()).unwrap();
+    }
+    tx.commit().unwrap();
+}
+
+fn cbor(value: Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    ciborium::ser::into_writer(&value, &mut out).unwrap();
+    out
+}
+
+#[test]
+fn test_erigon_source() {
+    let dir = tempfile::tempdir().unwrap();
+    let chaindata = dir.path().join("chaindata");
+    fs::create_dir(&chaindata).unwrap();
+    let env = Environment::<NoWriteMap>::new().set_max_dbs(16).open(&chaindata).unwrap();
+
+    let blocks = blocks::read_blocks(BLOCKS_PATH).unwrap();
+    let (mut canonical, mut headers, mut bodies) = (Vec::new(), Vec::new(), Vec::new());
+    let (mut transactions, mut receipts, mut logs) = (Vec::new(), Vec::new(), Vec::new());
+    let mut next_tx_id = 0u64;
+    for (block, (header, body)) in blocks.iter().zip(headers_and_bodies()) {
+        let number = block.number.to_be_bytes().to_vec();
+        let key = [number.clone(), block.hash().as_bytes().to_vec()].concat();
+        canonical.push((number.clone(), block.hash().as_bytes().to_vec()));
+        headers.push((key.clone(), header));
+
+        // Transactions are stored as their envelope, between two system transactions
+        let body = rlp::Rlp::new(&body);
+        let mut stored = rlp::RlpStream::new_list(3);
+        stored.append(&next_tx_id).append(&(block.body.len() as u32 + 2));
+        stored.append_raw(body.at(1).unwrap().as_raw(), 1);
+        bodies.push((key, stored.out().to_vec()));
+        for transaction in body.at(0).unwrap().iter() {
+            next_tx_id += 1;
+            let envelope = match transaction.is_list() {
+                true => transaction.as_raw().to_vec(),
+                false => transaction.data().unwrap().to_vec(),
+            };
+            transactions.push((next_tx_id.to_be_bytes().to_vec(), envelope));
+        }
+        next_tx_id += 2;
+
+        let block_receipts = (0..block.body.len() as u64)
+            .map(|index| {
+                let cumulative_gas_used = Value::Integer((21000 * (index + 1)).into());
+                let status = Value::Integer(1.into());
+                Value::Array(vec![
+                    Value::Integer(0.into()),
+                    Value::Null,
+                    status,
+                    cumulative_gas_used,
+                ])
+            })
+            .collect();
+        receipts.push((number.clone(), cbor(Value::Array(block_receipts))));
+        if !block.body.is_empty() {
+            let log = Value::Array(vec![
+                Value::Bytes(vec![0x11; 20]),
+                Value::Array(vec![Value::Bytes(vec![0x22; 32])]),
+                Value::Bytes(vec![1, 2, 3]),
+            ]);
+            logs.push(([number, vec![0; 4]].concat(), cbor(Value::Array(vec![log]))));
+        }
+    }
+    write_erigon_table(&env, erigon::CANONICAL_HEADERS, DatabaseFlags::empty(), &canonical);
+    write_erigon_table(&env, erigon::HEADERS, DatabaseFlags::empty(), &headers);
+    write_erigon_table(&env, erigon::BLOCK_BODIES, DatabaseFlags::empty(), &bodies);
+    write_erigon_table(&env, erigon::TRANSACTIONS, DatabaseFlags::empty(), &transactions);
+    write_erigon_table(&env, erigon::RECEIPTS, DatabaseFlags::empty(), &receipts);
+    write_erigon_table(&env, erigon::LOGS, DatabaseFlags::empty(), &logs);
+
+    // A contract of its second incarnation, with a stale slot of the first one, and an account
+    let contract = vec![0xaa; 20];
+    let code_hash = vec![0xcc; 32];
+    let slot = |incarnation: u64, slot: u8, value: &[u8]| {
+        let key = [contract.clone(), incarnation.to_be_bytes().to_vec()].concat();
+        (key, [vec![0; 31], vec![slot], value.to_vec()].concat())
+    };
+    let state = vec![
+        (contract.clone(), [&[0x0f, 1, 1, 1, 2, 1, 2, 32][..], &code_hash].concat()),
+        slot(1, 1, &[0xff]),
+        slot(2, 2, &[0x01, 0x00]),
+        (vec![0xbb; 20], vec![0x02, 2, 0x01, 0x00]),
+    ];
+    write_erigon_table(&env, erigon::PLAIN_STATE, DatabaseFlags::DUP_SORT, &state);
+    write_erigon_table(&env, erigon::CODE, DatabaseFlags::empty(), &[(code_hash, vec![0x60, 0])]);
+    drop(env);
+
+    let mut source = ErigonSource::new(dir.path());
+    assert_eq!(blocks, read(&mut source).unwrap());
+
+    let args = ImportArgs::default();
+    let progress = ImportProgress::default();
+    let mut ctx = SourceContext {
+        args: &args,
+        progress: &progress,
+        limiter: None,
+        dead_letter: None,
+        validator: None,
+    };
+    let receipts = source.read_receipts(&mut ctx).unwrap();
+    let with_transactions = blocks.iter().flat_map(|block| &block.body).collect::<Vec<_>>();
+    assert_eq!(receipts.len(), with_transactions.len());
+    for (receipt, transaction) in receipts.iter().zip(with_transactions) {
+        assert_eq!(receipt.tx_hash.0, transaction.hash().0);
+        assert_eq!(receipt.status, 1);
+        assert_eq!(receipt.gas_used, 21000);
+        let logs = receipt.decode_logs().unwrap();
+        assert_eq!(logs.len(), (receipt.transaction_index == 0) as usize);
+        assert_eq!(receipt.bloom != vec![0; 256], !logs.is_empty());
+    }
+
+    let state = source.read_state(&mut ctx).unwrap();
+    assert_eq!(state.len(), 2);
+    let contract = &state[&Address::from_slice(&contract)];
+    assert_eq!(contract.nonce, Some(1));
+    assert_eq!(contract.balance, U256::from(2));
+    assert_eq!(contract.code.as_deref(), Some("0x6000"));
+    let storage = contract.storage.as_ref().unwrap();
+    assert_eq!(storage.len(), 1);
+    assert_eq!(storage[&H256::from_low_u64_be(2)], U256::from(0x100));
+    let account = &state[&Address::repeat_byte(0xbb)];
+    assert_eq!((account.nonce, account.balance), (Some(0), U256::from(0x100)));
+    assert_eq!(account.code_hash, None);
+}
+
+#[test]
+fn test_parse_block_range() {
+    assert_eq!(parse_block_range("1..10"), Ok(1..=10));
+    assert_eq!(parse_block_range("5..=5"), Ok(5..=5));
+    assert!(parse_block_range("10..1").is_err());
+    assert!(parse_block_range("10").is_err());
+    assert!(parse_block_range("a..b").is_err());
+}
+
+#[test]
+fn test_era1_source() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut archive = vec![0x65, 0x32, 0, 0, 0, 0, 0, 0];
+    for (header, body) in headers_and_bodies() {
+        write_era1_entry(&mut archive, COMPRESSED_HEADER, &header);
+        write_era1_entry(&mut archive, COMPRESSED_BODY, &body);
+        // Receipts are skipped
+        write_era1_entry(&mut archive, [0x05, 0x00], &[0xc0]);
+    }
+    let path = dir.path().join("op-goerli-00000-00000000.era1");
+    fs::write(&path, archive).unwrap();
+
+    let expected = blocks::read_blocks(BLOCKS_PATH).unwrap();
+    assert_eq!(expected, read(&mut Era1Source::new(&path)).unwrap());
+    assert_eq!(expected, read(&mut Era1Source::new(dir.path())).unwrap());
+}
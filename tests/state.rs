@@ -1,82 +1,500 @@
-use std::{path::PathBuf, str::FromStr};
+use std::str::FromStr;
 
-use reth_db::{database::Database, tables, transaction::DbTx};
+use reth_db::{
+    cursor::DbDupCursorRO,
+    database::Database,
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
 use reth_primitives::*;
 
-use op_reth::cli::{db, state::*};
+use op_reth::cli::{
+    args::ImportArgs,
+    db,
+    dead_letter::{DeadLetterFile, ImportError},
+    genesis::Genesis,
+    state::*,
+};
 
-const TEMP_DB_DIR: &str = "temp-state";
-const STATE_PATH: &str = "data/alloc_everything_4061224_final.json";
+const STATE_PATH: &str = "tests/fixtures/state.json";
+const GENESIS_PATH: &str = "tests/fixtures/genesis.json";
+
+/// The code of the sequencer fee vault predeploy
+const VAULT_CODE: &str = "0x6080604052600436106100385760003560e01c80633ccfd60b14610044578063d3e5792b1461005b578063d4ff92181461008a57600080fd5b3661003f57005b600080fd5b34801561005057600080fd5b506100596100dc565b005b34801561006757600080fd5b5061007767d02ab486cedc000081565b6040519081526020015b60405180910390f35b34801561009657600080fd5b506000546100b79073ffffffffffffffffffffffffffffffffffffffff1681565b60405173ffffffffffffffffffffffffffffffffffffffff9091168152602001610081565b67d02ab486cedc000047101561019e576040517f08c379a000000000000000000000000000000000000000000000000000000000815260206004820152605760248201527f4f564d5f53657175656e6365724665655661756c743a2077697468647261776160448201527f6c20616d6f756e74206d7573742062652067726561746572207468616e206d6960648201527f6e696d756d207769746864726177616c20616d6f756e74000000000000000000608482015260a40160405180910390fd5b600080546040805160208101825283815290517fa3a795480000000000000000000000000000000000000000000000000000000081527342000000000000000000000000000000000000109363a3a79548936102309373deaddeaddeaddeaddeaddeaddeaddeaddead00009373ffffffffffffffffffffffffffffffffffffffff909216924792909190600401610264565b600060405180830381600087803b15801561024a57600080fd5b505af115801561025e573d6000803e3d6000fd5b50505050565b600073ffffffffffffffffffffffffffffffffffffffff808816835260208188168185015286604085015263ffffffff8616606085015260a06080850152845191508160a085015260005b828110156102cb5785810182015185820160c0015281016102af565b828111156102dd57600060c084870101525b5050601f017fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe0169190910160c001969550505050505056fea2646970667358221220387a6116dde263ea48767352a397053c8cffa776aecb43cded2f25a4a9cfbdc264736f6c63430008090033";
 
 #[test]
 fn test_state_from_file() {
     let state = from_file(STATE_PATH).unwrap();
-    let account = H160::from_str("0x4200000000000000000000000000000000000000").unwrap();
+    let account = H160::from_str("0x4200000000000000000000000000000000000011").unwrap();
     let exported_account = state.get(&account).unwrap();
     assert_eq!(U256::from(0), exported_account.balance);
     assert_eq!(
         Some(
-            H256::from_str("0x1f958654ab06a152993e7a0ae7b6dbb0d4b19265cc9337b8789fe1353bd9dc35")
+            H256::from_str("0x8b846c7bbf2a0a4e6d36d5b9fd759f8fd1d2887a1b6732460e86436c8dcefc4d")
                 .unwrap()
         ),
         exported_account.code_hash
     );
-    assert_eq!(
-        Some("60806040526004361061005e5760003560e01c80635c60da1b116100435780635c60da1b146100be5780638f283970146100f8578063f851a440146101185761006d565b80633659cfe6146100755780634f1ef286146100955761006d565b3661006d5761006b61012d565b005b61006b61012d565b34801561008157600080fd5b5061006b6100903660046106d9565b610224565b6100a86100a33660046106f4565b610296565b6040516100b59190610777565b60405180910390f35b3480156100ca57600080fd5b506100d3610419565b60405173ffffffffffffffffffffffffffffffffffffffff90911681526020016100b5565b34801561010457600080fd5b5061006b6101133660046106d9565b6104b0565b34801561012457600080fd5b506100d3610517565b60006101577f360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc5490565b905073ffffffffffffffffffffffffffffffffffffffff8116610201576040517f08c379a000000000000000000000000000000000000000000000000000000000815260206004820152602560248201527f50726f78793a20696d706c656d656e746174696f6e206e6f7420696e6974696160448201527f6c697a656400000000000000000000000000000000000000000000000000000060648201526084015b60405180910390fd5b3660008037600080366000845af43d6000803e8061021e573d6000fd5b503d6000f35b7fb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d61035473ffffffffffffffffffffffffffffffffffffffff163373ffffffffffffffffffffffffffffffffffffffff16148061027d575033155b1561028e5761028b816105a3565b50565b61028b61012d565b60606102c07fb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d61035490565b73ffffffffffffffffffffffffffffffffffffffff163373ffffffffffffffffffffffffffffffffffffffff1614806102f7575033155b1561040a57610305846105a3565b6000808573ffffffffffffffffffffffffffffffffffffffff16858560405161032f9291906107ea565b600060405180830381855af49150503d806000811461036a576040519150601f19603f3d011682016040523d82523d6000602084013e61036f565b606091505b509150915081610401576040517f08c379a000000000000000000000000000000000000000000000000000000000815260206004820152603960248201527f50726f78793a2064656c656761746563616c6c20746f206e657720696d706c6560448201527f6d656e746174696f6e20636f6e7472616374206661696c65640000000000000060648201526084016101f8565b91506104129050565b61041261012d565b9392505050565b60006104437fb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d61035490565b73ffffffffffffffffffffffffffffffffffffffff163373ffffffffffffffffffffffffffffffffffffffff16148061047a575033155b156104a557507f360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc5490565b6104ad61012d565b90565b7fb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d61035473ffffffffffffffffffffffffffffffffffffffff163373ffffffffffffffffffffffffffffffffffffffff161480610509575033155b1561028e5761028b8161060b565b60006105417fb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d61035490565b73ffffffffffffffffffffffffffffffffffffffff163373ffffffffffffffffffffffffffffffffffffffff161480610578575033155b156104a557507fb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d61035490565b7f360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc81905560405173ffffffffffffffffffffffffffffffffffffffff8216907fbc7cd75a20ee27fd9adebab32041f755214dbc6bffa90cc0225b39da2e5c2d3b90600090a250565b60006106357fb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d61035490565b7fb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d61038390556040805173ffffffffffffffffffffffffffffffffffffffff8084168252851660208201529192507f7e644d79422f17c01e4894b5f4f588d331ebfa28653d42ae832dc59e38c9798f910160405180910390a15050565b803573ffffffffffffffffffffffffffffffffffffffff811681146106d457600080fd5b919050565b6000602082840312156106eb57600080fd5b610412826106b0565b60008060006040848603121561070957600080fd5b610712846106b0565b9250602084013567ffffffffffffffff8082111561072f57600080fd5b818601915086601f83011261074357600080fd5b81358181111561075257600080fd5b87602082850101111561076457600080fd5b6020830194508093505050509250925092565b600060208083528351808285015260005b818110156107a457858101830151858201604001528201610788565b818111156107b6576000604083870101525b50601f017fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe016929092016040019392505050565b818382376000910190815291905056fea164736f6c634300080f000a".to_string()),
-        exported_account.code
-    );
-    assert_eq!(Some(0), exported_account.nonce);
+    assert_eq!(Some(VAULT_CODE.trim_start_matches("0x").to_string()), exported_account.code);
+    assert_eq!(None, exported_account.nonce);
     assert_eq!(
         Some(
-            H256::from_str("0xeb5ebb45ba1ff8cbbb517a45e8328a65a32c0397ecf2e8ecec2547b32290ca7b")
+            H256::from_str("0x6309e95d2fbe5885d83546bbcf48680beb54056b003f91fa349e3377b7118668")
                 .unwrap()
         ),
         exported_account.root
     );
-    assert_eq!(12602, exported_account.storage.as_ref().unwrap().len());
-    assert_eq!(127608, state.len());
+    assert_eq!(1, exported_account.storage.as_ref().unwrap().len());
+    assert_eq!(2, state.len());
+
+    // The fixture is the genesis alloc in the layout of a state export
+    let genesis = Genesis::from_file(GENESIS_PATH).unwrap();
+    assert_eq!(
+        serde_json::to_value(genesis.exported_state()).unwrap(),
+        serde_json::to_value(&state).unwrap()
+    );
+    assert_eq!(genesis.state_root().unwrap(), state_root_hash(&state).unwrap());
+}
+
+#[test]
+fn test_state_order() {
+    let state: State = serde_json::from_str(
+        r#"{
+            "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f": { "balance": "0x1" },
+            "0x0000000000000000000000000000000000000001": { "balance": "0x2" },
+            "0x4200000000000000000000000000000000000011": {
+                "balance": "0x3",
+                "storage": {
+                    "0x0000000000000000000000000000000000000000000000000000000000000002": "0x1",
+                    "0x0000000000000000000000000000000000000000000000000000000000000001": "0x2"
+                }
+            }
+        }"#,
+    )
+    .unwrap();
+
+    // Accounts and storage slots iterate and serialize in ascending order
+    let balances = state.values().map(|account| account.balance).collect::<Vec<_>>();
+    assert_eq!(vec![U256::from(2), U256::from(3), U256::from(1)], balances);
+    let account = H160::from_str("0x4200000000000000000000000000000000000011").unwrap();
+    let slots = state[&account].storage.as_ref().unwrap().values().copied().collect::<Vec<_>>();
+    assert_eq!(vec![U256::from(2), U256::from(1)], slots);
+    let json = serde_json::to_string(&state).unwrap();
+    assert_eq!(
+        json,
+        serde_json::to_string(&serde_json::from_str::<State>(&json).unwrap()).unwrap()
+    );
+    assert!(json.find("0x0000000000000000000000000000000000000001") < json.find("0x4200"));
 }
 
 #[tokio::test]
 async fn test_read_write_state() {
-    let db_path = PathBuf::from(TEMP_DB_DIR);
-    let mut db = db::open_rw_env(db_path.as_path()).unwrap();
-    apply(&mut db, Some(STATE_PATH)).await.unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    apply(&mut db, Some(STATE_PATH), &ImportArgs::default()).await.unwrap();
 
-    // Read account from genesis state
+    // Read accounts from the imported state
     let tx = db.tx().unwrap();
-    let address = H160::from_str("0x4200000000000000000000000000000000000000").unwrap();
+    let address = H160::from_str("0x4200000000000000000000000000000000000011").unwrap();
     let account = tx.get::<tables::PlainAccountState>(address).unwrap();
     assert_eq!(
         Some(
-            H256::from_str("0x1f958654ab06a152993e7a0ae7b6dbb0d4b19265cc9337b8789fe1353bd9dc35")
+            H256::from_str("0x8b846c7bbf2a0a4e6d36d5b9fd759f8fd1d2887a1b6732460e86436c8dcefc4d")
                 .unwrap()
         ),
         account.unwrap().bytecode_hash
     );
     assert_eq!(U256::ZERO, account.unwrap().balance);
     assert_eq!(0, account.unwrap().nonce);
+    let funded = H160::from_str("0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f").unwrap();
+    let account = tx.get::<tables::PlainAccountState>(funded).unwrap().unwrap();
+    assert_eq!(U256::from(1_000_000_000_000_000_000u64), account.balance);
+    assert_eq!(None, account.bytecode_hash);
 
     // Test storage
-    let tx = db.tx().unwrap();
-    let address = H160::from_str("0x4200000000000000000000000000000000000011").unwrap();
     let storage_key =
-        H256::from_str("0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc")
+        H256::from_str("0x0000000000000000000000000000000000000000000000000000000000000000")
             .unwrap();
     let storage_value =
-        U256::from_str("0x000000000000000000000000c0d3c0d3c0d3c0d3c0d3c0d3c0d3c0d3c0d30011")
+        U256::from_str("0x000000000000000000000000fd1d2e729ae8eee2e146c033bf4400fe75284301")
             .unwrap();
     let storage_entry = tx.get::<tables::PlainStorageState>(address).unwrap().unwrap();
     assert_eq!(StorageEntry { key: storage_key, value: storage_value }, storage_entry);
 
-    // Read bytecode from genesis state
-    let tx = db.tx().unwrap();
-    let code = "0x6080604052600436106100385760003560e01c80633ccfd60b14610044578063d3e5792b1461005b578063d4ff92181461008a57600080fd5b3661003f57005b600080fd5b34801561005057600080fd5b506100596100dc565b005b34801561006757600080fd5b5061007767d02ab486cedc000081565b6040519081526020015b60405180910390f35b34801561009657600080fd5b506000546100b79073ffffffffffffffffffffffffffffffffffffffff1681565b60405173ffffffffffffffffffffffffffffffffffffffff9091168152602001610081565b67d02ab486cedc000047101561019e576040517f08c379a000000000000000000000000000000000000000000000000000000000815260206004820152605760248201527f4f564d5f53657175656e6365724665655661756c743a2077697468647261776160448201527f6c20616d6f756e74206d7573742062652067726561746572207468616e206d6960648201527f6e696d756d207769746864726177616c20616d6f756e74000000000000000000608482015260a40160405180910390fd5b600080546040805160208101825283815290517fa3a795480000000000000000000000000000000000000000000000000000000081527342000000000000000000000000000000000000109363a3a79548936102309373deaddeaddeaddeaddeaddeaddeaddeaddead00009373ffffffffffffffffffffffffffffffffffffffff909216924792909190600401610264565b600060405180830381600087803b15801561024a57600080fd5b505af115801561025e573d6000803e3d6000fd5b50505050565b600073ffffffffffffffffffffffffffffffffffffffff808816835260208188168185015286604085015263ffffffff8616606085015260a06080850152845191508160a085015260005b828110156102cb5785810182015185820160c0015281016102af565b828111156102dd57600060c084870101525b5050601f017fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe0169190910160c001969550505050505056fea2646970667358221220387a6116dde263ea48767352a397053c8cffa776aecb43cded2f25a4a9cfbdc264736f6c63430008090033";
-    let code_bytes = Bytes::from_str(code).unwrap();
+    // Read bytecode from the imported state
+    let code_bytes = Bytes::from_str(VAULT_CODE).unwrap();
     let code_hash = keccak256(code_bytes.clone());
     let code = tx.get::<tables::Bytecodes>(code_hash).unwrap().unwrap();
     assert_eq!(code_bytes.to_vec(), code);
+}
+
+#[tokio::test]
+async fn test_dead_letter_accounts() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    let valid = H160::from_low_u64_be(1);
+    let invalid = H160::from_low_u64_be(2);
+    let hash = keccak256([0x60, 0x00]);
+    let account =
+        |code: &str| format!(r#"{{"balance":"0x2","codeHash":"{hash:?}","code":"{code}"}}"#);
+    let state = dir.path().join("state.json");
+    let contents =
+        format!(r#"{{"{valid:?}":{{"balance":"0x1"}},"{invalid:?}":{}}}"#, account("0xzz"));
+    std::fs::write(&state, contents).unwrap();
+
+    // Lenient imports write accounts with invalid code to the dead-letter file
+    let dead_letter_path = dir.path().join("dead-letter.jsonl");
+    let args = ImportArgs { dead_letter: Some(dead_letter_path.clone()), ..Default::default() };
+    apply(&mut db, Some(&state.display().to_string()), &args).await.unwrap();
+    assert!(db.tx().unwrap().get::<tables::PlainAccountState>(valid).unwrap().is_some());
+    assert_eq!(None, db.tx().unwrap().get::<tables::PlainAccountState>(invalid).unwrap());
+    let letters = DeadLetterFile::read(&dead_letter_path).unwrap();
+    assert_eq!(
+        vec![("account", 1)],
+        letters.iter().map(|l| (l.kind.as_str(), l.index)).collect::<Vec<_>>()
+    );
+
+    // Reprocessing the file fails again until the record is fixed
+    let args = ImportArgs { from_dead_letter: Some(dead_letter_path), ..Default::default() };
+    let err = apply(&mut db, None, &args).await.unwrap_err();
+    assert!(format!("{err:#}").contains("not valid hex"), "{err:#}");
+
+    let fixed_path = dir.path().join("fixed.jsonl");
+    let mut fixed = DeadLetterFile::create(&fixed_path).unwrap();
+    let raw = format!(r#"{{"{invalid:?}":{}}}"#, account("0x6000"));
+    fixed.write(&ImportError::new("account", 1, &letters[0].error), raw.as_bytes()).unwrap();
+    fixed.finish().unwrap();
+    let args = ImportArgs { from_dead_letter: Some(fixed_path), ..Default::default() };
+    apply(&mut db, None, &args).await.unwrap();
+    let account = db.tx().unwrap().get::<tables::PlainAccountState>(invalid).unwrap().unwrap();
+    assert_eq!((U256::from(2), Some(hash)), (account.balance, account.bytecode_hash));
+    assert_eq!(Some(vec![0x60, 0x00]), db.tx().unwrap().get::<tables::Bytecodes>(hash).unwrap());
+}
+
+#[test]
+fn test_write_storage() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = db::open_rw_env(dir.path()).unwrap();
+    let address = H160::from_str("0x4200000000000000000000000000000000000011").unwrap();
+    let slots = |values: &[u64]| {
+        values
+            .iter()
+            .map(|value| (H256::from_low_u64_be(*value), U256::from(*value)))
+            .collect::<std::collections::BTreeMap<_, _>>()
+    };
+    let stored = || {
+        let tx = db.tx().unwrap();
+        let mut cursor = tx.cursor_dup_read::<tables::PlainStorageState>().unwrap();
+        let mut entries = Vec::new();
+        let mut entry = cursor.seek_by_key_subkey(address, H256::zero()).unwrap();
+        while let Some(storage_entry) = entry {
+            entries.push(storage_entry.value);
+            entry = cursor.next_dup_val().unwrap();
+        }
+        entries
+    };
+
+    // The slots are written in ascending order and a second write replaces them
+    for (values, expected) in [(&[3, 1, 2][..], &[1, 2, 3][..]), (&[5, 4], &[4, 5])] {
+        let tx = db.tx_mut().unwrap();
+        let mut cursor = tx.cursor_dup_write::<tables::PlainStorageState>().unwrap();
+        write_storage(&mut cursor, address, &slots(values)).unwrap();
+        drop(cursor);
+        tx.commit().unwrap();
+        assert_eq!(expected.iter().copied().map(U256::from).collect::<Vec<_>>(), stored());
+    }
+}
+
+#[test]
+fn test_state_formats() {
+    use op_reth::cli::state::formats::{decode_state, detect_state_format, StateFormat};
+
+    let vault = H160::from_str("0x4200000000000000000000000000000000000011").unwrap();
+    let user = H160::from_str("0x00000000000000000000000000000000000000aa").unwrap();
+    let slot = H256::from_low_u64_be(1);
+    let code_hash = "0x8b846c7bbf2a0a4e6d36d5b9fd759f8fd1d2887a1b6732460e86436c8dcefc4d";
+    let empty_root = "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421";
+
+    let geth_dump = format!(
+        r#"{{
+    "root": "{empty_root}",
+    "accounts": {{
+        "{vault:?}": {{
+            "balance": "1000000000000000000",
+            "nonce": 1,
+            "root": "{empty_root}",
+            "codeHash": "{code_hash}",
+            "code": "0x6080",
+            "storage": {{ "{slot:?}": "2a" }},
+            "address": "{vault:?}",
+            "key": "0x3f15fe2e2f7f4cf3e2d6cd6c1b1e1f3fe74ad6c0a2b5e7c1c4f2f3a5b5d6e7f8"
+        }},
+        "{user:?}": {{
+            "balance": "5",
+            "nonce": 0,
+            "root": "{empty_root}",
+            "codeHash": "{KECCAK_EMPTY:?}"
+        }}
+    }}
+}}"#
+    );
+    let geth_dump_lines = format!(
+        "{{\"root\":\"{empty_root}\"}}\n\
+         {{\"balance\":\"1000000000000000000\",\"nonce\":1,\"codeHash\":\"{code_hash}\",\
+         \"code\":\"0x6080\",\"storage\":{{\"{slot:?}\":\"2a\"}},\"address\":\"{vault:?}\"}}\n\
+         {{\"balance\":\"5\",\"nonce\":0,\"codeHash\":\"{KECCAK_EMPTY:?}\",\
+         \"address\":\"{user:?}\"}}\n"
+    );
+    let erigon_csv = format!(
+        "address,nonce,balance,code_hash,code,storage_key,storage_value\n\
+         {vault:?},1,0xde0b6b3a7640000,{code_hash},6080,,\n\
+         {vault:?},,,,,{slot:?},0x2a\n\
+         {user:?},0,5,,,,\n"
+    );
+
+    for (format, contents) in [
+        (StateFormat::GethDump, geth_dump),
+        (StateFormat::GethDumpLines, geth_dump_lines),
+        (StateFormat::ErigonCsv, erigon_csv),
+    ] {
+        assert_eq!(detect_state_format(contents.as_bytes()), Some(format));
+        let state = decode_state(contents.as_bytes()).unwrap();
+        assert_eq!(state.len(), 2, "{format:?}");
+
+        let account = &state[&vault];
+        assert_eq!(account.balance, U256::from(1_000_000_000_000_000_000u64), "{format:?}");
+        assert_eq!(account.nonce, Some(1), "{format:?}");
+        assert_eq!(account.code_hash, Some(H256::from_str(code_hash).unwrap()), "{format:?}");
+        assert_eq!(account.code.as_deref(), Some("6080"), "{format:?}");
+        assert_eq!(account.storage.as_ref().unwrap()[&slot], U256::from(42), "{format:?}");
+
+        // Accounts without code have no code hash
+        let account = &state[&user];
+        assert_eq!(account.balance, U256::from(5), "{format:?}");
+        assert_eq!(account.code_hash, None, "{format:?}");
+        assert_eq!(account.storage, None, "{format:?}");
+    }
+
+    assert_eq!(detect_state_format(&std::fs::read(STATE_PATH).unwrap()), Some(StateFormat::Alloc));
+
+    // Accounts dumped without their address preimage are refused
+    let missing = format!("{{\"root\":\"{empty_root}\"}}\n{{\"balance\":\"1\",\"nonce\":0}}\n");
+    assert!(decode_state(missing.as_bytes()).is_err());
+}
+
+#[tokio::test]
+async fn test_state_diff() {
+    use op_reth::cli::state::{
+        diff::{diff_state, AccountDiff},
+        formats::stream_state,
+    };
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    apply(&mut db, Some(STATE_PATH), &ImportArgs::default()).await.unwrap();
+    let diff = |limit| {
+        let tx = db.tx().unwrap();
+        let open = || std::fs::File::open(STATE_PATH).unwrap();
+        diff_state(&tx, |visit| stream_state(open(), visit), limit).unwrap()
+    };
+
+    // The imported state matches its export
+    let clean = diff(10);
+    assert!(clean.is_empty());
+    assert_eq!(clean.summary.snapshot_accounts, 2);
+    assert_eq!(clean.summary.database_accounts, 2);
+
+    let vault = H160::from_str("0x4200000000000000000000000000000000000011").unwrap();
+    let funded = H160::from_str("0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f").unwrap();
+    let extra = H160::from_low_u64_be(1);
+    let slot = H256::from_low_u64_be(7);
+    let tx = db.tx_mut().unwrap();
+    let mut account = tx.get::<tables::PlainAccountState>(vault).unwrap().unwrap();
+    account.balance = U256::from(5);
+    tx.put::<tables::PlainAccountState>(vault, account).unwrap();
+    tx.put::<tables::PlainStorageState>(vault, StorageEntry { key: slot, value: U256::from(1) })
+        .unwrap();
+    tx.delete::<tables::PlainAccountState>(funded, None).unwrap();
+    tx.put::<tables::PlainAccountState>(extra, Account::default()).unwrap();
+    tx.commit().unwrap();
+
+    let diff = diff(10);
+    assert_eq!(diff.summary.differing_accounts, 3);
+    assert_eq!(diff.summary.balance, 1);
+    assert_eq!(diff.summary.storage, 1);
+    assert_eq!(diff.summary.missing_in_database, 1);
+    assert_eq!(diff.summary.missing_in_snapshot, 1);
+    let diffs = diff.diffs.iter().map(|entry| (entry.address, &entry.diff)).collect::<Vec<_>>();
+    assert!(diffs
+        .contains(&(vault, &AccountDiff::Balance { expected: U256::ZERO, found: U256::from(5) })));
+    assert!(diffs.contains(&(
+        vault,
+        &AccountDiff::Storage { slot, expected: U256::ZERO, found: U256::from(1) }
+    )));
+    assert!(diffs.contains(&(funded, &AccountDiff::MissingInDatabase)));
+    assert!(diffs.contains(&(extra, &AccountDiff::MissingInSnapshot)));
+
+    // All differences are counted, but only the first are listed
+    let truncated = diff(1);
+    assert_eq!(truncated.summary, diff.summary);
+    assert_eq!(truncated.diffs.len(), 1);
+    assert!(truncated.truncated);
+}
+
+#[tokio::test]
+async fn test_state_root() {
+    use op_reth::cli::state::formats::stream_state;
+
+    let expected = Genesis::from_file(GENESIS_PATH).unwrap().state_root().unwrap();
+    let open = || std::fs::File::open(STATE_PATH).unwrap();
+    let (root, accounts) = stream_state_root(|visit| stream_state(open(), visit)).unwrap();
+    assert_eq!((root, accounts), (expected, 2));
+
+    // Missing storage roots are computed from the storage
+    let mut state = from_file(STATE_PATH).unwrap();
+    state.values_mut().for_each(|account| account.root = None);
+    let json = serde_json::to_vec(&state).unwrap();
+    let (root, _) = stream_state_root(|visit| stream_state(json.as_slice(), visit)).unwrap();
+    assert_eq!(root, expected);
+
+    // The plain state of the database has the root of the export it was imported from
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    apply(&mut db, Some(STATE_PATH), &ImportArgs::default()).await.unwrap();
+    let tx = db.tx().unwrap();
+    let (root, accounts) = stream_state_root(|visit| stream_database_state(&tx, visit)).unwrap();
+    assert_eq!((root, accounts), (expected, 2));
+}
+
+#[tokio::test]
+async fn test_state_proof() {
+    use op_reth::cli::state::proof::{prove_account, verify_account_proof};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(dir.path()).unwrap();
+    apply(&mut db, Some(STATE_PATH), &ImportArgs::default()).await.unwrap();
+    let root = hash_and_trie(&db).unwrap();
+    assert_eq!(root, Genesis::from_file(GENESIS_PATH).unwrap().state_root().unwrap());
+
+    let tx = db.tx().unwrap();
+    let vault = H160::from_str("0x4200000000000000000000000000000000000011").unwrap();
+    let (set, unset) = (H256::zero(), H256::from_low_u64_be(1));
+    let proof = prove_account(&tx, root, vault, &[set, unset]).unwrap();
+    let exported = &from_file(STATE_PATH).unwrap()[&vault];
+    assert_eq!(proof.balance, exported.balance);
+    assert_eq!(Some(proof.code_hash), exported.code_hash);
+    assert_eq!(Some(proof.storage_hash), exported.root);
+    assert!(!proof.account_proof.is_empty());
+    assert_eq!(proof.storage_proof[0].value, exported.storage.as_ref().unwrap()[&set]);
+    assert_eq!(proof.storage_proof[1].value, U256::ZERO);
+    verify_account_proof(root, &proof).unwrap();
+
+    // A proof of another value or against another root is refused
+    let mut forged = proof.clone();
+    forged.balance = U256::from(1);
+    assert!(verify_account_proof(root, &forged).is_err());
+    let mut forged = proof.clone();
+    forged.storage_proof[1].value = U256::from(1);
+    assert!(verify_account_proof(root, &forged).is_err());
+    assert!(verify_account_proof(H256::from_low_u64_be(1), &proof).is_err());
+
+    // Missing accounts are proven absent
+    let missing = prove_account(&tx, root, H160::from_low_u64_be(1), &[]).unwrap();
+    assert_eq!((missing.balance, missing.nonce, missing.code_hash), (U256::ZERO, 0, KECCAK_EMPTY));
+    verify_account_proof(root, &missing).unwrap();
+
+    // The trie tables only hold the nodes of their own root
+    assert!(prove_account(&tx, H256::from_low_u64_be(1), vault, &[]).is_err());
+}
+
+#[tokio::test]
+async fn test_state_snapshot() {
+    use op_reth::cli::state::snapshot::{self, SnapshotManifest, SnapshotRecord};
+
+    let expected = Genesis::from_file(GENESIS_PATH).unwrap().state_root().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(&dir.path().join("source")).unwrap();
+    apply(&mut db, Some(STATE_PATH), &ImportArgs::default()).await.unwrap();
+
+    // Small chunks split the state into one chunk per record
+    let out = dir.path().join("snapshot");
+    let manifest = snapshot::create_snapshot(&db, &out, 1).unwrap();
+    assert_eq!((manifest.state_root, manifest.accounts, manifest.bytecodes), (expected, 2, 1));
+    assert_eq!(3, manifest.chunks.len());
+    assert_eq!(manifest, SnapshotManifest::read(&out).unwrap());
+    assert!(snapshot::create_snapshot(&db, &out, 1).is_err());
+
+    // The restored plain state has the root of the snapshot
+    let mut restored = db::open_rw_env(&dir.path().join("restored")).unwrap();
+    restored.create_tables().unwrap();
+    assert_eq!(manifest, snapshot::restore_snapshot(&restored, &out).unwrap());
+    let tx = restored.tx().unwrap();
+    let (root, accounts) = stream_state_root(|visit| stream_database_state(&tx, visit)).unwrap();
+    assert_eq!((root, accounts), (expected, 2));
+    let code_hash = keccak256(Bytes::from_str(VAULT_CODE).unwrap());
+    assert!(tx.get::<tables::Bytecodes>(code_hash).unwrap().is_some());
+    drop(tx);
+    // Restoring twice is refused
+    assert!(snapshot::restore_snapshot(&restored, &out).is_err());
+
+    // Corrupted chunks are refused before anything is written
+    let chunk = out.join(&manifest.chunks[0].file);
+    let mut data = std::fs::read(&chunk).unwrap();
+    *data.last_mut().unwrap() ^= 1;
+    std::fs::write(&chunk, data).unwrap();
+    let mut empty = db::open_rw_env(&dir.path().join("empty")).unwrap();
+    empty.create_tables().unwrap();
+    assert!(snapshot::restore_snapshot(&empty, &out).is_err());
+    assert!(empty
+        .tx()
+        .unwrap()
+        .get::<tables::PlainAccountState>(Address::zero())
+        .unwrap()
+        .is_none());
+
+    // Records round-trip through their encoding
+    let record = SnapshotRecord::Account {
+        address: Address::repeat_byte(1),
+        account: Account { nonce: 3, balance: U256::from(256), bytecode_hash: None },
+        storage: [(H256::repeat_byte(2), U256::ZERO), (H256::repeat_byte(3), U256::MAX)].into(),
+    };
+    let mut encoded = Vec::new();
+    record.encode(&mut encoded);
+    assert_eq!(vec![record], SnapshotRecord::decode_all(&encoded).unwrap());
+    assert!(SnapshotRecord::decode_all(&encoded[..encoded.len() - 1]).is_err());
+}
+
+#[tokio::test]
+async fn test_state_export() {
+    use op_reth::cli::state::export::export_state;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = db::open_rw_env(&dir.path().join("a")).unwrap();
+    apply(&mut db, Some(STATE_PATH), &ImportArgs::default()).await.unwrap();
+    let tx = db.tx().unwrap();
+    let mut alloc = Vec::<u8>::new();
+    let export = export_state(&tx, &[], None, &mut alloc).unwrap();
+    assert_eq!(2, export.accounts);
+
+    // The export imports into a database with the same state root, code included
+    let path = dir.path().join("alloc.json");
+    std::fs::write(&path, &alloc).unwrap();
+    let mut copy = db::open_rw_env(&dir.path().join("b")).unwrap();
+    apply(&mut copy, path.to_str(), &ImportArgs::default()).await.unwrap();
+    let copy_tx = copy.tx().unwrap();
+    assert_eq!(
+        stream_state_root(|visit| stream_database_state(&tx, visit)).unwrap(),
+        stream_state_root(|visit| stream_database_state(&copy_tx, visit)).unwrap()
+    );
+    let vault = H160::from_str("0x4200000000000000000000000000000000000011").unwrap();
+    let account = copy_tx.get::<tables::PlainAccountState>(vault).unwrap().unwrap();
+    assert!(copy_tx.get::<tables::Bytecodes>(account.bytecode_hash.unwrap()).unwrap().is_some());
 
-    // Clean up temp db
-    std::fs::remove_dir_all(db_path).unwrap();
+    // Only the given accounts are exported, and blocks after the state can't be exported
+    let mut alloc = Vec::<u8>::new();
+    let export = export_state(&tx, &[vault, H160::repeat_byte(0xee)], None, &mut alloc).unwrap();
+    assert_eq!(1, export.accounts);
+    let state: serde_json::Value = serde_json::from_slice(&alloc).unwrap();
+    assert_eq!(1, state.as_object().unwrap().len());
+    assert!(export_state(&tx, &[], Some(5), &mut Vec::<u8>::new()).is_err());
 }
@@ -0,0 +1,135 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use reth_db::{database::Database, tables, transaction::DbTx};
+use reth_primitives::{H160, H256, U256};
+
+use op_reth::cli::{
+    args::ImportArgs,
+    db,
+    state::{self, formats},
+};
+
+/// Tracks the heap memory of the test binary and its peak
+struct PeakAllocator;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(current, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAllocator = PeakAllocator;
+
+/// Resets the peak to the memory currently in use and returns it
+fn reset_peak() -> usize {
+    let current = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(current, Ordering::Relaxed);
+    current
+}
+
+/// The peak memory used on top of the memory in use when the peak was reset
+fn peak_since(baseline: usize) -> usize {
+    PEAK.load(Ordering::Relaxed) - baseline
+}
+
+/// Runs `f` and returns the peak memory it used
+fn peak_memory(f: impl FnOnce()) -> usize {
+    let baseline = reset_peak();
+    f();
+    peak_since(baseline)
+}
+
+const ACCOUNTS: u64 = 100_000;
+const SLOTS: u64 = 4;
+
+/// Writes a state dump in the alloc layout with [ACCOUNTS] accounts of [SLOTS] storage slots each
+fn write_state(path: &Path) {
+    let mut out = BufWriter::new(File::create(path).unwrap());
+    write!(out, "{{").unwrap();
+    for account in 0..ACCOUNTS {
+        let separator = if account == 0 { "" } else { "," };
+        let address = H160::from_low_u64_be(account + 1);
+        write!(out, r#"{separator}"{address:?}":{{"balance":"{account:#x}","storage":{{"#).unwrap();
+        for slot in 0..SLOTS {
+            let separator = if slot == 0 { "" } else { "," };
+            let key = H256::from_low_u64_be(slot);
+            write!(out, r#"{separator}"{key:?}":"{:#x}""#, account * SLOTS + slot).unwrap();
+        }
+        write!(out, "}}}}").unwrap();
+    }
+    write!(out, "}}").unwrap();
+}
+
+#[tokio::test]
+async fn test_stream_state_memory() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("alloc.json");
+    write_state(&path);
+    let size = std::fs::metadata(&path).unwrap().len() as usize;
+
+    // Decoding visits one account at a time, using a fraction of the size of the dump
+    let mut accounts = 0;
+    let peak = peak_memory(|| {
+        formats::stream_state(File::open(&path).unwrap(), &mut |_, account| {
+            assert_eq!(account.storage.map(|storage| storage.len()), Some(SLOTS as usize));
+            accounts += 1;
+            Ok(())
+        })
+        .unwrap()
+    });
+    assert_eq!(accounts, ACCOUNTS);
+    assert!(peak < 1024 * 1024, "decoding a {size} byte dump peaked at {peak} bytes");
+
+    // Collecting the dump holds every account, which takes more than the dump itself
+    let peak = peak_memory(|| assert_eq!(state::from_file(&path).unwrap().len() as u64, ACCOUNTS));
+    assert!(peak > size, "collecting a {size} byte dump peaked at {peak} bytes");
+
+    // Errors of the visitor stop the decoding
+    let mut visited = 0;
+    let err = formats::stream_state(File::open(&path).unwrap(), &mut |_, _| {
+        visited += 1;
+        if visited == 10 {
+            eyre::bail!("stop");
+        }
+        Ok(())
+    })
+    .unwrap_err();
+    assert_eq!(err.to_string(), "stop");
+
+    // The import holds one batch of accounts at a time
+    let db_dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(db_dir.path()).unwrap();
+    let args = ImportArgs { batch_size: 1000, quiet: true, ..Default::default() };
+    let path = path.display().to_string();
+    let baseline = reset_peak();
+    state::apply(&mut env, Some(&path), &args).await.unwrap();
+    let peak = peak_since(baseline);
+    assert!(peak < size / 4, "importing a {size} byte dump peaked at {peak} bytes");
+
+    let tx = env.tx().unwrap();
+    let last = H160::from_low_u64_be(ACCOUNTS);
+    let account = tx.get::<tables::PlainAccountState>(last).unwrap().unwrap();
+    assert_eq!(account.balance, U256::from(ACCOUNTS - 1));
+    let slot = tx.get::<tables::PlainStorageState>(last).unwrap().unwrap();
+    assert_eq!(slot.value, U256::from((ACCOUNTS - 1) * SLOTS));
+}
@@ -0,0 +1,18 @@
+use std::{io::Write, time::Instant};
+
+use op_reth::cli::throttle::{read_file, IoLimiter};
+
+#[test]
+fn test_read_file_is_throttled() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    let contents = vec![7u8; 200 * 1024];
+    file.write_all(&contents).unwrap();
+
+    let limiter = IoLimiter::new(1024 * 1024);
+    let start = Instant::now();
+    assert_eq!(contents, read_file(file.path(), Some(&limiter)).unwrap());
+    // 200 KiB at 1 MiB/s takes at least ~195ms
+    assert!(start.elapsed().as_millis() >= 150);
+
+    assert_eq!(contents, read_file(file.path(), None).unwrap());
+}
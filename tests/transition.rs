@@ -0,0 +1,60 @@
+use op_reth::cli::{
+    args::ImportArgs,
+    blocks, db,
+    db::head::ForkchoicePointers,
+    genesis, state,
+    transition::{self, BedrockTransition, ExpectedTransition, RollupConfig, TransitionMismatch},
+};
+use reth_primitives::H256;
+
+const BLOCKS_PATH: &str = "tests/fixtures/blocks.rlp";
+const GENESIS_PATH: &str = "tests/fixtures/genesis.json";
+
+#[tokio::test]
+async fn test_validate_transition() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut env, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut env, Some(BLOCKS_PATH), &args).await.unwrap();
+    let tip = blocks::read_blocks(BLOCKS_PATH).unwrap().pop().unwrap();
+
+    let config = format!(
+        r#"{{"genesis": {{"l2": {{"hash": "{:?}", "number": {}}}, "l2_time": {}}}}}"#,
+        tip.hash(),
+        tip.number,
+        tip.timestamp
+    );
+    let config_path = dir.path().join("rollup.json");
+    std::fs::write(&config_path, config).unwrap();
+    let expected =
+        ExpectedTransition::from_rollup_config(&RollupConfig::read(&config_path).unwrap());
+    assert_eq!(Some(tip.hash()), expected.hash);
+
+    // The state trie of the tip has to be computed
+    let (_, mismatches) = transition::check(&env, &expected).unwrap();
+    assert_eq!(vec![TransitionMismatch::IncompleteState { checkpoint: None }], mismatches);
+    assert!(transition::validate(&env, dir.path(), &expected).is_err());
+    assert_eq!(None, BedrockTransition::read(dir.path()).unwrap());
+    state::hash_and_trie(&env).unwrap();
+
+    let wrong = ExpectedTransition {
+        number: tip.number + 1,
+        hash: Some(H256::zero()),
+        state_root: Some(H256::zero()),
+        ..expected
+    };
+    let (_, mismatches) = transition::check(&env, &wrong).unwrap();
+    assert_eq!(3, mismatches.len());
+    assert!(
+        matches!(mismatches[0], TransitionMismatch::Number { actual, .. } if actual == tip.number)
+    );
+
+    let transition = transition::validate(&env, dir.path(), &expected).unwrap();
+    assert_eq!(tip.hash(), transition.block.hash);
+    assert_eq!(tip.state_root, transition.state_root);
+    assert_eq!(Some(transition), BedrockTransition::read(dir.path()).unwrap());
+    let pointers = ForkchoicePointers::read(dir.path()).unwrap();
+    assert_eq!(Some(transition.block), pointers.safe);
+    assert_eq!(Some(transition.block), pointers.finalized);
+}
@@ -0,0 +1,137 @@
+use std::str::FromStr;
+
+use reth_primitives::{rpc::H160, Header, SealedBlock, H256, U256};
+
+use op_reth::cli::{
+    blocks::{ErigonBlock, ErigonHeader, LegacyTx},
+    chain::{ChainPreset, L2_CROSS_DOMAIN_MESSENGER},
+    validate::{
+        check_continuity, check_timestamps, eip155_chain_id, report_continuity_issues,
+        report_timestamp_issues, BrokenContinuity, ContinuityIssue, SystemTxValidator,
+        TimestampIssue, ValidationIssue, MAX_FUTURE_DRIFT,
+    },
+};
+
+fn block(txs: Vec<LegacyTx>) -> ErigonBlock {
+    let header = Header { number: 1, ..Default::default() };
+    ErigonBlock {
+        header: ErigonHeader::from(&header),
+        txs: txs.into_iter().map(Into::into).collect(),
+        uncles: vec![],
+    }
+}
+
+fn tx(to: H160, v: u64, signed: bool) -> LegacyTx {
+    let signature = U256::from(signed as u64);
+    LegacyTx {
+        nonce: 0,
+        gas_price: 0,
+        gas: 21_000,
+        to: Some(to),
+        value: 0,
+        data: vec![],
+        v: U256::from(v),
+        r: signature,
+        s: signature,
+    }
+}
+
+#[test]
+fn test_eip155_chain_id() {
+    assert_eq!(None, eip155_chain_id(U256::from(27)));
+    assert_eq!(Some(10), eip155_chain_id(U256::from(55)));
+    assert_eq!(Some(420), eip155_chain_id(U256::from(876)));
+}
+
+#[test]
+fn test_validate_block() {
+    let messenger = H160::from_str(L2_CROSS_DOMAIN_MESSENGER).unwrap();
+    let mut validator = SystemTxValidator::new(ChainPreset::OpGoerli);
+    validator.validate_block(&block(vec![tx(messenger, 875, true), tx(messenger, 0, false)]));
+    assert!(validator.issues().is_empty());
+
+    // An unsigned message to another contract is only reported
+    validator.validate_block(&block(vec![tx(H160::repeat_byte(1), 0, false)]));
+    assert!(matches!(validator.issues()[0], ValidationIssue::UnexpectedMessageTarget { .. }));
+    validator.finish().unwrap();
+
+    // A transaction signed for OP Mainnet fails the import
+    let mut validator = SystemTxValidator::new(ChainPreset::OpGoerli);
+    validator.validate_block(&block(vec![tx(messenger, 55, true)]));
+    assert_eq!(
+        vec![ValidationIssue::ChainIdMismatch { block: 1, index: 0, chain_id: 10 }],
+        validator.issues()
+    );
+    assert!(validator.finish().is_err());
+}
+
+#[test]
+fn test_check_timestamps() {
+    let block = |number, timestamp| SealedBlock {
+        header: Header { number, timestamp, ..Default::default() }.seal_slow(),
+        body: vec![],
+        ommers: vec![],
+        withdrawals: None,
+    };
+    let now = 10_000;
+
+    // Equal timestamps are fine, gaps are allowed per block number
+    let blocks = [block(1, 100), block(2, 100), block(3, 160), block(5, 360)];
+    assert!(check_timestamps(&blocks, 100, now).is_empty());
+
+    let blocks = [block(1, 100), block(2, 90), block(3, 300), block(4, now + MAX_FUTURE_DRIFT + 1)];
+    let issues = check_timestamps(&blocks, 100, now);
+    assert_eq!(
+        vec![
+            TimestampIssue::Decreasing { block: 2, timestamp: 90, parent_timestamp: 100 },
+            TimestampIssue::Gap { block: 3, timestamp: 300, parent_timestamp: 90 },
+            TimestampIssue::Future { block: 4, timestamp: now + MAX_FUTURE_DRIFT + 1 },
+            TimestampIssue::Gap {
+                block: 4,
+                timestamp: now + MAX_FUTURE_DRIFT + 1,
+                parent_timestamp: 300
+            },
+        ],
+        issues
+    );
+    assert!(report_timestamp_issues(&issues, false).is_ok());
+    assert!(report_timestamp_issues(&issues, true).is_err());
+}
+
+#[test]
+fn test_check_continuity() {
+    let block = |number, parent_hash| SealedBlock {
+        header: Header { number, parent_hash, ..Default::default() }.seal_slow(),
+        body: vec![],
+        ommers: vec![],
+        withdrawals: None,
+    };
+    let parent = H256::repeat_byte(1);
+    let first = block(5, parent);
+    let second = block(6, first.hash());
+    assert!(check_continuity(Some((4, parent)), &[first.clone(), second.clone()]).is_empty());
+    // Without a parent the first block may follow anything
+    assert!(check_continuity(None, &[second.clone()]).is_empty());
+
+    let skipped = block(8, second.hash());
+    let unlinked = block(9, H256::repeat_byte(2));
+    let issues = check_continuity(
+        Some((3, parent)),
+        &[first.clone(), second.clone(), skipped.clone(), unlinked],
+    );
+    assert_eq!(
+        vec![
+            ContinuityIssue::Gap { block: 5, expected: 4 },
+            ContinuityIssue::Gap { block: 8, expected: 7 },
+            ContinuityIssue::BrokenLink {
+                block: 9,
+                parent_hash: H256::repeat_byte(2),
+                expected: skipped.hash()
+            },
+        ],
+        issues
+    );
+    assert!(report_continuity_issues(issues.clone(), true).is_ok());
+    let err = report_continuity_issues(issues.clone(), false).unwrap_err();
+    assert_eq!(issues, err.downcast::<BrokenContinuity>().unwrap().issues);
+}
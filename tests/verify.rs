@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+
+use reth_db::{
+    database::Database,
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::{keccak256, Account, Bytes, Log, H160, H256, MAINNET};
+
+use op_reth::cli::{
+    args::ImportArgs,
+    blocks, db,
+    deposit::DepositStore,
+    genesis,
+    l1_fee::L1FeeStore,
+    receipts, state,
+    verify::{self, BytecodeIssue, Mismatch, VerifyReport},
+};
+
+const BLOCKS_PATH: &str = "tests/fixtures/blocks.rlp";
+const GENESIS_PATH: &str = "tests/fixtures/genesis.json";
+const RECEIPTS_PATH: &str = "tests/fixtures/receipts.rlp";
+const STATE_PATH: &str = "tests/fixtures/state.json";
+
+#[test]
+fn test_partition() {
+    assert_eq!(vec![0..=3, 4..=6, 7..=9], verify::partition(0..=9, 3));
+    // There are never more parts than blocks
+    assert_eq!(vec![5..=5, 6..=6], verify::partition(5..=6, 4));
+    assert_eq!(vec![1..=10], verify::partition(1..=10, 0));
+    assert!(verify::partition(2..=1, 2).is_empty());
+}
+
+#[test]
+fn test_merge_reports() {
+    let mut report = VerifyReport { checked: 2, mismatches: vec![(7, Mismatch::Missing)] };
+    report.merge(VerifyReport { checked: 3, mismatches: vec![(2, Mismatch::Missing)] });
+    assert_eq!(5, report.checked);
+    assert_eq!(vec![2, 7], report.mismatches.iter().map(|(number, _)| *number).collect::<Vec<_>>());
+    assert!(!report.is_consistent());
+}
+
+#[test]
+fn test_bisect() {
+    let mut probes = 0;
+    let first = verify::bisect(0..=1_000_000, |number| {
+        probes += 1;
+        Ok(number < 123_456)
+    })
+    .unwrap();
+    assert_eq!(Some(123_456), first);
+    assert!(probes <= 21);
+
+    assert_eq!(None, verify::bisect(0..=10, |_| Ok(true)).unwrap());
+    assert_eq!(Some(5), verify::bisect(5..=10, |_| Ok(false)).unwrap());
+    assert_eq!(Some(10), verify::bisect(5..=10, |number| Ok(number < 10)).unwrap());
+    assert!(verify::bisect(0..=10, |_| eyre::bail!("unreachable")).is_err());
+}
+
+#[tokio::test]
+async fn test_verify() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut env, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut env, Some(BLOCKS_PATH), &args).await.unwrap();
+    let deposits = DepositStore::open(dir.path()).unwrap();
+
+    let mut reference: BTreeMap<u64, (H256, Vec<H256>)> = blocks::read_blocks(BLOCKS_PATH)
+        .unwrap()
+        .into_iter()
+        .map(|block| {
+            let transactions = block.body.iter().map(|tx| tx.hash()).collect();
+            (block.number, (block.hash(), transactions))
+        })
+        .collect();
+    let pool = [(), ()];
+    let fetch = |reference: &BTreeMap<u64, (H256, Vec<H256>)>| {
+        move |_: &(), number: u64| {
+            reference.get(&number).cloned().ok_or_else(|| eyre::eyre!("Unknown block"))
+        }
+    };
+
+    let report = verify::verify(&env, &deposits, 1..=2, 2, &pool, fetch(&reference)).unwrap();
+    assert_eq!(VerifyReport { checked: 2, mismatches: vec![] }, report);
+
+    // Blocks missing from the database and differing blocks are reported together
+    let hash = reference[&1].0;
+    let tip = reference[&2].clone();
+    reference.insert(3, tip);
+    reference.get_mut(&1).unwrap().0 = H256::repeat_byte(1);
+    let report = verify::verify(&env, &deposits, 1..=3, 3, &pool, fetch(&reference)).unwrap();
+    assert_eq!(3, report.checked);
+    assert_eq!(
+        vec![
+            (1, Mismatch::Hash { expected: H256::repeat_byte(1), found: hash }),
+            (3, Mismatch::Missing)
+        ],
+        report.mismatches
+    );
+
+    // A failing reference fails the verification
+    assert!(verify::verify(&env, &deposits, 1..=4, 2, &pool, fetch(&reference)).is_err());
+}
+
+#[tokio::test]
+async fn test_verify_bytecodes() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(dir.path()).unwrap();
+    state::apply(&mut env, Some(STATE_PATH), &ImportArgs::default()).await.unwrap();
+    let report = verify::verify_bytecodes(&env.tx().unwrap()).unwrap();
+    assert!(report.is_consistent(), "{report:?}");
+    assert_eq!((report.bytecodes, report.accounts), (1, 1));
+
+    // A code stored under the wrong hash and an account without its code are reported
+    let (corrupted, missing) = (H256::repeat_byte(1), H256::repeat_byte(2));
+    let tx = env.tx_mut().unwrap();
+    tx.put::<tables::Bytecodes>(corrupted, vec![0x60, 0x00]).unwrap();
+    let account = |hash| Account { bytecode_hash: Some(hash), ..Default::default() };
+    tx.put::<tables::PlainAccountState>(H160::from_low_u64_be(1), account(corrupted)).unwrap();
+    tx.put::<tables::PlainAccountState>(H160::from_low_u64_be(2), account(missing)).unwrap();
+    tx.commit().unwrap();
+
+    let report = verify::verify_bytecodes(&env.tx().unwrap()).unwrap();
+    assert_eq!((report.bytecodes, report.accounts), (2, 3));
+    assert_eq!(
+        vec![
+            BytecodeIssue::HashMismatch {
+                hash: corrupted,
+                computed: keccak256([0x60, 0x00]),
+                len: 2
+            },
+            BytecodeIssue::CorruptedCode { address: H160::from_low_u64_be(1), hash: corrupted },
+            BytecodeIssue::MissingCode { address: H160::from_low_u64_be(2), hash: missing },
+        ],
+        report.issues
+    );
+
+    // Codes that are not valid hex fail the import instead of being stored empty
+    let state = dir.path().join("invalid.json");
+    std::fs::write(
+        &state,
+        format!(
+            r#"{{"{:?}":{{"balance":"0x0","codeHash":"{missing:?}","code":"0xzz"}}}}"#,
+            H160::from_low_u64_be(3)
+        ),
+    )
+    .unwrap();
+    let err = state::apply(&mut env, Some(&state.display().to_string()), &ImportArgs::default())
+        .await
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("not valid hex"), "{err:#}");
+}
+
+#[tokio::test]
+async fn test_verify_blooms() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(dir.path()).unwrap();
+    let fees = L1FeeStore::open(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut env, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut env, Some(BLOCKS_PATH), &args).await.unwrap();
+    receipts::apply(&mut env, &fees, Some(RECEIPTS_PATH), &args).await.unwrap();
+    let report = verify::verify_blooms(&env.tx().unwrap(), 1..=2).unwrap();
+    assert!(report.is_consistent(), "{report:?}");
+    assert_eq!((report.blocks, report.incomplete), (2, 0));
+
+    // A log the header doesn't commit to and a missing receipt
+    let tx = env.tx_mut().unwrap();
+    let mut receipt = tx.get::<tables::Receipts>(1).unwrap().unwrap();
+    receipt.logs.push(Log {
+        address: H160::repeat_byte(1),
+        topics: vec![],
+        data: Bytes::default(),
+    });
+    tx.put::<tables::Receipts>(1, receipt).unwrap();
+    tx.delete::<tables::Receipts>(0, None).unwrap();
+    tx.commit().unwrap();
+
+    let report = verify::verify_blooms(&env.tx().unwrap(), 1..=2).unwrap();
+    assert_eq!((report.blocks, report.incomplete), (1, 1));
+    assert_eq!(
+        vec![2],
+        report.mismatches.iter().map(|mismatch| mismatch.block).collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn test_verify_execution() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut env = db::open_rw_env(dir.path()).unwrap();
+    let args = ImportArgs::default();
+    genesis::apply(&mut env, Some(GENESIS_PATH), &args).await.unwrap();
+    blocks::apply(&mut env, Some(BLOCKS_PATH), &args).await.unwrap();
+
+    // Every block of the range is reported on, and the genesis has nothing to re-execute
+    let report = verify::verify_execution(&env, &MAINNET, 0..=1, 4).unwrap();
+    assert_eq!(1, report.checked);
+    assert!(report.mismatches.iter().all(|(number, _)| *number == 1));
+
+    // The state of the database is the genesis state, the parent of block 2 is not available
+    assert!(verify::verify_execution(&env, &MAINNET, 2..=2, 1).is_err());
+}
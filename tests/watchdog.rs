@@ -0,0 +1,34 @@
+use std::{sync::Arc, thread, time::Duration};
+
+use op_reth::cli::{
+    progress::ImportProgress,
+    watchdog::{StalledImport, Watchdog},
+};
+
+#[test]
+fn test_watchdog_aborts_stalled_import() {
+    let progress = Arc::new(ImportProgress::default());
+    progress.set_stage("decode blocks");
+    progress.advance(3).unwrap();
+
+    let watchdog = Watchdog::spawn(progress.clone(), Duration::from_millis(100), true);
+    thread::sleep(Duration::from_millis(150));
+    drop(watchdog);
+
+    assert_eq!(
+        progress.advance(1).unwrap_err(),
+        StalledImport { stage: "decode blocks", items: 4 }
+    );
+}
+
+#[test]
+fn test_watchdog_ignores_progressing_import() {
+    let progress = Arc::new(ImportProgress::default());
+    let watchdog = Watchdog::spawn(progress.clone(), Duration::from_millis(100), true);
+    for _ in 0..10 {
+        thread::sleep(Duration::from_millis(20));
+        progress.advance(1).unwrap();
+    }
+    drop(watchdog);
+    assert!(progress.check().is_ok());
+}